@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    network::{EthereumWallet, TransactionBuilder},
+    primitives::{keccak256, Address, U256},
+    rpc::types::TransactionRequest,
+    signers::{
+        k256::{
+            ecdsa::SigningKey,
+            sha2::{Digest, Sha256},
+        },
+        local::PrivateKeySigner,
+    },
+};
+use blst::min_pk::Signature;
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ethereum_consensus::{crypto::PublicKey as ECBlsPublicKey, deneb::compute_signing_root};
+use interstate_gateway::{
+    config::ChainConfig,
+    constraints::{
+        versioned::{ConstraintsMessageV2, ConstraintsVersion},
+        Constraint, ConstraintsMessage, SignedConstraints,
+    },
+    delegation::{
+        signing::{compute_domain_from_mask, verify_constraints, verify_root, BLS_DST_PREFIX},
+        types::Chain,
+    },
+    keystores::Keystores,
+    utils::create_random_bls_secretkey,
+};
+
+/// Number of transactions in the benchmarked constraints message.
+const BUNDLE_SIZE: usize = 16;
+
+/// Number of constraints submitted in the benchmarked deadline, one per committed transaction.
+const DEADLINE_CONSTRAINTS: usize = 256;
+
+const SIGNER_KEY: &str = "5d2344259f42259f82d2c140aa66102ba89b57b4883ee441a8b312622bd42491";
+
+/// Public key of the keystore in `mock_data`.
+const KEYSTORE_PUBKEY: &str = "0x9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07";
+
+fn constraints_message(runtime: &tokio::runtime::Runtime) -> ConstraintsMessage {
+    let sk = SigningKey::from_slice(&alloy::hex::decode(SIGNER_KEY).unwrap()).unwrap();
+    let wallet = EthereumWallet::from(PrivateKeySigner::from_signing_key(sk.clone()));
+    let sender = Address::from_private_key(&sk);
+
+    let transactions = (0..BUNDLE_SIZE as u64)
+        .map(|nonce| {
+            let tx = TransactionRequest::default()
+                .with_from(sender)
+                .with_to(Address::ZERO)
+                .with_chain_id(1)
+                .with_nonce(nonce)
+                .with_value(U256::from(100))
+                .with_gas_limit(21_000)
+                .with_max_priority_fee_per_gas(1_000_000_000)
+                .with_max_fee_per_gas(20_000_000_000);
+            let signed = runtime.block_on(tx.build(&wallet)).unwrap();
+            Constraint::decode_enveloped(signed.encoded_2718()).unwrap()
+        })
+        .collect();
+
+    let pubkey =
+        ECBlsPublicKey::try_from(create_random_bls_secretkey().sk_to_pk().to_bytes().as_ref())
+            .unwrap();
+
+    ConstraintsMessage {
+        pubkey,
+        slot: 42,
+        top: false,
+        transactions,
+    }
+}
+
+fn bench_constraints(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let message = constraints_message(&runtime);
+    let encoded = serde_json::to_string(&message).unwrap();
+
+    c.bench_function("constraints_message_digest", |b| {
+        b.iter(|| black_box(&message).digest())
+    });
+
+    // The v2 digest used to be computed on a copy of the message, transactions included
+    c.bench_function("constraints_message_digest_v2_copied", |b| {
+        b.iter(|| ConstraintsMessageV2::from(black_box(&message)).digest())
+    });
+
+    c.bench_function("constraints_message_digest_v2", |b| {
+        b.iter(|| black_box(&message).digest_for(ConstraintsVersion::V2))
+    });
+
+    c.bench_function("constraints_message_serialize", |b| {
+        b.iter(|| serde_json::to_string(black_box(&message)).unwrap())
+    });
+
+    c.bench_function("constraints_message_deserialize", |b| {
+        b.iter(|| serde_json::from_str::<ConstraintsMessage>(black_box(&encoded)).unwrap())
+    });
+}
+
+/// The constraints of a deadline, each one signed by its own delegatee.
+fn signed_constraints(message: &ConstraintsMessage) -> Vec<SignedConstraints> {
+    let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+    (0..DEADLINE_CONSTRAINTS)
+        .map(|i| {
+            let sk = create_random_bls_secretkey();
+            let message = ConstraintsMessage {
+                pubkey: ECBlsPublicKey::try_from(sk.sk_to_pk().to_bytes().as_ref()).unwrap(),
+                slot: 42,
+                top: false,
+                transactions: vec![message.transactions[i % BUNDLE_SIZE].clone()],
+            };
+            let root = compute_signing_root(&message.digest(), domain).unwrap();
+            let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+            SignedConstraints { message, signature: signature.into() }
+        })
+        .collect()
+}
+
+fn bench_deadline(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let constraints = signed_constraints(&constraints_message(&runtime));
+    let digests = constraints.iter().map(|sc| sc.message.digest()).collect::<Vec<_>>();
+    let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+
+    c.bench_function("deadline_serialize_constraints", |b| {
+        b.iter(|| serde_json::to_vec(black_box(&constraints)).unwrap())
+    });
+
+    c.bench_function("deadline_verify_constraints_one_by_one", |b| {
+        b.iter(|| {
+            constraints.iter().zip(&digests).all(|(signed, digest)| {
+                let signature = Signature::from_bytes(signed.signature.as_ref()).unwrap();
+                verify_root(signed.message.pubkey.clone(), *digest, &signature, domain).is_ok()
+            })
+        })
+    });
+
+    c.bench_function("deadline_verify_constraints_batch", |b| {
+        b.iter(|| verify_constraints(black_box(&constraints), &digests, &Chain::Holesky))
+    });
+}
+
+fn bench_hashing(c: &mut Criterion) {
+    let data = vec![0xab_u8; 32 * BUNDLE_SIZE];
+
+    c.bench_function("keccak256", |b| b.iter(|| keccak256(black_box(&data))));
+
+    c.bench_function("sha256", |b| {
+        b.iter(|| {
+            let mut hasher = Sha256::new();
+            hasher.update(black_box(&data));
+            hasher.finalize()
+        })
+    });
+}
+
+fn bench_keystore_signing(c: &mut Criterion) {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let keystores = Keystores::new(
+        &manifest_dir.join("mock_data/keys"),
+        &manifest_dir.join("mock_data/secrets"),
+        &ChainConfig::default(),
+    );
+    let pubkey = ECBlsPublicKey::try_from(
+        alloy::hex::decode(KEYSTORE_PUBKEY.trim_start_matches("0x")).unwrap().as_slice(),
+    )
+    .unwrap();
+
+    c.bench_function("keystore_sign_commit_boost_root", |b| {
+        b.iter_batched(
+            rand::random::<[u8; 32]>,
+            |root| keystores.sign_commit_boost_root(root, &pubkey).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_constraints,
+    bench_deadline,
+    bench_hashing,
+    bench_keystore_signing
+);
+criterion_main!(benches);
@@ -16,12 +16,15 @@ use super::types::{
 /// - Create messages
 /// - Compute the signing roots and sign the message
 /// - Return the signed message
+///
+/// Delegations are bounded to `expiry_epoch` when given.
 pub fn generate_from_keystore(
     keys_path: &str,
     keystore_secret: KeystoreSecret,
     delegatee_pubkey: BlsPublicKey,
     chain: Chain,
     action: Action,
+    expiry_epoch: Option<u64>,
 ) -> Result<Vec<SignedMessage>> {
     let keystores_paths = keystore_paths(keys_path)?;
     let mut signed_messages = Vec::with_capacity(keystores_paths.len());
@@ -36,7 +39,9 @@ pub fn generate_from_keystore(
 
         match action {
             Action::Delegate => {
-                let message = DelegationMessage::new(validator_pubkey, delegatee_pubkey.clone());
+                let mut message =
+                    DelegationMessage::new(validator_pubkey, delegatee_pubkey.clone());
+                message.expiry_epoch = expiry_epoch;
                 let signing_root = compute_commit_boost_signing_root(message.digest(), &chain)?;
                 let signature = validator_private_key.sign(signing_root.0.into());
                 let signature = BlsSignature::try_from(signature.serialize().as_ref())?;
@@ -87,6 +92,7 @@ mod tests {
             delegatee_pubkey.clone(),
             chain,
             Action::Delegate,
+            None,
         )?;
 
         let signed_message = signed_delegations.first().expect("to get signed delegation");
@@ -50,6 +50,10 @@ async fn main() ->eyre::Result<()> {
     let delegate_pbukey_str = env::var("DELEGATEE_PUBLICKEY").expect("couldn't find delegatee publickey in env file");
     let delegatee_pubkey:BlsPublicKey = parse_bls_public_key(delegate_pbukey_str.as_str()).expect("Invalid public key");
     let keystore_secret = KeystoreSecret::from_directory(password_path.as_str()).unwrap();
+    // The delegations never expire when not set
+    let expiry_epoch: Option<u64> = env::var("EXPIRY_EPOCH")
+        .ok()
+        .map(|v| v.parse().expect("couldn't parse the expiry epoch in env file"));
     
     let signed_messages = generate_from_keystore(
         &keys_path,
@@ -57,11 +61,12 @@ async fn main() ->eyre::Result<()> {
         delegatee_pubkey.clone(),
         Chain::Kurtosis,
         Action::Delegate,
+        expiry_epoch,
     ).expect("Invalid signed message request");
 
     debug!("Signed {} messages with keystore", signed_messages.len());
     
-    // let signed_messages_web3 = generate_from_web3signer(Web3SignerOpts{ url:web3signer_url}, delegatee_pubkey, Action::Delegate).await?;
+    // let signed_messages_web3 = generate_from_web3signer(Web3SignerOpts{ url:web3signer_url}, delegatee_pubkey, Action::Delegate, expiry_epoch).await?;
     // debug!("Signed {} messages with web3signature", signed_messages_web3.len());
 
 
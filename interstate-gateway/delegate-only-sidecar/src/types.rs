@@ -147,20 +147,33 @@ pub struct DelegationMessage {
     action: u8,
     pub validator_pubkey: BlsPublicKey,
     pub delegatee_pubkey: BlsPublicKey,
+    /// Last epoch in which the delegation is valid. Delegations without expiry never expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry_epoch: Option<u64>,
 }
 
 impl DelegationMessage {
     /// Create a new delegation message.
     pub fn new(validator_pubkey: BlsPublicKey, delegatee_pubkey: BlsPublicKey) -> Self {
-        Self { action: SignedMessageAction::Delegation as u8, validator_pubkey, delegatee_pubkey }
+        Self {
+            action: SignedMessageAction::Delegation as u8,
+            validator_pubkey,
+            delegatee_pubkey,
+            expiry_epoch: None,
+        }
     }
 
     /// Compute the digest of the delegation message.
+    ///
+    /// The expiry is only hashed when set, as done by the gateway.
     pub fn digest(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
         hasher.update([self.action]);
         hasher.update(self.validator_pubkey.to_vec());
         hasher.update(self.delegatee_pubkey.to_vec());
+        if let Some(expiry_epoch) = self.expiry_epoch {
+            hasher.update(expiry_epoch.to_le_bytes());
+        }
 
         hasher.finalize().into()
     }
@@ -11,11 +11,13 @@ use tracing::debug;
 
 use super::types::{SignedMessage, CommitBoostSignatureRequest};
 
-/// Generate signed delegations/recovations using a remote Web3Signer.
+/// Generate signed delegations/recovations using a remote Web3Signer. Delegations are
+/// bounded to `expiry_epoch` when given.
 pub async fn generate_from_web3signer(
     opts: Web3SignerOpts,
     delegatee_pubkey: BlsPublicKey,
     action: Action,
+    expiry_epoch: Option<u64>,
 ) -> Result<Vec<SignedMessage>> {
     // Connect to web3signer.
     let mut web3signer = Web3Signer::connect(opts.url).await?;
@@ -34,7 +36,8 @@ pub async fn generate_from_web3signer(
 
         match action {
             Action::Delegate => {
-                let message = DelegationMessage::new(pubkey.clone(), delegatee_pubkey.clone());
+                let mut message = DelegationMessage::new(pubkey.clone(), delegatee_pubkey.clone());
+                message.expiry_epoch = expiry_epoch;
                 // Web3Signer expects the pre-pended 0x.
                 let signing_root = format!("0x{}", &hex::encode(message.digest()));
                 let returned_signature =
@@ -157,7 +160,7 @@ mod tests {
         let opts = Web3SignerOpts { url};
 
         let signed_delegations =
-            generate_from_web3signer(opts, delegatee_pubkey, Action::Delegate).await?;
+            generate_from_web3signer(opts, delegatee_pubkey, Action::Delegate, None).await?;
 
         let signed_message = signed_delegations.first().expect("to get signed delegation");
 
@@ -45,6 +45,21 @@ impl ConstraintsMessage {
     }
 }
 
+impl From<crate::constraints::SignedConstraints> for SignedConstraints {
+    fn from(signed: crate::constraints::SignedConstraints) -> Self {
+        let message = signed.message;
+        Self {
+            message: ConstraintsMessage {
+                pubkey: message.pubkey,
+                slot: message.slot,
+                top: message.top,
+                transactions: message.transactions,
+            },
+            signature: signed.signature,
+        }
+    }
+}
+
 impl SignableBLS for ConstraintsMessage {
     fn digest(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
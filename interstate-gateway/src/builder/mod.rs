@@ -57,17 +57,32 @@ impl BlockTemplate {
         })
     }
 
+    /// Add committed constraints to the template, accounting for the nonce and the maximum
+    /// spend (value + fees) of every transaction in the state diff of its sender. The senders
+    /// are recovered from the transactions, any claimed one is overwritten.
+    pub fn add_constraints(&mut self, mut constraints: SignedConstraints) {
+        for constraint in &mut constraints.message.transactions {
+            let Some(sender) = constraint.recover_sender() else {
+                warn!(hash = %constraint.tx.hash(), "Can't recover the sender of a constraint");
+                continue;
+            };
+            let (nonce, balance) = self.state_diff.diffs.entry(sender).or_insert((0, U256::ZERO));
+            *nonce += 1;
+            *balance = balance.saturating_add(max_transaction_cost(&constraint.tx));
+        }
+
+        self.signed_constraints_list.push(constraints);
+    }
+
     fn remove_constraints_at_index(&mut self, index: usize) {
         let constraints = self.signed_constraints_list.remove(index);
 
         for constraint in &constraints.message.transactions {
-            self.state_diff
-                .diffs
-                .entry(constraint.sender.expect("recovered sender"))
-                .and_modify(|(nonce, balance)| {
-                    *nonce = nonce.saturating_sub(1);
-                    *balance -= max_transaction_cost(&constraint.tx);
-                });
+            let Some(sender) = constraint.sender else { continue };
+            self.state_diff.diffs.entry(sender).and_modify(|(nonce, balance)| {
+                *nonce = nonce.saturating_sub(1);
+                *balance = balance.saturating_sub(max_transaction_cost(&constraint.tx));
+            });
         }
     }
 
@@ -79,18 +94,8 @@ impl BlockTemplate {
             .iter()
             .enumerate()
             .map(|(idx, c)| (idx, &c.message.transactions))
-            .filter(|(_idx, c)| {
-                c.iter()
-                    .any(|c| c.sender.expect("recovered sender") == address)
-            })
-            .map(|(idx, c)| {
-                (
-                    idx,
-                    c.iter()
-                        .filter(|c| c.sender.expect("recovered sender") == address)
-                        .collect(),
-                )
-            })
+            .filter(|(_idx, c)| c.iter().any(|c| c.sender == Some(address)))
+            .map(|(idx, c)| (idx, c.iter().filter(|c| c.sender == Some(address)).collect()))
             .collect();
 
         let (max_total_cost, min_nonce) = constraints_with_address
@@ -127,3 +132,62 @@ impl StateDiff {
         self.diffs.get(address).copied()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        hex,
+        network::{EthereumWallet, TransactionBuilder},
+        primitives::Address,
+        signers::{k256::ecdsa::SigningKey, local::PrivateKeySigner},
+    };
+    use alloy_v092::primitives::U256;
+
+    use super::{
+        constraint::{ConstraintsMessage, SignedConstraints},
+        BlockTemplate,
+    };
+    use crate::{
+        constraints::Constraint, state::account_state::AccountState,
+        test_utils::default_test_transaction, utils::transactions::max_transaction_cost,
+    };
+
+    #[tokio::test]
+    async fn test_state_diff_accumulates_committed_spend() -> eyre::Result<()> {
+        let sk = SigningKey::from_slice(&hex::decode(
+            "5d2344259f42259f82d2c140aa66102ba89b57b4883ee441a8b312622bd42491",
+        )?)?;
+        let wallet = EthereumWallet::from(PrivateKeySigner::from_signing_key(sk.clone()));
+        let sender = Address::from_private_key(&sk);
+
+        let mut template = BlockTemplate::default();
+        let mut total_cost = U256::ZERO;
+        for nonce in 0..2 {
+            let tx = default_test_transaction(sender, Some(nonce)).with_chain_id(1);
+            let raw = tx.build(&wallet).await?.encoded_2718();
+            let mut constraint = Constraint::decode_enveloped(raw)?;
+            // A claimed sender is replaced by the signer of the transaction
+            constraint.sender = Some(Address::repeat_byte(0xaa));
+            total_cost += max_transaction_cost(&constraint.tx);
+
+            let message = ConstraintsMessage::from_tx(Default::default(), 1, constraint);
+            template.add_constraints(SignedConstraints { message, signature: Default::default() });
+        }
+
+        assert_eq!(template.get_diff(&sender), Some((2, total_cost)));
+        assert_eq!(template.get_diff(&Address::repeat_byte(0xaa)), None);
+
+        // A balance that can't cover the cumulative spend invalidates the commitments
+        let state = AccountState {
+            transaction_count: 0,
+            balance: total_cost - U256::from(1),
+            has_code: false,
+        };
+        template.retain(sender, state);
+        assert!(template.signed_constraints_list.is_empty());
+        assert_eq!(template.get_diff(&sender), Some((0, U256::ZERO)));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{hex, primitives::B256};
+use clap::{Args, Subcommand, ValueEnum};
+use ethereum_consensus::crypto::{PublicKey as ECBlsPublicKey, Signature as ECBlsSignature};
+use eyre::{Context, Result};
+use reqwest::Url;
+
+#[cfg(feature = "signer-web3")]
+use crate::delegation::web3signer::{generate_from_web3signer, Action, Web3SignerOpts};
+use crate::{
+    commitment::STATUS_PATH,
+    config::{limits::DEFAULT_GAS_LIMIT, Config},
+    delegation::{
+        cb_signer::CBSigner,
+        limiter::SigningLimiters,
+        signer::{connect_signer, SignerBackend as _},
+        types::SignedDelegation,
+    },
+    state::{
+        archive::{read_archive, replay},
+        execution::{ExecutionSnapshot, ExecutionState},
+        fetcher::ClientState,
+        schema::{configured_stores, migrate_stores, MigrateOptions},
+        slot_clock::SlotClock,
+        status::SidecarStatus,
+        ConstraintState,
+    },
+    utils::url::join_path,
+};
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Sign an arbitrary 32-byte root with the commit-boost domain and print the signature.
+    SignRoot {
+        /// The hex encoded root to sign
+        #[arg(long)]
+        root: B256,
+        /// The hex encoded BLS public key to sign with
+        #[arg(long)]
+        pubkey: String,
+        /// The signer backend holding the key
+        #[arg(long, value_enum, default_value_t = SignRootBackend::Configured)]
+        signer: SignRootBackend,
+    },
+    /// Sign delegations, or revocations, of the Web3Signer validator keys to a delegatee and
+    /// write them to a file.
+    #[cfg(feature = "signer-web3")]
+    Delegate(DelegateOpts),
+    /// Re-sign, with a new expiry, the time-bounded delegations that expire soon.
+    RenewDelegations(RenewDelegationsOpts),
+    /// Show a live view of a running sidecar: slot, proposals, pending constraints, relay and
+    /// signer health, and last errors.
+    Status(StatusOpts),
+    /// Replay an archive of the slot handling of a sidecar, printing its state after each
+    /// head event, beacon node response and commitment deadline.
+    Replay(ReplayOpts),
+    /// Migrate the persisted stores to the schema of this version, as done at startup.
+    Migrate(MigrateOpts),
+}
+
+#[derive(Debug, Args)]
+pub struct MigrateOpts {
+    /// Print the migrations due without applying them
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Don't back up the stores before migrating them, regardless of `SCHEMA_BACKUP`
+    #[arg(long)]
+    pub no_backup: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ReplayOpts {
+    /// The archive recorded at `SLOT_ARCHIVE_PATH`
+    #[arg(long)]
+    pub archive: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct StatusOpts {
+    /// URL of the commitments API of the sidecar. Defaults to the local one
+    #[arg(long)]
+    pub url: Option<Url>,
+    /// Seconds between two refreshes of the view
+    #[arg(long, default_value_t = 2)]
+    pub interval_secs: u64,
+    /// Print the status once and exit
+    #[arg(long)]
+    pub once: bool,
+}
+
+#[cfg(feature = "signer-web3")]
+#[derive(Debug, Args)]
+pub struct DelegateOpts {
+    /// Whether to delegate to the delegatee or revoke its delegations
+    #[arg(long, value_enum)]
+    pub action: Action,
+    /// The hex encoded BLS public key of the delegatee
+    #[arg(long)]
+    pub delegatee_pubkey: String,
+    /// Last epoch in which the delegations are valid. They never expire when not set
+    #[arg(long)]
+    pub expiry_epoch: Option<u64>,
+    /// JSON file to write the signed messages to
+    #[arg(long)]
+    pub output: PathBuf,
+    #[command(flatten)]
+    pub web3signer: Web3SignerOpts,
+}
+
+/// Default number of epochs before expiry in which delegations are renewed, about a day.
+pub const DEFAULT_RENEWAL_WINDOW_EPOCHS: u64 = 225;
+
+#[derive(Debug, Args)]
+pub struct RenewDelegationsOpts {
+    /// JSON file with the signed delegations
+    #[arg(long)]
+    pub delegations: PathBuf,
+    /// File to write the delegations to. Defaults to overwriting the input file
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+    /// Last epoch in which the renewed delegations are valid
+    #[arg(long)]
+    pub expiry_epoch: u64,
+    /// Renew the delegations expiring within this number of epochs
+    #[arg(long, default_value_t = DEFAULT_RENEWAL_WINDOW_EPOCHS)]
+    pub within_epochs: u64,
+    /// Epoch the renewal window starts from. Defaults to the current epoch of the beacon chain
+    #[arg(long)]
+    pub current_epoch: Option<u64>,
+    /// The signer backend holding the validator keys
+    #[arg(long, value_enum, default_value_t = SignRootBackend::Configured)]
+    pub signer: SignRootBackend,
+}
+
+/// Signer backends available to the admin commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[clap(rename_all = "kebab_case")]
+pub enum SignRootBackend {
+    /// The signer configured for the sidecar, the local keystores by default
+    Configured,
+    /// The commit-boost signer module
+    CommitBoost,
+}
+
+/// Sign `root` with the commit-boost domain using the given signer backend, returning the
+/// hex encoded signature.
+pub async fn sign_root(
+    config: &Config,
+    root: B256,
+    pubkey: &str,
+    signer: SignRootBackend,
+) -> Result<String> {
+    match signer {
+        SignRootBackend::Configured => {
+            let pubkey_bytes =
+                hex::decode(pubkey.trim_start_matches("0x")).wrap_err("invalid hex pubkey")?;
+            let pubkey = ECBlsPublicKey::try_from(pubkey_bytes.as_slice())
+                .map_err(|e| eyre::eyre!("invalid BLS pubkey: {e:?}"))?;
+
+            let signer = connect_signer(config, &SigningLimiters::from_config(config)).await?;
+            let signature = signer.sign_root(root.0, &pubkey).await?;
+
+            Ok(signature.to_string())
+        }
+        SignRootBackend::CommitBoost => {
+            let signer = CBSigner::new(&config.commit_boost_signer_url, &config.jwt_hex)
+                .with_headers(&config.outbound_headers);
+            let response = signer.request_signature(pubkey, &root.to_string()).await?;
+
+            // The signer module responds with the signature as a JSON string
+            Ok(response.trim().trim_matches('"').to_string())
+        }
+    }
+}
+
+/// Sign the messages of `opts.action` to the delegatee with every key of the Web3Signer,
+/// writing them to `opts.output`. Returns the number of signed messages.
+#[cfg(feature = "signer-web3")]
+pub async fn delegate(opts: DelegateOpts) -> Result<usize> {
+    let pubkey_bytes = hex::decode(opts.delegatee_pubkey.trim_start_matches("0x"))
+        .wrap_err("invalid hex pubkey")?;
+    let delegatee_pubkey = ECBlsPublicKey::try_from(pubkey_bytes.as_slice())
+        .map_err(|e| eyre::eyre!("invalid BLS pubkey: {e:?}"))?;
+
+    let messages =
+        generate_from_web3signer(opts.web3signer, delegatee_pubkey, opts.action, opts.expiry_epoch)
+            .await?;
+    std::fs::write(&opts.output, serde_json::to_string_pretty(&messages)?)
+        .wrap_err_with(|| format!("failed to write {}", opts.output.display()))?;
+
+    Ok(messages.len())
+}
+
+/// Renew the delegations of `opts.delegations` expiring within the renewal window, signing
+/// them again with the validator keys. Delegations without expiry are left untouched.
+/// Returns the number of renewed delegations.
+pub async fn renew_delegations(config: &Config, opts: RenewDelegationsOpts) -> Result<usize> {
+    let current_epoch = match opts.current_epoch {
+        Some(epoch) => epoch,
+        None => current_epoch(config).await?,
+    };
+    if opts.expiry_epoch <= current_epoch {
+        eyre::bail!(
+            "expiry epoch {} must be after the current epoch {current_epoch}",
+            opts.expiry_epoch
+        );
+    }
+
+    let content = std::fs::read_to_string(&opts.delegations)
+        .wrap_err_with(|| format!("failed to read {}", opts.delegations.display()))?;
+    let mut delegations: Vec<SignedDelegation> = serde_json::from_str(&content)?;
+
+    let mut renewed = 0;
+    for delegation in delegations.iter_mut() {
+        if !delegation.message.expires_within(current_epoch, opts.within_epochs) {
+            continue;
+        }
+
+        let message = delegation.message.clone().with_expiry(opts.expiry_epoch);
+        let pubkey = format!("0x{}", hex::encode(message.validator_pubkey.to_vec()));
+        let signature =
+            sign_root(config, B256::from(message.digest()), &pubkey, opts.signer).await?;
+        let signature = ECBlsSignature::try_from(
+            hex::decode(signature.trim_start_matches("0x"))?.as_slice(),
+        )
+        .map_err(|e| eyre::eyre!("invalid signature for {pubkey}: {e:?}"))?;
+
+        *delegation = SignedDelegation { message, signature };
+        renewed += 1;
+    }
+
+    let output = opts.output.unwrap_or(opts.delegations);
+    std::fs::write(&output, serde_json::to_string_pretty(&delegations)?)
+        .wrap_err_with(|| format!("failed to write {}", output.display()))?;
+
+    Ok(renewed)
+}
+
+/// Render the status of the sidecar in the terminal, refreshing it until interrupted unless
+/// `opts.once` is set.
+pub async fn watch_status(config: &Config, opts: StatusOpts) -> Result<()> {
+    let base = match opts.url {
+        Some(url) => url,
+        None => format!("http://127.0.0.1:{}", config.commitment_port).parse()?,
+    };
+    let url = join_path(&base, STATUS_PATH)?;
+    let client = reqwest::Client::new();
+
+    loop {
+        let status = async {
+            client.get(url.clone()).send().await?.error_for_status()?.json::<SidecarStatus>().await
+        }
+        .await;
+        let now_ms = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+
+        if opts.once {
+            print!("{}", status.wrap_err_with(|| format!("failed to query {url}"))?.render(now_ms));
+            return Ok(());
+        }
+
+        // Clear the screen and redraw, keeping the view up while the sidecar is unreachable
+        print!("\x1b[2J\x1b[H{base}\n\n");
+        match status {
+            Ok(status) => print!("{}", status.render(now_ms)),
+            Err(err) => println!("sidecar unreachable: {err}"),
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(opts.interval_secs)) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Replay the archived slot handling against a fresh constraint state. The beacon node and the
+/// execution client of the configuration are never queried, the archive holding the responses.
+pub fn replay_archive(config: &Config, opts: ReplayOpts) -> Result<()> {
+    let events = read_archive(&opts.archive)
+        .wrap_err_with(|| format!("failed to read {}", opts.archive.display()))?;
+
+    let execution = ExecutionState::from_head(
+        ClientState::new(config.execution_api_url.clone()),
+        Default::default(),
+        DEFAULT_GAS_LIMIT,
+        config.chain.id,
+        ExecutionSnapshot::default(),
+    );
+    // Replaced by the clock of the archive once it started
+    let slot_clock = SlotClock::for_chain(0, &config.chain, config.slot_drift_threshold_ms);
+    let mut state = ConstraintState::new(
+        config.outbound_headers.beacon_client(config.beacon_api_url.clone()),
+        config.chain.get_commitment_deadline_duration(),
+        slot_clock,
+        execution,
+        &config.chain,
+    );
+
+    for step in replay(&mut state, events) {
+        println!("{step}");
+    }
+    Ok(())
+}
+
+/// Migrate the stores enabled in `config`, printing the migrations applied or due.
+pub fn migrate(config: &Config, opts: MigrateOpts) -> Result<()> {
+    let options =
+        MigrateOptions { dry_run: opts.dry_run, backup: config.schema_backup && !opts.no_backup };
+    let upgrades = migrate_stores(&configured_stores(config), options)?;
+    if upgrades.is_empty() {
+        println!("stores up to date");
+    }
+    for upgrade in upgrades {
+        let verb = if opts.dry_run { "would migrate" } else { "migrated" };
+        println!("{verb} {upgrade}");
+    }
+    Ok(())
+}
+
+/// The current epoch of the beacon chain, from its genesis time.
+async fn current_epoch(config: &Config) -> Result<u64> {
+    let genesis = config
+        .outbound_headers
+        .beacon_client(config.beacon_api_url.clone())
+        .get_genesis_details()
+        .await
+        .map_err(|e| eyre::eyre!("failed to fetch genesis details: {e}"))?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    Ok(config.chain.epoch_of(now.saturating_sub(genesis.genesis_time) / config.chain.slot_time))
+}
@@ -0,0 +1,88 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use clap::Parser;
+
+use crate::config::{
+    sources::{parse_override, ConfigSources},
+    validation::{check_envs, ConfigError},
+    Config,
+};
+
+#[cfg(feature = "cli")]
+mod commands;
+#[cfg(feature = "cli")]
+pub use commands::*;
+
+/// Interstate gateway sidecar. Runs the sidecar when no subcommand is given.
+#[derive(Debug, Parser)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// Load and check the configuration, print it with secrets redacted and exit.
+    /// Exits with a non-zero code if any problem is found.
+    #[arg(long)]
+    pub validate_config: bool,
+    /// TOML configuration file, overridden by the env file and the environment. Defaults to
+    /// `config.toml` in the crate directory when it exists
+    #[arg(long, env = "CONFIG_FILE")]
+    pub config: Option<PathBuf>,
+    /// Env file, overridden by the environment. Defaults to `.env` in the crate directory
+    /// when it exists
+    #[arg(long)]
+    pub env_file: Option<PathBuf>,
+    /// Set a configuration variable, overriding every other source, e.g.
+    /// `--set RELAY_URL=http://relay:9062`
+    #[arg(long = "set", value_name = "KEY=VALUE", value_parser = parse_override)]
+    pub overrides: Vec<(String, String)>,
+    #[cfg(feature = "cli")]
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// The sources of the configuration variables, layered as given by the flags.
+    pub fn config_sources(&self) -> ConfigSources {
+        ConfigSources {
+            config_file: self.config.clone(),
+            env_file: self.env_file.clone(),
+            environment: true,
+            overrides: self.overrides.clone(),
+        }
+    }
+}
+
+/// Build the configuration from `envs` and check it, including that the hosts of its URLs
+/// resolve. Returns the configuration, or every problem found.
+pub async fn validate_config(envs: HashMap<String, String>) -> Result<Config, Vec<ConfigError>> {
+    let errors = check_envs(&envs);
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    let config = Config::new(envs);
+    let mut errors = config.check();
+
+    for (name, url) in [
+        ("RELAY_URL", &config.relay_url),
+        ("BEACON_API_URL", &config.beacon_api_url),
+        ("EXECUTION_API_URL", &config.execution_api_url),
+        ("ENGINE_API_URL", &config.engine_api_url),
+    ]
+    .into_iter()
+    .chain(config.extra_relay_urls.iter().map(|url| ("EXTRA_RELAY_URLS", url)))
+    {
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            errors.push(ConfigError::invalid(name, "missing host"));
+            continue;
+        };
+
+        if let Err(err) = tokio::net::lookup_host((host, port)).await {
+            errors.push(ConfigError::invalid(name, format!("failed to resolve {host}: {err}")));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(config)
+    } else {
+        Err(errors)
+    }
+}
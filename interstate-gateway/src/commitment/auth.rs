@@ -0,0 +1,229 @@
+use std::{collections::HashMap, str::FromStr, sync::Arc};
+
+use alloy::primitives::{keccak256, B256};
+use alloy_rpc_types_engine::JwtSecret;
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::request::CommitmentRequestError;
+use crate::metrics::ApiMetrics;
+
+/// Header the API keys are sent in, unless sent as bearer tokens.
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Client label of the requests authenticated with a JWT.
+const JWT_CLIENT: &str = "jwt";
+
+/// An API key allowed to request commitments, labelled in the metrics by `label`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey {
+    pub label: String,
+    pub key: String,
+}
+
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiKey").field("label", &self.label).finish_non_exhaustive()
+    }
+}
+
+impl FromStr for ApiKey {
+    type Err = String;
+
+    /// Parse a `label:key` pair.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((label, key)) if !label.is_empty() && !key.is_empty() => {
+                Ok(Self { label: label.to_string(), key: key.to_string() })
+            }
+            _ => Err("expected `label:key`".to_string()),
+        }
+    }
+}
+
+/// Parse a comma separated list of `label:key` API keys.
+pub fn parse_api_keys(s: &str) -> Result<Vec<ApiKey>, String> {
+    s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(ApiKey::from_str).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthError {
+    #[error("missing credentials, expected an API key or a bearer token")]
+    MissingCredentials,
+    #[error("unknown API key")]
+    UnknownApiKey,
+    #[error("invalid or expired bearer token")]
+    InvalidToken,
+}
+
+impl AuthError {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::MissingCredentials => "missing_credentials",
+            Self::UnknownApiKey => "unknown_api_key",
+            Self::InvalidToken => "invalid_token",
+        }
+    }
+}
+
+/// Body of the responses to unauthenticated requests.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AuthFailure {
+    pub error: AuthError,
+    pub message: String,
+}
+
+impl From<AuthError> for AuthFailure {
+    fn from(error: AuthError) -> Self {
+        Self { error, message: error.to_string() }
+    }
+}
+
+/// Credentials required to request commitments: static API keys, and JWTs signed with a
+/// shared HS256 secret whose `iat` is within a minute. Anyone may request commitments if
+/// neither is configured.
+#[derive(Debug, Clone, Default)]
+pub struct ApiAuth {
+    /// Labels of the API keys, by the hash of the key.
+    api_keys: Arc<HashMap<B256, String>>,
+    jwt_secret: Option<JwtSecret>,
+}
+
+impl ApiAuth {
+    pub fn new(api_keys: Vec<ApiKey>, jwt_secret: Option<JwtSecret>) -> Self {
+        let api_keys =
+            api_keys.into_iter().map(|api_key| (keccak256(&api_key.key), api_key.label)).collect();
+        Self { api_keys: Arc::new(api_keys), jwt_secret }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.api_keys.is_empty() || self.jwt_secret.is_some()
+    }
+
+    /// Labels of the API keys.
+    pub fn labels(&self) -> Vec<&str> {
+        let mut labels = self.api_keys.values().map(String::as_str).collect::<Vec<_>>();
+        labels.sort_unstable();
+        labels
+    }
+
+    pub fn has_jwt_secret(&self) -> bool {
+        self.jwt_secret.is_some()
+    }
+
+    /// The label of the client whose credentials are in `headers`, `None` if no credentials
+    /// are required.
+    pub fn authenticate(&self, headers: &HeaderMap) -> Result<Option<String>, AuthError> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+
+        if let Some(key) = headers.get(API_KEY_HEADER) {
+            return self.api_key_label(key.as_bytes()).map(Some).ok_or(AuthError::UnknownApiKey);
+        }
+
+        let token = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(AuthError::MissingCredentials)?
+            .trim();
+        if let Some(label) = self.api_key_label(token.as_bytes()) {
+            return Ok(Some(label));
+        }
+        match &self.jwt_secret {
+            Some(secret) if secret.validate(token).is_ok() => Ok(Some(JWT_CLIENT.to_string())),
+            Some(_) => Err(AuthError::InvalidToken),
+            None => Err(AuthError::UnknownApiKey),
+        }
+    }
+
+    fn api_key_label(&self, key: &[u8]) -> Option<String> {
+        self.api_keys.get(&keccak256(key)).cloned()
+    }
+}
+
+/// Refuse the requests without valid credentials, counting the others by client.
+pub async fn require_auth(
+    State(auth): State<ApiAuth>,
+    req: Request,
+    next: Next,
+) -> Result<Response, CommitmentRequestError> {
+    match auth.authenticate(req.headers()) {
+        Ok(client) => {
+            if let Some(client) = client {
+                ApiMetrics::increment_authenticated_requests_count(client);
+            }
+            Ok(next.run(req).await)
+        }
+        Err(err) => {
+            tracing::debug!(%err, "Refusing an unauthenticated request");
+            ApiMetrics::increment_auth_failures_count(err.as_str());
+            Err(err.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use alloy_rpc_types_engine::{Claims, JwtSecret};
+    use axum::http::{HeaderMap, HeaderValue};
+
+    use super::{parse_api_keys, ApiAuth, AuthError, API_KEY_HEADER};
+
+    #[test]
+    fn test_api_auth() {
+        let headers = |name: &'static str, value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+
+        // Open when nothing is configured
+        assert_eq!(ApiAuth::default().authenticate(&HeaderMap::new()), Ok(None));
+
+        let secret = JwtSecret::random();
+        let iat = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let token = secret.encode(&Claims { iat, exp: None }).unwrap();
+        let stale = secret.encode(&Claims { iat: iat - 3600, exp: None }).unwrap();
+        let auth = ApiAuth::new(parse_api_keys("searcher:s3cret, wallet:k3y").unwrap(), Some(secret));
+        assert_eq!(auth.labels(), vec!["searcher", "wallet"]);
+
+        assert_eq!(auth.authenticate(&HeaderMap::new()), Err(AuthError::MissingCredentials));
+        assert_eq!(
+            auth.authenticate(&headers(API_KEY_HEADER, "k3y")),
+            Ok(Some("wallet".to_string()))
+        );
+        assert_eq!(
+            auth.authenticate(&headers(API_KEY_HEADER, "nope")),
+            Err(AuthError::UnknownApiKey)
+        );
+        assert_eq!(
+            auth.authenticate(&headers("authorization", "Bearer s3cret")),
+            Ok(Some("searcher".to_string()))
+        );
+
+        let bearer = format!("Bearer {token}");
+        assert_eq!(
+            auth.authenticate(&headers("authorization", &bearer)),
+            Ok(Some("jwt".to_string()))
+        );
+        let bearer = format!("Bearer {stale}");
+        assert_eq!(
+            auth.authenticate(&headers("authorization", &bearer)),
+            Err(AuthError::InvalidToken)
+        );
+
+        assert!(parse_api_keys("no-label").is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use std::{fmt, str::FromStr};
+
+use alloy::{hex, primitives::Bytes};
+use hpke::{
+    aead::ChaCha20Poly1305, kdf::HkdfSha256, kem::X25519HkdfSha256, Deserializable,
+    Kem as KemTrait, OpModeR, Serializable,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use crate::metrics::ApiMetrics;
+
+type Kem = X25519HkdfSha256;
+
+/// Field of a confidential request holding its encrypted transactions, in place of `txs`.
+pub const ENCRYPTED_TXS_FIELD: &str = "encrypted_txs";
+
+/// HPKE info string, binding the ciphertexts to the confidential requests of the gateway.
+const HPKE_INFO: &[u8] = b"interstate-confidential-preconf-v1";
+
+/// Transactions of a request sealed with HPKE (base mode, X25519 HKDF-SHA256, HKDF-SHA256,
+/// ChaCha20Poly1305) to the confidential key of the gateway.
+///
+/// The plaintext is the JSON `txs` array of a plain request, and the additional data is the
+/// big endian slot of the request, so that a ciphertext can't be replayed in another slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptedTxs {
+    pub encapped_key: Bytes,
+    pub ciphertext: Bytes,
+}
+
+/// Parameters clients need to encrypt their transactions to the gateway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ConfidentialInfo {
+    pub kem: &'static str,
+    pub kdf: &'static str,
+    pub aead: &'static str,
+    pub info: String,
+    #[schema(value_type = String)]
+    pub public_key: Bytes,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfidentialError {
+    #[error("confidential requests are not enabled")]
+    Disabled,
+    #[error("invalid confidential key: {0}")]
+    InvalidKey(String),
+    #[error("invalid encrypted transactions: {0}")]
+    InvalidPayload(String),
+    #[error("failed to decrypt the transactions")]
+    Decryption,
+}
+
+/// X25519 key clients encrypt their transactions to, so that they are only revealed to the
+/// gateway serving the commitment.
+#[derive(Clone)]
+pub struct ConfidentialKey {
+    secret: <Kem as KemTrait>::PrivateKey,
+    public: <Kem as KemTrait>::PublicKey,
+}
+
+impl ConfidentialKey {
+    pub fn from_bytes(secret: &[u8]) -> Result<Self, ConfidentialError> {
+        let secret = <Kem as KemTrait>::PrivateKey::from_bytes(secret)
+            .map_err(|err| ConfidentialError::InvalidKey(err.to_string()))?;
+        let public = <Kem as KemTrait>::sk_to_pk(&secret);
+        Ok(Self { secret, public })
+    }
+
+    pub fn public_key(&self) -> Bytes {
+        Bytes::copy_from_slice(&self.public.to_bytes())
+    }
+
+    pub fn info(&self) -> ConfidentialInfo {
+        ConfidentialInfo {
+            kem: "DHKEM(X25519, HKDF-SHA256)",
+            kdf: "HKDF-SHA256",
+            aead: "ChaCha20Poly1305",
+            info: String::from_utf8_lossy(HPKE_INFO).into_owned(),
+            public_key: self.public_key(),
+        }
+    }
+
+    /// Decrypt the transactions of a request for `slot`.
+    pub fn open(&self, slot: u64, encrypted: &EncryptedTxs) -> Result<Value, ConfidentialError> {
+        let encapped_key = <Kem as KemTrait>::EncappedKey::from_bytes(&encrypted.encapped_key)
+            .map_err(|err| ConfidentialError::InvalidPayload(err.to_string()))?;
+
+        let plaintext = hpke::single_shot_open::<ChaCha20Poly1305, HkdfSha256, Kem>(
+            &OpModeR::Base,
+            &self.secret,
+            &encapped_key,
+            HPKE_INFO,
+            &encrypted.ciphertext,
+            &slot.to_be_bytes(),
+        )
+        .map_err(|_| ConfidentialError::Decryption)?;
+
+        serde_json::from_slice(&plaintext)
+            .map_err(|err| ConfidentialError::InvalidPayload(err.to_string()))
+    }
+}
+
+/// Replace the encrypted transactions of a raw confidential request by their plaintext,
+/// leaving plain requests untouched.
+///
+/// The transactions travel encrypted up to the gateway, which decrypts them only to validate
+/// and commit to them. Only the peer gateway of the proposer, serving the commitment in its
+/// place, gets them in plaintext.
+pub fn decrypt_request(
+    key: Option<&ConfidentialKey>,
+    mut body: Value,
+) -> Result<Value, ConfidentialError> {
+    let Some(fields) = body.as_object_mut() else {
+        return Ok(body);
+    };
+    let Some(encrypted) = fields.remove(ENCRYPTED_TXS_FIELD) else {
+        return Ok(body);
+    };
+    let key = key.ok_or(ConfidentialError::Disabled)?;
+
+    // A missing slot is reported along with the other invalid fields of the request
+    let Some(slot) = fields.get("slot").and_then(Value::as_u64) else {
+        return Ok(body);
+    };
+
+    let decrypted = serde_json::from_value(encrypted)
+        .map_err(|err| ConfidentialError::InvalidPayload(err.to_string()))
+        .and_then(|encrypted| key.open(slot, &encrypted));
+
+    match decrypted {
+        Ok(txs) => {
+            ApiMetrics::increment_confidential_requests_count("decrypted");
+            fields.insert("txs".to_string(), txs);
+            Ok(body)
+        }
+        Err(err) => {
+            ApiMetrics::increment_confidential_requests_count("failed");
+            Err(err)
+        }
+    }
+}
+
+impl FromStr for ConfidentialKey {
+    type Err = ConfidentialError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = hex::decode(s.trim_start_matches("0x"))
+            .map_err(|err| ConfidentialError::InvalidKey(err.to_string()))?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl fmt::Debug for ConfidentialKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConfidentialKey").field("public", &self.public_key()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hpke::{
+        aead::ChaCha20Poly1305, kdf::HkdfSha256, Deserializable, Kem as KemTrait, OpModeS,
+        Serializable,
+    };
+    use serde_json::json;
+
+    use super::{
+        decrypt_request, ConfidentialError, ConfidentialKey, Kem, ENCRYPTED_TXS_FIELD, HPKE_INFO,
+    };
+
+    fn seal(key: &ConfidentialKey, slot: u64, txs: &serde_json::Value) -> serde_json::Value {
+        let public = <Kem as KemTrait>::PublicKey::from_bytes(&key.public_key()).unwrap();
+        let (encapped_key, ciphertext) =
+            hpke::single_shot_seal::<ChaCha20Poly1305, HkdfSha256, Kem, _>(
+                &OpModeS::Base,
+                &public,
+                HPKE_INFO,
+                &serde_json::to_vec(txs).unwrap(),
+                &slot.to_be_bytes(),
+                &mut rand::thread_rng(),
+            )
+            .unwrap();
+
+        json!({
+            "encapped_key": format!("0x{}", alloy::hex::encode(encapped_key.to_bytes())),
+            "ciphertext": format!("0x{}", alloy::hex::encode(ciphertext)),
+        })
+    }
+
+    #[test]
+    fn test_decrypt_request() {
+        let key: ConfidentialKey = format!("0x{}", "07".repeat(32)).parse().unwrap();
+        let txs = json!(["0x02f86b"]);
+
+        let body = json!({ "slot": 10, ENCRYPTED_TXS_FIELD: seal(&key, 10, &txs) });
+        let decrypted = decrypt_request(Some(&key), body.clone()).unwrap();
+        assert_eq!(decrypted, json!({ "slot": 10, "txs": txs }));
+
+        // Disabled gateways refuse them, and ciphertexts are bound to their slot
+        assert!(matches!(
+            decrypt_request(None, body),
+            Err(ConfidentialError::Disabled)
+        ));
+        let replayed = json!({ "slot": 11, ENCRYPTED_TXS_FIELD: seal(&key, 10, &txs) });
+        assert!(matches!(
+            decrypt_request(Some(&key), replayed),
+            Err(ConfidentialError::Decryption)
+        ));
+
+        // Plain requests are left untouched
+        let plain = json!({ "slot": 10, "txs": txs });
+        assert_eq!(decrypt_request(None, plain.clone()).unwrap(), plain);
+    }
+}
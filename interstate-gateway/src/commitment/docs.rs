@@ -0,0 +1,161 @@
+use utoipa::OpenApi;
+
+use super::{
+    auth::{AuthError, AuthFailure},
+    confidential::ConfidentialInfo,
+    quote::{PriceQuote, SignedQuote},
+    rate_limit::{RateLimitKey, RateLimited},
+    receipt::{
+        CommitmentReceipt, ContractDeployment, PreconfReceipt, SignedPreconfReceipt, SignedReceipt,
+    },
+    validation::{FieldError, FieldErrorCode},
+    FieldErrors, GatewayInfo, PreconfResponse, RelayHealth, StatusFeed, TxVerdict,
+};
+use crate::{
+    delegation::health::{DelegationGap, DelegationReport, GapReason},
+    delegation::keycheck::{ExpectedKeySource, KeyCheckReport, MissingKey},
+    delegation::lookup::{ActiveDelegation, DelegationSource, ValidatorDelegations},
+    delegation::registry::{
+        RegisteredDelegation, RegisteredValidator, RegistrySnapshot, RegistryStatus,
+    },
+    state::{
+        audit::{AuditedBid, SlotAudit},
+        capacity::{CapacityReport, CommittedConstraints, SlotCapacity, SlotConstraints, SlotSummary},
+        pricing::{CommittedSpace, PricingReport, SlotPricing, Underpriced},
+        reservations::{CapacityReservation, ReservationRequest},
+        inclusion::{RecentOutcomes, ReliabilitySummary},
+        revenue::{EpochRevenueReport, ProposalRevenue},
+        status::{ComponentHealth, RecordedError, SidecarStatus, UpcomingProposal},
+    },
+    utils::{
+        breaker::{BreakerOpen, BreakerState, BreakerStatus, BreakersReport, Dependency},
+        score_cache::{EvictionPolicy, ScoreCacheStats},
+    },
+};
+
+/// Path of the Swagger UI of the commitments API.
+pub const DOCS_PATH: &str = "/docs";
+/// Path of the OpenAPI specification of the commitments API.
+pub const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+/// OpenAPI specification of the commitments API, generated from the annotations of its handlers.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Interstate gateway commitments API",
+        description = "Requests with invalid fields are rejected with a 400 listing each field \
+                       by its JSON pointer along with a stable `code`, the other errors are \
+                       reported as plain text."
+    ),
+    paths(
+        super::handle_home,
+        super::handle_info,
+        super::handle_status_feed,
+        super::handle_preconfirmation,
+        super::handle_pricing,
+        super::handle_quote,
+        super::handle_capacity,
+        super::handle_reserve,
+        super::handle_reservations,
+        super::handle_receipt,
+        super::handle_events,
+        super::handle_account_states_cache,
+        super::handle_revenue,
+        super::handle_revenue_csv,
+        super::handle_slot_audit,
+        super::handle_slot_constraints,
+        super::handle_slot_summary,
+        super::handle_status,
+        super::handle_readyz,
+        super::handle_delegations,
+        super::handle_registered_delegations,
+    ),
+    components(schemas(
+        AuthFailure,
+        AuthError,
+        RateLimited,
+        RateLimitKey,
+        GatewayInfo,
+        ConfidentialInfo,
+        ReliabilitySummary,
+        StatusFeed,
+        RecentOutcomes,
+        RelayHealth,
+        PreconfResponse,
+        TxVerdict,
+        SignedReceipt,
+        CommitmentReceipt,
+        SignedPreconfReceipt,
+        PreconfReceipt,
+        ContractDeployment,
+        FieldErrors,
+        FieldError,
+        FieldErrorCode,
+        SignedQuote,
+        PriceQuote,
+        CapacityReport,
+        SlotCapacity,
+        SlotConstraints,
+        CommittedConstraints,
+        SlotSummary,
+        ReservationRequest,
+        CapacityReservation,
+        PricingReport,
+        SlotPricing,
+        CommittedSpace,
+        Underpriced,
+        ScoreCacheStats,
+        EvictionPolicy,
+        EpochRevenueReport,
+        ProposalRevenue,
+        SlotAudit,
+        AuditedBid,
+        SidecarStatus,
+        UpcomingProposal,
+        ComponentHealth,
+        RecordedError,
+        DelegationReport,
+        DelegationGap,
+        GapReason,
+        ValidatorDelegations,
+        ActiveDelegation,
+        DelegationSource,
+        RegistrySnapshot,
+        RegisteredValidator,
+        RegisteredDelegation,
+        RegistryStatus,
+        KeyCheckReport,
+        MissingKey,
+        ExpectedKeySource,
+        BreakerOpen,
+        BreakersReport,
+        BreakerStatus,
+        BreakerState,
+        Dependency,
+    )),
+    tags(
+        (name = "commitments", description = "Requesting and pricing commitments"),
+        (name = "stats", description = "Revenue and audit trail of the proposals served by the sidecar"),
+        (name = "admin", description = "Health and state of the sidecar, for the operators"),
+    )
+)]
+pub struct CommitmentsApiDoc;
+
+#[cfg(test)]
+mod tests {
+    use utoipa::OpenApi;
+
+    use super::CommitmentsApiDoc;
+    use crate::commitment::STATUS_PATH;
+
+    #[test]
+    fn test_commitments_api_doc() {
+        let doc = CommitmentsApiDoc::openapi();
+        for path in ["/api/v1/preconfirmation", "/api/v1/pricing", "/api/v1/pricing/quote", "/api/v1/capacity", "/api/v1/receipts/{tx_hash}", STATUS_PATH, "/readyz"] {
+            assert!(doc.paths.paths.contains_key(path), "{path} is not documented");
+        }
+
+        let schemas = doc.components.unwrap().schemas;
+        assert!(schemas.contains_key("FieldErrorCode"));
+    }
+}
@@ -0,0 +1,110 @@
+use axum::extract::ws::{Message, WebSocket};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::{errors::RelayErrorKind, metrics::ApiMetrics};
+
+/// Number of events buffered per subscriber before it starts lagging.
+const EVENTS_CHANNEL_CAPACITY: usize = 256;
+
+/// Events pushed to the clients connected to `/api/v1/events`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ApiEvent {
+    /// A new head was received from the beacon node.
+    NewHead { slot: u64 },
+    /// Commitments are accepted for `slot` until `deadline_ms` (unix timestamp in ms).
+    DeadlineOpened { slot: u64, deadline_ms: i64 },
+    /// The commitment deadline of `slot` passed, its constraints are being submitted.
+    DeadlineClosed { slot: u64 },
+    /// Pricing for the next slot after the execution state was updated.
+    PricingUpdate {
+        slot: u64,
+        basefee: u128,
+        /// Minimum priority fee of a simple transfer, in wei.
+        min_priority_fee: Option<u64>,
+    },
+    /// The block of a slot we committed constraints in was imported, and its commitments were
+    /// classified.
+    CommitmentsFinalized { slot: u64, honored: bool, included: usize, missing: usize },
+    /// The relay rejected the constraints of `slot`.
+    ConstraintsRejected { slot: u64, reason: RelayErrorKind, message: String },
+}
+
+/// Fan-out of [ApiEvent]s to the connected websocket clients.
+#[derive(Debug, Clone)]
+pub struct EventBroadcaster {
+    sender: broadcast::Sender<ApiEvent>,
+}
+
+impl Default for EventBroadcaster {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBroadcaster {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENTS_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Broadcast an event. Events are dropped when no client is connected.
+    pub fn send(&self, event: ApiEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ApiEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Forward the events to a websocket client until it disconnects.
+    pub async fn serve(self, mut socket: WebSocket) {
+        let mut events = self.subscribe();
+        ApiMetrics::set_events_subscribers(self.sender.receiver_count());
+
+        loop {
+            tokio::select! {
+                event = events.recv() => {
+                    let event = match event {
+                        Ok(event) => event,
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, "Events client lagging behind, skipped events");
+                            continue;
+                        }
+                        Err(RecvError::Closed) => break,
+                    };
+
+                    let Ok(text) = serde_json::to_string(&event) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
+                },
+                message = socket.recv() => match message {
+                    // Clients aren't expected to send anything but pings, answered by axum
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+
+        drop(events);
+        ApiMetrics::set_events_subscribers(self.sender.receiver_count());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ApiEvent;
+
+    #[test]
+    fn test_api_event_serialization() {
+        let event = ApiEvent::DeadlineOpened { slot: 42, deadline_ms: 1_700_000_000_000 };
+        assert_eq!(
+            serde_json::to_value(event).unwrap(),
+            serde_json::json!({ "type": "deadline_opened", "slot": 42, "deadline_ms": 1_700_000_000_000i64 })
+        );
+    }
+}
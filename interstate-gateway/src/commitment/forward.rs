@@ -0,0 +1,208 @@
+use std::{collections::HashMap, net::IpAddr, path::Path, sync::Arc, time::Duration};
+
+use alloy::{
+    hex,
+    primitives::{keccak256, B256},
+};
+use axum::{
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use beacon_api_client::ProposerDuty;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use parking_lot::RwLock;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::{
+    auth::API_KEY_HEADER, rate_limit::FORWARDED_FOR_HEADER, request::CommitmentRequestError,
+};
+use crate::{metrics::ApiMetrics, utils::url::join_path};
+
+/// Header set on forwarded requests, so that they are never forwarded twice. It carries the
+/// MAC of the request with the secret shared by the gateways, so that clients can't set it.
+pub const FORWARDED_HEADER: &str = "x-interstate-forwarded";
+
+/// Timeout of a request forwarded to a peer gateway.
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+const PRECONFIRMATION_PATH: &str = "/api/v1/preconfirmation";
+
+/// Proposers of the slots of the current and next epochs, shared with the commitments API.
+pub type SharedProposers = Arc<RwLock<HashMap<u64, BlsPublicKey>>>;
+
+/// Record the proposers of the epochs from their duties.
+pub fn update_proposers<'a>(
+    proposers: &SharedProposers,
+    duties: impl IntoIterator<Item = &'a ProposerDuty>,
+) {
+    *proposers.write() =
+        duties.into_iter().map(|duty| (duty.slot, duty.public_key.clone())).collect();
+}
+
+/// A gateway of the network and the validators it serves commitments for.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PeerGateway {
+    pub url: Url,
+    pub validators: Vec<BlsPublicKey>,
+}
+
+/// Forwards the requests for slots proposed by validators of peer gateways, so that the
+/// network feels like a single endpoint to searchers.
+#[derive(Debug, Clone)]
+pub struct PeerForwarder {
+    peers: HashMap<BlsPublicKey, Url>,
+    proposers: SharedProposers,
+    /// Secret shared by the gateways, authenticating the requests they forward.
+    secret: Arc<[u8]>,
+    client: reqwest::Client,
+}
+
+impl PeerForwarder {
+    pub fn new(peers: Vec<PeerGateway>, proposers: SharedProposers, secret: &str) -> Self {
+        let peers = peers
+            .into_iter()
+            .flat_map(|peer| {
+                let url = peer.url;
+                peer.validators.into_iter().map(move |pubkey| (pubkey, url.clone()))
+            })
+            .collect();
+
+        let secret = secret.as_bytes().into();
+        Self { peers, proposers, secret, client: reqwest::Client::new() }
+    }
+
+    /// Load the peer registry, a JSON list of [PeerGateway]s.
+    pub fn load(path: &Path, proposers: SharedProposers, secret: &str) -> eyre::Result<Self> {
+        let peers = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Self::new(peers, proposers, secret))
+    }
+
+    /// MAC of the serialized request `body`.
+    fn mac(&self, body: &[u8]) -> B256 {
+        keccak256([&self.secret[..], body].concat())
+    }
+
+    /// Whether the request was forwarded by a peer gateway, its [FORWARDED_HEADER] holding
+    /// the MAC of `body`.
+    pub fn is_forwarded(&self, headers: &HeaderMap, body: &Value) -> bool {
+        let Some(mac) = headers.get(FORWARDED_HEADER).and_then(|v| hex::decode(v.as_bytes()).ok())
+        else {
+            return false;
+        };
+        let Ok(body) = serde_json::to_vec(body) else { return false };
+        let expected = self.mac(&body);
+        // Compared in constant time, so that the MAC can't be guessed byte by byte
+        mac.len() == expected.len()
+            && mac.iter().zip(expected.iter()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    /// The gateway serving the proposer of `slot`, if it isn't us.
+    pub fn peer_for(&self, slot: u64) -> Option<Url> {
+        let proposers = self.proposers.read();
+        self.peers.get(proposers.get(&slot)?).cloned()
+    }
+
+    /// Forward the raw request of `client_ip` to `peer` along with its credentials, relaying
+    /// back its response as is.
+    pub async fn forward(
+        &self,
+        peer: &Url,
+        body: &Value,
+        headers: &HeaderMap,
+        client_ip: IpAddr,
+    ) -> Result<Response, CommitmentRequestError> {
+        let url = join_path(peer, PRECONFIRMATION_PATH)
+            .map_err(|err| CommitmentRequestError::Forward(err.to_string()))?;
+        let body = serde_json::to_vec(body)
+            .map_err(|err| CommitmentRequestError::Forward(err.to_string()))?;
+
+        let mut request = self
+            .client
+            .post(url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(FORWARDED_HEADER, hex::encode(self.mac(&body)))
+            .header(FORWARDED_FOR_HEADER, client_ip.to_string())
+            .timeout(FORWARD_TIMEOUT);
+        for name in [header::AUTHORIZATION.as_str(), API_KEY_HEADER] {
+            if let Some(value) = headers.get(name) {
+                request = request.header(name, value);
+            }
+        }
+        let result = request.body(body).send().await;
+
+        let response = match result {
+            Ok(response) => response,
+            Err(err) => {
+                ApiMetrics::increment_forwarded_requests_count("failed");
+                return Err(CommitmentRequestError::Forward(err.to_string()));
+            }
+        };
+
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| CommitmentRequestError::Forward(err.to_string()))?;
+
+        ApiMetrics::increment_forwarded_requests_count(if status.is_success() {
+            "accepted"
+        } else {
+            "rejected"
+        });
+        Ok((status, [(header::CONTENT_TYPE, "application/json")], body).into_response())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::hex;
+    use axum::http::{HeaderMap, HeaderValue};
+    use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+    use serde_json::json;
+
+    use super::{PeerForwarder, PeerGateway, FORWARDED_HEADER};
+
+    fn pubkey(byte: u8) -> BlsPublicKey {
+        let sk = blst::min_pk::SecretKey::key_gen(&[byte; 32], &[]).unwrap();
+        BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap()
+    }
+
+    #[test]
+    fn test_peer_for_slot() {
+        let peers: Vec<PeerGateway> = serde_json::from_str(&format!(
+            r#"[{{ "url": "http://peer:8000", "validators": ["0x{}"] }}]"#,
+            hex::encode(pubkey(1).as_ref())
+        ))
+        .unwrap();
+
+        let forwarder = PeerForwarder::new(peers, Default::default(), "s3cret");
+        forwarder.proposers.write().extend([(10, pubkey(1)), (11, pubkey(2))]);
+
+        assert_eq!(forwarder.peer_for(10).unwrap().as_str(), "http://peer:8000/");
+        // Our own validators, and slots of unknown proposers, are served locally
+        assert!(forwarder.peer_for(11).is_none());
+        assert!(forwarder.peer_for(12).is_none());
+    }
+
+    #[test]
+    fn test_forwarded_header_is_authenticated() {
+        let forwarder = PeerForwarder::new(vec![], Default::default(), "s3cret");
+        let body = json!({ "slot": 10, "txs": ["0x01"] });
+        let headers = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(FORWARDED_HEADER, HeaderValue::from_str(value).unwrap());
+            headers
+        };
+        let mac = hex::encode(forwarder.mac(&serde_json::to_vec(&body).unwrap()));
+
+        assert!(forwarder.is_forwarded(&headers(&mac), &body));
+        assert!(!forwarder.is_forwarded(&HeaderMap::new(), &body));
+        assert!(!forwarder.is_forwarded(&headers("1"), &body));
+        // Bound to the request, and to the secret
+        assert!(!forwarder.is_forwarded(&headers(&mac), &json!({ "slot": 11, "txs": ["0x01"] })));
+        let other = PeerForwarder::new(vec![], Default::default(), "other");
+        assert!(!other.is_forwarded(&headers(&mac), &body));
+    }
+}
@@ -0,0 +1,177 @@
+use alloy::primitives::{FixedBytes, B256};
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use super::receipt::{ReceiptGossipMode, SignedPreconfReceipt};
+use crate::metrics::ApiMetrics;
+use crate::constraints::CommitBoostApi;
+
+/// Most receipts pushed to the collector in a single request.
+const MAX_GOSSIP_BATCH: usize = 64;
+
+/// A [SignedPreconfReceipt] without its transactions, still verifiable as its signature is
+/// over the digest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptDigest {
+    pub slot: u64,
+    pub validator_pubkey: BlsPublicKey,
+    pub expiry_ms: u64,
+    pub digest: B256,
+    pub tx_count: usize,
+    pub signer: BlsPublicKey,
+    pub signature: FixedBytes<96>,
+}
+
+/// Receipt pushed to the collector, in the [ReceiptGossipMode] of the gateway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GossipedReceipt {
+    Full(SignedPreconfReceipt),
+    HashOnly(ReceiptDigest),
+}
+
+impl GossipedReceipt {
+    pub fn new(receipt: SignedPreconfReceipt, mode: ReceiptGossipMode) -> Self {
+        match mode {
+            ReceiptGossipMode::Full => Self::Full(receipt),
+            ReceiptGossipMode::HashOnly => Self::HashOnly(ReceiptDigest {
+                slot: receipt.message.slot,
+                digest: receipt.message.digest().into(),
+                tx_count: receipt.message.tx_hashes.len(),
+                validator_pubkey: receipt.message.validator_pubkey,
+                expiry_ms: receipt.message.expiry_ms,
+                signer: receipt.signer,
+                signature: receipt.signature,
+            }),
+        }
+    }
+
+    pub fn slot(&self) -> u64 {
+        match self {
+            Self::Full(receipt) => receipt.message.slot,
+            Self::HashOnly(digest) => digest.slot,
+        }
+    }
+}
+
+/// Pushes the signed receipts to the collector, giving the network a view of what was
+/// committed where.
+///
+/// The receipts are queued without waiting on the collector, and pushed in batches by a
+/// background task. Receipts are dropped rather than slowing the commitments down once the
+/// queue is full.
+#[derive(Debug, Clone)]
+pub struct ReceiptGossip {
+    queue: mpsc::Sender<GossipedReceipt>,
+    mode: ReceiptGossipMode,
+}
+
+impl ReceiptGossip {
+    /// Queue of `capacity` receipts, consumed from the returned receiver.
+    pub fn new(
+        mode: ReceiptGossipMode,
+        capacity: usize,
+    ) -> (Self, mpsc::Receiver<GossipedReceipt>) {
+        let (queue, receipts) = mpsc::channel(capacity.max(1));
+        (Self { queue, mode }, receipts)
+    }
+
+    /// Push the receipts to the collector of `api`, on the requests to the collector bypassing
+    /// the rate limit shared with the constraints submissions.
+    pub fn spawn(api: CommitBoostApi, mode: ReceiptGossipMode, capacity: usize) -> Self {
+        let (gossip, mut receipts) = Self::new(mode, capacity);
+        tokio::spawn(async move {
+            while let Some(batch) = next_batch(&mut receipts).await {
+                match api.send_receipts_to_be_collected(&batch).await {
+                    Ok(()) => ApiMetrics::increment_gossiped_receipts_count("pushed", batch.len()),
+                    Err(err) => {
+                        tracing::warn!(?err, receipts = batch.len(), "Failed to push receipts to the collector");
+                        ApiMetrics::increment_gossiped_receipts_count("failed", batch.len());
+                    }
+                }
+            }
+        });
+        gossip
+    }
+
+    pub fn mode(&self) -> ReceiptGossipMode {
+        self.mode
+    }
+
+    /// Queue `receipt` to be pushed, dropping it if the queue is full.
+    pub fn push(&self, receipt: SignedPreconfReceipt) {
+        match self.queue.try_send(GossipedReceipt::new(receipt, self.mode)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(receipt)) => {
+                tracing::debug!(slot = receipt.slot(), "Receipt gossip queue full, dropping a receipt");
+                ApiMetrics::increment_gossiped_receipts_count("dropped", 1);
+            }
+            Err(TrySendError::Closed(_)) => {
+                ApiMetrics::increment_gossiped_receipts_count("dropped", 1);
+            }
+        }
+    }
+}
+
+/// Wait for a receipt, batched with the others already queued, `None` once the queue closed.
+pub async fn next_batch(
+    receipts: &mut mpsc::Receiver<GossipedReceipt>,
+) -> Option<Vec<GossipedReceipt>> {
+    let first = receipts.recv().await?;
+    let mut batch = vec![first];
+    while batch.len() < MAX_GOSSIP_BATCH {
+        match receipts.try_recv() {
+            Ok(receipt) => batch.push(receipt),
+            Err(_) => break,
+        }
+    }
+    Some(batch)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{FixedBytes, B256};
+    use ethereum_consensus::{crypto::PublicKey as BlsPublicKey, deneb::compute_signing_root};
+
+    use super::{next_batch, GossipedReceipt, ReceiptGossip, ReceiptGossipMode};
+    use crate::{
+        commitment::receipt::{PreconfReceipt, SignedPreconfReceipt},
+        delegation::signing::{verify_root, BLS_DST_PREFIX},
+    };
+
+    fn receipt(slot: u64) -> SignedPreconfReceipt {
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let pubkey = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        let message = PreconfReceipt {
+            slot,
+            tx_hashes: vec![B256::repeat_byte(1), B256::repeat_byte(2)],
+            validator_pubkey: pubkey.clone(),
+            expiry_ms: slot * 12_000,
+            deployments: vec![],
+        };
+        let root = compute_signing_root(&message.digest(), [1; 32]).unwrap();
+        let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+        SignedPreconfReceipt { message, signer: pubkey, signature: FixedBytes::from(signature) }
+    }
+
+    #[tokio::test]
+    async fn test_hash_only_gossip_with_backpressure() {
+        let (gossip, mut receipts) = ReceiptGossip::new(ReceiptGossipMode::HashOnly, 2);
+        for slot in 10..13 {
+            gossip.push(receipt(slot));
+        }
+
+        // The receipt pushed to the full queue was dropped
+        let batch = next_batch(&mut receipts).await.unwrap();
+        assert_eq!(batch.iter().map(GossipedReceipt::slot).collect::<Vec<_>>(), vec![10, 11]);
+
+        let GossipedReceipt::HashOnly(digest) = &batch[0] else { panic!("full receipt gossiped") };
+        assert_eq!(digest.tx_count, 2);
+        let json = serde_json::to_string(&batch[0]).unwrap();
+        assert!(!json.contains(&B256::repeat_byte(1).to_string()[2..]));
+        // Still verifiable against the signature of the delegatee
+        let signature = blst::min_pk::Signature::from_bytes(digest.signature.as_slice()).unwrap();
+        assert!(verify_root(digest.signer.clone(), digest.digest.0, &signature, [1; 32]).is_ok());
+    }
+}
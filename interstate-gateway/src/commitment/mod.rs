@@ -1,84 +1,268 @@
+pub mod auth;
+pub mod confidential;
+pub mod docs;
+pub mod events;
+pub mod forward;
+#[cfg(feature = "collector-client")]
+pub mod gossip;
 pub mod misc;
+pub mod quote;
+pub mod rate_limit;
+pub mod receipt;
+pub mod replica;
 pub mod request;
+pub mod sequencer;
+pub mod validation;
+use alloy::primitives::{Address, B256};
 use axum::{
     debug_handler,
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{ws::WebSocketUpgrade, ConnectInfo, Path, Query, Request, State},
+    http::{header, HeaderMap, StatusCode},
     middleware::{self, Next},
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{post, get}, // Add 'get' to the routing imports
-    Json, Router,
+    Extension, Json, Router,
 };
-use axum_client_ip::{InsecureClientIp, SecureClientIp, SecureClientIpSource};
-use serde::Serialize;
+use axum_client_ip::InsecureClientIp;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use serde::{Deserialize, Serialize};
 use serde_json::{from_value, Value};
 use std::{net::SocketAddr, sync::Arc, time::Instant};
 use tokio::sync::mpsc;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::config::Config;
+use crate::handover::{spawn_server, InstanceLease};
+use crate::state::{
+    audit::{AuditTrail, SlotAudit},
+    capacity::{parse_slot_range, CapacityReport, CapacityView, SlotConstraints, SlotSummary},
+    pricing::{PricingReport, PriorityTier, Underpriced},
+    reservations::{CapacityReservation, ReservationBook, ReservationError, ReservationRequest},
+    revenue::{EpochRevenueReport, RevenueTracker},
+    execution::SharedExecutionSnapshot,
+    slot_clock::SlotClock,
+    inclusion::{InclusionStats, RecentOutcomes, ReliabilitySummary, OUTCOMES_WINDOW},
+    mempool::ReplacementGuard,
+    stale::StaleTxIndex,
+    status::{SidecarStatus, StatusBoard},
+    sync::ElSyncMonitor,
+};
+use crate::utils::{
+    breaker::{BreakerOpen, BreakerState, BreakersReport, CircuitBreakers},
+    score_cache::{ScoreCacheStats, SharedScoreCacheStats},
+};
 use crate::{
+    commitment::auth::{require_auth, AuthFailure},
+    commitment::confidential::{ConfidentialError, ConfidentialInfo},
+    commitment::docs::{CommitmentsApiDoc, DOCS_PATH, OPENAPI_PATH},
+    commitment::events::EventBroadcaster,
+    commitment::forward::PeerForwarder,
+    commitment::quote::{QuoteError, Quoter, SignedQuote},
+    commitment::rate_limit::{RateLimited, RequestRateLimiters, TrustedProxies},
+    commitment::receipt::{ReceiptStore, SignedPreconfReceipt, SignedReceipt},
     commitment::request::{
-        CommitmentRequestError, CommitmentRequestEvent, CommitmentRequestHandler, PreconfRequest,
+        CommitmentRequestError, CommitmentRequestEvent, CommitmentRequestHandler,
     },
+    commitment::sequencer::RequestSequencer,
+    commitment::validation::FieldError,
     constraints::SignedConstraints,
+    delegation::health::{DelegationHealth, DelegationReport},
+    delegation::keycheck::{KeyCheckReport, SignerKeyCheck},
+    delegation::lookup::{DelegationLookup, LookupError, ValidatorDelegations},
+    delegation::registry::{DelegationRegistry, RegistrySnapshot},
     metrics::ApiMetrics,
 };
 
 // Add this new handler function for the homepage
+#[utoipa::path(get, path = "/", tag = "commitments", responses((status = 200, body = Object)))]
 async fn handle_home() -> impl IntoResponse {
     Json(serde_json::json!({ "you're at the interstate rpc, read our docs at https://docs.interstate.so": true }))
 }
 
+/// Path of the endpoint serving the live state of the sidecar to the operators.
+pub const STATUS_PATH: &str = "/api/v1/admin/status";
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run_commitment_rpc_server(
     event_sender: mpsc::Sender<CommitmentRequestEvent>,
     config: &Config,
+    slot_clock: SlotClock,
+    account_states_stats: SharedScoreCacheStats,
+    execution: SharedExecutionSnapshot,
+    events: EventBroadcaster,
+    revenue: RevenueTracker,
+    el_sync: ElSyncMonitor,
+    quoter: Quoter,
+    forwarder: Option<PeerForwarder>,
+    status: StatusBoard,
+    delegation_health: Option<DelegationHealth>,
+    delegation_lookup: DelegationLookup,
+    delegation_registry: DelegationRegistry,
+    signer_keys: SignerKeyCheck,
+    inclusion_stats: InclusionStats,
+    replacements: ReplacementGuard,
+    stale: StaleTxIndex,
+    audit: AuditTrail,
+    capacity: CapacityView,
+    breakers: CircuitBreakers,
+    receipts: ReceiptStore,
+    reservations: ReservationBook,
+    sequencer: Option<RequestSequencer>,
+    rate_limits: RequestRateLimiters,
+    lease: InstanceLease,
 ) {
     let handler = CommitmentRequestHandler::new(
         event_sender,
         config.execution_api_url.clone(),
         config.gateway_contract,
+        config.chain.id,
+        slot_clock,
+        account_states_stats,
+        execution,
+        events,
+        el_sync,
+        quoter,
+        forwarder,
+        config.confidential_key.clone(),
+        config.sender_policy.clone(),
+        replacements,
+        stale,
+        sequencer,
+        rate_limits,
     );
 
     let app = Router::new()
         .route("/", get(handle_home)) // Add this route for the homepage
-        .route("/api/v1/preconfirmation", post(handle_preconfirmation))
+        .route("/api/v1/info", get(handle_info))
+        .route("/api/v1/status-feed", get(handle_status_feed))
+        .route(
+            "/api/v1/preconfirmation",
+            post(handle_preconfirmation)
+                .route_layer(middleware::from_fn_with_state(config.api_auth.clone(), require_auth)),
+        )
+        .route("/api/v1/debug/account_states_cache", get(handle_account_states_cache))
+        .route("/api/v1/events", get(handle_events))
+        .route("/api/v1/pricing", get(handle_pricing))
+        .route("/api/v1/pricing/quote", get(handle_quote))
+        .route("/api/v1/capacity", get(handle_capacity))
+        .route("/api/v1/reservations", get(handle_reservations).post(handle_reserve))
+        .route("/api/v1/receipts/:tx_hash", get(handle_receipt))
+        .route("/api/v1/stats/revenue", get(handle_revenue))
+        .route("/api/v1/stats/revenue.csv", get(handle_revenue_csv))
+        .route("/api/v1/slots/:slot/audit", get(handle_slot_audit))
+        .route("/api/v1/slots/:slot/constraints", get(handle_slot_constraints))
+        .route("/api/v1/slots/:slot/summary", get(handle_slot_summary))
+        .route(STATUS_PATH, get(handle_status))
+        .route("/readyz", get(handle_readyz))
+        .route("/api/v1/delegations", get(handle_registered_delegations))
+        .route("/api/v1/delegations/:validator_pubkey", get(handle_delegations))
+        .merge(SwaggerUi::new(DOCS_PATH).url(OPENAPI_PATH, CommitmentsApiDoc::openapi()))
         .route_layer(middleware::from_fn(track_metrics))
-        .layer(SecureClientIpSource::ConnectInfo.into_extension())
+        .layer(Extension(revenue))
+        .layer(Extension(audit))
+        .layer(Extension(status))
+        .layer(Extension(delegation_health))
+        .layer(Extension(delegation_lookup))
+        .layer(Extension(delegation_registry))
+        .layer(Extension(signer_keys))
+        .layer(Extension(inclusion_stats))
+        .layer(Extension(capacity))
+        .layer(Extension(breakers))
+        .layer(Extension(receipts))
+        .layer(Extension(reservations))
+        .layer(Extension(TrustedProxies::new(config.trusted_proxies.clone())))
         .with_state(handler.clone());
 
+    // With a request log, a standby instance hands the requests it receives to the leader
     let addr: SocketAddr = SocketAddr::from(([0, 0, 0, 0], config.commitment_port));
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-
-    tokio::spawn(async {
-        axum::serve(
-            listener,
-            app.into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .await
-        .unwrap();
-    });
-    tracing::info!("commitment RPC server is listening on .. {}", addr);
+    let standby_serves = config.request_log_path.is_some();
+    spawn_server(
+        "commitment RPC",
+        app,
+        addr,
+        lease,
+        config.instance_lease_path.is_some(),
+        standby_serves,
+    );
 }
 
+/// Request the commitment of transactions in a slot, signing constraints for them.
+#[utoipa::path(
+    post,
+    path = "/api/v1/preconfirmation",
+    tag = "commitments",
+    request_body(
+        content = Object,
+        description = "Transactions to commit in `txs`, or sealed to the confidential key of the gateway in `encrypted_txs`. With `partial`, the valid transactions are committed and the verdict of each is returned. With a `fast` or `top` `priority`, the transactions pay a multiple of the minimum inclusion tip and are placed ahead of the lower tiers",
+    ),
+    params(("x-interstate-forwarded" = Option<String>, Header, description = "Set by the peer gateway forwarding the request, the hex keccak256 of the shared secret followed by the JSON body")),
+    responses(
+        (status = 200, body = PreconfResponse),
+        (status = 400, description = "Invalid fields, located by their JSON pointer", body = FieldErrors),
+        (status = 400, description = "A transaction tips less than the minimum inclusion tip", body = Underpriced),
+        (status = 401, description = "Missing or invalid API key or bearer token", body = AuthFailure),
+        (status = 403, description = "Transaction not signed by the sender or an allowed relayer", body = String),
+        (status = 429, description = "Too many requests by the sender or from the client IP", body = RateLimited),
+        (status = 404, description = "Confidential requests are not enabled", body = String),
+        (status = 502, description = "Peer gateway of the proposer unreachable", body = String),
+        (status = 503, description = "Execution client syncing, gateway on standby or no leader processed the shared request in time", body = String),
+        (status = 503, description = "A dependency of the request fails fast, its circuit breaker is open", body = BreakerOpen),
+        (status = 500, body = String),
+    ),
+)]
 #[debug_handler]
 // async fn handle_preconfirmation (insecure_ip: InsecureClientIp, secure_ip: SecureClientIp, State(handler):State<Arc<CommitmentRequestHandler>>, Json(body):Json<PreconfRequest>) -> Result<Json<PreconfResponse>, CommitmentRequestError>{
 async fn handle_preconfirmation(
     State(handler): State<Arc<CommitmentRequestHandler>>,
-    Json(body): Json<PreconfRequest>,
-) -> Result<Json<PreconfResponse>, CommitmentRequestError> {
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Extension(proxies): Extension<TrustedProxies>,
+    headers: HeaderMap,
+    Json(raw): Json<Value>,
+) -> Result<Response, CommitmentRequestError> {
+    let client_ip = proxies.client_ip(peer.ip(), &headers);
+    handler.check_ip_rate_limit(client_ip)?;
+
+    // Confidential requests are decrypted here, the peer gateways can't decrypt them
+    let raw = handler.decrypt_request(raw)?;
+    let body = handler.parse_request(&raw)?;
+
+    // Requests for the slots of peer gateways are served by them, forwarded at most once
+    if let Some((forwarder, peer)) = handler.peer_for(body.slot) {
+        if !forwarder.is_forwarded(&headers, &raw) {
+            tracing::debug!(slot = body.slot, %peer, "Forwarding request to the gateway of the proposer");
+            return forwarder.forward(&peer, &raw, &headers, client_ip).await;
+        }
+    }
+
     match handler.handle_commitment_request(&body).await {
         Ok(value) => {
             let signed_contraints_list = value
                 .get("signed_contraints_list")
                 .and_then(|v| from_value::<Vec<SignedConstraints>>(v.clone()).ok()) // Deserialize safely
                 .unwrap_or_default(); // If None or error, return an empty Vec;
+            let receipt = value
+                .get("receipt")
+                .and_then(|v| from_value::<SignedReceipt>(v.clone()).ok());
+            let inclusion_receipt = value
+                .get("inclusion_receipt")
+                .and_then(|v| from_value::<SignedPreconfReceipt>(v.clone()).ok());
+            let verdicts =
+                value.get("verdicts").and_then(|v| from_value::<Vec<TxVerdict>>(v.clone()).ok());
+            let priority = value
+                .get("priority")
+                .and_then(|v| from_value::<PriorityTier>(v.clone()).ok())
+                .unwrap_or_default();
 
             let response = PreconfResponse {
                 ok: true,
                 signed_contraints_list: signed_contraints_list,
+                receipt,
+                inclusion_receipt,
+                verdicts,
+                priority,
             };
-            return Ok(Json(response));
+            return Ok(Json(response).into_response());
         }
         Err(e) => return Err(e),
     };
@@ -108,10 +292,475 @@ async fn handle_preconfirmation(
     // }
 }
 
-#[derive(Serialize)]
+/// Debug endpoint exposing the account states cache stats.
+#[utoipa::path(
+    get,
+    path = "/api/v1/debug/account_states_cache",
+    tag = "admin",
+    responses((status = 200, body = ScoreCacheStats)),
+)]
+async fn handle_account_states_cache(
+    State(handler): State<Arc<CommitmentRequestHandler>>,
+) -> Json<ScoreCacheStats> {
+    Json(handler.account_states_stats())
+}
+
+/// Per-epoch revenue of the proposals served by the sidecar.
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/revenue",
+    tag = "stats",
+    responses((status = 200, body = Vec<EpochRevenueReport>)),
+)]
+async fn handle_revenue(
+    Extension(revenue): Extension<RevenueTracker>,
+) -> Json<Vec<EpochRevenueReport>> {
+    Json(revenue.epoch_reports())
+}
+
+/// Revenue of the proposals served by the sidecar, one per line.
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/revenue.csv",
+    tag = "stats",
+    responses((status = 200, body = String, content_type = "text/csv")),
+)]
+async fn handle_revenue_csv(Extension(revenue): Extension<RevenueTracker>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/csv")], revenue.to_csv())
+}
+
+/// Inclusion receipt of a committed transaction, kept for the recent slots.
+#[utoipa::path(
+    get,
+    path = "/api/v1/receipts/{tx_hash}",
+    tag = "commitments",
+    params(("tx_hash" = String, Path, description = "Hash of the committed transaction")),
+    responses(
+        (status = 200, body = SignedPreconfReceipt),
+        (status = 400, description = "Invalid transaction hash", body = String),
+        (status = 404, description = "Transaction not committed, or past the retention window"),
+    ),
+)]
+async fn handle_receipt(
+    Extension(receipts): Extension<ReceiptStore>,
+    Path(tx_hash): Path<String>,
+) -> Response {
+    let Ok(hash) = tx_hash.parse::<B256>() else {
+        return (StatusCode::BAD_REQUEST, format!("invalid transaction hash {tx_hash}"))
+            .into_response();
+    };
+    match receipts.get(&hash) {
+        Some(receipt) => Json(receipt.as_ref().clone()).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("no receipt for transaction {hash}")).into_response(),
+    }
+}
+
+/// Audit chain of a proposed slot: the constraints submitted to the relay, the bid returned to
+/// the proposer and the payload delivered.
+#[utoipa::path(
+    get,
+    path = "/api/v1/slots/{slot}/audit",
+    tag = "stats",
+    params(("slot" = u64, Path, description = "Slot of the proposal")),
+    responses(
+        (status = 200, body = SlotAudit),
+        (status = 404, description = "Not a proposal served by the sidecar, or past the retention window"),
+    ),
+)]
+async fn handle_slot_audit(
+    Extension(audit): Extension<AuditTrail>,
+    Path(slot): Path<u64>,
+) -> Response {
+    match audit.slot(slot) {
+        Some(audit) => Json(audit).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("no audit trail for slot {slot}")).into_response(),
+    }
+}
+
+/// Constraints signed for a slot so far, with the hashes and gas of their transactions, for the
+/// relays and auditors to verify what was promised before the deadline.
+#[utoipa::path(
+    get,
+    path = "/api/v1/slots/{slot}/constraints",
+    tag = "commitments",
+    params(("slot" = u64, Path, description = "Slot of the constraints")),
+    responses((status = 200, description = "Empty once the constraints were submitted", body = SlotConstraints)),
+)]
+async fn handle_slot_constraints(
+    Extension(capacity): Extension<CapacityView>,
+    Path(slot): Path<u64>,
+) -> Json<SlotConstraints> {
+    Json(capacity.constraints(slot))
+}
+
+/// Gas and blobs committed in a slot so far.
+#[utoipa::path(
+    get,
+    path = "/api/v1/slots/{slot}/summary",
+    tag = "commitments",
+    params(("slot" = u64, Path, description = "Slot of the constraints")),
+    responses((status = 200, body = SlotSummary)),
+)]
+async fn handle_slot_summary(
+    Extension(capacity): Extension<CapacityView>,
+    Path(slot): Path<u64>,
+) -> Json<SlotSummary> {
+    Json(capacity.summary(slot))
+}
+
+/// Live state of the sidecar, rendered by the `status` command.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/status",
+    tag = "admin",
+    responses((status = 200, body = SidecarStatus)),
+)]
+async fn handle_status(Extension(status): Extension<StatusBoard>) -> Json<SidecarStatus> {
+    Json(status.snapshot())
+}
+
+/// Readiness of the sidecar, failing while a dependency fails fast, delegatee keys we are
+/// expected to sign with are missing from the signer, or upcoming proposals of our validators
+/// have no usable delegation.
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Ready, with the last delegation report if checked", body = DelegationReport),
+        (status = 503, description = "Circuit breaker of a dependency open or half open", body = BreakersReport),
+        (status = 503, description = "Delegations not checked yet or missing for upcoming proposals", body = DelegationReport),
+        (status = 503, description = "Signer keys not checked yet or missing delegatee keys", body = KeyCheckReport),
+    ),
+)]
+async fn handle_readyz(
+    Extension(delegation_health): Extension<Option<DelegationHealth>>,
+    Extension(signer_keys): Extension<SignerKeyCheck>,
+    Extension(breakers): Extension<CircuitBreakers>,
+) -> Response {
+    let breakers = breakers.report();
+    if breakers.is_degraded() {
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(breakers)).into_response();
+    }
+
+    match signer_keys.report() {
+        Some(report) if report.missing.is_empty() => {}
+        Some(report) => return (StatusCode::SERVICE_UNAVAILABLE, Json(report)).into_response(),
+        None => {
+            return (StatusCode::SERVICE_UNAVAILABLE, "signer keys not checked yet").into_response()
+        }
+    }
+
+    let Some(delegation_health) = delegation_health else {
+        return (StatusCode::OK, "ready").into_response();
+    };
+
+    match delegation_health.report() {
+        Some(report) if report.gaps.is_empty() => (StatusCode::OK, Json(report)).into_response(),
+        Some(report) => (StatusCode::SERVICE_UNAVAILABLE, Json(report)).into_response(),
+        None => (StatusCode::SERVICE_UNAVAILABLE, "delegations not checked yet").into_response(),
+    }
+}
+
+/// Verified active delegations and revocations of a validator known to the gateway, from the
+/// relay and the local delegations file.
+#[utoipa::path(
+    get,
+    path = "/api/v1/delegations/{validator_pubkey}",
+    tag = "admin",
+    params(("validator_pubkey" = String, Path, description = "BLS public key of the validator")),
+    responses(
+        (status = 200, body = ValidatorDelegations),
+        (status = 400, description = "Invalid validator public key"),
+        (status = 502, description = "Failed to fetch the delegations"),
+        (status = 503, description = "Lookup not ready yet"),
+    ),
+)]
+async fn handle_delegations(
+    Extension(lookup): Extension<DelegationLookup>,
+    Path(validator_pubkey): Path<String>,
+) -> Response {
+    let validator = alloy::hex::decode(validator_pubkey.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| BlsPublicKey::try_from(bytes.as_slice()).ok());
+    let Some(validator) = validator else {
+        return (StatusCode::BAD_REQUEST, "invalid validator public key").into_response();
+    };
+
+    match lookup.lookup(&validator).await {
+        Ok(delegations) => Json(delegations).into_response(),
+        Err(err @ LookupError::NotReady) => {
+            (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::BAD_GATEWAY, err.to_string()).into_response(),
+    }
+}
+
+/// Delegations of the proposers synced from the relay, with their state in the current epoch.
+#[utoipa::path(
+    get,
+    path = "/api/v1/delegations",
+    tag = "admin",
+    responses((status = 200, body = RegistrySnapshot)),
+)]
+async fn handle_registered_delegations(
+    Extension(registry): Extension<DelegationRegistry>,
+) -> Json<RegistrySnapshot> {
+    Json(registry.snapshot())
+}
+
+#[derive(Serialize, ToSchema)]
+struct GatewayInfo {
+    chain_id: u64,
+    /// Set when the gateway accepts requests with encrypted transactions.
+    confidential: Option<ConfidentialInfo>,
+    /// How reliably the recent commitments of the gateway were included.
+    reliability: ReliabilitySummary,
+}
+
+/// Information clients need to submit requests to the gateway.
+#[utoipa::path(get, path = "/api/v1/info", tag = "commitments", responses((status = 200, body = GatewayInfo)))]
+async fn handle_info(
+    State(handler): State<Arc<CommitmentRequestHandler>>,
+    Extension(inclusion_stats): Extension<InclusionStats>,
+) -> Json<GatewayInfo> {
+    Json(GatewayInfo {
+        chain_id: handler.chain_id(),
+        confidential: handler.confidential_info(),
+        reliability: inclusion_stats.summary(),
+    })
+}
+
+/// Health of the relay the constraints are submitted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+enum RelayHealth {
+    Healthy,
+    /// The latest submission failed, or the relay fails fast.
+    Degraded,
+    /// Nothing was submitted yet.
+    Unknown,
+}
+
+#[derive(Serialize, ToSchema)]
+struct StatusFeed {
+    /// Period the outcomes are tallied over.
+    window_secs: u64,
+    #[serde(flatten)]
+    outcomes: RecentOutcomes,
+    uptime_secs: u64,
+    relay: RelayHealth,
+}
+
+/// Summary of the recent performance of the gateway for public status pages, without any
+/// detail of the failures.
+#[utoipa::path(
+    get,
+    path = "/api/v1/status-feed",
+    tag = "commitments",
+    responses((status = 200, body = StatusFeed)),
+)]
+async fn handle_status_feed(
+    Extension(inclusion_stats): Extension<InclusionStats>,
+    Extension(status): Extension<StatusBoard>,
+    Extension(breakers): Extension<CircuitBreakers>,
+) -> Json<StatusFeed> {
+    let relay = match (breakers.relay.state(), status.is_relay_healthy()) {
+        (BreakerState::Closed, Some(true)) => RelayHealth::Healthy,
+        (BreakerState::Closed, None) => RelayHealth::Unknown,
+        _ => RelayHealth::Degraded,
+    };
+
+    Json(StatusFeed {
+        window_secs: OUTCOMES_WINDOW.as_secs(),
+        outcomes: inclusion_stats.recent_outcomes(),
+        uptime_secs: status.uptime().as_secs(),
+        relay,
+    })
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct QuoteParams {
+    /// Sender of the requests the quote is honored for.
+    #[param(value_type = String)]
+    sender: Address,
+    slot: u64,
+    gas: u64,
+}
+
+/// Signed quote of the price of a commitment, honored when attached to the requests of the same
+/// sender for the same slot before it expires, up to the quoted gas.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pricing/quote",
+    tag = "commitments",
+    params(QuoteParams),
+    responses(
+        (status = 200, body = SignedQuote),
+        (status = 400, description = "Slot or gas can't be quoted", body = String),
+        (status = 404, description = "Price quotes are not enabled", body = String),
+        (status = 500, body = String),
+    ),
+)]
+async fn handle_quote(
+    State(handler): State<Arc<CommitmentRequestHandler>>,
+    Query(params): Query<QuoteParams>,
+) -> Result<Json<SignedQuote>, CommitmentRequestError> {
+    Ok(Json(handler.quote(params.sender, params.slot, params.gas)?))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct PricingParams {
+    /// Slots to price, as `a..b` with `b` excluded, or a single slot. Defaults to the slots
+    /// following the head.
+    slots: Option<String>,
+}
+
+/// Current minimum priority fees of the upcoming slots, from their remaining gas and blob
+/// space and their proximity to the head.
+#[utoipa::path(
+    get,
+    path = "/api/v1/pricing",
+    tag = "commitments",
+    params(PricingParams),
+    responses(
+        (status = 200, body = PricingReport),
+        (status = 400, description = "Invalid slot range, or too many slots requested", body = String),
+    ),
+)]
+async fn handle_pricing(
+    State(handler): State<Arc<CommitmentRequestHandler>>,
+    Extension(capacity): Extension<CapacityView>,
+    Query(params): Query<PricingParams>,
+) -> Response {
+    let max_slots = capacity.max_slots();
+    match params.slots.as_deref().map(|slots| parse_slot_range(slots, max_slots)).transpose() {
+        Ok(slots) => Json(handler.pricing(slots)).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+struct CapacityParams {
+    /// Slots to report, as `a..b` with `b` excluded, or a single slot.
+    slots: String,
+}
+
+/// Remaining gas, commitments and blob space of the requested slots, along with their pricing,
+/// for the router to balance the commitment requests across the gateways.
+#[utoipa::path(
+    get,
+    path = "/api/v1/capacity",
+    tag = "commitments",
+    params(CapacityParams),
+    responses(
+        (status = 200, body = CapacityReport),
+        (status = 400, description = "Invalid slot range, or too many slots requested", body = String),
+    ),
+)]
+async fn handle_capacity(
+    Extension(capacity): Extension<CapacityView>,
+    Query(params): Query<CapacityParams>,
+) -> Response {
+    match parse_slot_range(&params.slots, capacity.max_slots()) {
+        Ok(slots) => Json(capacity.report(slots)).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    }
+}
+
+/// Register a recurring reservation of gas in each slot proposed by our validators, signed by
+/// a rollup sequencer allowed by the operator. The reserved gas is owed for at the agreed
+/// price whether it is used or not.
+#[utoipa::path(
+    post,
+    path = "/api/v1/reservations",
+    tag = "commitments",
+    request_body = ReservationRequest,
+    responses(
+        (status = 200, body = CapacityReservation),
+        (status = 400, description = "Invalid slots, price or gas, or not enough gas left to reserve", body = String),
+        (status = 403, description = "Signer not allowed to reserve capacity", body = String),
+        (status = 404, description = "Capacity reservations are not enabled", body = String),
+    ),
+)]
+async fn handle_reserve(
+    State(handler): State<Arc<CommitmentRequestHandler>>,
+    Extension(reservations): Extension<ReservationBook>,
+    Json(request): Json<ReservationRequest>,
+) -> Result<Json<CapacityReservation>, CommitmentRequestError> {
+    Ok(Json(reservations.register(&request, handler.current_slot())?))
+}
+
+/// The capacity reservations, with the proposal slots charged to them so far.
+#[utoipa::path(
+    get,
+    path = "/api/v1/reservations",
+    tag = "commitments",
+    responses((status = 200, body = Vec<CapacityReservation>)),
+)]
+async fn handle_reservations(
+    Extension(reservations): Extension<ReservationBook>,
+) -> Json<Vec<CapacityReservation>> {
+    Json(reservations.list())
+}
+
+/// Websocket stream of head, commitment deadline and pricing events.
+#[utoipa::path(
+    get,
+    path = "/api/v1/events",
+    tag = "commitments",
+    responses((status = 101, description = "Switched to the websocket protocol")),
+)]
+async fn handle_events(
+    State(handler): State<Arc<CommitmentRequestHandler>>,
+    ws: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let events = handler.events();
+    ws.on_upgrade(move |socket| events.serve(socket))
+}
+
+#[derive(Serialize, ToSchema)]
 pub struct PreconfResponse {
     pub ok: bool,
+    #[schema(value_type = Vec<Object>)]
     pub signed_contraints_list: Vec<SignedConstraints>,
+    /// Receipt of the commitment, signed with the ECDSA receipt key of the gateway.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub receipt: Option<SignedReceipt>,
+    /// Receipt of the inclusion of the transactions, signed with the delegatee key of the
+    /// constraints. Also served by `/api/v1/receipts/{tx_hash}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inclusion_receipt: Option<SignedPreconfReceipt>,
+    /// Verdict of each transaction of a partial request, in the order of the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verdicts: Option<Vec<TxVerdict>>,
+    /// Priority tier granted to the commitment, the one requested and paid for.
+    #[serde(skip_serializing_if = "PriorityTier::is_standard")]
+    pub priority: PriorityTier,
+}
+
+/// Whether a transaction of a partial request was committed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TxVerdict {
+    /// Committed, the receipts of the response cover it.
+    Accepted {
+        #[schema(value_type = String)]
+        tx_hash: B256,
+    },
+    /// Not committed, with a stable `code`.
+    Rejected {
+        #[schema(value_type = String)]
+        tx_hash: B256,
+        code: String,
+        reason: String,
+    },
+}
+
+/// Body of the responses to requests with invalid fields.
+#[derive(Serialize, ToSchema)]
+pub struct FieldErrors {
+    pub errors: Vec<FieldError>,
 }
 
 impl axum::response::IntoResponse for CommitmentRequestError {
@@ -126,6 +775,79 @@ impl axum::response::IntoResponse for CommitmentRequestError {
             CommitmentRequestError::NotAllowedIP(ip) => {
                 (StatusCode::UNAUTHORIZED, ip).into_response()
             }
+            CommitmentRequestError::Unauthorized(err) => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                Json(AuthFailure::from(err)),
+            )
+                .into_response(),
+            CommitmentRequestError::ExecutionClientSyncing(_)
+            | CommitmentRequestError::Standby
+            | CommitmentRequestError::ShuttingDown
+            | CommitmentRequestError::Sequencer(_) => {
+                (StatusCode::SERVICE_UNAVAILABLE, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Quote(QuoteError::Disabled) => {
+                (StatusCode::NOT_FOUND, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Quote(QuoteError::Signing(_)) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Quote(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Forward(_) => {
+                (StatusCode::BAD_GATEWAY, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Confidential(ConfidentialError::Disabled) => {
+                (StatusCode::NOT_FOUND, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Confidential(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Reservation(ReservationError::Disabled) => {
+                (StatusCode::NOT_FOUND, self.to_string()).into_response()
+            }
+            CommitmentRequestError::Reservation(
+                ReservationError::NotAllowed(_) | ReservationError::InvalidSignature,
+            ) => (StatusCode::FORBIDDEN, self.to_string()).into_response(),
+            CommitmentRequestError::Reservation(_) => {
+                (StatusCode::BAD_REQUEST, self.to_string()).into_response()
+            }
+            CommitmentRequestError::ForeignTransaction { .. } => {
+                (StatusCode::FORBIDDEN, self.to_string()).into_response()
+            }
+            CommitmentRequestError::ReplacedInMempool { .. } => {
+                (StatusCode::CONFLICT, self.to_string()).into_response()
+            }
+            CommitmentRequestError::AtomicBatchFailed { index, tx_hash, reason } => {
+                let failed = serde_json::json!({ "index": index, "tx_hash": tx_hash, "reason": reason });
+                (StatusCode::UNPROCESSABLE_ENTITY, Json(failed)).into_response()
+            }
+            CommitmentRequestError::Underpriced(underpriced) => {
+                (StatusCode::BAD_REQUEST, Json(underpriced)).into_response()
+            }
+            CommitmentRequestError::InvalidFields(errors) => {
+                (StatusCode::BAD_REQUEST, Json(FieldErrors { errors })).into_response()
+            }
+            CommitmentRequestError::Sequenced { status, body } => {
+                let status =
+                    StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+                match body {
+                    Value::String(message) => (status, message).into_response(),
+                    body => (status, Json(body)).into_response(),
+                }
+            }
+            CommitmentRequestError::RateLimited(limited) => {
+                let retry_after = limited.retry_in_ms.div_ceil(1000).to_string();
+                (StatusCode::TOO_MANY_REQUESTS, [(header::RETRY_AFTER, retry_after)], Json(limited))
+                    .into_response()
+            }
+            CommitmentRequestError::Unavailable(open) => {
+                let retry_after = open.retry_in_ms.div_ceil(1000).to_string();
+                (StatusCode::SERVICE_UNAVAILABLE, [(header::RETRY_AFTER, retry_after)], Json(open))
+                    .into_response()
+            }
         }
     }
 }
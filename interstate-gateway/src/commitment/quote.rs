@@ -0,0 +1,429 @@
+use std::{collections::HashMap, ops::Range, sync::Arc};
+
+use alloy::{
+    primitives::{keccak256, Address, PrimitiveSignature, B256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::request::{deserialize_sig, serialize_sig, PreconfRequest};
+use crate::{
+    metrics::ApiMetrics,
+    state::{
+        execution::SharedExecutionSnapshot,
+        pricing::{
+            PreconfPricer, PricingError, PricingInput, PricingReport, SharedCommittedSpace,
+            SlotPricing,
+        },
+    },
+    utils::now_ms,
+};
+
+/// Slots after the head reported by default in the pricing of the commitments.
+pub const DEFAULT_PRICING_SLOTS: u64 = 4;
+
+/// Gas of the transaction priced in the pricing reports, a simple transfer.
+const PRICED_GAS: u64 = 21_000;
+
+/// Price quoted to `sender` for committing up to `gas` in `slot`, honored until `expiry_ms`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PriceQuote {
+    #[schema(value_type = String)]
+    pub sender: Address,
+    pub slot: u64,
+    /// Gas honored over all the requests presenting the quote.
+    pub gas: u64,
+    /// Minimum priority fee per gas in wei, including the inclusion profit.
+    pub min_priority_fee: u64,
+    /// Unix timestamp in ms after which the quote is no longer honored.
+    pub expiry_ms: u64,
+}
+
+impl PriceQuote {
+    pub fn digest(&self) -> B256 {
+        let mut data = Vec::with_capacity(52);
+        data.extend_from_slice(self.sender.as_slice());
+        data.extend_from_slice(&self.slot.to_be_bytes());
+        data.extend_from_slice(&self.gas.to_be_bytes());
+        data.extend_from_slice(&self.min_priority_fee.to_be_bytes());
+        data.extend_from_slice(&self.expiry_ms.to_be_bytes());
+
+        keccak256(data)
+    }
+}
+
+/// A [PriceQuote] attested by the quote signing key of the gateway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SignedQuote {
+    pub message: PriceQuote,
+    #[schema(value_type = String)]
+    pub signer: Address,
+    #[serde(deserialize_with = "deserialize_sig", serialize_with = "serialize_sig")]
+    #[schema(value_type = String)]
+    pub signature: PrimitiveSignature,
+}
+
+#[derive(Debug, Error)]
+pub enum QuoteError {
+    #[error("price quotes are not enabled")]
+    Disabled,
+    #[error("quote is not signed by this gateway")]
+    InvalidSignature,
+    #[error("quote expired at {0}")]
+    Expired(u64),
+    #[error("quote was issued to {quoted}, request is sent by {requested}")]
+    SenderMismatch { quoted: Address, requested: Address },
+    #[error("quote is for slot {quoted}, request targets slot {requested}")]
+    SlotMismatch { quoted: u64, requested: u64 },
+    #[error("request uses {requested} gas, quote covers {remaining} more")]
+    GasExceeded { remaining: u64, requested: u64 },
+    #[error("can't quote slot {0}, it already passed")]
+    PastSlot(u64),
+    #[error("failed to sign the quote: {0}")]
+    Signing(String),
+    #[error(transparent)]
+    Pricing(#[from] PricingError),
+}
+
+/// Issues and verifies signed price quotes.
+///
+/// A quote locks the minimum priority fee of a slot for requests presented before it expires,
+/// so that clients get predictable pricing even though the price moves up with every
+/// commitment. It doesn't reserve gas: a request can still be refused when the slot is full.
+///
+/// A quote is issued to a sender, and honored for its gas over all the requests it commits.
+#[derive(Debug, Clone)]
+pub struct Quoter {
+    signer: Option<PrivateKeySigner>,
+    ttl_ms: u64,
+    pricing: PreconfPricer,
+    min_inclusion_profit: u64,
+    committed_space: SharedCommittedSpace,
+    /// Head the slots are priced from.
+    head: SharedExecutionSnapshot,
+    /// Gas used per quote digest, until the quotes expire.
+    used: SharedQuoteUsage,
+}
+
+type SharedQuoteUsage = Arc<Mutex<HashMap<B256, QuoteUsage>>>;
+
+#[derive(Debug, Clone, Copy)]
+struct QuoteUsage {
+    gas: u64,
+    expiry_ms: u64,
+}
+
+/// Gas of a quote reserved by a request, given back unless the request is committed.
+#[derive(Debug)]
+pub struct QuoteReservation {
+    used: SharedQuoteUsage,
+    digest: B256,
+    gas: u64,
+    committed: bool,
+}
+
+impl QuoteReservation {
+    /// Consume the reserved gas of the quote.
+    pub fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for QuoteReservation {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        if let Some(usage) = self.used.lock().get_mut(&self.digest) {
+            usage.gas = usage.gas.saturating_sub(self.gas);
+        }
+    }
+}
+
+impl Quoter {
+    pub fn new(
+        signer: Option<PrivateKeySigner>,
+        ttl_ms: u64,
+        pricing: PreconfPricer,
+        min_inclusion_profit: u64,
+        committed_space: SharedCommittedSpace,
+        head: SharedExecutionSnapshot,
+    ) -> Self {
+        Self {
+            signer,
+            ttl_ms,
+            pricing,
+            min_inclusion_profit,
+            committed_space,
+            head,
+            used: Default::default(),
+        }
+    }
+
+    /// Quote to `sender` the price of committing `gas` in `slot` at the current committed gas.
+    pub fn quote(&self, sender: Address, slot: u64, gas: u64) -> Result<SignedQuote, QuoteError> {
+        let signer = self.signer.as_ref().ok_or(QuoteError::Disabled)?;
+
+        let min_priority_fee = self.min_priority_fee(slot, gas)?;
+
+        let message =
+            PriceQuote { sender, slot, gas, min_priority_fee, expiry_ms: now_ms() + self.ttl_ms };
+        let signature =
+            signer.sign_hash_sync(&message.digest()).map_err(|err| QuoteError::Signing(err.to_string()))?;
+
+        ApiMetrics::increment_price_quotes_count("issued");
+        Ok(SignedQuote { message, signer: signer.address(), signature })
+    }
+
+    /// Minimum priority fee of committing `gas` in `slot` at the current committed space.
+    pub fn min_priority_fee(&self, slot: u64, gas: u64) -> Result<u64, PricingError> {
+        let input = PricingInput {
+            gas,
+            blobs: 0,
+            committed: self.committed_space.read().get(&slot).copied().unwrap_or_default(),
+            slots_ahead: slot.saturating_sub(self.head.load().slot),
+        };
+        Ok(self.pricing.min_inclusion_tip(&input)? + self.min_inclusion_profit)
+    }
+
+    /// Pricing of the `slots`, by default the [DEFAULT_PRICING_SLOTS] after the head.
+    pub fn report(&self, slots: Option<Range<u64>>) -> PricingReport {
+        let head_slot = self.head.load().slot;
+        let slots = slots.unwrap_or(head_slot + 1..head_slot + 1 + DEFAULT_PRICING_SLOTS);
+        let committed_space = self.committed_space.read();
+
+        let slots = slots
+            .map(|slot| {
+                let committed = committed_space.get(&slot).copied().unwrap_or_default();
+                let slots_ahead = slot.saturating_sub(head_slot);
+                let input = PricingInput { gas: PRICED_GAS, blobs: 0, committed, slots_ahead };
+                SlotPricing {
+                    slot,
+                    committed,
+                    remaining_gas: self.pricing.block_gas_limit().saturating_sub(committed.gas),
+                    remaining_blobs: self.pricing.max_blobs().saturating_sub(committed.blobs),
+                    min_priority_fee: self
+                        .pricing
+                        .min_inclusion_tip(&input)
+                        .ok()
+                        .map(|tip| tip + self.min_inclusion_profit),
+                    blob_fee: self.pricing.blob_fee(committed.blobs, 1).ok(),
+                    proximity_multiplier: self.pricing.proximity_multiplier(slots_ahead),
+                }
+            })
+            .collect();
+
+        PricingReport { head_slot, min_inclusion_profit: self.min_inclusion_profit, slots }
+    }
+
+    /// Verify that `quote` was issued by us to the sender of `request`, is still valid and
+    /// covers it, reserving the gas of `request` on the quote.
+    pub fn verify(
+        &self,
+        quote: &SignedQuote,
+        request: &PreconfRequest,
+    ) -> Result<QuoteReservation, QuoteError> {
+        let result = self.verify_at(quote, request, now_ms());
+        ApiMetrics::increment_price_quotes_count(if result.is_ok() {
+            "honored"
+        } else {
+            "rejected"
+        });
+        result
+    }
+
+    fn verify_at(
+        &self,
+        quote: &SignedQuote,
+        request: &PreconfRequest,
+        now_ms: u64,
+    ) -> Result<QuoteReservation, QuoteError> {
+        let signer = self.signer.as_ref().ok_or(QuoteError::Disabled)?;
+
+        let recovered = quote
+            .signature
+            .recover_address_from_prehash(&quote.message.digest())
+            .map_err(|_| QuoteError::InvalidSignature)?;
+        if recovered != signer.address() || quote.signer != signer.address() {
+            return Err(QuoteError::InvalidSignature);
+        }
+
+        if now_ms > quote.message.expiry_ms {
+            return Err(QuoteError::Expired(quote.message.expiry_ms));
+        }
+
+        if quote.message.sender != request.sender {
+            return Err(QuoteError::SenderMismatch {
+                quoted: quote.message.sender,
+                requested: request.sender,
+            });
+        }
+
+        if quote.message.slot != request.slot {
+            return Err(QuoteError::SlotMismatch {
+                quoted: quote.message.slot,
+                requested: request.slot,
+            });
+        }
+
+        // Reserved at once, so that concurrent requests don't overspend the quote
+        let digest = quote.message.digest();
+        let gas = request.total_gas_limit();
+        let mut used = self.used.lock();
+        used.retain(|_, usage| usage.expiry_ms >= now_ms);
+        let usage =
+            used.entry(digest).or_insert(QuoteUsage { gas: 0, expiry_ms: quote.message.expiry_ms });
+        let remaining = quote.message.gas.saturating_sub(usage.gas);
+        if gas > remaining {
+            return Err(QuoteError::GasExceeded { remaining, requested: gas });
+        }
+        usage.gas += gas;
+
+        Ok(QuoteReservation { used: self.used.clone(), digest, gas, committed: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::{EthereumWallet, TransactionBuilder},
+        primitives::{Address, PrimitiveSignature, U256},
+        signers::local::PrivateKeySigner,
+    };
+
+    use std::sync::Arc;
+
+    use super::{QuoteError, Quoter, DEFAULT_PRICING_SLOTS};
+    use crate::{
+        commitment::request::PreconfRequest,
+        constraints::Constraint,
+        state::{
+            execution::ExecutionSnapshot,
+            pricing::{CommittedSpace, PreconfPricer},
+        },
+        test_utils::default_test_transaction,
+    };
+
+    fn request(slot: u64) -> PreconfRequest {
+        PreconfRequest {
+            slot,
+            txs: vec![],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: Default::default(),
+            chain_id: 1,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        }
+    }
+
+    #[test]
+    fn test_signed_quote_is_honored_until_expiry() {
+        let quoter = Quoter::new(
+            Some(PrivateKeySigner::random()),
+            12_000,
+            PreconfPricer::default(),
+            0,
+            Default::default(),
+            Default::default(),
+        );
+
+        let quote = quoter.quote(Address::ZERO, 10, 21_000).unwrap();
+        assert!(quoter.verify_at(&quote, &request(10), quote.message.expiry_ms).is_ok());
+
+        assert!(matches!(
+            quoter.verify_at(&quote, &request(10), quote.message.expiry_ms + 1),
+            Err(QuoteError::Expired(_))
+        ));
+        assert!(matches!(
+            quoter.verify_at(&quote, &request(11), quote.message.expiry_ms),
+            Err(QuoteError::SlotMismatch { .. })
+        ));
+        let other_sender = PreconfRequest { sender: Address::repeat_byte(1), ..request(10) };
+        assert!(matches!(
+            quoter.verify_at(&quote, &other_sender, quote.message.expiry_ms),
+            Err(QuoteError::SenderMismatch { .. })
+        ));
+
+        // A tampered price doesn't match the attestation
+        let mut tampered = quote.clone();
+        tampered.message.min_priority_fee = 0;
+        assert!(matches!(
+            quoter.verify_at(&tampered, &request(10), quote.message.expiry_ms),
+            Err(QuoteError::InvalidSignature)
+        ));
+
+        // Quotes of another gateway aren't honored
+        let other = Quoter::new(
+            Some(PrivateKeySigner::random()),
+            12_000,
+            PreconfPricer::default(),
+            0,
+            Default::default(),
+            Default::default(),
+        );
+        assert!(matches!(
+            other.verify_at(&quote, &request(10), quote.message.expiry_ms),
+            Err(QuoteError::InvalidSignature)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_quote_gas_is_consumed() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let raw = default_test_transaction(signer.address(), Some(0)).build(&wallet).await?;
+        let transfer = PreconfRequest {
+            txs: vec![Constraint::decode_enveloped(raw.encoded_2718())?],
+            sender: signer.address(),
+            ..request(10)
+        };
+
+        let quoter = Quoter::new(
+            Some(PrivateKeySigner::random()),
+            12_000,
+            PreconfPricer::default(),
+            0,
+            Default::default(),
+            Default::default(),
+        );
+        let quote = quoter.quote(signer.address(), 10, 21_000)?;
+        let now_ms = quote.message.expiry_ms;
+
+        // The gas of a request which isn't committed is given back
+        drop(quoter.verify_at(&quote, &transfer, now_ms)?);
+        quoter.verify_at(&quote, &transfer, now_ms)?.commit();
+        assert!(matches!(
+            quoter.verify_at(&quote, &transfer, now_ms),
+            Err(QuoteError::GasExceeded { remaining: 0, requested: 21_000 })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pricing_report() {
+        let quoter = Quoter::new(None, 0, PreconfPricer::default(), 7, Default::default(), Default::default());
+        quoter.head.store(Arc::new(ExecutionSnapshot { slot: 10, ..Default::default() }));
+        quoter.committed_space.write().insert(12, CommittedSpace { gas: 29_990_000, blobs: 6 });
+
+        let report = quoter.report(None);
+        assert_eq!(report.head_slot, 10);
+        assert_eq!(report.slots.len() as u64, DEFAULT_PRICING_SLOTS);
+        let (next, full) = (&report.slots[0], &report.slots[1]);
+        assert_eq!(next.slot, 11);
+        assert!(next.proximity_multiplier > full.proximity_multiplier);
+        assert!(next.min_priority_fee.unwrap() > 7);
+        // No room left for a transfer or a blob
+        assert_eq!((full.remaining_blobs, full.min_priority_fee, full.blob_fee), (0, None, None));
+
+        let report = quoter.report(Some(20..22));
+        assert_eq!(report.slots.iter().map(|s| s.slot).collect::<Vec<_>>(), vec![20, 21]);
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    net::IpAddr,
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::primitives::Address;
+use axum::http::HeaderMap;
+use parking_lot::Mutex;
+use serde::Serialize;
+use tokio::time::Instant;
+use utoipa::ToSchema;
+
+use crate::metrics::ApiMetrics;
+
+/// Number of clients tracked by a limiter past which the ones with a full bucket are forgotten.
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+/// Header the proxies record the IP of the client in, the closest proxy appending it last.
+pub const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Sustained rate and burst of the commitment requests of a single client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestRateLimit {
+    pub per_sec: u32,
+    pub burst: u32,
+}
+
+impl RequestRateLimit {
+    /// A limit of `per_sec` requests per second, with bursts of twice that by default.
+    pub fn new(per_sec: u32, burst: Option<u32>) -> Self {
+        Self { per_sec, burst: burst.unwrap_or(per_sec.saturating_mul(2)) }
+    }
+}
+
+/// What the requests are limited by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// Address recovered from the signature of the request.
+    Sender,
+    /// Address of the client the request was received from.
+    Ip,
+}
+
+impl RateLimitKey {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sender => "sender",
+            Self::Ip => "ip",
+        }
+    }
+}
+
+/// Body of the responses to rate limited requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RateLimited {
+    pub key: RateLimitKey,
+    pub retry_in_ms: u64,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token buckets of the clients, refusing the requests above their rate rather than delaying
+/// them. Clones share the buckets.
+#[derive(Debug, Clone)]
+pub struct KeyedRateLimiter<K> {
+    key: RateLimitKey,
+    rate_per_sec: f64,
+    burst: f64,
+    buckets: Arc<Mutex<HashMap<K, Bucket>>>,
+}
+
+impl<K: Hash + Eq> KeyedRateLimiter<K> {
+    pub fn new(key: RateLimitKey, limit: RequestRateLimit) -> Self {
+        Self {
+            key,
+            rate_per_sec: limit.per_sec.max(1) as f64,
+            burst: limit.burst.max(1) as f64,
+            buckets: Default::default(),
+        }
+    }
+
+    /// Take a token from the bucket of `client`, or tell when the next one is available.
+    pub fn check(&self, client: K) -> Result<(), RateLimited> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock();
+        if buckets.len() >= MAX_TRACKED_CLIENTS {
+            let (rate_per_sec, burst) = (self.rate_per_sec, self.burst);
+            buckets.retain(|_, bucket| {
+                bucket.tokens + now.duration_since(bucket.last_refill).as_secs_f64() * rate_per_sec
+                    < burst
+            });
+        }
+
+        let bucket =
+            buckets.entry(client).or_insert(Bucket { tokens: self.burst, last_refill: now });
+        let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate_per_sec;
+        bucket.tokens = (bucket.tokens + refill).min(self.burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        ApiMetrics::increment_rate_limited_requests_count(self.key.as_str());
+        let retry_in = Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate_per_sec);
+        Err(RateLimited { key: self.key, retry_in_ms: retry_in.as_millis() as u64 + 1 })
+    }
+}
+
+/// Rate limits of the commitment requests, by sender and by client IP. Unlimited when not set.
+#[derive(Debug, Clone, Default)]
+pub struct RequestRateLimiters {
+    senders: Option<KeyedRateLimiter<Address>>,
+    ips: Option<KeyedRateLimiter<IpAddr>>,
+}
+
+impl RequestRateLimiters {
+    pub fn new(sender: Option<RequestRateLimit>, ip: Option<RequestRateLimit>) -> Self {
+        Self {
+            senders: sender.map(|limit| KeyedRateLimiter::new(RateLimitKey::Sender, limit)),
+            ips: ip.map(|limit| KeyedRateLimiter::new(RateLimitKey::Ip, limit)),
+        }
+    }
+
+    pub fn check_sender(&self, sender: Address) -> Result<(), RateLimited> {
+        self.senders.as_ref().map_or(Ok(()), |limiter| limiter.check(sender))
+    }
+
+    pub fn check_ip(&self, ip: IpAddr) -> Result<(), RateLimited> {
+        self.ips.as_ref().map_or(Ok(()), |limiter| limiter.check(ip))
+    }
+}
+
+/// Peers trusted to give the IP of the clients whose requests they forward, such as replicas.
+/// The IP of the other clients is the one they connect from, whatever they claim.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies(Arc<HashSet<IpAddr>>);
+
+impl TrustedProxies {
+    pub fn new(proxies: HashSet<IpAddr>) -> Self {
+        Self(Arc::new(proxies))
+    }
+
+    /// IP of the client of a request received from `peer`: the one appended to the
+    /// `X-Forwarded-For` header by a trusted proxy, `peer` itself otherwise.
+    pub fn client_ip(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        if !self.0.contains(&peer) {
+            return peer;
+        }
+        headers
+            .get_all(FORWARDED_FOR_HEADER)
+            .iter()
+            .last()
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit(',').next())
+            .and_then(|ip| ip.trim().parse().ok())
+            .unwrap_or(peer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, time::Duration};
+
+    use alloy::primitives::Address;
+    use axum::http::HeaderMap;
+
+    use super::{
+        RateLimitKey, RequestRateLimit, RequestRateLimiters, TrustedProxies, FORWARDED_FOR_HEADER,
+    };
+
+    #[tokio::test]
+    async fn test_request_rate_limiters() {
+        let limiters = RequestRateLimiters::new(Some(RequestRateLimit::new(20, Some(2))), None);
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        assert!(limiters.check_sender(alice).is_ok());
+        assert!(limiters.check_sender(alice).is_ok());
+        let limited = limiters.check_sender(alice).unwrap_err();
+        assert_eq!(limited.key, RateLimitKey::Sender);
+        assert!(limited.retry_in_ms > 0 && limited.retry_in_ms <= 51);
+
+        // Each sender has its own bucket, and IPs are not limited
+        assert!(limiters.check_sender(bob).is_ok());
+        let ip = IpAddr::from([127, 0, 0, 1]);
+        assert!((0..10).all(|_| limiters.check_ip(ip).is_ok()));
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        assert!(limiters.check_sender(alice).is_ok());
+        assert!(limiters.check_sender(alice).is_err());
+    }
+
+    #[test]
+    fn test_client_ip_behind_trusted_proxies() {
+        let (replica, client) = (IpAddr::from([10, 0, 0, 2]), IpAddr::from([1, 2, 3, 4]));
+        let proxies = TrustedProxies::new([replica].into());
+        let mut headers = HeaderMap::new();
+        headers.insert(FORWARDED_FOR_HEADER, "6.6.6.6, 1.2.3.4".parse().unwrap());
+
+        // Only the entry appended by the trusted proxy is taken, not the ones claimed before
+        assert_eq!(proxies.client_ip(replica, &headers), client);
+        let other = IpAddr::from([5, 6, 7, 8]);
+        assert_eq!(proxies.client_ip(other, &headers), other);
+        assert_eq!(proxies.client_ip(replica, &HeaderMap::new()), replica);
+    }
+}
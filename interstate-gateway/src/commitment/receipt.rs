@@ -0,0 +1,386 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    str::FromStr,
+    sync::Arc,
+};
+
+use alloy::{
+    primitives::{keccak256, Address, FixedBytes, PrimitiveSignature, B256},
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use utoipa::ToSchema;
+
+#[cfg(feature = "collector-client")]
+use super::gossip::ReceiptGossip;
+use super::request::{deserialize_sig, serialize_sig};
+use crate::{
+    constraints::signature::compute_signing_root,
+    delegation::{cb_signer::CBSigner, signing::verify_root},
+    metrics::ApiMetrics,
+    utils::now_ms,
+};
+
+/// Slots for which the inclusion receipts are kept after the latest committed slot.
+pub const RECEIPT_RETENTION_SLOTS: u64 = 64;
+
+/// Acknowledges that the gateway committed to the transactions of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CommitmentReceipt {
+    pub slot: u64,
+    /// Digest of the committed request.
+    #[schema(value_type = String)]
+    pub request_digest: B256,
+    /// Unix timestamp in ms at which the commitment was made.
+    pub issued_at_ms: u64,
+}
+
+impl CommitmentReceipt {
+    pub fn new(slot: u64, request_digest: B256) -> Self {
+        Self { slot, request_digest, issued_at_ms: now_ms() }
+    }
+
+    pub fn digest(&self) -> B256 {
+        let mut data = Vec::with_capacity(48);
+        data.extend_from_slice(&self.slot.to_be_bytes());
+        data.extend_from_slice(self.request_digest.as_slice());
+        data.extend_from_slice(&self.issued_at_ms.to_be_bytes());
+
+        keccak256(data)
+    }
+}
+
+/// A [CommitmentReceipt] signed by the receipt key of the gateway, over its signing root in
+/// the commit-boost domain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SignedReceipt {
+    pub message: CommitmentReceipt,
+    #[schema(value_type = String)]
+    pub signer: Address,
+    #[serde(deserialize_with = "deserialize_sig", serialize_with = "serialize_sig")]
+    #[schema(value_type = String)]
+    pub signature: PrimitiveSignature,
+}
+
+impl SignedReceipt {
+    /// Whether the receipt is signed by its signer in `domain`.
+    pub fn verify(&self, domain: [u8; 32]) -> bool {
+        let root = compute_signing_root(self.message.digest().0, domain);
+        self.signature
+            .recover_address_from_prehash(&root.into())
+            .is_ok_and(|recovered| recovered == self.signer)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ReceiptError {
+    #[error("failed to sign the receipt: {0}")]
+    Signing(String),
+    #[error("failed to set up the proxy ECDSA key: {0}")]
+    Proxy(String),
+}
+
+#[derive(Clone)]
+enum ReceiptKey {
+    Local(PrivateKeySigner),
+    /// Proxy ECDSA key held by the commit-boost signer, delegated by a consensus key.
+    Proxy { signer: CBSigner, proxy: Address },
+}
+
+/// Signs commitment receipts with an ECDSA key, so that the BLS validator keys are only used
+/// for consensus signatures.
+#[derive(Clone)]
+pub struct ReceiptSigner {
+    key: ReceiptKey,
+    domain: [u8; 32],
+}
+
+impl ReceiptSigner {
+    pub fn local(signer: PrivateKeySigner, domain: [u8; 32]) -> Self {
+        Self { key: ReceiptKey::Local(signer), domain }
+    }
+
+    /// Sign with a proxy ECDSA key of the consensus key `delegator`, reusing the first one it
+    /// already delegated to, or having the signer generate one.
+    pub async fn commit_boost_proxy(
+        signer: CBSigner,
+        delegator: &str,
+        domain: [u8; 32],
+    ) -> Result<Self, ReceiptError> {
+        let existing = signer
+            .get_proxy_ecdsa_keys(delegator)
+            .await
+            .map_err(|err| ReceiptError::Proxy(err.to_string()))?;
+
+        let proxy = match existing.first() {
+            Some(proxy) => *proxy,
+            None => signer
+                .generate_proxy_ecdsa_key(delegator)
+                .await
+                .map_err(|err| ReceiptError::Proxy(err.to_string()))?,
+        };
+        tracing::info!(?proxy, delegator, "Signing commitment receipts with a proxy ECDSA key");
+
+        Ok(Self { key: ReceiptKey::Proxy { signer, proxy }, domain })
+    }
+
+    pub fn address(&self) -> Address {
+        match &self.key {
+            ReceiptKey::Local(signer) => signer.address(),
+            ReceiptKey::Proxy { proxy, .. } => *proxy,
+        }
+    }
+
+    pub async fn sign(&self, message: CommitmentReceipt) -> Result<SignedReceipt, ReceiptError> {
+        let root = message.digest();
+        let signature = match &self.key {
+            ReceiptKey::Local(signer) => signer
+                .sign_hash_sync(&compute_signing_root(root.0, self.domain).into())
+                .map_err(|err| ReceiptError::Signing(err.to_string())),
+            ReceiptKey::Proxy { signer, proxy } => signer
+                .request_proxy_ecdsa_signature(*proxy, &root.to_string())
+                .await
+                .map_err(|err| ReceiptError::Signing(err.to_string())),
+        };
+
+        ApiMetrics::increment_commitment_receipts_count(if signature.is_ok() {
+            "signed"
+        } else {
+            "failed"
+        });
+        Ok(SignedReceipt { message, signer: self.address(), signature: signature? })
+    }
+}
+
+impl fmt::Debug for ReceiptSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.key {
+            ReceiptKey::Local(_) => "local",
+            ReceiptKey::Proxy { .. } => "commit_boost_proxy",
+        };
+        f.debug_struct("ReceiptSigner")
+            .field("kind", &kind)
+            .field("address", &self.address())
+            .finish()
+    }
+}
+
+/// Commits the gateway to include transactions in the block of a slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct PreconfReceipt {
+    pub slot: u64,
+    #[schema(value_type = Vec<String>)]
+    pub tx_hashes: Vec<B256>,
+    /// Proposer of the slot.
+    #[schema(value_type = String)]
+    pub validator_pubkey: BlsPublicKey,
+    /// Unix timestamp in ms at which the slot ends, and the commitment with it.
+    pub expiry_ms: u64,
+    /// Contracts deployed by the committed transactions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deployments: Vec<ContractDeployment>,
+}
+
+/// Contract deployed by a committed transaction, at the address derived from its sender and
+/// nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ContractDeployment {
+    #[schema(value_type = String)]
+    pub tx_hash: B256,
+    #[schema(value_type = String)]
+    pub address: Address,
+}
+
+impl PreconfReceipt {
+    pub fn digest(&self) -> [u8; 32] {
+        let mut data = Vec::with_capacity(64 + 32 * self.tx_hashes.len());
+        data.extend_from_slice(&self.slot.to_be_bytes());
+        data.extend_from_slice(self.validator_pubkey.as_ref());
+        data.extend_from_slice(&self.expiry_ms.to_be_bytes());
+        for hash in &self.tx_hashes {
+            data.extend_from_slice(hash.as_slice());
+        }
+        for deployment in &self.deployments {
+            data.extend_from_slice(deployment.tx_hash.as_slice());
+            data.extend_from_slice(deployment.address.as_slice());
+        }
+
+        keccak256(data).0
+    }
+}
+
+/// A [PreconfReceipt] signed in the commit-boost domain by the delegatee key that signed the
+/// constraints of its transactions, verifiable against the delegation of the proposer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SignedPreconfReceipt {
+    pub message: PreconfReceipt,
+    #[schema(value_type = String)]
+    pub signer: BlsPublicKey,
+    #[schema(value_type = String)]
+    pub signature: FixedBytes<96>,
+}
+
+impl SignedPreconfReceipt {
+    /// Whether the receipt is signed by its signer in `domain`.
+    pub fn verify(&self, domain: [u8; 32]) -> bool {
+        blst::min_pk::Signature::from_bytes(self.signature.as_slice()).is_ok_and(|signature| {
+            verify_root(self.signer.clone(), self.message.digest(), &signature, domain).is_ok()
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Receipts {
+    by_tx: HashMap<B256, Arc<SignedPreconfReceipt>>,
+    /// Hashes of the transactions committed in each slot, to prune them.
+    by_slot: BTreeMap<u64, Vec<B256>>,
+}
+
+/// Default number of receipts waiting to be pushed to the collector.
+pub const DEFAULT_RECEIPT_GOSSIP_QUEUE: usize = 1_024;
+
+/// What the collector learns of the committed receipts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiptGossipMode {
+    /// The whole receipts, with the hashes of the committed transactions.
+    Full,
+    /// The digests of the receipts only, hiding the committed transactions.
+    HashOnly,
+}
+
+impl FromStr for ReceiptGossipMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "full" => Ok(Self::Full),
+            "hash_only" => Ok(Self::HashOnly),
+            other => Err(format!("unknown receipt gossip mode `{other}`, expected full or hash_only")),
+        }
+    }
+}
+
+impl fmt::Display for ReceiptGossipMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Full => "full",
+            Self::HashOnly => "hash_only",
+        })
+    }
+}
+
+/// Inclusion receipts of the recent commitments, looked up by the hash of their transactions.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiptStore {
+    receipts: Arc<RwLock<Receipts>>,
+    #[cfg(feature = "collector-client")]
+    gossip: Option<ReceiptGossip>,
+}
+
+impl ReceiptStore {
+    /// Gossip the receipts kept to the collector.
+    #[cfg(feature = "collector-client")]
+    pub fn with_gossip(mut self, gossip: ReceiptGossip) -> Self {
+        self.gossip = Some(gossip);
+        self
+    }
+
+    /// Keep `receipt`, dropping the receipts of the slots [RECEIPT_RETENTION_SLOTS] before it.
+    pub fn insert(&self, receipt: SignedPreconfReceipt) {
+        #[cfg(feature = "collector-client")]
+        if let Some(gossip) = &self.gossip {
+            gossip.push(receipt.clone());
+        }
+
+        let slot = receipt.message.slot;
+        let receipt = Arc::new(receipt);
+        let mut receipts = self.receipts.write();
+        for hash in &receipt.message.tx_hashes {
+            receipts.by_tx.insert(*hash, receipt.clone());
+        }
+        receipts.by_slot.entry(slot).or_default().extend(receipt.message.tx_hashes.iter().copied());
+
+        let latest = *receipts.by_slot.keys().next_back().expect("receipt inserted");
+        let kept = receipts.by_slot.split_off(&latest.saturating_sub(RECEIPT_RETENTION_SLOTS));
+        let pruned = std::mem::replace(&mut receipts.by_slot, kept);
+        for hash in pruned.into_values().flatten() {
+            receipts.by_tx.remove(&hash);
+        }
+    }
+
+    pub fn get(&self, tx_hash: &B256) -> Option<Arc<SignedPreconfReceipt>> {
+        self.receipts.read().by_tx.get(tx_hash).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{FixedBytes, B256},
+        signers::local::PrivateKeySigner,
+    };
+    use ethereum_consensus::{crypto::PublicKey as BlsPublicKey, deneb::compute_signing_root};
+
+    use super::{
+        CommitmentReceipt, PreconfReceipt, ReceiptSigner, ReceiptStore, SignedPreconfReceipt,
+        RECEIPT_RETENTION_SLOTS,
+    };
+    use crate::delegation::signing::BLS_DST_PREFIX;
+
+    #[tokio::test]
+    async fn test_signed_receipt_verifies_in_its_domain() {
+        let signer = ReceiptSigner::local(PrivateKeySigner::random(), [1; 32]);
+        let receipt = signer.sign(CommitmentReceipt::new(10, B256::repeat_byte(2))).await.unwrap();
+
+        assert_eq!(receipt.signer, signer.address());
+        assert!(receipt.verify([1; 32]));
+        assert!(!receipt.verify([2; 32]));
+
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(serde_json::from_value::<super::SignedReceipt>(json).unwrap(), receipt);
+    }
+
+    #[test]
+    fn test_preconf_receipts_by_tx_hash() {
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let pubkey = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        let sign = |slot: u64, hash: u8| {
+            let message = PreconfReceipt {
+                slot,
+                tx_hashes: vec![B256::repeat_byte(hash)],
+                validator_pubkey: pubkey.clone(),
+                expiry_ms: slot * 12_000,
+                deployments: vec![],
+            };
+            let root = compute_signing_root(&message.digest(), [1; 32]).unwrap();
+            let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+            SignedPreconfReceipt {
+                message,
+                signer: pubkey.clone(),
+                signature: FixedBytes::from(signature),
+            }
+        };
+
+        let receipt = sign(10, 1);
+        assert!(receipt.verify([1; 32]));
+        assert!(!receipt.verify([2; 32]));
+        let json = serde_json::to_value(&receipt).unwrap();
+        assert_eq!(serde_json::from_value::<SignedPreconfReceipt>(json).unwrap(), receipt);
+
+        let store = ReceiptStore::default();
+        store.insert(receipt.clone());
+        assert_eq!(*store.get(&B256::repeat_byte(1)).unwrap(), receipt);
+        assert!(store.get(&B256::repeat_byte(2)).is_none());
+
+        // The receipts of the slots too far behind the latest one are dropped
+        store.insert(sign(10 + RECEIPT_RETENTION_SLOTS, 2));
+        assert!(store.get(&B256::repeat_byte(1)).is_some());
+        store.insert(sign(11 + RECEIPT_RETENTION_SLOTS, 3));
+        assert!(store.get(&B256::repeat_byte(1)).is_none());
+        assert!(store.get(&B256::repeat_byte(2)).is_some());
+    }
+}
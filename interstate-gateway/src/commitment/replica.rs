@@ -0,0 +1,230 @@
+use std::{fmt, net::SocketAddr, str::FromStr, sync::Arc, time::Duration};
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header, StatusCode},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use parking_lot::RwLock;
+use reqwest::Url;
+use serde::Serialize;
+
+use super::{
+    auth::API_KEY_HEADER, events::ApiEvent, rate_limit::FORWARDED_FOR_HEADER, track_metrics,
+};
+use crate::{
+    config::Config,
+    handover::bind_listener,
+    state::{
+        revenue::{EpochRevenueReport, RevenueTracker},
+        store::{SharedStore, StoreSnapshot},
+    },
+    utils::{score_cache::ScoreCacheStats, url::join_path},
+};
+
+/// Interval at which a replica reloads the shared store.
+const STORE_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Max size of a request body forwarded to the primary.
+const MAX_FORWARDED_BODY_BYTES: usize = 4 * 1024 * 1024;
+
+/// Role of a sidecar instance.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InstanceRole {
+    /// Signs commitments and submits constraints.
+    #[default]
+    Primary,
+    /// Serves the query endpoints from the shared store, and forwards the others to the
+    /// primary. Never signs.
+    Replica,
+}
+
+impl FromStr for InstanceRole {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "primary" => Ok(Self::Primary),
+            "replica" => Ok(Self::Replica),
+            other => Err(format!("unknown role `{other}`, expected primary or replica")),
+        }
+    }
+}
+
+impl fmt::Display for InstanceRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Primary => "primary",
+            Self::Replica => "replica",
+        })
+    }
+}
+
+impl Serialize for InstanceRole {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ReplicaState {
+    snapshot: Arc<RwLock<Option<StoreSnapshot>>>,
+    primary_url: Url,
+    client: reqwest::Client,
+    slots_per_epoch: u64,
+}
+
+impl ReplicaState {
+    fn snapshot(&self) -> Result<StoreSnapshot, ReplicaError> {
+        self.snapshot.read().clone().ok_or(ReplicaError::StoreUnavailable)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum ReplicaError {
+    #[error("shared store not loaded yet")]
+    StoreUnavailable,
+    #[error("failed to forward the request to the primary: {0}")]
+    Forward(String),
+}
+
+impl IntoResponse for ReplicaError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            Self::StoreUnavailable => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Forward(_) => StatusCode::BAD_GATEWAY,
+        };
+        (status, self.to_string()).into_response()
+    }
+}
+
+/// Serve the commitments API as a read-only replica of the primary at `primary_url`.
+pub async fn run_replica_rpc_server(config: &Config, store: SharedStore, primary_url: Url) {
+    let state = ReplicaState {
+        snapshot: store.spawn_reloader(STORE_RELOAD_INTERVAL),
+        primary_url,
+        client: reqwest::Client::new(),
+        slots_per_epoch: config.chain.slots_per_epoch,
+    };
+
+    // Served by the primary from the state it doesn't share in the store
+    let app = Router::new()
+        .route("/api/v1/preconfirmation", post(forward_to_primary))
+        .route("/api/v1/pricing", get(forward_to_primary))
+        .route("/api/v1/pricing/quote", get(forward_to_primary))
+        .route("/api/v1/capacity", get(forward_to_primary))
+        .route("/api/v1/reservations", get(forward_to_primary).post(forward_to_primary))
+        .route("/api/v1/receipts/:tx_hash", get(forward_to_primary))
+        .route("/api/v1/slots/:slot/audit", get(forward_to_primary))
+        .route("/api/v1/slots/:slot/constraints", get(forward_to_primary))
+        .route("/api/v1/slots/:slot/summary", get(forward_to_primary))
+        .route("/api/v1/delegations", get(forward_to_primary))
+        .route("/api/v1/delegations/:validator_pubkey", get(forward_to_primary))
+        .route("/api/v1/status-feed", get(forward_to_primary))
+        .route("/api/v1/status", get(handle_status))
+        .route("/api/v1/debug/account_states_cache", get(handle_account_states_cache))
+        .route("/api/v1/stats/revenue", get(handle_revenue))
+        .route("/api/v1/stats/revenue.csv", get(handle_revenue_csv))
+        .route_layer(middleware::from_fn(track_metrics))
+        .with_state(state);
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.commitment_port));
+    let listener = bind_listener(addr, config.instance_lease_path.is_some()).unwrap();
+
+    tokio::spawn(async {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .await
+            .unwrap();
+    });
+    tracing::info!(%addr, store = %store.path().display(), "replica RPC server is listening");
+}
+
+#[derive(Serialize)]
+struct ReplicaStatus {
+    role: InstanceRole,
+    current_slot: u64,
+    /// Age of the data served, in ms.
+    store_age_ms: u64,
+    pricing: Option<ApiEvent>,
+}
+
+async fn handle_status(
+    State(state): State<ReplicaState>,
+) -> Result<Json<ReplicaStatus>, ReplicaError> {
+    let snapshot = state.snapshot()?;
+    Ok(Json(ReplicaStatus {
+        role: InstanceRole::Replica,
+        current_slot: snapshot.current_slot,
+        store_age_ms: snapshot.age_ms(),
+        pricing: snapshot.pricing,
+    }))
+}
+
+async fn handle_account_states_cache(
+    State(state): State<ReplicaState>,
+) -> Result<Json<ScoreCacheStats>, ReplicaError> {
+    Ok(Json(state.snapshot()?.account_states))
+}
+
+async fn handle_revenue(
+    State(state): State<ReplicaState>,
+) -> Result<Json<Vec<EpochRevenueReport>>, ReplicaError> {
+    let revenue =
+        RevenueTracker::from_proposals(state.snapshot()?.proposals, state.slots_per_epoch);
+    Ok(Json(revenue.epoch_reports()))
+}
+
+async fn handle_revenue_csv(State(state): State<ReplicaState>) -> Result<Response, ReplicaError> {
+    let revenue =
+        RevenueTracker::from_proposals(state.snapshot()?.proposals, state.slots_per_epoch);
+    let csv = revenue.to_csv();
+    Ok(([(header::CONTENT_TYPE, "text/csv")], csv).into_response())
+}
+
+/// Forward a request needing the signing state of the primary as is, and relay its response.
+///
+/// The credentials of the client are forwarded along, and its IP appended to the
+/// `X-Forwarded-For` header for the primary trusting the replica to limit it.
+async fn forward_to_primary(
+    State(state): State<ReplicaState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+) -> Result<Response, ReplicaError> {
+    let (parts, body) = request.into_parts();
+
+    let path = parts.uri.path_and_query().map(|p| p.as_str()).unwrap_or(parts.uri.path());
+    let url = join_path(&state.primary_url, path)
+        .map_err(|err| ReplicaError::Forward(err.to_string()))?;
+    let body = to_bytes(body, MAX_FORWARDED_BODY_BYTES)
+        .await
+        .map_err(|err| ReplicaError::Forward(err.to_string()))?;
+
+    let mut forwarded = state.client.request(parts.method, url);
+    for name in [header::CONTENT_TYPE.as_str(), header::AUTHORIZATION.as_str(), API_KEY_HEADER] {
+        if let Some(value) = parts.headers.get(name) {
+            forwarded = forwarded.header(name, value);
+        }
+    }
+    let forwarded_for = match parts.headers.get(FORWARDED_FOR_HEADER).map(|v| v.to_str()) {
+        Some(Ok(proxies)) => format!("{proxies}, {}", peer.ip()),
+        _ => peer.ip().to_string(),
+    };
+    forwarded = forwarded.header(FORWARDED_FOR_HEADER, forwarded_for);
+
+    let response =
+        forwarded.body(body).send().await.map_err(|err| ReplicaError::Forward(err.to_string()))?;
+
+    let status = response.status();
+    let content_type = response.headers().get(header::CONTENT_TYPE).cloned();
+    let body = response.bytes().await.map_err(|err| ReplicaError::Forward(err.to_string()))?;
+
+    let mut relayed = Response::builder().status(status);
+    if let Some(content_type) = content_type {
+        relayed = relayed.header(header::CONTENT_TYPE, content_type);
+    }
+    Ok(relayed.body(Body::from(body)).expect("valid response"))
+}
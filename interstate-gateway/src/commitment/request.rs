@@ -1,18 +1,36 @@
 use alloy::{
     hex,
-    primitives::{keccak256, Address, PrimitiveSignature, SignatureError, B256},
+    primitives::{keccak256, Address, PrimitiveSignature, B256},
 };
 
 use parking_lot::RwLock;
 use reqwest::Url;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
-use std::{num::NonZeroUsize, str::FromStr, sync::Arc};
+use std::{collections::HashSet, net::IpAddr, num::NonZeroUsize, ops::Range, str::FromStr, sync::Arc};
 use thiserror::Error;
 use tokio::sync::{mpsc, oneshot};
 
-use crate::{constraints::{deserialize_txs, serialize_txs, Constraint, TransactionExt}, state::pricing::{PreconfPricer, PricingError}};
+use crate::{constraints::{deserialize_txs, serialize_txs, Constraint, TransactionExt}, state::{execution::SharedExecutionSnapshot, mempool::{ReplacementGuard, ReplacementPolicy}, pricing::{CommittedSpace, PreconfPricer, PricingError, PricingInput, PricingReport, PriorityTier, Underpriced}, reservations::ReservationError, slot_clock::SlotClock, stale::{StaleReason, StaleTxIndex}, sync::ElSyncMonitor}};
+use crate::metrics::ApiMetrics;
 use crate::onchain::gateway::GatewayController;
+use crate::utils::breaker::BreakerOpen;
+use crate::utils::score_cache::{ScoreCacheStats, SharedScoreCacheStats};
+
+use super::{
+    auth::AuthError,
+    confidential::{decrypt_request, ConfidentialError, ConfidentialInfo, ConfidentialKey},
+    events::EventBroadcaster,
+    forward::PeerForwarder,
+    quote::{QuoteError, Quoter, SignedQuote},
+    rate_limit::{RateLimited, RequestRateLimiters},
+    sequencer::RequestSequencer,
+    validation::{validate_preconf_request, FieldError, FieldErrorCode},
+};
+
+/// Flags of the commitment modes in the [PreconfRequest::digest], above the priority tiers.
+const ATOMIC_DIGEST_FLAG: u8 = 0x10;
+const PARTIAL_DIGEST_FLAG: u8 = 0x20;
 
 #[derive(Debug)]
 pub struct CommitmentRequestEvent {
@@ -25,13 +43,42 @@ pub struct CommitmentRequestHandler {
     cache: Arc<RwLock<lru::LruCache<u64, Vec<PreconfRequest>>>>,
     event_sender: mpsc::Sender<CommitmentRequestEvent>,
     gateway_controller: GatewayController,
+    chain_id: u64,
+    slot_clock: SlotClock,
+    account_states_stats: SharedScoreCacheStats,
+    execution: SharedExecutionSnapshot,
+    events: EventBroadcaster,
+    el_sync: ElSyncMonitor,
+    quoter: Quoter,
+    forwarder: Option<PeerForwarder>,
+    confidential_key: Option<ConfidentialKey>,
+    sender_policy: SenderPolicy,
+    replacements: ReplacementGuard,
+    stale: StaleTxIndex,
+    sequencer: Option<RequestSequencer>,
+    rate_limits: RequestRateLimiters,
 }
 
 impl CommitmentRequestHandler {
+    #[allow(clippy::too_many_arguments)]
     pub fn new<U: Into<Url>>(
         event_sender: mpsc::Sender<CommitmentRequestEvent>,
         rpc_url: U,
         contract_address: Address,
+        chain_id: u64,
+        slot_clock: SlotClock,
+        account_states_stats: SharedScoreCacheStats,
+        execution: SharedExecutionSnapshot,
+        events: EventBroadcaster,
+        el_sync: ElSyncMonitor,
+        quoter: Quoter,
+        forwarder: Option<PeerForwarder>,
+        confidential_key: Option<ConfidentialKey>,
+        sender_policy: SenderPolicy,
+        replacements: ReplacementGuard,
+        stale: StaleTxIndex,
+        sequencer: Option<RequestSequencer>,
+        rate_limits: RequestRateLimiters,
     ) -> Arc<Self> {
         let cap = NonZeroUsize::new(100).unwrap();
 
@@ -39,39 +86,183 @@ impl CommitmentRequestHandler {
             cache: Arc::new(RwLock::new(lru::LruCache::new(cap))),
             event_sender,
             gateway_controller: GatewayController::from_address(rpc_url, contract_address),
+            chain_id,
+            slot_clock,
+            account_states_stats,
+            execution,
+            events,
+            el_sync,
+            quoter,
+            forwarder,
+            confidential_key,
+            sender_policy,
+            replacements,
+            stale,
+            sequencer,
+            rate_limits,
         })
     }
 
+    /// Refuse the requests of clients above the rate allowed for their IP.
+    pub fn check_ip_rate_limit(&self, ip: IpAddr) -> Result<(), CommitmentRequestError> {
+        Ok(self.rate_limits.check_ip(ip)?)
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn current_slot(&self) -> u64 {
+        self.slot_clock.current_slot()
+    }
+
+    /// Parameters to encrypt transactions to the gateway, if confidential requests are enabled.
+    pub fn confidential_info(&self) -> Option<ConfidentialInfo> {
+        self.confidential_key.as_ref().map(ConfidentialKey::info)
+    }
+
+    /// Decrypt the transactions of a confidential request, see [decrypt_request].
+    pub fn decrypt_request(&self, body: Value) -> Result<Value, CommitmentRequestError> {
+        Ok(decrypt_request(self.confidential_key.as_ref(), body)?)
+    }
+
+    /// The peer gateway serving the proposer of `slot`, to forward its requests to.
+    pub fn peer_for(&self, slot: u64) -> Option<(&PeerForwarder, Url)> {
+        let forwarder = self.forwarder.as_ref()?;
+        Some((forwarder, forwarder.peer_for(slot)?))
+    }
+
+    pub fn events(&self) -> EventBroadcaster {
+        self.events.clone()
+    }
+
+    pub fn account_states_stats(&self) -> ScoreCacheStats {
+        self.account_states_stats.read().clone()
+    }
+
+    /// Current pricing of the `slots`, by default the slots following the head.
+    pub fn pricing(&self, slots: Option<Range<u64>>) -> PricingReport {
+        self.quoter.report(slots)
+    }
+
+    /// Signed quote to `sender` of the price of committing `gas` in `slot`.
+    pub fn quote(
+        &self,
+        sender: Address,
+        slot: u64,
+        gas: u64,
+    ) -> Result<SignedQuote, CommitmentRequestError> {
+        if slot < self.slot_clock.current_slot() {
+            return Err(QuoteError::PastSlot(slot).into());
+        }
+
+        Ok(self.quoter.quote(sender, slot, gas)?)
+    }
+
+    /// Validate the raw request body, reporting every invalid field.
+    pub fn parse_request(&self, body: &Value) -> Result<PreconfRequest, CommitmentRequestError> {
+        validate_preconf_request(body, self.chain_id, Some(self.slot_clock.current_slot()))
+            .map_err(CommitmentRequestError::InvalidFields)
+    }
+
     pub async fn handle_commitment_request(&self, request: &PreconfRequest) -> PreconfResult {
+        // Validation against the state of a lagging execution client can't be trusted
+        if let Err(lag_blocks) = self.el_sync.check() {
+            ApiMetrics::increment_validation_errors_count("el_syncing".to_string());
+            return Err(CommitmentRequestError::ExecutionClientSyncing(lag_blocks));
+        }
+
         let digest = request.digest();
         tracing::debug!("digest: {}", digest);
 
-        let recovered_signer = request
-            .signature
-            .recover_address_from_prehash(&digest)
-            .map_err(|_e| {
-                CommitmentRequestError::Custom(
-                    "Failed to recover signer from request signature".to_string(),
-                )
-            })?;
-        tracing::debug!("{}:{}", recovered_signer, request.sender);
-
-        if recovered_signer != request.sender {
-            tracing::error!("Signer is a not a sender");
-            return Err(CommitmentRequestError::Custom(
-                "Invalid signature".to_string(),
-            ));
+        // The claimed sender is only trusted once it signed the request
+        let recovered_signer = request.signature.recover_address_from_prehash(&digest).ok();
+        tracing::debug!(?recovered_signer, sender = %request.sender, "Recovered the signer");
+
+        if recovered_signer != Some(request.sender) {
+            tracing::warn!(sender = %request.sender, "The request isn't signed by its sender");
+            let error = FieldError::new(
+                "/signature",
+                FieldErrorCode::InvalidSignature,
+                "the request isn't signed by its sender",
+            );
+            ApiMetrics::increment_validation_errors_count(error.code.as_str().to_string());
+            return Err(CommitmentRequestError::InvalidFields(vec![error]));
         }
 
-        for tx in request.txs.iter() {
-            if !tx.validate(request.sender) {
-                tracing::error!("Sender of the transaction is not a signer");
-                return Err(CommitmentRequestError::Custom(
-                    "Sender of the transaction is invalid".to_owned(),
-                ));
+        // The senders of the transactions are recovered from their signatures, never claimed
+        let mut request = request.clone();
+        if let Err(error) = request.recover_signers() {
+            ApiMetrics::increment_validation_errors_count(error.code.as_str().to_string());
+            return Err(CommitmentRequestError::InvalidFields(vec![error]));
+        }
+        let request = &request;
+
+        // Limited once authenticated, so that nobody exhausts the requests of another sender
+        self.rate_limits.check_sender(request.sender)?;
+
+        // The gas of the quote is consumed once the request is committed
+        let quote_reservation =
+            request.quote.as_ref().map(|quote| self.quoter.verify(quote, request)).transpose()?;
+        let consume_quote = move |_: &_| {
+            if let Some(reservation) = quote_reservation {
+                reservation.commit();
+            }
+        };
+
+        self.sender_policy.check(request)?;
+
+        // Transactions that can never be included are refused before their full validation
+        if let Some(stale) = self.stale.check(request) {
+            let pointer = format!("/txs/{}", stale.index);
+            let error = match stale.reason {
+                StaleReason::AlreadyIncluded => FieldError::new(
+                    pointer,
+                    FieldErrorCode::AlreadyIncluded,
+                    "transaction is already included on chain",
+                ),
+                StaleReason::NonceConsumed { nonce, next } => FieldError::new(
+                    pointer,
+                    FieldErrorCode::NonceConsumed,
+                    format!("nonce {nonce} is already used, the next nonce is {next}"),
+                ),
+            };
+            ApiMetrics::increment_validation_errors_count(error.code.as_str().to_string());
+            return Err(CommitmentRequestError::InvalidFields(vec![error]));
+        }
+
+        if let Some(replacement) = self.replacements.find_replacement(request) {
+            let policy = self.replacements.policy();
+            tracing::warn!(
+                index = replacement.index,
+                replacement = %replacement.hash,
+                %policy,
+                "Transaction is replaced by a pending transaction paying more"
+            );
+            ApiMetrics::increment_mempool_replacements_count(match policy {
+                ReplacementPolicy::Reject => "rejected",
+                _ => "warned",
+            });
+            if policy == ReplacementPolicy::Reject {
+                return Err(CommitmentRequestError::ReplacedInMempool {
+                    index: replacement.index,
+                    replacement: replacement.hash.to_string(),
+                });
             }
         }
 
+        // Requests for past slots or not covering the basefee are refused against the latest
+        // head, without waiting for the state lock held by the head updates
+        if let Err(err) = self.execution.load().validate_request(request) {
+            ApiMetrics::increment_validation_errors_count(err.to_tag_str().to_string());
+            return Err(CommitmentRequestError::Custom(err.to_string()));
+        }
+
+        // Processed by the leader instance in the order of the shared log
+        if let Some(sequencer) = &self.sequencer {
+            return sequencer.submit(request.clone()).await.inspect(consume_quote);
+        }
+
         let (response_tx, response_rx) = oneshot::channel();
 
         let event = CommitmentRequestEvent {
@@ -88,7 +279,7 @@ impl CommitmentRequestHandler {
 
         tracing::debug!("sent request to event loop");
         match response_rx.await {
-            Ok(event_response) => event_response,
+            Ok(event_response) => event_response.inspect(consume_quote),
             Err(e) => {
                 tracing::error!(err = ?e, "Failed in receiving commitment request event response from event loop");
                 Err(CommitmentRequestError::Custom(
@@ -104,6 +295,76 @@ impl CommitmentRequestHandler {
     }
 }
 
+/// Constraints of a request, committed as they are signed or, for an atomic request, all
+/// together once every one of them is signed. An atomic batch dropped on a failure commits none.
+#[derive(Debug)]
+pub struct ConstraintsBatch<T> {
+    atomic: bool,
+    staged: Vec<T>,
+}
+
+impl<T> ConstraintsBatch<T> {
+    pub fn new(atomic: bool) -> Self {
+        Self { atomic, staged: Vec::new() }
+    }
+
+    /// The signed `constraints` to commit right away, none for an atomic batch.
+    pub fn push(&mut self, constraints: T) -> Option<T> {
+        if !self.atomic {
+            return Some(constraints);
+        }
+        self.staged.push(constraints);
+        None
+    }
+
+    /// The constraints of the atomic batch to commit, once all of them are signed.
+    pub fn complete(self) -> Vec<T> {
+        self.staged
+    }
+}
+
+/// Who may request commitments for a transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderPolicy {
+    /// Require every transaction to be signed by the authenticated sender of the request, so
+    /// that nobody commits the transactions of others.
+    pub require_signer: bool,
+    /// Senders allowed to request commitments for transactions they didn't sign.
+    pub relayers: HashSet<Address>,
+}
+
+impl Default for SenderPolicy {
+    fn default() -> Self {
+        Self { require_signer: true, relayers: HashSet::new() }
+    }
+}
+
+impl SenderPolicy {
+    /// Check that the sender of `request` may commit each of its transactions.
+    pub fn check(&self, request: &PreconfRequest) -> Result<(), CommitmentRequestError> {
+        if !self.require_signer || self.relayers.contains(&request.sender) {
+            return Ok(());
+        }
+
+        for (index, tx) in request.txs.iter().enumerate() {
+            if !tx.validate(request.sender) {
+                tracing::warn!(
+                    index,
+                    sender = %request.sender,
+                    "Refusing to commit a transaction of someone else"
+                );
+                ApiMetrics::increment_validation_errors_count("foreign_transaction".to_string());
+                return Err(CommitmentRequestError::ForeignTransaction {
+                    index,
+                    signer: tx.tx.recover_signer(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PreconfRequest {
     pub slot: u64,
@@ -117,6 +378,31 @@ pub struct PreconfRequest {
     pub(crate) sender: Address,
 
     pub chain_id: u64,
+
+    /// Price quote to honor instead of the current price, verified when the request is
+    /// received.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quote: Option<SignedQuote>,
+
+    /// Commit to all the transactions or to none of them: their constraints are only added
+    /// once every one of them is signed.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub atomic: bool,
+
+    /// Commit to the valid transactions only, answering with the verdict of each transaction
+    /// instead of refusing the whole request because of one of them.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+
+    /// Priority tier of the commitment, priced at a multiple of the minimum inclusion tip and
+    /// placed ahead of the lower tiers in the block.
+    #[serde(default, skip_serializing_if = "PriorityTier::is_standard")]
+    pub priority: PriorityTier,
+
+    /// Set on the requests forcing in long pending transactions of the mempool, which are
+    /// not priced as they don't pay us for the commitment.
+    #[serde(skip)]
+    pub(crate) inclusion_list: bool,
 }
 
 impl PreconfRequest {
@@ -128,13 +414,36 @@ impl PreconfRequest {
         for tx in &self.txs {
             data.extend_from_slice(tx.tx.hash().as_slice());
         }
+        // The tier is signed by the sender, the standard one left out as before the tiers
+        if !self.priority.is_standard() {
+            data.push(self.priority as u8);
+        }
+        // So are the commitment modes, apart from the tiers and left out when unset
+        let modes =
+            (self.atomic as u8 * ATOMIC_DIGEST_FLAG) | (self.partial as u8 * PARTIAL_DIGEST_FLAG);
+        if modes != 0 {
+            data.push(modes);
+        }
 
         keccak256(data)
     }
 
-    pub fn gas_limit(&self) -> u64 {
-        self.txs.iter().map(|c| c.tx.gas_limit()).sum()
+    /// Gas limit of each transaction of the request, in order.
+    pub fn tx_gas_limits(&self) -> impl Iterator<Item = u64> + '_ {
+        self.txs.iter().map(|c| c.tx.gas_limit())
+    }
+
+    /// Largest gas limit of a single transaction, the one bounded by the block gas limit.
+    pub fn max_tx_gas_limit(&self) -> u64 {
+        self.tx_gas_limits().max().unwrap_or(0)
+    }
+
+    /// Sum of the gas limits of all the transactions, the gas committed by the request.
+    /// Saturates rather than overflowing on adversarial gas limits.
+    pub fn total_gas_limit(&self) -> u64 {
+        self.tx_gas_limits().fold(0, u64::saturating_add)
     }
+
     /// Validates the tx size limit.
     pub fn validate_tx_size_limit(&self, limit: usize) -> bool {
         for c in &self.txs {
@@ -182,40 +491,63 @@ impl PreconfRequest {
         true
     }
 
-    pub fn recover_signers(&mut self) -> Result<(), SignatureError> {
-        for tx in &mut self.txs {
-            let signer = tx.tx.recover_signer().unwrap_or_default();
-            tx.sender = Some(signer);
+    /// Recover the sender of every transaction from its signature, overwriting the claimed
+    /// ones. Fails on the first transaction whose signer can't be recovered.
+    pub fn recover_signers(&mut self) -> Result<(), FieldError> {
+        for (index, tx) in self.txs.iter_mut().enumerate() {
+            if tx.recover_sender().is_none() {
+                return Err(FieldError::new(
+                    format!("/txs/{index}"),
+                    FieldErrorCode::InvalidSignature,
+                    "can't recover the signer of the transaction",
+                ));
+            }
         }
 
         Ok(())
     }
 
+    /// Validates the tips against the minimum inclusion tip, each transaction being priced on
+    /// the gas at the same index of `priced_gas` and its blobs, given the space `committed` in
+    /// the slot `slots_ahead` of the head. The tips must pay for the priority tier requested.
     pub fn validate_min_priority_fee(
         &self,
         pricing: &PreconfPricer,
-        preconfirmed_gas: u64,
+        committed: CommittedSpace,
+        slots_ahead: u64,
         min_inclusion_profit: u64,
         max_base_fee: u128,
+        priced_gas: &[u64],
     ) -> Result<bool, PricingError> {
+        // A quoted price is honored as is, it already includes the inclusion profit
+        if let Some(quote) = &self.quote {
+            let required_tip = self.priority.price(quote.message.min_priority_fee as u128);
+            for (index, tx) in self.txs.iter().enumerate() {
+                let tip = tx.effective_tip_per_gas(max_base_fee).unwrap_or_default();
+                if tip < required_tip {
+                    return Err(PricingError::TipTooLow(Underpriced { index, tip, required_tip }));
+                }
+            }
+            return Ok(true);
+        }
+
         // Each included tx will move the price up
         // So we need to calculate the minimum priority fee for each tx
-        let mut local_preconfirmed_gas = preconfirmed_gas;
-        for tx in &self.txs {
-            // Calculate minimum required priority fee for this transaction
-            let min_priority_fee = pricing
-                .calculate_min_priority_fee(tx.tx.gas_limit(), preconfirmed_gas)? +
-                min_inclusion_profit;
+        let mut committed = committed;
+        for (index, (tx, gas)) in self.txs.iter().zip(priced_gas).enumerate() {
+            let blobs = tx.tx.blob_sidecar().map_or(0, |sidecar| sidecar.blobs.len());
+            let input = PricingInput { gas: *gas, blobs, committed, slots_ahead };
+            let required_tip = self
+                .priority
+                .price((pricing.min_inclusion_tip(&input)? + min_inclusion_profit) as u128);
 
             let tip = tx.effective_tip_per_gas(max_base_fee).unwrap_or_default();
-            if tip < min_priority_fee as u128 {
-                return Err(PricingError::TipTooLow {
-                    tip,
-                    min_priority_fee: min_priority_fee as u128,
-                });
+            if tip < required_tip {
+                return Err(PricingError::TipTooLow(Underpriced { index, tip, required_tip }));
             }
-            // Increment the preconfirmed gas for the next transaction in the bundle
-            local_preconfirmed_gas = local_preconfirmed_gas.saturating_add(tx.tx.gas_limit());
+            // The next transactions of the bundle are priced on the space left by this one
+            committed.gas = committed.gas.saturating_add(*gas);
+            committed.blobs += blobs;
         }
         Ok(true)
     }
@@ -249,11 +581,63 @@ pub enum CommitmentRequestError {
 
     #[error("Not allowed ip: {0}")]
     NotAllowedIP(String),
+
+    #[error("unauthorized: {0}")]
+    Unauthorized(#[from] AuthError),
+
+    #[error("invalid request fields: {0:?}")]
+    InvalidFields(Vec<FieldError>),
+
+    #[error("execution client is {0} blocks behind, not accepting commitments")]
+    ExecutionClientSyncing(u64),
+
+    #[error("instance is handing over to another one, not signing commitments")]
+    Standby,
+
+    #[error("gateway is shutting down, not accepting commitments")]
+    ShuttingDown,
+
+    #[error("invalid price quote: {0}")]
+    Quote(#[from] QuoteError),
+
+    #[error("failed to forward the request to the gateway of the proposer: {0}")]
+    Forward(String),
+
+    #[error("invalid confidential request: {0}")]
+    Confidential(#[from] ConfidentialError),
+
+    #[error("transaction {index} is signed by {signer:?}, not by the sender of the request")]
+    ForeignTransaction { index: usize, signer: Option<Address> },
+
+    #[error("transaction {index} is replaced by the pending transaction {replacement}")]
+    ReplacedInMempool { index: usize, replacement: String },
+
+    #[error("request is underpriced: {0}")]
+    Underpriced(Underpriced),
+
+    #[error("atomic request failed at transaction {index}, none committed: {reason}")]
+    AtomicBatchFailed { index: usize, tx_hash: B256, reason: String },
+
+    #[error("invalid capacity reservation: {0}")]
+    Reservation(#[from] ReservationError),
+
+    #[error(transparent)]
+    Unavailable(#[from] BreakerOpen),
+
+    #[error("too many requests by {}, retry in {}ms", .0.key.as_str(), .0.retry_in_ms)]
+    RateLimited(#[from] RateLimited),
+
+    #[error("failed to sequence the request: {0}")]
+    Sequencer(String),
+
+    /// Failure of a request processed by the leader instance, answered as it answered it.
+    #[error("request failed with status {status}: {body}")]
+    Sequenced { status: u16, body: Value },
 }
 
 pub type PreconfResult = Result<Value, CommitmentRequestError>;
 
-fn deserialize_sig<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+pub(crate) fn deserialize_sig<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
     D: Deserializer<'de>,
     T: FromStr,
@@ -263,7 +647,7 @@ where
     T::from_str(s.trim_start_matches("0x")).map_err(de::Error::custom)
 }
 
-fn serialize_sig<S: serde::Serializer>(
+pub(crate) fn serialize_sig<S: serde::Serializer>(
     sig: &PrimitiveSignature,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
@@ -273,3 +657,162 @@ fn serialize_sig<S: serde::Serializer>(
     bytes[bytes.len() - 1] = if parity { 1 } else { 0 };
     serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        hex,
+        network::{EthereumWallet, TransactionBuilder},
+        primitives::{Address, PrimitiveSignature, U256},
+        signers::{k256::ecdsa::SigningKey, local::PrivateKeySigner},
+    };
+
+    use super::{CommitmentRequestError, ConstraintsBatch, PreconfRequest, SenderPolicy};
+    use crate::{
+        constraints::Constraint, state::pricing::PriorityTier, test_utils::default_test_transaction,
+    };
+
+    #[tokio::test]
+    async fn test_gas_accessors_for_mixed_txs() -> eyre::Result<()> {
+        let sk = SigningKey::from_slice(&hex::decode(
+            "5d2344259f42259f82d2c140aa66102ba89b57b4883ee441a8b312622bd42491",
+        )?)?;
+        let wallet = EthereumWallet::from(PrivateKeySigner::from_signing_key(sk.clone()));
+        let sender = Address::from_private_key(&sk);
+
+        let transfer = default_test_transaction(sender, Some(0));
+        let mut legacy = default_test_transaction(sender, Some(1)).with_gas_limit(500_000);
+        legacy.max_fee_per_gas = None;
+        legacy.max_priority_fee_per_gas = None;
+        let legacy = legacy.with_gas_price(20_000_000_000);
+
+        let mut txs = Vec::new();
+        for tx in [transfer, legacy] {
+            let raw = tx.build(&wallet).await?.encoded_2718();
+            txs.push(Constraint::decode_enveloped(raw)?);
+        }
+
+        let request = PreconfRequest {
+            slot: 1,
+            txs,
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender,
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+
+        assert_eq!(request.tx_gas_limits().collect::<Vec<_>>(), vec![21_000, 500_000]);
+        assert_eq!(request.max_tx_gas_limit(), 500_000);
+        assert_eq!(request.total_gas_limit(), 521_000);
+
+        let empty = PreconfRequest { txs: vec![], ..request };
+        assert_eq!(empty.max_tx_gas_limit(), 0);
+        assert_eq!(empty.total_gas_limit(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_atomic_batch_commits_all_or_nothing() {
+        let mut batch = ConstraintsBatch::new(false);
+        assert_eq!(batch.push(1), Some(1));
+        assert!(batch.complete().is_empty());
+
+        // Nothing is committed before the last constraints are signed
+        let mut batch = ConstraintsBatch::new(true);
+        assert_eq!(batch.push(1), None);
+        assert_eq!(batch.push(2), None);
+        assert_eq!(batch.complete(), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_sender_policy() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let raw = default_test_transaction(signer.address(), Some(0)).build(&wallet).await?;
+
+        let request = PreconfRequest {
+            slot: 1,
+            txs: vec![Constraint::decode_enveloped(raw.encoded_2718())?],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: signer.address(),
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+        let relayer = Address::repeat_byte(1);
+        let relayed = PreconfRequest { sender: relayer, ..request.clone() };
+
+        let policy = SenderPolicy::default();
+        assert!(policy.check(&request).is_ok());
+        assert!(matches!(
+            policy.check(&relayed),
+            Err(CommitmentRequestError::ForeignTransaction { index: 0, signer: Some(s) })
+                if s == signer.address()
+        ));
+
+        // Allow-listed relayers commit the transactions of others, as does everyone if disabled
+        let policy = SenderPolicy { relayers: [relayer].into(), ..Default::default() };
+        assert!(policy.check(&relayed).is_ok());
+        let policy = SenderPolicy { require_signer: false, ..Default::default() };
+        assert!(policy.check(&relayed).is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_atomic_flag_defaults_off() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let raw = default_test_transaction(signer.address(), Some(0)).build(&wallet).await?;
+
+        let request = PreconfRequest {
+            slot: 1,
+            txs: vec![Constraint::decode_enveloped(raw.encoded_2718())?],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: signer.address(),
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+        let json = serde_json::to_value(&request)?;
+        assert!(json.get("atomic").is_none());
+        assert!(json.get("partial").is_none());
+        assert_eq!(serde_json::from_value::<PreconfRequest>(json)?, request);
+
+        let atomic = PreconfRequest { atomic: true, ..request.clone() };
+        let json = serde_json::to_value(&atomic)?;
+        assert_eq!(json["atomic"], true);
+        assert_eq!(serde_json::from_value::<PreconfRequest>(json)?, atomic);
+
+        // The commitment modes are signed, apart from each other and from the tiers
+        let partial = PreconfRequest { partial: true, ..request.clone() };
+        let digests = [&request, &atomic, &partial].map(|request| request.digest());
+        assert_ne!(digests[0], digests[1]);
+        assert_ne!(digests[0], digests[2]);
+        assert_ne!(digests[1], digests[2]);
+        let fast_atomic = PreconfRequest { priority: PriorityTier::Fast, ..atomic.clone() };
+        assert_ne!(fast_atomic.digest(), atomic.digest());
+
+        // The standard tier keeps the digest of the requests without a tier
+        assert!(json.get("priority").is_none());
+        let fast = PreconfRequest { priority: PriorityTier::Fast, ..request.clone() };
+        let json = serde_json::to_value(&fast)?;
+        assert_eq!(json["priority"], "fast");
+        assert_eq!(serde_json::from_value::<PreconfRequest>(json)?, fast);
+        assert_ne!(fast.digest(), request.digest());
+
+        Ok(())
+    }
+}
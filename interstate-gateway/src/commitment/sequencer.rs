@@ -0,0 +1,342 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use axum::{body::to_bytes, response::IntoResponse};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::{mpsc, oneshot};
+
+use super::request::{
+    CommitmentRequestError, CommitmentRequestEvent, PreconfRequest, PreconfResult,
+};
+use crate::{handover::InstanceLease, metrics::ApiMetrics, utils::now_ms};
+
+/// Interval at which the instances read the entries appended to the request log.
+const SEQUENCER_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Time a request waits for its outcome. The leader skips the requests older than that, their
+/// client was already answered.
+pub const SEQUENCED_REQUEST_TIMEOUT: Duration = Duration::from_secs(4);
+
+/// Max size of the body of a failure recorded in the log.
+const MAX_OUTCOME_BODY_BYTES: usize = 64 * 1024;
+
+/// Record of the shared request log, one JSON object per line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+enum SequencedEntry {
+    /// A request received by the instance `id` is prefixed with, to process at this position.
+    Request { id: String, received_ms: u64, request: PreconfRequest },
+    /// Outcome of the request `id`, recorded by the leader that processed it.
+    Outcome { id: String, outcome: SequencedOutcome },
+}
+
+/// Outcome of a sequenced request, as answered to its client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SequencedOutcome {
+    Committed(Value),
+    /// Status and body of the error response, a string unless the body is JSON.
+    Failed {
+        status: u16,
+        body: Value,
+    },
+}
+
+impl SequencedOutcome {
+    async fn from_result(result: PreconfResult) -> Self {
+        let err = match result {
+            Ok(response) => return Self::Committed(response),
+            Err(err) => err,
+        };
+
+        let response = err.into_response();
+        let status = response.status().as_u16();
+        let body = match to_bytes(response.into_body(), MAX_OUTCOME_BODY_BYTES).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned())),
+            Err(err) => Value::String(err.to_string()),
+        };
+        Self::Failed { status, body }
+    }
+
+    fn into_result(self) -> PreconfResult {
+        match self {
+            Self::Committed(response) => Ok(response),
+            Self::Failed { status, body } => {
+                Err(CommitmentRequestError::Sequenced { status, body })
+            }
+        }
+    }
+}
+
+/// Orders the commitment requests of the instances of a sidecar through a log shared by them.
+///
+/// Every instance appends the requests it receives to the log, and waits for their outcome to
+/// be appended after them. Only the leader processes the requests, one at a time in the order
+/// of the log, so that the constraints signed don't depend on which instance received the
+/// requests, and a new leader resumes from the first request without an outcome.
+///
+/// Entries are appended with a single write to a file opened in append mode, which doesn't
+/// interleave them on local filesystems.
+#[derive(Debug, Clone)]
+pub struct RequestSequencer {
+    path: PathBuf,
+    instance_id: String,
+    next_request: Arc<AtomicU64>,
+    file: Arc<Mutex<File>>,
+    waiters: Arc<Mutex<HashMap<String, oneshot::Sender<SequencedOutcome>>>>,
+}
+
+impl RequestSequencer {
+    /// Open the log at `path`, processing its requests with `events` while `lease` is held.
+    pub fn open(
+        path: PathBuf,
+        lease: InstanceLease,
+        events: mpsc::Sender<CommitmentRequestEvent>,
+    ) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let sequencer = Self {
+            path,
+            instance_id: format!("{}-{}", std::process::id(), now_ms()),
+            next_request: Arc::new(AtomicU64::new(0)),
+            file: Arc::new(Mutex::new(file)),
+            waiters: Default::default(),
+        };
+
+        tokio::spawn(sequencer.clone().follow(lease, events));
+        tracing::info!(path = %sequencer.path.display(), "Sequencing the requests through the shared log");
+        Ok(sequencer)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `request` to the log and wait for the leader to process it.
+    pub async fn submit(&self, request: PreconfRequest) -> PreconfResult {
+        let id =
+            format!("{}-{}", self.instance_id, self.next_request.fetch_add(1, Ordering::Relaxed));
+        let (outcome_tx, outcome_rx) = oneshot::channel();
+        self.waiters.lock().insert(id.clone(), outcome_tx);
+
+        let entry = SequencedEntry::Request { id: id.clone(), received_ms: now_ms(), request };
+        if let Err(err) = self.append(&entry) {
+            self.waiters.lock().remove(&id);
+            tracing::error!(?err, "Failed to append a request to the shared log");
+            return Err(CommitmentRequestError::Sequencer(err.to_string()));
+        }
+
+        match tokio::time::timeout(SEQUENCED_REQUEST_TIMEOUT, outcome_rx).await {
+            Ok(Ok(outcome)) => outcome.into_result(),
+            _ => {
+                self.waiters.lock().remove(&id);
+                ApiMetrics::increment_sequenced_requests_count("timeout");
+                Err(CommitmentRequestError::Sequencer(
+                    "no leader processed the request".to_string(),
+                ))
+            }
+        }
+    }
+
+    /// Follow the entries appended to the log, processing the requests without an outcome
+    /// in order while leading.
+    async fn follow(self, lease: InstanceLease, events: mpsc::Sender<CommitmentRequestEvent>) {
+        let mut reader = LogReader::default();
+        let mut unprocessed = VecDeque::new();
+        let mut ticker = tokio::time::interval(SEQUENCER_POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+
+            let entries = match reader.read_new(&self.path) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    tracing::error!(?err, "Failed to read the shared request log");
+                    continue;
+                }
+            };
+            for entry in entries {
+                match entry {
+                    SequencedEntry::Request { id, received_ms, request } => {
+                        unprocessed.push_back((id, received_ms, request));
+                    }
+                    SequencedEntry::Outcome { id, outcome } => {
+                        unprocessed.retain(|(pending, ..)| *pending != id);
+                        self.resolve(&id, outcome);
+                    }
+                }
+            }
+
+            while lease.is_leader() {
+                let Some((id, received_ms, request)) = unprocessed.pop_front() else { break };
+                if now_ms().saturating_sub(received_ms)
+                    > SEQUENCED_REQUEST_TIMEOUT.as_millis() as u64
+                {
+                    tracing::warn!(%id, slot = request.slot, "Skipping an expired sequenced request");
+                    ApiMetrics::increment_sequenced_requests_count("expired");
+                    continue;
+                }
+
+                let outcome = process(request, &events).await;
+                ApiMetrics::increment_sequenced_requests_count(match outcome {
+                    SequencedOutcome::Committed(_) => "committed",
+                    SequencedOutcome::Failed { .. } => "failed",
+                });
+                let entry = SequencedEntry::Outcome { id: id.clone(), outcome: outcome.clone() };
+                if let Err(err) = self.append(&entry) {
+                    tracing::error!(?err, %id, "Failed to record the outcome of a sequenced request");
+                }
+                self.resolve(&id, outcome);
+            }
+        }
+    }
+
+    fn resolve(&self, id: &str, outcome: SequencedOutcome) {
+        if let Some(waiter) = self.waiters.lock().remove(id) {
+            let _ = waiter.send(outcome);
+        }
+    }
+
+    fn append(&self, entry: &SequencedEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        self.file.lock().write_all(&line)
+    }
+}
+
+/// Process a request with the event loop, waiting for its outcome.
+async fn process(
+    request: PreconfRequest,
+    events: &mpsc::Sender<CommitmentRequestEvent>,
+) -> SequencedOutcome {
+    let (res, response_rx) = oneshot::channel();
+    let result = match events.send(CommitmentRequestEvent { req: request, res }).await {
+        Ok(()) => response_rx.await.unwrap_or_else(|_| {
+            Err(CommitmentRequestError::Custom(
+                "Failed in receiving commitment request event response from event loop".to_owned(),
+            ))
+        }),
+        Err(_) => Err(CommitmentRequestError::ShuttingDown),
+    };
+    SequencedOutcome::from_result(result).await
+}
+
+/// Reads the complete lines appended to the log since the previous read.
+#[derive(Debug, Default)]
+struct LogReader {
+    offset: u64,
+}
+
+impl LogReader {
+    fn read_new(&mut self, path: &Path) -> io::Result<Vec<SequencedEntry>> {
+        let mut file = File::open(path)?;
+        if file.metadata()?.len() < self.offset {
+            tracing::warn!(path = %path.display(), "Shared request log truncated, reading it again");
+            self.offset = 0;
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+
+        // The last line may still be being written
+        let Some(end) = content.iter().rposition(|byte| *byte == b'\n') else {
+            return Ok(Vec::new());
+        };
+        self.offset += end as u64 + 1;
+
+        let mut entries = Vec::new();
+        for line in content[..end].split(|byte| *byte == b'\n').filter(|line| !line.is_empty()) {
+            match serde_json::from_slice(line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => {
+                    tracing::warn!(?err, "Ignoring an invalid entry of the shared request log")
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{PrimitiveSignature, U256};
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::RequestSequencer;
+    use crate::{
+        commitment::request::{CommitmentRequestError, CommitmentRequestEvent, PreconfRequest},
+        handover::InstanceLease,
+    };
+
+    fn request(slot: u64) -> PreconfRequest {
+        PreconfRequest {
+            slot,
+            txs: vec![],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: Default::default(),
+            chain_id: 1,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_requests_processed_by_the_leader_in_order() {
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("sequencer-test-{}.jsonl", std::process::id()));
+        let lease_path = dir.join(format!("sequencer-test-lease-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        // The leader commits the even slots and refuses the others
+        let (events_tx, mut events_rx) = mpsc::channel::<CommitmentRequestEvent>(8);
+        let processed = tokio::spawn(async move {
+            let mut slots = Vec::new();
+            while let Some(CommitmentRequestEvent { req, res }) = events_rx.recv().await {
+                slots.push(req.slot);
+                let _ = res.send(match req.slot % 2 {
+                    0 => Ok(json!({ "slot": req.slot })),
+                    _ => Err(CommitmentRequestError::Standby),
+                });
+                if slots.len() == 3 {
+                    return slots;
+                }
+            }
+            slots
+        });
+        let leader =
+            RequestSequencer::open(path.clone(), InstanceLease::standalone(), events_tx).unwrap();
+
+        // Still on standby, never processes the requests it receives
+        let (standby_tx, _standby_rx) = mpsc::channel(8);
+        let lease = InstanceLease::acquire(lease_path.clone()).unwrap();
+        let standby = RequestSequencer::open(path.clone(), lease, standby_tx).unwrap();
+
+        assert_eq!(leader.submit(request(10)).await.unwrap(), json!({ "slot": 10 }));
+        assert_eq!(standby.submit(request(12)).await.unwrap(), json!({ "slot": 12 }));
+        match standby.submit(request(11)).await {
+            Err(CommitmentRequestError::Sequenced { status, body }) => {
+                assert_eq!(status, 503);
+                assert_eq!(body, CommitmentRequestError::Standby.to_string());
+            }
+            other => panic!("unexpected outcome {other:?}"),
+        }
+        assert_eq!(processed.await.unwrap(), vec![10, 12, 11]);
+
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(lease_path);
+    }
+}
@@ -0,0 +1,374 @@
+use std::str::FromStr;
+
+use alloy::{
+    hex,
+    primitives::{Address, PrimitiveSignature},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use utoipa::ToSchema;
+
+use super::request::PreconfRequest;
+use crate::{constraints::deserialize_txs, state::pricing::PriorityTier};
+
+/// Stable error codes reported for invalid commitment request fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldErrorCode {
+    InvalidRequest,
+    MissingField,
+    InvalidType,
+    InvalidHex,
+    EmptyTransactions,
+    InvalidTransaction,
+    InvalidSignature,
+    InvalidAddress,
+    ChainIdMismatch,
+    SlotInPast,
+    AlreadyIncluded,
+    NonceConsumed,
+}
+
+impl FieldErrorCode {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::InvalidRequest => "invalid_request",
+            Self::MissingField => "missing_field",
+            Self::InvalidType => "invalid_type",
+            Self::InvalidHex => "invalid_hex",
+            Self::EmptyTransactions => "empty_transactions",
+            Self::InvalidTransaction => "invalid_transaction",
+            Self::InvalidSignature => "invalid_signature",
+            Self::InvalidAddress => "invalid_address",
+            Self::ChainIdMismatch => "chain_id_mismatch",
+            Self::SlotInPast => "slot_in_past",
+            Self::AlreadyIncluded => "already_included",
+            Self::NonceConsumed => "nonce_consumed",
+        }
+    }
+}
+
+/// A validation error for a single field, located with a JSON pointer (RFC 6901).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct FieldError {
+    pub pointer: String,
+    pub code: FieldErrorCode,
+    pub message: String,
+}
+
+impl FieldError {
+    pub(crate) fn new(pointer: impl Into<String>, code: FieldErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            pointer: pointer.into(),
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Validate a raw commitment request body and parse it into a [PreconfRequest].
+///
+/// All fields are checked so that a single response reports every invalid field.
+/// `current_slot` is optional as the slot can't be checked before the chain clock is known.
+pub fn validate_preconf_request(
+    body: &Value,
+    chain_id: u64,
+    current_slot: Option<u64>,
+) -> Result<PreconfRequest, Vec<FieldError>> {
+    let Some(fields) = body.as_object() else {
+        return Err(vec![FieldError::new(
+            "",
+            FieldErrorCode::InvalidType,
+            "request body must be a JSON object",
+        )]);
+    };
+
+    let mut errors = Vec::new();
+
+    if let Some(slot) = u64_field(fields, "slot", &mut errors) {
+        if let Some(current_slot) = current_slot.filter(|current| slot <= *current) {
+            errors.push(FieldError::new(
+                "/slot",
+                FieldErrorCode::SlotInPast,
+                format!("slot {slot} is not after the current slot {current_slot}"),
+            ));
+        }
+    }
+
+    if let Some(request_chain_id) = u64_field(fields, "chain_id", &mut errors) {
+        if request_chain_id != chain_id {
+            errors.push(FieldError::new(
+                "/chain_id",
+                FieldErrorCode::ChainIdMismatch,
+                format!("expected chain id {chain_id}, got {request_chain_id}"),
+            ));
+        }
+    }
+
+    validate_txs(fields, &mut errors);
+
+    if let Some(signature) = hex_field(fields, "signature", &mut errors) {
+        if PrimitiveSignature::from_str(signature).is_err() {
+            errors.push(FieldError::new(
+                "/signature",
+                FieldErrorCode::InvalidSignature,
+                "expected a 65 bytes ECDSA signature",
+            ));
+        }
+    }
+
+    if let Some(sender) = hex_field(fields, "sender", &mut errors) {
+        if Address::from_str(sender).is_err() {
+            errors.push(FieldError::new(
+                "/sender",
+                FieldErrorCode::InvalidAddress,
+                "expected a 20 bytes address",
+            ));
+        }
+    }
+
+    // Partial requests commit to a subset of their transactions, atomic ones to all or none
+    let flag = |name| fields.get(name).and_then(Value::as_bool).unwrap_or(false);
+    if flag("atomic") && flag("partial") {
+        errors.push(FieldError::new(
+            "/partial",
+            FieldErrorCode::InvalidRequest,
+            "a request can't be both atomic and partial",
+        ));
+    }
+
+    if let Some(priority) = fields.get("priority") {
+        if PriorityTier::deserialize(priority).is_err() {
+            errors.push(FieldError::new(
+                "/priority",
+                FieldErrorCode::InvalidRequest,
+                "expected a standard, fast or top priority tier",
+            ));
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    serde_json::from_value(body.clone())
+        .map_err(|err| vec![FieldError::new("", FieldErrorCode::InvalidRequest, err.to_string())])
+}
+
+fn validate_txs(fields: &Map<String, Value>, errors: &mut Vec<FieldError>) {
+    let Some(txs) = field(fields, "txs", errors) else {
+        return;
+    };
+
+    let Some(txs) = txs.as_array() else {
+        errors.push(FieldError::new(
+            "/txs",
+            FieldErrorCode::InvalidType,
+            "expected an array of hex encoded transactions",
+        ));
+        return;
+    };
+
+    if txs.is_empty() {
+        errors.push(FieldError::new(
+            "/txs",
+            FieldErrorCode::EmptyTransactions,
+            "at least one transaction is required",
+        ));
+    }
+
+    for (i, tx) in txs.iter().enumerate() {
+        let pointer = format!("/txs/{i}");
+        let Some(raw) = tx.as_str() else {
+            errors.push(FieldError::new(pointer, FieldErrorCode::InvalidType, "expected a string"));
+            continue;
+        };
+
+        if hex::decode(raw.trim_start_matches("0x")).is_err() {
+            errors.push(FieldError::new(pointer, FieldErrorCode::InvalidHex, "invalid hex string"));
+            continue;
+        }
+
+        if let Err(err) = deserialize_txs(Value::Array(vec![tx.clone()])) {
+            errors.push(FieldError::new(
+                pointer,
+                FieldErrorCode::InvalidTransaction,
+                err.to_string(),
+            ));
+        }
+    }
+}
+
+fn field<'a>(
+    fields: &'a Map<String, Value>,
+    name: &str,
+    errors: &mut Vec<FieldError>,
+) -> Option<&'a Value> {
+    let value = fields.get(name);
+    if value.is_none() {
+        errors.push(FieldError::new(
+            format!("/{name}"),
+            FieldErrorCode::MissingField,
+            format!("missing field `{name}`"),
+        ));
+    }
+    value
+}
+
+fn u64_field(fields: &Map<String, Value>, name: &str, errors: &mut Vec<FieldError>) -> Option<u64> {
+    let value = field(fields, name, errors)?.as_u64();
+    if value.is_none() {
+        errors.push(FieldError::new(
+            format!("/{name}"),
+            FieldErrorCode::InvalidType,
+            "expected an unsigned integer",
+        ));
+    }
+    value
+}
+
+/// Returns the hex string value of a field, without its `0x` prefix.
+fn hex_field<'a>(
+    fields: &'a Map<String, Value>,
+    name: &str,
+    errors: &mut Vec<FieldError>,
+) -> Option<&'a str> {
+    let Some(value) = field(fields, name, errors)?.as_str() else {
+        errors.push(FieldError::new(
+            format!("/{name}"),
+            FieldErrorCode::InvalidType,
+            "expected a hex string",
+        ));
+        return None;
+    };
+
+    let value = value.trim_start_matches("0x");
+    if hex::decode(value).is_err() {
+        errors.push(FieldError::new(
+            format!("/{name}"),
+            FieldErrorCode::InvalidHex,
+            "invalid hex string",
+        ));
+        return None;
+    }
+
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        hex,
+        network::{EthereumWallet, TransactionBuilder},
+        signers::local::PrivateKeySigner,
+    };
+    use proptest::prelude::*;
+    use serde_json::{json, Value};
+
+    use super::{validate_preconf_request, FieldErrorCode};
+    use crate::test_utils::default_test_transaction;
+
+    const CHAIN_ID: u64 = 17000;
+
+    /// A hex encoded transfer signed once for all the cases.
+    fn signed_tx() -> &'static str {
+        static TX: OnceLock<String> = OnceLock::new();
+        TX.get_or_init(|| {
+            let signer = PrivateKeySigner::random();
+            let wallet = EthereumWallet::from(signer.clone());
+            let tx = default_test_transaction(signer.address(), Some(0)).with_chain_id(CHAIN_ID);
+
+            let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+            let signed = runtime.block_on(tx.build(&wallet)).unwrap();
+            hex::encode_prefixed(signed.encoded_2718())
+        })
+    }
+
+    fn valid_fields() -> Value {
+        json!({
+            "slot": 100,
+            "txs": [signed_tx()],
+            "signature": format!("0x{}", "11".repeat(65)),
+            "sender": format!("0x{}", "22".repeat(20)),
+            "chain_id": CHAIN_ID,
+        })
+    }
+
+    /// Each invalid field case with the pointer and code it must always map to.
+    fn invalid_cases() -> Vec<(&'static str, Option<Value>, &'static str, FieldErrorCode)> {
+        vec![
+            ("slot", None, "/slot", FieldErrorCode::MissingField),
+            ("slot", Some(json!("100")), "/slot", FieldErrorCode::InvalidType),
+            ("slot", Some(json!(10)), "/slot", FieldErrorCode::SlotInPast),
+            ("chain_id", None, "/chain_id", FieldErrorCode::MissingField),
+            ("chain_id", Some(json!(1)), "/chain_id", FieldErrorCode::ChainIdMismatch),
+            ("txs", None, "/txs", FieldErrorCode::MissingField),
+            ("txs", Some(json!("0x00")), "/txs", FieldErrorCode::InvalidType),
+            ("txs", Some(json!([])), "/txs", FieldErrorCode::EmptyTransactions),
+            ("txs", Some(json!([1])), "/txs/0", FieldErrorCode::InvalidType),
+            ("txs", Some(json!(["0xzz"])), "/txs/0", FieldErrorCode::InvalidHex),
+            ("txs", Some(json!(["0x00"])), "/txs/0", FieldErrorCode::InvalidTransaction),
+            ("signature", None, "/signature", FieldErrorCode::MissingField),
+            ("signature", Some(json!("0xzz")), "/signature", FieldErrorCode::InvalidHex),
+            ("signature", Some(json!("0x11")), "/signature", FieldErrorCode::InvalidSignature),
+            ("sender", None, "/sender", FieldErrorCode::MissingField),
+            ("sender", Some(json!(false)), "/sender", FieldErrorCode::InvalidType),
+            ("sender", Some(json!("0x1234")), "/sender", FieldErrorCode::InvalidAddress),
+            ("priority", Some(json!("urgent")), "/priority", FieldErrorCode::InvalidRequest),
+        ]
+    }
+
+    #[test]
+    fn test_valid_request() {
+        let request = validate_preconf_request(&valid_fields(), CHAIN_ID, Some(99)).unwrap();
+        assert_eq!(request.slot, 100);
+        assert_eq!(request.txs.len(), 1);
+    }
+
+    #[test]
+    fn test_non_object_body() {
+        let errors = validate_preconf_request(&json!([]), CHAIN_ID, None).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].pointer, "");
+        assert_eq!(errors[0].code, FieldErrorCode::InvalidType);
+    }
+
+    #[test]
+    fn test_atomic_partial_request() {
+        let mut fields = valid_fields();
+        fields["atomic"] = json!(true);
+        fields["partial"] = json!(true);
+        let errors = validate_preconf_request(&fields, CHAIN_ID, None).unwrap_err();
+        assert!(errors.iter().any(
+            |error| error.pointer == "/partial" && error.code == FieldErrorCode::InvalidRequest
+        ));
+    }
+
+    proptest! {
+        #[test]
+        fn test_invalid_field_maps_to_stable_code(
+            case in 0..invalid_cases().len(),
+            current_slot in 10u64..100,
+        ) {
+            let (name, value, pointer, code) = invalid_cases().swap_remove(case);
+
+            let mut body = valid_fields();
+            match value {
+                Some(value) => body[name] = value,
+                None => {
+                    body.as_object_mut().unwrap().remove(name);
+                }
+            }
+
+            // The rest of the body is valid, so the broken field is the only one reported
+            let errors = validate_preconf_request(&body, CHAIN_ID, Some(current_slot)).unwrap_err();
+            prop_assert!(
+                errors.iter().all(|err| err.pointer == pointer && err.code == code),
+                "expected {} at {}, got {:?}", code.as_str(), pointer, errors
+            );
+        }
+    }
+}
@@ -1,9 +1,44 @@
-use alloy::primitives::b256;
-use ethereum_consensus::deneb::{compute_fork_data_root, Root};
+use alloy::{eips::eip4844::MAX_BLOBS_PER_BLOCK, primitives::b256};
+use ethereum_consensus::{
+    deneb::{compute_fork_data_root, Root},
+    phase0::mainnet::SLOTS_PER_EPOCH,
+    Fork,
+};
 use std::{str::FromStr, time::Duration};
 /// Default slot time duration in seconds.
 pub const DEFAULT_SLOT_TIME_SECONDS: u64 = 12;
 
+/// Slot time of Gnosis Chain in seconds.
+pub const GNOSIS_SLOT_TIME_SECONDS: u64 = 5;
+
+/// Slots in an epoch of Gnosis Chain.
+pub const GNOSIS_SLOTS_PER_EPOCH: u64 = 16;
+
+/// Blobs per block of Gnosis Chain.
+pub const GNOSIS_MAX_BLOBS_PER_BLOCK: usize = 2;
+
+/// First epoch of the Electra fork on Mainnet, Holesky and Gnosis Chain.
+pub const MAINNET_ELECTRA_FORK_EPOCH: u64 = 364_032;
+pub const HOLESKY_ELECTRA_FORK_EPOCH: u64 = 115_968;
+pub const GNOSIS_ELECTRA_FORK_EPOCH: u64 = 1_337_856;
+
+/// Forks of the relay bids and payloads the sidecar can handle.
+pub const SUPPORTED_FORKS: [Fork; 2] = [Fork::Deneb, Fork::Electra];
+
+/// Parse a comma-separated list of forks among the [SUPPORTED_FORKS] (e.g. "deneb,electra").
+pub fn parse_forks(s: &str) -> Result<Vec<Fork>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            SUPPORTED_FORKS
+                .into_iter()
+                .find(|fork| fork.to_string() == name)
+                .ok_or_else(|| format!("unsupported fork {name}"))
+        })
+        .collect()
+}
+
 /// Default commitment deadline duration.
 pub const DEFAULT_COMMITMENT_DEADLINE_MILLIS: u64 = 8_000;
 
@@ -11,6 +46,7 @@ pub const HOLEKSY_CHAIN_ID: u64 = 17000;
 pub const KURTOSIS_CHAIN_ID: u64 = 3151908;
 pub const MAINNET_CHAIN_ID: u64 = 1;
 pub const HELDER_CHAIN_ID: u64 = 7014190335;
+pub const GNOSIS_CHAIN_ID: u64 = 100;
 
 /// Builder domain for signing messages on Holesky, Kurtosis and Mainnet.
 /// ToDo: Add Mainnet domain
@@ -23,6 +59,10 @@ const BUILDER_DOMAIN_KURTOSIS: [u8; 32] =
 const BUILDER_DOMAIN_HELDER: [u8; 32] =
     b256!("0000000194c41af484fff7964969e0bdd922f82dff0f4be87a60d0664cc9d1ff").0;
 
+/// The domain type of the application builder messages, computed for the chains without a
+/// builder domain above.
+const BUILDER_DOMAIN_MASK: [u8; 4] = [0, 0, 0, 1];
+
 /// The domain mask for signing commit-boost messages.
 pub const COMMIT_BOOST_DOMAIN_MASK: [u8; 4] = [109, 109, 111, 67];
 
@@ -35,18 +75,21 @@ pub struct ChainConfig {
     pub commitment_deadline: u64,
     /// customized slot time
     pub slot_time: u64,
+    /// slots in an epoch
+    pub slots_per_epoch: u64,
     /// chain id
     pub id: u64,
+    /// blobs allowed in a block
+    pub max_blobs_per_block: usize,
+    /// first epoch of the Electra fork, none when it isn't scheduled
+    pub electra_fork_epoch: Option<u64>,
+    /// forks of the relay bids and payloads we accept
+    pub accepted_forks: Vec<Fork>,
 }
 
 impl Default for ChainConfig {
     fn default() -> Self {
-        Self {
-            chain: Chain::Holesky,
-            commitment_deadline: DEFAULT_COMMITMENT_DEADLINE_MILLIS,
-            slot_time: DEFAULT_SLOT_TIME_SECONDS,
-            id: HOLEKSY_CHAIN_ID,
-        }
+        Self::preset(Chain::Holesky)
     }
 }
 
@@ -57,7 +100,8 @@ pub enum Chain {
     Mainnet,
     Holesky,
     Kurtosis,
-    Helder
+    Helder,
+    Gnosis,
 }
 
 impl Chain {
@@ -68,9 +112,17 @@ impl Chain {
             Chain::Holesky => "holesky",
             Chain::Kurtosis => "kurtosis",
             Chain::Helder => "helder",
+            Chain::Gnosis => "gnosis",
         }
     }
 
+    /// Parse a chain from its name, see [Chain::get_name].
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Chain::Mainnet, Chain::Holesky, Chain::Kurtosis, Chain::Helder, Chain::Gnosis]
+            .into_iter()
+            .find(|chain| chain.get_name() == name)
+    }
+
     // get fork version of chain
     pub fn get_fork_version(&self) -> [u8; 4] {
         match self {
@@ -78,11 +130,83 @@ impl Chain {
             Chain::Holesky => [1, 1, 112, 0],
             Chain::Kurtosis => [16, 0, 0, 56],
             Chain::Helder => [16, 0, 0, 0],
+            Chain::Gnosis => [0, 0, 0, 100],
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        match self {
+            Chain::Mainnet => MAINNET_CHAIN_ID,
+            Chain::Holesky => HOLEKSY_CHAIN_ID,
+            Chain::Kurtosis => KURTOSIS_CHAIN_ID,
+            Chain::Helder => HELDER_CHAIN_ID,
+            Chain::Gnosis => GNOSIS_CHAIN_ID,
+        }
+    }
+
+    /// Seconds per slot of the beacon chain.
+    pub fn slot_time(&self) -> u64 {
+        match self {
+            Chain::Gnosis => GNOSIS_SLOT_TIME_SECONDS,
+            _ => DEFAULT_SLOT_TIME_SECONDS,
+        }
+    }
+
+    /// Slots per epoch of the beacon chain.
+    pub fn slots_per_epoch(&self) -> u64 {
+        match self {
+            Chain::Gnosis => GNOSIS_SLOTS_PER_EPOCH,
+            _ => SLOTS_PER_EPOCH,
+        }
+    }
+
+    /// Blobs allowed in a block of the chain.
+    pub fn max_blobs_per_block(&self) -> usize {
+        match self {
+            Chain::Gnosis => GNOSIS_MAX_BLOBS_PER_BLOCK,
+            _ => MAX_BLOBS_PER_BLOCK,
         }
     }
+
+    /// First epoch of the Electra fork of the chain, none when unknown.
+    pub fn electra_fork_epoch(&self) -> Option<u64> {
+        match self {
+            Chain::Mainnet => Some(MAINNET_ELECTRA_FORK_EPOCH),
+            Chain::Holesky => Some(HOLESKY_ELECTRA_FORK_EPOCH),
+            Chain::Gnosis => Some(GNOSIS_ELECTRA_FORK_EPOCH),
+            Chain::Kurtosis | Chain::Helder => None,
+        }
+    }
+
+    /// Commitment deadline in milliseconds, two thirds into the slot as on a 12 seconds one.
+    pub fn commitment_deadline(&self) -> u64 {
+        self.slot_time() * DEFAULT_COMMITMENT_DEADLINE_MILLIS / DEFAULT_SLOT_TIME_SECONDS
+    }
 }
 
 impl ChainConfig {
+    /// The configuration of `chain`, with its slot timing.
+    pub fn preset(chain: Chain) -> Self {
+        Self {
+            commitment_deadline: chain.commitment_deadline(),
+            slot_time: chain.slot_time(),
+            slots_per_epoch: chain.slots_per_epoch(),
+            max_blobs_per_block: chain.max_blobs_per_block(),
+            electra_fork_epoch: chain.electra_fork_epoch(),
+            accepted_forks: SUPPORTED_FORKS.to_vec(),
+            id: chain.id(),
+            chain,
+        }
+    }
+
+    /// The fork of the blocks of `slot`.
+    pub fn fork_at(&self, slot: u64) -> Fork {
+        match self.electra_fork_epoch {
+            Some(epoch) if self.epoch_of(slot) >= epoch => Fork::Electra,
+            _ => Fork::Deneb,
+        }
+    }
+
     /// get duration of commitment deadline.
     pub fn get_commitment_deadline_duration(&self) -> Duration {
         Duration::from_millis(self.commitment_deadline)
@@ -96,6 +220,16 @@ impl ChainConfig {
         self.slot_time
     }
 
+    /// The epoch of `slot`.
+    pub fn epoch_of(&self, slot: u64) -> u64 {
+        slot / self.slots_per_epoch
+    }
+
+    /// The first slot of `epoch`.
+    pub fn epoch_start_slot(&self, epoch: u64) -> u64 {
+        epoch * self.slots_per_epoch
+    }
+
     /// Get the domain for signing messages on the given chain.
     pub fn builder_domain(&self) -> [u8; 32] {
         match self.chain {
@@ -103,6 +237,7 @@ impl ChainConfig {
             Chain::Holesky => BUILDER_DOMAIN_HOLESKY,
             Chain::Kurtosis => BUILDER_DOMAIN_KURTOSIS,
             Chain::Helder => BUILDER_DOMAIN_HELDER,
+            Chain::Gnosis => self.compute_domain_from_mask(BUILDER_DOMAIN_MASK),
         }
     }
 
@@ -177,3 +312,38 @@ impl From<Vec<u64>> for ValidatorIndexes {
         Self(vec)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ethereum_consensus::Fork;
+
+    use super::{parse_forks, Chain, ChainConfig, GNOSIS_CHAIN_ID, GNOSIS_ELECTRA_FORK_EPOCH};
+
+    #[test]
+    fn test_gnosis_preset() {
+        let chain = ChainConfig::preset(Chain::from_name("gnosis").unwrap());
+        assert_eq!(chain.id, GNOSIS_CHAIN_ID);
+        assert_eq!((chain.slot_time, chain.slots_per_epoch), (5, 16));
+        assert_eq!(chain.max_blobs_per_block, 2);
+        assert_eq!(chain.commitment_deadline, 3_333);
+        assert_eq!(chain.epoch_of(47), 2);
+        assert_eq!(chain.epoch_start_slot(3), 48);
+
+        // Computed as the builder domains of the other chains
+        let holesky = ChainConfig::preset(Chain::Holesky);
+        assert_eq!(holesky.compute_domain_from_mask([0, 0, 0, 1]), holesky.builder_domain());
+        assert_eq!(chain.builder_domain()[..4], [0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_forks() {
+        let chain = ChainConfig::preset(Chain::Gnosis);
+        let electra_slot = GNOSIS_ELECTRA_FORK_EPOCH * chain.slots_per_epoch;
+        assert_eq!(chain.fork_at(electra_slot - 1), Fork::Deneb);
+        assert_eq!(chain.fork_at(electra_slot), Fork::Electra);
+        assert_eq!(ChainConfig::preset(Chain::Kurtosis).fork_at(electra_slot), Fork::Deneb);
+
+        assert_eq!(parse_forks("deneb, electra"), Ok(vec![Fork::Deneb, Fork::Electra]));
+        assert!(parse_forks("capella").is_err());
+    }
+}
@@ -1,7 +1,10 @@
 use std::num::NonZero;
 
+use alloy::eips::eip4844::MAX_BLOBS_PER_BLOCK;
 use clap::Parser;
 
+use crate::utils::score_cache::EvictionPolicy;
+
 /// Default max commitments to accept per block.
 pub const DEFAULT_MAX_COMMITMENTS: usize = 128;
 
@@ -14,6 +17,9 @@ pub const DEFAULT_MIN_PROFIT: u64 = 2_000_000_000; // 2 Gwei
 /// Default max account states size.
 pub const DEFAULT_MAX_ACCOUNT_STATES_SIZE: u64 = 1_024;
 
+/// Default time to live of cached account states, zero disables expiry.
+pub const DEFAULT_ACCOUNT_STATES_TTL_SECS: u64 = 0;
+
 /// Default gas limit for the sidecar.
 pub const DEFAULT_GAS_LIMIT: u64 = 30_000_000;
 
@@ -45,6 +51,31 @@ pub struct LimitOptions {
         default_value_t = LimitOptions::default().max_account_states_size,
     )]
     pub max_account_states_size: NonZero<usize>,
+    /// Eviction policy of the account states cache once it's full
+    #[clap(
+        long,
+        env = "ACCOUNT_STATES_EVICTION_POLICY",
+        value_enum,
+        default_value_t = LimitOptions::default().account_states_eviction_policy,
+    )]
+    pub account_states_eviction_policy: EvictionPolicy,
+    /// Seconds after which an account state that hasn't been accessed expires, 0 to disable
+    #[clap(
+        long,
+        env = "ACCOUNT_STATES_TTL_SECS",
+        default_value_t = LimitOptions::default().account_states_ttl_secs,
+    )]
+    pub account_states_ttl_secs: u64,
+    /// Max ratio between the declared gas limit of a committed transaction and its gas
+    /// estimated with an access list. When set, commitments are priced on their estimated gas
+    #[clap(long, env = "MAX_GAS_LIMIT_RATIO")]
+    pub max_gas_limit_ratio: Option<NonZero<u64>>,
+    /// Refuse the deployments of contracts at an address that already has code or a nonce
+    #[clap(long, env = "CHECK_DEPLOYMENT_COLLISIONS", default_value_t = false)]
+    pub check_deployment_collisions: bool,
+    /// Max blobs committed per slot, the blob limit of the chain
+    #[clap(long, env = "MAX_BLOBS_PER_BLOCK", default_value_t = MAX_BLOBS_PER_BLOCK)]
+    pub max_blobs_per_block: usize,
 }
 
 impl Default for LimitOptions {
@@ -54,6 +85,11 @@ impl Default for LimitOptions {
                 .expect("Valid non-zero"),
             min_inclusion_profit: DEFAULT_MIN_PROFIT,
             max_account_states_size: NonZero::new(1_024).expect("Valid non-zero"),
+            account_states_eviction_policy: EvictionPolicy::default(),
+            account_states_ttl_secs: DEFAULT_ACCOUNT_STATES_TTL_SECS,
+            max_gas_limit_ratio: None,
+            check_deployment_collisions: false,
+            max_blobs_per_block: MAX_BLOBS_PER_BLOCK,
         }
     }
 }
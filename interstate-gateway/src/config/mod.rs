@@ -1,15 +1,73 @@
-use group_config::{HOLEKSY_CHAIN_ID, KURTOSIS_CHAIN_ID, MAINNET_CHAIN_ID, HELDER_CHAIN_ID};
 use reqwest::Url;
 
 use rand::RngCore;
-use std::{collections::HashMap, path::PathBuf, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{AddrParseError, IpAddr},
+    num::NonZero,
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
 
-use alloy::primitives::Address;
+use alloy::{hex::FromHexError, primitives::Address, signers::local::PrivateKeySigner};
+use alloy_rpc_types_engine::JwtSecret;
 use blst::min_pk::SecretKey as BLSSecretKey;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+
+use crate::{
+    commitment::{
+        auth::{parse_api_keys, ApiAuth},
+        confidential::ConfidentialKey,
+        rate_limit::RequestRateLimit,
+        receipt::{ReceiptGossipMode, DEFAULT_RECEIPT_GOSSIP_QUEUE},
+        replica::InstanceRole,
+        request::SenderPolicy,
+    },
+    constraints::{
+        auth::RelayAuth,
+        bid_checks::{BidPolicy, MAX_EXTRA_DATA_BYTES},
+        compression::RelayCompression,
+        multi_relay::DEFAULT_RELAY_TIMEOUT_MS,
+        rate_limit::{DEFAULT_RELAY_RATE_LIMIT_BURST, DEFAULT_RELAY_RATE_LIMIT_PER_SEC},
+        value::DEFAULT_FALLBACK_BID_VALUE_WEI,
+    },
+    delegation::{
+        limiter::{DEFAULT_MAX_CONCURRENT_SIGNINGS, DEFAULT_SIGNING_QUEUE_TIMEOUT_MILLIS},
+        signer::{DirkConfig, SignerType},
+    },
+    state::{
+        budget::DEFAULT_DEADLINE_STAGE_BUDGET_MILLIS,
+        mempool::{
+            InclusionListPolicy, DEFAULT_INCLUSION_LIST_MAX_GAS, DEFAULT_INCLUSION_LIST_MAX_TXS,
+            DEFAULT_INCLUSION_LIST_MIN_PRIORITY_FEE, ReplacementPolicy,
+        },
+        reservations::DEFAULT_MAX_RESERVED_GAS,
+        slot_clock::DEFAULT_DRIFT_THRESHOLD_MILLIS,
+        sync::DEFAULT_MAX_EL_LAG_BLOCKS,
+        DEFAULT_MAX_PENDING_BLOB_BYTES,
+    },
+    utils::{
+        breaker::{
+            BreakerPolicy, DEFAULT_BREAKER_ERROR_RATE, DEFAULT_BREAKER_MIN_CALLS,
+            DEFAULT_BREAKER_OPEN_MILLIS, DEFAULT_BREAKER_WINDOW_MILLIS,
+        },
+        http::{default_user_agent, OutboundHeaders},
+        retry::{
+            RetryPolicy, DEFAULT_RETRY_ATTEMPT_TIMEOUT_MILLIS, DEFAULT_RETRY_INITIAL_BACKOFF_MILLIS,
+            DEFAULT_RETRY_MAX_ATTEMPTS, DEFAULT_RETRY_MAX_BACKOFF_MILLIS,
+        },
+        score_cache::EvictionPolicy,
+        url::normalize_base_url,
+    },
+};
 
 pub mod group_config;
 pub mod limits;
+pub mod sources;
+pub mod validation;
 pub use group_config::{Chain, ChainConfig, ValidatorIndexes};
+use limits::DEFAULT_ACCOUNT_STATES_TTL_SECS;
 
 /// Default port for the commitment server exposed by the sidecar.
 pub const DEFAULT_COMMITMENT_PORT: u16 = 8000;
@@ -32,6 +90,24 @@ pub struct Config {
     pub cb_url: Url,
     /// relay url
     pub relay_url: Url,
+    /// Authentication applied to the requests sent to the relay
+    pub relay_auth: RelayAuth,
+    /// Sustained rate of requests sent to the relay, per second
+    pub relay_rate_limit_per_sec: u32,
+    /// Number of requests that can be sent to the relay in a burst
+    pub relay_rate_limit_burst: u32,
+    /// Other relays the constraints are submitted to along with the relay
+    pub extra_relay_urls: Vec<Url>,
+    /// Authentication applied to the requests sent to each of the extra relays, in order
+    pub extra_relay_auth: Vec<RelayAuth>,
+    /// Number of relays that must accept the constraints of a slot for their submission to
+    /// succeed
+    pub relay_quorum: usize,
+    /// Time each relay is given to answer a constraints submission, in ms
+    pub relay_timeout_ms: u64,
+    /// Content encoding of the constraints submissions, `none` or `gzip`. Relays rejecting it
+    /// are sent uncompressed bodies
+    pub relay_compression: RelayCompression,
     /// The router url
     pub sidecar_info_sender_url: Url,
     /// URL for the beacon client API URL
@@ -48,10 +124,19 @@ pub struct Config {
     pub fee_recipient: Address,
     /// Local builder bls private key for signing fallback payloads.
     pub builder_bls_private_key: BLSSecretKey,
+    /// Backend holding the delegatee keys the constraints are signed with
+    pub signer_type: SignerType,
     pub keystore_secrets_path: PathBuf,
     /// Path to the keystores folder.
     pub keystore_pubkeys_path: PathBuf,
-    /// Path to the delegations file.
+    /// Whether the keystores are reloaded when their files or passwords change, so that keys
+    /// are rotated without restarting
+    pub keystore_reload: bool,
+    /// Local file of signed delegations and revocations, served along with the ones of the
+    /// relay by the delegations lookup
+    pub delegations_path: Option<PathBuf>,
+    /// Delegatee keys we are expected to sign with, checked against the keystore at startup
+    pub delegatee_pubkeys: Vec<BlsPublicKey>,
     /// Gateway contract address
     pub gateway_contract: Address,
     /// Web3Signer settings
@@ -59,6 +144,151 @@ pub struct Config {
     pub ca_cert_path: String,
     pub combined_pem_path: String,
     pub commit_boost_signer_url: String,
+    /// Dirk instance signing the constraints, when the signer is Dirk
+    pub dirk: Option<DirkConfig>,
+    /// Max number of in-flight signing requests per remote signer backend
+    pub max_concurrent_signings: usize,
+    /// Max time in milliseconds a signing request waits for a free slot
+    pub signing_queue_timeout_ms: u64,
+    /// Host clock drift in milliseconds above which the slot clock is corrected
+    pub slot_drift_threshold_ms: u64,
+    /// Number of blocks the execution client can lag behind before commitments are refused
+    pub max_el_lag_blocks: u64,
+    /// Lease file shared with the other instances of the sidecar, to hand over without
+    /// downtime on deploys. Listeners are bound with SO_REUSEPORT when set, once leading, and
+    /// the pending constraints are handed over through the constraints journal
+    pub instance_lease_path: Option<PathBuf>,
+    /// Log of the commitment requests shared with the other instances, which the leader
+    /// processes in its order. Requests are processed by the instance receiving them when not
+    /// set
+    pub request_log_path: Option<PathBuf>,
+    /// File the per-epoch revenue report is exported to as CSV, if set
+    pub revenue_report_path: Option<PathBuf>,
+    /// ECDSA key attesting the price quotes. Quotes are disabled when not set
+    pub quote_signer: Option<PrivateKeySigner>,
+    /// Time in milliseconds a price quote is honored for, one slot by default
+    pub quote_ttl_ms: u64,
+    /// Consensus key of the commit-boost signer whose proxy ECDSA key signs the commitment
+    /// receipts. Receipts are signed with the quote signing key when not set
+    pub receipt_proxy_delegator: Option<String>,
+    /// Push the inclusion receipts to the collector, whole or as digests only. Not gossiped
+    /// when not set
+    pub receipt_gossip: Option<ReceiptGossipMode>,
+    /// Number of receipts waiting to be pushed to the collector, past which they are dropped
+    pub receipt_gossip_queue: usize,
+    /// Webhook notified with the inclusion report of the slots we committed constraints in
+    pub inclusion_webhook_url: Option<Url>,
+    /// Max ratio between the declared and the estimated gas of committed transactions. Gas
+    /// estimation is disabled when not set
+    pub max_gas_limit_ratio: Option<NonZero<u64>>,
+    /// Which account states are evicted first once their cache is full
+    pub account_states_eviction_policy: EvictionPolicy,
+    /// Seconds after which a cached account state that wasn't accessed expires, 0 to disable
+    pub account_states_ttl_secs: u64,
+    /// Policy forcing long pending transactions in our own proposals. Disabled when not set
+    pub inclusion_list: Option<InclusionListPolicy>,
+    /// Role of this instance, replicas serve the query endpoints without signing
+    pub role: InstanceRole,
+    /// Commitments API of the primary, replicas forward the write endpoints to it
+    pub primary_url: Option<Url>,
+    /// Store the primary shares its state through, read by the replicas
+    pub shared_store_path: Option<PathBuf>,
+    /// Write-ahead log of the constraints submissions, resumed after a restart. Submissions
+    /// in flight during a crash are lost when not set
+    pub submission_log_path: Option<PathBuf>,
+    /// Log of the audit trail of our proposals, kept across restarts. The trail is only kept
+    /// in memory when not set
+    pub audit_log_path: Option<PathBuf>,
+    /// Journal of the pending constraints, restored after a restart. Constraints accepted
+    /// before a crash are lost when not set. Shared by the instances of the sidecar
+    pub constraints_journal_path: Option<PathBuf>,
+    /// Archive of the head events, the beacon node responses and the commitment deadlines,
+    /// replayed with the `replay` command to debug the slot handling. Nothing is archived
+    /// when not set
+    pub slot_archive_path: Option<PathBuf>,
+    /// Registry of the delegations synced from the relay, kept across restarts. The
+    /// registry is only kept in memory when not set
+    pub delegation_registry_path: Option<PathBuf>,
+    /// Whether the persisted stores are backed up before they are migrated at startup, to
+    /// the schema of this sidecar
+    pub schema_backup: bool,
+    /// User agent and operator id sent to the relays, the remote signers and the beacon node
+    pub outbound_headers: OutboundHeaders,
+    /// Time budget in milliseconds of the deadline handler. Defaults to the time left until
+    /// the start of the slot
+    pub deadline_budget_ms: Option<u64>,
+    /// Time in milliseconds a deadline stage may take before the optional stages are skipped
+    pub deadline_stage_budget_ms: u64,
+    /// Time in milliseconds before the commitment deadline at which the constraints of the
+    /// slot are submitted if every relay is degraded. Never submitted early when not set
+    pub early_deadline_ms: Option<u64>,
+    /// Whether the relays accept several constraints submissions for a slot. The constraints
+    /// committed after an early submission are then submitted at the deadline, instead of
+    /// being refused
+    pub relay_incremental_submissions: bool,
+    /// JSON list of the peer gateways and their validators, to forward the requests for
+    /// their slots to. Requests are never forwarded when not set
+    pub peer_registry_path: Option<PathBuf>,
+    /// Secret shared by the peer gateways, authenticating the requests they forward
+    pub peer_forward_secret: Option<String>,
+    /// X25519 key clients encrypt the transactions of confidential requests to. Confidential
+    /// requests are refused when not set
+    pub confidential_key: Option<ConfidentialKey>,
+    /// Indexes of our validators, whose upcoming proposals are checked for usable
+    /// delegations. The delegation health check is disabled when not set
+    pub validator_indexes: Option<ValidatorIndexes>,
+    /// How the calls to the beacon node, the execution client and the relay are retried
+    pub retry: RetryPolicy,
+    /// When the calls to the relay, the signer, the beacon node and the execution client fail
+    /// fast after too many errors
+    pub breaker: BreakerPolicy,
+    /// Who may request commitments for transactions they didn't sign
+    pub sender_policy: SenderPolicy,
+    /// API keys and JWT secret the commitment requests are authenticated with, anyone may
+    /// request commitments when neither is set
+    pub api_auth: ApiAuth,
+    /// Commitment requests allowed per recovered sender, not limited when not set
+    pub sender_rate_limit: Option<RequestRateLimit>,
+    /// Commitment requests allowed per client IP, not limited when not set
+    pub ip_rate_limit: Option<RequestRateLimit>,
+    /// Peers whose `X-Forwarded-For` header gives the client IP, such as the replicas
+    /// forwarding the requests to this instance
+    pub trusted_proxies: HashSet<IpAddr>,
+    /// Rollup sequencers allowed to reserve capacity in each of our proposal slots, none
+    /// disables the reservations
+    pub reservation_sequencers: HashSet<Address>,
+    /// Max gas reserved in a slot across the reservations
+    pub max_reserved_gas: u64,
+    /// Whether payloads are built locally when no relay delivers one for our slot. Always
+    /// off without the `fallback-builder` feature
+    pub fallback_builder: bool,
+    /// Whether a fallback payload is built for our proposal slots without constraints too, so
+    /// that the slot isn't missed when the relays fail
+    pub fallback_always_build: bool,
+    /// What to do with requests whose transactions a pending transaction of the same sender
+    /// and nonce, paying more, would replace. The mempool isn't checked when off
+    pub replacement_policy: ReplacementPolicy,
+    /// Endpoint pricing the fallback blocks, see
+    /// [HttpValueEstimator](crate::constraints::value::HttpValueEstimator)
+    pub fallback_value_estimator_url: Option<Url>,
+    /// Value of the fallback bids when no estimator is set or it is unavailable, in wei
+    pub fallback_bid_value_wei: u128,
+    /// Extra data and fee recipients allowed in the headers of the relay bids, the bids
+    /// violating it are replaced by the fallback payload
+    pub bid_policy: BidPolicy,
+    /// Whether the relay bids must prove the inclusion of the constraints submitted for their
+    /// slot, the others being replaced by the fallback payload. Only for relays all supporting
+    /// the constraints API, vanilla relays returning no proofs
+    pub require_inclusion_proofs: bool,
+    /// Max bytes of blob sidecars held for the pending constraints, requests whose blobs
+    /// don't fit are refused
+    pub max_pending_blob_bytes: usize,
+    /// Whether the signatures of the constraints are verified before they are submitted at
+    /// the deadline, dropping the ones a faulty signer got wrong
+    pub verify_constraints: bool,
+    /// Whether the contracts deployed by committed transactions are checked not to collide
+    /// with an existing account, at the cost of a state lookup per deployment
+    pub check_deployment_collisions: bool,
 }
 
 impl Default for Config {
@@ -69,6 +299,14 @@ impl Default for Config {
             metrics_port: DEFAULT_METRICS_PORT,
             cb_url: "http://localhost:3030".parse().expect("Valid URL"),
             relay_url: "http://localhost:3040".parse().expect("Valid URL"),
+            relay_auth: RelayAuth::None,
+            relay_rate_limit_per_sec: DEFAULT_RELAY_RATE_LIMIT_PER_SEC,
+            relay_rate_limit_burst: DEFAULT_RELAY_RATE_LIMIT_BURST,
+            extra_relay_urls: Vec::new(),
+            extra_relay_auth: Vec::new(),
+            relay_quorum: 1,
+            relay_timeout_ms: DEFAULT_RELAY_TIMEOUT_MS,
+            relay_compression: RelayCompression::default(),
             sidecar_info_sender_url: "http://localhost:8000".parse().expect("Valid URL"),
             beacon_api_url: "http://localhost:5052".parse().expect("Valid URL"),
             execution_api_url: "http://localhost:8545".parse().expect("Valid URL"),
@@ -77,49 +315,139 @@ impl Default for Config {
             jwt_hex: String::new(),
             fee_recipient: Address::ZERO,
             builder_bls_private_key: random_bls_secret(),
+            signer_type: SignerType::default(),
+            delegations_path: None,
+            delegatee_pubkeys: Vec::new(),
             gateway_contract: Address::from_str("0x8aC112a5540f441cC9beBcC647041A6E0D595B94")
                 .unwrap(),
             web3signer_url: String::new(),
             ca_cert_path: String::new(),
             combined_pem_path: String::new(),
             commit_boost_signer_url: String::new(),
+            dirk: None,
+            max_concurrent_signings: DEFAULT_MAX_CONCURRENT_SIGNINGS,
+            signing_queue_timeout_ms: DEFAULT_SIGNING_QUEUE_TIMEOUT_MILLIS,
+            slot_drift_threshold_ms: DEFAULT_DRIFT_THRESHOLD_MILLIS,
+            max_el_lag_blocks: DEFAULT_MAX_EL_LAG_BLOCKS,
+            instance_lease_path: None,
+            request_log_path: None,
+            revenue_report_path: None,
+            quote_signer: None,
+            quote_ttl_ms: ChainConfig::default().slot_time * 1000,
+            receipt_proxy_delegator: None,
+            receipt_gossip: None,
+            receipt_gossip_queue: DEFAULT_RECEIPT_GOSSIP_QUEUE,
+            inclusion_webhook_url: None,
+            max_gas_limit_ratio: None,
+            account_states_eviction_policy: EvictionPolicy::default(),
+            account_states_ttl_secs: DEFAULT_ACCOUNT_STATES_TTL_SECS,
+            inclusion_list: None,
+            role: InstanceRole::Primary,
+            primary_url: None,
+            shared_store_path: None,
+            submission_log_path: None,
+            audit_log_path: None,
+            constraints_journal_path: None,
+            slot_archive_path: None,
+            delegation_registry_path: None,
+            schema_backup: true,
+            outbound_headers: OutboundHeaders::default(),
+            deadline_budget_ms: None,
+            deadline_stage_budget_ms: DEFAULT_DEADLINE_STAGE_BUDGET_MILLIS,
+            early_deadline_ms: None,
+            relay_incremental_submissions: false,
+            peer_registry_path: None,
+            peer_forward_secret: None,
+            confidential_key: None,
+            validator_indexes: None,
+            retry: RetryPolicy::default(),
+            breaker: BreakerPolicy::default(),
+            sender_policy: SenderPolicy::default(),
+            api_auth: ApiAuth::default(),
+            sender_rate_limit: None,
+            ip_rate_limit: None,
+            trusted_proxies: HashSet::new(),
+            reservation_sequencers: HashSet::new(),
+            max_reserved_gas: DEFAULT_MAX_RESERVED_GAS,
+            fallback_builder: cfg!(feature = "fallback-builder"),
+            fallback_always_build: false,
+            replacement_policy: ReplacementPolicy::default(),
+            fallback_value_estimator_url: None,
+            fallback_bid_value_wei: DEFAULT_FALLBACK_BID_VALUE_WEI,
+            bid_policy: BidPolicy::default(),
+            require_inclusion_proofs: false,
+            max_pending_blob_bytes: DEFAULT_MAX_PENDING_BLOB_BYTES,
+            verify_constraints: false,
+            check_deployment_collisions: false,
             keystore_secrets_path: PathBuf::from(
                 "/root/assigned_data/secrets",
             ),
             keystore_pubkeys_path: PathBuf::from(
                 "/root/assigned_data/keys",
             ),
+            keystore_reload: false,
         }
     }
 }
 
 impl Config {
     pub fn new(envs: HashMap<String, String>) -> Self {
-        let chain = ChainConfig {
-            chain: match envs["CHAIN"].clone().as_str() {
-                "kurtosis" => Chain::Kurtosis,
-                "mainnet" => Chain::Mainnet,
-                "holesky" => Chain::Holesky,
-                "helder" => Chain::Helder,
-                _ => Chain::Holesky,
-            },
-            commitment_deadline: envs["COMMITMENT_DEADLINE"].parse().unwrap(),
-            slot_time: envs["SLOT_TIME"].parse().unwrap(),
-            id: match envs["CHAIN"].clone().as_str() {
-                "kurtosis" => KURTOSIS_CHAIN_ID,
-                "mainnet" => MAINNET_CHAIN_ID,
-                "holesky" => HOLEKSY_CHAIN_ID,
-                "helder" => HELDER_CHAIN_ID,
-                _ => HOLEKSY_CHAIN_ID,
-            },
-        };
+        // The slot timing defaults to the one of the chain
+        let mut chain = ChainConfig::preset(Chain::from_name(&envs["CHAIN"]).unwrap_or(Chain::Holesky));
+        if let Some(deadline) = envs.get("COMMITMENT_DEADLINE") {
+            chain.commitment_deadline = deadline.parse().unwrap();
+        }
+        if let Some(slot_time) = envs.get("SLOT_TIME") {
+            chain.slot_time = slot_time.parse().unwrap();
+        }
+        if let Some(slots_per_epoch) = envs.get("SLOTS_PER_EPOCH") {
+            chain.slots_per_epoch = slots_per_epoch.parse().unwrap();
+        }
+        if let Some(max_blobs) = envs.get("MAX_BLOBS_PER_BLOCK") {
+            chain.max_blobs_per_block = max_blobs.parse().unwrap();
+        }
+        if let Some(epoch) = envs.get("ELECTRA_FORK_EPOCH") {
+            chain.electra_fork_epoch = Some(epoch.parse().unwrap());
+        }
+        if let Some(forks) = envs.get("ACCEPTED_FORKS") {
+            chain.accepted_forks = group_config::parse_forks(forks).expect("Valid accepted forks");
+        }
+        let slot_time_ms = chain.slot_time * 1000;
 
         Self {
             commitment_port: envs["COMMITMENT_PORT"].parse().unwrap(),
             metrics_port: envs["METRICS_PORT"].parse().unwrap(),
             builder_port: envs["BUILDER_PORT"].parse().unwrap(),
-            cb_url: envs["RELAY_URL"].parse().expect("Valid URL"),
-            relay_url: envs["RELAY_URL"].parse().expect("Valid URL"),
+            cb_url: relay_url(&envs),
+            relay_url: relay_url(&envs),
+            relay_auth: RelayAuth::from_envs(&envs),
+            relay_rate_limit_per_sec: envs
+                .get("RELAY_RATE_LIMIT_PER_SEC")
+                .map(|v| v.parse().expect("Valid relay rate limit"))
+                .unwrap_or(DEFAULT_RELAY_RATE_LIMIT_PER_SEC),
+            relay_rate_limit_burst: envs
+                .get("RELAY_RATE_LIMIT_BURST")
+                .map(|v| v.parse().expect("Valid relay rate limit burst"))
+                .unwrap_or(DEFAULT_RELAY_RATE_LIMIT_BURST),
+            extra_relay_urls: envs
+                .get("EXTRA_RELAY_URLS")
+                .map(|v| parse_relay_urls(v).unwrap_or_else(|err| panic!("Invalid EXTRA_RELAY_URLS: {err}")))
+                .unwrap_or_default(),
+            extra_relay_auth: (1..=extra_relays(&envs))
+                .map(|index| RelayAuth::from_extra_relay_envs(&envs, index))
+                .collect(),
+            relay_quorum: envs
+                .get("RELAY_QUORUM")
+                .map(|v| v.parse().expect("Valid relay quorum"))
+                .unwrap_or(1),
+            relay_timeout_ms: envs
+                .get("RELAY_TIMEOUT_MS")
+                .map(|v| v.parse().expect("Valid relay timeout"))
+                .unwrap_or(DEFAULT_RELAY_TIMEOUT_MS),
+            relay_compression: envs
+                .get("RELAY_COMPRESSION")
+                .map(|v| v.parse().expect("Valid relay compression"))
+                .unwrap_or_default(),
             sidecar_info_sender_url: "http://localhost:8000".parse().expect("Valid URL"),
             beacon_api_url: envs["BEACON_API_URL"].parse().expect("Valid URL"),
             execution_api_url: envs["EXECUTION_API_URL"].parse().expect("Valid URL"),
@@ -128,18 +456,350 @@ impl Config {
             jwt_hex: envs["JWT"].clone(),
             fee_recipient: Address::parse_checksummed(&envs["FEE_RECIPIENT"], None).unwrap(),
             builder_bls_private_key: random_bls_secret(),
+            signer_type: envs
+                .get("SIGNER_TYPE")
+                .map(|v| v.parse().expect("Valid signer type"))
+                .unwrap_or_default(),
+            delegations_path: envs.get("DELEGATIONS_PATH").map(PathBuf::from),
+            delegatee_pubkeys: envs
+                .get("DELEGATEE_PUBKEYS")
+                .map(|v| parse_bls_pubkeys(v).expect("Valid delegatee pubkeys"))
+                .unwrap_or_default(),
             gateway_contract: Address::from_str("0x8aC112a5540f441cC9beBcC647041A6E0D595B94")
             .unwrap(),
-            web3signer_url: "http://localhost:3030".parse().expect("Valid URL"),
+            web3signer_url: envs
+                .get("WEB3SIGNER_URL")
+                .cloned()
+                .unwrap_or_else(|| "http://localhost:3030".to_string()),
             ca_cert_path: String::new(),
             combined_pem_path: String::new(),
             commit_boost_signer_url: "http://localhost:3030".parse().expect("Valid URL"),
-            keystore_secrets_path: PathBuf::from(envs["KEYSTORE_SECRETS_PATH"].as_str()),
-            keystore_pubkeys_path: PathBuf::from(envs["KEYSTORE_PUBKEYS_PATH"].as_str()),
+            dirk: dirk_config(&envs),
+            max_concurrent_signings: envs
+                .get("MAX_CONCURRENT_SIGNINGS")
+                .map(|v| v.parse().expect("Valid max concurrent signings"))
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_SIGNINGS),
+            signing_queue_timeout_ms: envs
+                .get("SIGNING_QUEUE_TIMEOUT_MS")
+                .map(|v| v.parse().expect("Valid signing queue timeout"))
+                .unwrap_or(DEFAULT_SIGNING_QUEUE_TIMEOUT_MILLIS),
+            slot_drift_threshold_ms: envs
+                .get("SLOT_DRIFT_THRESHOLD_MS")
+                .map(|v| v.parse().expect("Valid slot drift threshold"))
+                .unwrap_or(DEFAULT_DRIFT_THRESHOLD_MILLIS),
+            max_el_lag_blocks: envs
+                .get("MAX_EL_LAG_BLOCKS")
+                .map(|v| v.parse().expect("Valid max EL lag"))
+                .unwrap_or(DEFAULT_MAX_EL_LAG_BLOCKS),
+            instance_lease_path: envs.get("INSTANCE_LEASE_PATH").map(PathBuf::from),
+            request_log_path: envs.get("REQUEST_LOG_PATH").map(PathBuf::from),
+            revenue_report_path: envs.get("REVENUE_REPORT_PATH").map(PathBuf::from),
+            quote_signer: envs
+                .get("QUOTE_SIGNING_KEY")
+                .map(|v| v.parse().expect("Valid quote signing key")),
+            quote_ttl_ms: envs
+                .get("QUOTE_TTL_MS")
+                .map(|v| v.parse().expect("Valid quote TTL"))
+                .unwrap_or(slot_time_ms),
+            receipt_proxy_delegator: envs.get("RECEIPT_PROXY_DELEGATOR").cloned(),
+            receipt_gossip: envs
+                .get("RECEIPT_GOSSIP")
+                .map(|v| v.parse().expect("Valid receipt gossip mode")),
+            receipt_gossip_queue: envs
+                .get("RECEIPT_GOSSIP_QUEUE")
+                .map(|v| v.parse().expect("Valid receipt gossip queue"))
+                .unwrap_or(DEFAULT_RECEIPT_GOSSIP_QUEUE),
+            inclusion_webhook_url: envs
+                .get("INCLUSION_WEBHOOK_URL")
+                .map(|v| v.parse().expect("Valid URL")),
+            max_gas_limit_ratio: envs
+                .get("MAX_GAS_LIMIT_RATIO")
+                .map(|v| v.parse().expect("Valid non-zero max gas limit ratio")),
+            account_states_eviction_policy: envs
+                .get("ACCOUNT_STATES_EVICTION_POLICY")
+                .map(|v| v.parse().expect("Valid account states eviction policy"))
+                .unwrap_or_default(),
+            account_states_ttl_secs: envs
+                .get("ACCOUNT_STATES_TTL_SECS")
+                .map(|v| v.parse().expect("Valid account states ttl"))
+                .unwrap_or(DEFAULT_ACCOUNT_STATES_TTL_SECS),
+            inclusion_list: inclusion_list(&envs),
+            role: envs
+                .get("INSTANCE_ROLE")
+                .map(|v| v.parse().expect("Valid instance role"))
+                .unwrap_or_default(),
+            primary_url: envs.get("PRIMARY_URL").map(|v| v.parse().expect("Valid URL")),
+            shared_store_path: envs.get("SHARED_STORE_PATH").map(PathBuf::from),
+            submission_log_path: envs.get("SUBMISSION_LOG_PATH").map(PathBuf::from),
+            audit_log_path: envs.get("AUDIT_LOG_PATH").map(PathBuf::from),
+            constraints_journal_path: envs.get("CONSTRAINTS_JOURNAL_PATH").map(PathBuf::from),
+            slot_archive_path: envs.get("SLOT_ARCHIVE_PATH").map(PathBuf::from),
+            delegation_registry_path: envs.get("DELEGATION_REGISTRY_PATH").map(PathBuf::from),
+            schema_backup: envs
+                .get("SCHEMA_BACKUP")
+                .map(|v| v.parse().expect("Valid schema backup flag"))
+                .unwrap_or(true),
+            outbound_headers: OutboundHeaders {
+                user_agent: envs.get("USER_AGENT").cloned().unwrap_or_else(default_user_agent),
+                operator_id: envs.get("OPERATOR_ID").cloned(),
+            },
+            deadline_budget_ms: envs
+                .get("DEADLINE_BUDGET_MS")
+                .map(|v| v.parse().expect("Valid deadline budget")),
+            deadline_stage_budget_ms: envs
+                .get("DEADLINE_STAGE_BUDGET_MS")
+                .map(|v| v.parse().expect("Valid deadline stage budget"))
+                .unwrap_or(DEFAULT_DEADLINE_STAGE_BUDGET_MILLIS),
+            early_deadline_ms: envs
+                .get("EARLY_DEADLINE_MS")
+                .map(|v| v.parse().expect("Valid early deadline")),
+            relay_incremental_submissions: envs
+                .get("RELAY_INCREMENTAL_SUBMISSIONS")
+                .map(|v| v.parse().expect("Valid relay incremental submissions flag"))
+                .unwrap_or_default(),
+            peer_registry_path: envs.get("PEER_REGISTRY_PATH").map(PathBuf::from),
+            peer_forward_secret: envs.get("PEER_FORWARD_SECRET").cloned(),
+            confidential_key: envs
+                .get("CONFIDENTIAL_KEY")
+                .map(|v| v.parse().expect("Valid confidential key")),
+            validator_indexes: envs
+                .get("VALIDATOR_INDEXES")
+                .map(|v| v.parse().expect("Valid validator indexes")),
+            retry: retry_policy(&envs),
+            breaker: breaker_policy(&envs),
+            sender_policy: SenderPolicy {
+                require_signer: envs
+                    .get("REQUIRE_SENDER_SIGNER")
+                    .map(|v| v.parse().expect("Valid require sender signer flag"))
+                    .unwrap_or(true),
+                relayers: envs
+                    .get("ALLOWED_RELAYERS")
+                    .map(|v| parse_addresses(v).expect("Valid allowed relayers"))
+                    .unwrap_or_default(),
+            },
+            api_auth: ApiAuth::new(
+                envs.get("COMMITMENT_API_KEYS")
+                    .map(|v| parse_api_keys(v).expect("Valid commitment API keys"))
+                    .unwrap_or_default(),
+                envs.get("COMMITMENT_JWT_SECRET")
+                    .map(|v| JwtSecret::from_hex(v).expect("Valid commitment JWT secret")),
+            ),
+            sender_rate_limit: envs.get("SENDER_RATE_LIMIT_PER_SEC").map(|v| {
+                RequestRateLimit::new(
+                    v.parse().expect("Valid sender rate limit"),
+                    envs.get("SENDER_RATE_LIMIT_BURST")
+                        .map(|v| v.parse().expect("Valid sender rate limit burst")),
+                )
+            }),
+            ip_rate_limit: envs.get("IP_RATE_LIMIT_PER_SEC").map(|v| {
+                RequestRateLimit::new(
+                    v.parse().expect("Valid IP rate limit"),
+                    envs.get("IP_RATE_LIMIT_BURST")
+                        .map(|v| v.parse().expect("Valid IP rate limit burst")),
+                )
+            }),
+            trusted_proxies: envs
+                .get("TRUSTED_PROXIES")
+                .map(|v| parse_ips(v).expect("Valid trusted proxies"))
+                .unwrap_or_default(),
+            reservation_sequencers: envs
+                .get("RESERVATION_SEQUENCERS")
+                .map(|v| parse_addresses(v).expect("Valid reservation sequencers"))
+                .unwrap_or_default(),
+            max_reserved_gas: envs
+                .get("MAX_RESERVED_GAS")
+                .map(|v| v.parse().expect("Valid max reserved gas"))
+                .unwrap_or(DEFAULT_MAX_RESERVED_GAS),
+            fallback_builder: cfg!(feature = "fallback-builder")
+                && envs
+                    .get("FALLBACK_BUILDER_ENABLED")
+                    .map(|v| v.parse().expect("Valid fallback builder flag"))
+                    .unwrap_or(true),
+            fallback_always_build: envs
+                .get("FALLBACK_ALWAYS_BUILD")
+                .map(|v| v.parse().expect("Valid fallback always build flag"))
+                .unwrap_or_default(),
+            replacement_policy: envs
+                .get("MEMPOOL_REPLACEMENT_POLICY")
+                .map(|v| v.parse().expect("Valid mempool replacement policy"))
+                .unwrap_or_default(),
+            fallback_value_estimator_url: envs
+                .get("FALLBACK_VALUE_ESTIMATOR_URL")
+                .map(|v| v.parse().expect("Valid fallback value estimator URL")),
+            fallback_bid_value_wei: envs
+                .get("FALLBACK_BID_VALUE_WEI")
+                .map(|v| v.parse().expect("Valid fallback bid value"))
+                .unwrap_or(DEFAULT_FALLBACK_BID_VALUE_WEI),
+            bid_policy: BidPolicy {
+                max_extra_data_bytes: envs
+                    .get("BID_MAX_EXTRA_DATA_BYTES")
+                    .map(|v| v.parse().expect("Valid bid max extra data bytes"))
+                    .unwrap_or(MAX_EXTRA_DATA_BYTES),
+                fee_recipients: envs
+                    .get("BID_FEE_RECIPIENTS")
+                    .map(|v| parse_addresses(v).expect("Valid bid fee recipients"))
+                    .unwrap_or_default(),
+            },
+            require_inclusion_proofs: envs
+                .get("REQUIRE_INCLUSION_PROOFS")
+                .map(|v| v.parse().expect("Valid inclusion proofs flag"))
+                .unwrap_or(false),
+            max_pending_blob_bytes: envs
+                .get("MAX_PENDING_BLOB_BYTES")
+                .map(|v| v.parse().expect("Valid max pending blob bytes"))
+                .unwrap_or(DEFAULT_MAX_PENDING_BLOB_BYTES),
+            verify_constraints: envs
+                .get("VERIFY_CONSTRAINTS")
+                .map(|v| v.parse().expect("Valid constraints verification flag"))
+                .unwrap_or(false),
+            check_deployment_collisions: envs
+                .get("CHECK_DEPLOYMENT_COLLISIONS")
+                .map(|v| v.parse().expect("Valid deployment collisions check flag"))
+                .unwrap_or(false),
+            keystore_secrets_path: envs
+                .get("KEYSTORE_SECRETS_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_default(),
+            keystore_pubkeys_path: envs
+                .get("KEYSTORE_PUBKEYS_PATH")
+                .map(PathBuf::from)
+                .unwrap_or_default(),
+            keystore_reload: envs
+                .get("KEYSTORE_RELOAD")
+                .map(|v| v.parse().expect("Valid keystore reload flag"))
+                .unwrap_or(false),
         }
     }
 }
 
+/// Read the inclusion list policy, enabled by setting the minimum pending slots.
+fn inclusion_list(envs: &HashMap<String, String>) -> Option<InclusionListPolicy> {
+    let min_pending_slots = envs
+        .get("INCLUSION_LIST_MIN_PENDING_SLOTS")
+        .map(|v| v.parse().expect("Valid inclusion list min pending slots"))?;
+
+    Some(InclusionListPolicy {
+        min_pending_slots,
+        min_priority_fee: envs
+            .get("INCLUSION_LIST_MIN_PRIORITY_FEE")
+            .map(|v| v.parse().expect("Valid inclusion list min priority fee"))
+            .unwrap_or(DEFAULT_INCLUSION_LIST_MIN_PRIORITY_FEE),
+        max_gas: envs
+            .get("INCLUSION_LIST_MAX_GAS")
+            .map(|v| v.parse().expect("Valid inclusion list max gas"))
+            .unwrap_or(DEFAULT_INCLUSION_LIST_MAX_GAS),
+        max_txs: envs
+            .get("INCLUSION_LIST_MAX_TXS")
+            .map(|v| v.parse().expect("Valid inclusion list max txs"))
+            .unwrap_or(DEFAULT_INCLUSION_LIST_MAX_TXS),
+    })
+}
+
+/// Read the Dirk connection, set along with `DIRK_URL`.
+fn dirk_config(envs: &HashMap<String, String>) -> Option<DirkConfig> {
+    let url = envs.get("DIRK_URL").map(|v| v.parse().expect("Valid Dirk URL"))?;
+    let path = |name: &str| envs.get(name).map(PathBuf::from).unwrap_or_default();
+
+    Some(DirkConfig {
+        url,
+        wallets: envs
+            .get("DIRK_WALLETS")
+            .map(|v| v.split(',').map(str::trim).filter(|s| !s.is_empty()).map(String::from).collect())
+            .unwrap_or_default(),
+        ca_cert_path: path("DIRK_CA_CERT_PATH"),
+        client_cert_path: path("DIRK_CLIENT_CERT_PATH"),
+        client_key_path: path("DIRK_CLIENT_KEY_PATH"),
+    })
+}
+
+/// Read the retry policy shared by the calls to the beacon node, execution client and relay.
+fn retry_policy(envs: &HashMap<String, String>) -> RetryPolicy {
+    let millis = |name: &str, default: u64| {
+        Duration::from_millis(
+            envs.get(name).map(|v| v.parse().expect("Valid retry duration")).unwrap_or(default),
+        )
+    };
+
+    RetryPolicy {
+        max_attempts: envs
+            .get("RETRY_MAX_ATTEMPTS")
+            .map(|v| v.parse().expect("Valid retry max attempts"))
+            .unwrap_or(DEFAULT_RETRY_MAX_ATTEMPTS),
+        initial_backoff: millis("RETRY_INITIAL_BACKOFF_MS", DEFAULT_RETRY_INITIAL_BACKOFF_MILLIS),
+        max_backoff: millis("RETRY_MAX_BACKOFF_MS", DEFAULT_RETRY_MAX_BACKOFF_MILLIS),
+        attempt_timeout: millis("RETRY_ATTEMPT_TIMEOUT_MS", DEFAULT_RETRY_ATTEMPT_TIMEOUT_MILLIS),
+    }
+}
+
+/// Read the policy of the circuit breakers of the dependencies.
+fn breaker_policy(envs: &HashMap<String, String>) -> BreakerPolicy {
+    let millis = |name: &str, default: u64| {
+        Duration::from_millis(
+            envs.get(name).map(|v| v.parse().expect("Valid breaker duration")).unwrap_or(default),
+        )
+    };
+
+    BreakerPolicy {
+        error_rate: envs
+            .get("BREAKER_ERROR_RATE")
+            .map(|v| v.parse().expect("Valid breaker error rate"))
+            .unwrap_or(DEFAULT_BREAKER_ERROR_RATE),
+        min_calls: envs
+            .get("BREAKER_MIN_CALLS")
+            .map(|v| v.parse().expect("Valid breaker min calls"))
+            .unwrap_or(DEFAULT_BREAKER_MIN_CALLS),
+        window: millis("BREAKER_WINDOW_MS", DEFAULT_BREAKER_WINDOW_MILLIS),
+        open_for: millis("BREAKER_OPEN_MS", DEFAULT_BREAKER_OPEN_MILLIS),
+    }
+}
+
+/// Parse a comma separated list of addresses.
+pub(crate) fn parse_addresses(s: &str) -> Result<HashSet<Address>, FromHexError> {
+    s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(Address::from_str).collect()
+}
+
+/// Parse a comma separated list of IP addresses.
+pub(crate) fn parse_ips(s: &str) -> Result<HashSet<IpAddr>, AddrParseError> {
+    s.split(',').map(str::trim).filter(|s| !s.is_empty()).map(IpAddr::from_str).collect()
+}
+
+/// Parse and normalize a comma separated list of relay urls.
+pub(crate) fn parse_relay_urls(s: &str) -> Result<Vec<Url>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|url| {
+            let url = url.parse::<Url>().map_err(|err| format!("`{url}`: {err}"))?;
+            normalize_base_url(&url).map_err(|err| err.to_string())
+        })
+        .collect()
+}
+
+/// Parse a comma separated list of BLS public keys.
+pub(crate) fn parse_bls_pubkeys(s: &str) -> Result<Vec<BlsPublicKey>, String> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|key| {
+            alloy::hex::decode(key.trim_start_matches("0x"))
+                .ok()
+                .and_then(|bytes| BlsPublicKey::try_from(bytes.as_slice()).ok())
+                .ok_or_else(|| format!("invalid BLS public key `{key}`"))
+        })
+        .collect()
+}
+
+/// Read and normalize the relay url, failing at load rather than on the first request.
+fn relay_url(envs: &HashMap<String, String>) -> Url {
+    let url = envs["RELAY_URL"].parse().expect("Valid URL");
+    normalize_base_url(&url).unwrap_or_else(|err| panic!("Invalid RELAY_URL: {err}"))
+}
+
+/// Number of extra relays, each authenticated by the `EXTRA_RELAY_<index>_AUTH_*` variables.
+pub(crate) fn extra_relays(envs: &HashMap<String, String>) -> usize {
+    envs.get("EXTRA_RELAY_URLS").and_then(|v| parse_relay_urls(v).ok()).map_or(0, |urls| urls.len())
+}
+
 /// Generate a random BLS secret key.
 pub fn random_bls_secret() -> BLSSecretKey {
     let mut rng = rand::thread_rng();
@@ -151,6 +811,7 @@ pub fn random_bls_secret() -> BLSSecretKey {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use group_config::KURTOSIS_CHAIN_ID;
     use std::collections::HashMap;
     #[test]
     fn test_config_default() {
@@ -235,6 +896,9 @@ mod tests {
         assert_eq!(config.chain.id, KURTOSIS_CHAIN_ID);
         assert_eq!(config.chain.commitment_deadline, 12);
         assert_eq!(config.chain.slot_time, 10);
+        assert_eq!(config.chain.slots_per_epoch, 32);
+        assert!(!config.fallback_always_build);
+        assert!(config.schema_backup);
     }
 
     #[test]
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+use toml::{value::Table, Value};
+
+/// Name of the configuration file loaded from the crate directory when none is given.
+pub const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+/// Name of the env file loaded from the crate directory when none is given.
+pub const DEFAULT_ENV_FILE: &str = ".env";
+
+#[derive(Debug, Error)]
+pub enum SourceError {
+    #[error("failed to read {path}: {source}")]
+    Read { path: PathBuf, source: io::Error },
+    #[error("invalid configuration file {path}: {source}")]
+    Toml { path: PathBuf, source: toml::de::Error },
+    #[error("invalid override `{0}`, expected KEY=VALUE")]
+    Override(String),
+}
+
+/// Layers the configuration variables are read from, each overriding the ones before:
+/// the TOML configuration file, the env file, the environment, then the CLI overrides.
+///
+/// The keys of the configuration file are the names of the variables in lower case, with
+/// tables prefixing the names of their keys: `url` in a `[relay]` table is `RELAY_URL`.
+/// Arrays are joined with commas.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    /// Configuration file, `config.toml` in the crate directory if it exists when not set.
+    pub config_file: Option<PathBuf>,
+    /// Env file, `.env` in the crate directory if it exists when not set.
+    pub env_file: Option<PathBuf>,
+    /// Whether the variables of the environment override the files.
+    pub environment: bool,
+    pub overrides: Vec<(String, String)>,
+}
+
+impl ConfigSources {
+    /// Read the variables of every layer. The files given explicitly must exist.
+    pub fn load(&self) -> Result<HashMap<String, String>, SourceError> {
+        let mut envs = HashMap::new();
+
+        if let Some(path) = existing(self.config_file.as_deref(), DEFAULT_CONFIG_FILE) {
+            let content = std::fs::read_to_string(&path)
+                .map_err(|source| SourceError::Read { path: path.clone(), source })?;
+            let table = toml::from_str::<Table>(&content)
+                .map_err(|source| SourceError::Toml { path, source })?;
+            flatten(None, table, &mut envs);
+        }
+
+        if let Some(path) = existing(self.env_file.as_deref(), DEFAULT_ENV_FILE) {
+            let file = env_file_reader::read_file(&path)
+                .map_err(|source| SourceError::Read { path, source })?;
+            envs.extend(file);
+        }
+
+        if self.environment {
+            envs.extend(std::env::vars());
+        }
+        envs.extend(self.overrides.iter().cloned());
+        Ok(envs)
+    }
+}
+
+/// Parse a `KEY=VALUE` override of a variable.
+pub fn parse_override(value: &str) -> Result<(String, String), SourceError> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(SourceError::Override(value.to_string())),
+    }
+}
+
+/// `path`, or the default file of the crate directory if it exists.
+fn existing(path: Option<&Path>, default: &str) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => {
+            let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(default);
+            path.is_file().then_some(path)
+        }
+    }
+}
+
+fn flatten(prefix: Option<&str>, table: Table, envs: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let name = match prefix {
+            Some(prefix) => format!("{prefix}_{}", key.to_uppercase()),
+            None => key.to_uppercase(),
+        };
+        match value {
+            Value::Table(table) => flatten(Some(&name), table, envs),
+            Value::Array(values) => {
+                let values = values.into_iter().map(scalar).collect::<Vec<_>>();
+                envs.insert(name, values.join(","));
+            }
+            value => {
+                envs.insert(name, scalar(value));
+            }
+        }
+    }
+}
+
+fn scalar(value: Value) -> String {
+    match value {
+        Value::String(value) => value,
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_override, ConfigSources};
+
+    #[test]
+    fn test_layered_config() {
+        let dir = std::env::temp_dir().join(format!("config-sources-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config_file = dir.join("config.toml");
+        let env_file = dir.join(".env");
+        std::fs::write(
+            &config_file,
+            r#"
+            chain = "holesky"
+            commitment_deadline = 8
+            extra_relay_urls = ["http://a", "http://b"]
+
+            [relay]
+            url = "http://relay"
+            "#,
+        )
+        .unwrap();
+        std::fs::write(&env_file, "COMMITMENT_DEADLINE=10\nMETRICS_PORT=9090\n").unwrap();
+
+        let sources = ConfigSources {
+            config_file: Some(config_file),
+            env_file: Some(env_file),
+            environment: false,
+            overrides: vec![parse_override("METRICS_PORT=9091").unwrap()],
+        };
+        let envs = sources.load().unwrap();
+        assert_eq!(envs["CHAIN"], "holesky");
+        assert_eq!(envs["RELAY_URL"], "http://relay");
+        assert_eq!(envs["EXTRA_RELAY_URLS"], "http://a,http://b");
+        assert_eq!(envs["COMMITMENT_DEADLINE"], "10");
+        assert_eq!(envs["METRICS_PORT"], "9091");
+
+        assert!(parse_override("METRICS_PORT").is_err());
+        let missing = ConfigSources { config_file: Some(dir.join("missing.toml")), ..sources };
+        assert!(missing.load().is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,605 @@
+use std::{collections::HashMap, fmt::Display, num::NonZero, path::Path, str::FromStr};
+
+use alloy::{hex, primitives::Address, signers::local::PrivateKeySigner};
+use alloy_rpc_types_engine::JwtSecret;
+use reqwest::{header::HeaderValue, Url};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use super::{
+    extra_relays, group_config::parse_forks, parse_addresses, parse_bls_pubkeys, parse_ips,
+    parse_relay_urls, Config, ValidatorIndexes,
+};
+use crate::{
+    commitment::{
+        auth::parse_api_keys, confidential::ConfidentialKey, receipt::ReceiptGossipMode,
+        replica::InstanceRole,
+    },
+    constraints::{
+        auth::extra_relay_auth_prefix, bid_checks::MAX_EXTRA_DATA_BYTES,
+        compression::RelayCompression,
+    },
+    delegation::signer::SignerType,
+    state::mempool::ReplacementPolicy,
+    utils::{score_cache::EvictionPolicy, url::normalize_base_url},
+};
+
+/// Variables that [Config::new] requires to be set.
+const REQUIRED_ENVS: &[&str] = &[
+    "CHAIN",
+    "COMMITMENT_PORT",
+    "METRICS_PORT",
+    "BUILDER_PORT",
+    "RELAY_URL",
+    "BEACON_API_URL",
+    "EXECUTION_API_URL",
+    "ENGINE_API_URL",
+    "JWT",
+    "FEE_RECIPIENT",
+];
+
+/// Variables that [Config::new] requires to be set for each signer.
+const SIGNER_ENVS: &[(SignerType, &[&str])] = &[
+    (SignerType::Keystores, &["KEYSTORE_SECRETS_PATH", "KEYSTORE_PUBKEYS_PATH"]),
+    (SignerType::Web3Signer, &["WEB3SIGNER_URL"]),
+    (
+        SignerType::Dirk,
+        &["DIRK_URL", "DIRK_WALLETS", "DIRK_CA_CERT_PATH", "DIRK_CLIENT_CERT_PATH", "DIRK_CLIENT_KEY_PATH"],
+    ),
+];
+
+const KNOWN_CHAINS: &[&str] = &["mainnet", "holesky", "kurtosis", "helder", "gnosis"];
+
+const REDACTED: &str = "<redacted>";
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ConfigError {
+    #[error("missing required variable {0}")]
+    Missing(&'static str),
+    #[error("invalid {name}: {reason}")]
+    Invalid { name: &'static str, reason: String },
+}
+
+impl ConfigError {
+    pub(crate) fn invalid(name: &'static str, reason: impl Display) -> Self {
+        Self::Invalid { name, reason: reason.to_string() }
+    }
+}
+
+/// Check that every variable read by [Config::new] is present and parses, so that building
+/// the config can't panic.
+pub fn check_envs(envs: &HashMap<String, String>) -> Vec<ConfigError> {
+    let mut errors = REQUIRED_ENVS
+        .iter()
+        .filter(|name| !envs.contains_key(**name))
+        .map(|name| ConfigError::Missing(name))
+        .collect::<Vec<_>>();
+
+    if let Some(chain) = envs.get("CHAIN") {
+        if !KNOWN_CHAINS.contains(&chain.as_str()) {
+            errors.push(ConfigError::invalid(
+                "CHAIN",
+                format!("unknown chain `{chain}`, expected one of {}", KNOWN_CHAINS.join(", ")),
+            ));
+        }
+    }
+
+    check_parse::<u64>(envs, "COMMITMENT_DEADLINE", &mut errors);
+    check_parse::<u64>(envs, "SLOT_TIME", &mut errors);
+    check_parse::<u64>(envs, "SLOTS_PER_EPOCH", &mut errors);
+    check_parse::<usize>(envs, "MAX_BLOBS_PER_BLOCK", &mut errors);
+    check_parse::<u64>(envs, "ELECTRA_FORK_EPOCH", &mut errors);
+    match envs.get("ACCEPTED_FORKS").map(|v| parse_forks(v)) {
+        Some(Ok(forks)) if forks.is_empty() => {
+            errors.push(ConfigError::invalid("ACCEPTED_FORKS", "must accept at least one fork"));
+        }
+        Some(Err(err)) => errors.push(ConfigError::invalid("ACCEPTED_FORKS", err)),
+        _ => {}
+    }
+    check_parse::<u16>(envs, "COMMITMENT_PORT", &mut errors);
+    check_parse::<u16>(envs, "METRICS_PORT", &mut errors);
+    check_parse::<u16>(envs, "BUILDER_PORT", &mut errors);
+    check_parse::<Url>(envs, "RELAY_URL", &mut errors);
+    if let Some(Ok(url)) = envs.get("RELAY_URL").map(|url| url.parse::<Url>()) {
+        if let Err(err) = normalize_base_url(&url) {
+            errors.push(ConfigError::invalid("RELAY_URL", err));
+        }
+    }
+    check_parse::<Url>(envs, "BEACON_API_URL", &mut errors);
+    check_parse::<Url>(envs, "EXECUTION_API_URL", &mut errors);
+    check_parse::<Url>(envs, "ENGINE_API_URL", &mut errors);
+    check_parse::<usize>(envs, "MAX_CONCURRENT_SIGNINGS", &mut errors);
+    check_parse::<u64>(envs, "SIGNING_QUEUE_TIMEOUT_MS", &mut errors);
+    check_parse::<u64>(envs, "SLOT_DRIFT_THRESHOLD_MS", &mut errors);
+    check_parse::<u32>(envs, "RELAY_RATE_LIMIT_PER_SEC", &mut errors);
+    check_parse::<u32>(envs, "RELAY_RATE_LIMIT_BURST", &mut errors);
+    check_parse::<u32>(envs, "SENDER_RATE_LIMIT_PER_SEC", &mut errors);
+    check_parse::<u32>(envs, "SENDER_RATE_LIMIT_BURST", &mut errors);
+    check_parse::<u32>(envs, "IP_RATE_LIMIT_PER_SEC", &mut errors);
+    check_parse::<u32>(envs, "IP_RATE_LIMIT_BURST", &mut errors);
+    if let Some(Err(err)) = envs.get("TRUSTED_PROXIES").map(|v| parse_ips(v)) {
+        errors.push(ConfigError::invalid("TRUSTED_PROXIES", err));
+    }
+    if let Some(Err(err)) = envs.get("EXTRA_RELAY_URLS").map(|v| parse_relay_urls(v)) {
+        errors.push(ConfigError::invalid("EXTRA_RELAY_URLS", err));
+    }
+    check_parse::<NonZero<usize>>(envs, "RELAY_QUORUM", &mut errors);
+    check_parse::<u64>(envs, "RELAY_TIMEOUT_MS", &mut errors);
+    check_parse::<RelayCompression>(envs, "RELAY_COMPRESSION", &mut errors);
+    check_parse::<u64>(envs, "MAX_EL_LAG_BLOCKS", &mut errors);
+    check_parse::<u64>(envs, "QUOTE_TTL_MS", &mut errors);
+    check_parse::<Url>(envs, "INCLUSION_WEBHOOK_URL", &mut errors);
+    check_parse::<ReceiptGossipMode>(envs, "RECEIPT_GOSSIP", &mut errors);
+    check_parse::<NonZero<usize>>(envs, "RECEIPT_GOSSIP_QUEUE", &mut errors);
+    check_parse::<NonZero<u64>>(envs, "MAX_GAS_LIMIT_RATIO", &mut errors);
+    check_parse::<EvictionPolicy>(envs, "ACCOUNT_STATES_EVICTION_POLICY", &mut errors);
+    check_parse::<u64>(envs, "ACCOUNT_STATES_TTL_SECS", &mut errors);
+    check_parse::<u64>(envs, "INCLUSION_LIST_MIN_PENDING_SLOTS", &mut errors);
+    check_parse::<u128>(envs, "INCLUSION_LIST_MIN_PRIORITY_FEE", &mut errors);
+    check_parse::<u64>(envs, "INCLUSION_LIST_MAX_GAS", &mut errors);
+    check_parse::<usize>(envs, "INCLUSION_LIST_MAX_TXS", &mut errors);
+    check_parse::<InstanceRole>(envs, "INSTANCE_ROLE", &mut errors);
+    check_parse::<Url>(envs, "PRIMARY_URL", &mut errors);
+    check_parse::<u64>(envs, "DEADLINE_BUDGET_MS", &mut errors);
+    check_parse::<u64>(envs, "DEADLINE_STAGE_BUDGET_MS", &mut errors);
+    check_parse::<u64>(envs, "EARLY_DEADLINE_MS", &mut errors);
+    check_parse::<bool>(envs, "RELAY_INCREMENTAL_SUBMISSIONS", &mut errors);
+    check_parse::<ValidatorIndexes>(envs, "VALIDATOR_INDEXES", &mut errors);
+    check_parse::<NonZero<u32>>(envs, "RETRY_MAX_ATTEMPTS", &mut errors);
+    check_parse::<u64>(envs, "RETRY_INITIAL_BACKOFF_MS", &mut errors);
+    check_parse::<u64>(envs, "RETRY_MAX_BACKOFF_MS", &mut errors);
+    check_parse::<u64>(envs, "RETRY_ATTEMPT_TIMEOUT_MS", &mut errors);
+    check_parse::<u32>(envs, "BREAKER_MIN_CALLS", &mut errors);
+    check_parse::<u64>(envs, "BREAKER_WINDOW_MS", &mut errors);
+    check_parse::<u64>(envs, "BREAKER_OPEN_MS", &mut errors);
+    match envs.get("BREAKER_ERROR_RATE").map(|v| v.parse::<f64>()) {
+        Some(Ok(rate)) if !(0.0..=1.0).contains(&rate) => {
+            errors.push(ConfigError::invalid("BREAKER_ERROR_RATE", "must be between 0 and 1"));
+        }
+        Some(Err(err)) => errors.push(ConfigError::invalid("BREAKER_ERROR_RATE", err)),
+        _ => {}
+    }
+    check_parse::<bool>(envs, "REQUIRE_SENDER_SIGNER", &mut errors);
+    check_parse::<bool>(envs, "FALLBACK_BUILDER_ENABLED", &mut errors);
+    check_parse::<bool>(envs, "FALLBACK_ALWAYS_BUILD", &mut errors);
+    check_parse::<bool>(envs, "SCHEMA_BACKUP", &mut errors);
+    check_parse::<ReplacementPolicy>(envs, "MEMPOOL_REPLACEMENT_POLICY", &mut errors);
+    check_parse::<Url>(envs, "FALLBACK_VALUE_ESTIMATOR_URL", &mut errors);
+    check_parse::<u128>(envs, "FALLBACK_BID_VALUE_WEI", &mut errors);
+    match envs.get("BID_MAX_EXTRA_DATA_BYTES").map(|v| v.parse::<usize>()) {
+        Some(Ok(len)) if len > MAX_EXTRA_DATA_BYTES => {
+            let reason = format!("must be at most {MAX_EXTRA_DATA_BYTES}");
+            errors.push(ConfigError::invalid("BID_MAX_EXTRA_DATA_BYTES", reason));
+        }
+        Some(Err(err)) => errors.push(ConfigError::invalid("BID_MAX_EXTRA_DATA_BYTES", err)),
+        _ => {}
+    }
+    if let Some(Err(err)) = envs.get("BID_FEE_RECIPIENTS").map(|v| parse_addresses(v)) {
+        errors.push(ConfigError::invalid("BID_FEE_RECIPIENTS", err));
+    }
+    check_parse::<usize>(envs, "MAX_PENDING_BLOB_BYTES", &mut errors);
+    check_parse::<bool>(envs, "REQUIRE_INCLUSION_PROOFS", &mut errors);
+    check_parse::<bool>(envs, "VERIFY_CONSTRAINTS", &mut errors);
+    check_parse::<bool>(envs, "KEYSTORE_RELOAD", &mut errors);
+    check_parse::<bool>(envs, "CHECK_DEPLOYMENT_COLLISIONS", &mut errors);
+    check_parse::<SignerType>(envs, "SIGNER_TYPE", &mut errors);
+    check_parse::<Url>(envs, "DIRK_URL", &mut errors);
+    if let Some(Err(err)) = envs.get("ALLOWED_RELAYERS").map(|v| parse_addresses(v)) {
+        errors.push(ConfigError::invalid("ALLOWED_RELAYERS", err));
+    }
+    if let Some(Err(err)) = envs.get("RESERVATION_SEQUENCERS").map(|v| parse_addresses(v)) {
+        errors.push(ConfigError::invalid("RESERVATION_SEQUENCERS", err));
+    }
+    check_parse::<u64>(envs, "MAX_RESERVED_GAS", &mut errors);
+    if let Some(Err(err)) = envs.get("DELEGATEE_PUBKEYS").map(|v| parse_bls_pubkeys(v)) {
+        errors.push(ConfigError::invalid("DELEGATEE_PUBKEYS", err));
+    }
+
+    let signer_type =
+        envs.get("SIGNER_TYPE").and_then(|v| v.parse().ok()).unwrap_or(SignerType::Keystores);
+    for (_, names) in SIGNER_ENVS.iter().filter(|(signer, _)| *signer == signer_type) {
+        errors.extend(names.iter().filter(|name| !envs.contains_key(**name)).map(|name| ConfigError::Missing(name)));
+    }
+
+    // Replicas serve the shared store of the primary and forward the requests to it
+    if envs.get("INSTANCE_ROLE").is_some_and(|role| role == "replica") {
+        for name in ["PRIMARY_URL", "SHARED_STORE_PATH"] {
+            if !envs.contains_key(name) {
+                errors.push(ConfigError::Missing(name));
+            }
+        }
+    }
+
+    // The requests forwarded by the peer gateways are authenticated with a shared secret
+    if envs.contains_key("PEER_REGISTRY_PATH") && !envs.contains_key("PEER_FORWARD_SECRET") {
+        errors.push(ConfigError::Missing("PEER_FORWARD_SECRET"));
+    }
+
+    // The leader processing the shared requests is the holder of the lease
+    if envs.contains_key("REQUEST_LOG_PATH") && !envs.contains_key("INSTANCE_LEASE_PATH") {
+        errors.push(ConfigError::Missing("INSTANCE_LEASE_PATH"));
+    }
+
+    // The newer instance submits the constraints accepted by the one it replaces from the journal
+    if envs.contains_key("INSTANCE_LEASE_PATH") && !envs.contains_key("CONSTRAINTS_JOURNAL_PATH") {
+        errors.push(ConfigError::Missing("CONSTRAINTS_JOURNAL_PATH"));
+    }
+
+    if let Some(fee_recipient) = envs.get("FEE_RECIPIENT") {
+        if let Err(err) = Address::parse_checksummed(fee_recipient, None) {
+            errors.push(ConfigError::invalid("FEE_RECIPIENT", err));
+        }
+    }
+
+    if let Some(header) = envs.get("RELAY_AUTH_HEADER") {
+        if header.split_once(':').is_none() {
+            errors.push(ConfigError::invalid(
+                "RELAY_AUTH_HEADER",
+                "expected a header formatted as `Name: value`",
+            ));
+        }
+    }
+
+    for name in ["USER_AGENT", "OPERATOR_ID"] {
+        if let Some(Err(err)) = envs.get(name).map(|v| HeaderValue::from_str(v)) {
+            errors.push(ConfigError::invalid(name, err));
+        }
+    }
+
+    if let Some(key) = envs.get("RELAY_AUTH_SIGNING_KEY") {
+        if PrivateKeySigner::from_str(key).is_err() {
+            errors.push(ConfigError::invalid("RELAY_AUTH_SIGNING_KEY", "invalid ECDSA key"));
+        }
+    }
+
+    for prefix in (1..=extra_relays(envs)).map(extra_relay_auth_prefix) {
+        if envs.get(&format!("{prefix}_HEADER")).is_some_and(|h| h.split_once(':').is_none()) {
+            errors.push(ConfigError::invalid(
+                "EXTRA_RELAY_URLS",
+                format!("{prefix}_HEADER must be formatted as `Name: value`"),
+            ));
+        }
+        if let Some(key) = envs.get(&format!("{prefix}_SIGNING_KEY")) {
+            if PrivateKeySigner::from_str(key).is_err() {
+                errors.push(ConfigError::invalid(
+                    "EXTRA_RELAY_URLS",
+                    format!("{prefix}_SIGNING_KEY is not a valid ECDSA key"),
+                ));
+            }
+        }
+    }
+
+    if let Some(key) = envs.get("QUOTE_SIGNING_KEY") {
+        if PrivateKeySigner::from_str(key).is_err() {
+            errors.push(ConfigError::invalid("QUOTE_SIGNING_KEY", "invalid ECDSA key"));
+        }
+    }
+
+    if let Some(key) = envs.get("RECEIPT_PROXY_DELEGATOR") {
+        if hex::decode(key.trim_start_matches("0x")).map_or(true, |bytes| bytes.len() != 48) {
+            errors.push(ConfigError::invalid("RECEIPT_PROXY_DELEGATOR", "invalid BLS public key"));
+        }
+    }
+
+    if let Some(key) = envs.get("CONFIDENTIAL_KEY") {
+        if ConfidentialKey::from_str(key).is_err() {
+            errors.push(ConfigError::invalid("CONFIDENTIAL_KEY", "invalid X25519 key"));
+        }
+    }
+
+    if let Some(Err(err)) = envs.get("COMMITMENT_API_KEYS").map(|v| parse_api_keys(v)) {
+        errors.push(ConfigError::invalid("COMMITMENT_API_KEYS", err));
+    }
+    if let Some(Err(err)) = envs.get("COMMITMENT_JWT_SECRET").map(|v| JwtSecret::from_hex(v)) {
+        errors.push(ConfigError::invalid("COMMITMENT_JWT_SECRET", err));
+    }
+
+    errors
+}
+
+fn check_parse<T>(envs: &HashMap<String, String>, name: &'static str, errors: &mut Vec<ConfigError>)
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(Err(err)) = envs.get(name).map(|value| value.parse::<T>()) {
+        errors.push(ConfigError::invalid(name, err));
+    }
+}
+
+impl Config {
+    /// Check the resolved configuration: URL schemes, ports, deadlines, the JWT secret and
+    /// the keystore paths.
+    pub fn check(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+
+        for (name, url) in [
+            ("RELAY_URL", &self.relay_url),
+            ("BEACON_API_URL", &self.beacon_api_url),
+            ("EXECUTION_API_URL", &self.execution_api_url),
+            ("ENGINE_API_URL", &self.engine_api_url),
+        ] {
+            if !matches!(url.scheme(), "http" | "https") {
+                errors.push(ConfigError::invalid(name, "expected an http(s) URL"));
+            }
+        }
+
+        let ports = [self.commitment_port, self.metrics_port, self.builder_port];
+        if ports.iter().enumerate().any(|(i, port)| ports[..i].contains(port)) {
+            errors.push(ConfigError::invalid(
+                "COMMITMENT_PORT, METRICS_PORT, BUILDER_PORT",
+                "ports must be distinct",
+            ));
+        }
+
+        if self.chain.slot_time == 0 {
+            errors.push(ConfigError::invalid("SLOT_TIME", "must be at least 1 second"));
+        }
+        if self.chain.slots_per_epoch == 0 {
+            errors.push(ConfigError::invalid("SLOTS_PER_EPOCH", "must be at least 1"));
+        }
+        if self.chain.commitment_deadline >= self.chain.slot_time * 1_000 {
+            errors.push(ConfigError::invalid(
+                "COMMITMENT_DEADLINE",
+                format!(
+                    "{}ms deadline doesn't fit in a {}s slot",
+                    self.chain.commitment_deadline, self.chain.slot_time
+                ),
+            ));
+        }
+
+        if self.early_deadline_ms.is_some_and(|early| early >= self.chain.commitment_deadline) {
+            errors.push(ConfigError::invalid(
+                "EARLY_DEADLINE_MS",
+                format!("must be below the {}ms commitment deadline", self.chain.commitment_deadline),
+            ));
+        }
+
+        let relays = 1 + self.extra_relay_urls.len();
+        if self.relay_quorum > relays {
+            errors.push(ConfigError::invalid(
+                "RELAY_QUORUM",
+                format!("quorum of {} out of {relays} relays", self.relay_quorum),
+            ));
+        }
+
+        match hex::decode(self.jwt_hex.trim_start_matches("0x")) {
+            Ok(secret) if secret.len() == 32 => {}
+            Ok(_) => errors.push(ConfigError::invalid("JWT", "expected a 32 bytes secret")),
+            Err(_) => errors.push(ConfigError::invalid("JWT", "expected a hex encoded secret")),
+        }
+
+        let keystores = match self.signer_type {
+            SignerType::Keystores => vec![
+                ("KEYSTORE_SECRETS_PATH", &self.keystore_secrets_path),
+                ("KEYSTORE_PUBKEYS_PATH", &self.keystore_pubkeys_path),
+            ],
+            _ => Vec::new(),
+        };
+        for (name, path) in keystores {
+            if !Path::new(path).is_dir() {
+                errors.push(ConfigError::invalid(
+                    name,
+                    format!("{} is not a directory", path.display()),
+                ));
+            }
+        }
+
+        for (name, path) in [
+            ("REVENUE_REPORT_PATH", &self.revenue_report_path),
+            ("INSTANCE_LEASE_PATH", &self.instance_lease_path),
+            ("REQUEST_LOG_PATH", &self.request_log_path),
+            ("SUBMISSION_LOG_PATH", &self.submission_log_path),
+            ("AUDIT_LOG_PATH", &self.audit_log_path),
+            ("CONSTRAINTS_JOURNAL_PATH", &self.constraints_journal_path),
+            ("SLOT_ARCHIVE_PATH", &self.slot_archive_path),
+            ("DELEGATION_REGISTRY_PATH", &self.delegation_registry_path),
+        ] {
+            let Some(path) = path else { continue };
+            if matches!(path.parent(), Some(dir) if !dir.as_os_str().is_empty() && !dir.is_dir()) {
+                errors.push(ConfigError::invalid(
+                    name,
+                    format!("parent directory of {} doesn't exist", path.display()),
+                ));
+            }
+        }
+
+        for (name, path) in [
+            ("PEER_REGISTRY_PATH", &self.peer_registry_path),
+            ("DELEGATIONS_PATH", &self.delegations_path),
+        ] {
+            let Some(path) = path else { continue };
+            if !path.is_file() {
+                errors.push(ConfigError::invalid(name, format!("{} is not a file", path.display())));
+            }
+        }
+
+        if let (SignerType::Dirk, Some(dirk)) = (self.signer_type, &self.dirk) {
+            for (name, path) in [
+                ("DIRK_CA_CERT_PATH", &dirk.ca_cert_path),
+                ("DIRK_CLIENT_CERT_PATH", &dirk.client_cert_path),
+                ("DIRK_CLIENT_KEY_PATH", &dirk.client_key_path),
+            ] {
+                if !path.is_file() {
+                    errors.push(ConfigError::invalid(name, format!("{} is not a file", path.display())));
+                }
+            }
+            if !cfg!(feature = "signer-dirk") {
+                errors.push(ConfigError::invalid("SIGNER_TYPE", "built without the signer-dirk feature"));
+            }
+        }
+
+        if matches!(self.signer_type, SignerType::Web3Signer) && !cfg!(feature = "signer-web3") {
+            let reason = "built without the signer-web3 feature";
+            errors.push(ConfigError::invalid("SIGNER_TYPE", reason));
+        }
+
+        if self.receipt_gossip.is_some() && !cfg!(feature = "collector-client") {
+            let reason = "built without the collector-client feature";
+            errors.push(ConfigError::invalid("RECEIPT_GOSSIP", reason));
+        }
+
+        if self.max_concurrent_signings == 0 {
+            errors.push(ConfigError::invalid("MAX_CONCURRENT_SIGNINGS", "must be at least 1"));
+        }
+
+        errors
+    }
+
+    /// The effective configuration with secrets redacted, for operators to review.
+    pub fn redacted(&self) -> Value {
+        json!({
+            "chain": {
+                "name": self.chain.chain.get_name(),
+                "id": self.chain.id,
+                "slot_time_secs": self.chain.slot_time,
+                "slots_per_epoch": self.chain.slots_per_epoch,
+                "max_blobs_per_block": self.chain.max_blobs_per_block,
+                "electra_fork_epoch": self.chain.electra_fork_epoch,
+                "accepted_forks": self.chain.accepted_forks.iter().map(|f| f.to_string()).collect::<Vec<_>>(),
+                "commitment_deadline_ms": self.chain.commitment_deadline,
+            },
+            "commitment_port": self.commitment_port,
+            "metrics_port": self.metrics_port,
+            "builder_port": self.builder_port,
+            "relay_url": self.relay_url.as_str(),
+            "relay_auth": format!("{:?}", self.relay_auth),
+            "relay_rate_limit_per_sec": self.relay_rate_limit_per_sec,
+            "relay_rate_limit_burst": self.relay_rate_limit_burst,
+            "extra_relay_urls": self.extra_relay_urls.iter().map(Url::as_str).collect::<Vec<_>>(),
+            "extra_relay_auth": self.extra_relay_auth.iter().map(|auth| format!("{auth:?}")).collect::<Vec<_>>(),
+            "relay_quorum": self.relay_quorum,
+            "relay_timeout_ms": self.relay_timeout_ms,
+            "relay_compression": self.relay_compression.to_string(),
+            "beacon_api_url": self.beacon_api_url.as_str(),
+            "execution_api_url": self.execution_api_url.as_str(),
+            "engine_api_url": self.engine_api_url.as_str(),
+            "jwt_hex": REDACTED,
+            "fee_recipient": self.fee_recipient.to_string(),
+            "builder_bls_private_key": REDACTED,
+            "gateway_contract": self.gateway_contract.to_string(),
+            "signer_type": self.signer_type.to_string(),
+            "keystore_secrets_path": self.keystore_secrets_path.display().to_string(),
+            "keystore_pubkeys_path": self.keystore_pubkeys_path.display().to_string(),
+            "keystore_reload": self.keystore_reload,
+            "web3signer_url": self.web3signer_url,
+            "ca_cert_path": self.ca_cert_path,
+            "combined_pem_path": self.combined_pem_path,
+            "commit_boost_signer_url": self.commit_boost_signer_url,
+            "user_agent": self.outbound_headers.user_agent,
+            "operator_id": self.outbound_headers.operator_id,
+            "dirk": self.dirk.as_ref().map(|dirk| json!({
+                "url": dirk.url.as_str(),
+                "wallets": dirk.wallets,
+                "ca_cert_path": dirk.ca_cert_path.display().to_string(),
+                "client_cert_path": dirk.client_cert_path.display().to_string(),
+                "client_key_path": dirk.client_key_path.display().to_string(),
+            })),
+            "max_concurrent_signings": self.max_concurrent_signings,
+            "signing_queue_timeout_ms": self.signing_queue_timeout_ms,
+            "slot_drift_threshold_ms": self.slot_drift_threshold_ms,
+            "max_el_lag_blocks": self.max_el_lag_blocks,
+            "instance_lease_path": self.instance_lease_path.as_ref().map(|p| p.display().to_string()),
+            "request_log_path": self.request_log_path.as_ref().map(|p| p.display().to_string()),
+            "revenue_report_path": self.revenue_report_path.as_ref().map(|p| p.display().to_string()),
+            "quote_signer": self.quote_signer.as_ref().map(|s| s.address().to_string()),
+            "receipt_proxy_delegator": self.receipt_proxy_delegator,
+            "receipt_gossip": self.receipt_gossip.map(|mode| mode.to_string()),
+            "receipt_gossip_queue": self.receipt_gossip_queue,
+            "quote_ttl_ms": self.quote_ttl_ms,
+            "inclusion_webhook_url": self.inclusion_webhook_url.as_ref().map(|u| u.as_str()),
+            "max_gas_limit_ratio": self.max_gas_limit_ratio,
+            "account_states_eviction_policy": self.account_states_eviction_policy.to_string(),
+            "account_states_ttl_secs": self.account_states_ttl_secs,
+            "inclusion_list": self.inclusion_list.map(|policy| json!({
+                "min_pending_slots": policy.min_pending_slots,
+                "min_priority_fee": policy.min_priority_fee.to_string(),
+                "max_gas": policy.max_gas,
+                "max_txs": policy.max_txs,
+            })),
+            "role": self.role.to_string(),
+            "primary_url": self.primary_url.as_ref().map(|u| u.as_str()),
+            "shared_store_path": self.shared_store_path.as_ref().map(|p| p.display().to_string()),
+            "submission_log_path": self.submission_log_path.as_ref().map(|p| p.display().to_string()),
+            "audit_log_path": self.audit_log_path.as_ref().map(|p| p.display().to_string()),
+            "constraints_journal_path": self.constraints_journal_path.as_ref().map(|p| p.display().to_string()),
+            "slot_archive_path": self.slot_archive_path.as_ref().map(|p| p.display().to_string()),
+            "delegation_registry_path": self.delegation_registry_path.as_ref().map(|p| p.display().to_string()),
+            "schema_backup": self.schema_backup,
+            "deadline_budget_ms": self.deadline_budget_ms,
+            "deadline_stage_budget_ms": self.deadline_stage_budget_ms,
+            "early_deadline_ms": self.early_deadline_ms,
+            "relay_incremental_submissions": self.relay_incremental_submissions,
+            "peer_registry_path": self.peer_registry_path.as_ref().map(|p| p.display().to_string()),
+            "peer_forward_secret": self.peer_forward_secret.is_some().then_some(REDACTED),
+            "delegations_path": self.delegations_path.as_ref().map(|p| p.display().to_string()),
+            "delegatee_pubkeys": self.delegatee_pubkeys.iter().map(|k| k.to_string()).collect::<Vec<_>>(),
+            "confidential_public_key": self.confidential_key.as_ref().map(|k| k.public_key().to_string()),
+            "require_sender_signer": self.sender_policy.require_signer,
+            "allowed_relayers": self.sender_policy.relayers.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            "commitment_api_keys": self.api_auth.labels(),
+            "commitment_jwt_secret": self.api_auth.has_jwt_secret().then_some(REDACTED),
+            "sender_rate_limit": self.sender_rate_limit.map(|l| json!({ "per_sec": l.per_sec, "burst": l.burst })),
+            "ip_rate_limit": self.ip_rate_limit.map(|l| json!({ "per_sec": l.per_sec, "burst": l.burst })),
+            "trusted_proxies": self.trusted_proxies.iter().map(|ip| ip.to_string()).collect::<Vec<_>>(),
+            "reservation_sequencers": self.reservation_sequencers.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            "max_reserved_gas": self.max_reserved_gas,
+            "fallback_builder": self.fallback_builder,
+            "fallback_always_build": self.fallback_always_build,
+            "mempool_replacement_policy": self.replacement_policy.to_string(),
+            "fallback_value_estimator_url": self.fallback_value_estimator_url.as_ref().map(|u| u.as_str()),
+            "fallback_bid_value_wei": self.fallback_bid_value_wei.to_string(),
+            "bid_max_extra_data_bytes": self.bid_policy.max_extra_data_bytes,
+            "bid_fee_recipients": self.bid_policy.fee_recipients.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+            "require_inclusion_proofs": self.require_inclusion_proofs,
+            "max_pending_blob_bytes": self.max_pending_blob_bytes,
+            "verify_constraints": self.verify_constraints,
+            "check_deployment_collisions": self.check_deployment_collisions,
+            "retry": json!({
+                "max_attempts": self.retry.max_attempts,
+                "initial_backoff_ms": self.retry.initial_backoff.as_millis() as u64,
+                "max_backoff_ms": self.retry.max_backoff.as_millis() as u64,
+                "attempt_timeout_ms": self.retry.attempt_timeout.as_millis() as u64,
+            }),
+            "breaker": json!({
+                "error_rate": self.breaker.error_rate,
+                "min_calls": self.breaker.min_calls,
+                "window_ms": self.breaker.window.as_millis() as u64,
+                "open_ms": self.breaker.open_for.as_millis() as u64,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{check_envs, ConfigError};
+    use crate::config::Config;
+
+    #[test]
+    fn test_check_envs_reports_every_problem() {
+        let envs = HashMap::from([
+            ("CHAIN".to_string(), "goerli".to_string()),
+            ("SLOT_TIME".to_string(), "twelve".to_string()),
+            ("RELAY_URL".to_string(), "not a url".to_string()),
+        ]);
+
+        let errors = check_envs(&envs);
+        assert!(errors.contains(&ConfigError::Missing("JWT")));
+        assert!(!errors.contains(&ConfigError::Missing("CHAIN")));
+        for name in ["CHAIN", "SLOT_TIME", "RELAY_URL"] {
+            assert!(errors
+                .iter()
+                .any(|err| matches!(err, ConfigError::Invalid { name: n, .. } if *n == name)));
+        }
+    }
+
+    #[test]
+    fn test_redacted_config_hides_secrets() {
+        let config = Config { jwt_hex: "deadbeef".to_string(), ..Default::default() };
+        let redacted = config.redacted().to_string();
+        assert!(!redacted.contains("deadbeef"));
+    }
+}
@@ -0,0 +1,237 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    hex,
+    primitives::keccak256,
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use reqwest::{
+    header::{HeaderName, HeaderValue, AUTHORIZATION},
+    Request, RequestBuilder, Response,
+};
+
+use super::rate_limit::RelayRateLimiter;
+
+/// Header carrying the unix timestamp of a signed relay request.
+pub const RELAY_AUTH_TIMESTAMP_HEADER: &str = "x-relay-auth-timestamp";
+/// Header carrying the address of the key that signed a relay request.
+pub const RELAY_AUTH_SIGNER_HEADER: &str = "x-relay-auth-signer";
+/// Header carrying the signature of a signed relay request.
+pub const RELAY_AUTH_SIGNATURE_HEADER: &str = "x-relay-auth-signature";
+
+/// Authentication applied to the outbound requests sent to a relay.
+#[derive(Clone, Default)]
+pub enum RelayAuth {
+    /// No authentication.
+    #[default]
+    None,
+    /// A static header, e.g. an API key.
+    Header { name: HeaderName, value: HeaderValue },
+    /// A bearer token sent in the `Authorization` header, e.g. a builder JWT.
+    Bearer(String),
+    /// Requests signed with an ECDSA key over
+    /// `keccak256(timestamp || method || path_and_query || body)`.
+    Signed(PrivateKeySigner),
+}
+
+impl RelayAuth {
+    /// Read the relay authentication from the environment:
+    /// - `RELAY_AUTH_HEADER`: a static header formatted as `Name: value`,
+    /// - `RELAY_AUTH_BEARER`: a bearer token,
+    /// - `RELAY_AUTH_SIGNING_KEY`: a hex encoded ECDSA key used to sign requests.
+    pub fn from_envs(envs: &HashMap<String, String>) -> Self {
+        Self::from_prefixed_envs(envs, "RELAY_AUTH")
+    }
+
+    /// Read the authentication of the `index`-th extra relay, starting at 1, from the
+    /// `EXTRA_RELAY_<index>_AUTH_*` variables. Extra relays don't share the credentials of the
+    /// relay, they are sent none unless configured.
+    pub fn from_extra_relay_envs(envs: &HashMap<String, String>, index: usize) -> Self {
+        Self::from_prefixed_envs(envs, &extra_relay_auth_prefix(index))
+    }
+
+    fn from_prefixed_envs(envs: &HashMap<String, String>, prefix: &str) -> Self {
+        if let Some(header) = envs.get(&format!("{prefix}_HEADER")) {
+            let (name, value) = header
+                .split_once(':')
+                .unwrap_or_else(|| panic!("{prefix}_HEADER must be formatted as `Name: value`"));
+            return Self::Header {
+                name: HeaderName::try_from(name.trim()).expect("Valid relay auth header name"),
+                value: HeaderValue::try_from(value.trim()).expect("Valid relay auth header value"),
+            };
+        }
+
+        if let Some(token) = envs.get(&format!("{prefix}_BEARER")) {
+            return Self::Bearer(token.clone());
+        }
+
+        if let Some(key) = envs.get(&format!("{prefix}_SIGNING_KEY")) {
+            return Self::Signed(key.parse().expect("Valid relay auth signing key"));
+        }
+
+        Self::None
+    }
+
+    /// Add the authentication headers to the request. Header values are marked as sensitive
+    /// so that they are redacted when the request is logged.
+    pub fn apply(&self, request: &mut Request) {
+        match self {
+            Self::None => {}
+            Self::Header { name, value } => {
+                let mut value = value.clone();
+                value.set_sensitive(true);
+                request.headers_mut().insert(name.clone(), value);
+            }
+            Self::Bearer(token) => match HeaderValue::try_from(format!("Bearer {token}")) {
+                Ok(mut value) => {
+                    value.set_sensitive(true);
+                    request.headers_mut().insert(AUTHORIZATION, value);
+                }
+                Err(_) => tracing::error!("Invalid relay bearer token, sending request without it"),
+            },
+            Self::Signed(signer) => {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    .to_string();
+
+                let mut payload = timestamp.as_bytes().to_vec();
+                payload.extend_from_slice(request.method().as_str().as_bytes());
+                payload.extend_from_slice(path_and_query(request).as_bytes());
+                if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+                    payload.extend_from_slice(body);
+                }
+
+                let signature = match signer.sign_hash_sync(&keccak256(payload)) {
+                    Ok(signature) => signature,
+                    Err(err) => {
+                        tracing::error!(?err, "Failed to sign relay request");
+                        return;
+                    }
+                };
+
+                let mut signature = HeaderValue::try_from(hex::encode_prefixed(
+                    signature.as_bytes(),
+                ))
+                .expect("hex is a valid header value");
+                signature.set_sensitive(true);
+
+                let headers = request.headers_mut();
+                headers.insert(
+                    RELAY_AUTH_TIMESTAMP_HEADER,
+                    HeaderValue::try_from(timestamp).expect("digits are a valid header value"),
+                );
+                headers.insert(
+                    RELAY_AUTH_SIGNER_HEADER,
+                    HeaderValue::try_from(signer.address().to_string())
+                        .expect("hex is a valid header value"),
+                );
+                headers.insert(RELAY_AUTH_SIGNATURE_HEADER, signature);
+            }
+        }
+    }
+}
+
+/// Never print credentials.
+impl fmt::Debug for RelayAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::Header { name, .. } => write!(f, "Header({name}: <redacted>)"),
+            Self::Bearer(_) => write!(f, "Bearer(<redacted>)"),
+            Self::Signed(signer) => write!(f, "Signed({})", signer.address()),
+        }
+    }
+}
+
+/// Prefix of the variables holding the authentication of the `index`-th extra relay.
+pub fn extra_relay_auth_prefix(index: usize) -> String {
+    format!("EXTRA_RELAY_{index}_AUTH")
+}
+
+fn path_and_query(request: &Request) -> String {
+    let url = request.url();
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// Send a request to a relay with its authentication applied.
+#[allow(async_fn_in_trait)]
+pub trait RelayRequestExt {
+    async fn send_with(self, auth: &RelayAuth) -> reqwest::Result<Response>;
+
+    /// Like [RelayRequestExt::send_with], waiting on the relay rate limiter first.
+    async fn send_throttled(
+        self,
+        auth: &RelayAuth,
+        limiter: &RelayRateLimiter,
+    ) -> reqwest::Result<Response>;
+}
+
+impl RelayRequestExt for RequestBuilder {
+    async fn send_with(self, auth: &RelayAuth) -> reqwest::Result<Response> {
+        let (client, request) = self.build_split();
+        let mut request = request?;
+        auth.apply(&mut request);
+        client.execute(request).await
+    }
+
+    async fn send_throttled(
+        self,
+        auth: &RelayAuth,
+        limiter: &RelayRateLimiter,
+    ) -> reqwest::Result<Response> {
+        limiter.acquire().await;
+        self.send_with(auth).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use reqwest::{header::AUTHORIZATION, Client};
+
+    use super::{RelayAuth, RELAY_AUTH_SIGNATURE_HEADER, RELAY_AUTH_SIGNER_HEADER};
+
+    #[test]
+    fn test_relay_auth_headers_are_redacted() {
+        let client = Client::new();
+
+        let mut request = client.get("http://localhost:3040/relay").build().unwrap();
+        RelayAuth::Bearer("secret".to_string()).apply(&mut request);
+        let value = request.headers().get(AUTHORIZATION).unwrap();
+        assert!(value.is_sensitive());
+        assert!(!format!("{:?}", request.headers()).contains("secret"));
+        assert!(!format!("{:?}", RelayAuth::Bearer("secret".to_string())).contains("secret"));
+
+        let mut request = client.post("http://localhost:3040/relay").body("{}").build().unwrap();
+        RelayAuth::Signed(alloy::signers::local::PrivateKeySigner::random()).apply(&mut request);
+        assert!(request.headers().contains_key(RELAY_AUTH_SIGNER_HEADER));
+        assert!(request.headers().get(RELAY_AUTH_SIGNATURE_HEADER).unwrap().is_sensitive());
+    }
+
+    #[test]
+    fn test_extra_relays_dont_share_the_relay_auth() {
+        let envs = HashMap::from([
+            ("RELAY_AUTH_BEARER".to_string(), "primary".to_string()),
+            ("EXTRA_RELAY_2_AUTH_HEADER".to_string(), "x-api-key: second".to_string()),
+        ]);
+
+        assert!(
+            matches!(RelayAuth::from_envs(&envs), RelayAuth::Bearer(token) if token == "primary")
+        );
+        assert!(matches!(RelayAuth::from_extra_relay_envs(&envs, 1), RelayAuth::None));
+        assert!(matches!(
+            RelayAuth::from_extra_relay_envs(&envs, 2),
+            RelayAuth::Header { name, value } if name == "x-api-key" && value == "second"
+        ));
+    }
+}
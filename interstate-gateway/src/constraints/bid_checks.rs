@@ -0,0 +1,208 @@
+use std::collections::HashSet;
+
+use alloy::primitives::{Address, B256};
+use ethereum_consensus::deneb::presets::mainnet::ExecutionPayloadHeader;
+
+/// Max size of the extra data of an execution header.
+pub const MAX_EXTRA_DATA_BYTES: usize = 32;
+
+/// Divisor bounding the change of the gas limit from the parent block (EIP-1559).
+const GAS_LIMIT_BOUND_DIVISOR: u64 = 1024;
+
+/// Minimum gas limit of an execution block.
+const MIN_GAS_LIMIT: u64 = 5000;
+
+/// What the headers of the builder bids may contain, besides their consistency with the chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BidPolicy {
+    /// Max size of the extra data, at most [MAX_EXTRA_DATA_BYTES].
+    pub max_extra_data_bytes: usize,
+    /// Fee recipients the bids may pay to, any when empty.
+    pub fee_recipients: HashSet<Address>,
+}
+
+impl Default for BidPolicy {
+    fn default() -> Self {
+        Self { max_extra_data_bytes: MAX_EXTRA_DATA_BYTES, fee_recipients: HashSet::new() }
+    }
+}
+
+/// Our view of the block the bids of a slot must build on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParentHeader {
+    pub hash: B256,
+    pub number: u64,
+    pub gas_limit: u64,
+}
+
+/// What the execution client knows of the parent block of a bid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParentView {
+    Known(ParentHeader),
+    /// The execution client doesn't know the block.
+    Unknown,
+    /// The execution client couldn't be queried, the checks against the parent are skipped.
+    Unavailable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum BidHeaderError {
+    #[error("parent hash {have} doesn't match the requested parent {expected}")]
+    ParentHashMismatch { expected: B256, have: B256 },
+    #[error("parent {0} is unknown to the execution client")]
+    UnknownParent(B256),
+    #[error("block number {have} doesn't follow the parent block {parent}")]
+    BlockNumber { parent: u64, have: u64 },
+    #[error("timestamp {have} doesn't match the timestamp {expected} of the slot")]
+    Timestamp { expected: u64, have: u64 },
+    #[error("gas limit {have} is out of the bounds of the parent gas limit {parent}")]
+    GasLimit { parent: u64, have: u64 },
+    #[error("extra data of {len} bytes, at most {max} allowed")]
+    ExtraData { len: usize, max: usize },
+    #[error("fee recipient {0} isn't allowed")]
+    FeeRecipient(Address),
+}
+
+impl BidHeaderError {
+    /// Stable reason reported in the metrics.
+    pub const fn reason(&self) -> &'static str {
+        match self {
+            Self::ParentHashMismatch { .. } => "parent_hash",
+            Self::UnknownParent(_) => "unknown_parent",
+            Self::BlockNumber { .. } => "block_number",
+            Self::Timestamp { .. } => "timestamp",
+            Self::GasLimit { .. } => "gas_limit",
+            Self::ExtraData { .. } => "extra_data",
+            Self::FeeRecipient(_) => "fee_recipient",
+        }
+    }
+}
+
+/// Sanity checks of the headers returned by the relays, so that a malformed bid is refused
+/// before the proposer signs it rather than missing the slot.
+#[derive(Debug, Clone)]
+pub struct BidHeaderChecker {
+    genesis_time: u64,
+    slot_time: u64,
+    policy: BidPolicy,
+}
+
+impl BidHeaderChecker {
+    pub fn new(genesis_time: u64, slot_time: u64, policy: BidPolicy) -> Self {
+        Self { genesis_time, slot_time, policy }
+    }
+
+    /// Check the `header` bid for `slot` on top of `requested_parent`.
+    pub fn check(
+        &self,
+        slot: u64,
+        requested_parent: B256,
+        parent: ParentView,
+        header: &ExecutionPayloadHeader,
+    ) -> Result<(), BidHeaderError> {
+        let parent_hash = B256::from_slice(header.parent_hash.as_ref());
+        if parent_hash != requested_parent {
+            return Err(BidHeaderError::ParentHashMismatch {
+                expected: requested_parent,
+                have: parent_hash,
+            });
+        }
+
+        let expected = self.genesis_time + slot * self.slot_time;
+        if header.timestamp != expected {
+            return Err(BidHeaderError::Timestamp { expected, have: header.timestamp });
+        }
+
+        match parent {
+            ParentView::Unknown => return Err(BidHeaderError::UnknownParent(parent_hash)),
+            ParentView::Known(parent) => {
+                if header.block_number != parent.number + 1 {
+                    return Err(BidHeaderError::BlockNumber {
+                        parent: parent.number,
+                        have: header.block_number,
+                    });
+                }
+                if !is_gas_limit_in_bounds(parent.gas_limit, header.gas_limit) {
+                    return Err(BidHeaderError::GasLimit {
+                        parent: parent.gas_limit,
+                        have: header.gas_limit,
+                    });
+                }
+            }
+            ParentView::Unavailable => {}
+        }
+
+        let max = self.policy.max_extra_data_bytes.min(MAX_EXTRA_DATA_BYTES);
+        if header.extra_data.len() > max {
+            return Err(BidHeaderError::ExtraData { len: header.extra_data.len(), max });
+        }
+
+        let fee_recipient = Address::from_slice(header.fee_recipient.as_ref());
+        if !self.policy.fee_recipients.is_empty()
+            && !self.policy.fee_recipients.contains(&fee_recipient)
+        {
+            return Err(BidHeaderError::FeeRecipient(fee_recipient));
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `gas_limit` may follow `parent_gas_limit`, per the EIP-1559 bounds.
+fn is_gas_limit_in_bounds(parent_gas_limit: u64, gas_limit: u64) -> bool {
+    gas_limit >= MIN_GAS_LIMIT
+        && gas_limit.abs_diff(parent_gas_limit) < parent_gas_limit / GAS_LIMIT_BOUND_DIVISOR
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{Address, B256};
+    use ethereum_consensus::deneb::presets::mainnet::ExecutionPayloadHeader;
+
+    use super::{BidHeaderChecker, BidHeaderError, BidPolicy, ParentHeader, ParentView};
+
+    #[test]
+    fn test_bid_header_checks() {
+        let parent = ParentHeader { hash: B256::repeat_byte(1), number: 99, gas_limit: 30_000_000 };
+        let recipient = Address::repeat_byte(2);
+        let policy = BidPolicy { fee_recipients: [recipient].into(), ..Default::default() };
+        let checker = BidHeaderChecker::new(1_000, 12, policy);
+
+        let mut header = ExecutionPayloadHeader::default();
+        header.parent_hash = parent.hash.as_slice().try_into().unwrap();
+        header.fee_recipient = recipient.as_slice().try_into().unwrap();
+        header.block_number = 100;
+        header.timestamp = 1_200;
+        header.gas_limit = 30_029_000;
+        assert_eq!(checker.check(17, parent.hash, ParentView::Known(parent), &header), Ok(()));
+
+        // Built on another parent, or for another slot
+        let other = B256::repeat_byte(3);
+        assert!(matches!(
+            checker.check(17, other, ParentView::Known(parent), &header),
+            Err(BidHeaderError::ParentHashMismatch { .. })
+        ));
+        assert_eq!(
+            checker.check(18, parent.hash, ParentView::Known(parent), &header),
+            Err(BidHeaderError::Timestamp { expected: 1_216, have: 1_200 })
+        );
+        assert_eq!(
+            checker.check(17, parent.hash, ParentView::Unknown, &header),
+            Err(BidHeaderError::UnknownParent(parent.hash))
+        );
+
+        // The gas limit moves by less than 1/1024 of the parent one, unchecked without a parent
+        header.gas_limit = 30_030_000;
+        assert_eq!(
+            checker.check(17, parent.hash, ParentView::Known(parent), &header),
+            Err(BidHeaderError::GasLimit { parent: 30_000_000, have: 30_030_000 })
+        );
+        assert_eq!(checker.check(17, parent.hash, ParentView::Unavailable, &header), Ok(()));
+
+        header.fee_recipient = Default::default();
+        assert_eq!(
+            checker.check(17, parent.hash, ParentView::Unavailable, &header),
+            Err(BidHeaderError::FeeRecipient(Address::ZERO))
+        );
+    }
+}
@@ -5,7 +5,7 @@ use alloy::{
         eip2718::Encodable2718, eip4895::Withdrawal, BlockNumberOrTag,
     },
     hex::FromHex,
-    primitives::{Address, Bloom, Bytes, B256, B64, U256},
+    primitives::{b256, Address, Bloom, Bytes, B256, B64, U256},
     rpc::{
         client::{ClientBuilder, RpcClient},
         types::{
@@ -34,6 +34,7 @@ use ethereum_consensus::{
     },
     ssz::prelude::{ssz_rs, ByteList, ByteVector, HashTreeRoot, List},
     types::mainnet::ExecutionPayload as ConsensusExecutionPayload,
+    Fork,
 };
 
 use regex::Regex;
@@ -43,13 +44,16 @@ use beacon_api_client::{presets::mainnet::Client as BeaconRPCClient, BlockId, St
 use reqwest::{Client, Url};
 use serde_json::Value;
 
-use crate::config::Config;
+use crate::{
+    config::{ChainConfig, Config},
+    utils::retry::{retry_with_backoff, RetryError, RetryPolicy},
+};
 
 use super::builder::BuilderError;
-use std::time::Duration;
-use tokio::time::timeout;
 
-const GET_BLOCK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Requests hash of the blocks without execution requests (EIP-7685), sha256 of nothing.
+const EMPTY_REQUESTS_HASH: B256 =
+    b256!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
 
 /// Extra-data payload field used for locally built blocks, decoded in UTF-8.
 ///
@@ -59,12 +63,14 @@ const DEFAULT_EXTRA_DATA: [u8; 20] = [
 ];
 
 pub struct BlockBuilder {
+    chain: ChainConfig,
     el_rpc_client: ExecutionRpcClient,
     beacon_rpc_client: BeaconRPCClient,
     extra_data: Bytes,
     fee_recipient: Address,
     engine_hinter: EngineHinter,
     slot_time_in_seconds: u64,
+    retry: RetryPolicy,
 }
 
 impl BlockBuilder {
@@ -77,41 +83,32 @@ impl BlockBuilder {
         };
 
         Self {
+            chain: config.chain.clone(),
             engine_hinter,
             extra_data: DEFAULT_EXTRA_DATA.into(),
             fee_recipient: config.fee_recipient,
-            beacon_rpc_client: BeaconRPCClient::new(config.beacon_api_url.clone()),
+            beacon_rpc_client: config.outbound_headers.beacon_client(config.beacon_api_url.clone()),
             el_rpc_client: ExecutionRpcClient::new(config.execution_api_url.clone()),
             slot_time_in_seconds: config.chain.get_slot_time_in_seconds(),
+            retry: config.retry,
         }
     }
 
-    const MAX_RETRIES: u32 = 5;
-    const RETRY_DELAY: Duration = Duration::from_secs(2);
-
     async fn get_latest_block(&self) -> Result<Block, BuilderError> {
-        let mut retries = 0;
-        loop {
-            let res = timeout(GET_BLOCK_TIMEOUT, self.el_rpc_client.get_block(None, true)).await;
-
-            match res {
-                Ok(block) => {
-                    tracing::debug!("got latest block");
-                    return block.map_err(BuilderError::RpcError);
-                }
-                Err(_) if retries < Self::MAX_RETRIES => {
-                    retries += 1;
-                    tokio::time::sleep(Self::RETRY_DELAY).await;
-                }
-                Err(err) => {
-                    return Err(BuilderError::Timeout(format!(
-                        "Getting latest block timed out after {} retries: {}",
-                        Self::MAX_RETRIES,
-                        err
-                    )))
-                }
-            }
-        }
+        let block = retry_with_backoff("latest_block", &self.retry, || {
+            self.el_rpc_client.get_block(None, true)
+        })
+        .await
+        .map_err(|err| match err {
+            RetryError::Timeout(elapsed) => BuilderError::Timeout(format!(
+                "Getting latest block timed out after {} attempts: {elapsed}",
+                self.retry.max_attempts
+            )),
+            RetryError::Failed(err) => BuilderError::RpcError(err),
+        })?;
+
+        tracing::debug!("got latest block");
+        Ok(block)
     }
 
     pub async fn build_sealed_block(
@@ -163,11 +160,12 @@ impl BlockBuilder {
         );
         tracing::debug!(parent = ?parent_beacon_block_root, "got parent_beacon_block_root");
 
-        let versioned_hashes = txs
-            .iter()
-            .flat_map(|tx| tx.blob_versioned_hashes())
-            .flatten()
-            .collect::<Vec<_>>();
+        let BlobGasFields { versioned_hashes, blob_gas_used, excess_blob_gas } =
+            BlobGasFields::new(
+                txs,
+                latest_block.header.excess_blob_gas.unwrap_or_default(),
+                latest_block.header.blob_gas_used.unwrap_or_default(),
+            );
         tracing::info!(amount = ?versioned_hashes.len(), "got versioned_hashes");
 
         let base_fee = calc_next_block_base_fee(
@@ -177,16 +175,10 @@ impl BlockBuilder {
             BaseFeeParams::ethereum(),
         ) as u64;
 
-        let excess_blob_gas = calc_excess_blob_gas(
-            latest_block.header.excess_blob_gas.unwrap_or_default(),
-            latest_block.header.blob_gas_used.unwrap_or_default(),
-        ) as u64;
-
-        let blob_gas_used = txs
-            .iter()
-            .fold(0, |acc, tx| acc + tx.blob_gas_used().unwrap_or_default());
-
+        // The Electra blocks commit to their execution requests, none for the local blocks
+        let electra = self.chain.fork_at(slot) == Fork::Electra;
         let ctx = Context {
+            requests_hash: electra.then_some(EMPTY_REQUESTS_HASH),
             base_fee,
             blob_gas_used,
             excess_blob_gas,
@@ -228,7 +220,12 @@ impl BlockBuilder {
 
             let engine_hint = self
                 .engine_hinter
-                .fetch_next_payload_hint(&exec_payload, &versioned_hashes, parent_beacon_block_root)
+                .fetch_next_payload_hint(
+                    &exec_payload,
+                    &versioned_hashes,
+                    parent_beacon_block_root,
+                    electra,
+                )
                 .await?;
 
             tracing::debug!("engine_hint: {:?}", engine_hint);
@@ -464,6 +461,7 @@ pub(crate) fn create_consensus_execution_payload(value: &SealedBlock) -> Consens
 
 #[derive(Debug, Default)]
 struct Context {
+    requests_hash: Option<B256>,
     extra_data: Bytes,
     base_fee: u64,
     blob_gas_used: u64,
@@ -545,21 +543,32 @@ impl EngineHinter {
 
         Err(BuilderError::Custom("EL node is unreachable".to_owned()))
     }
-    /// Fetch the next payload hint from the engine API to complete the sealed block.
+    /// Fetch the next payload hint from the engine API to complete the sealed block. Electra
+    /// payloads are sent with their (empty) execution requests.
     pub async fn fetch_next_payload_hint(
         &self,
         exec_payload: &AlloyExecutionPayload,
         versioned_hashes: &[B256],
         parent_beacon_root: B256,
+        electra: bool,
     ) -> Result<EngineApiHint, BuilderError> {
         let auth_jwt = secret_to_bearer_header(&JwtSecret::from_hex(&self.jwt_hex)?);
 
-        let body = format!(
-            r#"{{"id":1,"jsonrpc":"2.0","method":"engine_newPayloadV3","params":[{}, {}, "{:?}"]}}"#,
-            serde_json::to_string(&exec_payload)?,
-            serde_json::to_string(&versioned_hashes)?,
-            parent_beacon_root
-        );
+        let body = if electra {
+            format!(
+                r#"{{"id":1,"jsonrpc":"2.0","method":"engine_newPayloadV4","params":[{}, {}, "{:?}", []]}}"#,
+                serde_json::to_string(&exec_payload)?,
+                serde_json::to_string(&versioned_hashes)?,
+                parent_beacon_root
+            )
+        } else {
+            format!(
+                r#"{{"id":1,"jsonrpc":"2.0","method":"engine_newPayloadV3","params":[{}, {}, "{:?}"]}}"#,
+                serde_json::to_string(&exec_payload)?,
+                serde_json::to_string(&versioned_hashes)?,
+                parent_beacon_root
+            )
+        };
 
         let raw_hint = self
             .client
@@ -664,11 +673,42 @@ fn build_header_with_hints_and_context(
         blob_gas_used: Some(context.blob_gas_used),
         excess_blob_gas: Some(context.excess_blob_gas),
         parent_beacon_block_root: Some(context.parent_beacon_block_root),
-        requests_hash: None,
+        requests_hash: context.requests_hash,
         extra_data: context.extra_data.clone(),
     }
 }
 
+/// Blob fields of a block including `txs`, built on top of a parent with the given blob gas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BlobGasFields {
+    versioned_hashes: Vec<B256>,
+    blob_gas_used: u64,
+    excess_blob_gas: u64,
+}
+
+impl BlobGasFields {
+    fn new(
+        txs: &[TransactionSigned],
+        parent_excess_blob_gas: u64,
+        parent_blob_gas_used: u64,
+    ) -> Self {
+        let versioned_hashes = txs
+            .iter()
+            .flat_map(|tx| tx.blob_versioned_hashes())
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let blob_gas_used = txs
+            .iter()
+            .fold(0, |acc, tx| acc + tx.blob_gas_used().unwrap_or_default());
+
+        let excess_blob_gas =
+            calc_excess_blob_gas(parent_excess_blob_gas, parent_blob_gas_used) as u64;
+
+        Self { versioned_hashes, blob_gas_used, excess_blob_gas }
+    }
+}
+
 pub(crate) fn to_bytes32(value: B256) -> spec::Bytes32 {
     spec::Bytes32::try_from(value.as_ref()).unwrap()
 }
@@ -684,20 +724,31 @@ pub(crate) fn to_byte_vector(value: Bloom) -> ByteVector<256> {
 #[cfg(test)]
 mod tests {
     use alloy::{
-        eips::eip2718::Encodable2718,
+        eips::{
+            eip2718::Encodable2718,
+            eip4844::{DATA_GAS_PER_BLOB, TARGET_DATA_GAS_PER_BLOCK},
+        },
         network::{EthereumWallet, TransactionBuilder},
-        primitives::{hex, keccak256, Address},
+        primitives::{hex, keccak256, Address, TxKind, U256},
         signers::{k256::ecdsa::SigningKey, local::PrivateKeySigner, Signer},
     };
+    use reth_primitives::TxType;
 
+    use super::BlobGasFields;
     use crate::{
         commitment::request::PreconfRequest,
-        constraints::{ConstraintsMessage, SignedConstraints},
+        constraints::{ConstraintsMessage, SignedConstraints, TransactionExt},
         state::Block,
-        test_utils::{default_test_transaction, get_test_config},
+        test_utils::{
+            default_test_7702_transaction, default_test_blob_transaction,
+            default_test_transaction, get_test_config, BlobFixture,
+        },
         BLSBytes, BLS_DST_PREFIX,
     };
-    use crate::{constraints::Constraint, utils::create_random_bls_secretkey};
+    use crate::{
+        constraints::{versioned::ConstraintsVersion, Constraint},
+        utils::create_random_bls_secretkey,
+    };
     use ethereum_consensus::crypto::PublicKey as ECBlsPublicKey;
 
     #[tokio::test]
@@ -739,6 +790,11 @@ mod tests {
             sender: addy,
             slot: 42,
             chain_id: 171000,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
         };
 
         // println!("preconf request {:#?}", request);
@@ -764,4 +820,139 @@ mod tests {
         assert_eq!(block.signed_constraints_list.len(), 1);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_blob_transaction_fallback_fields() -> eyre::Result<()> {
+        let fixture = BlobFixture::load();
+
+        let raw_sk = "5d2344259f42259f82d2c140aa66102ba89b57b4883ee441a8b312622bd42491".to_string();
+        let sk = SigningKey::from_slice(hex::decode(raw_sk)?.as_slice())?;
+        let signer = PrivateKeySigner::from_signing_key(sk.clone());
+        let wallet = EthereumWallet::from(signer.clone());
+
+        let addy = Address::from_private_key(&sk);
+        let tx_signed = default_test_blob_transaction(addy, Some(1), 2).build(&wallet).await?;
+        let raw_encoded = tx_signed.encoded_2718();
+
+        // 1. request validation
+        let constraint = Constraint::decode_enveloped(&mut raw_encoded.as_slice())?;
+        assert_eq!(constraint.tx.tx_type(), TxType::Eip4844);
+        assert!(constraint.validate(addy));
+
+        let message_digest = keccak256(constraint.tx.hash());
+        let request = PreconfRequest {
+            signature: signer.sign_hash(&message_digest).await?,
+            txs: vec![constraint],
+            sender: addy,
+            slot: 42,
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+        assert!(request.validate_chain_id(1337));
+        assert!(request.validate_max_priority_fee());
+        assert!(request.validate_tx_size_limit(4 * 32 * 1024));
+
+        // 2. constraint signing
+        let validator_pubkey =
+            ECBlsPublicKey::try_from(create_random_bls_secretkey().sk_to_pk().to_bytes().as_ref())
+                .unwrap();
+        let message = ConstraintsMessage::build(validator_pubkey, request);
+        let signature = BLSBytes::from(
+            create_random_bls_secretkey().sign(&message.digest(), BLS_DST_PREFIX, &[]).to_bytes(),
+        );
+
+        let mut block = Block::default();
+        block.add_constraints(SignedConstraints { message, signature });
+
+        // 3. blobs bundle assembly
+        let bundle = block.parse_to_blobs_bundle();
+        assert_eq!(bundle.blobs.len(), 2);
+        assert!(bundle.commitments.iter().all(|c| c.as_ref() == fixture.commitment.as_slice()));
+        assert!(bundle.proofs.iter().all(|p| p.as_ref() == fixture.proof.as_slice()));
+
+        // 4. fallback payload blob fields, on top of a parent at the target blob gas
+        let txs = block.convert_constraints_to_transactions();
+        let fields = BlobGasFields::new(&txs, TARGET_DATA_GAS_PER_BLOCK, 2 * DATA_GAS_PER_BLOB);
+        assert_eq!(
+            fields,
+            BlobGasFields {
+                versioned_hashes: vec![fixture.versioned_hash; 2],
+                blob_gas_used: 2 * DATA_GAS_PER_BLOB,
+                excess_blob_gas: 2 * DATA_GAS_PER_BLOB,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_eip7702_transaction_pipeline() -> eyre::Result<()> {
+        let raw_sk = "5d2344259f42259f82d2c140aa66102ba89b57b4883ee441a8b312622bd42491".to_string();
+        let sk = SigningKey::from_slice(hex::decode(raw_sk)?.as_slice())?;
+        let signer = PrivateKeySigner::from_signing_key(sk.clone());
+        let wallet = EthereumWallet::from(signer.clone());
+
+        let addy = Address::from_private_key(&sk);
+        let delegate = Address::repeat_byte(0x77);
+        let tx_signed = default_test_7702_transaction(&signer, Some(1), delegate).build(&wallet).await?;
+        let raw_encoded = tx_signed.encoded_2718();
+
+        // 1. request validation, on the accessors that used to be unimplemented
+        let constraint = Constraint::decode_enveloped(&mut raw_encoded.as_slice())?;
+        assert_eq!(constraint.tx.tx_type(), TxType::Eip7702);
+        assert!(constraint.validate(addy));
+        assert_eq!(constraint.tx.gas_limit(), 100_000);
+        assert_eq!(constraint.tx.value(), U256::from(100));
+        assert_eq!(constraint.tx.tx_kind(), TxKind::Call(Address::ZERO));
+        assert_eq!(constraint.tx.chain_id(), Some(1337));
+        assert!(constraint.tx.input().is_empty());
+        assert!(constraint.tx.size() > 0);
+        assert!(constraint.tx.blob_sidecar().is_none());
+
+        let message_digest = keccak256(constraint.tx.hash());
+        let request = PreconfRequest {
+            signature: signer.sign_hash(&message_digest).await?,
+            txs: vec![constraint],
+            sender: addy,
+            slot: 42,
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+        assert!(request.validate_chain_id(1337));
+        assert!(request.validate_max_priority_fee());
+        assert!(request.validate_tx_size_limit(4 * 32 * 1024));
+        assert_eq!(request.tx_gas_limits().collect::<Vec<_>>(), [100_000]);
+
+        // 2. constraint digesting and signing, in both message formats
+        let validator_pubkey =
+            ECBlsPublicKey::try_from(create_random_bls_secretkey().sk_to_pk().to_bytes().as_ref())
+                .unwrap();
+        let message = ConstraintsMessage::build(validator_pubkey, request);
+        assert_ne!(
+            message.digest_for(ConstraintsVersion::V1),
+            message.digest_for(ConstraintsVersion::V2)
+        );
+        let signature = BLSBytes::from(
+            create_random_bls_secretkey().sign(&message.digest(), BLS_DST_PREFIX, &[]).to_bytes(),
+        );
+        let mut block = Block::default();
+        block.add_constraints(SignedConstraints { message, signature });
+        assert_eq!(block.committed_gas(), 100_000);
+
+        // 3. fallback block inclusion
+        let txs = block.convert_constraints_to_transactions();
+        assert_eq!(txs.len(), 1);
+        assert_eq!(txs[0].tx_type(), TxType::Eip7702);
+        assert_eq!(txs[0].encoded_2718(), raw_encoded);
+
+        Ok(())
+    }
 }
@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
 use alloy::hex::hex;
+use alloy::primitives::B256;
 use alloy::transports::TransportError;
+#[cfg(feature = "fallback-builder")]
 use blst::min_pk::SecretKey as BLSSecretKey;
 use ethereum_consensus::{
     crypto::{KzgCommitment, PublicKey as BlsPublicKey, Signature as BlsSignature},
@@ -7,7 +11,7 @@ use ethereum_consensus::{
         self,
         mainnet::{BlobsBundle, MAX_BLOB_COMMITMENTS_PER_BLOCK},
         presets::mainnet::ExecutionPayloadHeader,
-        Hash32,
+        Bytes32, ExecutionAddress, Hash32,
     },
     serde::as_str,
     ssz::prelude::*,
@@ -15,14 +19,32 @@ use ethereum_consensus::{
     Fork,
 };
 
-use crate::config::{ChainConfig, Config};
+#[cfg(feature = "fallback-builder")]
+use crate::config::ChainConfig;
+use crate::config::Config;
 use crate::state::Block;
 
+use super::proofs::InclusionProofs;
+#[cfg(feature = "fallback-builder")]
+use crate::{constraints::SignedConstraints, metrics::ApiMetrics};
+
+/// Number of slots the fallback payloads are kept for before the slot of the latest one.
+const PAYLOAD_CACHE_SLOTS: u64 = 2;
+
+/// Max number of attempts at building a fallback payload, retried on transient errors only.
+#[cfg(feature = "fallback-builder")]
+const MAX_BUILD_ATTEMPTS: usize = 2;
+
+#[cfg(feature = "fallback-builder")]
+use reth_primitives::SealedBlock;
+
+#[cfg(feature = "fallback-builder")]
 use super::{
     block_builder::{
         create_consensus_execution_payload, create_execution_payload_header, BlockBuilder,
     },
     signature::sign_builder_message,
+    value::{BlockValueSource, HttpValueEstimator, ValueEstimator},
 };
 
 #[derive(Debug, serde::Deserialize)]
@@ -54,6 +76,38 @@ pub struct PayloadAndBid {
     pub payload: GetPayloadResponse,
 }
 
+/// Fallback payloads by slot, so that the payload of a slot is never served for another.
+/// Expired once the payload of a slot [PAYLOAD_CACHE_SLOTS] later is built.
+#[derive(Debug, Default)]
+pub struct PayloadCache {
+    payloads: BTreeMap<u64, PayloadAndBid>,
+}
+
+impl PayloadCache {
+    pub fn insert(&mut self, slot: u64, payload_and_bid: PayloadAndBid) {
+        self.payloads.insert(slot, payload_and_bid);
+        self.payloads = self.payloads.split_off(&slot.saturating_sub(PAYLOAD_CACHE_SLOTS));
+    }
+
+    /// Forget the payload of `slot`, such as when it can't be rebuilt.
+    pub fn remove(&mut self, slot: u64) {
+        self.payloads.remove(&slot);
+    }
+
+    /// Take the payload built for `slot`, none when only other slots have one.
+    pub fn take(&mut self, slot: u64) -> Option<PayloadAndBid> {
+        self.payloads.remove(&slot)
+    }
+
+    pub fn len(&self) -> usize {
+        self.payloads.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.payloads.is_empty()
+    }
+}
+
 #[derive(Debug, serde::Serialize, serde::Deserialize, Clone)]
 #[serde(tag = "version", content = "data")]
 pub enum GetPayloadResponse {
@@ -87,6 +141,17 @@ impl GetPayloadResponse {
     }
 }
 
+impl GetPayloadResponse {
+    /// The response serving `payload_and_blobs` for a block of `fork`. The payloads of Deneb
+    /// and Electra share the same fields, only their version tells them apart.
+    pub fn for_fork(fork: Fork, payload_and_blobs: PayloadAndBlobs) -> Self {
+        match fork {
+            Fork::Electra => GetPayloadResponse::Electra(payload_and_blobs),
+            _ => GetPayloadResponse::from(payload_and_blobs),
+        }
+    }
+}
+
 impl From<PayloadAndBlobs> for GetPayloadResponse {
     fn from(payload_and_blobs: PayloadAndBlobs) -> Self {
         match payload_and_blobs.execution_payload.version() {
@@ -100,12 +165,112 @@ impl From<PayloadAndBlobs> for GetPayloadResponse {
     }
 }
 
+/// Max execution requests of each type in a payload, as of the Electra mainnet preset.
+pub const MAX_DEPOSIT_REQUESTS_PER_PAYLOAD: usize = 8192;
+pub const MAX_WITHDRAWAL_REQUESTS_PER_PAYLOAD: usize = 16;
+pub const MAX_CONSOLIDATION_REQUESTS_PER_PAYLOAD: usize = 2;
+
+#[derive(Debug, Default, Clone, SimpleSerialize, serde::Serialize, serde::Deserialize)]
+pub struct DepositRequest {
+    pub pubkey: BlsPublicKey,
+    pub withdrawal_credentials: Bytes32,
+    #[serde(with = "as_str")]
+    pub amount: u64,
+    pub signature: BlsSignature,
+    #[serde(with = "as_str")]
+    pub index: u64,
+}
+
+#[derive(Debug, Default, Clone, SimpleSerialize, serde::Serialize, serde::Deserialize)]
+pub struct WithdrawalRequest {
+    pub source_address: ExecutionAddress,
+    pub validator_pubkey: BlsPublicKey,
+    #[serde(with = "as_str")]
+    pub amount: u64,
+}
+
+#[derive(Debug, Default, Clone, SimpleSerialize, serde::Serialize, serde::Deserialize)]
+pub struct ConsolidationRequest {
+    pub source_address: ExecutionAddress,
+    pub source_pubkey: BlsPublicKey,
+    pub target_pubkey: BlsPublicKey,
+}
+
+/// Requests of the execution layer to the consensus layer carried by the Electra payloads.
+#[derive(Debug, Default, Clone, SimpleSerialize, serde::Serialize, serde::Deserialize)]
+pub struct ExecutionRequests {
+    pub deposits: List<DepositRequest, MAX_DEPOSIT_REQUESTS_PER_PAYLOAD>,
+    pub withdrawals: List<WithdrawalRequest, MAX_WITHDRAWAL_REQUESTS_PER_PAYLOAD>,
+    pub consolidations: List<ConsolidationRequest, MAX_CONSOLIDATION_REQUESTS_PER_PAYLOAD>,
+}
+
+/// A builder bid of any supported fork. The Electra bids carry the execution requests of
+/// their payload in the message, next to the fields of the Deneb ones.
 #[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(from = "SignedBuilderBidJson", into = "SignedBuilderBidJson")]
 pub struct SignedBuilderBid {
     pub message: BuilderBid,
+    pub execution_requests: Option<ExecutionRequests>,
     pub signature: BlsSignature,
 }
 
+impl SignedBuilderBid {
+    /// The fork of the bid, Electra when it carries execution requests.
+    pub fn fork(&self) -> Fork {
+        if self.execution_requests.is_some() {
+            Fork::Electra
+        } else {
+            Fork::Deneb
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SignedBuilderBidJson {
+    message: BuilderBidJson,
+    signature: BlsSignature,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BuilderBidJson {
+    #[serde(flatten)]
+    bid: BuilderBid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    execution_requests: Option<ExecutionRequests>,
+}
+
+impl From<SignedBuilderBidJson> for SignedBuilderBid {
+    fn from(json: SignedBuilderBidJson) -> Self {
+        Self {
+            message: json.message.bid,
+            execution_requests: json.message.execution_requests,
+            signature: json.signature,
+        }
+    }
+}
+
+impl From<SignedBuilderBid> for SignedBuilderBidJson {
+    fn from(bid: SignedBuilderBid) -> Self {
+        Self {
+            message: BuilderBidJson {
+                bid: bid.message,
+                execution_requests: bid.execution_requests,
+            },
+            signature: bid.signature,
+        }
+    }
+}
+
+/// A relay bid with the proofs of inclusion of the constraints of its slot, stripped before
+/// the bid is returned to the proposer.
+#[derive(Debug, Default, Clone, serde::Serialize, serde::Deserialize)]
+pub struct SignedBuilderBidWithProofs {
+    #[serde(flatten)]
+    pub bid: SignedBuilderBid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proofs: Option<InclusionProofs>,
+}
+
 #[derive(Debug, Default, Clone, SimpleSerialize, serde::Serialize, serde::Deserialize)]
 pub struct BuilderBid {
     pub header: ExecutionPayloadHeader,
@@ -116,27 +281,56 @@ pub struct BuilderBid {
     pub public_key: BlsPublicKey,
 }
 
+/// The message of the Electra builder bids, as signed.
+#[derive(Debug, Default, Clone, SimpleSerialize)]
+pub struct ElectraBuilderBid {
+    pub header: ExecutionPayloadHeader,
+    pub blob_kzg_commitments: List<KzgCommitment, MAX_BLOB_COMMITMENTS_PER_BLOCK>,
+    pub execution_requests: ExecutionRequests,
+    pub value: U256,
+    pub public_key: BlsPublicKey,
+}
+
+#[cfg(feature = "fallback-builder")]
 pub struct FallbackBuilder {
     // be used to sign the block bid
     bls_secret_key: BLSSecretKey,
     // chain config
     chain: ChainConfig,
-    // block generator
-    block_builder: BlockBuilder,
-    // the last built block with bid
-    payload_and_bid: Option<PayloadAndBid>,
+    // block generator, with its beacon and execution clients created on the first build
+    block_builder: Option<BlockBuilder>,
+    config: Config,
+    // prices the built blocks
+    value_source: BlockValueSource,
+    // the built blocks with their bid, by slot
+    payloads: PayloadCache,
 }
 
+#[cfg(feature = "fallback-builder")]
 impl FallbackBuilder {
     pub fn new(config: &Config) -> Self {
         Self {
             bls_secret_key: config.builder_bls_private_key.clone(),
             chain: config.chain.clone(),
-            block_builder: BlockBuilder::new(config),
-            payload_and_bid: None,
+            block_builder: None,
+            config: config.clone(),
+            value_source: BlockValueSource::new(
+                config.fallback_value_estimator_url.clone().map(|url| {
+                    Box::new(HttpValueEstimator::new(url)) as Box<dyn ValueEstimator>
+                }),
+                config.fallback_bid_value_wei,
+            ),
+            payloads: PayloadCache::default(),
         }
     }
 
+    /// Price the built blocks with `estimator`, falling back to the configured static value.
+    pub fn with_value_estimator(mut self, estimator: Box<dyn ValueEstimator>) -> Self {
+        self.value_source =
+            BlockValueSource::new(Some(estimator), self.config.fallback_bid_value_wei);
+        self
+    }
+
     pub async fn build_fallback_payload(
         &mut self,
         block: &Block,
@@ -147,19 +341,42 @@ impl FallbackBuilder {
         let kzg_commitments = blobs_bundle.commitments.clone();
 
         // 1. build a fallback payload with the given transactions, on top of
-        // the current head of the chain
-        let sealed_block = self
-            .block_builder
-            .build_sealed_block(&transactions, slot)
-            .await?;
-
-        // NOTE: we use a big value for the bid to ensure it gets chosen by mev-boost.
-        // the client has no way to actually verify this, and we don't need to trust
-        // an external relay as this block is self-built, so the fake bid value is fine.
+        // the current head of the chain, and make sure it includes all of them
+        let block_builder =
+            self.block_builder.get_or_insert_with(|| BlockBuilder::new(&self.config));
+        let mut attempt = 1;
+        let sealed_block = loop {
+            match block_builder.build_sealed_block(&transactions, slot).await {
+                Ok(sealed_block) => break sealed_block,
+                Err(err) if err.is_transient() && attempt < MAX_BUILD_ATTEMPTS => {
+                    tracing::warn!(?err, slot, attempt, "Rebuilding fallback payload");
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        };
+
+        // The same inputs build the same payload, a missing transaction is never fixed by a
+        // rebuild
+        if let Err(err) = verify_committed_transactions(block, &sealed_block) {
+            // Never serve a payload that would break our commitments
+            tracing::error!(
+                ?err,
+                slot,
+                "CRITICAL: fallback payload misses committed transactions, refusing to serve it"
+            );
+            ApiMetrics::increment_fallback_payload_rejected_count();
+            self.payloads.remove(slot);
+            return Err(err);
+        }
+
+        // NOTE: without an estimator we use a big static value for the bid to ensure it gets
+        // chosen by mev-boost. The client has no way to actually verify this, and we don't
+        // need to trust an external relay as this block is self-built.
         //
         // NOTE: we don't strictly need this. The validator & beacon nodes have options
         // to ALWAYS prefer PBS blocks. This is a safety measure that doesn't hurt to keep.
-        let value = U256::from(1_000_000_000_000_000_000u128);
+        let value = U256::from(self.value_source.value(slot, &sealed_block).await);
 
         let eth_payload = create_consensus_execution_payload(&sealed_block);
         let payload_and_blobs = PayloadAndBlobs {
@@ -170,33 +387,42 @@ impl FallbackBuilder {
         // 2. create a signed builder bid with the sealed block header we just created
         let eth_header = create_execution_payload_header(&sealed_block, transactions);
 
-        // 3. sign the bid with the local builder's BLS key
-        let signed_bid = self.create_signed_builder_bid(value, eth_header, kzg_commitments)?;
+        // 3. sign the bid with the local builder's BLS key. The local blocks carry no execution
+        // requests, the engine refuses the ones that would
+        let fork = self.chain.fork_at(slot);
+        let execution_requests = (fork == Fork::Electra).then(ExecutionRequests::default);
+        let signed_bid =
+            self.create_signed_builder_bid(value, eth_header, kzg_commitments, execution_requests)?;
 
         // 4. prepare a get_payload response for when the beacon node will ask for it
-        let get_payload_response = GetPayloadResponse::from(payload_and_blobs);
+        let get_payload_response = GetPayloadResponse::for_fork(fork, payload_and_blobs);
 
-        self.payload_and_bid = Some(PayloadAndBid {
-            bid: signed_bid,
-            payload: get_payload_response,
-        });
+        let payload_and_bid = PayloadAndBid { bid: signed_bid, payload: get_payload_response };
+        self.payloads.insert(slot, payload_and_bid);
 
         Ok(())
     }
 
-    /// Get the cached payload and bid from the local builder, consuming the value.
+    /// Get the cached payload and bid built for `slot`, consuming the value.
     #[inline]
-    pub fn get_cached_payload(&mut self) -> Option<PayloadAndBid> {
-        self.payload_and_bid.take()
+    pub fn get_cached_payload(&mut self, slot: u64) -> Option<PayloadAndBid> {
+        self.payloads.take(slot)
+    }
+
+    /// Whether a payload is built for the proposal slots without constraints.
+    #[inline]
+    pub fn always_builds(&self) -> bool {
+        self.config.fallback_always_build
     }
 
     /// transform a sealed header into a signed builder bid using
-    /// the local builder's BLS key.
+    /// the local builder's BLS key. Electra bids carry the `execution_requests` of the payload.
     fn create_signed_builder_bid(
         &self,
         value: U256,
         header: ExecutionPayloadHeader,
         blob_kzg_commitments: Vec<KzgCommitment>,
+        execution_requests: Option<ExecutionRequests>,
     ) -> Result<SignedBuilderBid, BuilderError> {
         // compat: convert from blst to ethereum consensus types
         let pubkey = self.bls_secret_key.sk_to_pk().to_bytes();
@@ -211,9 +437,93 @@ impl FallbackBuilder {
             value,
         };
 
-        let signature = sign_builder_message(&self.chain, &self.bls_secret_key, &message)?;
+        let signature = match &execution_requests {
+            Some(execution_requests) => {
+                let message = ElectraBuilderBid {
+                    header: message.header.clone(),
+                    blob_kzg_commitments: message.blob_kzg_commitments.clone(),
+                    execution_requests: execution_requests.clone(),
+                    value: message.value,
+                    public_key: message.public_key.clone(),
+                };
+                sign_builder_message(&self.chain, &self.bls_secret_key, &message)?
+            }
+            None => sign_builder_message(&self.chain, &self.bls_secret_key, &message)?,
+        };
 
-        Ok(SignedBuilderBid { message, signature })
+        Ok(SignedBuilderBid { message, execution_requests, signature })
+    }
+}
+
+/// Verify that the sealed block contains every committed transaction of the slot in a valid
+/// position: constraints flagged as `top` must open the block in order, the others must be
+/// included in order anywhere after them.
+#[cfg(feature = "fallback-builder")]
+fn verify_committed_transactions(
+    block: &Block,
+    sealed_block: &SealedBlock,
+) -> Result<(), BuilderError> {
+    let included = sealed_block
+        .body
+        .transactions
+        .iter()
+        .map(|tx| tx.hash())
+        .collect::<Vec<_>>();
+
+    let (top, rest): (Vec<_>, Vec<_>) =
+        block.signed_constraints_list.iter().partition(|sc| sc.message.top);
+    let committed_hashes = |constraints: Vec<&SignedConstraints>| {
+        constraints
+            .into_iter()
+            .flat_map(|sc| sc.message.transactions.iter().map(|c| *c.tx.hash()))
+            .collect::<Vec<_>>()
+    };
+    let (top, rest) = (committed_hashes(top), committed_hashes(rest));
+
+    if let Some((position, hash)) =
+        top.iter().enumerate().find(|(i, hash)| included.get(*i) != Some(*hash))
+    {
+        return Err(BuilderError::MisplacedCommittedTransaction(*hash, position));
+    }
+
+    let mut remaining = included[top.len()..].iter();
+    for hash in rest {
+        if !remaining.any(|included| *included == hash) {
+            return Err(BuilderError::MissingCommittedTransaction(hash));
+        }
+    }
+
+    Ok(())
+}
+
+/// Stand-in for the local builder when the sidecar is built without the `fallback-builder`
+/// feature. It never produces a payload, so the proxy always defers to the relays.
+#[cfg(not(feature = "fallback-builder"))]
+pub struct FallbackBuilder;
+
+#[cfg(not(feature = "fallback-builder"))]
+impl FallbackBuilder {
+    pub fn new(_config: &Config) -> Self {
+        Self
+    }
+
+    pub async fn build_fallback_payload(
+        &mut self,
+        _block: &Block,
+        slot: u64,
+    ) -> Result<(), BuilderError> {
+        tracing::debug!(slot, "Fallback builder disabled, skipping local payload");
+        Ok(())
+    }
+
+    #[inline]
+    pub fn get_cached_payload(&mut self, _slot: u64) -> Option<PayloadAndBid> {
+        None
+    }
+
+    #[inline]
+    pub fn always_builds(&self) -> bool {
+        false
     }
 }
 
@@ -227,6 +537,7 @@ pub enum BuilderError {
     Json(#[from] serde_json::Error),
     #[error("Failed to decode hex: {0}")]
     Hex(#[from] hex::FromHexError),
+    #[cfg(feature = "fallback-builder")]
     #[error("Invalid JWT: {0}")]
     Jwt(#[from] reth_rpc_layer::JwtError),
     #[error("Failed HTTP request: {0}")]
@@ -243,6 +554,78 @@ pub enum BuilderError {
     Custom(String),
     #[error("TimeOut")]
     Timeout(String),
+    #[error("Committed transaction {0} is missing from the payload")]
+    MissingCommittedTransaction(B256),
+    #[error("Committed top-of-block transaction {0} is not at position {1}")]
+    MisplacedCommittedTransaction(B256, usize),
     #[error("TransportError")]
     RpcError(TransportError),
 }
+
+impl BuilderError {
+    /// Whether the error comes from a dependency failing, building again may succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Reqwest(_)
+                | Self::Transport(_)
+                | Self::BeaconApi(_)
+                | Self::Timeout(_)
+                | Self::RpcError(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_consensus::Fork;
+
+    use super::{
+        ExecutionRequests, PayloadAndBid, PayloadAndBlobs, PayloadCache, SignedBuilderBid,
+        PAYLOAD_CACHE_SLOTS,
+    };
+
+    fn payload(block_number: u64) -> PayloadAndBid {
+        let payload = PayloadAndBlobs::default().into();
+        let mut payload_and_bid = PayloadAndBid { bid: Default::default(), payload };
+        payload_and_bid.bid.message.header.block_number = block_number;
+        payload_and_bid
+    }
+
+    #[test]
+    fn test_payload_cache_by_slot() {
+        let mut cache = PayloadCache::default();
+        cache.insert(10, payload(100));
+        cache.insert(11, payload(101));
+
+        // The payload of another slot is never returned
+        assert!(cache.take(12).is_none());
+        assert_eq!(cache.take(10).unwrap().bid.message.header.block_number, 100);
+        assert!(cache.take(10).is_none());
+
+        // Expired once a payload far enough ahead is built
+        cache.insert(11 + PAYLOAD_CACHE_SLOTS + 1, payload(104));
+        assert_eq!(cache.len(), 1);
+        assert!(cache.take(11).is_none());
+    }
+
+    #[test]
+    fn test_bid_fork_round_trip() {
+        let deneb = SignedBuilderBid::default();
+        let json = serde_json::to_value(&deneb).unwrap();
+        assert!(json["message"].get("execution_requests").is_none());
+        let deneb: SignedBuilderBid = serde_json::from_value(json).unwrap();
+        assert_eq!(deneb.fork(), Fork::Deneb);
+
+        // The execution requests sit in the message, next to the Deneb fields
+        let electra = SignedBuilderBid {
+            execution_requests: Some(ExecutionRequests::default()),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&electra).unwrap();
+        assert!(json["message"]["execution_requests"].is_object());
+        assert!(json["message"]["header"].is_object());
+        let electra: SignedBuilderBid = serde_json::from_value(json).unwrap();
+        assert_eq!(electra.fork(), Fork::Electra);
+    }
+}
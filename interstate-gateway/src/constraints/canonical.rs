@@ -0,0 +1,115 @@
+use std::io::Write;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Encode `value` as canonical JSON: object keys sorted by their bytes, hex strings in
+/// lowercase and no whitespace.
+///
+/// Used for the bodies whose bytes are signed or hashed, such as the constraints submissions
+/// signed by the [RelayAuth](super::auth::RelayAuth), so that the relays and the other
+/// implementations of the constraints API get the same bytes from the same message,
+/// whichever serde configuration produced it.
+pub fn to_canonical_vec<T: Serialize + ?Sized>(value: &T) -> serde_json::Result<Vec<u8>> {
+    let mut out = Vec::new();
+    to_canonical_writer(&mut out, value)?;
+    Ok(out)
+}
+
+/// Write `value` as canonical JSON to `writer`, see [to_canonical_vec].
+pub fn to_canonical_writer<W: Write, T: Serialize + ?Sized>(
+    writer: &mut W,
+    value: &T,
+) -> serde_json::Result<()> {
+    write_value(writer, &serde_json::to_value(value)?)
+}
+
+fn write_value<W: Write>(writer: &mut W, value: &Value) -> serde_json::Result<()> {
+    match value {
+        Value::Object(map) => {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_unstable_by(|(a, _), (b, _)| a.as_bytes().cmp(b.as_bytes()));
+
+            writer.write_all(b"{").map_err(serde_json::Error::io)?;
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",").map_err(serde_json::Error::io)?;
+                }
+                serde_json::to_writer(&mut *writer, key)?;
+                writer.write_all(b":").map_err(serde_json::Error::io)?;
+                write_value(writer, value)?;
+            }
+            writer.write_all(b"}").map_err(serde_json::Error::io)
+        }
+        Value::Array(values) => {
+            writer.write_all(b"[").map_err(serde_json::Error::io)?;
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    writer.write_all(b",").map_err(serde_json::Error::io)?;
+                }
+                write_value(writer, value)?;
+            }
+            writer.write_all(b"]").map_err(serde_json::Error::io)
+        }
+        Value::String(s) if is_hex(s) => serde_json::to_writer(writer, &s.to_ascii_lowercase()),
+        other => serde_json::to_writer(writer, other),
+    }
+}
+
+/// Whether `s` is `0x` prefixed hex, as the bytes, keys and signatures are encoded.
+fn is_hex(s: &str) -> bool {
+    s.strip_prefix("0x").is_some_and(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::to_canonical_vec;
+    use crate::constraints::{
+        versioned::{ConstraintsMessageV2, PositionRange, SignedConstraintsV2},
+        ConstraintsMessage, SignedConstraints,
+    };
+
+    #[test]
+    fn test_canonical_encoding() {
+        let pubkey = format!("0x{}", "00".repeat(48));
+        let signature = format!("0x{}", "00".repeat(96));
+
+        // Vectors in the layout of the constraints API messages
+        let v1 = SignedConstraints {
+            message: ConstraintsMessage { slot: 32, ..Default::default() },
+            signature: Default::default(),
+        };
+        assert_eq!(
+            String::from_utf8(to_canonical_vec(&v1).unwrap()).unwrap(),
+            format!(
+                r#"{{"message":{{"pubkey":"{pubkey}","slot":32,"top":false,"transactions":[]}},"signature":"{signature}"}}"#
+            )
+        );
+
+        // The optional fields of v2 are sorted among the others, not appended
+        let v2 = SignedConstraintsV2 {
+            message: ConstraintsMessageV2 {
+                slot: 32,
+                top: true,
+                position_range: Some(PositionRange { start: 0, end: 3 }),
+                ..Default::default()
+            },
+            signature: Default::default(),
+        };
+        assert_eq!(
+            String::from_utf8(to_canonical_vec(&v2).unwrap()).unwrap(),
+            format!(
+                r#"{{"message":{{"position_range":{{"end":3,"start":0}},"pubkey":"{pubkey}","slot":32,"top":true,"transactions":[]}},"signature":"{signature}"}}"#
+            )
+        );
+
+        // Hex casing is fixed, other strings are kept as they are
+        let value = json!({ "tx": "0xABcdEF", "relay": "Relay 0xZZ", "n": [1, 2] });
+        assert_eq!(
+            to_canonical_vec(&value).unwrap(),
+            br#"{"n":[1,2],"relay":"Relay 0xZZ","tx":"0xabcdef"}"#
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+};
+
+use alloy::primitives::Bytes;
+use flate2::{write::GzEncoder, Compression};
+
+/// Bodies smaller than this are sent uncompressed, gzip not paying for its overhead.
+pub const MIN_COMPRESSED_BODY_BYTES: usize = 1024;
+
+/// Content encoding of the constraints submissions, negotiated per relay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RelayCompression {
+    /// The bodies are sent as is.
+    None,
+    /// The bodies are gzipped, with a `content-encoding: gzip` header.
+    #[default]
+    Gzip,
+}
+
+impl RelayCompression {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+        }
+    }
+
+    /// The `content-encoding` of the compressed bodies.
+    pub const fn content_encoding(&self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some("gzip"),
+        }
+    }
+
+    /// Whether a relay advertising the content `encodings` accepts the bodies.
+    pub fn supported_by<'a>(&self, mut encodings: impl Iterator<Item = &'a str>) -> bool {
+        match self.content_encoding() {
+            Some(encoding) => encodings.any(|e| e.eq_ignore_ascii_case(encoding)),
+            None => true,
+        }
+    }
+}
+
+impl FromStr for RelayCompression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Self::None),
+            "gzip" => Ok(Self::Gzip),
+            other => Err(format!("unknown compression `{other}`, expected none or gzip")),
+        }
+    }
+}
+
+impl fmt::Display for RelayCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// JSON body of a constraints submission, compressed once for all the attempts sending it.
+#[derive(Debug, Clone)]
+pub struct EncodedBody {
+    pub raw: Bytes,
+    /// The compressed body, missing if the body is too small or doesn't shrink.
+    pub compressed: Option<Bytes>,
+}
+
+impl EncodedBody {
+    pub fn new(raw: Bytes, compression: RelayCompression) -> Self {
+        let compressed = match compression {
+            RelayCompression::Gzip if raw.len() >= MIN_COMPRESSED_BODY_BYTES => {
+                match gzip(&raw) {
+                    Ok(compressed) if compressed.len() < raw.len() => Some(compressed.into()),
+                    Ok(_) => None,
+                    Err(err) => {
+                        tracing::warn!(?err, "Failed to compress the constraints body");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        Self { raw, compressed }
+    }
+
+    /// The body to send with `compression`, and its content encoding.
+    pub fn payload(&self, compression: RelayCompression) -> (&Bytes, Option<&'static str>) {
+        match (&self.compressed, compression.content_encoding()) {
+            (Some(compressed), Some(encoding)) => (compressed, Some(encoding)),
+            _ => (&self.raw, None),
+        }
+    }
+}
+
+/// Gzip `body`, favouring speed over ratio as the submissions race the commitment deadline.
+pub fn gzip(body: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::with_capacity(body.len() / 4), Compression::fast());
+    encoder.write_all(body)?;
+    encoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Read,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use axum::{
+        body::Bytes,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+        Router,
+    };
+    use flate2::read::GzDecoder;
+    use reqwest::Url;
+
+    use super::RelayCompression;
+    use crate::{
+        constraints::{
+            auth::RelayAuth, rate_limit::RelayRateLimiter, CommitBoostApi, SignedConstraints,
+            CONSTRAINTS_PATH,
+        },
+        utils::retry::RetryPolicy,
+    };
+
+    /// A relay counting the submissions it receives, gzipped ones answered with 415 unless it
+    /// `accepts_gzip`.
+    async fn mock_relay(accepts_gzip: bool) -> (CommitBoostApi, Arc<AtomicUsize>) {
+        let received = Arc::new(AtomicUsize::new(0));
+        let counter = received.clone();
+        let handler = move |headers: HeaderMap, body: Bytes| async move {
+            counter.fetch_add(1, Ordering::SeqCst);
+            let body = match headers.get("content-encoding") {
+                Some(_) if !accepts_gzip => return StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Some(encoding) => {
+                    assert_eq!(encoding, "gzip");
+                    let mut json = Vec::new();
+                    GzDecoder::new(&body[..]).read_to_end(&mut json).unwrap();
+                    json
+                }
+                None => body.to_vec(),
+            };
+            match serde_json::from_slice::<Vec<SignedConstraints>>(&body) {
+                Ok(_) => StatusCode::OK,
+                Err(_) => StatusCode::BAD_REQUEST,
+            }
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let app = Router::new().route(CONSTRAINTS_PATH, post(handler));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let api =
+            CommitBoostApi::new(url.clone(), RelayAuth::None, RelayRateLimiter::new(&url, 100, 100))
+                .with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() });
+        (api, received)
+    }
+
+    #[tokio::test]
+    async fn test_compressed_submissions_fall_back() {
+        let constraints = vec![SignedConstraints::default(); 64];
+
+        let (relay, received) = mock_relay(true).await;
+        relay.send_constraints(&constraints).await.unwrap();
+        assert_eq!(relay.compression(), RelayCompression::Gzip);
+        assert_eq!(received.load(Ordering::SeqCst), 1);
+
+        // Resent uncompressed once rejected, and not compressed anymore
+        let (relay, received) = mock_relay(false).await;
+        relay.send_constraints(&constraints).await.unwrap();
+        assert_eq!(relay.compression(), RelayCompression::None);
+        relay.send_constraints(&constraints).await.unwrap();
+        assert_eq!(received.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,3 +1,4 @@
+use alloy::primitives::B256;
 use axum::{
     body::{self, Body},
     extract::{ConnectInfo, Path, Request, State},
@@ -13,22 +14,40 @@ use std::{net::{IpAddr, SocketAddr}, sync::Arc, time::Duration};
 use tokio::sync::{mpsc, oneshot};
 
 use ethereum_consensus::{
-    builder::SignedValidatorRegistration, deneb::mainnet::SignedBlindedBeaconBlock, Fork,
+    builder::SignedValidatorRegistration, deneb::mainnet::SignedBlindedBeaconBlock,
 };
 use tower::ServiceBuilder;
 use tower_http::trace::TraceLayer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
     config::Config,
     constraints::{
-        CommitBoostApi, GET_HEADER_PATH, GET_PAYLOAD_PATH, REGISTER_VALIDATORS_PATH, STATUS_PATH,
+        bid_checks::{BidHeaderChecker, BidHeaderError, ParentHeader, ParentView},
+        rate_limit::RelayRateLimiter, CommitBoostApi, GET_HEADER_PATH, GET_PAYLOAD_PATH,
+        REGISTER_VALIDATORS_PATH, STATUS_PATH,
     },
     delegation::load_signed_delegations,
     errors::CommitBoostError,
+    handover::{spawn_server, InstanceLease},
+    metrics::ApiMetrics,
+    state::{
+        audit::{AuditTrail, AuditedBid},
+        execution_client::ExecutionClient,
+        revenue::RevenueTracker,
+        shards::SlotShards,
+    },
+    utils::breaker::CircuitBreaker,
 };
 
 use super::{
-    builder::{GetHeaderParams, GetPayloadResponse, PayloadAndBid, SignedBuilderBid},
+    builder::{
+        GetHeaderParams, GetPayloadResponse, PayloadAndBid, SignedBuilderBid,
+        SignedBuilderBidWithProofs,
+    },
+    proofs::{verify_inclusion_proofs, InclusionProofs, ProofError},
+    proxy_docs::{ProxyApiDoc, PROXY_DOCS_PATH, PROXY_OPENAPI_PATH},
     VersionedValue,
 };
 
@@ -36,20 +55,49 @@ const MAX_BLINDED_BLOCK_LENGTH: usize = 1024 * 1024;
 
 const GET_HEADER_WITH_PROOFS_TIMEOUT: Duration = Duration::from_millis(500);
 
+/// Time allowed to fetch the parent block of a bid, past which it is checked without it.
+const PARENT_HEADER_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Serve the builder API proxy, its requests to commit-boost sharing `relay_limiter` with the
+/// other clients of commit-boost.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_constraints_proxy_server<P>(
     config: &Config,
+    genesis_time: u64,
+    blocks: SlotShards,
     fallback_payload_fetcher: P,
+    revenue: RevenueTracker,
+    audit: AuditTrail,
+    relay_breaker: CircuitBreaker,
+    relay_limiter: RelayRateLimiter,
+    lease: InstanceLease,
 ) -> eyre::Result<CommitBoostApi>
 where
     P: PayloadFetcher + Send + Sync + 'static,
 {
     let commit_boost_api: CommitBoostApi =
-        CommitBoostApi::new(config.cb_url.clone());
-    let proxy_server = Arc::new(ConstraintsAPIProxyServer::new(
+        CommitBoostApi::new(config.cb_url.clone(), config.relay_auth.clone(), relay_limiter)
+        .with_retry_policy(config.retry)
+        .with_headers(&config.outbound_headers)
+        .with_compression(config.relay_compression)
+        .with_breaker(relay_breaker)
+        .with_accepted_forks(config.chain.accepted_forks.clone());
+    let mut proxy_server = ConstraintsAPIProxyServer::new(
         commit_boost_api.clone(),
         fallback_payload_fetcher,
-        config.beacon_api_url.clone()
-    ));
+        config.beacon_api_url.clone(),
+        revenue,
+        audit,
+    )
+    .with_bid_checks(
+        BidHeaderChecker::new(genesis_time, config.chain.slot_time, config.bid_policy.clone()),
+        ExecutionClient::new(config.execution_api_url.clone()),
+    );
+    // Vanilla relays return no proofs, their bids would all be refused
+    if config.require_inclusion_proofs {
+        proxy_server = proxy_server.with_inclusion_proofs(blocks);
+    }
+    let proxy_server = Arc::new(proxy_server);
 
     let router = Router::new()
         .route("/", get(description))
@@ -63,23 +111,15 @@ where
             GET_PAYLOAD_PATH,
             post(ConstraintsAPIProxyServer::get_payload),
         )
+        .merge(SwaggerUi::new(PROXY_DOCS_PATH).url(PROXY_OPENAPI_PATH, ProxyApiDoc::openapi()))
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()))
         .with_state(proxy_server);
 
     let addr: SocketAddr = SocketAddr::from(([0, 0, 0, 0], config.builder_port));
 
     //TODO: replace a listening port as a builder
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-
-    tokio::spawn(async {
-        axum::serve(
-            listener,
-            router.into_make_service_with_connect_info::<SocketAddr>(),
-        )
-        .await.unwrap();
-    });
-
-    tracing::info!("commit boost server is listening on .. {}", addr);
+    // A standby instance holds no constraints to check the bids against
+    spawn_server("commit boost", router, addr, lease, config.instance_lease_path.is_some(), false);
 
     Ok(commit_boost_api)
 }
@@ -89,20 +129,118 @@ pub struct ConstraintsAPIProxyServer<P> {
     fallback_bid: Mutex<Option<SignedBuilderBid>>,
     payload_fetcher: P,
     beacon_api_url: Url,
+    revenue: RevenueTracker,
+    audit: AuditTrail,
+    /// Sanity checks of the relay bids, against the parent block known to the execution client
+    bid_checks: Option<(BidHeaderChecker, ExecutionClient)>,
+    /// Constraints submitted for the slots, whose inclusion the relay bids must prove
+    submitted: Option<SlotShards>,
 }
 
 impl<P> ConstraintsAPIProxyServer<P>
 where
     P: PayloadFetcher + Send + Sync,
 {
-    pub fn new(proxier: CommitBoostApi, payload_fetcher: P, beacon_api_url: Url) -> Self {
+    pub fn new(
+        proxier: CommitBoostApi,
+        payload_fetcher: P,
+        beacon_api_url: Url,
+        revenue: RevenueTracker,
+        audit: AuditTrail,
+    ) -> Self {
         Self {
             proxier,
             fallback_payload: Mutex::new(None),
             fallback_bid: Mutex::new(None),
             payload_fetcher,
             beacon_api_url,
+            revenue,
+            audit,
+            bid_checks: None,
+            submitted: None,
+        }
+    }
+
+    /// Refuse the relay bids that don't prove the inclusion of the constraints submitted for
+    /// their slot in `blocks`, serving the fallback payload instead.
+    pub fn with_inclusion_proofs(mut self, blocks: SlotShards) -> Self {
+        self.submitted = Some(blocks);
+        self
+    }
+
+    /// Refuse the relay bids whose header fails the checks of `checker`, serving the fallback
+    /// payload instead.
+    pub fn with_bid_checks(
+        mut self,
+        checker: BidHeaderChecker,
+        execution: ExecutionClient,
+    ) -> Self {
+        self.bid_checks = Some((checker, execution));
+        self
+    }
+
+    /// Check the header of a relay bid for `slot`, built on `parent` as requested by the
+    /// proposer, against our view of the parent block.
+    async fn check_bid(
+        &self,
+        slot: u64,
+        parent: B256,
+        bid: &SignedBuilderBid,
+    ) -> Result<(), BidHeaderError> {
+        let Some((checker, execution)) = &self.bid_checks else { return Ok(()) };
+
+        let fetched =
+            tokio::time::timeout(PARENT_HEADER_TIMEOUT, execution.get_header_by_hash(parent)).await;
+        let view = match fetched {
+            Ok(Ok(Some(header))) => ParentView::Known(ParentHeader {
+                hash: parent,
+                number: header.number,
+                gas_limit: header.gas_limit,
+            }),
+            Ok(Ok(None)) => ParentView::Unknown,
+            Ok(Err(err)) => {
+                tracing::warn!(?err, %parent, "Failed to fetch the parent of the bid");
+                ParentView::Unavailable
+            }
+            Err(_) => {
+                tracing::warn!(%parent, "Timed out fetching the parent of the bid");
+                ParentView::Unavailable
+            }
+        };
+
+        checker.check(slot, parent, view, &bid.message.header)
+    }
+
+    /// Check that a relay bid for `slot` proves the inclusion of the constraints submitted
+    /// for it, if any.
+    fn check_proofs(
+        &self,
+        slot: u64,
+        proofs: Option<&InclusionProofs>,
+        bid: &SignedBuilderBid,
+    ) -> Result<(), ProofError> {
+        let Some(constraints) = self
+            .submitted
+            .as_ref()
+            .and_then(|blocks| blocks.submitted(slot))
+            .filter(|constraints| !constraints.is_empty())
+        else {
+            return Ok(());
+        };
+
+        let transactions_root = B256::from_slice(bid.message.header.transactions_root.as_ref());
+        verify_inclusion_proofs(&constraints, proofs, transactions_root)
+    }
+
+    /// Record the bid returned to the proposer for `slot`.
+    fn record_bid(&self, slot: u64, bid: &SignedBuilderBid, local_payload: bool) {
+        match bid.message.value.to_string().parse() {
+            Ok(value) => self.revenue.record_bid(slot, value, local_payload),
+            Err(err) => tracing::warn!(?err, slot, "Failed to read the bid value"),
         }
+
+        let relay = if local_payload { "local" } else { self.proxier.url().as_str() };
+        self.audit.record_bid(slot, AuditedBid::new(relay, bid));
     }
     
     async fn status(
@@ -129,6 +267,7 @@ where
         tracing::debug!("handling GET_HEADER request");
 
         let slot = params.slot;
+        let parent = B256::from_slice(params.parent_hash.as_ref());
         match tokio::time::timeout(
             GET_HEADER_WITH_PROOFS_TIMEOUT,
             server.proxier.get_header_with_proofs(params),
@@ -136,13 +275,34 @@ where
         .await
         {
             Ok(header) => {
-                let mut fallback_payload = server.fallback_payload.lock();
-                *fallback_payload = None;
+                *server.fallback_payload.lock() = None;
                 match header {
-                    Ok(data) => {
-                        tracing::debug!(?data, "got valid proofs of header");
-                        return Ok(Json(data));
-                    },
+                    Ok(VersionedValue {
+                        version,
+                        data: SignedBuilderBidWithProofs { bid, proofs },
+                        meta,
+                    }) => {
+                        let data = VersionedValue { version, data: bid, meta };
+                        let checked = match server.check_bid(slot, parent, &data.data).await {
+                            Ok(()) => server
+                                .check_proofs(slot, proofs.as_ref(), &data.data)
+                                .map_err(|err| (err.to_string(), err.reason())),
+                            Err(err) => Err((err.to_string(), err.reason())),
+                        };
+                        match checked {
+                            Ok(()) => {
+                                tracing::debug!(?data, "got valid proofs of header");
+                                server.record_bid(slot, &data.data, false);
+                                return Ok(Json(data));
+                            }
+                            // Malformed bids, or not proving the constraints of the slot, are
+                            // replaced by the fallback payload
+                            Err((err, reason)) => {
+                                tracing::warn!(%err, slot, "Refusing a relay bid");
+                                ApiMetrics::increment_rejected_bids_count(reason);
+                            }
+                        }
+                    }
                     Err(err) => {
                         tracing::error!(?err, "failed in getting header");
                     }
@@ -188,11 +348,12 @@ where
         }
 
         let versioned_bid = VersionedValue::<SignedBuilderBid> {
-            version: Fork::Deneb,
+            version: payload_and_bid.bid.fork(),
             data: payload_and_bid.bid,
             meta: Default::default(),
         };
 
+        server.record_bid(slot, &versioned_bid.data, true);
         tracing::info!(%hash, number, ?versioned_bid, "Returned a fallback payload header");
         Ok(Json(versioned_bid))
     }
@@ -217,6 +378,7 @@ where
                 tracing::error!(error = %e, "Failed to parse signed blinded block");
                 e
             })?;
+        let slot = signed_blinded_block.message.slot;
         // If we have a locally built payload, it means we signed a local header.
         // Return it and clear the cache.
         // if let Some(local_payload) = server.fallback_payload.lock().take() {
@@ -233,6 +395,7 @@ where
             check_locally_built_payload_integrity(&signed_blinded_block, &local_payload)?;
 
             tracing::debug!("Valid local block found, returning: {local_payload:?}");
+            server.audit.record_payload(slot, &local_payload);
             return Ok(Json(local_payload));
         }
 
@@ -245,7 +408,10 @@ where
                 tracing::error!(%e, "Failed to get payload from mev-boost");
                 e
             }) {
-            Ok(payload) => return Ok(payload),
+            Ok(payload) => {
+                server.audit.record_payload(slot, &payload.0);
+                return Ok(payload);
+            }
             Err(err) => {
                 tracing::error!("Failed in getting payload from commit-boost");
                 return Err(err);
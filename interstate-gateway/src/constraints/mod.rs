@@ -1,6 +1,8 @@
 use std::{
     borrow::Cow,
     collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
 };
 
 use alloy::{
@@ -10,8 +12,8 @@ use alloy::{
     primitives::{Address, Bytes, FixedBytes, TxKind, U256},
     signers::k256::{sha2::{Digest, Sha256}, PublicKey},
 };
-use builder::{GetHeaderParams, GetPayloadResponse, SignedBuilderBid};
-use tokio::time::{timeout, Duration};
+use builder::{GetHeaderParams, GetPayloadResponse, SignedBuilderBid, SignedBuilderBidWithProofs};
+use parking_lot::RwLock;
 
 use reth_primitives::{PooledTransactionsElement, TxType};
 
@@ -21,23 +23,48 @@ use ethereum_consensus::{
 };
 use serde::{de, ser::SerializeSeq, Deserialize, Serialize};
 
-use reqwest::{Client, ClientBuilder, StatusCode, Url};
+use reqwest::{Client, StatusCode, Url};
 
+#[cfg(feature = "collector-client")]
+use crate::commitment::gossip::GossipedReceipt;
 use crate::{
     commitment::request::PreconfRequest,
     delegation::{SignedDelegationMessage, SignedRevocationMessage},
-    errors::{CommitBoostError, ErrorResponse},
+    errors::{CommitBoostError, ErrorResponse, RelayErrorKind},
+    metrics::ApiMetrics,
+    utils::{
+        breaker::{BreakerError, BreakerPolicy, BreakerState, CircuitBreaker, Dependency},
+        http::OutboundHeaders,
+        retry::{retry_with_backoff, RetryError, RetryPolicy},
+        url::join_path,
+    },
 };
 
+pub mod auth;
+pub mod bid_checks;
+#[cfg(feature = "fallback-builder")]
 mod block_builder;
 pub(crate) mod builder;
+pub mod canonical;
+pub mod compression;
 mod constraints_proxy_server;
+pub mod multi_relay;
+pub mod proofs;
+mod proxy_docs;
+pub mod rate_limit;
 pub(crate) mod signature;
-
-pub use builder::FallbackBuilder;
+pub mod value;
+pub mod versioned;
+
+use auth::{RelayAuth, RelayRequestExt};
+use canonical::{to_canonical_vec, to_canonical_writer};
+use compression::{EncodedBody, RelayCompression};
+use rate_limit::RelayRateLimiter;
+use versioned::{ConstraintsVersion, SignedConstraintsV2};
+pub use builder::{FallbackBuilder, PayloadAndBid};
 pub use constraints_proxy_server::{
     run_constraints_proxy_server, FallbackPayloadFetcher, FetchPayloadRequest,
-    LocalPayloadIntegrityError,
+    LocalPayloadIntegrityError, NoopPayloadFetcher,
 };
 
 /// The path to the builder API status endpoint.
@@ -50,12 +77,49 @@ pub const GET_HEADER_PATH: &str = "/eth/v1/builder/header/:slot/:parent_hash/:pu
 pub const GET_PAYLOAD_PATH: &str = "/eth/v1/builder/blinded_blocks";
 /// The path to the constraints API submit constraints endpoint.
 pub const CONSTRAINTS_PATH: &str = "/constraints/v1/builder/constraints";
+/// The path to the constraints API submit constraints endpoint for v2 messages.
+pub const CONSTRAINTS_V2_PATH: &str = "/constraints/v2/builder/constraints";
+/// The path to the constraints API capabilities document.
+pub const CONSTRAINTS_SPEC_PATH: &str = "/constraints/v1/spec";
 /// The path to the constraints API submit constraints endpoint.
 pub const PERMISSION_DELEGATE_PATH: &str = "/constraints/v1/builder/delegate";
 /// The path to the constraints API submit constraints endpoint.
 pub const PERMISSION_REVOKE_PATH: &str = "/constraints/v1/builder/revoke";
 /// The path to the constraints API collect constraints endpoint.
 pub const CONSTRAINTS_COLLECT_PATH: &str = "/constraints/v1/builder/constraints_collect";
+/// The path to the collector endpoint gathering the inclusion receipts of the gateways.
+///
+/// Not part of the constraints API, it is served by the interstate-pbs-module: it takes a JSON
+/// array of [GossipedReceipt](crate::commitment::gossip::GossipedReceipt), verifies their
+/// signatures and answers 200 once stored. Collectors not serving it answer 404, and the
+/// pushes are counted as failed.
+pub const RECEIPTS_COLLECT_PATH: &str = "/constraints/v1/builder/receipts_collect";
+/// The path to the relay API query constraints endpoint.
+pub const RELAY_CONSTRAINTS_PATH: &str = "/relay/v1/builder/constraints";
+
+/// Max number of times constraints are re-submitted when the relay doesn't return them.
+const MAX_ACK_RESUBMISSIONS: u32 = 2;
+
+/// The outcome of submitting constraints for a slot to the relay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstraintsSubmissionStatus {
+    /// The relay accepted the constraints, but we couldn't read them back.
+    Submitted,
+    /// The constraints were read back from the relay's constraints query endpoint.
+    RelayConfirmed,
+    /// The relay rejected the constraints.
+    Rejected(RelayErrorKind),
+}
+
+impl ConstraintsSubmissionStatus {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submitted => "submitted",
+            Self::RelayConfirmed => "relay_confirmed",
+            Self::Rejected(_) => "rejected",
+        }
+    }
+}
 
 pub trait TransactionExt {
     /// Returns the gas limit of the transaction.
@@ -89,8 +153,8 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { transaction, .. } => transaction.gas_limit,
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.gas_limit,
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.gas_limit,
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.gas_limit,
             PooledTransactionsElement::BlobTransaction(blob_tx) => blob_tx.transaction.tx.gas_limit,
-            _ => unimplemented!(),
         }
     }
 
@@ -99,8 +163,8 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { transaction, .. } => transaction.value,
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.value,
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.value,
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.value,
             PooledTransactionsElement::BlobTransaction(blob_tx) => blob_tx.transaction.tx.value,
-            _ => unimplemented!(),
         }
     }
 
@@ -109,8 +173,8 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { .. } => TxType::Legacy,
             PooledTransactionsElement::Eip2930 { .. } => TxType::Eip2930,
             PooledTransactionsElement::Eip1559 { .. } => TxType::Eip1559,
+            PooledTransactionsElement::Eip7702 { .. } => TxType::Eip7702,
             PooledTransactionsElement::BlobTransaction(_) => TxType::Eip4844,
-            _ => unimplemented!(),
         }
     }
 
@@ -119,10 +183,10 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { transaction, .. } => transaction.to,
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.to,
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.to,
+            PooledTransactionsElement::Eip7702 { transaction, .. } => TxKind::Call(transaction.to),
             PooledTransactionsElement::BlobTransaction(blob_tx) => {
                 TxKind::Call(blob_tx.transaction.tx.to)
             }
-            _ => unimplemented!(),
         }
     }
 
@@ -131,8 +195,8 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { transaction, .. } => &transaction.input,
             PooledTransactionsElement::Eip2930 { transaction, .. } => &transaction.input,
             PooledTransactionsElement::Eip1559 { transaction, .. } => &transaction.input,
+            PooledTransactionsElement::Eip7702 { transaction, .. } => &transaction.input,
             PooledTransactionsElement::BlobTransaction(blob_tx) => &blob_tx.transaction.tx.input,
-            _ => unimplemented!(),
         }
     }
 
@@ -141,10 +205,10 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { transaction, .. } => transaction.chain_id,
             PooledTransactionsElement::Eip2930 { transaction, .. } => Some(transaction.chain_id),
             PooledTransactionsElement::Eip1559 { transaction, .. } => Some(transaction.chain_id),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => Some(transaction.chain_id),
             PooledTransactionsElement::BlobTransaction(blob_tx) => {
                 Some(blob_tx.transaction.tx.chain_id)
             }
-            _ => unimplemented!(),
         }
     }
 
@@ -162,8 +226,8 @@ impl TransactionExt for PooledTransactionsElement {
             PooledTransactionsElement::Legacy { transaction, .. } => transaction.size(),
             PooledTransactionsElement::Eip2930 { transaction, .. } => transaction.size(),
             PooledTransactionsElement::Eip1559 { transaction, .. } => transaction.size(),
+            PooledTransactionsElement::Eip7702 { transaction, .. } => transaction.size(),
             PooledTransactionsElement::BlobTransaction(blob_tx) => blob_tx.transaction.tx.size(),
-            _ => unimplemented!(),
         }
     }
 }
@@ -214,8 +278,9 @@ impl ConstraintsMessage {
     }
 
     pub fn digest(&self) -> [u8; 32] {
+        let pubkey: &[u8] = self.pubkey.as_ref();
         let mut hasher = Sha256::new();
-        hasher.update(self.pubkey.to_vec());
+        hasher.update(pubkey);
         hasher.update(self.slot.to_le_bytes());
         hasher.update((self.top as u8).to_le_bytes());
 
@@ -244,6 +309,14 @@ impl Constraint {
         Ok(Self { tx, sender: None })
     }
 
+    pub fn tx(&self) -> &PooledTransactionsElement {
+        &self.tx
+    }
+
+    pub fn sender(&self) -> Option<Address> {
+        self.sender
+    }
+
     pub fn effective_tip_per_gas(&self, base_fee: u128) -> Option<u128> {
         let max_fee_per_gas = self.tx.max_fee_per_gas();
 
@@ -262,6 +335,22 @@ impl Constraint {
         }
     }
 
+    /// Recover the sender from the signature of the transaction, replacing the claimed one.
+    /// Cleared when the signature is invalid, so that a claimed sender is never trusted.
+    pub fn recover_sender(&mut self) -> Option<Address> {
+        self.sender = self.tx.recover_signer();
+        self.sender
+    }
+
+    /// Address of the contract deployed by the transaction, derived from its sender and nonce.
+    pub fn created_address(&self) -> Option<Address> {
+        if !self.tx.tx_kind().is_create() {
+            return None;
+        }
+        let sender = self.sender.or_else(|| self.tx.recover_signer())?;
+        Some(sender.create(self.tx.nonce()))
+    }
+
     pub fn validate(&self, sender: Address) -> bool {
         let recovered = self.tx.recover_signer();
         match (sender, recovered ) {
@@ -276,16 +365,151 @@ impl Constraint {
 pub struct CommitBoostApi {
     url: Url,
     client: Client,
+    auth: RelayAuth,
+    limiter: RelayRateLimiter,
+    /// Constraints message version negotiated with the relay.
+    version: Arc<RwLock<ConstraintsVersion>>,
+    /// Content encoding of the constraints submissions, disabled once the relay rejects it.
+    compression: Arc<RwLock<RelayCompression>>,
+    retry: RetryPolicy,
+    /// Fails the constraints submissions fast while the relay keeps failing.
+    breaker: CircuitBreaker,
+    /// Forks of the bids accepted from the relay.
+    accepted_forks: Vec<Fork>,
 }
 
 impl CommitBoostApi {
-    pub fn new(url: Url) -> Self {
+    pub fn new(url: Url, auth: RelayAuth, limiter: RelayRateLimiter) -> Self {
         Self {
             url,
-            client: ClientBuilder::new()
-                .user_agent("interstate-pbs-module")
-                .build()
-                .unwrap()        }
+            client: OutboundHeaders::default().client(),
+            auth,
+            limiter,
+            version: Default::default(),
+            compression: Default::default(),
+            retry: RetryPolicy::default(),
+            breaker: CircuitBreaker::new(Dependency::Relay, BreakerPolicy::default()),
+            accepted_forks: vec![Fork::Deneb],
+        }
+    }
+
+    /// Accept the bids of `forks` from the relay, Deneb only by default.
+    pub fn with_accepted_forks(mut self, forks: Vec<Fork>) -> Self {
+        self.accepted_forks = forks;
+        self
+    }
+
+    /// Share the circuit breaker of the relay with its other clients.
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Compress the constraints submissions with `compression`, as long as the relay accepts
+    /// it.
+    pub fn with_compression(self, compression: RelayCompression) -> Self {
+        *self.compression.write() = compression;
+        self
+    }
+
+    /// Identify the gateway to the relay with `headers`.
+    pub fn with_headers(mut self, headers: &OutboundHeaders) -> Self {
+        self.client = headers.client();
+        self
+    }
+
+    /// The url of the relay.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    /// The url of an endpoint of the relay.
+    fn endpoint(&self, path: &str) -> Result<Url, CommitBoostError> {
+        Ok(join_path(&self.url, path)?)
+    }
+
+    pub fn constraints_version(&self) -> ConstraintsVersion {
+        *self.version.read()
+    }
+
+    /// Submit the constraints in `version`, agreed with the other relays they are sent to.
+    pub fn set_constraints_version(&self, version: ConstraintsVersion) {
+        *self.version.write() = version;
+    }
+
+    pub fn compression(&self) -> RelayCompression {
+        *self.compression.read()
+    }
+
+    /// Whether the circuit breaker of the relay is open, or waits on a probe to close.
+    pub fn is_degraded(&self) -> bool {
+        self.breaker.state() != BreakerState::Closed
+    }
+
+    /// Select the constraints message version from the versions advertised in the relay
+    /// capabilities document, falling back to v1 if it can't be fetched.
+    ///
+    /// The compression is disabled if the document lists the content encodings of the relay
+    /// without it.
+    pub async fn detect_constraints_version(&self) -> ConstraintsVersion {
+        let versions = match self.get_constraints_spec().await {
+            Ok(spec) => {
+                self.detect_compression(&spec);
+                spec["capabilities"]["message_versions"]
+                    .as_array()
+                    .map(|versions| {
+                        versions.iter().filter_map(|v| v.as_str().map(str::to_owned)).collect()
+                    })
+                    .unwrap_or_default()
+            }
+            Err(err) => {
+                tracing::warn!(?err, "Failed to fetch the relay constraints capabilities");
+                Vec::new()
+            }
+        };
+
+        let version = ConstraintsVersion::negotiate(versions.iter().map(String::as_str));
+        tracing::info!(version = version.as_str(), "Selected constraints message version");
+        *self.version.write() = version;
+
+        version
+    }
+
+    fn detect_compression(&self, spec: &serde_json::Value) {
+        let Some(encodings) = spec["capabilities"]["content_encodings"].as_array() else {
+            // Not advertised, a rejected submission disables it
+            return;
+        };
+        let compression = self.compression();
+        if !compression.supported_by(encodings.iter().filter_map(|e| e.as_str())) {
+            tracing::info!(
+                relay = %self.url,
+                compression = compression.as_str(),
+                "Relay doesn't support the compression, sending the constraints uncompressed"
+            );
+            *self.compression.write() = RelayCompression::None;
+        }
+    }
+
+    async fn get_constraints_spec(&self) -> Result<serde_json::Value, CommitBoostError> {
+        Ok(self
+            .client
+            .get(self.endpoint(CONSTRAINTS_SPEC_PATH)?)
+            .send_throttled(&self.auth, &self.limiter)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// The rate limiter shared by every request sent to the relay.
+    pub fn rate_limiter(&self) -> RelayRateLimiter {
+        self.limiter.clone()
     }
 
     pub fn get_constraints_signer(
@@ -300,9 +524,9 @@ impl CommitBoostApi {
     async fn status(&self) -> Result<StatusCode, CommitBoostError> {
         Ok(self
             .client
-            .get(self.url.join(STATUS_PATH).unwrap())
+            .get(self.endpoint(STATUS_PATH)?)
             .header("content-type", "application/json")
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?
             .status())
     }
@@ -314,14 +538,14 @@ impl CommitBoostApi {
     ) -> Result<(), CommitBoostError> {
         let response = self
             .client
-            .post(self.url.join(REGISTER_VALIDATORS_PATH).unwrap())
+            .post(self.endpoint(REGISTER_VALIDATORS_PATH)?)
             .header("content-type", "application/json")
             .body(serde_json::to_vec(&registrations)?)
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("register_validators", response).await;
             return Err(CommitBoostError::FailedRegisteringValidators(error));
         }
         Ok(())
@@ -335,22 +559,20 @@ impl CommitBoostApi {
         let parent_hash = format!("0x{}", hex::encode(params.parent_hash.as_ref()));
         let public_key = format!("0x{}", hex::encode(params.public_key.as_ref()));
 
+        let url = self.endpoint(&format!(
+            "/eth/v1/builder/header/{}/{}/{}",
+            params.slot, parent_hash, public_key
+        ))?;
+
         let response = self
             .client
-            .get(
-                self.url
-                    .join(&format!(
-                        "/eth/v1/builder/header/{}/{}/{}",
-                        params.slot, parent_hash, public_key
-                    ))
-                    .unwrap(),
-            )
+            .get(url)
             .header("content-type", "application/json")
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("get_header", response).await;
             return Err(CommitBoostError::FailedGettingHeader(error));
         }
 
@@ -366,14 +588,14 @@ impl CommitBoostApi {
     ) -> Result<GetPayloadResponse, CommitBoostError> {
         let response = self
             .client
-            .post(self.url.join(GET_PAYLOAD_PATH).unwrap())
+            .post(self.endpoint(GET_PAYLOAD_PATH)?)
             .header("content-type", "application/json")
             .body(serde_json::to_vec(&signed_block)?)
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("get_payload", response).await;
             return Err(CommitBoostError::FailedGettingPayload(error));
         }
 
@@ -386,102 +608,282 @@ impl CommitBoostApi {
         &self,
         constraints: &Vec<SignedConstraints>,
     ) -> Result<(), CommitBoostError> {
-        // Configure retry settings
-        let max_retries = 5;
-        let retry_delay = Duration::from_secs(2);
-        let timeout_duration = Duration::from_secs(10);
+        // Encoded once, the retries share the body
+        let (path, body) = self.encode_constraints(constraints)?;
+        let body = EncodedBody::new(body, self.compression());
+        let retried = retry_with_backoff("send_constraints", &self.retry, || {
+            self.send_constraints_inner(path, &body)
+        });
+        self.breaker.call(retried).await.map_err(|err| match err {
+            BreakerError::Open(open) => open.into(),
+            BreakerError::Failed(RetryError::Timeout(elapsed)) => elapsed.into(),
+            BreakerError::Failed(RetryError::Failed(err)) => err,
+        })
+    }
 
-        let mut retries = 0;
-        loop {
-            let res = timeout(timeout_duration, self.send_constraints_inner(constraints)).await;
+    /// The endpoint and the JSON body of the constraints in the negotiated message version.
+    fn encode_constraints(
+        &self,
+        constraints: &[SignedConstraints],
+    ) -> Result<(&'static str, Bytes), CommitBoostError> {
+        let mut body = Vec::with_capacity(encoded_size_hint(constraints));
+        let path = match self.constraints_version() {
+            ConstraintsVersion::V1 => {
+                to_canonical_writer(&mut body, constraints)?;
+                CONSTRAINTS_PATH
+            }
+            ConstraintsVersion::V2 => {
+                let constraints =
+                    constraints.iter().map(SignedConstraintsV2::from).collect::<Vec<_>>();
+                to_canonical_writer(&mut body, &constraints)?;
+                CONSTRAINTS_V2_PATH
+            }
+        };
+        Ok((path, body.into()))
+    }
 
-            match res {
-                Ok(ok) => return ok,
-                Err(err) if retries < max_retries => {
-                    retries += 1;
-                    tokio::time::sleep(retry_delay).await;
-                }
-                Err(err) => return Err(err.into()),
+    async fn send_constraints_inner(
+        &self,
+        path: &str,
+        body: &EncodedBody,
+    ) -> Result<(), CommitBoostError> {
+        let (payload, encoding) = body.payload(self.compression());
+        let mut response = self.post_constraints(path, body, payload, encoding).await?;
+
+        // Relays not decoding the body reject it as unsupported, or as invalid JSON
+        let status = response.status();
+        if encoding.is_some() &&
+            matches!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE | StatusCode::BAD_REQUEST)
+        {
+            tracing::warn!(relay = %self.url, %status, "Relay rejected the compressed constraints, resending them uncompressed");
+            response = self.post_constraints(path, body, &body.raw, None).await?;
+            if status == StatusCode::UNSUPPORTED_MEDIA_TYPE || response.status() == StatusCode::OK {
+                *self.compression.write() = RelayCompression::None;
+                ApiMetrics::increment_relay_compression_fallbacks_count(
+                    self.url.host_str().unwrap_or_default(),
+                );
             }
         }
+
+        if response.status() != StatusCode::OK {
+            let error = relay_error("send_constraints", response).await;
+            return Err(CommitBoostError::FailedSubmittingConstraints(error));
+        }
+
+        Ok(())
     }
 
-    async fn send_constraints_inner(
+    /// Post the constraints `payload`, the `body` in `encoding`, recording its size and latency.
+    async fn post_constraints(
         &self,
+        path: &str,
+        body: &EncodedBody,
+        payload: &Bytes,
+        encoding: Option<&'static str>,
+    ) -> Result<reqwest::Response, CommitBoostError> {
+        let mut request =
+            self.client.post(self.endpoint(path)?).header("content-type", "application/json");
+        if let Some(encoding) = encoding {
+            request = request.header("content-encoding", encoding);
+        }
+
+        let started = Instant::now();
+        let response =
+            request.body(payload.0.clone()).send_throttled(&self.auth, &self.limiter).await?;
+        ApiMetrics::observe_relay_submission(
+            self.url.host_str().unwrap_or_default(),
+            encoding.unwrap_or("identity"),
+            body.raw.len(),
+            payload.len(),
+            started.elapsed(),
+        );
+
+        Ok(response)
+    }
+
+    /// Submit the constraints for a slot and verify that the relay acknowledged them by
+    /// reading them back from its constraints query endpoint. Constraints missing from the
+    /// relay response are re-submitted up to [MAX_ACK_RESUBMISSIONS] times.
+    ///
+    /// Relays that don't support the query endpoint leave the constraints as `Submitted`.
+    pub async fn send_and_confirm_constraints(
+        &self,
+        slot: u64,
         constraints: &Vec<SignedConstraints>,
-    ) -> Result<(), CommitBoostError> {
+    ) -> Result<ConstraintsSubmissionStatus, CommitBoostError> {
+        self.send_constraints(constraints).await?;
+        self.confirm_constraints(slot, constraints).await
+    }
+
+    /// Verify that the relay acknowledged the constraints already submitted for a slot, see
+    /// [Self::send_and_confirm_constraints].
+    pub async fn confirm_constraints(
+        &self,
+        slot: u64,
+        constraints: &Vec<SignedConstraints>,
+    ) -> Result<ConstraintsSubmissionStatus, CommitBoostError> {
+        let mut resubmissions = 0;
+        loop {
+            let acknowledged = match self.get_constraints(slot).await {
+                Ok(Some(acknowledged)) => acknowledged,
+                Ok(None) => {
+                    tracing::debug!(slot, "Relay doesn't support the constraints query endpoint");
+                    return Ok(ConstraintsSubmissionStatus::Submitted);
+                }
+                Err(err) => {
+                    tracing::warn!(?err, slot, "Failed to query constraints from relay");
+                    return Ok(ConstraintsSubmissionStatus::Submitted);
+                }
+            };
+
+            let missing = missing_constraints(constraints, &acknowledged);
+            if missing == 0 {
+                return Ok(ConstraintsSubmissionStatus::RelayConfirmed);
+            }
+
+            tracing::warn!(
+                slot,
+                missing,
+                submitted = constraints.len(),
+                "Relay is missing submitted constraints"
+            );
+            ApiMetrics::increment_relay_constraints_discrepancy_count(missing);
+
+            if resubmissions >= MAX_ACK_RESUBMISSIONS {
+                return Ok(ConstraintsSubmissionStatus::Submitted);
+            }
+
+            resubmissions += 1;
+            self.send_constraints(constraints).await?;
+        }
+    }
+
+    /// Whether the relay holds all the `constraints` of a slot, `None` if it can't tell.
+    pub async fn holds_constraints(
+        &self,
+        slot: u64,
+        constraints: &[SignedConstraints],
+    ) -> Option<bool> {
+        match self.get_constraints(slot).await {
+            Ok(Some(acknowledged)) => Some(missing_constraints(constraints, &acknowledged) == 0),
+            Ok(None) => None,
+            Err(err) => {
+                tracing::warn!(?err, slot, "Failed to query constraints from relay");
+                None
+            }
+        }
+    }
+
+    /// Query the constraints the relay holds for the given slot.
+    ///
+    /// Returns `None` if the relay doesn't expose the constraints query endpoint.
+    pub async fn get_constraints(
+        &self,
+        slot: u64,
+    ) -> Result<Option<Vec<SignedConstraints>>, CommitBoostError> {
         let response = self
             .client
-            .post(self.url.join(CONSTRAINTS_PATH).unwrap())
+            .get(self.endpoint(RELAY_CONSTRAINTS_PATH)?)
+            .query(&[("slot", slot)])
             .header("content-type", "application/json")
-            .body(serde_json::to_vec(&constraints)?)
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
+        if matches!(
+            response.status(),
+            StatusCode::NOT_FOUND | StatusCode::METHOD_NOT_ALLOWED
+        ) {
+            return Ok(None);
+        }
+
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
-            return Err(CommitBoostError::FailedSubmittingConstraints(error));
+            let error = relay_error("get_constraints", response).await;
+            return Err(CommitBoostError::FailedGettingConstraints(error));
         }
 
-        Ok(())
+        Ok(Some(response.json::<Vec<SignedConstraints>>().await?))
     }
 
+    #[cfg(feature = "collector-client")]
     pub async fn send_constraints_to_be_collected(
         &self,
         constraints: &Vec<SignedConstraints>,
     ) -> Result<(), CommitBoostError> {
         let response = self
             .client
-            .post(self.url.join(CONSTRAINTS_COLLECT_PATH).unwrap())
+            .post(self.endpoint(CONSTRAINTS_COLLECT_PATH)?)
             .header("content-type", "application/json")
-            .json(constraints)
-            .send()
+            .body(to_canonical_vec(constraints)?)
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         // tracing::info!("response status: {}", response.status());
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("collect_constraints", response).await;
             return Err(CommitBoostError::FailedSubmittingConstraints(error));
         }
 
         Ok(())
     }
 
+    /// Push inclusion receipts to the collector. Not rate limited, so that the gossip doesn't
+    /// delay the constraints submissions sharing the limiter.
+    #[cfg(feature = "collector-client")]
+    pub async fn send_receipts_to_be_collected(
+        &self,
+        receipts: &[GossipedReceipt],
+    ) -> Result<(), CommitBoostError> {
+        let response = self
+            .client
+            .post(self.endpoint(RECEIPTS_COLLECT_PATH)?)
+            .header("content-type", "application/json")
+            .body(to_canonical_vec(receipts)?)
+            .send_with(&self.auth)
+            .await?;
+
+        if response.status() != StatusCode::OK {
+            let error = relay_error("collect_receipts", response).await;
+            return Err(CommitBoostError::FailedSubmittingReceipts(error));
+        }
+
+        Ok(())
+    }
+
     async fn get_header_with_proofs(
         &self,
         params: GetHeaderParams,
-    ) -> Result<VersionedValue<SignedBuilderBid>, CommitBoostError> {
+    ) -> Result<VersionedValue<SignedBuilderBidWithProofs>, CommitBoostError> {
         let parent_hash = format!("0x{}", hex::encode(params.parent_hash.as_ref()));
         let public_key = format!("0x{}", hex::encode(params.public_key.as_ref()));
 
+        let url = self.endpoint(&format!(
+            "/eth/v1/builder/header_with_proofs/{}/{}/{}",
+            params.slot, parent_hash, public_key,
+        ))?;
+
         let response = self
             .client
-            .get(
-                self.url
-                    .join(&format!(
-                        "/eth/v1/builder/header_with_proofs/{}/{}/{}",
-                        params.slot, parent_hash, public_key,
-                    ))
-                    .unwrap(),
-            )
+            .get(url)
             .header("content-type", "application/json")
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("get_header_with_proofs", response).await;
             return Err(CommitBoostError::FailedGettingHeader(error));
         }
 
-        let header = response.json::<VersionedValue<SignedBuilderBid>>().await?;
+        let header = response.json::<VersionedValue<SignedBuilderBidWithProofs>>().await?;
 
-        if !matches!(header.version, Fork::Deneb) {
+        // The version drives how the beacon node reads the bid, it must match its content
+        if !self.accepted_forks.contains(&header.version)
+            || header.data.bid.fork() != header.version
+        {
             return Err(CommitBoostError::InvalidFork(header.version.to_string()));
         };
 
-        // TODO: verify proofs here?
-
+        // The proofs are verified by the proxy, against the constraints submitted for the slot
         Ok(header)
     }
 
@@ -491,14 +893,14 @@ impl CommitBoostApi {
     ) -> Result<(), CommitBoostError> {
         let response = self
             .client
-            .post(self.url.join(PERMISSION_DELEGATE_PATH).unwrap())
+            .post(self.endpoint(PERMISSION_DELEGATE_PATH)?)
             .header("content-type", "application/json")
             .body(serde_json::to_string(signed_data)?)
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("delegate", response).await;
             return Err(CommitBoostError::FailedDelegating(error));
         }
 
@@ -511,14 +913,14 @@ impl CommitBoostApi {
     ) -> Result<(), CommitBoostError> {
         let response = self
             .client
-            .post(self.url.join(PERMISSION_REVOKE_PATH).unwrap())
+            .post(self.endpoint(PERMISSION_REVOKE_PATH)?)
             .header("content-type", "application/json")
             .body(serde_json::to_string(signed_data)?)
-            .send()
+            .send_throttled(&self.auth, &self.limiter)
             .await?;
 
         if response.status() != StatusCode::OK {
-            let error = response.json::<ErrorResponse>().await?;
+            let error = relay_error("revoke", response).await;
             return Err(CommitBoostError::FailedRevoking(error));
         }
 
@@ -526,6 +928,43 @@ impl CommitBoostApi {
     }
 }
 
+/// Read the error of a response the relay failed, counted by operation and kind.
+async fn relay_error(operation: &'static str, response: reqwest::Response) -> ErrorResponse {
+    let error = ErrorResponse::from_response(response).await;
+    let kind = error.kind();
+    tracing::warn!(operation, kind = kind.as_str(), ?error, "Relay rejected the request");
+    ApiMetrics::increment_relay_errors_count(operation, kind.as_str());
+    error
+}
+
+/// Count the submitted constraints that are missing from the relay's acknowledged set.
+/// Size of the JSON encoding of `constraints`, the hex of the transactions and the fixed
+/// fields, so that the body is allocated once.
+fn encoded_size_hint(constraints: &[SignedConstraints]) -> usize {
+    constraints
+        .iter()
+        .map(|signed| {
+            let txs = &signed.message.transactions;
+            txs.iter().map(|c| 2 * c.tx.encode_2718_len() + 8).sum::<usize>() + 512
+        })
+        .sum()
+}
+
+fn missing_constraints(
+    submitted: &[SignedConstraints],
+    acknowledged: &[SignedConstraints],
+) -> usize {
+    let acknowledged = acknowledged
+        .iter()
+        .map(|sc| sc.message.digest())
+        .collect::<HashSet<_>>();
+
+    submitted
+        .iter()
+        .filter(|sc| !acknowledged.contains(&sc.message.digest()))
+        .count()
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(bound = "T: serde::Serialize + serde::de::DeserializeOwned")]
 pub struct VersionedValue<T> {
@@ -541,10 +980,20 @@ pub fn serialize_txs<S: serde::Serializer>(
     txs: &[Constraint],
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
+    // The buffers are reused across the transactions
+    let mut encoded = Vec::new();
+    let mut prefixed = Vec::new();
     let mut seq = serializer.serialize_seq(Some(txs.len()))?;
     for tx in txs {
-        let encoded = tx.tx.encoded_2718();
-        seq.serialize_element(&hex::encode_prefixed(encoded))?;
+        encoded.clear();
+        tx.tx.encode_2718(&mut encoded);
+
+        prefixed.clear();
+        prefixed.resize(2 + 2 * encoded.len(), 0);
+        prefixed[..2].copy_from_slice(b"0x");
+        hex::encode_to_slice(&encoded, &mut prefixed[2..]).map_err(serde::ser::Error::custom)?;
+        let prefixed = std::str::from_utf8(&prefixed).map_err(serde::ser::Error::custom)?;
+        seq.serialize_element(prefixed)?;
     }
     seq.end()
 }
@@ -566,4 +1015,62 @@ where
     }
 
     Ok(txs)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::{EthereumWallet, TransactionBuilder},
+        primitives::Bytes,
+        signers::local::PrivateKeySigner,
+    };
+    use reqwest::Url;
+
+    use super::{
+        auth::RelayAuth, rate_limit::RelayRateLimiter, CommitBoostApi, Constraint,
+        CONSTRAINTS_COLLECT_PATH, CONSTRAINTS_PATH, CONSTRAINTS_SPEC_PATH, CONSTRAINTS_V2_PATH,
+        GET_PAYLOAD_PATH, PERMISSION_DELEGATE_PATH, PERMISSION_REVOKE_PATH,
+        REGISTER_VALIDATORS_PATH, RELAY_CONSTRAINTS_PATH, STATUS_PATH,
+    };
+    use crate::test_utils::default_test_transaction;
+
+    #[test]
+    fn test_endpoints_keep_the_relay_base_path() {
+        let url = Url::parse("https://relay.xyz/prefix").unwrap();
+        let api = CommitBoostApi::new(
+            url.clone(),
+            RelayAuth::None,
+            RelayRateLimiter::new(&url, 1, 1),
+        );
+
+        for path in [
+            STATUS_PATH,
+            REGISTER_VALIDATORS_PATH,
+            GET_PAYLOAD_PATH,
+            CONSTRAINTS_PATH,
+            CONSTRAINTS_V2_PATH,
+            CONSTRAINTS_SPEC_PATH,
+            CONSTRAINTS_COLLECT_PATH,
+            RELAY_CONSTRAINTS_PATH,
+            PERMISSION_DELEGATE_PATH,
+            PERMISSION_REVOKE_PATH,
+        ] {
+            let endpoint = api.endpoint(path).unwrap();
+            assert_eq!(endpoint.as_str(), format!("https://relay.xyz/prefix{path}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_created_address() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let call = default_test_transaction(signer.address(), Some(3));
+        let deploy = call.clone().with_deploy_code(Bytes::from_static(&[0x60, 0x00]));
+
+        let call = Constraint::decode_enveloped(call.build(&wallet).await?.encoded_2718())?;
+        assert_eq!(call.created_address(), None);
+        let deploy = Constraint::decode_enveloped(deploy.build(&wallet).await?.encoded_2718())?;
+        assert_eq!(deploy.created_address(), Some(signer.address().create(3)));
+        Ok(())
+    }
+}
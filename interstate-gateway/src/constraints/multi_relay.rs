@@ -0,0 +1,218 @@
+use std::time::Duration;
+
+use futures::future::join_all;
+
+use super::{
+    versioned::ConstraintsVersion, CommitBoostApi, ConstraintsSubmissionStatus, SignedConstraints,
+};
+use crate::{errors::CommitBoostError, metrics::ApiMetrics};
+
+/// Default time each relay is given to answer a constraints submission.
+pub const DEFAULT_RELAY_TIMEOUT_MS: u64 = 1_000;
+
+/// Submits the constraints of a slot to several relays concurrently, so that a relay being
+/// down doesn't lose them. A submission succeeds once `quorum` relays accept it.
+///
+/// The first relay is the primary one, also proxied by the builder API.
+#[derive(Debug, Clone)]
+pub struct MultiRelayClient {
+    relays: Vec<CommitBoostApi>,
+    quorum: usize,
+    timeout: Duration,
+}
+
+impl MultiRelayClient {
+    pub fn new(primary: CommitBoostApi, others: Vec<CommitBoostApi>, quorum: usize) -> Self {
+        let relays = std::iter::once(primary).chain(others).collect::<Vec<_>>();
+        let quorum = quorum.clamp(1, relays.len());
+        Self { relays, quorum, timeout: Duration::from_millis(DEFAULT_RELAY_TIMEOUT_MS) }
+    }
+
+    /// Give up on a relay that doesn't answer a submission within `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn primary(&self) -> &CommitBoostApi {
+        &self.relays[0]
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.quorum
+    }
+
+    /// Whether every relay is degraded, the constraints then being submitted ahead of the
+    /// deadline if enabled.
+    pub fn all_degraded(&self) -> bool {
+        self.relays.iter().all(CommitBoostApi::is_degraded)
+    }
+
+    pub fn constraints_version(&self) -> ConstraintsVersion {
+        self.primary().constraints_version()
+    }
+
+    /// Negotiate the constraints message version with every relay. The constraints are signed
+    /// once for all of them, so the relays not supporting v2 hold everyone to v1.
+    pub async fn detect_constraints_version(&self) -> ConstraintsVersion {
+        let versions =
+            join_all(self.relays.iter().map(|relay| relay.detect_constraints_version())).await;
+        let version = if versions.iter().all(|version| *version == ConstraintsVersion::V2) {
+            ConstraintsVersion::V2
+        } else {
+            ConstraintsVersion::V1
+        };
+
+        for relay in &self.relays {
+            relay.set_constraints_version(version);
+        }
+        version
+    }
+
+    /// Submit the constraints to every relay, returning how many accepted them once they
+    /// reach the quorum.
+    pub async fn send_constraints(
+        &self,
+        constraints: &Vec<SignedConstraints>,
+    ) -> Result<usize, CommitBoostError> {
+        let submissions = self.relays.iter().map(|relay| async move {
+            let submitted = tokio::time::timeout(self.timeout, relay.send_constraints(constraints))
+                .await
+                .unwrap_or_else(|elapsed| Err(elapsed.into()));
+            (relay, submitted)
+        });
+
+        let mut accepted = 0;
+        let mut failure = None;
+        for (relay, submitted) in join_all(submissions).await {
+            let host = relay.url().host_str().unwrap_or_default();
+            match submitted {
+                Ok(()) => {
+                    ApiMetrics::increment_relay_submissions_count(host, "accepted");
+                    accepted += 1;
+                }
+                Err(err) => {
+                    tracing::warn!(relay = %relay.url(), ?err, "Relay failed to accept the constraints");
+                    ApiMetrics::increment_relay_submissions_count(host, "failed");
+                    failure.get_or_insert(err);
+                }
+            }
+        }
+
+        match failure {
+            Some(source) if accepted < self.quorum => Err(CommitBoostError::QuorumNotReached {
+                accepted,
+                quorum: self.quorum,
+                source: Box::new(source),
+            }),
+            _ => Ok(accepted),
+        }
+    }
+
+    /// Submit the constraints for a slot and verify that the quorum acknowledged them, see
+    /// [CommitBoostApi::send_and_confirm_constraints].
+    pub async fn send_and_confirm_constraints(
+        &self,
+        slot: u64,
+        constraints: &Vec<SignedConstraints>,
+    ) -> Result<ConstraintsSubmissionStatus, CommitBoostError> {
+        self.send_constraints(constraints).await?;
+        self.confirm_constraints(slot, constraints).await
+    }
+
+    /// Verify that the relays acknowledged the constraints already submitted for a slot. They
+    /// are `RelayConfirmed` once read back from the quorum, the relays missing some being sent
+    /// them again.
+    pub async fn confirm_constraints(
+        &self,
+        slot: u64,
+        constraints: &Vec<SignedConstraints>,
+    ) -> Result<ConstraintsSubmissionStatus, CommitBoostError> {
+        let confirmations = join_all(self.relays.iter().map(|relay| {
+            tokio::time::timeout(self.timeout, relay.confirm_constraints(slot, constraints))
+        }))
+        .await;
+
+        let confirmed = confirmations
+            .into_iter()
+            .filter(|status| matches!(status, Ok(Ok(ConstraintsSubmissionStatus::RelayConfirmed))))
+            .count();
+        Ok(if confirmed >= self.quorum {
+            ConstraintsSubmissionStatus::RelayConfirmed
+        } else {
+            ConstraintsSubmissionStatus::Submitted
+        })
+    }
+
+    /// Whether the quorum holds all the `constraints` of a slot, `None` if no relay can tell.
+    pub async fn holds_constraints(
+        &self,
+        slot: u64,
+        constraints: &[SignedConstraints],
+    ) -> Option<bool> {
+        let holds =
+            join_all(self.relays.iter().map(|relay| relay.holds_constraints(slot, constraints)))
+                .await;
+
+        if holds.iter().all(Option::is_none) {
+            return None;
+        }
+        Some(holds.iter().filter(|holds| **holds == Some(true)).count() >= self.quorum)
+    }
+
+    #[cfg(feature = "collector-client")]
+    pub async fn send_constraints_to_be_collected(
+        &self,
+        constraints: &Vec<SignedConstraints>,
+    ) -> Result<(), CommitBoostError> {
+        self.primary().send_constraints_to_be_collected(constraints).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{http::StatusCode, routing::post, Router};
+    use reqwest::Url;
+
+    use super::MultiRelayClient;
+    use crate::{
+        constraints::{
+            auth::RelayAuth, rate_limit::RelayRateLimiter, CommitBoostApi, CONSTRAINTS_PATH,
+        },
+        errors::CommitBoostError,
+        utils::retry::RetryPolicy,
+    };
+
+    /// A relay answering the constraints submissions with `status`.
+    async fn mock_relay(status: StatusCode) -> CommitBoostApi {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let app = Router::new().route(CONSTRAINTS_PATH, post(move || async move { status }));
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        CommitBoostApi::new(url.clone(), RelayAuth::None, RelayRateLimiter::new(&url, 100, 100))
+            .with_retry_policy(RetryPolicy { max_attempts: 1, ..Default::default() })
+    }
+
+    #[tokio::test]
+    async fn test_submission_quorum() {
+        let up = mock_relay(StatusCode::OK).await;
+        let down = mock_relay(StatusCode::INTERNAL_SERVER_ERROR).await;
+        let constraints = vec![Default::default()];
+
+        let client = MultiRelayClient::new(down.clone(), vec![up.clone()], 1)
+            .with_timeout(Duration::from_secs(1));
+        assert_eq!(client.send_constraints(&constraints).await.unwrap(), 1);
+
+        let client = MultiRelayClient::new(up, vec![down], 2);
+        let err = client.send_constraints(&constraints).await.unwrap_err();
+        assert!(matches!(err, CommitBoostError::QuorumNotReached { accepted: 1, quorum: 2, .. }));
+        // The rejection of the relay is still classified
+        assert!(err.relay_error().is_some());
+
+        // Only degraded once the breakers of all the relays opened
+        assert!(!client.all_degraded());
+    }
+}
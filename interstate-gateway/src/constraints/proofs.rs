@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{Bytes, B256};
+use ethereum_consensus::{bellatrix::mainnet::Transaction, ssz::prelude::HashTreeRoot};
+use ssz_rs::multiproofs::verify_merkle_multiproof;
+
+/// Proofs of inclusion of the constraints of a slot in the transactions of a relay bid, as
+/// an SSZ multiproof against its transactions root.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct InclusionProofs {
+    /// Hashes of the proven transactions, in the order of their generalized indexes.
+    pub transaction_hashes: Vec<B256>,
+    /// Generalized indexes of the proven transactions in the transactions list.
+    pub generalized_indexes: Vec<usize>,
+    /// Helper nodes of the multiproof, in decreasing order of their generalized indexes.
+    pub merkle_hashes: Vec<B256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ProofError {
+    #[error("the bid carries no inclusion proofs")]
+    Missing,
+    #[error("{hashes} transaction hashes proven at {indexes} generalized indexes")]
+    LengthMismatch { hashes: usize, indexes: usize },
+    #[error("constraint {0} isn't proven")]
+    MissingConstraint(B256),
+    #[error("transaction {0} isn't a constraint of the slot")]
+    UnknownTransaction(B256),
+    #[error("the multiproof doesn't match the transactions root")]
+    Invalid,
+}
+
+impl ProofError {
+    /// Stable reason reported in the metrics.
+    pub const fn reason(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing_proofs",
+            Self::LengthMismatch { .. } | Self::UnknownTransaction(_) => "malformed_proofs",
+            Self::MissingConstraint(_) => "missing_constraint",
+            Self::Invalid => "invalid_proofs",
+        }
+    }
+}
+
+/// Verify that the constraints of a slot, by hash with their EIP-2718 encoding, are all
+/// included in the transactions whose SSZ root is `transactions_root`.
+pub fn verify_inclusion_proofs(
+    constraints: &HashMap<B256, Bytes>,
+    proofs: Option<&InclusionProofs>,
+    transactions_root: B256,
+) -> Result<(), ProofError> {
+    let Some(proofs) = proofs else { return Err(ProofError::Missing) };
+
+    if proofs.transaction_hashes.len() != proofs.generalized_indexes.len() {
+        return Err(ProofError::LengthMismatch {
+            hashes: proofs.transaction_hashes.len(),
+            indexes: proofs.generalized_indexes.len(),
+        });
+    }
+    if let Some(hash) = constraints.keys().find(|hash| !proofs.transaction_hashes.contains(hash)) {
+        return Err(ProofError::MissingConstraint(*hash));
+    }
+
+    // The leaves are the roots of the proven transactions, as opaque byte lists
+    let leaves = proofs
+        .transaction_hashes
+        .iter()
+        .map(|hash| {
+            let encoded = constraints.get(hash).ok_or(ProofError::UnknownTransaction(*hash))?;
+            Transaction::try_from(encoded.as_ref())
+                .ok()
+                .and_then(|tx| tx.hash_tree_root().ok())
+                .ok_or(ProofError::Invalid)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    verify_merkle_multiproof(
+        &leaves,
+        &proofs.merkle_hashes,
+        &proofs.generalized_indexes,
+        transactions_root,
+    )
+    .map_err(|_| ProofError::Invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy::{
+        primitives::{Bytes, B256},
+        signers::k256::sha2::{Digest, Sha256},
+    };
+    use ethereum_consensus::{
+        bellatrix::mainnet::Transaction,
+        deneb::mainnet::MAX_TRANSACTIONS_PER_PAYLOAD,
+        ssz::prelude::{HashTreeRoot, List},
+    };
+
+    use super::{verify_inclusion_proofs, InclusionProofs, ProofError};
+
+    #[test]
+    fn test_verify_inclusion_proofs() {
+        let encoded = Bytes::from_static(&[2, 0xaa, 0xbb]);
+        let hash = B256::repeat_byte(1);
+        let constraints = HashMap::from([(hash, encoded.clone())]);
+
+        let mut transactions: List<Transaction, MAX_TRANSACTIONS_PER_PAYLOAD> = List::default();
+        transactions.push(Transaction::try_from(encoded.as_ref()).unwrap());
+        let root = transactions.hash_tree_root().unwrap();
+
+        // The branch of the first transaction: the empty subtrees up to the data root, then
+        // the length mixed in
+        let depth = MAX_TRANSACTIONS_PER_PAYLOAD.trailing_zeros() as usize;
+        let mut merkle_hashes = vec![B256::ZERO];
+        for _ in 1..depth {
+            let zero = merkle_hashes.last().unwrap();
+            let mut hasher = Sha256::new();
+            hasher.update(zero);
+            hasher.update(zero);
+            merkle_hashes.push(B256::from_slice(&hasher.finalize()));
+        }
+        let mut length = B256::ZERO;
+        length.0[0] = 1;
+        merkle_hashes.push(length);
+
+        let mut proofs = InclusionProofs {
+            transaction_hashes: vec![hash],
+            generalized_indexes: vec![2 << depth],
+            merkle_hashes,
+        };
+        assert_eq!(verify_inclusion_proofs(&constraints, Some(&proofs), root), Ok(()));
+        assert_eq!(verify_inclusion_proofs(&constraints, None, root), Err(ProofError::Missing));
+        assert_eq!(
+            verify_inclusion_proofs(&constraints, Some(&proofs), B256::ZERO),
+            Err(ProofError::Invalid)
+        );
+
+        // Every constraint of the slot must be proven
+        let other = B256::repeat_byte(2);
+        let mut more = constraints.clone();
+        more.insert(other, encoded);
+        assert_eq!(
+            verify_inclusion_proofs(&more, Some(&proofs), root),
+            Err(ProofError::MissingConstraint(other))
+        );
+
+        proofs.transaction_hashes = vec![other];
+        assert_eq!(
+            verify_inclusion_proofs(&constraints, Some(&proofs), root),
+            Err(ProofError::MissingConstraint(hash))
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! OpenAPI specification of the builder API proxied to the relay.
+//!
+//! The handlers of the proxy are methods of a server generic over its payload fetcher, which
+//! can't carry the path annotations, so the operations are declared here and checked against
+//! the routes of the proxy by the tests.
+
+use utoipa::OpenApi;
+
+use crate::errors::ErrorResponse;
+
+/// Path of the Swagger UI of the builder API.
+pub const PROXY_DOCS_PATH: &str = "/docs";
+/// Path of the OpenAPI specification of the builder API.
+pub const PROXY_OPENAPI_PATH: &str = "/openapi.json";
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Interstate gateway builder API",
+        description = "Builder API served to the beacon node, proxied to the constraints relay \
+                       with a fallback on locally built payloads."
+    ),
+    paths(status, register_validators, get_header, get_payload),
+    components(schemas(ErrorResponse)),
+    tags((name = "builder", description = "Builder API, see https://github.com/ethereum/builder-specs")),
+)]
+pub struct ProxyApiDoc;
+
+/// Status of the relay.
+#[utoipa::path(
+    get,
+    path = "/eth/v1/builder/status",
+    tag = "builder",
+    responses((status = 200), (status = 500, description = "Relay unreachable")),
+)]
+#[allow(dead_code)]
+fn status() {}
+
+/// Register the fee recipients and gas limits of the validators.
+#[utoipa::path(
+    post,
+    path = "/eth/v1/builder/validators",
+    tag = "builder",
+    request_body(content = Vec<Object>, description = "Signed validator registrations"),
+    responses((status = 200), (status = "4XX", body = ErrorResponse)),
+)]
+#[allow(dead_code)]
+fn register_validators() {}
+
+/// Header of the best bid of the slot with the inclusion proofs of its constraints, or of a
+/// locally built payload if the relay doesn't return one in time or its header is malformed:
+/// built on another parent, for another timestamp, out of the gas limit bounds of the parent,
+/// or with extra data or a fee recipient not allowed. The bids must also prove the inclusion
+/// of the constraints submitted for the slot.
+#[utoipa::path(
+    get,
+    path = "/eth/v1/builder/header/{slot}/{parent_hash}/{pubkey}",
+    tag = "builder",
+    params(
+        ("slot" = u64, Path),
+        ("parent_hash" = String, Path),
+        ("pubkey" = String, Path, description = "BLS public key of the proposer"),
+    ),
+    responses(
+        (status = 200, description = "Versioned signed builder bid", body = Object),
+        (status = 204, description = "No bid and no local payload for the slot"),
+    ),
+)]
+#[allow(dead_code)]
+fn get_header() {}
+
+/// Payload of a signed blinded block.
+#[utoipa::path(
+    post,
+    path = "/eth/v1/builder/blinded_blocks",
+    tag = "builder",
+    request_body(content = Object, description = "Signed blinded beacon block"),
+    responses(
+        (status = 200, description = "Versioned execution payload and blobs bundle", body = Object),
+        (status = 400, description = "Invalid block, or not matching the local payload", body = String),
+        (status = "4XX", body = ErrorResponse),
+    ),
+)]
+#[allow(dead_code)]
+fn get_payload() {}
+
+#[cfg(test)]
+mod tests {
+    use utoipa::OpenApi;
+
+    use super::ProxyApiDoc;
+    use crate::constraints::{
+        GET_HEADER_PATH, GET_PAYLOAD_PATH, REGISTER_VALIDATORS_PATH, STATUS_PATH,
+    };
+
+    #[test]
+    fn test_proxy_api_doc_matches_routes() {
+        let doc = ProxyApiDoc::openapi();
+        let mut documented = doc.paths.paths.keys().cloned().collect::<Vec<_>>();
+        documented.sort();
+
+        // Axum captures `:param`, OpenAPI templates `{param}`
+        let mut routes = [STATUS_PATH, REGISTER_VALIDATORS_PATH, GET_HEADER_PATH, GET_PAYLOAD_PATH]
+            .map(|path| {
+                path.split('/')
+                    .map(|segment| match segment.strip_prefix(':') {
+                        Some(param) => format!("{{{param}}}"),
+                        None => segment.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/")
+            });
+        routes.sort();
+        assert_eq!(documented, routes);
+    }
+}
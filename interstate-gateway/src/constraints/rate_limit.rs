@@ -0,0 +1,106 @@
+use std::{sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use reqwest::Url;
+use tokio::time::Instant;
+
+use crate::{config::Config, metrics::ApiMetrics};
+
+/// Default sustained rate of requests sent to a relay, per second.
+pub const DEFAULT_RELAY_RATE_LIMIT_PER_SEC: u32 = 20;
+
+/// Default number of requests that can be sent to a relay in a burst.
+pub const DEFAULT_RELAY_RATE_LIMIT_BURST: u32 = 40;
+
+/// Token bucket bounding the outbound request rate to a single relay.
+///
+/// Clones share the same bucket, so that the builder proxy (get_header retries) and the
+/// constraints submissions can't add up to a request storm toward the relay. Requests above
+/// the rate are delayed rather than rejected, as they are all needed to propose a block.
+#[derive(Debug, Clone)]
+pub struct RelayRateLimiter {
+    relay: Arc<str>,
+    rate_per_sec: f64,
+    burst: f64,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RelayRateLimiter {
+    pub fn new(relay: &Url, rate_per_sec: u32, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self {
+            relay: relay.host_str().unwrap_or(relay.as_str()).into(),
+            rate_per_sec: rate_per_sec.max(1) as f64,
+            burst,
+            bucket: Arc::new(Mutex::new(Bucket { tokens: burst, last_refill: Instant::now() })),
+        }
+    }
+
+    /// Create a limiter for the given relay using the limits from the sidecar config.
+    pub fn from_config(relay: &Url, config: &Config) -> Self {
+        Self::new(relay, config.relay_rate_limit_per_sec, config.relay_rate_limit_burst)
+    }
+
+    /// Wait until a request can be sent to the relay.
+    pub async fn acquire(&self) {
+        let start = Instant::now();
+
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock();
+                let now = Instant::now();
+                let refill = now.duration_since(bucket.last_refill).as_secs_f64() * self.rate_per_sec;
+                bucket.tokens = (bucket.tokens + refill).min(self.burst);
+                bucket.last_refill = now;
+
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    break;
+                }
+
+                Duration::from_secs_f64((1.0 - bucket.tokens) / self.rate_per_sec)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+
+        let queued = start.elapsed();
+        if !queued.is_zero() {
+            tracing::debug!(relay = %self.relay, ?queued, "Throttled relay request");
+            ApiMetrics::increment_relay_requests_throttled_count(&self.relay);
+        }
+        ApiMetrics::observe_relay_request_queue_time(&self.relay, queued);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::Url;
+    use tokio::time::Instant;
+
+    use super::RelayRateLimiter;
+
+    #[tokio::test]
+    async fn test_relay_rate_limiter_burst() {
+        let relay = Url::parse("http://localhost:3040").unwrap();
+        let limiter = RelayRateLimiter::new(&relay, 20, 2);
+
+        // The burst allowance goes through right away
+        let start = Instant::now();
+        limiter.acquire().await;
+        limiter.clone().acquire().await;
+        assert!(start.elapsed() < Duration::from_millis(25));
+
+        // Clones share the bucket, so the next request waits for a refill
+        limiter.clone().acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(25));
+    }
+}
@@ -0,0 +1,175 @@
+use std::time::Duration;
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    hex,
+    primitives::{Address, B256},
+};
+use reqwest::{Client, Url};
+use reth_primitives::SealedBlock;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::metrics::ApiMetrics;
+
+/// Default value of the fallback bid, 1 ETH, so that mev-boost picks the self-built payload.
+pub const DEFAULT_FALLBACK_BID_VALUE_WEI: u128 = 1_000_000_000_000_000_000;
+
+/// Time the estimator has to price a block before the static value is used.
+const ESTIMATE_TIMEOUT: Duration = Duration::from_millis(300);
+
+#[derive(Debug, Error)]
+pub enum EstimatorError {
+    #[error("failed to reach the estimator: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("invalid estimated value: {0}")]
+    InvalidValue(String),
+    #[error("timed out")]
+    Timeout,
+}
+
+/// Prices a fallback block, e.g. by simulating it with a local builder.
+#[async_trait::async_trait]
+pub trait ValueEstimator: Send + Sync {
+    /// Value of `block` to the proposer of `slot`, in wei.
+    async fn estimate(&self, slot: u64, block: &SealedBlock) -> Result<u128, EstimatorError>;
+}
+
+/// Block priced by an [HttpValueEstimator].
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EstimateRequest {
+    slot: u64,
+    block_number: u64,
+    block_hash: B256,
+    parent_hash: B256,
+    fee_recipient: Address,
+    gas_used: u64,
+    base_fee_per_gas: Option<u64>,
+    /// EIP-2718 encoded transactions.
+    transactions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EstimateResponse {
+    /// Value in wei, as a decimal string.
+    value: String,
+}
+
+/// Estimator served over HTTP: the block is posted as JSON to its URL, which answers with
+/// `{ "value": "<wei>" }`.
+#[derive(Debug, Clone)]
+pub struct HttpValueEstimator {
+    client: Client,
+    url: Url,
+}
+
+impl HttpValueEstimator {
+    pub fn new(url: Url) -> Self {
+        Self { client: Client::new(), url }
+    }
+}
+
+#[async_trait::async_trait]
+impl ValueEstimator for HttpValueEstimator {
+    async fn estimate(&self, slot: u64, block: &SealedBlock) -> Result<u128, EstimatorError> {
+        let header = &block.header;
+        let request = EstimateRequest {
+            slot,
+            block_number: header.number,
+            block_hash: block.hash(),
+            parent_hash: header.parent_hash,
+            fee_recipient: header.beneficiary,
+            gas_used: header.gas_used,
+            base_fee_per_gas: header.base_fee_per_gas,
+            transactions: block
+                .body
+                .transactions
+                .iter()
+                .map(|tx| hex::encode_prefixed(tx.encoded_2718()))
+                .collect(),
+        };
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&request)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<EstimateResponse>()
+            .await?;
+
+        response.value.parse().map_err(|_| EstimatorError::InvalidValue(response.value))
+    }
+}
+
+/// Value of the fallback bids: the one of the estimator when it answers in time, the static
+/// one otherwise.
+pub struct BlockValueSource {
+    estimator: Option<Box<dyn ValueEstimator>>,
+    static_value: u128,
+}
+
+impl BlockValueSource {
+    pub fn new(estimator: Option<Box<dyn ValueEstimator>>, static_value: u128) -> Self {
+        Self { estimator, static_value }
+    }
+
+    pub async fn value(&self, slot: u64, block: &SealedBlock) -> u128 {
+        let Some(estimator) = &self.estimator else {
+            return self.static_value;
+        };
+
+        let estimate = tokio::time::timeout(ESTIMATE_TIMEOUT, estimator.estimate(slot, block))
+            .await
+            .unwrap_or(Err(EstimatorError::Timeout));
+        match estimate {
+            Ok(value) => {
+                ApiMetrics::increment_fallback_value_estimates_count("estimated");
+                value
+            }
+            Err(err) => {
+                tracing::warn!(?err, slot, "Failed to estimate the fallback block value");
+                ApiMetrics::increment_fallback_value_estimates_count("static");
+                self.static_value
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for BlockValueSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockValueSource")
+            .field("estimator", &self.estimator.is_some())
+            .field("static_value", &self.static_value)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reth_primitives::SealedBlock;
+
+    use super::{BlockValueSource, EstimatorError, ValueEstimator};
+
+    struct Fixed(Option<u128>);
+
+    #[async_trait::async_trait]
+    impl ValueEstimator for Fixed {
+        async fn estimate(&self, _slot: u64, _block: &SealedBlock) -> Result<u128, EstimatorError> {
+            self.0.ok_or(EstimatorError::InvalidValue("unavailable".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_static_value_when_estimator_unavailable() {
+        let block = SealedBlock::default();
+
+        assert_eq!(BlockValueSource::new(None, 7).value(1, &block).await, 7);
+        let source = BlockValueSource::new(Some(Box::new(Fixed(Some(42)))), 7);
+        assert_eq!(source.value(1, &block).await, 42);
+        let source = BlockValueSource::new(Some(Box::new(Fixed(None))), 7);
+        assert_eq!(source.value(1, &block).await, 7);
+    }
+}
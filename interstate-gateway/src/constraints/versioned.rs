@@ -0,0 +1,289 @@
+use alloy::{
+    primitives::FixedBytes,
+    signers::k256::sha2::{Digest, Sha256},
+};
+use ethereum_consensus::crypto::PublicKey as ECBlsPublicKey;
+use serde::{Deserialize, Serialize};
+
+use super::{deserialize_txs, serialize_txs, Constraint, ConstraintsMessage, SignedConstraints};
+
+/// Max number of fields of the v2 constraints message. Fields can be appended up to this
+/// capacity without changing the generalized index, hence the digest, of the existing ones.
+const CONSTRAINTS_MESSAGE_V2_CAPACITY: usize = 16;
+
+/// Max number of transactions in a v2 constraints message.
+const MAX_TRANSACTIONS_PER_MESSAGE: usize = 256;
+
+type Chunk = [u8; 32];
+
+/// Version of the constraints message format, selected per relay from its capabilities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConstraintsVersion {
+    /// Flat sha256 digest over the message fields.
+    #[default]
+    V1,
+    /// SSZ `StableContainer` (EIP-7495) digest with optional fields.
+    V2,
+}
+
+impl ConstraintsVersion {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::V1 => "v1",
+            Self::V2 => "v2",
+        }
+    }
+
+    /// The latest version supported by both the sidecar and a relay advertising `versions`.
+    pub fn negotiate<'a>(versions: impl IntoIterator<Item = &'a str>) -> Self {
+        if versions.into_iter().any(|v| v == Self::V2.as_str()) {
+            Self::V2
+        } else {
+            Self::V1
+        }
+    }
+}
+
+/// Range of positions in the block in which the constraint transactions must be included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PositionRange {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Conditions under which the constraints are valid, as unix timestamps in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidityConditions {
+    pub not_before: u64,
+    pub not_after: u64,
+}
+
+/// The v2 constraints message, a superset of [ConstraintsMessage] with optional fields.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ConstraintsMessageV2 {
+    pub pubkey: ECBlsPublicKey,
+    pub slot: u64,
+    pub top: bool,
+    #[serde(deserialize_with = "deserialize_txs", serialize_with = "serialize_txs")]
+    pub transactions: Vec<Constraint>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position_range: Option<PositionRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validity: Option<ValidityConditions>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SignedConstraintsV2 {
+    pub message: ConstraintsMessageV2,
+    pub signature: FixedBytes<96>,
+}
+
+impl From<&ConstraintsMessage> for ConstraintsMessageV2 {
+    fn from(message: &ConstraintsMessage) -> Self {
+        Self {
+            pubkey: message.pubkey.clone(),
+            slot: message.slot,
+            top: message.top,
+            transactions: message.transactions.clone(),
+            position_range: None,
+            validity: None,
+        }
+    }
+}
+
+impl From<&SignedConstraints> for SignedConstraintsV2 {
+    fn from(signed: &SignedConstraints) -> Self {
+        Self { message: (&signed.message).into(), signature: signed.signature }
+    }
+}
+
+impl ConstraintsMessageV2 {
+    /// The `hash_tree_root` of the message as an SSZ `StableContainer[16]`:
+    ///
+    /// ```text
+    /// pubkey:         BLSPubkey
+    /// slot:           uint64
+    /// top:            boolean
+    /// transactions:   List[Bytes32, 256]    (transaction hashes)
+    /// position_range: Optional[PositionRange]
+    /// validity:       Optional[ValidityConditions]
+    /// ```
+    pub fn digest(&self) -> [u8; 32] {
+        v2_root(
+            &self.pubkey,
+            self.slot,
+            self.top,
+            &self.transactions,
+            self.position_range,
+            self.validity,
+        )
+    }
+}
+
+impl ConstraintsMessage {
+    /// The digest of the message in the given format version.
+    pub fn digest_for(&self, version: ConstraintsVersion) -> [u8; 32] {
+        match version {
+            ConstraintsVersion::V1 => self.digest(),
+            // The v2 message without its optional fields, without cloning the transactions
+            ConstraintsVersion::V2 => {
+                v2_root(&self.pubkey, self.slot, self.top, &self.transactions, None, None)
+            }
+        }
+    }
+}
+
+/// The root of a v2 message, computed on the stack unless it holds more transactions than
+/// [MAX_TRANSACTIONS_PER_MESSAGE].
+fn v2_root(
+    pubkey: &ECBlsPublicKey,
+    slot: u64,
+    top: bool,
+    transactions: &[Constraint],
+    position_range: Option<PositionRange>,
+    validity: Option<ValidityConditions>,
+) -> Chunk {
+    let mut fields = [None; CONSTRAINTS_MESSAGE_V2_CAPACITY];
+    fields[0] = Some(pubkey_root(pubkey));
+    fields[1] = Some(uint_chunk(slot));
+    fields[2] = Some(uint_chunk(top as u64));
+    fields[3] = Some(transactions_root(transactions));
+    fields[4] = position_range
+        .map(|range| hash_pair(&uint_chunk(range.start as u64), &uint_chunk(range.end as u64)));
+    fields[5] = validity.map(|validity| {
+        hash_pair(&uint_chunk(validity.not_before), &uint_chunk(validity.not_after))
+    });
+
+    stable_container_root(&fields)
+}
+
+/// EIP-7495: `mix_in_aux(merkleize(field_roots), hash_tree_root(active_fields))`, with the
+/// roots of inactive fields set to zero.
+fn stable_container_root(fields: &[Option<Chunk>; CONSTRAINTS_MESSAGE_V2_CAPACITY]) -> Chunk {
+    let mut roots = fields.map(Option::unwrap_or_default);
+
+    // Bitvector[16] fits in a single chunk, which is its own root
+    let mut active_fields = Chunk::default();
+    for (i, field) in fields.iter().enumerate() {
+        if field.is_some() {
+            active_fields[i / 8] |= 1 << (i % 8);
+        }
+    }
+
+    hash_pair(&merkleize(&mut roots, CONSTRAINTS_MESSAGE_V2_CAPACITY), &active_fields)
+}
+
+/// The root of the 48 bytes of the key, two chunks.
+fn pubkey_root(pubkey: &ECBlsPublicKey) -> Chunk {
+    let bytes: &[u8] = pubkey.as_ref();
+    let (mut left, mut right) = (Chunk::default(), Chunk::default());
+    left.copy_from_slice(&bytes[..32]);
+    right[..16].copy_from_slice(&bytes[32..]);
+    hash_pair(&left, &right)
+}
+
+fn transactions_root(transactions: &[Constraint]) -> Chunk {
+    let mut buffer = [Chunk::default(); MAX_TRANSACTIONS_PER_MESSAGE];
+    let mut overflow = Vec::new();
+    let hashes = match buffer.get_mut(..transactions.len()) {
+        Some(hashes) => hashes,
+        None => {
+            overflow.resize(transactions.len(), Chunk::default());
+            &mut overflow[..]
+        }
+    };
+    for (hash, constraint) in hashes.iter_mut().zip(transactions) {
+        *hash = constraint.tx.hash().0;
+    }
+    mix_in_length(&merkleize(hashes, MAX_TRANSACTIONS_PER_MESSAGE), transactions.len())
+}
+
+fn uint_chunk(value: u64) -> Chunk {
+    let mut chunk = Chunk::default();
+    chunk[..8].copy_from_slice(&value.to_le_bytes());
+    chunk
+}
+
+fn mix_in_length(root: &Chunk, length: usize) -> Chunk {
+    hash_pair(root, &uint_chunk(length as u64))
+}
+
+/// Merkleize the chunks, padded with zero chunks up to `limit` rounded to a power of two.
+/// The layers are hashed in place, overwriting the chunks.
+fn merkleize(chunks: &mut [Chunk], limit: usize) -> Chunk {
+    let depth = limit.next_power_of_two().trailing_zeros();
+
+    let mut zero_hash = Chunk::default();
+    let mut len = chunks.len();
+    for _ in 0..depth {
+        for i in 0..len.div_ceil(2) {
+            let right = if 2 * i + 1 < len { chunks[2 * i + 1] } else { zero_hash };
+            chunks[i] = hash_pair(&chunks[2 * i], &right);
+        }
+        len = len.div_ceil(2);
+        zero_hash = hash_pair(&zero_hash, &zero_hash);
+    }
+
+    if len == 0 {
+        zero_hash
+    } else {
+        chunks[0]
+    }
+}
+
+fn hash_pair(left: &Chunk, right: &Chunk) -> Chunk {
+    let mut hasher = Sha256::new();
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::hex;
+    use ethereum_consensus::crypto::PublicKey as ECBlsPublicKey;
+
+    use super::{ConstraintsMessageV2, ConstraintsVersion, PositionRange};
+    use crate::constraints::ConstraintsMessage;
+
+    fn message() -> ConstraintsMessage {
+        let pubkey = hex::decode("97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb").unwrap();
+        ConstraintsMessage {
+            pubkey: ECBlsPublicKey::try_from(pubkey.as_slice()).unwrap(),
+            slot: 42,
+            top: false,
+            transactions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_v1_digest_is_unchanged() {
+        let message = message();
+        let expected = "5bbdfcb74df57c2d9aacc1fbc3b3b86e9affad1ae6eec39724e6028cf3298474";
+
+        assert_eq!(hex::encode(message.digest()), expected);
+        assert_eq!(hex::encode(message.digest_for(ConstraintsVersion::V1)), expected);
+    }
+
+    #[test]
+    fn test_v2_digest_commits_to_optional_fields() {
+        let message = message();
+        let v2 = ConstraintsMessageV2::from(&message);
+        assert_eq!(message.digest_for(ConstraintsVersion::V2), v2.digest());
+        assert_ne!(v2.digest(), message.digest());
+
+        let with_range = ConstraintsMessageV2 {
+            position_range: Some(PositionRange { start: 0, end: 0 }),
+            ..v2.clone()
+        };
+        assert_ne!(with_range.digest(), v2.digest());
+    }
+
+    #[test]
+    fn test_negotiate_version() {
+        assert_eq!(ConstraintsVersion::negotiate(["v1"]), ConstraintsVersion::V1);
+        assert_eq!(ConstraintsVersion::negotiate(["v1", "v2"]), ConstraintsVersion::V2);
+        assert_eq!(ConstraintsVersion::negotiate(Vec::new()), ConstraintsVersion::V1);
+    }
+}
@@ -1,18 +1,22 @@
+use alloy::primitives::{Address, PrimitiveSignature};
 use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc};
 use tokio::sync::Mutex;
 use eyre::Result;
+
+use super::limiter::SigningLimiter;
+use crate::utils::http::OutboundHeaders;
+
 #[derive(Serialize, Deserialize)]
 struct Keys {
     /// The consensus keys stored in the Web3Signer.
     pub consensus: String,
-    /// The two below proxy fields are here for deserialisation purposes.
-    /// They are not used as signing is only over the consensus type.
+    /// The proxy BLS keys are here for deserialisation purposes only.
     #[allow(unused)]
     pub proxy_bls: Vec<String>,
-    #[allow(unused)]
+    /// Proxy ECDSA keys delegated by the consensus key, used for non-consensus signatures.
     pub proxy_ecdsa: Vec<String>,
 }
 
@@ -31,22 +35,67 @@ struct CommitBoostSignatureRequest {
     pub object_root: String,
 }
 
+/// Request signature from a proxy ECDSA key of the signer.
+#[derive(Serialize, Deserialize)]
+struct ProxyEcdsaSignatureRequest {
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub proxy: Address,
+    pub object_root: String,
+}
+
+/// Request a new proxy key delegated by a consensus key.
+#[derive(Serialize, Deserialize)]
+struct GenerateProxyKeyRequest {
+    pub pubkey: String,
+    pub scheme: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProxyDelegation {
+    pub delegator: String,
+    pub proxy: Address,
+}
+
+/// Delegation of a consensus key to a newly generated proxy key.
+#[derive(Serialize, Deserialize)]
+struct SignedProxyDelegation {
+    pub message: ProxyDelegation,
+    #[allow(unused)]
+    pub signature: String,
+}
+
 #[derive(Clone)]
 pub struct CBSigner {
     client: Client,
     base_url: String,
     jwt_token: Arc<Mutex<Option<String>>>,
+    limiter: Option<SigningLimiter>,
 }
 
 impl CBSigner {
     // Constructor to create a new API Client
     pub fn new(base_url: &str, jwt: &str) -> Self {
         CBSigner {
-            client: Client::new(),
+            client: OutboundHeaders::default().client(),
             base_url: base_url.to_string(),
             jwt_token: Arc::new(Mutex::new(Some(jwt.to_string()))),
+            limiter: None,
         }
     }
+
+    /// Bound the number of concurrent signing requests sent to the commit-boost signer.
+    pub fn with_limiter(mut self, limiter: SigningLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Identify the gateway to the commit-boost signer with `headers`.
+    pub fn with_headers(mut self, headers: &OutboundHeaders) -> Self {
+        self.client = headers.client();
+        self
+    }
+
     // Helper function to construct full URL
     fn full_url(&self, endpoint: &str) -> String {
         format!(
@@ -85,12 +134,109 @@ impl CBSigner {
         Ok(consensus_keys)
     }
 
+    // Headers of the authenticated JSON requests
+    async fn json_headers(&self) -> Result<HeaderMap> {
+        let jwt = self.jwt_token.lock().await;
+        let mut headers = HeaderMap::new();
+
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+        if let Some(token) = jwt.as_ref() {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(&format!("Bearer {}", token))?);
+        }
+        Ok(headers)
+    }
+
+    /// Proxy ECDSA keys delegated by the consensus key `pub_key`.
+    pub async fn get_proxy_ecdsa_keys(&self, pub_key: &str) -> Result<Vec<Address>> {
+        let url = self.full_url("signer/v1/get_pubkeys");
+        let response = self
+            .client
+            .get(url)
+            .headers(self.json_headers().await?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<CommitBoostKeys>()
+            .await?;
+
+        let pub_key = pub_key.to_lowercase();
+        response
+            .keys
+            .into_iter()
+            .filter(|key_set| key_set.consensus.to_lowercase() == pub_key)
+            .flat_map(|key_set| key_set.proxy_ecdsa)
+            .map(|proxy| Address::from_str(&proxy).map_err(Into::into))
+            .collect()
+    }
+
+    /// Generate a new proxy ECDSA key delegated by the consensus key `pub_key`.
+    pub async fn generate_proxy_ecdsa_key(&self, pub_key: &str) -> Result<Address> {
+        let url = self.full_url("/signer/v1/generate_proxy_key");
+        let body = GenerateProxyKeyRequest {
+            pubkey: pub_key.to_string(),
+            scheme: "ecdsa".to_string(),
+        };
+
+        let delegation = self
+            .client
+            .post(url)
+            .headers(self.json_headers().await?)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SignedProxyDelegation>()
+            .await?;
+
+        Ok(delegation.message.proxy)
+    }
+
+    /// Sign `object_root` with the proxy ECDSA key `proxy`. The signer signs its signing root
+    /// in the commit-boost domain.
+    pub async fn request_proxy_ecdsa_signature(
+        &self,
+        proxy: Address,
+        object_root: &str,
+    ) -> Result<PrimitiveSignature> {
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await?),
+            None => None,
+        };
+
+        let url = self.full_url("/signer/v1/request_signature");
+        let body = ProxyEcdsaSignatureRequest {
+            type_: "proxy_ecdsa".to_string(),
+            proxy,
+            object_root: object_root.to_string(),
+        };
+
+        let response = self
+            .client
+            .post(url)
+            .headers(self.json_headers().await?)
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        // The signer module responds with the signature as a JSON string
+        let signature = response.trim().trim_matches('"');
+        Ok(PrimitiveSignature::from_str(signature.trim_start_matches("0x"))?)
+    }
+
     // Generic function to send POST requests with authentication
     pub async fn request_signature(
         &self,
         pub_key: &str,
         object_root: &str,
     ) -> Result<String> {
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await?),
+            None => None,
+        };
+
         let url = self.full_url("/signer/v1/request_signature");
         let jwt = self.jwt_token.lock().await;
         let mut headers = HeaderMap::new();
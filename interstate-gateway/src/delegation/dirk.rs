@@ -0,0 +1,458 @@
+use std::{collections::HashMap, fs, sync::Arc, time::Duration};
+
+use alloy::hex;
+use blst::{
+    blst_fr, blst_fr_from_uint64, blst_fr_inverse, blst_fr_mul, blst_fr_sub, blst_p2,
+    blst_p2_add_or_double, blst_p2_affine, blst_p2_compress, blst_p2_from_affine, blst_p2_mult,
+    blst_p2_uncompress, blst_scalar, blst_scalar_from_fr, BLST_ERROR,
+};
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use futures::future::join_all;
+use tonic::{
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    metadata::MetadataValue,
+    transport::{Certificate, Channel, ClientTlsConfig, Identity},
+};
+
+use super::{
+    limiter::{SigningLimitError, SigningLimiter},
+    signer::DirkConfig,
+};
+use crate::{
+    keystores::BLSSig,
+    utils::http::{OutboundHeaders, OPERATOR_ID_HEADER},
+};
+
+const LIST_ACCOUNTS_PATH: &str = "/v1.Lister/ListAccounts";
+const SIGN_PATH: &str = "/v1.Signer/Sign";
+
+/// Time a Dirk instance has to answer a request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum DirkError {
+    #[error("failed to read the TLS credentials: {0}")]
+    Credentials(#[from] std::io::Error),
+    #[error("invalid endpoint {0}")]
+    InvalidEndpoint(String),
+    #[error("failed to reach Dirk: {0}")]
+    Transport(#[from] tonic::transport::Error),
+    #[error("request failed: {0}")]
+    Status(#[from] tonic::Status),
+    #[error("request {0} by Dirk")]
+    Refused(&'static str),
+    #[error("invalid public key of account {0}")]
+    InvalidPublicKey(String),
+    #[error("no Dirk account for public key {0}")]
+    UnknownPublicKey(String),
+    #[error("got {got} of the {threshold} signature shares required")]
+    NotEnoughShares { got: usize, threshold: usize },
+    #[error("invalid signature {0}")]
+    InvalidSignature(String),
+    #[error(transparent)]
+    Limit(#[from] SigningLimitError),
+}
+
+/// Messages of the Dirk API we use, mirroring its `v1` protobuf definitions.
+///
+/// Reference: https://github.com/wealdtech/eth2-signer-api
+mod proto {
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum ResponseState {
+        Unknown = 0,
+        Succeeded = 1,
+        Denied = 2,
+        Failed = 3,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListAccountsRequest {
+        #[prost(string, repeated, tag = "1")]
+        pub paths: Vec<String>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct ListAccountsResponse {
+        #[prost(enumeration = "ResponseState", tag = "1")]
+        pub state: i32,
+        #[prost(message, repeated, tag = "2")]
+        pub accounts: Vec<Account>,
+        #[prost(message, repeated, tag = "3")]
+        pub distributed_accounts: Vec<DistributedAccount>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Account {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(bytes = "vec", tag = "2")]
+        pub public_key: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DistributedAccount {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        /// Key of the share held by the instance answering.
+        #[prost(bytes = "vec", tag = "2")]
+        pub public_key: Vec<u8>,
+        #[prost(message, repeated, tag = "3")]
+        pub participants: Vec<Endpoint>,
+        #[prost(uint32, tag = "4")]
+        pub signing_threshold: u32,
+        #[prost(bytes = "vec", tag = "5")]
+        pub composite_public_key: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Endpoint {
+        #[prost(uint64, tag = "1")]
+        pub id: u64,
+        #[prost(string, tag = "2")]
+        pub name: String,
+        #[prost(uint32, tag = "3")]
+        pub port: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SignRequest {
+        #[prost(oneof = "sign_request::Id", tags = "1, 2")]
+        pub id: Option<sign_request::Id>,
+        #[prost(bytes = "vec", tag = "3")]
+        pub data: Vec<u8>,
+        #[prost(bytes = "vec", tag = "4")]
+        pub domain: Vec<u8>,
+    }
+
+    pub mod sign_request {
+        #[derive(Clone, PartialEq, prost::Oneof)]
+        pub enum Id {
+            #[prost(string, tag = "1")]
+            Account(String),
+            #[prost(bytes, tag = "2")]
+            PublicKey(Vec<u8>),
+        }
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SignResponse {
+        #[prost(enumeration = "ResponseState", tag = "1")]
+        pub state: i32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub signature: Vec<u8>,
+    }
+}
+
+/// A Dirk instance holding a share of a distributed account.
+#[derive(Debug, Clone)]
+struct Participant {
+    /// Id of the share, the point it was evaluated at.
+    id: u64,
+    channel: Channel,
+}
+
+#[derive(Debug, Clone)]
+enum Account {
+    /// Held whole by the instance we connect to.
+    Single { name: String },
+    /// Split among the participants, `threshold` of which must sign.
+    Distributed { name: String, participants: Vec<Participant>, threshold: usize },
+}
+
+/// Dirk remote signer.
+///
+/// Signatures of distributed accounts are requested from every participant and recombined
+/// from the first `threshold` shares.
+///
+/// Reference: https://github.com/attestantio/dirk
+#[derive(Debug, Clone)]
+pub struct DirkSigner {
+    channel: Channel,
+    accounts: Arc<HashMap<BlsPublicKey, Account>>,
+    domain: [u8; 32],
+    limiter: SigningLimiter,
+    headers: OutboundHeaders,
+}
+
+impl DirkSigner {
+    /// Connect to Dirk with mutual TLS and list the accounts of the configured wallets, signing
+    /// with `domain`. Every request identifies the gateway with `headers`.
+    pub async fn connect(
+        config: &DirkConfig,
+        domain: [u8; 32],
+        limiter: SigningLimiter,
+        headers: &OutboundHeaders,
+    ) -> Result<Self, DirkError> {
+        let tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(fs::read(&config.ca_cert_path)?))
+            .identity(Identity::from_pem(
+                fs::read(&config.client_cert_path)?,
+                fs::read(&config.client_key_path)?,
+            ));
+        let host = config.url.host_str().unwrap_or_default();
+        let channel = connect_lazy(config.url.as_str(), host, &tls, headers)?;
+
+        let request = proto::ListAccountsRequest { paths: config.wallets.clone() };
+        let response: proto::ListAccountsResponse =
+            unary(channel.clone(), LIST_ACCOUNTS_PATH, request, headers).await?;
+        check_state(response.state)?;
+
+        let mut accounts = HashMap::new();
+        for account in response.accounts {
+            let pubkey = parse_pubkey(&account.name, &account.public_key)?;
+            accounts.insert(pubkey, Account::Single { name: account.name });
+        }
+        for account in response.distributed_accounts {
+            let pubkey = parse_pubkey(&account.name, &account.composite_public_key)?;
+            let participants = account
+                .participants
+                .iter()
+                .map(|endpoint| {
+                    let url = format!("https://{}:{}", endpoint.name, endpoint.port);
+                    let channel = connect_lazy(&url, &endpoint.name, &tls, headers)?;
+                    Ok(Participant { id: endpoint.id, channel })
+                })
+                .collect::<Result<_, DirkError>>()?;
+            let threshold = account.signing_threshold as usize;
+            accounts.insert(pubkey, Account::Distributed { name: account.name, participants, threshold });
+        }
+        tracing::info!(accounts = accounts.len(), "Connected to Dirk");
+
+        Ok(Self {
+            channel,
+            accounts: Arc::new(accounts),
+            domain,
+            limiter,
+            headers: headers.clone(),
+        })
+    }
+
+    /// The public keys of the accounts, the composite ones for distributed accounts.
+    pub fn pubkeys(&self) -> impl Iterator<Item = &BlsPublicKey> {
+        self.accounts.keys()
+    }
+
+    /// Sign `root` with the account of `public_key`.
+    pub async fn sign(&self, root: [u8; 32], public_key: &BlsPublicKey) -> Result<BLSSig, DirkError> {
+        let account = self
+            .accounts
+            .get(public_key)
+            .ok_or_else(|| DirkError::UnknownPublicKey(public_key.to_string()))?;
+        let _permit = self.limiter.acquire().await?;
+
+        let signature = match account {
+            Account::Single { name } => self.sign_share(self.channel.clone(), name, root).await?,
+            Account::Distributed { name, participants, threshold } => {
+                let shares = join_all(participants.iter().map(|participant| async move {
+                    let share = self.sign_share(participant.channel.clone(), name, root).await;
+                    (participant.id, share)
+                }))
+                .await;
+
+                let shares = shares
+                    .into_iter()
+                    .filter_map(|(id, share)| match share {
+                        Ok(share) => Some((id, share)),
+                        Err(err) => {
+                            tracing::warn!(?err, id, account = %name, "Dirk participant failed to sign");
+                            None
+                        }
+                    })
+                    .take(*threshold)
+                    .collect::<Vec<_>>();
+                if shares.len() < *threshold {
+                    return Err(DirkError::NotEnoughShares { got: shares.len(), threshold: *threshold });
+                }
+                recover_signature(&shares)?
+            }
+        };
+
+        Ok(BLSSig::from(signature))
+    }
+
+    async fn sign_share(
+        &self,
+        channel: Channel,
+        account: &str,
+        root: [u8; 32],
+    ) -> Result<[u8; 96], DirkError> {
+        let request = proto::SignRequest {
+            id: Some(proto::sign_request::Id::Account(account.to_string())),
+            data: root.to_vec(),
+            domain: self.domain.to_vec(),
+        };
+        let response: proto::SignResponse = unary(channel, SIGN_PATH, request, &self.headers).await?;
+        check_state(response.state)?;
+
+        response
+            .signature
+            .as_slice()
+            .try_into()
+            .map_err(|_| DirkError::InvalidSignature(hex::encode_prefixed(&response.signature)))
+    }
+}
+
+fn connect_lazy(
+    url: &str,
+    host: &str,
+    tls: &ClientTlsConfig,
+    headers: &OutboundHeaders,
+) -> Result<Channel, DirkError> {
+    let endpoint =
+        Channel::from_shared(url.to_string()).map_err(|_| DirkError::InvalidEndpoint(url.to_string()))?;
+    Ok(endpoint
+        .user_agent(headers.user_agent.as_str())?
+        .tls_config(tls.clone().domain_name(host))?
+        .timeout(REQUEST_TIMEOUT)
+        .connect_lazy())
+}
+
+/// Call the gRPC method at `path`.
+async fn unary<Req, Res>(
+    channel: Channel,
+    path: &'static str,
+    request: Req,
+    headers: &OutboundHeaders,
+) -> Result<Res, DirkError>
+where
+    Req: prost::Message + Send + 'static,
+    Res: prost::Message + Default + Send + 'static,
+{
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await?;
+    let codec = ProstCodec::<Req, Res>::default();
+    let mut request = tonic::Request::new(request);
+    if let Some(Ok(operator_id)) = headers.operator_id.as_deref().map(MetadataValue::try_from) {
+        request.metadata_mut().insert(OPERATOR_ID_HEADER, operator_id);
+    }
+    let response = grpc.unary(request, PathAndQuery::from_static(path), codec).await?;
+    Ok(response.into_inner())
+}
+
+fn check_state(state: i32) -> Result<(), DirkError> {
+    match proto::ResponseState::try_from(state) {
+        Ok(proto::ResponseState::Succeeded) => Ok(()),
+        Ok(proto::ResponseState::Denied) => Err(DirkError::Refused("denied")),
+        _ => Err(DirkError::Refused("failed")),
+    }
+}
+
+fn parse_pubkey(account: &str, bytes: &[u8]) -> Result<BlsPublicKey, DirkError> {
+    BlsPublicKey::try_from(bytes).map_err(|_| DirkError::InvalidPublicKey(account.to_string()))
+}
+
+fn fr_from_u64(value: u64) -> blst_fr {
+    let mut fr = blst_fr::default();
+    // SAFETY: the input is 4 limbs long, as expected
+    unsafe { blst_fr_from_uint64(&mut fr, [value, 0, 0, 0].as_ptr()) };
+    fr
+}
+
+/// Recover the signature of a distributed account from the signature shares of distinct
+/// participants, by Lagrange interpolation at zero over their ids.
+pub fn recover_signature(shares: &[(u64, [u8; 96])]) -> Result<[u8; 96], DirkError> {
+    let ids = shares.iter().map(|(id, _)| fr_from_u64(*id)).collect::<Vec<_>>();
+    let mut recovered: Option<blst_p2> = None;
+
+    for (i, (_, share)) in shares.iter().enumerate() {
+        let mut affine = blst_p2_affine::default();
+        // SAFETY: the input is a 96 bytes compressed point
+        if unsafe { blst_p2_uncompress(&mut affine, share.as_ptr()) } != BLST_ERROR::BLST_SUCCESS {
+            return Err(DirkError::InvalidSignature(hex::encode_prefixed(share)));
+        }
+
+        // Coefficient of the share: the product of x_j / (x_j - x_i) over the other ids
+        let (mut numerator, mut denominator) = (fr_from_u64(1), fr_from_u64(1));
+        let (numerator_ptr, denominator_ptr): (*mut blst_fr, *mut blst_fr) =
+            (&mut numerator, &mut denominator);
+        for (_, id) in ids.iter().enumerate().filter(|(j, _)| *j != i) {
+            let mut difference = blst_fr::default();
+            // SAFETY: in place operations on valid field elements
+            unsafe {
+                blst_fr_mul(numerator_ptr, numerator_ptr, id);
+                blst_fr_sub(&mut difference, id, &ids[i]);
+                blst_fr_mul(denominator_ptr, denominator_ptr, &difference);
+            }
+        }
+        let (mut inverse, mut coefficient) = (blst_fr::default(), blst_fr::default());
+        let mut scalar = blst_scalar::default();
+        let (mut point, mut term) = (blst_p2::default(), blst_p2::default());
+        // SAFETY: operations on valid field elements and points, the scalar is 255 bits
+        unsafe {
+            blst_fr_inverse(&mut inverse, &denominator);
+            blst_fr_mul(&mut coefficient, &numerator, &inverse);
+            blst_scalar_from_fr(&mut scalar, &coefficient);
+            blst_p2_from_affine(&mut point, &affine);
+            blst_p2_mult(&mut term, &point, scalar.b.as_ptr(), 255);
+        }
+
+        recovered = Some(match recovered {
+            None => term,
+            Some(sum) => {
+                let mut total = blst_p2::default();
+                // SAFETY: addition of valid points
+                unsafe { blst_p2_add_or_double(&mut total, &sum, &term) };
+                total
+            }
+        });
+    }
+
+    let recovered = recovered.ok_or(DirkError::NotEnoughShares { got: 0, threshold: 1 })?;
+    let mut signature = [0u8; 96];
+    // SAFETY: the output is 96 bytes long, as expected
+    unsafe { blst_p2_compress(signature.as_mut_ptr(), &recovered) };
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use blst::{
+        blst_bendian_from_scalar, blst_fr, blst_fr_add, blst_fr_from_scalar, blst_fr_mul,
+        blst_scalar, blst_scalar_from_bendian, blst_scalar_from_fr, min_pk::SecretKey,
+    };
+
+    use super::{fr_from_u64, recover_signature};
+    use crate::BLS_DST_PREFIX;
+
+    fn to_fr(sk: &SecretKey) -> blst_fr {
+        let (mut scalar, mut fr) = (blst_scalar::default(), blst_fr::default());
+        unsafe {
+            blst_scalar_from_bendian(&mut scalar, sk.to_bytes().as_ptr());
+            blst_fr_from_scalar(&mut fr, &scalar);
+        }
+        fr
+    }
+
+    fn to_sk(fr: &blst_fr) -> SecretKey {
+        let (mut scalar, mut bytes) = (blst_scalar::default(), [0u8; 32]);
+        unsafe {
+            blst_scalar_from_fr(&mut scalar, fr);
+            blst_bendian_from_scalar(bytes.as_mut_ptr(), &scalar);
+        }
+        SecretKey::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_recover_threshold_signature() {
+        let secret = SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let slope = SecretKey::key_gen(&[2; 32], &[]).unwrap();
+        let root = [7u8; 32];
+
+        // 2 of 3 shares of the secret, on the line through it with the slope
+        let share = |id: u64| {
+            let (mut product, mut key) = (blst_fr::default(), blst_fr::default());
+            unsafe {
+                blst_fr_mul(&mut product, &to_fr(&slope), &fr_from_u64(id));
+                blst_fr_add(&mut key, &to_fr(&secret), &product);
+            }
+            (id, to_sk(&key).sign(&root, BLS_DST_PREFIX, &[]).to_bytes())
+        };
+
+        let expected = secret.sign(&root, BLS_DST_PREFIX, &[]).to_bytes();
+        assert_eq!(recover_signature(&[share(1), share(3)]).unwrap(), expected);
+        assert_eq!(recover_signature(&[share(3), share(2)]).unwrap(), expected);
+        assert_ne!(recover_signature(&[share(1)]).unwrap(), expected);
+    }
+}
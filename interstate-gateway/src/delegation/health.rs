@@ -0,0 +1,270 @@
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use beacon_api_client::mainnet::Client;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use futures::future::join_all;
+use parking_lot::RwLock;
+use reqwest::Url;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::types::{merge_messages, Chain, SignedDelegation, SignedMessage};
+use crate::{
+    config::ValidatorIndexes,
+    constraints::{
+        auth::{RelayAuth, RelayRequestExt},
+        rate_limit::RelayRateLimiter,
+    },
+    metrics::ApiMetrics,
+    state::slot_clock::SlotClock,
+    utils::{now_ms, url::join_path},
+};
+
+/// Why a proposal of one of our validators can't be served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GapReason {
+    /// The relay has no valid delegation from the validator.
+    NoDelegation,
+    /// The delegations of the validator to our keys are all expired.
+    Expired,
+    /// The validator only delegates to keys we don't hold.
+    UnknownDelegatee,
+}
+
+impl GapReason {
+    pub const ALL: [Self; 3] = [Self::NoDelegation, Self::Expired, Self::UnknownDelegatee];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::NoDelegation => "no_delegation",
+            Self::Expired => "expired",
+            Self::UnknownDelegatee => "unknown_delegatee",
+        }
+    }
+}
+
+/// An upcoming proposal of one of our validators we can't commit for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DelegationGap {
+    pub slot: u64,
+    #[schema(value_type = String)]
+    pub validator: BlsPublicKey,
+    pub reason: GapReason,
+}
+
+/// Outcome of the last delegation health check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DelegationReport {
+    pub checked_at_ms: u64,
+    pub epoch: u64,
+    /// Number of upcoming proposals of our validators in the epoch.
+    pub proposals: usize,
+    pub gaps: Vec<DelegationGap>,
+}
+
+/// Whether `delegations` let us commit for a proposal of `validator` in `epoch`.
+pub fn find_gap(
+    validator: &BlsPublicKey,
+    delegations: &[SignedDelegation],
+    local_keys: &HashSet<BlsPublicKey>,
+    epoch: u64,
+) -> Option<GapReason> {
+    let delegations = delegations
+        .iter()
+        .filter(|d| &d.message.validator_pubkey == validator)
+        .collect::<Vec<_>>();
+    let (ours, theirs): (Vec<_>, Vec<_>) =
+        delegations.iter().partition(|d| local_keys.contains(&d.message.delegatee_pubkey));
+
+    if ours.iter().any(|d| !d.message.is_expired(epoch)) {
+        None
+    } else if !ours.is_empty() {
+        Some(GapReason::Expired)
+    } else if !theirs.is_empty() {
+        Some(GapReason::UnknownDelegatee)
+    } else {
+        Some(GapReason::NoDelegation)
+    }
+}
+
+/// Periodically cross-references the delegations registered on the relay with the delegatee
+/// keys we hold and the proposer duties of our validators, to flag the upcoming slots we
+/// won't be able to commit for before they come.
+#[derive(Debug, Clone, Default)]
+pub struct DelegationHealth {
+    report: Arc<RwLock<Option<DelegationReport>>>,
+}
+
+impl DelegationHealth {
+    /// The last report, `None` until the first check completed.
+    pub fn report(&self) -> Option<DelegationReport> {
+        self.report.read().clone()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        &self,
+        interval: Duration,
+        beacon_client: Client,
+        slot_clock: SlotClock,
+        validators: ValidatorIndexes,
+        local_keys: HashSet<BlsPublicKey>,
+        chain: Chain,
+        relay: RelayDelegations,
+    ) {
+        let health = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let slot = slot_clock.current_slot();
+                let epoch = slot_clock.epoch_of(slot);
+                let duties = match beacon_client.get_proposer_duties(epoch).await {
+                    Ok((_, duties)) => duties,
+                    Err(err) => {
+                        tracing::error!(?err, epoch, "Failed to fetch the proposer duties");
+                        continue;
+                    }
+                };
+
+                let mut report = DelegationReport {
+                    checked_at_ms: now_ms(),
+                    epoch,
+                    proposals: 0,
+                    gaps: Vec::new(),
+                };
+                let upcoming = duties.iter().filter(|duty| {
+                    duty.slot > slot && validators.contains(duty.validator_index as u64)
+                });
+                for duty in upcoming {
+                    let delegations = match relay.fetch_messages(duty.slot).await {
+                        Ok(messages) => merge_messages(messages, chain),
+                        Err(err) => {
+                            tracing::error!(?err, slot = duty.slot, "Failed to fetch delegations");
+                            continue;
+                        }
+                    };
+
+                    report.proposals += 1;
+                    if let Some(reason) =
+                        find_gap(&duty.public_key, &delegations, &local_keys, epoch)
+                    {
+                        tracing::warn!(
+                            slot = duty.slot,
+                            validator = ?duty.public_key,
+                            reason = reason.as_str(),
+                            "No usable delegation for an upcoming proposal"
+                        );
+                        report.gaps.push(DelegationGap {
+                            slot: duty.slot,
+                            validator: duty.public_key.clone(),
+                            reason,
+                        });
+                    }
+                }
+
+                for reason in GapReason::ALL {
+                    let count = report.gaps.iter().filter(|gap| gap.reason == reason).count();
+                    ApiMetrics::set_delegation_gaps(reason.as_str(), count);
+                }
+                *health.report.write() = Some(report);
+            }
+        });
+    }
+}
+
+/// A relay the delegations are read from.
+#[derive(Debug, Clone)]
+pub struct DelegationRelay {
+    pub url: Url,
+    pub auth: RelayAuth,
+    pub limiter: RelayRateLimiter,
+}
+
+/// Reads the delegations of the proposer of a slot from every configured relay.
+#[derive(Debug, Clone)]
+pub struct RelayDelegations {
+    pub client: reqwest::Client,
+    pub relays: Vec<DelegationRelay>,
+}
+
+impl RelayDelegations {
+    /// The signed delegations and revocations of the proposer of a slot, from every relay.
+    ///
+    /// The relays are queried concurrently and those failing are skipped, the fetch only fails
+    /// when all of them do. The messages are returned as is, see [merge_messages].
+    pub async fn fetch_messages(&self, slot: u64) -> eyre::Result<Vec<SignedMessage>> {
+        let responses =
+            join_all(self.relays.iter().map(|relay| self.fetch_from(relay, slot))).await;
+
+        let mut messages = Vec::new();
+        let (mut fetched, mut last_err) = (false, None);
+        for (relay, response) in self.relays.iter().zip(responses) {
+            match response {
+                Ok(relay_messages) => {
+                    fetched = true;
+                    messages.extend(relay_messages);
+                }
+                Err(err) => {
+                    tracing::warn!(?err, relay = %relay.url, slot, "Failed to fetch delegations");
+                    last_err = Some(err);
+                }
+            }
+        }
+
+        match last_err {
+            Some(err) if !fetched => Err(err),
+            _ => Ok(messages),
+        }
+    }
+
+    async fn fetch_from(
+        &self,
+        relay: &DelegationRelay,
+        slot: u64,
+    ) -> eyre::Result<Vec<SignedMessage>> {
+        let url = join_path(&relay.url, &format!("/relay/v1/builder/delegations?slot={slot}"))?;
+        let response = self.client.get(url).send_throttled(&relay.auth, &relay.limiter).await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ethereum_consensus::crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature};
+
+    use super::{find_gap, GapReason};
+    use crate::delegation::types::{DelegationMessage, SignedDelegation};
+
+    fn pubkey(byte: u8) -> BlsPublicKey {
+        let sk = blst::min_pk::SecretKey::key_gen(&[byte; 32], &[]).unwrap();
+        BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap()
+    }
+
+    fn delegation(message: DelegationMessage) -> SignedDelegation {
+        SignedDelegation { message, signature: BlsSignature::default() }
+    }
+
+    #[test]
+    fn test_find_gap() {
+        let (validator, ours, theirs) = (pubkey(1), pubkey(2), pubkey(3));
+        let local_keys = HashSet::from([ours.clone()]);
+        let to_ours = DelegationMessage::new(validator.clone(), ours);
+        let to_theirs = delegation(DelegationMessage::new(validator.clone(), theirs));
+
+        let gap = |delegations: &[SignedDelegation]| {
+            find_gap(&validator, delegations, &local_keys, 10)
+        };
+        assert_eq!(gap(&[]), Some(GapReason::NoDelegation));
+        assert_eq!(gap(&[to_theirs.clone()]), Some(GapReason::UnknownDelegatee));
+        assert_eq!(
+            gap(&[to_theirs.clone(), delegation(to_ours.clone().with_expiry(9))]),
+            Some(GapReason::Expired)
+        );
+        assert_eq!(gap(&[to_theirs, delegation(to_ours.with_expiry(10))]), None);
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use beacon_api_client::mainnet::Client;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use parking_lot::RwLock;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{
+    health::RelayDelegations,
+    types::{merge_messages, Chain, SignedDelegation, SignedMessage},
+};
+use crate::{config::ValidatorIndexes, state::slot_clock::SlotClock, utils::now_ms};
+
+/// Where we learned that we are expected to sign with a delegatee key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpectedKeySource {
+    /// Listed in `DELEGATEE_PUBKEYS`.
+    Config,
+    /// Delegated to in the local delegations file.
+    LocalDelegations,
+    /// Delegated to on the relay by one of our validators proposing soon.
+    Relay,
+}
+
+/// A delegatee key we are expected to sign with, missing from the signer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct MissingKey {
+    #[schema(value_type = String)]
+    pub delegatee: BlsPublicKey,
+    pub sources: Vec<ExpectedKeySource>,
+}
+
+/// Outcome of the startup check of the delegatee keys.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct KeyCheckReport {
+    pub checked_at_ms: u64,
+    /// Number of distinct delegatee keys we are expected to sign with.
+    pub expected: usize,
+    pub missing: Vec<MissingKey>,
+}
+
+/// The expected keys that aren't in `available`, with every source expecting them.
+pub fn find_missing_keys(
+    expected: impl IntoIterator<Item = (BlsPublicKey, ExpectedKeySource)>,
+    available: &HashSet<BlsPublicKey>,
+) -> (usize, Vec<MissingKey>) {
+    // In the order they were first expected
+    let mut keys: Vec<(BlsPublicKey, Vec<ExpectedKeySource>)> = Vec::new();
+    for (delegatee, source) in expected {
+        match keys.iter_mut().find(|(key, _)| *key == delegatee) {
+            Some((_, sources)) if !sources.contains(&source) => sources.push(source),
+            Some(_) => {}
+            None => keys.push((delegatee, vec![source])),
+        }
+    }
+
+    let expected = keys.len();
+    let missing = keys
+        .into_iter()
+        .filter(|(delegatee, _)| !available.contains(delegatee))
+        .map(|(delegatee, mut sources)| {
+            sources.sort();
+            MissingKey { delegatee, sources }
+        })
+        .collect();
+    (expected, missing)
+}
+
+/// The delegatees of the active relay delegations of `validator` we are expected to sign
+/// with: all of them when none is in `available`, as the validator then can't be served.
+fn relay_delegatees(
+    validator: &BlsPublicKey,
+    delegations: &[SignedDelegation],
+    available: &HashSet<BlsPublicKey>,
+    epoch: u64,
+) -> Vec<BlsPublicKey> {
+    let delegatees = delegations
+        .iter()
+        .filter(|d| &d.message.validator_pubkey == validator && !d.message.is_expired(epoch))
+        .map(|d| d.message.delegatee_pubkey.clone())
+        .collect::<Vec<_>>();
+    if delegatees.iter().any(|delegatee| available.contains(delegatee)) {
+        return Vec::new();
+    }
+    delegatees
+}
+
+/// Sources of the delegatee keys we are expected to sign with.
+#[derive(Debug, Clone)]
+pub struct ExpectedKeys {
+    pub configured: Vec<BlsPublicKey>,
+    pub local_path: Option<PathBuf>,
+    /// Our validators, whose relay delegations for the current and next epochs are checked.
+    pub validators: Option<ValidatorIndexes>,
+    pub beacon_client: Client,
+    pub slot_clock: SlotClock,
+    pub relay: RelayDelegations,
+    pub chain: Chain,
+}
+
+/// Checks at startup that the signer holds every delegatee key we are expected to sign with,
+/// so that a missing key fails readiness instead of the commitment requests.
+#[derive(Debug, Clone, Default)]
+pub struct SignerKeyCheck {
+    report: Arc<RwLock<Option<KeyCheckReport>>>,
+}
+
+impl SignerKeyCheck {
+    /// The report, `None` until the check completed.
+    pub fn report(&self) -> Option<KeyCheckReport> {
+        self.report.read().clone()
+    }
+
+    pub async fn run(&self, expected: ExpectedKeys, available: HashSet<BlsPublicKey>) {
+        let mut keys = expected
+            .configured
+            .iter()
+            .map(|key| (key.clone(), ExpectedKeySource::Config))
+            .collect::<Vec<_>>();
+
+        if let Some(path) = &expected.local_path {
+            match read_local_delegatees(path) {
+                Ok(delegatees) => keys.extend(
+                    delegatees.into_iter().map(|key| (key, ExpectedKeySource::LocalDelegations)),
+                ),
+                Err(err) => tracing::error!(%err, "Failed to read the local delegations"),
+            }
+        }
+
+        if let Some(validators) = &expected.validators {
+            let slot = expected.slot_clock.current_slot();
+            let epoch = expected.slot_clock.epoch_of(slot);
+            for epoch in [epoch, epoch + 1] {
+                let duties = match expected.beacon_client.get_proposer_duties(epoch).await {
+                    Ok((_, duties)) => duties,
+                    Err(err) => {
+                        tracing::error!(?err, epoch, "Failed to fetch the proposer duties");
+                        continue;
+                    }
+                };
+                let upcoming = duties.iter().filter(|duty| {
+                    duty.slot > slot && validators.contains(duty.validator_index as u64)
+                });
+                for duty in upcoming {
+                    let delegations = match expected.relay.fetch_messages(duty.slot).await {
+                        Ok(messages) => merge_messages(messages, expected.chain),
+                        Err(err) => {
+                            tracing::error!(?err, slot = duty.slot, "Failed to fetch delegations");
+                            continue;
+                        }
+                    };
+                    let delegatees =
+                        relay_delegatees(&duty.public_key, &delegations, &available, epoch);
+                    keys.extend(delegatees.into_iter().map(|key| (key, ExpectedKeySource::Relay)));
+                }
+            }
+        }
+
+        let (expected, missing) = find_missing_keys(keys, &available);
+        for key in &missing {
+            tracing::error!(
+                delegatee = ?key.delegatee,
+                sources = ?key.sources,
+                "Expected delegatee key missing from the signer"
+            );
+        }
+        tracing::info!(expected, missing = missing.len(), "Checked the delegatee keys");
+
+        *self.report.write() = Some(KeyCheckReport { checked_at_ms: now_ms(), expected, missing });
+    }
+}
+
+/// The delegatees of the delegations in the local delegations file.
+fn read_local_delegatees(path: &Path) -> eyre::Result<Vec<BlsPublicKey>> {
+    let messages = serde_json::from_slice::<Vec<SignedMessage>>(&std::fs::read(path)?)?;
+    Ok(messages
+        .into_iter()
+        .filter_map(|message| match message {
+            SignedMessage::Delegation(delegation) => Some(delegation.message.delegatee_pubkey),
+            SignedMessage::Revocation(_) => None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ethereum_consensus::crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature};
+
+    use super::{find_missing_keys, relay_delegatees, ExpectedKeySource};
+    use crate::delegation::types::{DelegationMessage, SignedDelegation};
+
+    fn pubkey(byte: u8) -> BlsPublicKey {
+        let sk = blst::min_pk::SecretKey::key_gen(&[byte; 32], &[]).unwrap();
+        BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap()
+    }
+
+    #[test]
+    fn test_missing_delegatee_keys() {
+        let (validator, held, missing, other) = (pubkey(1), pubkey(2), pubkey(3), pubkey(4));
+        let available = HashSet::from([held.clone()]);
+        let delegation = |delegatee: &BlsPublicKey| SignedDelegation {
+            message: DelegationMessage::new(validator.clone(), delegatee.clone()),
+            signature: BlsSignature::default(),
+        };
+
+        // A validator also delegating to a key we hold is served, the other key isn't ours
+        let delegations = [delegation(&held), delegation(&other)];
+        assert!(relay_delegatees(&validator, &delegations, &available, 10).is_empty());
+        let delegations = [delegation(&missing)];
+        let relay = relay_delegatees(&validator, &delegations, &available, 10);
+        assert_eq!(relay, vec![missing.clone()]);
+
+        let expected = [
+            (held.clone(), ExpectedKeySource::Config),
+            (missing.clone(), ExpectedKeySource::Relay),
+            (missing.clone(), ExpectedKeySource::Config),
+            (missing.clone(), ExpectedKeySource::Relay),
+        ];
+        let (count, missing_keys) = find_missing_keys(expected, &available);
+        assert_eq!(count, 2);
+        assert_eq!(missing_keys.len(), 1);
+        assert_eq!(missing_keys[0].delegatee, missing);
+        assert_eq!(
+            missing_keys[0].sources,
+            vec![ExpectedKeySource::Config, ExpectedKeySource::Relay]
+        );
+    }
+}
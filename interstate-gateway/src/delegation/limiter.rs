@@ -0,0 +1,150 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{config::Config, metrics::ApiMetrics};
+
+/// Default max number of in-flight signing requests per remote signer backend.
+pub const DEFAULT_MAX_CONCURRENT_SIGNINGS: usize = 32;
+
+/// Default time a signing request may wait in the queue for a free slot.
+pub const DEFAULT_SIGNING_QUEUE_TIMEOUT_MILLIS: u64 = 1_000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningLimitError {
+    #[error("signer backend {backend} saturated: no signing slot freed up within {timeout:?}")]
+    Saturated {
+        backend: &'static str,
+        timeout: Duration,
+    },
+    #[error("signer backend {0} limiter is closed")]
+    Closed(&'static str),
+}
+
+/// Bounds the number of concurrent signing requests sent to a single signer backend.
+///
+/// Requests above the limit are queued on a semaphore and rejected once they have been
+/// waiting for longer than the configured queue timeout, so that a burst of commitment
+/// requests can't trip the rate limits of a remote signer.
+#[derive(Debug, Clone)]
+pub struct SigningLimiter {
+    backend: &'static str,
+    semaphore: Arc<Semaphore>,
+    max_concurrent: usize,
+    queue_timeout: Duration,
+}
+
+impl SigningLimiter {
+    pub fn new(backend: &'static str, max_concurrent: usize, queue_timeout: Duration) -> Self {
+        Self {
+            backend,
+            semaphore: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            queue_timeout,
+        }
+    }
+
+    /// Create a limiter for the given backend using the limits from the sidecar config.
+    pub fn from_config(backend: &'static str, config: &Config) -> Self {
+        Self::new(
+            backend,
+            config.max_concurrent_signings,
+            Duration::from_millis(config.signing_queue_timeout_ms),
+        )
+    }
+
+    /// Wait for a free signing slot. The returned permit releases the slot when dropped.
+    pub async fn acquire(&self) -> Result<SigningPermit, SigningLimitError> {
+        let permit = match tokio::time::timeout(
+            self.queue_timeout,
+            self.semaphore.clone().acquire_owned(),
+        )
+        .await
+        {
+            Ok(Ok(permit)) => permit,
+            Ok(Err(_)) => return Err(SigningLimitError::Closed(self.backend)),
+            Err(_) => {
+                tracing::warn!(
+                    backend = self.backend,
+                    "Signer backend saturated, rejecting signing request"
+                );
+                ApiMetrics::increment_signer_saturated_count(self.backend);
+                return Err(SigningLimitError::Saturated {
+                    backend: self.backend,
+                    timeout: self.queue_timeout,
+                });
+            }
+        };
+
+        ApiMetrics::set_signer_inflight_signings(self.backend, self.in_flight());
+
+        Ok(SigningPermit {
+            _permit: permit,
+            limiter: self.clone(),
+        })
+    }
+
+    /// Number of signing requests currently holding a slot.
+    pub fn in_flight(&self) -> usize {
+        self.max_concurrent - self.semaphore.available_permits()
+    }
+}
+
+/// Signing limiters of the signer backends, shared by all the clients of each backend so that
+/// their requests count against the same limit.
+#[derive(Debug, Clone)]
+pub struct SigningLimiters {
+    pub web3signer: SigningLimiter,
+    pub dirk: SigningLimiter,
+    pub commit_boost: SigningLimiter,
+}
+
+impl SigningLimiters {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            web3signer: SigningLimiter::from_config("web3signer", config),
+            dirk: SigningLimiter::from_config("dirk", config),
+            commit_boost: SigningLimiter::from_config("commit-boost", config),
+        }
+    }
+}
+
+/// A slot held on a [SigningLimiter] for the duration of a single signing request.
+#[derive(Debug)]
+pub struct SigningPermit {
+    _permit: OwnedSemaphorePermit,
+    limiter: SigningLimiter,
+}
+
+impl Drop for SigningPermit {
+    fn drop(&mut self) {
+        // The inner permit is released right after this, hence the `- 1`.
+        ApiMetrics::set_signer_inflight_signings(
+            self.limiter.backend,
+            self.limiter.in_flight().saturating_sub(1),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{SigningLimitError, SigningLimiter};
+
+    #[tokio::test]
+    async fn test_signing_limiter_saturation() {
+        let limiter = SigningLimiter::new("test", 2, Duration::from_millis(50));
+
+        let first = limiter.acquire().await.expect("free slot");
+        let _second = limiter.acquire().await.expect("free slot");
+        assert_eq!(limiter.in_flight(), 2);
+
+        let err = limiter.acquire().await.expect_err("limiter should be saturated");
+        assert!(matches!(err, SigningLimitError::Saturated { .. }));
+
+        drop(first);
+        assert_eq!(limiter.in_flight(), 1);
+        assert!(limiter.acquire().await.is_ok());
+    }
+}
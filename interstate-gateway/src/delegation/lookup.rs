@@ -0,0 +1,295 @@
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use beacon_api_client::mainnet::Client;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::{
+    health::RelayDelegations,
+    types::{merge_delegations, verify_revocations, Chain, SignedDelegation, SignedMessage},
+};
+use crate::state::slot_clock::SlotClock;
+
+/// Where a delegation known to the gateway comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DelegationSource {
+    Relay,
+    Local,
+    Both,
+}
+
+/// A verified delegation of a validator, valid in the current epoch and not revoked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ActiveDelegation {
+    #[schema(value_type = String)]
+    pub delegatee: BlsPublicKey,
+    pub expiry_epoch: Option<u64>,
+    pub source: DelegationSource,
+    /// Whether the gateway holds the key of the delegatee, and can sign for the validator.
+    pub local_key: bool,
+}
+
+/// Delegations and revocations of a validator known to the gateway.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ValidatorDelegations {
+    #[schema(value_type = String)]
+    pub validator: BlsPublicKey,
+    pub epoch: u64,
+    /// Upcoming proposal of the validator the relay delegations were fetched for. The relay
+    /// is only queried when the validator proposes in the current or next epoch.
+    pub relay_slot: Option<u64>,
+    pub delegations: Vec<ActiveDelegation>,
+    /// Delegatees whose delegation the validator revoked.
+    #[schema(value_type = Vec<String>)]
+    pub revocations: Vec<BlsPublicKey>,
+}
+
+#[derive(Debug, Error)]
+pub enum LookupError {
+    #[error("delegations lookup not ready")]
+    NotReady,
+    #[error("failed to fetch the proposer duties: {0}")]
+    Beacon(String),
+    #[error("failed to fetch delegations from the relay: {0}")]
+    Relay(String),
+    #[error("failed to read the local delegations: {0}")]
+    Local(String),
+}
+
+/// Sources of the delegations lookup.
+#[derive(Debug, Clone)]
+pub struct LookupSources {
+    pub beacon_client: Client,
+    pub slot_clock: SlotClock,
+    pub relay: RelayDelegations,
+    pub local_path: Option<PathBuf>,
+    pub local_keys: HashSet<BlsPublicKey>,
+    pub chain: Chain,
+}
+
+/// Looks up the delegations applicable to a validator, to debug why it isn't served.
+///
+/// Its sources are connected once the relay client is set up, lookups fail until then.
+#[derive(Debug, Clone, Default)]
+pub struct DelegationLookup {
+    sources: Arc<OnceLock<LookupSources>>,
+}
+
+impl DelegationLookup {
+    pub fn connect(&self, sources: LookupSources) {
+        if self.sources.set(sources).is_err() {
+            tracing::warn!("Delegations lookup already connected");
+        }
+    }
+
+    pub async fn lookup(
+        &self,
+        validator: &BlsPublicKey,
+    ) -> Result<ValidatorDelegations, LookupError> {
+        let sources = self.sources.get().ok_or(LookupError::NotReady)?;
+        let slot = sources.slot_clock.current_slot();
+        let epoch = sources.slot_clock.epoch_of(slot);
+
+        let mut relay_slot = None;
+        for epoch in [epoch, epoch + 1] {
+            let (_, duties) = sources
+                .beacon_client
+                .get_proposer_duties(epoch)
+                .await
+                .map_err(|err| LookupError::Beacon(err.to_string()))?;
+            relay_slot = duties
+                .iter()
+                .find(|duty| duty.slot > slot && &duty.public_key == validator)
+                .map(|duty| duty.slot);
+            if relay_slot.is_some() {
+                break;
+            }
+        }
+
+        let relay = match relay_slot {
+            Some(slot) => sources
+                .relay
+                .fetch_messages(slot)
+                .await
+                .map_err(|err| LookupError::Relay(err.to_string()))?,
+            None => Vec::new(),
+        };
+
+        let local = match &sources.local_path {
+            Some(path) => {
+                let content =
+                    std::fs::read(path).map_err(|err| LookupError::Local(err.to_string()))?;
+                serde_json::from_slice::<Vec<SignedMessage>>(&content)
+                    .map_err(|err| LookupError::Local(err.to_string()))?
+            }
+            None => Vec::new(),
+        };
+
+        let (delegations, revocations) =
+            summarize(validator, relay, local, &sources.local_keys, epoch, sources.chain);
+        Ok(ValidatorDelegations {
+            validator: validator.clone(),
+            epoch,
+            relay_slot,
+            delegations,
+            revocations,
+        })
+    }
+}
+
+/// Verify and de-duplicate the delegations of `validator` from the relays and the local file,
+/// keeping those active in `epoch` and not revoked by a verified revocation from either.
+pub fn summarize(
+    validator: &BlsPublicKey,
+    relay: Vec<SignedMessage>,
+    local: Vec<SignedMessage>,
+    local_keys: &HashSet<BlsPublicKey>,
+    epoch: u64,
+    chain: Chain,
+) -> (Vec<ActiveDelegation>, Vec<BlsPublicKey>) {
+    let mut revocations = Vec::new();
+    let mut split = |messages: Vec<SignedMessage>| {
+        let mut delegations = Vec::new();
+        for message in messages {
+            match message {
+                SignedMessage::Delegation(delegation) => delegations.push(delegation),
+                SignedMessage::Revocation(revocation)
+                    if revocation.message.validator_pubkey == *validator =>
+                {
+                    revocations.push(revocation)
+                }
+                SignedMessage::Revocation(_) => {}
+            }
+        }
+        delegations
+    };
+    let (relay, local_delegations) = (split(relay), split(local));
+    let revocations = verify_revocations(revocations, chain);
+
+    let of_validator = |delegations: Vec<SignedDelegation>| {
+        let delegations = delegations
+            .into_iter()
+            .filter(|delegation| delegation.message.validator_pubkey == *validator);
+        merge_delegations(delegations, chain)
+    };
+    let relay = of_validator(relay);
+    let local = of_validator(local_delegations);
+    let delegatees = |delegations: &[SignedDelegation]| {
+        delegations.iter().map(|d| d.message.delegatee_pubkey.clone()).collect::<HashSet<_>>()
+    };
+    let (from_relay, from_local) = (delegatees(&relay), delegatees(&local));
+
+    let delegations = merge_delegations(relay.into_iter().chain(local), chain)
+        .into_iter()
+        .filter(|delegation| !delegation.message.is_expired(epoch))
+        .filter(|delegation| !revocations.iter().any(|revocation| revocation.revokes(delegation)))
+        .map(|delegation| {
+            let delegatee = delegation.message.delegatee_pubkey;
+            let source = match (from_relay.contains(&delegatee), from_local.contains(&delegatee)) {
+                (true, true) => DelegationSource::Both,
+                (true, false) => DelegationSource::Relay,
+                _ => DelegationSource::Local,
+            };
+            ActiveDelegation {
+                local_key: local_keys.contains(&delegatee),
+                delegatee,
+                expiry_epoch: delegation.message.expiry_epoch,
+                source,
+            }
+        })
+        .collect();
+
+    let revoked = revocations.into_iter().map(|revocation| revocation.message.delegatee_pubkey);
+    (delegations, revoked.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use blst::min_pk::SecretKey;
+    use ethereum_consensus::{
+        crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature},
+        deneb::compute_signing_root,
+    };
+
+    use super::{summarize, DelegationSource};
+    use crate::delegation::{
+        signing::{compute_domain_from_mask, BLS_DST_PREFIX},
+        types::{
+            Chain, DelegationMessage, RevocationMessage, SignedDelegation, SignedMessage,
+            SignedRevocation,
+        },
+    };
+
+    fn key(byte: u8) -> (SecretKey, BlsPublicKey) {
+        let sk = SecretKey::key_gen(&[byte; 32], &[]).unwrap();
+        let pk = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        (sk, pk)
+    }
+
+    fn sign(sk: &SecretKey, digest: [u8; 32]) -> BlsSignature {
+        let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+        let root = compute_signing_root(&digest, domain).unwrap();
+        let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+        BlsSignature::try_from(&signature[..]).unwrap()
+    }
+
+    #[test]
+    fn test_summarize_delegations() {
+        let (sk, validator) = key(1);
+        let (ours, theirs, revoked, expired) = (key(2).1, key(3).1, key(4).1, key(5).1);
+        let withdrawn = key(6).1;
+        let delegation = |delegatee: &BlsPublicKey, expiry: Option<u64>| {
+            let mut message = DelegationMessage::new(validator.clone(), delegatee.clone());
+            message.expiry_epoch = expiry;
+            SignedDelegation { signature: sign(&sk, message.digest()), message }
+        };
+        let revocation = |delegatee: &BlsPublicKey| {
+            let message = RevocationMessage::new(validator.clone(), delegatee.clone());
+            SignedRevocation { signature: sign(&sk, message.digest()), message }
+        };
+
+        // The local file round trips delegations and revocations
+        let local = serde_json::to_string(&[
+            SignedMessage::Delegation(delegation(&ours, None)),
+            SignedMessage::Delegation(delegation(&revoked, None)),
+            SignedMessage::Revocation(revocation(&revoked)),
+        ])
+        .unwrap();
+        let local = serde_json::from_str(&local).unwrap();
+        // A revocation from any relay drops the delegations it revokes from the others
+        let relay = vec![
+            SignedMessage::Delegation(delegation(&ours, None)),
+            SignedMessage::Delegation(delegation(&theirs, Some(20))),
+            SignedMessage::Delegation(delegation(&expired, Some(5))),
+            SignedMessage::Delegation(delegation(&withdrawn, None)),
+            SignedMessage::Revocation(revocation(&withdrawn)),
+        ];
+
+        let (delegations, revocations) = summarize(
+            &validator,
+            relay,
+            local,
+            &HashSet::from([ours.clone()]),
+            10,
+            Chain::Holesky,
+        );
+        assert_eq!(revocations, vec![withdrawn, revoked]);
+        let summary = delegations
+            .iter()
+            .map(|d| (d.delegatee.clone(), d.source, d.local_key))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![(ours, DelegationSource::Both, true), (theirs, DelegationSource::Relay, false)]
+        );
+    }
+}
@@ -1,5 +1,14 @@
+#[cfg(feature = "signer-web3")]
 pub mod web3signer;
 pub mod cb_signer;
+#[cfg(feature = "signer-dirk")]
+pub mod dirk;
+pub mod health;
+pub mod keycheck;
+pub mod limiter;
+pub mod lookup;
+pub mod registry;
+pub mod signer;
 pub mod types;
 pub mod signing;
 use std::{fs::read_to_string, ops::Deref, path::PathBuf};
@@ -46,6 +55,8 @@ pub struct DelegationMessage {
     pub action: u8,
     pub validator_pubkey: BlsPublicKey,
     pub delegatee_pubkey: BlsPublicKey,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_epoch: Option<u64>,
 }
 
 impl DelegationMessage {
@@ -54,6 +65,7 @@ impl DelegationMessage {
             action: DelegationMessageType::Delegation as u8,
             validator_pubkey,
             delegatee_pubkey,
+            expiry_epoch: None,
         }
     }
 
@@ -62,6 +74,9 @@ impl DelegationMessage {
         hasher.update([self.action]);
         hasher.update(self.validator_pubkey.to_vec());
         hasher.update(self.delegatee_pubkey.to_vec());
+        if let Some(expiry_epoch) = self.expiry_epoch {
+            hasher.update(expiry_epoch.to_le_bytes());
+        }
         let result = hasher.finalize().into();
         result
     }
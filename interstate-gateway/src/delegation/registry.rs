@@ -0,0 +1,415 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use beacon_api_client::mainnet::Client;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use parking_lot::RwLock;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{
+    health::RelayDelegations,
+    types::{
+        merge_delegations, verify_revocations, Chain, SignedDelegation, SignedMessage,
+        SignedRevocation,
+    },
+};
+use crate::{metrics::ApiMetrics, state::slot_clock::SlotClock};
+
+/// State of a registered delegation in the current epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistryStatus {
+    Active,
+    /// Expired, and pruned from the registry at the next sync.
+    Expired,
+    /// Revoked by the validator, never served.
+    Revoked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RegisteredDelegation {
+    #[schema(value_type = String)]
+    pub delegatee: BlsPublicKey,
+    pub expiry_epoch: Option<u64>,
+    pub status: RegistryStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RegisteredValidator {
+    #[schema(value_type = String)]
+    pub validator: BlsPublicKey,
+    pub delegations: Vec<RegisteredDelegation>,
+}
+
+/// Delegations held by the registry, as served to the operators.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RegistrySnapshot {
+    pub epoch: u64,
+    /// Latest proposal slot whose delegations were synced from the relay.
+    pub last_synced_slot: Option<u64>,
+    pub validators: Vec<RegisteredValidator>,
+}
+
+/// Verified delegations and revocations of a validator.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Entry {
+    delegations: Vec<SignedDelegation>,
+    revocations: Vec<SignedRevocation>,
+}
+
+impl Entry {
+    /// Keep the messages of `validator` with a valid signature, merging its delegations.
+    fn verify(validator: &BlsPublicKey, messages: Vec<SignedMessage>, chain: Chain) -> Self {
+        let (mut delegations, mut revocations) = (Vec::new(), Vec::new());
+        for message in messages {
+            match message {
+                SignedMessage::Delegation(delegation)
+                    if delegation.message.validator_pubkey == *validator =>
+                {
+                    delegations.push(delegation)
+                }
+                SignedMessage::Revocation(revocation)
+                    if revocation.message.validator_pubkey == *validator =>
+                {
+                    revocations.push(revocation)
+                }
+                _ => {}
+            }
+        }
+
+        Self {
+            delegations: merge_delegations(delegations, chain),
+            revocations: verify_revocations(revocations, chain),
+        }
+    }
+
+    fn is_revoked(&self, delegation: &SignedDelegation) -> bool {
+        self.revocations.iter().any(|revocation| revocation.revokes(delegation))
+    }
+
+    fn messages(&self) -> impl Iterator<Item = SignedMessage> + '_ {
+        let delegations = self.delegations.iter().cloned().map(SignedMessage::Delegation);
+        delegations.chain(self.revocations.iter().cloned().map(SignedMessage::Revocation))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    validators: HashMap<BlsPublicKey, Entry>,
+    /// Proposal slots whose delegations were synced, pruned as they pass.
+    synced: BTreeSet<u64>,
+}
+
+/// Delegations of the proposers, synced from the relays ahead of their slots so that the
+/// commitment requests don't wait on them, and persisted across restarts when a path is set.
+///
+/// The relays are the source of truth: the delegations and revocations of a proposer are
+/// replaced by the ones they return for its slot. Delegations revoked on any relay are never
+/// served, and expired ones are pruned as the epochs pass.
+///
+/// The registry is connected to the relays once their client is set up, syncs fail until then.
+#[derive(Debug, Clone)]
+pub struct DelegationRegistry {
+    chain: Chain,
+    slot_clock: SlotClock,
+    path: Option<PathBuf>,
+    relay: Arc<OnceLock<RelayDelegations>>,
+    registry: Arc<RwLock<Registry>>,
+}
+
+impl DelegationRegistry {
+    /// Open the registry persisted at `path`, in the format of the local delegations file.
+    /// Its messages are verified again, and the expired delegations pruned.
+    pub fn open(path: Option<PathBuf>, chain: Chain, slot_clock: SlotClock) -> eyre::Result<Self> {
+        let mut registry = Registry::default();
+        if let Some(path) = path.as_ref().filter(|path| path.exists()) {
+            let messages: Vec<SignedMessage> = serde_json::from_slice(&std::fs::read(path)?)?;
+            let mut by_validator: HashMap<BlsPublicKey, Vec<SignedMessage>> = HashMap::new();
+            for message in messages {
+                let validator = match &message {
+                    SignedMessage::Delegation(delegation) => &delegation.message.validator_pubkey,
+                    SignedMessage::Revocation(revocation) => &revocation.message.validator_pubkey,
+                };
+                by_validator.entry(validator.clone()).or_default().push(message);
+            }
+            for (validator, messages) in by_validator {
+                let entry = Entry::verify(&validator, messages, chain);
+                if !entry.delegations.is_empty() {
+                    registry.validators.insert(validator, entry);
+                }
+            }
+        }
+
+        let registry = Self {
+            chain,
+            path,
+            relay: Arc::default(),
+            registry: Arc::new(RwLock::new(registry)),
+            slot_clock,
+        };
+        registry.prune(registry.slot_clock.current_slot());
+        Ok(registry)
+    }
+
+    pub fn connect(&self, relay: RelayDelegations) {
+        if self.relay.set(relay).is_err() {
+            tracing::warn!("Delegation registry already connected");
+        }
+    }
+
+    /// Whether the delegations of the proposer of `slot` were synced.
+    pub fn is_synced(&self, slot: u64) -> bool {
+        self.registry.read().synced.contains(&slot)
+    }
+
+    /// Sync the delegations and revocations of `proposer` from the relays, for its proposal
+    /// at `slot`.
+    pub async fn sync_slot(&self, slot: u64, proposer: &BlsPublicKey) -> eyre::Result<()> {
+        let relay =
+            self.relay.get().ok_or_else(|| eyre::eyre!("delegation registry not connected"))?;
+        let messages = match relay.fetch_messages(slot).await {
+            Ok(messages) => messages,
+            Err(err) => {
+                ApiMetrics::increment_delegation_syncs_count("failed");
+                return Err(err);
+            }
+        };
+
+        ApiMetrics::increment_delegation_syncs_count("synced");
+        self.apply(slot, proposer, messages);
+        Ok(())
+    }
+
+    /// The delegations of `validator` which aren't revoked, expired in the current epoch or not.
+    pub fn delegations_of(&self, validator: &BlsPublicKey) -> Vec<SignedDelegation> {
+        let registry = self.registry.read();
+        let Some(entry) = registry.validators.get(validator) else { return Vec::new() };
+        entry
+            .delegations
+            .iter()
+            .filter(|delegation| !entry.is_revoked(delegation))
+            .cloned()
+            .collect()
+    }
+
+    pub fn snapshot(&self) -> RegistrySnapshot {
+        let epoch = self.slot_clock.epoch_of(self.slot_clock.current_slot());
+        let registry = self.registry.read();
+
+        let mut validators = registry
+            .validators
+            .iter()
+            .map(|(validator, entry)| {
+                let delegations = entry
+                    .delegations
+                    .iter()
+                    .map(|delegation| RegisteredDelegation {
+                        delegatee: delegation.message.delegatee_pubkey.clone(),
+                        expiry_epoch: delegation.message.expiry_epoch,
+                        status: if entry.is_revoked(delegation) {
+                            RegistryStatus::Revoked
+                        } else if delegation.message.is_expired(epoch) {
+                            RegistryStatus::Expired
+                        } else {
+                            RegistryStatus::Active
+                        },
+                    })
+                    .collect();
+                RegisteredValidator { validator: validator.clone(), delegations }
+            })
+            .collect::<Vec<_>>();
+        validators.sort_by_key(|validator| validator.validator.to_vec());
+
+        RegistrySnapshot { epoch, last_synced_slot: registry.synced.last().copied(), validators }
+    }
+
+    /// Periodically sync the delegations of the upcoming proposers of the current and next
+    /// epochs. Those of the current epoch are synced again at every tick to catch the late
+    /// revocations, those of the next epoch only once.
+    pub fn spawn(&self, interval: Duration, beacon_client: Client) {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let slot = registry.slot_clock.current_slot();
+                let epoch = registry.slot_clock.epoch_of(slot);
+                registry.prune(slot);
+
+                for (epoch, resync) in [(epoch, true), (epoch + 1, false)] {
+                    let duties = match beacon_client.get_proposer_duties(epoch).await {
+                        Ok((_, duties)) => duties,
+                        Err(err) => {
+                            tracing::error!(?err, epoch, "Failed to fetch the proposer duties");
+                            continue;
+                        }
+                    };
+
+                    let upcoming = duties.iter().filter(|duty| {
+                        duty.slot > slot && (resync || !registry.is_synced(duty.slot))
+                    });
+                    for duty in upcoming {
+                        if let Err(err) = registry.sync_slot(duty.slot, &duty.public_key).await {
+                            tracing::warn!(?err, slot = duty.slot, "Failed to sync delegations");
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    fn apply(&self, slot: u64, proposer: &BlsPublicKey, messages: Vec<SignedMessage>) {
+        let entry = Entry::verify(proposer, messages, self.chain);
+
+        let mut registry = self.registry.write();
+        registry.synced.insert(slot);
+        let previous = if entry.delegations.is_empty() {
+            registry.validators.remove(proposer)
+        } else {
+            registry.validators.insert(proposer.clone(), entry.clone())
+        };
+        if previous.unwrap_or_default() != entry {
+            self.persist(&registry);
+        }
+    }
+
+    /// Drop the synced slots before `slot`, and the delegations expired in its epoch.
+    fn prune(&self, slot: u64) {
+        let epoch = self.slot_clock.epoch_of(slot);
+
+        let mut registry = self.registry.write();
+        registry.synced = registry.synced.split_off(&slot);
+        let mut pruned = false;
+        registry.validators.retain(|_, entry| {
+            let before = entry.delegations.len();
+            entry.delegations.retain(|delegation| !delegation.message.is_expired(epoch));
+            pruned |= entry.delegations.len() != before;
+            !entry.delegations.is_empty()
+        });
+        if pruned {
+            self.persist(&registry);
+        }
+    }
+
+    fn persist(&self, registry: &Registry) {
+        let Some(path) = &self.path else { return };
+        let messages = registry.validators.values().flat_map(Entry::messages).collect::<Vec<_>>();
+        if let Err(err) = write_messages(path, &messages) {
+            tracing::error!(?err, ?path, "Failed to persist the delegation registry");
+        }
+    }
+}
+
+/// Replace the registry file atomically, so that a crash never leaves a partial one.
+fn write_messages(path: &Path, messages: &[SignedMessage]) -> io::Result<()> {
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&tmp, serde_json::to_vec(messages)?)?;
+    std::fs::rename(tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use blst::min_pk::SecretKey;
+    use ethereum_consensus::{
+        crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature},
+        deneb::compute_signing_root,
+    };
+
+    use super::{DelegationRegistry, RegistryStatus};
+    use crate::{
+        delegation::{
+            signing::{compute_domain_from_mask, BLS_DST_PREFIX},
+            types::{
+                Chain, DelegationMessage, RevocationMessage, SignedDelegation, SignedMessage,
+                SignedRevocation,
+            },
+        },
+        state::slot_clock::SlotClock,
+    };
+
+    fn key(byte: u8) -> (SecretKey, BlsPublicKey) {
+        let sk = SecretKey::key_gen(&[byte; 32], &[]).unwrap();
+        let pk = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        (sk, pk)
+    }
+
+    fn sign(sk: &SecretKey, digest: [u8; 32]) -> BlsSignature {
+        let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+        let root = compute_signing_root(&digest, domain).unwrap();
+        let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+        BlsSignature::try_from(&signature[..]).unwrap()
+    }
+
+    #[test]
+    fn test_registry_honors_revocations_and_expiry() {
+        let (sk, validator) = key(1);
+        let (other_sk, other) = key(2);
+        let (ours, revoked, expiring) = (key(3).1, key(4).1, key(5).1);
+        let delegation = |sk: &SecretKey, validator: &BlsPublicKey, delegatee: &BlsPublicKey| {
+            let message = DelegationMessage::new(validator.clone(), delegatee.clone());
+            SignedDelegation { signature: sign(sk, message.digest()), message }
+        };
+        let mut expires = DelegationMessage::new(validator.clone(), expiring.clone());
+        expires.expiry_epoch = Some(10);
+        let expires = SignedDelegation { signature: sign(&sk, expires.digest()), message: expires };
+        let revocation = RevocationMessage::new(validator.clone(), revoked.clone());
+        let revocation =
+            SignedRevocation { signature: sign(&sk, revocation.digest()), message: revocation };
+
+        let path = std::env::temp_dir().join(format!("registry-test-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        // Genesis in 2100, the delegations aren't expired when the registry is opened
+        let clock = SlotClock::new(4_102_444_800, 12, 500);
+        let registry =
+            DelegationRegistry::open(Some(path.clone()), Chain::Holesky, clock.clone()).unwrap();
+
+        let messages = vec![
+            SignedMessage::Delegation(delegation(&sk, &validator, &ours)),
+            SignedMessage::Delegation(delegation(&sk, &validator, &revoked)),
+            SignedMessage::Delegation(expires),
+            SignedMessage::Revocation(revocation),
+            // Delegations of other proposers, or not signed by the validator, are dropped
+            SignedMessage::Delegation(delegation(&other_sk, &other, &ours)),
+            SignedMessage::Delegation(delegation(&other_sk, &validator, &ours)),
+        ];
+        registry.apply(100, &validator, messages);
+        assert!(registry.is_synced(100));
+        assert!(registry.delegations_of(&other).is_empty());
+        let delegatees = |registry: &DelegationRegistry| {
+            let delegations = registry.delegations_of(&validator);
+            delegations.into_iter().map(|d| d.message.delegatee_pubkey).collect::<Vec<_>>()
+        };
+        assert_eq!(delegatees(&registry), vec![ours.clone(), expiring.clone()]);
+
+        let snapshot = registry.snapshot();
+        let statuses = snapshot.validators[0]
+            .delegations
+            .iter()
+            .map(|delegation| (delegation.delegatee.clone(), delegation.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                (ours.clone(), RegistryStatus::Active),
+                (revoked, RegistryStatus::Revoked),
+                (expiring, RegistryStatus::Active),
+            ]
+        );
+
+        let reopened = DelegationRegistry::open(Some(path.clone()), Chain::Holesky, clock).unwrap();
+        assert_eq!(reopened.delegations_of(&validator), registry.delegations_of(&validator));
+
+        registry.prune(11 * 32);
+        assert!(!registry.is_synced(100));
+        assert_eq!(delegatees(&registry), vec![ours]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
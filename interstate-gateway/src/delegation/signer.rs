@@ -0,0 +1,228 @@
+use std::{collections::HashSet, fmt, path::PathBuf, str::FromStr, sync::Arc};
+
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use reqwest::Url;
+use tokio::sync::watch;
+
+use super::{cb_signer::CBSigner, limiter::SigningLimiters};
+#[cfg(feature = "signer-dirk")]
+use super::dirk::{DirkError, DirkSigner};
+#[cfg(feature = "signer-web3")]
+use super::web3signer::Web3Signer;
+use crate::{
+    config::Config,
+    keystores::{BLSSig, KeystoreError, Keystores},
+};
+
+/// Backend holding the delegatee keys the constraints are signed with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignerType {
+    /// Local EIP-2335 keystores.
+    #[default]
+    Keystores,
+    /// Remote Web3Signer, through the commit-boost signer API.
+    Web3Signer,
+    /// Remote Dirk, including distributed accounts.
+    Dirk,
+    /// The commit-boost signer module.
+    CommitBoost,
+}
+
+impl FromStr for SignerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keystores" => Ok(Self::Keystores),
+            "web3signer" => Ok(Self::Web3Signer),
+            "dirk" => Ok(Self::Dirk),
+            "commit-boost" => Ok(Self::CommitBoost),
+            other => Err(format!(
+                "unknown signer `{other}`, expected keystores, web3signer, dirk or commit-boost"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SignerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Keystores => "keystores",
+            Self::Web3Signer => "web3signer",
+            Self::Dirk => "dirk",
+            Self::CommitBoost => "commit-boost",
+        })
+    }
+}
+
+/// Connection to a Dirk instance, authenticated with mutual TLS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirkConfig {
+    /// Instance listing the accounts, and signing with the non-distributed ones.
+    pub url: Url,
+    /// Wallets, or `wallet/account` paths, whose accounts sign the constraints.
+    pub wallets: Vec<String>,
+    pub ca_cert_path: PathBuf,
+    pub client_cert_path: PathBuf,
+    pub client_key_path: PathBuf,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignerError {
+    #[error(transparent)]
+    Keystore(#[from] KeystoreError),
+    #[error("web3signer: {0}")]
+    Web3Signer(String),
+    #[error("commit-boost: {0}")]
+    CommitBoost(String),
+    #[cfg(feature = "signer-dirk")]
+    #[error("dirk: {0}")]
+    Dirk(#[from] DirkError),
+    #[error("{0} signer not configured")]
+    NotConfigured(SignerType),
+    #[error("{0} signer not compiled in")]
+    Disabled(SignerType),
+}
+
+/// Signs the constraints with the delegatee keys, held locally or by a remote signer.
+#[async_trait::async_trait]
+pub trait SignerBackend: Send + Sync {
+    /// The delegatee keys the backend signs with.
+    async fn list_pubkeys(&self) -> Result<HashSet<BlsPublicKey>, SignerError>;
+
+    /// Signs `root` with the key of `pubkey` and the Commit Boost domain.
+    async fn sign_root(&self, root: [u8; 32], pubkey: &BlsPublicKey) -> Result<BLSSig, SignerError>;
+
+    /// The delegatee keys as they change, for the backends reloading them while running.
+    fn key_updates(&self) -> Option<watch::Receiver<HashSet<BlsPublicKey>>> {
+        None
+    }
+}
+
+/// Connect to the signer selected by the config, its requests bounded by the limiter of its
+/// backend in `limiters`.
+pub async fn connect_signer(
+    config: &Config,
+    limiters: &SigningLimiters,
+) -> Result<Arc<dyn SignerBackend>, SignerError> {
+    match config.signer_type {
+        SignerType::Keystores => {
+            let keystores = Keystores::new(
+                &config.keystore_pubkeys_path,
+                &config.keystore_secrets_path,
+                &config.chain,
+            );
+            if config.keystore_reload {
+                return Ok(Arc::new(keystores.watch()?));
+            }
+            Ok(Arc::new(keystores))
+        }
+        #[cfg(feature = "signer-web3")]
+        SignerType::Web3Signer => {
+            let signer = Web3Signer::connect(config.web3signer_url.clone())
+                .await
+                .map_err(|err| SignerError::Web3Signer(err.to_string()))?
+                .with_limiter(limiters.web3signer.clone())
+                .with_headers(&config.outbound_headers);
+            Ok(Arc::new(signer))
+        }
+        #[cfg(feature = "signer-dirk")]
+        SignerType::Dirk => {
+            let dirk = config.dirk.as_ref().ok_or(SignerError::NotConfigured(SignerType::Dirk))?;
+            let limiter = limiters.dirk.clone();
+            let domain = config.chain.commit_boost_domain();
+            let headers = &config.outbound_headers;
+            Ok(Arc::new(DirkSigner::connect(dirk, domain, limiter, headers).await?))
+        }
+        SignerType::CommitBoost => {
+            let signer = CBSigner::new(&config.commit_boost_signer_url, &config.jwt_hex)
+                .with_limiter(limiters.commit_boost.clone())
+                .with_headers(&config.outbound_headers);
+            Ok(Arc::new(signer))
+        }
+        #[allow(unreachable_patterns)]
+        signer_type => Err(SignerError::Disabled(signer_type)),
+    }
+}
+
+/// Parse the hex encoded keys listed by a remote signer.
+fn parse_pubkeys(accounts: &[String]) -> Result<HashSet<BlsPublicKey>, String> {
+    Ok(crate::config::parse_bls_pubkeys(&accounts.join(","))?.into_iter().collect())
+}
+
+/// Parse the signature of a remote signer, sent as a JSON string.
+fn parse_signature(response: &str) -> Result<BLSSig, String> {
+    BLSSig::from_str(response.trim().trim_matches('"')).map_err(|err| err.to_string())
+}
+
+#[async_trait::async_trait]
+impl SignerBackend for Keystores {
+    async fn list_pubkeys(&self) -> Result<HashSet<BlsPublicKey>, SignerError> {
+        Ok(self.get_pubkeys())
+    }
+
+    async fn sign_root(&self, root: [u8; 32], pubkey: &BlsPublicKey) -> Result<BLSSig, SignerError> {
+        Ok(self.sign_commit_boost_root(root, pubkey)?)
+    }
+
+    fn key_updates(&self) -> Option<watch::Receiver<HashSet<BlsPublicKey>>> {
+        Some(self.subscribe())
+    }
+}
+
+#[cfg(feature = "signer-web3")]
+#[async_trait::async_trait]
+impl SignerBackend for Web3Signer {
+    async fn list_pubkeys(&self) -> Result<HashSet<BlsPublicKey>, SignerError> {
+        let web3signer_err = |err: eyre::Report| SignerError::Web3Signer(err.to_string());
+        let accounts = self.w3_list_accounts().await.map_err(web3signer_err)?;
+        parse_pubkeys(&accounts).map_err(SignerError::Web3Signer)
+    }
+
+    async fn sign_root(&self, root: [u8; 32], pubkey: &BlsPublicKey) -> Result<BLSSig, SignerError> {
+        // The commit-boost signer applies the domain itself
+        let signature = self
+            .w3_request_signature(
+                &alloy::hex::encode_prefixed(pubkey.to_vec()),
+                &alloy::hex::encode_prefixed(root),
+            )
+            .await
+            .map_err(|err| SignerError::Web3Signer(err.to_string()))?;
+        parse_signature(&signature).map_err(SignerError::Web3Signer)
+    }
+}
+
+#[cfg(feature = "signer-dirk")]
+#[async_trait::async_trait]
+impl SignerBackend for DirkSigner {
+    async fn list_pubkeys(&self) -> Result<HashSet<BlsPublicKey>, SignerError> {
+        Ok(self.pubkeys().cloned().collect())
+    }
+
+    async fn sign_root(&self, root: [u8; 32], pubkey: &BlsPublicKey) -> Result<BLSSig, SignerError> {
+        Ok(self.sign(root, pubkey).await?)
+    }
+}
+
+#[async_trait::async_trait]
+impl SignerBackend for CBSigner {
+    async fn list_pubkeys(&self) -> Result<HashSet<BlsPublicKey>, SignerError> {
+        let accounts = self
+            .get_list_accounts()
+            .await
+            .map_err(|err| SignerError::CommitBoost(err.to_string()))?;
+        parse_pubkeys(&accounts).map_err(SignerError::CommitBoost)
+    }
+
+    async fn sign_root(&self, root: [u8; 32], pubkey: &BlsPublicKey) -> Result<BLSSig, SignerError> {
+        // The signer module applies the domain itself
+        let signature = self
+            .request_signature(
+                &alloy::hex::encode_prefixed(pubkey.to_vec()),
+                &alloy::hex::encode_prefixed(root),
+            )
+            .await
+            .map_err(|err| SignerError::CommitBoost(err.to_string()))?;
+        parse_signature(&signature).map_err(SignerError::CommitBoost)
+    }
+}
@@ -1,8 +1,14 @@
-use blst::{min_pk::Signature, BLST_ERROR};
+use blst::{
+    blst_scalar,
+    min_pk::{PublicKey, Signature},
+    BLST_ERROR,
+};
 use ethereum_consensus::{crypto::PublicKey as BlsPublicKey, deneb::{compute_fork_data_root, compute_signing_root, Root}};
 use eyre::{eyre, Result};
+use rand::RngCore;
 
-use super::types::Chain;
+use super::types::{Chain, SignedDelegation};
+use crate::constraints::SignedConstraints;
 
 /// The domain mask for the Commit Boost domain.
 pub const COMMIT_BOOST_DOMAIN_MASK: [u8; 4] = [109, 109, 111, 67];
@@ -10,6 +16,9 @@ pub const COMMIT_BOOST_DOMAIN_MASK: [u8; 4] = [109, 109, 111, 67];
 /// The BLS Domain Separator used in Ethereum 2.0.
 pub const BLS_DST_PREFIX: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
 
+/// Bits of the random scalars weighting the signatures of a batch verification.
+const BATCH_RAND_BITS: usize = 64;
+
 /// Compute the commit boost domain from the fork version
 pub fn compute_domain_from_mask(fork_version: [u8; 4]) -> [u8; 32] {
     let mut domain = [0; 32];
@@ -53,3 +62,137 @@ pub fn verify_root(
         Err(eyre!("bls verification failed"))
     }
 }
+
+/// Verify the signatures of `delegations` with the Commit Boost domain, returning whether
+/// each one is valid.
+///
+/// The whole set is checked in a single randomized batch verification, much cheaper than
+/// one pairing check per delegation. Only if the batch fails are the signatures verified one
+/// by one, to pinpoint the invalid ones.
+pub fn verify_delegations(delegations: &[SignedDelegation], chain: &Chain) -> Vec<bool> {
+    let domain = compute_domain_from_mask(chain.fork_version());
+
+    // Delegations whose keys or signatures can't be parsed are invalid, and left out
+    let parsed = delegations
+        .iter()
+        .map(|delegation| {
+            let pk = PublicKey::from_bytes(delegation.message.validator_pubkey.as_ref()).ok()?;
+            let signature = Signature::from_bytes(delegation.signature.as_ref()).ok()?;
+            let root = compute_signing_root(&delegation.message.digest(), domain).ok()?;
+            Some((pk, signature, root))
+        })
+        .collect::<Vec<_>>();
+
+    verify_parsed(&parsed)
+}
+
+/// Verify the signatures of `constraints` over their `digests`, in the message version they
+/// were signed in, with the Commit Boost domain. Returns whether each one is valid.
+///
+/// Batched as [verify_delegations], so that verifying the hundreds of constraints of a block
+/// at the deadline costs about a single pairing check.
+pub fn verify_constraints(
+    constraints: &[SignedConstraints],
+    digests: &[[u8; 32]],
+    chain: &Chain,
+) -> Vec<bool> {
+    let domain = compute_domain_from_mask(chain.fork_version());
+
+    let parsed = constraints
+        .iter()
+        .zip(digests)
+        .map(|(signed, digest)| {
+            let pk = PublicKey::from_bytes(signed.message.pubkey.as_ref()).ok()?;
+            let signature = Signature::from_bytes(signed.signature.as_ref()).ok()?;
+            let root = compute_signing_root(digest, domain).ok()?;
+            Some((pk, signature, root))
+        })
+        .collect::<Vec<_>>();
+
+    verify_parsed(&parsed)
+}
+
+/// Verify the parsed signatures in a single batch, one by one only if the batch fails.
+fn verify_parsed(parsed: &[Option<(PublicKey, Signature, Root)>]) -> Vec<bool> {
+    let batch = parsed.iter().flatten().collect::<Vec<_>>();
+    let batch_valid = !batch.is_empty() && verify_batch(&batch);
+
+    parsed
+        .iter()
+        .map(|parsed| match parsed {
+            None => false,
+            Some(_) if batch_valid => true,
+            Some((pk, signature, root)) => {
+                signature.verify(true, root.as_ref(), BLS_DST_PREFIX, &[], pk, true)
+                    == BLST_ERROR::BLST_SUCCESS
+            }
+        })
+        .collect()
+}
+
+fn verify_batch(batch: &[&(PublicKey, Signature, Root)]) -> bool {
+    let mut rng = rand::thread_rng();
+    let rands = batch
+        .iter()
+        .map(|_| {
+            let mut scalar = blst_scalar::default();
+            rng.fill_bytes(&mut scalar.b[..BATCH_RAND_BITS / 8]);
+            // A zero scalar would drop its signature from the check
+            scalar.b[0] |= 1;
+            scalar
+        })
+        .collect::<Vec<_>>();
+
+    let msgs = batch.iter().map(|(_, _, root)| root.as_ref()).collect::<Vec<&[u8]>>();
+    let pks = batch.iter().map(|(pk, ..)| pk).collect::<Vec<_>>();
+    let signatures = batch.iter().map(|(_, signature, _)| signature).collect::<Vec<_>>();
+
+    Signature::verify_multiple_aggregate_signatures(
+        &msgs,
+        BLS_DST_PREFIX,
+        &pks,
+        true,
+        &signatures,
+        true,
+        &rands,
+        BATCH_RAND_BITS,
+    ) == BLST_ERROR::BLST_SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use blst::min_pk::SecretKey;
+    use ethereum_consensus::{crypto::PublicKey as BlsPublicKey, deneb::compute_signing_root};
+
+    use super::{compute_domain_from_mask, verify_constraints, BLS_DST_PREFIX};
+    use crate::{
+        constraints::{ConstraintsMessage, SignedConstraints},
+        delegation::types::Chain,
+    };
+
+    #[test]
+    fn test_verify_constraints_batch() {
+        let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+        let mut constraints = (1..=8u8)
+            .map(|i| {
+                let sk = SecretKey::key_gen(&[i; 32], &[]).unwrap();
+                let pubkey = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+                let message = ConstraintsMessage { pubkey, slot: i as u64, ..Default::default() };
+                let root = compute_signing_root(&message.digest(), domain).unwrap();
+                let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+                SignedConstraints { message, signature: signature.into() }
+            })
+            .collect::<Vec<_>>();
+        let digests = |constraints: &[SignedConstraints]| {
+            constraints.iter().map(|sc| sc.message.digest()).collect::<Vec<_>>()
+        };
+        let valid = verify_constraints(&constraints, &digests(&constraints), &Chain::Holesky);
+        assert_eq!(valid, [true; 8]);
+
+        // The invalid signature is pinpointed once the batch fails
+        constraints[5].message.top = true;
+        let valid = verify_constraints(&constraints, &digests(&constraints), &Chain::Holesky);
+        assert_eq!(valid.iter().filter(|valid| !**valid).count(), 1);
+        assert!(!valid[5]);
+    }
+}
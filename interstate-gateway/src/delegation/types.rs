@@ -3,9 +3,10 @@ use alloy_v092::{providers::Provider, transports::Transport};
 use clap::ValueEnum;
 use ethereum_consensus::crypto::{PublicKey as BlsPublicKey, Signature as BlsSignature};
 use eyre::Result;
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
 
-use super::signing::verify_commit_boost_root;
+use super::signing::{verify_commit_boost_root, verify_delegations};
 
 /// Supported chains for the CLI
 #[derive(Debug, Clone, Copy, ValueEnum, Hash, PartialEq, Eq)]
@@ -15,6 +16,7 @@ pub enum Chain {
     Holesky,
     Helder,
     Kurtosis,
+    Gnosis,
 }
 
 impl Chain {
@@ -25,6 +27,7 @@ impl Chain {
             Self::Holesky => [1, 1, 112, 0],
             Self::Helder => [16, 0, 0, 0],
             Self::Kurtosis => [16, 0, 0, 56],
+            Self::Gnosis => [0, 0, 0, 100],
         }
     }
 
@@ -35,6 +38,7 @@ impl Chain {
             17000 => Some(Self::Holesky),
             3151908 => Some(Self::Kurtosis),
             7014190335 => Some(Self::Helder),
+            100 => Some(Self::Gnosis),
             _ => None,
         }
     }
@@ -86,6 +90,22 @@ pub enum SignedMessage {
     Revocation(SignedRevocation),
 }
 
+impl<'de> Deserialize<'de> for SignedMessage {
+    /// Delegations and revocations have the same shape, they are told apart by their action.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = Value::deserialize(deserializer)?;
+        match value.pointer("/message/action").and_then(Value::as_u64) {
+            Some(action) if action == SignedMessageAction::Delegation as u64 => {
+                serde_json::from_value(value).map(Self::Delegation).map_err(de::Error::custom)
+            }
+            Some(action) if action == SignedMessageAction::Revocation as u64 => {
+                serde_json::from_value(value).map(Self::Revocation).map_err(de::Error::custom)
+            }
+            action => Err(de::Error::custom(format!("unknown signed message action {action:?}"))),
+        }
+    }
+}
+
 impl SignedMessage {
     /// Verify the signature of a signed message
     pub fn verify_signature(&self, chain: Chain) -> Result<()> {
@@ -122,37 +142,146 @@ pub struct SignedDelegation {
     pub signature: BlsSignature,
 }
 
+/// Merge the delegations returned by the relays, before any signing decision is made on them.
+///
+/// Delegations with an invalid signature and the copies of the same message returned by
+/// several relays are dropped. Distinct messages for the same validator and delegatee are all
+/// kept: each is signed by the validator and valid up to its own expiry, the relays can't tell
+/// which one came last. The signatures are batch verified, see [verify_delegations].
+pub fn merge_delegations(
+    delegations: impl IntoIterator<Item = SignedDelegation>,
+    chain: Chain,
+) -> Vec<SignedDelegation> {
+    let delegations = delegations.into_iter().collect::<Vec<_>>();
+    let valid = verify_delegations(&delegations, &chain);
+
+    let mut merged: Vec<SignedDelegation> = Vec::new();
+    for (delegation, valid) in delegations.into_iter().zip(valid) {
+        if !valid {
+            tracing::warn!(
+                validator = ?delegation.message.validator_pubkey,
+                "Dropping delegation with an invalid signature"
+            );
+            continue;
+        }
+        if !merged.iter().any(|current| current.message == delegation.message) {
+            merged.push(delegation);
+        }
+    }
+
+    merged
+}
+
+/// Keep the revocations with a valid signature, without duplicates.
+pub fn verify_revocations(
+    revocations: impl IntoIterator<Item = SignedRevocation>,
+    chain: Chain,
+) -> Vec<SignedRevocation> {
+    let mut verified: Vec<SignedRevocation> = Vec::new();
+    for revocation in revocations {
+        if verified.iter().any(|current| current.message == revocation.message) {
+            continue;
+        }
+        match SignedMessage::Revocation(revocation.clone()).verify_signature(chain) {
+            Ok(()) => verified.push(revocation),
+            Err(err) => tracing::warn!(?err, "Ignoring a revocation with an invalid signature"),
+        }
+    }
+    verified
+}
+
+/// Merge the delegations and revocations returned by the relays, see [merge_delegations].
+///
+/// A delegation is dropped when any relay returned a verified revocation of it: the relays
+/// which still return the delegation are behind.
+pub fn merge_messages(
+    messages: impl IntoIterator<Item = SignedMessage>,
+    chain: Chain,
+) -> Vec<SignedDelegation> {
+    let (mut delegations, mut revocations) = (Vec::new(), Vec::new());
+    for message in messages {
+        match message {
+            SignedMessage::Delegation(delegation) => delegations.push(delegation),
+            SignedMessage::Revocation(revocation) => revocations.push(revocation),
+        }
+    }
+
+    let revocations = verify_revocations(revocations, chain);
+    merge_delegations(delegations, chain)
+        .into_iter()
+        .filter(|delegation| !revocations.iter().any(|revocation| revocation.revokes(delegation)))
+        .collect()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct DelegationMessage {
     action: u8,
     pub validator_pubkey: BlsPublicKey,
     pub delegatee_pubkey: BlsPublicKey,
+    /// Last epoch in which the delegation is valid. Delegations without expiry never expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_epoch: Option<u64>,
 }
 
 impl DelegationMessage {
     /// Create a new delegation message.
     pub fn new(validator_pubkey: BlsPublicKey, delegatee_pubkey: BlsPublicKey) -> Self {
-        Self { action: SignedMessageAction::Delegation as u8, validator_pubkey, delegatee_pubkey }
+        Self {
+            action: SignedMessageAction::Delegation as u8,
+            validator_pubkey,
+            delegatee_pubkey,
+            expiry_epoch: None,
+        }
+    }
+
+    /// Bound the delegation to the epochs up to and including `expiry_epoch`.
+    pub fn with_expiry(mut self, expiry_epoch: u64) -> Self {
+        self.expiry_epoch = Some(expiry_epoch);
+        self
+    }
+
+    /// Whether the delegation is no longer valid in `epoch`.
+    pub fn is_expired(&self, epoch: u64) -> bool {
+        matches!(self.expiry_epoch, Some(expiry) if epoch > expiry)
+    }
+
+    /// Whether the delegation expires within `epochs` epochs after `epoch`.
+    pub fn expires_within(&self, epoch: u64, epochs: u64) -> bool {
+        matches!(self.expiry_epoch, Some(expiry) if expiry <= epoch.saturating_add(epochs))
     }
 
     /// Compute the digest of the delegation message.
+    ///
+    /// The expiry is only hashed when set, so that the digest of delegations without expiry
+    /// is unchanged.
     pub fn digest(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
         hasher.update([self.action]);
         hasher.update(self.validator_pubkey.to_vec());
         hasher.update(self.delegatee_pubkey.to_vec());
+        if let Some(expiry_epoch) = self.expiry_epoch {
+            hasher.update(expiry_epoch.to_le_bytes());
+        }
 
         hasher.finalize().into()
     }
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SignedRevocation {
     pub message: RevocationMessage,
     pub signature: BlsSignature,
 }
 
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+impl SignedRevocation {
+    /// Whether the revocation is of `delegation`, from the same validator to the same delegatee.
+    pub fn revokes(&self, delegation: &SignedDelegation) -> bool {
+        self.message.validator_pubkey == delegation.message.validator_pubkey
+            && self.message.delegatee_pubkey == delegation.message.delegatee_pubkey
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RevocationMessage {
     action: u8,
     pub validator_pubkey: BlsPublicKey,
@@ -175,3 +304,109 @@ impl RevocationMessage {
         hasher.finalize().into()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::hex;
+    use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+
+    use blst::min_pk::SecretKey;
+    use ethereum_consensus::{crypto::Signature as BlsSignature, deneb::compute_signing_root};
+
+    use super::{
+        merge_delegations, merge_messages, Chain, DelegationMessage, RevocationMessage,
+        SignedDelegation, SignedMessage, SignedRevocation,
+    };
+    use crate::delegation::signing::{
+        compute_domain_from_mask, verify_delegations, BLS_DST_PREFIX,
+    };
+
+    fn pubkey() -> BlsPublicKey {
+        let bytes = hex::decode("97f1d3a73197d7942695638c4fa9ac0fc3688c4f9774b905a14e3a3f171bac586c55e83ff97a1aeffb3af00adb22c6bb").unwrap();
+        BlsPublicKey::try_from(bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn test_delegation_expiry() {
+        let message = DelegationMessage::new(pubkey(), pubkey());
+        assert!(!message.is_expired(u64::MAX));
+        assert!(!message.expires_within(0, u64::MAX));
+
+        let bounded = message.clone().with_expiry(100);
+        assert!(!bounded.is_expired(100));
+        assert!(bounded.is_expired(101));
+        assert!(bounded.expires_within(90, 10));
+        assert!(!bounded.expires_within(89, 10));
+
+        // The expiry is signed over, and absent from the json of unbounded delegations
+        assert_ne!(bounded.digest(), message.digest());
+        assert!(!serde_json::to_string(&message).unwrap().contains("expiry_epoch"));
+        let json = serde_json::to_string(&bounded).unwrap();
+        assert_eq!(serde_json::from_str::<DelegationMessage>(&json).unwrap(), bounded);
+    }
+
+    fn signed(sk: &SecretKey, message: DelegationMessage) -> SignedDelegation {
+        let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+        let root = compute_signing_root(&message.digest(), domain).unwrap();
+        let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+        SignedDelegation { message, signature: BlsSignature::try_from(&signature[..]).unwrap() }
+    }
+
+    #[test]
+    fn test_merge_delegations() {
+        let sk = SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let validator = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        let message = DelegationMessage::new(validator.clone(), pubkey());
+
+        let (bounded, renewed) =
+            (signed(&sk, message.clone().with_expiry(10)), signed(&sk, message.with_expiry(20)));
+        let mut forged = renewed.clone();
+        forged.message.expiry_epoch = Some(40);
+
+        // Each relay returns its own copy, the valid messages are kept once each
+        let merged = merge_delegations(
+            [bounded.clone(), renewed.clone(), renewed.clone(), forged],
+            Chain::Holesky,
+        );
+        assert_eq!(merged, vec![bounded.clone(), renewed.clone()]);
+
+        // A revocation returned by any relay drops the delegations it revokes
+        let other = signed(&sk, DelegationMessage::new(validator.clone(), validator.clone()));
+        let revocation = RevocationMessage::new(validator, pubkey());
+        let domain = compute_domain_from_mask(Chain::Holesky.fork_version());
+        let root = compute_signing_root(&revocation.digest(), domain).unwrap();
+        let signature = sk.sign(root.as_ref(), BLS_DST_PREFIX, &[]).to_bytes();
+        let revocation = SignedRevocation {
+            message: revocation,
+            signature: BlsSignature::try_from(&signature[..]).unwrap(),
+        };
+        let merged = merge_messages(
+            [
+                SignedMessage::Delegation(bounded),
+                SignedMessage::Delegation(other.clone()),
+                SignedMessage::Revocation(revocation),
+                SignedMessage::Delegation(renewed),
+            ],
+            Chain::Holesky,
+        );
+        assert_eq!(merged, vec![other]);
+    }
+
+    #[test]
+    fn test_verify_delegations_batch() {
+        let mut delegations = (1..=8u8)
+            .map(|i| {
+                let sk = SecretKey::key_gen(&[i; 32], &[]).unwrap();
+                let validator = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+                signed(&sk, DelegationMessage::new(validator, pubkey()))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(verify_delegations(&delegations, &Chain::Holesky), vec![true; 8]);
+
+        // A single invalid signature fails the batch, and is pinpointed individually
+        delegations[3].message = delegations[3].message.clone().with_expiry(10);
+        let valid = verify_delegations(&delegations, &Chain::Holesky);
+        assert_eq!(valid.iter().filter(|valid| !**valid).count(), 1);
+        assert!(!valid[3]);
+    }
+}
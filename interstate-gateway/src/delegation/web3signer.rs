@@ -10,6 +10,9 @@ use serde::{Deserialize, Serialize};
 use std::{fs, process::{Child, Command}, time::Duration};
 use tracing::debug;
 
+use super::limiter::SigningLimiter;
+use crate::utils::http::OutboundHeaders;
+
 /// Web3Signer remote server.
 ///
 ///  Functionality:
@@ -21,6 +24,7 @@ use tracing::debug;
 pub struct Web3Signer {
     base_url: Url,
     client: reqwest::Client,
+    limiter: Option<SigningLimiter>,
 }
 
 impl Web3Signer {
@@ -29,13 +33,26 @@ impl Web3Signer {
         let base_url = addr.parse()?;
         // let (cert, identity) = compose_credentials(credentials)?;
         
-        let client = reqwest::Client::builder()
+        let client = OutboundHeaders::default()
+            .client_builder()
             // .add_root_certificate(cert)
             // .identity(identity)
             // .use_rustls_tls()
             .build()?;
 
-        Ok(Self { base_url, client })
+        Ok(Self { base_url, client, limiter: None })
+    }
+
+    /// Bound the number of concurrent signing requests sent to the Web3Signer.
+    pub fn with_limiter(mut self, limiter: SigningLimiter) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Identify the gateway to the Web3Signer with `headers`.
+    pub fn with_headers(mut self, headers: &OutboundHeaders) -> Self {
+        self.client = headers.client();
+        self
     }
 
     /// List the consensus accounts of the keystore.
@@ -70,6 +87,11 @@ impl Web3Signer {
     ///
     /// Reference: https://commit-boost.github.io/commit-boost-client/api/
     pub async fn w3_request_signature(&self, pub_key: &str, object_root: &str) -> Result<String> {
+        let _permit = match &self.limiter {
+            Some(limiter) => Some(limiter.acquire().await?),
+            None => None,
+        };
+
         let path = self.base_url.join("/signer/v1/request_signature")?;
         let body = CommitBoostSignatureRequest {
             type_: "consensus".to_string(),
@@ -163,11 +185,13 @@ pub struct Web3SignerTlsCredentials {
     pub combined_pem_path: String,
 }
 
-/// Generate signed delegations/recovations using a remote Web3Signer.
+/// Generate signed delegations/recovations using a remote Web3Signer. Delegations are
+/// bounded to `expiry_epoch` when given.
 pub async fn generate_from_web3signer(
     opts: Web3SignerOpts,
     delegatee_pubkey: BlsPublicKey,
     action: Action,
+    expiry_epoch: Option<u64>,
 ) -> Result<Vec<SignedMessage>> {
     // Connect to web3signer.
     let mut web3signer = Web3Signer::connect(opts.url).await?;
@@ -186,7 +210,8 @@ pub async fn generate_from_web3signer(
 
         match action {
             Action::Delegate => {
-                let message = DelegationMessage::new(pubkey.clone(), delegatee_pubkey.clone());
+                let mut message = DelegationMessage::new(pubkey.clone(), delegatee_pubkey.clone());
+                message.expiry_epoch = expiry_epoch;
                 // Web3Signer expects the pre-pended 0x.
                 let signing_root = format!("0x{}", &hex::encode(message.digest()));
                 let returned_signature = web3signer
@@ -4,15 +4,82 @@ use axum::{
 };
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize, Serializer};
+use utoipa::ToSchema;
 
 /// A response object for errors.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ErrorResponse {
     #[serde(serialize_with = "serialize_status_code")]
     code: u16,
     message: String,
 }
 
+impl ErrorResponse {
+    /// Read the error of a failed relay response, keeping its raw body as the message if it
+    /// isn't a JSON error.
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let code = response.status().as_u16();
+        let body = response.text().await.unwrap_or_default();
+        serde_json::from_str(&body).unwrap_or(Self { code, message: body })
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn kind(&self) -> RelayErrorKind {
+        RelayErrorKind::classify(self.code, &self.message)
+    }
+}
+
+/// Why the relay rejected a request, classified from the status and the message of its error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelayErrorKind {
+    /// The validator of the proposer isn't registered on the relay.
+    UnknownValidator,
+    /// The proposer didn't delegate to the signer of the constraints.
+    NoDelegation,
+    /// The constraints conflict with the ones the relay already holds for the slot.
+    ConstraintsConflict,
+    /// The request reached the relay after the deadline of its slot.
+    TooLate,
+    Unauthorized,
+    RateLimited,
+    Other,
+}
+
+impl RelayErrorKind {
+    pub fn classify(code: u16, message: &str) -> Self {
+        let message = message.to_lowercase();
+        let mentions = |patterns: &[&str]| patterns.iter().any(|p| message.contains(p));
+
+        match code {
+            401 | 403 => Self::Unauthorized,
+            429 => Self::RateLimited,
+            _ if mentions(&["unknown validator", "validator not registered", "unknown proposer"]) => {
+                Self::UnknownValidator
+            }
+            _ if mentions(&["delegat"]) => Self::NoDelegation,
+            _ if mentions(&["too late", "deadline", "slot has passed", "past slot"]) => Self::TooLate,
+            _ if code == 409 || mentions(&["conflict", "duplicate"]) => Self::ConstraintsConflict,
+            _ => Self::Other,
+        }
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnknownValidator => "unknown_validator",
+            Self::NoDelegation => "no_delegation",
+            Self::ConstraintsConflict => "constraints_conflict",
+            Self::TooLate => "too_late",
+            Self::Unauthorized => "unauthorized",
+            Self::RateLimited => "rate_limited",
+            Self::Other => "other",
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
 #[non_exhaustive]
@@ -25,6 +92,10 @@ pub enum CommitBoostError {
     FailedGettingPayload(ErrorResponse),
     #[error("Failed submitting constraints: {0:?}")]
     FailedSubmittingConstraints(ErrorResponse),
+    #[error("Failed getting constraints: {0:?}")]
+    FailedGettingConstraints(ErrorResponse),
+    #[error("Failed submitting receipts: {0:?}")]
+    FailedSubmittingReceipts(ErrorResponse),
     #[error("Failed to fetch local payload for slot {0}")]
     FailedToFetchLocalPayload(u64),
     #[error("Failed to send delegating request {0:?}")]
@@ -47,6 +118,30 @@ pub enum CommitBoostError {
     LocalPayloadIntegrity(#[from] super::constraints::LocalPayloadIntegrityError),
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
+    #[error("Invalid relay url: {0}")]
+    Url(#[from] crate::utils::url::UrlError),
+    #[error(transparent)]
+    CircuitOpen(#[from] crate::utils::breaker::BreakerOpen),
+    #[error("{accepted} relays accepted the constraints, {quorum} required: {source}")]
+    QuorumNotReached { accepted: usize, quorum: usize, source: Box<CommitBoostError> },
+}
+
+impl CommitBoostError {
+    /// The error returned by the relay, if it rejected the request.
+    pub fn relay_error(&self) -> Option<&ErrorResponse> {
+        match self {
+            Self::FailedRegisteringValidators(error)
+            | Self::FailedGettingHeader(error)
+            | Self::FailedGettingPayload(error)
+            | Self::FailedSubmittingConstraints(error)
+            | Self::FailedGettingConstraints(error)
+            | Self::FailedSubmittingReceipts(error)
+            | Self::FailedDelegating(error)
+            | Self::FailedRevoking(error) => Some(error),
+            Self::QuorumNotReached { source, .. } => source.relay_error(),
+            _ => None,
+        }
+    }
 }
 
 impl IntoResponse for CommitBoostError {
@@ -64,6 +159,12 @@ impl IntoResponse for CommitBoostError {
             CommitBoostError::FailedSubmittingConstraints(error) => {
                 (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
             }
+            CommitBoostError::FailedGettingConstraints(error) => {
+                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
+            }
+            CommitBoostError::FailedSubmittingReceipts(error) => {
+                (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
+            }
             CommitBoostError::FailedDelegating(error) => {
                 (StatusCode::from_u16(error.code).unwrap(), Json(error)).into_response()
             }
@@ -103,6 +204,15 @@ impl IntoResponse for CommitBoostError {
             CommitBoostError::Unauthorized(_) => {
                 (StatusCode::UNAUTHORIZED).into_response()
             },
+            CommitBoostError::Url(err) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+            }
+            CommitBoostError::CircuitOpen(err) => {
+                (StatusCode::SERVICE_UNAVAILABLE, err.to_string()).into_response()
+            }
+            err @ CommitBoostError::QuorumNotReached { .. } => {
+                (StatusCode::BAD_GATEWAY, err.to_string()).into_response()
+            }
         }
     }
 }
@@ -113,3 +223,21 @@ where
 {
     serializer.serialize_str(&value.to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorResponse, RelayErrorKind};
+
+    #[test]
+    fn test_classify_relay_errors() {
+        let kind = |code, message: &str| {
+            ErrorResponse { code, message: message.to_string() }.kind()
+        };
+        assert_eq!(kind(400, "Unknown validator 0xab"), RelayErrorKind::UnknownValidator);
+        assert_eq!(kind(400, "no delegation found for proposer"), RelayErrorKind::NoDelegation);
+        assert_eq!(kind(400, "constraints submitted too late"), RelayErrorKind::TooLate);
+        assert_eq!(kind(409, "already exists"), RelayErrorKind::ConstraintsConflict);
+        assert_eq!(kind(429, "slow down"), RelayErrorKind::RateLimited);
+        assert_eq!(kind(500, "internal error"), RelayErrorKind::Other);
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::Router;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::{TcpListener, TcpSocket},
+    sync::watch,
+};
+
+use crate::utils::now_ms;
+
+/// Interval at which a running instance checks whether it was replaced.
+const LEASE_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Time a new instance waits after claiming the lease before signing, so that the instance it
+/// replaces has seen the claim and stopped signing.
+const HANDOVER_DELAY: Duration = Duration::from_millis(1_500);
+
+const LISTEN_BACKLOG: u32 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeaseState {
+    /// Waiting for the previous instance to step down.
+    Standby,
+    /// The only instance allowed to sign.
+    Leader,
+    /// Replaced by a newer instance.
+    Lost,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LeaseRecord {
+    instance_id: String,
+    acquired_at_ms: u64,
+}
+
+/// Leader lease shared by the instances of a sidecar during a deploy.
+///
+/// The newest instance always wins: it claims the lease file on startup, and the instance it
+/// replaces steps down as soon as it sees someone else's claim. The new instance only signs
+/// after [HANDOVER_DELAY], so that overlapping instances never both sign commitments or
+/// submit constraints for the same slot.
+#[derive(Debug, Clone)]
+pub struct InstanceLease {
+    state: Arc<watch::Sender<LeaseState>>,
+}
+
+impl InstanceLease {
+    /// A lease for a single instance deployment, always the leader.
+    pub fn standalone() -> Self {
+        Self { state: Arc::new(watch::Sender::new(LeaseState::Leader)) }
+    }
+
+    /// Claim the lease at `path`, taking over from the instance currently holding it.
+    pub fn acquire(path: PathBuf) -> io::Result<Self> {
+        let instance_id = format!("{}-{}", std::process::id(), now_ms());
+        let record = LeaseRecord { instance_id: instance_id.clone(), acquired_at_ms: now_ms() };
+        write_record(&path, &record)?;
+        tracing::info!(%instance_id, path = %path.display(), "Claimed the instance lease");
+
+        let lease = Self { state: Arc::new(watch::Sender::new(LeaseState::Standby)) };
+        let state = lease.state.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(HANDOVER_DELAY).await;
+
+            let mut ticker = tokio::time::interval(LEASE_POLL_INTERVAL);
+            loop {
+                ticker.tick().await;
+
+                match read_record(&path) {
+                    Ok(record) if record.instance_id != instance_id => {
+                        tracing::warn!(
+                            successor = %record.instance_id,
+                            "Replaced by a newer instance, stepping down"
+                        );
+                        state.send_replace(LeaseState::Lost);
+                        return;
+                    }
+                    Ok(_) => {
+                        if state.send_replace(LeaseState::Leader) == LeaseState::Standby {
+                            tracing::info!(%instance_id, "Took over as the signing instance");
+                        }
+                    }
+                    // Keep the current state, the file may be in the middle of a replacement
+                    Err(err) => tracing::error!(?err, "Failed to read the instance lease"),
+                }
+            }
+        });
+
+        Ok(lease)
+    }
+
+    pub fn state(&self) -> LeaseState {
+        *self.state.borrow()
+    }
+
+    /// Whether this instance is allowed to sign.
+    pub fn is_leader(&self) -> bool {
+        self.state() == LeaseState::Leader
+    }
+
+    /// Resolves once this instance took over as the leader, or was replaced before that.
+    /// Returns whether it's the leader.
+    pub async fn leading(&self) -> bool {
+        let mut state = self.state.subscribe();
+        match state.wait_for(|state| *state != LeaseState::Standby).await {
+            Ok(state) => *state == LeaseState::Leader,
+            Err(_) => false,
+        }
+    }
+
+    /// Resolves once this instance was replaced by a newer one.
+    pub async fn lost(&self) {
+        let mut state = self.state.subscribe();
+        if state.wait_for(|state| *state == LeaseState::Lost).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+/// Bind a TCP listener on `addr`. With `reuse_port`, the listener is bound with
+/// `SO_REUSEPORT`, so that a new instance can listen on the same port before the instance it
+/// replaces exits.
+pub fn bind_listener(addr: SocketAddr, reuse_port: bool) -> io::Result<TcpListener> {
+    let socket = if addr.is_ipv4() { TcpSocket::new_v4()? } else { TcpSocket::new_v6()? };
+    socket.set_reuseaddr(true)?;
+    #[cfg(unix)]
+    socket.set_reuseport(reuse_port)?;
+    #[cfg(not(unix))]
+    let _ = reuse_port;
+
+    socket.bind(addr)?;
+    socket.listen(LISTEN_BACKLOG)
+}
+
+/// Serve `router` on `addr` in a task, as the `name` server of the instance holding `lease`.
+///
+/// The listener is only bound once this instance leads, and closed once it is replaced, so that
+/// the kernel doesn't hand the connections shared with `SO_REUSEPORT` to an instance which
+/// would refuse them. With `standby_serves`, it is bound right away, for servers whose requests
+/// a standby instance hands over to the leader.
+pub fn spawn_server(
+    name: &'static str,
+    router: Router,
+    addr: SocketAddr,
+    lease: InstanceLease,
+    reuse_port: bool,
+    standby_serves: bool,
+) {
+    tokio::spawn(async move {
+        if !standby_serves && !lease.leading().await {
+            return;
+        }
+
+        let listener = match bind_listener(addr, reuse_port) {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::error!(?err, %addr, "Failed to bind the {name} server");
+                std::process::exit(1);
+            }
+        };
+        tracing::info!("{name} server is listening on .. {addr}");
+
+        let replaced = async move {
+            if standby_serves {
+                std::future::pending::<()>().await;
+            }
+            lease.lost().await
+        };
+        let service = router.into_make_service_with_connect_info::<SocketAddr>();
+        if let Err(err) = axum::serve(listener, service).with_graceful_shutdown(replaced).await {
+            tracing::error!(?err, "The {name} server failed");
+        }
+    });
+}
+
+/// Replace the lease file atomically, so that readers never see a partial record.
+fn write_record(path: &Path, record: &LeaseRecord) -> io::Result<()> {
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&tmp, serde_json::to_vec(record)?)?;
+    std::fs::rename(tmp, path)
+}
+
+fn read_record(path: &Path) -> io::Result<LeaseRecord> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{InstanceLease, LeaseState, HANDOVER_DELAY, LEASE_POLL_INTERVAL};
+
+    #[tokio::test]
+    async fn test_newer_instance_takes_over() {
+        let path = std::env::temp_dir().join(format!("lease-test-{}.json", std::process::id()));
+
+        let old = InstanceLease::acquire(path.clone()).unwrap();
+        assert_eq!(old.state(), LeaseState::Standby);
+        tokio::time::sleep(HANDOVER_DELAY + LEASE_POLL_INTERVAL).await;
+        assert!(old.is_leader());
+
+        // Both instances never sign at the same time
+        let new = InstanceLease::acquire(path.clone()).unwrap();
+        assert!(!new.is_leader());
+        tokio::time::timeout(Duration::from_secs(2), old.lost()).await.unwrap();
+        assert!(!new.is_leader());
+
+        tokio::time::sleep(HANDOVER_DELAY).await;
+        assert!(new.is_leader());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,19 +1,27 @@
 use alloy::{hex, primitives::FixedBytes};
+use arc_swap::ArcSwap;
 use ethereum_consensus::crypto::PublicKey as ECBlsPublicKey;
 use lighthouse_bls::Keypair;
 use lighthouse_eth2_keystore::Keystore;
+use parking_lot::Mutex;
 use ssz::Encode;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     ffi::OsString,
     fmt::Debug,
     fs::{self, DirEntry, ReadDir},
     io,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
 };
+use tokio::sync::watch;
 
 use crate::config::ChainConfig;
 use crate::constraints::signature::compute_signing_root;
+use crate::metrics::ApiMetrics;
+
+pub mod watcher;
 
 #[derive(Debug, thiserror::Error)]
 #[allow(missing_docs)]
@@ -30,64 +38,127 @@ pub enum KeystoreError {
     UnknownPublicKey(String),
     #[error("invalid signature key length -- signature: {0} -- message: {1}")]
     SignatureLength(String, String),
+    #[error("failed to watch the keystore directories: {0}")]
+    Watch(#[from] notify::Error),
+}
+
+/// A keystore decrypted with its password, and when both files were last modified.
+#[derive(Clone)]
+struct LoadedKeystore {
+    keystore_modified: SystemTime,
+    secret_modified: SystemTime,
+    keypair: Keypair,
 }
 
 #[derive(Clone)]
 pub struct Keystores {
-    keypairs: Vec<Keypair>,
+    /// Swapped as a whole when the keystores are reloaded.
+    keypairs: Arc<ArcSwap<Vec<Keypair>>>,
     chain: ChainConfig,
+    pubkeys_root_path: PathBuf,
+    secrets_path: PathBuf,
+    /// Keystores loaded by path, kept as they are on reload while their files are unchanged.
+    loaded: Arc<Mutex<HashMap<PathBuf, LoadedKeystore>>>,
+    updates: Arc<watch::Sender<HashSet<ECBlsPublicKey>>>,
+    /// Reloads the keys on changes to the keystore files, if enabled.
+    watcher: Option<Arc<watcher::KeystoreWatcher>>,
 }
 
 impl Keystores {
     pub fn new(pubkeys_root_path: &Path, secrets_path: &Path, chain: &ChainConfig) -> Self {
-        let mut keystore_paths = Vec::new();
-
         tracing::debug!(?pubkeys_root_path, ?secrets_path, "path");
 
-        for dir_entry in read_dir(&pubkeys_root_path.to_path_buf()).expect(&format!(
-            "invalid pubkeys root path {:#?}",
-            pubkeys_root_path
-        )) {
-            let path = read_path(dir_entry).expect(&format!("invalid root directory entry"));
-            if path.is_dir() {
-                for dir_entry in
-                    read_dir(&path).expect(&format!("invalid directory path {:#?}", path))
-                {
-                    let path = read_path(dir_entry).expect(&format!("invalid directory entry"));
-                    if path.is_file() && path.extension() == Some(&OsString::from("json")) {
-                        keystore_paths.push(path);
+        let keystores = Self {
+            keypairs: Default::default(),
+            chain: chain.clone(),
+            pubkeys_root_path: pubkeys_root_path.to_path_buf(),
+            secrets_path: secrets_path.to_path_buf(),
+            loaded: Default::default(),
+            updates: Arc::new(watch::Sender::new(HashSet::new())),
+            watcher: None,
+        };
+
+        let keystore_paths = keystore_paths(pubkeys_root_path)
+            .unwrap_or_else(|err| panic!("invalid pubkeys root path {pubkeys_root_path:#?}: {err}"));
+        let loaded = keystore_paths
+            .into_iter()
+            .map(|path| {
+                let keystore = load_keystore(&path, secrets_path, None)
+                    .unwrap_or_else(|err| panic!("invalid keystore {path:#?}: {err}"));
+                (path, keystore)
+            })
+            .collect();
+        keystores.install(&mut keystores.loaded.lock(), loaded);
+
+        tracing::debug!("keypairs from local {}", keystores.keypairs.load().len());
+        keystores
+    }
+
+    /// Load the keystores again, swapping the keys in at once. The keystores that fail to load,
+    /// e.g. while their password is being updated, keep their previous key if they had one.
+    ///
+    /// Returns the number of keys added and removed.
+    pub fn reload(&self) -> Result<(usize, usize), KeystoreError> {
+        let keystore_paths = keystore_paths(&self.pubkeys_root_path)?;
+
+        let mut loaded = self.loaded.lock();
+        let mut next = HashMap::with_capacity(keystore_paths.len());
+        for path in keystore_paths {
+            let previous = loaded.get(&path);
+            match load_keystore(&path, &self.secrets_path, previous) {
+                Ok(keystore) => {
+                    next.insert(path, keystore);
+                }
+                Err(err) => {
+                    tracing::warn!(%err, ?path, "Failed to reload a keystore");
+                    if let Some(previous) = previous {
+                        next.insert(path, previous.clone());
                     }
                 }
             }
         }
 
-        let mut keypairs = Vec::with_capacity(keystore_paths.len());
-
-        for path in keystore_paths {
-            let keystore = Keystore::from_json_file(path.clone())
-                .expect(&format!("invalid public key path {:#?}", path));
+        let before = self.get_pubkeys();
+        self.install(&mut loaded, next);
+        let after = self.get_pubkeys();
 
-            let pubkey = format!("0x{}", keystore.pubkey());
-
-            let mut secret_path = secrets_path.to_path_buf();
-            secret_path.push(pubkey);
+        let added = after.difference(&before).collect::<Vec<_>>();
+        let removed = before.difference(&after).collect::<Vec<_>>();
+        for pubkey in &added {
+            tracing::info!(%pubkey, "Loaded a key added to the keystores");
+        }
+        for pubkey in &removed {
+            tracing::info!(%pubkey, "Unloaded a key removed from the keystores");
+        }
+        let changed = !added.is_empty() || !removed.is_empty();
+        ApiMetrics::increment_keystore_reloads_count(if changed { "changed" } else { "unchanged" });
+        if changed {
+            tracing::info!(keys = after.len(), added = added.len(), removed = removed.len(), "Reloaded the keystores");
+            self.updates.send_replace(after);
+        }
 
-            let password = fs::read_to_string(secret_path.clone())
-                .expect(&format!("invalid secret path {:#?}", secret_path));
+        Ok((added.len(), removed.len()))
+    }
 
-            let keypair = keystore.decrypt_keypair(password.as_bytes()).unwrap();
+    /// The key set, updated on every reload that changes it.
+    pub fn subscribe(&self) -> watch::Receiver<HashSet<ECBlsPublicKey>> {
+        self.updates.subscribe()
+    }
 
-            keypairs.push(keypair);
-        }
-        tracing::debug!("keypairs from local {}", keypairs.len());
-        Self {
-            keypairs,
-            chain: chain.clone(),
-        }
+    fn install(
+        &self,
+        loaded: &mut HashMap<PathBuf, LoadedKeystore>,
+        next: HashMap<PathBuf, LoadedKeystore>,
+    ) {
+        let keypairs = next.values().map(|keystore| keystore.keypair.clone()).collect::<Vec<_>>();
+        ApiMetrics::set_keystore_keys(keypairs.len());
+        self.keypairs.store(Arc::new(keypairs));
+        *loaded = next;
     }
 
     pub fn get_pubkeys(&self) -> HashSet<ECBlsPublicKey> {
         self.keypairs
+            .load()
             .iter()
             .map(|kp| {
                 ECBlsPublicKey::try_from(kp.pk.serialize().to_vec().as_ref()).expect("valid pubkey")
@@ -111,8 +182,8 @@ impl Keystores {
         public_key: &ECBlsPublicKey,
         domain: [u8; 32],
     ) -> Result<BLSSig, KeystoreError> {
-        let sk = self
-            .keypairs
+        let keypairs = self.keypairs.load();
+        let sk = keypairs
             .iter()
             // `as_ssz_bytes` returns the raw bytes we need
             .find(|kp| kp.pk.as_ssz_bytes() == public_key.as_ref())
@@ -128,6 +199,55 @@ impl Keystores {
     }
 }
 
+/// The JSON keystore files, one directory down the pubkeys root path.
+fn keystore_paths(pubkeys_root_path: &Path) -> Result<Vec<PathBuf>, KeystoreError> {
+    let mut keystore_paths = Vec::new();
+    for dir_entry in read_dir(&pubkeys_root_path.to_path_buf())? {
+        let path = read_path(dir_entry)?;
+        if path.is_dir() {
+            for dir_entry in read_dir(&path)? {
+                let path = read_path(dir_entry)?;
+                if path.is_file() && path.extension() == Some(&OsString::from("json")) {
+                    keystore_paths.push(path);
+                }
+            }
+        }
+    }
+    Ok(keystore_paths)
+}
+
+/// Decrypt the keystore at `path` with its password, unless both files are unchanged since
+/// the `previous` load.
+fn load_keystore(
+    path: &Path,
+    secrets_path: &Path,
+    previous: Option<&LoadedKeystore>,
+) -> Result<LoadedKeystore, KeystoreError> {
+    let keystore_modified = fs::metadata(path)?.modified()?;
+    let keystore = Keystore::from_json_file(path)
+        .map_err(|err| KeystoreError::ReadFromJSON(path.to_path_buf(), format!("{err:?}")))?;
+
+    let secret_path = secrets_path.join(format!("0x{}", keystore.pubkey()));
+    let secret_error =
+        |err: io::Error| KeystoreError::ReadFromSecretFile(format!("{secret_path:#?}: {err}"));
+    let secret_modified =
+        fs::metadata(&secret_path).and_then(|meta| meta.modified()).map_err(secret_error)?;
+
+    if let Some(previous) = previous.filter(|previous| {
+        previous.keystore_modified == keystore_modified &&
+            previous.secret_modified == secret_modified
+    }) {
+        return Ok(previous.clone());
+    }
+
+    let password = fs::read_to_string(&secret_path).map_err(secret_error)?;
+    let keypair = keystore
+        .decrypt_keypair(password.as_bytes())
+        .map_err(|err| KeystoreError::KeypairDecryption(path.to_path_buf(), format!("{err:?}")))?;
+
+    Ok(LoadedKeystore { keystore_modified, secret_modified, keypair })
+}
+
 fn read_dir(path: &PathBuf) -> Result<ReadDir, std::io::Error> {
     fs::read_dir(path)
 }
@@ -274,10 +394,11 @@ mod tests {
             let keystore_signer_from_directory =
                 Keystores::new(&keys_path, &keystores_secrets_path, &chain_config);
 
-            assert_eq!(keystore_signer_from_directory.keypairs.len(), 1);
+            assert_eq!(keystore_signer_from_directory.keypairs.load().len(), 1);
             assert_eq!(
                 keystore_signer_from_directory
                     .keypairs
+                    .load()
                     .first()
                     .expect("to get keypair")
                     .pk
@@ -285,5 +406,17 @@ mod tests {
                 public_key
             );
         }
+
+        // Reloaded keystores keep their key while their password can't decrypt them
+        let keystores_secrets_path = make_path(KEYSTORES_SECRETS_DEFAULT_PATH_TEST);
+        let keystores =
+            Keystores::new(&make_path(KEYSTORES_DEFAULT_PATH_TEST), &keystores_secrets_path, &chain_config);
+        assert_eq!(keystores.reload().unwrap(), (0, 0));
+
+        let secret_path = keystores_secrets_path.join(public_key);
+        std::fs::write(&secret_path, "not the password").unwrap();
+        assert_eq!(keystores.reload().unwrap(), (0, 0));
+        assert_eq!(keystores.get_pubkeys().len(), 1);
+        std::fs::write(&secret_path, password).unwrap();
     }
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use std::{sync::Arc, time::Duration};
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::{sync::mpsc, task::AbortHandle};
+
+use super::{KeystoreError, Keystores};
+
+/// Time the keystore files are left to settle after a change before they are reloaded, so
+/// that a key rotated with several writes is reloaded once.
+pub const KEYSTORE_RELOAD_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Watches the keystore and secret directories, reloading the keys once they change.
+/// Stops when dropped.
+pub struct KeystoreWatcher {
+    _watcher: RecommendedWatcher,
+    reloads: AbortHandle,
+}
+
+impl Drop for KeystoreWatcher {
+    fn drop(&mut self) {
+        self.reloads.abort();
+    }
+}
+
+impl Keystores {
+    /// Reload the keys whenever keystores or passwords are added, removed or updated.
+    pub fn watch(mut self) -> Result<Self, KeystoreError> {
+        let (changes_tx, mut changes_rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+            match event {
+                Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+                Ok(_) => {
+                    let _ = changes_tx.send(());
+                }
+                Err(err) => tracing::warn!(?err, "Failed to watch the keystores"),
+            }
+        })?;
+        watcher.watch(&self.pubkeys_root_path, RecursiveMode::Recursive)?;
+        watcher.watch(&self.secrets_path, RecursiveMode::NonRecursive)?;
+
+        let keystores = self.clone();
+        let reloads = tokio::spawn(async move {
+            while changes_rx.recv().await.is_some() {
+                tokio::time::sleep(KEYSTORE_RELOAD_DEBOUNCE).await;
+                while changes_rx.try_recv().is_ok() {}
+
+                // Decrypting the keystores takes a while
+                let reloading = keystores.clone();
+                match tokio::task::spawn_blocking(move || reloading.reload()).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(err)) => tracing::error!(%err, "Failed to reload the keystores"),
+                    Err(err) => tracing::error!(?err, "Keystores reload panicked"),
+                }
+            }
+        })
+        .abort_handle();
+
+        tracing::info!(path = ?self.pubkeys_root_path, "Watching the keystores for changes");
+        self.watcher = Some(Arc::new(KeystoreWatcher { _watcher: watcher, reloads }));
+        Ok(self)
+    }
+}
@@ -0,0 +1,22 @@
+use alloy::primitives::FixedBytes;
+
+pub mod builder;
+pub mod cli;
+pub mod commitment;
+pub mod config;
+pub mod constraints;
+pub mod crypto;
+pub mod delegation;
+pub mod errors;
+pub mod handover;
+pub mod keystores;
+pub mod metrics;
+pub mod onchain;
+pub mod shutdown;
+pub mod state;
+#[cfg(test)]
+mod test_utils;
+pub mod utils;
+
+pub type BLSBytes = FixedBytes<96>;
+pub const BLS_DST_PREFIX: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
@@ -1,120 +1,395 @@
-use crate::commitment::request::{PreconfRequest, PreconfResult};
 use alloy::hex::{self, decode};
+use alloy::primitives::{Address, B256, U256};
 use alloy::rpc::types::beacon::{BlsPublicKey, BlsSignature};
-use alloy::{primitives::FixedBytes, rpc::types::beacon::events::HeadEvent};
+use alloy::rpc::types::beacon::events::HeadEvent;
 pub use beacon_api_client::mainnet::Client;
-use commitment::request::{CommitmentRequestError, CommitmentRequestEvent};
-use delegation::cb_signer::{trim_hex_prefix, CBSigner};
-use delegation::types::SignedDelegation;
 use ethereum_consensus::crypto::PublicKey as ECBlsPublicKey;
+use interstate_gateway::commitment::request::{
+    CommitmentRequestError, CommitmentRequestEvent, ConstraintsBatch, PreconfRequest,
+    PreconfResult,
+};
+use interstate_gateway::commitment::rate_limit::RequestRateLimiters;
+use interstate_gateway::commitment::sequencer::RequestSequencer;
+use interstate_gateway::delegation::cb_signer::{trim_hex_prefix, CBSigner};
+use interstate_gateway::delegation::health::{DelegationHealth, DelegationRelay, RelayDelegations};
+use interstate_gateway::delegation::keycheck::{ExpectedKeys, SignerKeyCheck};
+use interstate_gateway::delegation::limiter::SigningLimiters;
+use interstate_gateway::delegation::lookup::{DelegationLookup, LookupSources};
+use interstate_gateway::delegation::registry::DelegationRegistry;
+use interstate_gateway::delegation::signer::{connect_signer, SignerBackend};
+use interstate_gateway::delegation::signing::verify_constraints;
+use interstate_gateway::delegation::types::Chain;
 
-use delegation::web3signer::{Web3Signer, Web3SignerTlsCredentials};
 use ethereum_consensus::crypto::PublicKey;
-use keystores::Keystores;
-use metrics::{run_metrics_server, ApiMetrics};
+use interstate_gateway::handover::InstanceLease;
+use interstate_gateway::shutdown::{ShutdownController, SHUTDOWN_TIMEOUT};
+use interstate_gateway::metrics::{run_metrics_server, runtime::timed, ApiMetrics};
 use serde::{Deserialize, Serialize};
-use state::{execution::ExecutionState, fetcher::ClientState, ConstraintState, HeadEventListener};
+use interstate_gateway::state::{
+    archive::{BeaconResponses, SlotArchive, SlotEvent},
+    audit::AuditTrail,
+    budget::DeadlineBudget,
+    capacity::{CapacityLimits, CapacityView},
+    execution::ExecutionState, execution_client::ExecutionClient, fetcher::ClientState,
+    inclusion::{BlockEvent, BlockEventListener, InclusionStats, InclusionTracker},
+    journal::open_journal,
+    mempool::{MempoolWatcher, ReplacementGuard},
+    reservations::ReservationBook,
+    revenue::RevenueTracker,
+    store::SharedStore,
+    status::{Component, StatusBoard},
+    wal::{PendingSubmission, SubmissionLog},
+    scheduler::{DeadlineEvent, DeadlineScheduler},
+    schema::{configured_stores, migrate_stores, MigrateOptions},
+    shards::SlotShards,
+    slot_clock::SlotClock, sync::{slot_execution_block_hash, ElSyncMonitor}, Block, ConstraintState, HeadEventListener,
+    PartialValidation, StateError,
+};
+use arc_swap::ArcSwap;
+use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
-use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+use tokio::sync::{Mutex, RwLock};
+use reqwest::Url;
 use tracing_subscriber::fmt::Subscriber;
-use utils::send_sidecar_info;
+use interstate_gateway::utils::send_sidecar_info;
+use interstate_gateway::utils::breaker::{BreakerError, CircuitBreaker, CircuitBreakers, Dependency};
 
-use commitment::{run_commitment_rpc_server, PreconfResponse};
-use config::{
+use interstate_gateway::commitment::events::{ApiEvent, EventBroadcaster};
+#[cfg(feature = "collector-client")]
+use interstate_gateway::commitment::gossip::ReceiptGossip;
+use interstate_gateway::commitment::{
+    forward::{PeerForwarder, SharedProposers},
+    receipt::{
+        CommitmentReceipt, ContractDeployment, PreconfReceipt, ReceiptSigner, ReceiptStore,
+        SignedPreconfReceipt,
+    },
+    replica::{run_replica_rpc_server, InstanceRole},
+    run_commitment_rpc_server, PreconfResponse, TxVerdict,
+};
+use interstate_gateway::config::{
     limits::{LimitOptions, DEFAULT_GAS_LIMIT},
+    validation::check_envs,
     Config,
 };
-use constraints::builder::PayloadAndBid;
-use constraints::CommitBoostApi;
-use constraints::{
-    run_constraints_proxy_server, ConstraintsMessage, FallbackBuilder, FallbackPayloadFetcher,
-    FetchPayloadRequest, SignedConstraints, TransactionExt,
+use interstate_gateway::constraints::rate_limit::RelayRateLimiter;
+use interstate_gateway::constraints::{
+    multi_relay::MultiRelayClient, versioned::ConstraintsVersion, CommitBoostApi,
+};
+use interstate_gateway::constraints::{
+    run_constraints_proxy_server, ConstraintsMessage, ConstraintsSubmissionStatus,
+    FallbackBuilder, FallbackPayloadFetcher, FetchPayloadRequest, NoopPayloadFetcher, PayloadAndBid,
+    SignedConstraints, TransactionExt,
 };
-use env_file_reader::read_file;
+use clap::Parser;
+#[cfg(feature = "cli")]
+use interstate_gateway::cli::Command;
+use interstate_gateway::cli::{self, Cli};
 
 use tokio::sync::oneshot::Sender;
-mod builder;
-mod commitment;
-mod config;
-mod constraints;
-mod crypto;
-mod delegation;
-mod errors;
-mod metrics;
-mod onchain;
-mod state;
-mod test_utils;
-mod utils;
-mod keystores;
-
-pub type BLSBytes = FixedBytes<96>;
-pub const BLS_DST_PREFIX: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_";
 
+/// Gas used by a simple transfer, used to quote the minimum priority fee in pricing events.
+const TRANSFER_GAS: u64 = 21_000;
+
+/// Interval at which the execution client sync status is polled.
+const EL_SYNC_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Interval at which the mempool is polled for the inclusion list and the replacements.
+const MEMPOOL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval at which the primary publishes its state to the replicas.
+const STORE_PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Interval at which the delegations of the upcoming proposals of our validators are checked.
+const DELEGATION_HEALTH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval at which the delegations of the upcoming proposers are synced from the relay.
+const DELEGATION_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+#[allow(clippy::too_many_arguments)]
 async fn handle_preconfirmation_request(
-    req: PreconfRequest,
+    mut req: PreconfRequest,
     res: Sender<PreconfResult>,
-    constraint_state: Arc<Mutex<ConstraintState>>,
-    keystores: Keystores,
-    relay_client: reqwest::Client,
-    relay_url:reqwest::Url
+    constraint_state: Arc<RwLock<ConstraintState>>,
+    signer: Arc<dyn SignerBackend>,
+    pubkeys: Arc<HashSet<ECBlsPublicKey>>,
+    delegation_registry: DelegationRegistry,
+    receipt_signer: Option<ReceiptSigner>,
+    breakers: CircuitBreakers,
+    receipts: ReceiptStore,
 ) {
-    let mut constraint_state = constraint_state.lock().await;
-
     tracing::info!("Received preconfirmation request");
     ApiMetrics::increment_received_commitments_count();
 
+    // Refused right away rather than after the timeouts of a dependency known to be failing
+    let dependencies = [Dependency::Execution, Dependency::Relay, Dependency::Signer];
+    if let Some(open) = breakers.failing_fast(&dependencies) {
+        tracing::warn!(%open, "Refusing the request, a dependency fails fast");
+        let _ = res.send(Err(open.into()));
+        return;
+    }
+
+    // The constraints carry the senders recovered from the transactions, whatever the
+    // request went through
+    if let Err(error) = req.recover_signers() {
+        let _ = res.send(Err(CommitmentRequestError::InvalidFields(vec![error])));
+        return;
+    }
+
     let slot = req.slot;
-    let pubkeys = keystores.get_pubkeys();
 
-    match constraint_state.validate_preconf_request(req.clone()).await {
-        Ok(pubkey) => {
+    // Held until the constraints are added, the requests to other slots proceed meanwhile
+    let blocks = timed("constraint_state", "read", constraint_state.read()).await.blocks.clone();
+    let _committing = blocks.lock_slot(slot).await;
+    // Requests of the same senders to other slots are validated once these constraints are
+    // added, against their nonces and spend
+    let senders = req.txs.iter().filter_map(|tx| tx.sender());
+    let _committing_senders = blocks.lock_senders(senders).await;
 
-            let response = relay_client.
-            get(relay_url.join(&format!("/relay/v1/builder/delegations?slot={}", slot).as_str()).expect("invalid delegation url")).send()
-            .await.expect("failed to get delegations");
+    // The verdicts of partial requests are reported for the transactions as requested
+    let tx_hashes: Vec<B256> = req.txs.iter().map(|tx| B256::from_slice(tx.tx().hash().as_slice())).collect();
+    let validated = {
+        let state = timed("constraint_state", "read", constraint_state.read()).await;
+        let validation = if req.partial {
+            state.validate_partial_request(req.clone()).await
+        } else {
+            state.validate_preconf_request(req.clone()).await.map(|pubkey| {
+                let accepted_indexes = (0..req.txs.len()).collect();
+                (pubkey, PartialValidation { accepted: req.clone(), accepted_indexes, rejected: Vec::new() })
+            })
+        };
+        validation.map(|(pubkey, validation)| {
+            let expiry_ms = state.slot_clock.slot_start_ms(slot + 1).max(0) as u64;
+            let epoch = state.config.epoch_of(slot);
+            (pubkey, validation, state.constraints_version, state.status.clone(), expiry_ms, epoch)
+        })
+    };
 
-            let delegations: Vec<SignedDelegation> = response.json().await.expect("failed to deserialize delgations");
+    match validated {
+        Ok((pubkey, validation, constraints_version, status, expiry_ms, epoch)) => {
+            // Only the accepted transactions of a partial request are committed
+            let PartialValidation { accepted: mut req, accepted_indexes, rejected } = validation;
+            let mut rejected: Vec<(usize, &'static str, String)> = rejected
+                .into_iter()
+                .map(|(index, err)| {
+                    ApiMetrics::increment_validation_errors_count(err.code().to_string());
+                    (index, err.code(), err.to_string())
+                })
+                .collect();
+
+            // The delegations of the proposer are usually synced ahead of its slot, the relay
+            // is only queried when they aren't yet
+            if !delegation_registry.is_synced(slot) {
+                match breakers.relay.call(delegation_registry.sync_slot(slot, &pubkey)).await {
+                    Ok(()) => {}
+                    Err(BreakerError::Open(open)) => {
+                        let _ = res.send(Err(open.into()));
+                        return;
+                    }
+                    Err(BreakerError::Failed(err)) => {
+                        tracing::error!(?err, "Failed to fetch the delegations");
+                        let _ = res.send(Err(CommitmentRequestError::Custom(format!("failed to fetch the delegations: {err}"))));
+                        return;
+                    }
+                }
+            }
+            let delegations = delegation_registry.delegations_of(&pubkey);
             let mut signed_contraints_list: Vec<SignedConstraints> = vec![];
+            // Delegatee key the constraints were signed with, signing the inclusion receipt
+            let mut delegatee = None;
+            // Constraints of an atomic request, added once all of them are signed
+            let mut batch = ConstraintsBatch::new(req.atomic);
 
-           
+            'delegations: for delegation in delegations {
+                if delegation.message.is_expired(epoch) {
+                    tracing::warn!(
+                        delegatee = ?delegation.message.delegatee_pubkey,
+                        expiry_epoch = ?delegation.message.expiry_epoch,
+                        epoch,
+                        "Refusing to sign with the delegatee of an expired delegation"
+                    );
+                    ApiMetrics::increment_expired_delegations_count();
+                    continue;
+                }
 
-            for delegation in delegations {
                 if (delegation.message.validator_pubkey == pubkey) && (pubkeys.contains(&delegation.message.delegatee_pubkey)) {
 
-                    for tx in req.clone().txs.iter() {
+                    for (index, tx) in req.clone().txs.iter().enumerate() {
                         let message = ConstraintsMessage::from_tx(delegation.message.delegatee_pubkey.clone(), slot, tx.clone());
-                        let digest = message.digest();
+                        let digest = message.digest_for(constraints_version);
         
-                        let signature = keystores.sign_commit_boost_root(digest, &delegation.message.delegatee_pubkey);
+                        let signature = breakers.signer.call(signer.sign_root(digest, &delegation.message.delegatee_pubkey)).await;
         
                         let signed_constraints = match signature {
-                            Ok(signature) => SignedConstraints { message, signature },
-                            Err(e) => {
+                            Ok(signature) => {
+                                status.record_success(Component::Signer);
+                                SignedConstraints { message, signature }
+                            }
+                            Err(BreakerError::Open(open)) => {
+                                if req.partial {
+                                    // The transactions signed before are committed, the others are
+                                    // refused along with the invalid ones
+                                    for &index in &accepted_indexes[index..] {
+                                        if !rejected.iter().any(|(other, ..)| *other == index) {
+                                            rejected.push((index, "unavailable", open.to_string()));
+                                        }
+                                    }
+                                    break 'delegations;
+                                }
+                                let err = if req.atomic {
+                                    // Nothing was added yet, the whole request is refused
+                                    let tx_hash = B256::from_slice(tx.tx().hash().as_slice());
+                                    CommitmentRequestError::AtomicBatchFailed {
+                                        index,
+                                        tx_hash,
+                                        reason: open.to_string(),
+                                    }
+                                } else {
+                                    open.into()
+                                };
+                                let _ = res.send(Err(err));
+                                return;
+                            }
+                            Err(BreakerError::Failed(e)) => {
                                 tracing::error!(?e, "Failed to sign constraints");
+                                let reason = e.to_string();
+                                status.record_failure(Component::Signer, e);
+                                if req.partial {
+                                    // Only this transaction is rejected, the others are committed
+                                    if !rejected.iter().any(|(rejected, ..)| *rejected == accepted_indexes[index]) {
+                                        rejected.push((accepted_indexes[index], "signing_failed", reason));
+                                    }
+                                    continue;
+                                }
+                                let err = if req.atomic {
+                                    // Nothing was added yet, the whole request is refused
+                                    let tx_hash = B256::from_slice(tx.tx().hash().as_slice());
+                                    CommitmentRequestError::AtomicBatchFailed { index, tx_hash, reason }
+                                } else {
+                                    // The transactions signed before this one are committed
+                                    CommitmentRequestError::Custom(format!(
+                                        "failed to sign transaction {index}, the ones before it are committed: {reason}"
+                                    ))
+                                };
+                                let _ = res.send(Err(err));
                                 return;
                             }
                         };
-        
-                        ApiMetrics::increment_preconfirmed_transactions_count(tx.tx.tx_type());
-        
-                        constraint_state.add_constraint(slot, signed_constraints.clone());
+
+                        if let Some((index, signed_constraints)) = batch.push((index, signed_constraints.clone())) {
+                            ApiMetrics::increment_preconfirmed_transactions_count(req.txs[index].tx().tx_type());
+                            timed("constraint_state", "read", constraint_state.read())
+                                .await
+                                .add_constraint(slot, signed_constraints, req.priority)
+                                .await;
+                        }
                         signed_contraints_list.push(signed_constraints.clone());
                     }
+                    delegatee.get_or_insert(delegation.message.delegatee_pubkey.clone());
                    
                 } else{}
             }
 
+            // Transactions whose signing failed aren't covered by the receipts
+            if req.partial {
+                let failed: HashSet<usize> = rejected.iter().map(|(index, ..)| *index).collect();
+                req.txs = std::mem::take(&mut req.txs)
+                    .into_iter()
+                    .zip(&accepted_indexes)
+                    .filter(|(_, index)| !failed.contains(index))
+                    .map(|(tx, _)| tx)
+                    .collect();
+            }
+
+            let pending_constraints = batch.complete();
+            if !pending_constraints.is_empty() {
+                let state = timed("constraint_state", "read", constraint_state.read()).await;
+                for (index, signed_constraints) in pending_constraints {
+                    ApiMetrics::increment_preconfirmed_transactions_count(req.txs[index].tx().tx_type());
+                    state.add_constraint(slot, signed_constraints, req.priority).await;
+                }
+            }
+
+            let receipt = match &receipt_signer {
+                Some(signer) if !signed_contraints_list.is_empty() => {
+                    match signer.sign(CommitmentReceipt::new(slot, req.digest())).await {
+                        Ok(receipt) => Some(receipt),
+                        Err(err) => {
+                            tracing::error!(?err, "Failed to sign the commitment receipt");
+                            None
+                        }
+                    }
+                }
+                _ => None,
+            };
+
+            let inclusion_receipt = match delegatee {
+                Some(delegatee) => {
+                    let message = PreconfReceipt {
+                        slot,
+                        tx_hashes: req.txs.iter().map(|tx| B256::from_slice(tx.tx().hash().as_slice())).collect(),
+                        validator_pubkey: pubkey.clone(),
+                        expiry_ms,
+                        deployments: req
+                            .txs
+                            .iter()
+                            .filter_map(|tx| {
+                                let address = tx.created_address()?;
+                                Some(ContractDeployment { tx_hash: B256::from_slice(tx.tx().hash().as_slice()), address })
+                            })
+                            .collect(),
+                    };
+                    // The constraints are already added, a missing receipt doesn't fail the request
+                    match breakers.signer.call(signer.sign_root(message.digest(), &delegatee)).await {
+                        Ok(signature) => {
+                            let receipt = SignedPreconfReceipt { message, signer: delegatee, signature };
+                            receipts.insert(receipt.clone());
+                            Some(receipt)
+                        }
+                        Err(err) => {
+                            tracing::error!(?err, "Failed to sign the inclusion receipt");
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+
+            let verdicts = req.partial.then(|| {
+                let mut verdicts: Vec<TxVerdict> =
+                    tx_hashes.iter().map(|tx_hash| TxVerdict::Accepted { tx_hash: *tx_hash }).collect();
+                for (index, code, reason) in rejected {
+                    verdicts[index] = TxVerdict::Rejected { tx_hash: tx_hashes[index], code: code.to_string(), reason };
+                }
+                verdicts
+            });
+
+            if !signed_contraints_list.is_empty() {
+                ApiMetrics::increment_priority_tier_requests_count(req.priority.as_str(), "committed");
+            }
             let response = serde_json::to_value(PreconfResponse {
                 ok: true,
                 signed_contraints_list,
+                receipt,
+                inclusion_receipt,
+                verdicts,
+                priority: req.priority,
             })
             .map_err(Into::into);
             let _ = res.send(response).ok();
            
         }
+        Err(StateError::Underpriced(underpriced)) => {
+            ApiMetrics::increment_validation_errors_count("max_priority_fee_per_gas_too_low".to_string());
+            ApiMetrics::increment_priority_tier_requests_count(req.priority.as_str(), "underpriced");
+            tracing::debug!(%underpriced, "Refusing an underpriced request");
+            let _ = res.send(Err(CommitmentRequestError::Underpriced(underpriced)));
+        }
         Err(err) => {
-            ApiMetrics::increment_validation_errors_count("validation error".to_string());
+            ApiMetrics::increment_validation_errors_count(err.code().to_string());
             tracing::error!(?err, "validation error");
             res.send(Err(CommitmentRequestError::Custom(err.to_string())))
                 .err();
@@ -122,36 +397,216 @@ async fn handle_preconfirmation_request(
     };
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn handle_commitment_deadline(
     slot: u64,
-    constraint_state: Arc<Mutex<ConstraintState>>,
-    commit_boost_api: Arc<Mutex<CommitBoostApi>>,
-    fallback_builder: Arc<Mutex<FallbackBuilder>>,
+    early: bool,
+    constraint_state: Arc<RwLock<ConstraintState>>,
+    commit_boost_api: Arc<Mutex<MultiRelayClient>>,
+    fallback_builder: Option<Arc<Mutex<FallbackBuilder>>>,
+    events: EventBroadcaster,
+    inclusion: InclusionTracker,
+    submission_log: Option<SubmissionLog>,
+    audit: AuditTrail,
+    mut budget: DeadlineBudget,
 ) {
-    let mut constraint_state = constraint_state.lock().await;
-    let commit_boost_api = commit_boost_api.lock().await;
-    let mut fallback_builder = fallback_builder.lock().await;
+    let (blocks, status_board, verify_chain, follow_up, proposing) = {
+        let mut state = timed("constraint_state", "write", constraint_state.write()).await;
+        let verify_chain = state
+            .verify_constraints
+            .then_some(state.config.id)
+            .and_then(|id| Chain::try_from_id(id).ok());
+        // The requests to the slot are refused from now on, its block being removed below
+        if !early {
+            state.reach_deadline(slot - 1);
+        }
+        // Following up on an early submission with the constraints committed since
+        let follow_up = !early && state.submitted_early == Some(slot);
+        if follow_up && !state.incremental_submissions {
+            if let Some(block) = state.blocks.remove(slot) {
+                tracing::error!(slot, constraints = block.signed_constraints_list.len(), "Dropping the constraints committed after the early submission");
+            }
+            budget.finish();
+            return;
+        }
+        if early {
+            state.submitted_early = Some(slot);
+        }
+        let proposing = state.proposers.read().contains_key(&slot);
+        // Our proposal slots are charged to the reservations, used or not
+        if !follow_up && proposing {
+            state.reservations.settle(slot);
+        }
+        (state.blocks.clone(), state.status.clone(), verify_chain, follow_up, proposing)
+    };
+    // Only the slot is held, after the commitments to it in flight are added
+    let (_committing, commit_boost_api, mut fallback_builder) = budget
+        .stage("lock", async {
+            let committing = blocks.lock_slot(slot).await;
+            let commit_boost_api =
+                timed("commit_boost_api", "lock", commit_boost_api.lock()).await;
+            let fallback_builder = match &fallback_builder {
+                Some(fallback_builder) => {
+                    Some(timed("fallback_builder", "lock", fallback_builder.lock()).await)
+                }
+                None => None,
+            };
+            (committing, commit_boost_api, fallback_builder)
+        })
+        .await;
 
-    tracing::info!("The commitment deadline is reached in slot {}", slot);
+    if follow_up {
+        tracing::info!(slot, "Submitting the constraints committed after the early submission");
+    } else {
+        tracing::info!("The commitment deadline is reached in slot {}", slot);
+        events.send(ApiEvent::DeadlineClosed { slot });
+    }
 
-    let Some(block) = constraint_state.blocks.remove(&slot) else {
+    let block = budget.stage("remove_block", async { blocks.remove(slot) }).await;
+    constraint_state.read().await.publish_status();
+    let Some(mut block) = block else {
         tracing::debug!("Couldn't find a block at slot {slot}");
+        // Without constraints, a payload of our own still stands in for the relays failing
+        if let Some(fallback_builder) = fallback_builder
+            .as_mut()
+            .filter(|builder| proposing && !follow_up && builder.always_builds())
+        {
+            let built = budget
+                .stage("fallback", fallback_builder.build_fallback_payload(&Block::default(), slot))
+                .await;
+            if let Err(e) = built {
+                tracing::error!(err = ?e, "Failed in building standby payload at slot {slot}");
+                status_board.record_error("fallback", e);
+            }
+        }
+        budget.finish();
         return;
     };
 
     tracing::debug!("removed constraints at slot {slot}");
 
-    match commit_boost_api
-        .send_constraints(&block.signed_constraints_list)
+    // A constraint signed wrong by a faulty signer would get the whole batch rejected
+    if let Some(chain) = verify_chain {
+        let version = commit_boost_api.constraints_version();
+        let dropped = budget
+            .stage("verify", async {
+                let constraints = &block.signed_constraints_list;
+                let valid = match version {
+                    ConstraintsVersion::V1 => {
+                        verify_constraints(constraints, block.digests(), &chain)
+                    }
+                    ConstraintsVersion::V2 => {
+                        let digests = constraints
+                            .iter()
+                            .map(|sc| sc.message.digest_for(version))
+                            .collect::<Vec<_>>();
+                        verify_constraints(constraints, &digests, &chain)
+                    }
+                };
+                block.retain_valid(&valid)
+            })
+            .await;
+        if dropped > 0 {
+            tracing::error!(slot, dropped, "Dropping constraints with invalid signatures");
+            status_board
+                .record_error("signer", format!("{dropped} invalid constraints in slot {slot}"));
+        }
+    }
+    inclusion.track(slot, block.commitments());
+
+    // The submission must reach the relay before the cutoff, whatever the retries
+    let constraints = &block.signed_constraints_list;
+    let batch = block.batch_id();
+    audit.record_constraints(slot, batch, constraints);
+    audit.prune(slot);
+    let batch = submission_log.as_ref().and_then(|log| {
+        log.record_intent(slot, batch, constraints)
+            .map(|()| batch)
+            .map_err(|err| tracing::error!(?err, slot, "Failed to log the submission intent"))
+            .ok()
+    });
+    let cutoff = budget.remaining();
+    let submitted = budget
+        .stage("submit", tokio::time::timeout(cutoff, commit_boost_api.send_constraints(constraints)))
+        .await;
+
+    let delivered = matches!(submitted, Ok(Ok(_)));
+    // The relay bids of the slot must prove the inclusion of the constraints it took
+    if delivered {
+        blocks.record_submitted(slot, &block);
+    }
+    let mut submission = None;
+    match submitted {
+        Ok(Ok(accepted)) => {
+            let status = budget
+                .optional("confirm", commit_boost_api.confirm_constraints(slot, constraints))
+                .await
+                .and_then(Result::ok)
+                .unwrap_or(ConstraintsSubmissionStatus::Submitted);
+
+            tracing::info!(status = status.as_str(), accepted, "Sent constratins successfully.");
+            ApiMetrics::increment_constraints_submissions_count(status.as_str());
+            submission = Some(status);
+            status_board.record_success(Component::Relay);
+        }
+        Ok(Err(err)) => {
+            tracing::error!(err = ?err, "Error sending constraints");
+            if let Some(error) = err.relay_error() {
+                let status = ConstraintsSubmissionStatus::Rejected(error.kind());
+                ApiMetrics::increment_constraints_submissions_count(status.as_str());
+                submission = Some(status);
+                events.send(ApiEvent::ConstraintsRejected {
+                    slot,
+                    reason: error.kind(),
+                    message: error.message().to_string(),
+                });
+            }
+            status_board.record_failure(Component::Relay, err);
+        }
+        Err(_) => {
+            tracing::error!(slot, "Constraints submission missed the slot cutoff");
+            status_board.record_failure(
+                Component::Relay,
+                format!("submission for slot {slot} missed the cutoff"),
+            );
+        }
+    };
+    if let Some(submission) = submission {
+        timed("constraint_state", "write", constraint_state.write())
+            .await
+            .submissions
+            .insert(slot, submission);
+    }
+
+    if let (Some(log), Some(batch)) = (&submission_log, batch) {
+        let logged = if delivered {
+            log.record_acked(slot, batch)
+        } else {
+            log.record_abandoned(slot, batch)
+        };
+        if let Err(err) = logged {
+            tracing::error!(?err, slot, "Failed to log the submission outcome");
+        }
+    }
+
+    #[cfg(feature = "collector-client")]
+    if let Some(Err(err)) = budget
+        .optional("collector", commit_boost_api.send_constraints_to_be_collected(constraints))
         .await
     {
-        Ok(_) => tracing::info!("Sent constratins successfully."),
-        Err(err) => tracing::error!(err = ?err, "Error sending constraints"),
-    };
+        tracing::error!(?err, "Failed to mirror constraints to the collector");
+    }
 
-    if let Err(e) = fallback_builder.build_fallback_payload(&block, slot).await {
-        tracing::error!(err = ?e, "Failed in building fallback payload at slot {slot}");
-    };
+    if let Some(fallback_builder) = fallback_builder.as_mut() {
+        let built =
+            budget.stage("fallback", fallback_builder.build_fallback_payload(&block, slot)).await;
+        if let Err(e) = built {
+            tracing::error!(err = ?e, "Failed in building fallback payload at slot {slot}");
+            status_board.record_error("fallback", e);
+        };
+    }
+
+    budget.finish();
 }
 
 async fn handle_local_payload_request(
@@ -159,11 +614,11 @@ async fn handle_local_payload_request(
     fallback_builder: Arc<Mutex<FallbackBuilder>>,
     response_tx: Sender<Option<PayloadAndBid>>,
 ) {
-    let mut fallback_builder = fallback_builder.lock().await;
+    let mut fallback_builder = timed("fallback_builder", "lock", fallback_builder.lock()).await;
 
     tracing::info!(slot, "Received local payload request");
 
-    let Some(payload_and_bid) = fallback_builder.get_cached_payload() else {
+    let Some(payload_and_bid) = fallback_builder.get_cached_payload(slot) else {
         tracing::warn!("No local payload found for {slot}");
         let _ = response_tx.send(None);
         return;
@@ -176,105 +631,757 @@ async fn handle_local_payload_request(
     }
 }
 
-async fn handle_head_event(slot: u64, constraint_state: Arc<Mutex<ConstraintState>>) {
-    let mut constraint_state = constraint_state.lock().await;
+/// Resume the submissions in flight when the previous instance stopped, unless the relay
+/// already holds their constraints.
+async fn resume_submissions(
+    pending: Vec<PendingSubmission>,
+    commit_boost_api: Arc<Mutex<MultiRelayClient>>,
+    submission_log: SubmissionLog,
+) {
+    for PendingSubmission { slot, batch, constraints } in pending {
+        let commit_boost_api = timed("commit_boost_api", "lock", commit_boost_api.lock()).await;
 
-    tracing::info!(slot, "Got received a new head event");
+        let logged = if commit_boost_api.holds_constraints(slot, &constraints).await == Some(true) {
+            tracing::info!(slot, %batch, "Interrupted submission already reached the relay");
+            ApiMetrics::increment_submission_recoveries_count("delivered");
+            submission_log.record_acked(slot, batch)
+        } else {
+            tracing::info!(slot, %batch, "Resuming an interrupted constraints submission");
+            ApiMetrics::increment_submission_recoveries_count("resumed");
+            match commit_boost_api.send_and_confirm_constraints(slot, &constraints).await {
+                Ok(status) => {
+                    ApiMetrics::increment_constraints_submissions_count(status.as_str());
+                    submission_log.record_acked(slot, batch)
+                }
+                Err(err) => {
+                    tracing::error!(?err, slot, "Failed to resume the constraints submission");
+                    submission_log.record_abandoned(slot, batch)
+                }
+            }
+        };
+        if let Err(err) = logged {
+            tracing::error!(?err, slot, "Failed to log the submission outcome");
+        }
+    }
+}
 
-    // We use None to signal that we want to fetch the latest EL head
-    if let Err(e) = constraint_state.update_head(slot).await {
-        tracing::error!(err = ?e, "Occurred errors in updating the constraint state head");
+/// Next payload request of the builder proxy, pending forever without a fallback builder.
+async fn next_payload_request(
+    payload_rx: &mut Option<mpsc::Receiver<FetchPayloadRequest>>,
+) -> Option<FetchPayloadRequest> {
+    match payload_rx {
+        Some(payload_rx) => payload_rx.recv().await,
+        None => std::future::pending().await,
     }
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_head_event(
+    slot: u64,
+    slot_clock: SlotClock,
+    constraint_state: Arc<RwLock<ConstraintState>>,
+    events: EventBroadcaster,
+    payments: ProposerPayments,
+    mempool: Option<MempoolWatcher>,
+    sender: mpsc::Sender<CommitmentRequestEvent>,
+) {
+    tracing::info!(slot, "Got received a new head event");
+    events.send(ApiEvent::NewHead { slot });
+
+    let next_slot = slot + 1;
+    let epoch_started = slot % slot_clock.slots_per_epoch() == 0;
+    // Looked up by the hash of the block of the slot, without holding the execution state
+    tokio::spawn(payments.account(slot, epoch_started));
+
+    let (execution, deadline_ms, chain_id) = {
+        let mut constraint_state =
+            timed("constraint_state", "write", constraint_state.write()).await;
+        if let Err(e) = constraint_state.update_head(slot, slot_clock).await {
+            tracing::error!(err = ?e, "Occurred errors in updating the constraint state head");
+            constraint_state.status.record_error("head", e);
+        }
+        (
+            constraint_state.execution.clone(),
+            constraint_state.commitment_deadline_ms(next_slot),
+            constraint_state.config.id,
+        )
+    };
+
+    // The execution state is updated without holding the constraint state
+    let mut execution = timed("execution_state", "lock", execution.lock()).await;
 
     // We use None to signal that we want to fetch the latest EL head
-    if let Err(e) = constraint_state.execution.update_head(None, slot).await {
+    if let Err(e) = execution.update_head(None, slot).await {
         tracing::error!(err = ?e, "Failed to update execution state head");
     }
+
+    if let Some(deadline_ms) = deadline_ms {
+        events.send(ApiEvent::DeadlineOpened { slot: next_slot, deadline_ms });
+
+        // Force the long pending transactions in our proposal. Spawned as the requests are
+        // validated against the execution state held here
+        if let Some(mempool) = mempool {
+            tokio::spawn(async move { mempool.submit(next_slot, chain_id, sender).await });
+        }
+    }
+
+    events.send(ApiEvent::PricingUpdate {
+        slot: next_slot,
+        basefee: execution.basefee(),
+        min_priority_fee: execution.min_priority_fee(next_slot, TRANSFER_GAS),
+    });
+}
+
+/// Reconciles the bids we served with the payments received by the fee recipient, and exports
+/// the revenue report at epoch boundaries.
+#[derive(Debug, Clone)]
+struct ProposerPayments {
+    revenue: RevenueTracker,
+    execution: ExecutionClient,
+    beacon: reqwest::Client,
+    beacon_url: Url,
+    fee_recipient: Address,
+    report_path: Option<PathBuf>,
+}
+
+impl ProposerPayments {
+    /// Record the payment received in the block of `slot`, if we served the bid for it.
+    async fn account(self, slot: u64, epoch_started: bool) {
+        if self.revenue.awaits_payment(slot) {
+            match self.payment(slot).await {
+                Ok(payment) => self.revenue.record_onchain_payment(slot, payment),
+                Err(err) => tracing::error!(?err, slot, "Failed to fetch the proposer payment"),
+            }
+        }
+
+        if epoch_started {
+            self.revenue.prune(slot);
+
+            if let Some(path) = &self.report_path {
+                if let Err(err) = self.revenue.export_csv(path) {
+                    tracing::error!(?err, path = %path.display(), "Failed to export the revenue report");
+                }
+            }
+        }
+    }
+
+    async fn payment(&self, slot: u64) -> eyre::Result<U256> {
+        let hash = slot_execution_block_hash(&self.beacon, &self.beacon_url, slot).await?;
+        Ok(self.execution.get_balance_change(self.fee_recipient, hash).await?)
+    }
+}
+
+/// Serve the query endpoints from the store shared by the primary until stopped. Replicas
+/// don't claim the lease nor load the keystores, so they can never sign.
+async fn run_replica(config: &Config) {
+    let (Some(store_path), Some(primary_url)) = (&config.shared_store_path, &config.primary_url)
+    else {
+        tracing::error!("Replicas require SHARED_STORE_PATH and PRIMARY_URL");
+        std::process::exit(1);
+    };
+
+    let _ = run_metrics_server(config.metrics_port);
+    run_replica_rpc_server(config, SharedStore::new(store_path.clone()), primary_url.clone())
+        .await;
+
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        tracing::error!(?err, "Failed to listen for the shutdown signal");
+    }
+}
+
+/// Run the operator subcommand, exiting with a non-zero code if it fails.
+#[cfg(feature = "cli")]
+async fn run_command(config: &Config, command: Command) {
+    match command {
+        Command::SignRoot { root, pubkey, signer } => {
+            match cli::sign_root(config, root, &pubkey, signer).await {
+                Ok(signature) => println!("{signature}"),
+                Err(err) => {
+                    tracing::error!(?err, "Failed to sign root");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Status(opts) => {
+            if let Err(err) = cli::watch_status(config, opts).await {
+                eprintln!("{err:?}");
+                std::process::exit(1);
+            }
+        }
+        Command::Replay(opts) => {
+            if let Err(err) = cli::replay_archive(config, opts) {
+                eprintln!("{err:?}");
+                std::process::exit(1);
+            }
+        }
+        Command::Migrate(opts) => {
+            if let Err(err) = cli::migrate(config, opts) {
+                eprintln!("{err:?}");
+                std::process::exit(1);
+            }
+        }
+        #[cfg(feature = "signer-web3")]
+        Command::Delegate(opts) => match cli::delegate(opts).await {
+            Ok(signed) => tracing::info!(signed, "Signed delegation messages"),
+            Err(err) => {
+                tracing::error!(?err, "Failed to sign delegation messages");
+                std::process::exit(1);
+            }
+        },
+        Command::RenewDelegations(opts) => match cli::renew_delegations(config, opts).await {
+            Ok(renewed) => tracing::info!(renewed, "Renewed delegations"),
+            Err(err) => {
+                tracing::error!(?err, "Failed to renew delegations");
+                std::process::exit(1);
+            }
+        },
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
     let subscriber = Subscriber::builder()
         .with_max_level(tracing::Level::DEBUG)
         .finish();
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
-    // let config = Config::parse_from_cli().unwrap();
-    tracing::info!("path: {}", env!["CARGO_MANIFEST_DIR"]);
-    let mut env_path = env!["CARGO_MANIFEST_DIR"].to_string();
-    env_path.push_str("/.env");
-    let envs = read_file(env_path).unwrap();
+    let envs = match cli.config_sources().load() {
+        Ok(envs) => envs,
+        Err(err) => {
+            eprintln!("config error: {err}");
+            std::process::exit(1);
+        }
+    };
+
+    if cli.validate_config {
+        match cli::validate_config(envs).await {
+            Ok(config) => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&config.redacted()).expect("valid json")
+                );
+                return;
+            }
+            Err(errors) => {
+                for err in errors {
+                    eprintln!("config error: {err}");
+                }
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Every missing or invalid variable is reported, rather than panicking on the first one
+    let errors = check_envs(&envs);
+    if !errors.is_empty() {
+        for err in errors {
+            eprintln!("config error: {err}");
+        }
+        std::process::exit(1);
+    }
 
     let (sender, mut receiver) = mpsc::channel(1024);
     let config = Config::new(envs);
-    let keystores = Keystores::new(
-        &config.keystore_pubkeys_path,
-        &config.keystore_secrets_path,
-        &config.chain,
-    );
+
+    #[cfg(feature = "cli")]
+    if let Some(command) = cli.command {
+        run_command(&config, command).await;
+        return;
+    }
+
+    if config.role == InstanceRole::Replica {
+        run_replica(&config).await;
+        return;
+    }
+
+    // Claim the lease first, so that the instance being replaced stops signing while we start
+    let shutdown = ShutdownController::listen();
+    let lease = match &config.instance_lease_path {
+        Some(path) => {
+            InstanceLease::acquire(path.clone()).expect("Failed to claim the instance lease")
+        }
+        None => InstanceLease::standalone(),
+    };
+
+    // The stores written by an older version are migrated before any of them is opened
+    let options = MigrateOptions { dry_run: false, backup: config.schema_backup };
+    match migrate_stores(&configured_stores(&config), options) {
+        Ok(upgrades) => {
+            for upgrade in upgrades {
+                tracing::info!(%upgrade, "Migrated store");
+            }
+        }
+        Err(err) => {
+            tracing::error!(?err, "Failed to migrate the persisted stores");
+            std::process::exit(1);
+        }
+    }
+
+    // Shared by the signer and the receipt signer when both are the commit-boost signer
+    let signing_limiters = SigningLimiters::from_config(&config);
+    let signer =
+        connect_signer(&config, &signing_limiters).await.expect("Failed to connect to the signer");
+    // Listed once, remote signers are only asked for signatures afterwards. The backends
+    // reloading their keys update them as they change
+    let signer_pubkeys = Arc::new(ArcSwap::from_pointee(
+        signer.list_pubkeys().await.expect("Failed to list the signer keys"),
+    ));
+    tracing::info!(signer = %config.signer_type, keys = signer_pubkeys.load().len(), "Signer ready");
+    if let Some(mut key_updates) = signer.key_updates() {
+        let signer_pubkeys = signer_pubkeys.clone();
+        tokio::spawn(async move {
+            while key_updates.changed().await.is_ok() {
+                signer_pubkeys.store(Arc::new(key_updates.borrow_and_update().clone()));
+            }
+        });
+    }
 
     let commit_boost_signer_url = &config.commit_boost_signer_url;
     let jwt = &config.jwt_hex;
     tracing::info!(?commit_boost_signer_url);
 
-    let web3signer_enabled = !config.ca_cert_path.is_empty() && !config.combined_pem_path.is_empty();
+    // Receipts are signed with an ECDSA key, never with the validator keys
+    let receipt_domain = config.chain.commit_boost_domain();
+    let receipt_signer = match &config.receipt_proxy_delegator {
+        Some(delegator) => Some(
+            ReceiptSigner::commit_boost_proxy(
+                CBSigner::new(commit_boost_signer_url, jwt)
+                    .with_limiter(signing_limiters.commit_boost.clone())
+                    .with_headers(&config.outbound_headers),
+                delegator,
+                receipt_domain,
+            )
+            .await
+            .expect("Failed to set up the receipt proxy key"),
+        ),
+        None => config
+            .quote_signer
+            .clone()
+            .map(|signer| ReceiptSigner::local(signer, receipt_domain)),
+    };
+    tracing::info!(?receipt_signer);
+    // Shared with the builder proxy, the constraints submissions and the receipt gossip, so that
+    // all the requests to the relay are rate limited together.
+    let relay_limiter = RelayRateLimiter::from_config(&config.cb_url, &config);
+    // Inclusion receipts are signed with the delegatee keys of the constraints
+    let receipts = ReceiptStore::default();
+    let receipts = match config.receipt_gossip {
+        #[cfg(feature = "collector-client")]
+        Some(mode) => {
+            // A client of its own, so that the gossip doesn't trip the breaker of the relay
+            let collector = CommitBoostApi::new(
+                config.cb_url.clone(),
+                config.relay_auth.clone(),
+                relay_limiter.clone(),
+            )
+            .with_headers(&config.outbound_headers);
+            tracing::info!(%mode, "Gossiping the inclusion receipts to the collector");
+            receipts.with_gossip(ReceiptGossip::spawn(collector, mode, config.receipt_gossip_queue))
+        }
+        #[cfg(not(feature = "collector-client"))]
+        Some(_) => {
+            tracing::warn!("Receipt gossip requires the collector-client feature, not gossiping");
+            receipts
+        }
+        None => receipts,
+    };
+
+    let web3signer_enabled = cfg!(feature = "signer-web3")
+        && !config.ca_cert_path.is_empty()
+        && !config.combined_pem_path.is_empty();
     tracing::info!(?web3signer_enabled);
     let _ = run_metrics_server(config.metrics_port);
 
-    run_commitment_rpc_server(sender, &config).await;
+    let beacon_client = config.outbound_headers.beacon_client(config.beacon_api_url.clone());
 
-    let (payload_tx, mut payload_rx) = mpsc::channel(16);
-    let payload_fetcher = FallbackPayloadFetcher::new(payload_tx);
-
-    let commit_boost_api = run_constraints_proxy_server(&config, payload_fetcher)
+    let genesis = beacon_client
+        .get_genesis_details()
         .await
-        .unwrap();
+        .expect("Failed to fetch genesis details");
+    let slot_clock =
+        SlotClock::for_chain(genesis.genesis_time, &config.chain, config.slot_drift_threshold_ms);
+
+    // Shared by all the clients of each dependency
+    let breakers = CircuitBreakers::new(config.breaker);
+    let client_state = ClientState::new(config.execution_api_url.clone())
+        .with_retry_policy(config.retry)
+        .with_breaker(breakers.execution.clone());
+    let limits =
+        LimitOptions {
+            account_states_eviction_policy: config.account_states_eviction_policy,
+            account_states_ttl_secs: config.account_states_ttl_secs,
+            max_gas_limit_ratio: config.max_gas_limit_ratio,
+            check_deployment_collisions: config.check_deployment_collisions,
+            max_blobs_per_block: config.chain.max_blobs_per_block,
+            ..Default::default()
+        };
+    let execution_state =
+        ExecutionState::new(client_state, limits, DEFAULT_GAS_LIMIT)
+            .await
+            .expect("Failed to create Execution State")
+            .with_slots_per_epoch(config.chain.slots_per_epoch);
+
+    let events = EventBroadcaster::new();
+
+    let payments = ProposerPayments {
+        revenue: execution_state.revenue(),
+        execution: ExecutionClient::new(config.execution_api_url.clone()),
+        beacon: config.outbound_headers.client(),
+        beacon_url: config.beacon_api_url.clone(),
+        fee_recipient: config.fee_recipient,
+        report_path: config.revenue_report_path.clone(),
+    };
+
+    let el_sync = ElSyncMonitor::new(config.max_el_lag_blocks);
+    el_sync.spawn(
+        ExecutionClient::new(config.execution_api_url.clone()),
+        config.outbound_headers.client(),
+        config.beacon_api_url.clone(),
+        EL_SYNC_POLL_INTERVAL,
+    );
+
+    let replacements = ReplacementGuard::new(config.replacement_policy);
+    replacements.spawn(
+        ExecutionClient::new(config.execution_api_url.clone()),
+        MEMPOOL_POLL_INTERVAL,
+    );
+
+    let inclusion_list_sender = sender.clone();
+    if let Some(path) = &config.shared_store_path {
+        SharedStore::new(path.clone()).spawn_publisher(
+            STORE_PUBLISH_INTERVAL,
+            slot_clock.clone(),
+            execution_state.revenue(),
+            execution_state.account_states_stats(),
+            events.clone(),
+        );
+    }
+
+    // Shared with the constraint state, which updates the proposers of the epoch
+    let proposers = SharedProposers::default();
+    // Shared with the constraint state, so that the capacity of the slots is read without its lock
+    let blocks = SlotShards::default();
+    let forwarder = config.peer_registry_path.as_ref().map(|path| {
+        let secret = config.peer_forward_secret.as_deref().expect("Peer forward secret set");
+        PeerForwarder::load(path, proposers.clone(), secret)
+            .expect("Failed to load the peer registry")
+    });
+
+    // Shared with the constraint state, which reports its live state to the operators
+    let status = StatusBoard::default();
+    let delegation_health = config.validator_indexes.as_ref().map(|_| DelegationHealth::default());
+    // Connected once the relay client is set up
+    let delegation_lookup = DelegationLookup::default();
+    let chain = Chain::try_from_id(config.chain.id).expect("supported chain");
+    // Connected once the relay client is set up, serves the delegations to the requests
+    let delegation_registry = DelegationRegistry::open(config.delegation_registry_path.clone(), chain, slot_clock.clone())
+        .expect("Failed to open the delegation registry");
+    // Run once the relay client is set up, readiness fails until then
+    let signer_keys = SignerKeyCheck::default();
+    // Shared with the inclusion tracker, which records where our commitments landed
+    let inclusion_stats = InclusionStats::default();
+    // Shared with the builder proxy and the deadline handler, which record our proposals
+    let audit = match &config.audit_log_path {
+        Some(path) => AuditTrail::open(path.clone(), slot_clock.current_slot(), config.chain.slots_per_epoch)
+            .expect("Failed to open the audit log"),
+        None => AuditTrail::new(config.chain.slots_per_epoch),
+    };
+
+    // Shared with the constraint state, which holds the reserved gas back from the others
+    let reservations = ReservationBook::new(config.reservation_sequencers.clone())
+        .with_max_reserved_gas(config.max_reserved_gas)
+        .with_min_price_per_gas(limits.min_inclusion_profit);
+
+    let capacity = CapacityView::new(
+        blocks.clone(),
+        proposers.clone(),
+        execution_state.quoter(None, config.quote_ttl_ms),
+        execution_state.shared_snapshot(),
+        slot_clock.clone(),
+    )
+    .with_limits(CapacityLimits {
+        max_pending_blob_bytes: config.max_pending_blob_bytes,
+        ..Default::default()
+    })
+    .with_reservations(reservations.clone());
+
+    // Requests received by any instance are processed by the leader, in the order of the log
+    let sequencer = config.request_log_path.as_ref().map(|path| {
+        RequestSequencer::open(path.clone(), lease.clone(), sender.clone())
+            .expect("Failed to open the shared request log")
+    });
+
+    run_commitment_rpc_server(
+        sender,
+        &config,
+        slot_clock.clone(),
+        execution_state.account_states_stats(),
+        execution_state.shared_snapshot(),
+        events.clone(),
+        execution_state.revenue(),
+        el_sync,
+        execution_state.quoter(config.quote_signer.clone(), config.quote_ttl_ms),
+        forwarder,
+        status.clone(),
+        delegation_health.clone(),
+        delegation_lookup.clone(),
+        delegation_registry.clone(),
+        signer_keys.clone(),
+        inclusion_stats.clone(),
+        replacements,
+        execution_state.stale_index(),
+        audit.clone(),
+        capacity,
+        breakers.clone(),
+        receipts.clone(),
+        reservations.clone(),
+        sequencer,
+        RequestRateLimiters::new(config.sender_rate_limit, config.ip_rate_limit),
+        lease.clone(),
+    )
+    .await;
+
+    // Without a fallback builder there is no local payload to serve, and no channel to ask for it
+    let (commit_boost_api, mut payload_rx) = if config.fallback_builder {
+        let (payload_tx, payload_rx) = mpsc::channel(16);
+        let payload_fetcher = FallbackPayloadFetcher::new(payload_tx);
+        let api = run_constraints_proxy_server(&config, genesis.genesis_time, blocks.clone(), payload_fetcher, execution_state.revenue(), audit.clone(), breakers.relay.clone(), relay_limiter.clone(), lease.clone())
+            .await
+            .unwrap();
+        (api, Some(payload_rx))
+    } else {
+        tracing::info!("Fallback builder disabled");
+        let api = run_constraints_proxy_server(&config, genesis.genesis_time, blocks.clone(), NoopPayloadFetcher, execution_state.revenue(), audit.clone(), breakers.relay.clone(), relay_limiter.clone(), lease.clone())
+            .await
+            .unwrap();
+        (api, None)
+    };
+
+    let relay_client = config.outbound_headers.client();
+
+    // The other relays get breakers of their own, so that one failing doesn't fail the
+    // submissions to the others fast
+    let extra_relays = config
+        .extra_relay_urls
+        .iter()
+        .zip(&config.extra_relay_auth)
+        .map(|(url, auth)| {
+            CommitBoostApi::new(url.clone(), auth.clone(), RelayRateLimiter::from_config(url, &config))
+                .with_retry_policy(config.retry)
+                .with_headers(&config.outbound_headers)
+                .with_compression(config.relay_compression)
+                .with_breaker(CircuitBreaker::new(Dependency::Relay, config.breaker))
+                .with_accepted_forks(config.chain.accepted_forks.clone())
+        })
+        .collect::<Vec<_>>();
+    // The delegations are read from every relay, sharing the rate limits of their submissions
+    let delegation_relays = std::iter::once(DelegationRelay {
+        url: config.relay_url.clone(),
+        auth: config.relay_auth.clone(),
+        limiter: relay_limiter.clone(),
+    })
+    .chain(config.extra_relay_urls.iter().zip(&config.extra_relay_auth).zip(&extra_relays).map(
+        |((url, auth), relay)| DelegationRelay {
+            url: url.clone(),
+            auth: auth.clone(),
+            limiter: relay.rate_limiter(),
+        },
+    ))
+    .collect();
+    let commit_boost_api = MultiRelayClient::new(commit_boost_api, extra_relays, config.relay_quorum)
+        .with_timeout(Duration::from_millis(config.relay_timeout_ms));
+    tracing::info!(relays = 1 + config.extra_relay_urls.len(), quorum = commit_boost_api.quorum(), "Submitting constraints to the relays");
+    let relay_delegations =
+        RelayDelegations { client: relay_client.clone(), relays: delegation_relays };
+    delegation_registry.connect(relay_delegations.clone());
+    delegation_registry.spawn(DELEGATION_SYNC_INTERVAL, beacon_client.clone());
 
-    let beacon_client = Client::new(config.beacon_api_url.clone());
+    delegation_lookup.connect(LookupSources {
+        beacon_client: beacon_client.clone(),
+        slot_clock: slot_clock.clone(),
+        relay: relay_delegations.clone(),
+        local_path: config.delegations_path.clone(),
+        local_keys: HashSet::clone(&signer_pubkeys.load()),
+        chain,
+    });
 
-    let relay_client = reqwest::Client::builder().build().expect("failed to create relay client");
+    let expected_keys = ExpectedKeys {
+        configured: config.delegatee_pubkeys.clone(),
+        local_path: config.delegations_path.clone(),
+        validators: config.validator_indexes.clone(),
+        beacon_client: beacon_client.clone(),
+        slot_clock: slot_clock.clone(),
+        relay: relay_delegations.clone(),
+        chain,
+    };
+    let local_keys = HashSet::clone(&signer_pubkeys.load());
+    let key_check = signer_keys.clone();
+    tokio::spawn(async move { key_check.run(expected_keys, local_keys).await });
+
+    if let (Some(health), Some(validators)) = (&delegation_health, &config.validator_indexes) {
+        health.spawn(
+            DELEGATION_HEALTH_INTERVAL,
+            beacon_client.clone(),
+            slot_clock.clone(),
+            validators.clone(),
+            HashSet::clone(&signer_pubkeys.load()),
+            chain,
+            relay_delegations,
+        );
+    }
 
-    let client_state = ClientState::new(config.execution_api_url.clone());
     // let mut constraint_state = Arc::new(RwLock::new(ConstraintState::new( beacon_client.clone(), config.validator_indexes.clone(), config.chain.get_commitment_deadline_duration()))) ;
-    let constraint_state = ConstraintState::new(
+    let mut constraint_state = ConstraintState::new(
         beacon_client.clone(),
         config.chain.get_commitment_deadline_duration(),
-        ExecutionState::new(client_state, LimitOptions::default(), DEFAULT_GAS_LIMIT)
-            .await
-            .expect("Failed to create Execution State"),
+        slot_clock,
+        execution_state,
         &config.chain,
     );
 
+    constraint_state.constraints_version = commit_boost_api.detect_constraints_version().await;
+    constraint_state.blocks = blocks;
+    constraint_state.proposers = proposers;
+    constraint_state.status = status;
+    constraint_state.retry = config.retry;
+    constraint_state.beacon_breaker = breakers.beacon.clone();
+    constraint_state.max_pending_blob_bytes = config.max_pending_blob_bytes;
+    constraint_state.verify_constraints = config.verify_constraints;
+    constraint_state.reservations = reservations;
+    constraint_state.incremental_submissions = config.relay_incremental_submissions;
+    let slot_archive = config.slot_archive_path.as_ref().map(|path| {
+        let archive =
+            SlotArchive::open(path.clone()).expect("Failed to open the slot archive");
+        archive.record(&SlotEvent::<BeaconResponses>::Started {
+            genesis_time: genesis.genesis_time,
+            slot_time: config.chain.slot_time,
+            drift_threshold_ms: config.slot_drift_threshold_ms,
+        });
+        tracing::info!(path = %path.display(), "Archiving the slot handling");
+        archive
+    });
+    constraint_state.archive = slot_archive.clone();
+    if let Some(path) = &config.constraints_journal_path {
+        let journal = open_journal(path).expect("Failed to open the constraints journal");
+        let restored =
+            constraint_state.attach_journal(journal).await.expect("Failed to restore the constraints");
+        tracing::info!(restored, path = %path.display(), "Restored the journaled constraints");
+    }
+
+    let inclusion = InclusionTracker::new(
+        ExecutionClient::new(config.execution_api_url.clone()),
+        constraint_state.slot_clock.clone(),
+        config.inclusion_webhook_url.clone(),
+        events.clone(),
+        inclusion_stats,
+    );
+    let mempool = config.inclusion_list.map(|policy| {
+        let watcher = MempoolWatcher::new(
+            ExecutionClient::new(config.execution_api_url.clone()),
+            constraint_state.slot_clock.clone(),
+            policy,
+        );
+        watcher.spawn(MEMPOOL_POLL_INTERVAL);
+        watcher
+    });
+    let mut block_event_listener = BlockEventListener::run(beacon_client.clone());
     let mut head_event_listener = HeadEventListener::run(beacon_client);
 
-    let fallback_builder = FallbackBuilder::new(&config);
+    // Its beacon and execution clients are only created on the first build
+    let fallback_builder = config.fallback_builder.then(|| FallbackBuilder::new(&config));
 
     tracing::debug!("Connected to the server!");
 
-    let constraint_state_arc = Arc::new(Mutex::new(constraint_state));
+    // Commitment deadlines of the slots, armed from the slot clock corrected with the head events
+    let mut deadlines = DeadlineScheduler::new(config.chain.get_commitment_deadline_duration());
+    if let Some(early_deadline_ms) = config.early_deadline_ms {
+        deadlines = deadlines.with_early_fire(Duration::from_millis(early_deadline_ms));
+    }
+    let slot_clock = constraint_state.slot_clock.clone();
+    let constraint_state_arc = Arc::new(RwLock::new(constraint_state));
+    // Shares the circuit breakers of the relays, read at the early checkpoints
+    let relays = commit_boost_api.clone();
     let commit_boost_api = Arc::new(Mutex::new(commit_boost_api));
-    let fallback_builder = Arc::new(Mutex::new(fallback_builder));
+    let fallback_builder = fallback_builder.map(|builder| Arc::new(Mutex::new(builder)));
+
+    // Only the signing instance writes the submission log, the one it replaces stopped by then
+    let submission_log = match &config.submission_log_path {
+        Some(path) if lease.leading().await => {
+            let current_slot = slot_clock.current_slot();
+            let (log, pending) = SubmissionLog::open(path.clone(), current_slot)
+                .expect("Failed to open the submission log");
+            tokio::spawn(resume_submissions(pending, commit_boost_api.clone(), log.clone()));
+            Some(log)
+        }
+        _ => None,
+    };
+
+    // The constraints accepted by the replaced instance are submitted by this one once it leads
+    tokio::spawn({
+        let constraint_state = constraint_state_arc.clone();
+        let lease = lease.clone();
+        async move {
+            if lease.leading().await {
+                match constraint_state.read().await.import_journal().await {
+                    Ok(imported) => tracing::info!(imported, "Took over the journaled constraints"),
+                    Err(err) => tracing::error!(?err, "Failed to take over the journaled constraints"),
+                }
+            }
+        }
+    });
 
+    // Preconfirmations and constraints submissions, completed before shutting down
+    let mut in_flight = JoinSet::new();
     loop {
-        let constraint_stat_inner_clone = Arc::clone(&constraint_state_arc);
-        let mut constraint_state_inner = constraint_stat_inner_clone.lock().await;
-        // this will be unlocked after the second tokio::select slot is finished.
         tokio::select! {
             Some( CommitmentRequestEvent{req, res} ) = receiver.recv() => {
                 tracing::info!("received preconf request");
+                if !lease.is_leader() {
+                    let _ = res.send(Err(CommitmentRequestError::Standby));
+                    continue;
+                }
                 let constraint_state_clone = Arc::clone(&constraint_state_arc);
-                tokio::spawn(
-                    handle_preconfirmation_request(req, res, constraint_state_clone, keystores.clone(), relay_client.clone(), config.relay_url.clone())
+                in_flight.spawn(
+                    handle_preconfirmation_request(req, res, constraint_state_clone, signer.clone(), signer_pubkeys.load_full(), delegation_registry.clone(), receipt_signer.clone(), breakers.clone(), receipts.clone())
                 );
             },
-            Some(slot) = constraint_state_inner.commitment_deadline.wait() => {
+            Some(event) = deadlines.next(&slot_clock) => {
+                let (slot, early) = match event {
+                    DeadlineEvent::Reached(slot) => (slot, false),
+                    // Submitted with what we have while the relays may still take it
+                    DeadlineEvent::Early(slot) if relays.all_degraded() => {
+                        tracing::warn!(slot = slot + 1, "Every relay is degraded, submitting the constraints ahead of the deadline");
+                        ApiMetrics::increment_commitment_deadlines_count("early");
+                        (slot, true)
+                    }
+                    DeadlineEvent::Early(_) => continue,
+                };
+                if let Some(archive) = slot_archive.as_ref().filter(|_| !early) {
+                    archive.record(&SlotEvent::<BeaconResponses>::Deadline { slot: slot + 1, at_ms: slot_clock.now_ms() });
+                }
+                if !lease.is_leader() {
+                    tracing::warn!(slot, "Not the signing instance, skipping constraints submission");
+                    continue;
+                }
+                // Constraints must reach the relay before the slot starts, unless overridden
+                let until_slot_start =
+                    (slot_clock.slot_start_ms(slot + 1) - slot_clock.now_ms()).max(0) as u64;
+                let budget = DeadlineBudget::new(
+                    slot + 1,
+                    Duration::from_millis(config.deadline_budget_ms.unwrap_or(until_slot_start)),
+                    Duration::from_millis(config.deadline_stage_budget_ms),
+                );
                 let constraint_state_clone = Arc::clone(&constraint_state_arc);
-                tokio::spawn(
-                    handle_commitment_deadline(slot+1, constraint_state_clone, commit_boost_api.clone(), fallback_builder.clone())
+                in_flight.spawn(
+                    handle_commitment_deadline(slot+1, early, constraint_state_clone, commit_boost_api.clone(), fallback_builder.clone(), events.clone(), inclusion.clone(), submission_log.clone(), audit.clone(), budget)
                 );
             },
-            Some(FetchPayloadRequest { slot, response_tx }) = payload_rx.recv() => {
-                handle_local_payload_request(slot, fallback_builder.clone(), response_tx).await;
+            Some(FetchPayloadRequest { slot, response_tx }) = next_payload_request(&mut payload_rx) => {
+                if let Some(fallback_builder) = &fallback_builder {
+                    handle_local_payload_request(slot, fallback_builder.clone(), response_tx).await;
+                }
             },
             // Some(Ok(msg)) = read.next() => {
             //     if let tokio_tungstenite::tungstenite::protocol::Message::Text(text) = msg {
@@ -285,11 +1392,84 @@ async fn main() {
             //     }
             // },
             Ok(HeadEvent { slot, .. }) = head_event_listener.next_head() => {
+                let arrival = SystemTime::now();
+                if let Some(archive) = &slot_archive {
+                    // The arrival on the system clock, as observed by the slot clock
+                    let at_ms = arrival.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as i64;
+                    archive.record(&SlotEvent::<BeaconResponses>::Head { slot, at_ms });
+                }
+                // The deadline is armed by the slot clock whether or not the head event arrives,
+                // and only re-anchored here to the clock corrected with the arrival of the event
+                slot_clock.observe_head(slot, arrival);
+                deadlines.on_head(slot, &slot_clock);
                 let constraint_state_clone = Arc::clone(&constraint_state_arc);
                 tokio::spawn(
-                    handle_head_event(slot, constraint_state_clone)
+                    handle_head_event(
+                        slot,
+                        slot_clock.clone(),
+                        constraint_state_clone,
+                        events.clone(),
+                        payments.clone(),
+                        mempool.clone(),
+                        inclusion_list_sender.clone(),
+                    )
                 );
             },
+            Ok(BlockEvent { slot, .. }) = block_event_listener.next_block() => {
+                let inclusion = inclusion.clone();
+                tokio::spawn(async move { inclusion.finalize(slot).await });
+            },
+            Some(_) = in_flight.join_next() => {},
+            _ = lease.lost() => {
+                tracing::info!("Handed over to a newer instance, exiting");
+                break;
+            },
+            _ = shutdown.requested() => {
+                tracing::info!("Shutting down");
+                break;
+            },
         }
     }
+
+    // Refuse the requests from now on, and stop following the head
+    tokio::spawn(async move {
+        while let Some(CommitmentRequestEvent { res, .. }) = receiver.recv().await {
+            let _ = res.send(Err(CommitmentRequestError::ShuttingDown));
+        }
+    });
+    head_event_listener.stop();
+
+    let drain = async {
+        while in_flight.join_next().await.is_some() {}
+
+        // The constraints of the next slot are submitted now, its deadline won't be handled. A
+        // replaced instance leaves them to the newer one, which imports them from the journal
+        let slot = slot_clock.current_slot() + 1;
+        let pending = constraint_state_arc.read().await.blocks.contains(slot);
+        if pending && lease.is_leader() {
+            tracing::info!(slot, "Submitting the pending constraints before exiting");
+            let budget = DeadlineBudget::new(
+                slot,
+                SHUTDOWN_TIMEOUT,
+                Duration::from_millis(config.deadline_stage_budget_ms),
+            );
+            handle_commitment_deadline(
+                slot,
+                false,
+                constraint_state_arc.clone(),
+                commit_boost_api.clone(),
+                fallback_builder.clone(),
+                events.clone(),
+                inclusion.clone(),
+                submission_log.clone(),
+                audit.clone(),
+                budget,
+            )
+            .await;
+        }
+    };
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, drain).await.is_err() {
+        tracing::warn!("Timed out completing the in-flight work, exiting anyway");
+    }
+    tracing::info!("Shut down");
 }
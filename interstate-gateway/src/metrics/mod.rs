@@ -8,6 +8,8 @@ use tracing::info;
 use metrics::{counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram};
 use reth_primitives::TxType;
 
+pub mod runtime;
+
 //  Counters ----------------------------------------------------------------
 const HTTP_REQUESTS_COUNTER: &str = "http_requests_counter";
 const PROPOSED_LOCAL_BLOCKS_COUNTER: &str = "proposed_local_blocks_counter";
@@ -17,12 +19,66 @@ const APPROVED_COMMITMENTS_COUNTER: &str = "approved_commitments_counter";
 const PRECONFIRMED_TRANSACTIONS_COUNTER: &str = "preconfirmed_transactions_counter";
 const VALIDATION_ERRORS_COUNTER: &str = "validation_errors_counter";
 const GROSS_TIP_REVENUE_COUNTER: &str = "gross_tip_revenue_counter";
+const SIGNER_SATURATED_COUNTER: &str = "signer_saturated_counter";
+const CONSTRAINTS_SUBMISSIONS_COUNTER: &str = "constraints_submissions_counter";
+const RELAY_CONSTRAINTS_DISCREPANCY_COUNTER: &str = "relay_constraints_discrepancy_counter";
+const RELAY_ERRORS_COUNTER: &str = "relay_errors_counter";
+const FALLBACK_PAYLOAD_REJECTED_COUNTER: &str = "fallback_payload_rejected_counter";
+const ACCOUNT_STATES_LOOKUPS_COUNTER: &str = "account_states_lookups_counter";
+const ACCOUNT_STATES_EVICTIONS_COUNTER: &str = "account_states_evictions_counter";
+const RELAY_REQUESTS_THROTTLED_COUNTER: &str = "relay_requests_throttled_counter";
+const EXPIRED_DELEGATIONS_COUNTER: &str = "expired_delegations_counter";
+const PRICE_QUOTES_COUNTER: &str = "price_quotes_counter";
+const COMMITMENT_RECEIPTS_COUNTER: &str = "commitment_receipts_counter";
+const GOSSIPED_RECEIPTS_COUNTER: &str = "gossiped_receipts_counter";
+const SUBMISSION_RECOVERIES_COUNTER: &str = "submission_recoveries_counter";
+const COMMITMENTS_INCLUSION_COUNTER: &str = "commitments_inclusion_counter";
+const INCLUSION_LIST_COUNTER: &str = "inclusion_list_counter";
+const DEADLINE_STAGES_SKIPPED_COUNTER: &str = "deadline_stages_skipped_counter";
+const FORWARDED_REQUESTS_COUNTER: &str = "forwarded_requests_counter";
+const CONFIDENTIAL_REQUESTS_COUNTER: &str = "confidential_requests_counter";
+const RETRIES_COUNTER: &str = "retries_counter";
+const COMMITMENT_DEADLINES_COUNTER: &str = "commitment_deadlines_counter";
+const MEMPOOL_REPLACEMENTS_COUNTER: &str = "mempool_replacements_counter";
+const FALLBACK_VALUE_ESTIMATES_COUNTER: &str = "fallback_value_estimates_counter";
+const BLOB_MEMORY_REJECTIONS_COUNTER: &str = "blob_memory_rejections_counter";
+const BREAKER_REJECTIONS_COUNTER: &str = "breaker_rejections_counter";
+const RELAY_SUBMISSIONS_COUNTER: &str = "relay_submissions_counter";
+const RELAY_SUBMISSION_BYTES_COUNTER: &str = "relay_submission_bytes_counter";
+const RELAY_COMPRESSION_FALLBACKS_COUNTER: &str = "relay_compression_fallbacks_counter";
+const CAPACITY_RESERVATIONS_COUNTER: &str = "capacity_reservations_counter";
+const KEYSTORE_RELOADS_COUNTER: &str = "keystore_reloads_counter";
+const AUTHENTICATED_REQUESTS_COUNTER: &str = "authenticated_requests_counter";
+const AUTH_FAILURES_COUNTER: &str = "auth_failures_counter";
+const SEQUENCED_REQUESTS_COUNTER: &str = "sequenced_requests_counter";
+const RATE_LIMITED_REQUESTS_COUNTER: &str = "rate_limited_requests_counter";
+const REJECTED_BIDS_COUNTER: &str = "rejected_bids_counter";
+const PRIORITY_TIER_REQUESTS_COUNTER: &str = "priority_tier_requests_counter";
+const DELEGATION_SYNCS_COUNTER: &str = "delegation_syncs_counter";
 
 //  Gauges ------------------------------------------------------------------
 const LATEST_HEAD: &str = "latest_head";
+const SIGNER_INFLIGHT_SIGNINGS: &str = "signer_inflight_signings";
+const SLOT_CLOCK_DRIFT_MILLIS: &str = "slot_clock_drift_millis";
+const EVENTS_SUBSCRIBERS: &str = "events_subscribers";
+const EL_SYNC_LAG_BLOCKS: &str = "el_sync_lag_blocks";
+const DELEGATION_GAPS: &str = "delegation_gaps";
+const PENDING_BLOB_BYTES: &str = "pending_blob_bytes";
+const BREAKER_STATE: &str = "breaker_state";
+const RUNTIME_WORKERS: &str = "runtime_workers";
+const RUNTIME_ALIVE_TASKS: &str = "runtime_alive_tasks";
+const RUNTIME_GLOBAL_QUEUE_DEPTH: &str = "runtime_global_queue_depth";
+const KEYSTORE_KEYS: &str = "keystore_keys";
 
 //  Histograms --------------------------------------------------------------
 const HTTP_REQUESTS_DURATION_SECONDS: &str = "http_requests_duration_seconds";
+const RELAY_REQUEST_QUEUE_SECONDS: &str = "relay_request_queue_seconds";
+const DEADLINE_STAGE_DURATION_SECONDS: &str = "deadline_stage_duration_seconds";
+const COMMITMENT_INCLUSION_SECONDS: &str = "commitment_inclusion_seconds";
+const COMMITMENT_INCLUSION_SLOT_OFFSET: &str = "commitment_inclusion_slot_offset";
+const RELAY_SUBMISSION_SECONDS: &str = "relay_submission_seconds";
+const LOCK_WAIT_SECONDS: &str = "lock_wait_seconds";
+const RUNTIME_SCHEDULING_DELAY_SECONDS: &str = "runtime_scheduling_delay_seconds";
 const ACCOUNT_STATES: &str = "interstate_sidecar_account_states";
 /// Metrics for the commitments API.
 #[derive(Debug, Clone, Copy)]
@@ -58,15 +114,219 @@ impl ApiMetrics {
             GROSS_TIP_REVENUE_COUNTER,
             "Total number of gross tip revenue"
         );
+        describe_counter!(
+            SIGNER_SATURATED_COUNTER,
+            "Total number of signing requests rejected because the signer backend was saturated"
+        );
+        describe_counter!(
+            CONSTRAINTS_SUBMISSIONS_COUNTER,
+            "Total number of constraints submissions to the relay by status"
+        );
+        describe_counter!(
+            RELAY_CONSTRAINTS_DISCREPANCY_COUNTER,
+            "Total number of submitted constraints missing from the relay"
+        );
+        describe_counter!(
+            RELAY_ERRORS_COUNTER,
+            "Total number of requests rejected by the relay, by operation and error kind"
+        );
+        describe_counter!(
+            FALLBACK_PAYLOAD_REJECTED_COUNTER,
+            "Total number of fallback payloads refused for missing committed transactions"
+        );
+        describe_counter!(
+            ACCOUNT_STATES_LOOKUPS_COUNTER,
+            "Total number of account states cache lookups by result"
+        );
+        describe_counter!(
+            ACCOUNT_STATES_EVICTIONS_COUNTER,
+            "Total number of account states evicted from the cache by reason"
+        );
+        describe_counter!(
+            RELAY_REQUESTS_THROTTLED_COUNTER,
+            "Total number of outbound relay requests delayed by the rate limiter"
+        );
+        describe_counter!(
+            EXPIRED_DELEGATIONS_COUNTER,
+            "Total number of expired delegations skipped when signing constraints"
+        );
+        describe_counter!(
+            PRICE_QUOTES_COUNTER,
+            "Total number of price quotes issued, honored and rejected"
+        );
+        describe_counter!(
+            COMMITMENT_RECEIPTS_COUNTER,
+            "Total number of commitment receipts signed or failed to sign"
+        );
+        describe_counter!(
+            GOSSIPED_RECEIPTS_COUNTER,
+            "Total number of inclusion receipts pushed to the collector, failed or dropped"
+        );
+        describe_counter!(
+            SUBMISSION_RECOVERIES_COUNTER,
+            "Total number of in-flight constraints submissions recovered from the write-ahead log"
+        );
+        describe_counter!(
+            COMMITMENTS_INCLUSION_COUNTER,
+            "Total number of committed transactions included or missing in the proposed blocks"
+        );
+        describe_counter!(
+            INCLUSION_LIST_COUNTER,
+            "Total number of long pending transactions forced in our proposals or rejected"
+        );
+        describe_counter!(
+            DEADLINE_STAGES_SKIPPED_COUNTER,
+            "Total number of optional deadline stages skipped to meet the time budget"
+        );
+        describe_counter!(
+            FORWARDED_REQUESTS_COUNTER,
+            "Total number of requests forwarded to the gateway of the proposer"
+        );
+        describe_counter!(
+            CONFIDENTIAL_REQUESTS_COUNTER,
+            "Total number of requests received with encrypted transactions"
+        );
+        describe_counter!(
+            RETRIES_COUNTER,
+            "Total number of retried and exhausted calls to the beacon node, execution client and relay"
+        );
+        describe_counter!(
+            COMMITMENT_DEADLINES_COUNTER,
+            "Total number of commitment deadlines reached, by whether the head event of their slot arrived in time, or fired early as the relays degraded"
+        );
+        describe_counter!(
+            MEMPOOL_REPLACEMENTS_COUNTER,
+            "Total number of requested transactions replaced by a pending transaction paying more"
+        );
+        describe_counter!(
+            FALLBACK_VALUE_ESTIMATES_COUNTER,
+            "Total number of fallback blocks priced by the value estimator or the static value"
+        );
+        describe_counter!(
+            BLOB_MEMORY_REJECTIONS_COUNTER,
+            "Total number of requests refused because their blobs exceed the pending blob memory cap"
+        );
+        describe_counter!(
+            BREAKER_REJECTIONS_COUNTER,
+            "Total number of calls to a dependency failed fast by its open circuit breaker"
+        );
+        describe_counter!(
+            RELAY_SUBMISSIONS_COUNTER,
+            "Total number of constraints submissions to each relay, by outcome"
+        );
+        describe_counter!(
+            RELAY_SUBMISSION_BYTES_COUNTER,
+            "Total bytes of the constraints submissions to each relay, uncompressed and as sent"
+        );
+        describe_counter!(
+            RELAY_COMPRESSION_FALLBACKS_COUNTER,
+            "Total number of relays the constraints submissions stopped being compressed for"
+        );
+        describe_counter!(
+            CAPACITY_RESERVATIONS_COUNTER,
+            "Total number of capacity reservations registered or refused, and of slots charged to them"
+        );
+        describe_counter!(
+            KEYSTORE_RELOADS_COUNTER,
+            "Total number of reloads of the keystores, by whether the key set changed"
+        );
+        describe_counter!(
+            AUTHENTICATED_REQUESTS_COUNTER,
+            "Total number of authenticated commitment requests, by the label of their API key or jwt"
+        );
+        describe_counter!(
+            AUTH_FAILURES_COUNTER,
+            "Total number of commitment requests refused for missing or invalid credentials"
+        );
+        describe_counter!(
+            SEQUENCED_REQUESTS_COUNTER,
+            "Total number of commitment requests of the shared request log, by outcome"
+        );
+        describe_counter!(
+            RATE_LIMITED_REQUESTS_COUNTER,
+            "Total number of commitment requests refused above the rate of their sender or IP"
+        );
+        describe_counter!(
+            REJECTED_BIDS_COUNTER,
+            "Total number of relay bids refused for a malformed header or invalid inclusion proofs, by reason"
+        );
+        describe_counter!(
+            PRIORITY_TIER_REQUESTS_COUNTER,
+            "Total number of commitment requests by priority tier, committed or underpriced"
+        );
+        describe_counter!(
+            DELEGATION_SYNCS_COUNTER,
+            "Total number of syncs of the delegations of a proposer from the relay, synced or failed"
+        );
 
         // Gauges
         describe_gauge!(LATEST_HEAD, "Latest slot");
+        describe_gauge!(
+            SLOT_CLOCK_DRIFT_MILLIS,
+            "Estimated drift between the host clock and the beacon chain slot boundaries"
+        );
+        describe_gauge!(
+            EL_SYNC_LAG_BLOCKS,
+            "Number of blocks the execution client is behind the chain head"
+        );
+        describe_gauge!(
+            SIGNER_INFLIGHT_SIGNINGS,
+            "Number of in-flight signing requests per signer backend"
+        );
+        describe_gauge!(EVENTS_SUBSCRIBERS, "Number of clients connected to the events stream");
+        describe_gauge!(
+            DELEGATION_GAPS,
+            "Number of upcoming proposals of our validators without a usable delegation"
+        );
+        describe_gauge!(
+            PENDING_BLOB_BYTES,
+            "Bytes of blob sidecars held in memory for the pending constraints"
+        );
+        describe_gauge!(
+            BREAKER_STATE,
+            "State of the circuit breaker of each dependency, 0 closed, 1 half-open and 2 open"
+        );
+        describe_gauge!(RUNTIME_WORKERS, "Number of worker threads of the tokio runtime");
+        describe_gauge!(RUNTIME_ALIVE_TASKS, "Number of tasks alive on the tokio runtime");
+        describe_gauge!(
+            RUNTIME_GLOBAL_QUEUE_DEPTH,
+            "Number of tasks waiting in the global queue of the tokio runtime"
+        );
+        describe_gauge!(KEYSTORE_KEYS, "Number of keys loaded from the keystores");
 
         // Histograms
         describe_histogram!(
             HTTP_REQUESTS_DURATION_SECONDS,
             "Total duration of HTTP requests in seconds"
         );
+        describe_histogram!(
+            RELAY_REQUEST_QUEUE_SECONDS,
+            "Time outbound relay requests waited on the rate limiter in seconds"
+        );
+        describe_histogram!(
+            DEADLINE_STAGE_DURATION_SECONDS,
+            "Duration of the stages of the commitment deadline handler in seconds"
+        );
+        describe_histogram!(
+            COMMITMENT_INCLUSION_SECONDS,
+            "Time from the acceptance of a commitment to the block including it in seconds"
+        );
+        describe_histogram!(
+            COMMITMENT_INCLUSION_SLOT_OFFSET,
+            "Slots between the slot of a commitment and the block including it, zero if honored"
+        );
+        describe_histogram!(
+            RELAY_SUBMISSION_SECONDS,
+            "Duration of the constraints submissions to each relay in seconds, by content encoding"
+        );
+        describe_histogram!(
+            LOCK_WAIT_SECONDS,
+            "Time waited to acquire the locks on the hot path in seconds, by lock and access"
+        );
+        describe_histogram!(
+            RUNTIME_SCHEDULING_DELAY_SECONDS,
+            "Time from spawning a probe task to its first poll by the tokio runtime in seconds"
+        );
     }
 
     /// Counters ----------------------------------------------------------------
@@ -117,12 +377,199 @@ impl ApiMetrics {
         counter!(VALIDATION_ERRORS_COUNTER, &[("type", err_type)]).increment(1);
     }
 
+    pub fn increment_signer_saturated_count(backend: &'static str) {
+        counter!(SIGNER_SATURATED_COUNTER, &[("backend", backend)]).increment(1);
+    }
+
+    pub fn increment_constraints_submissions_count(status: &'static str) {
+        counter!(CONSTRAINTS_SUBMISSIONS_COUNTER, &[("status", status)]).increment(1);
+    }
+
+    pub fn increment_fallback_payload_rejected_count() {
+        counter!(FALLBACK_PAYLOAD_REJECTED_COUNTER).increment(1);
+    }
+
+    pub fn increment_account_states_lookups(hit: bool) {
+        let result = if hit { "hit" } else { "miss" };
+        counter!(ACCOUNT_STATES_LOOKUPS_COUNTER, &[("result", result)]).increment(1);
+    }
+
+    pub fn increment_account_states_evictions(reason: &'static str, count: u64) {
+        counter!(ACCOUNT_STATES_EVICTIONS_COUNTER, &[("reason", reason)]).increment(count);
+    }
+
+    pub fn increment_relay_constraints_discrepancy_count(missing: usize) {
+        counter!(RELAY_CONSTRAINTS_DISCREPANCY_COUNTER).increment(missing as u64);
+    }
+
+    pub fn increment_relay_errors_count(operation: &'static str, kind: &'static str) {
+        counter!(RELAY_ERRORS_COUNTER, &[("operation", operation), ("kind", kind)]).increment(1);
+    }
+
+    pub fn increment_relay_requests_throttled_count(relay: &str) {
+        counter!(RELAY_REQUESTS_THROTTLED_COUNTER, &[("relay", relay.to_string())]).increment(1);
+    }
+
+    pub fn increment_expired_delegations_count() {
+        counter!(EXPIRED_DELEGATIONS_COUNTER).increment(1);
+    }
+
+    pub fn increment_price_quotes_count(outcome: &'static str) {
+        counter!(PRICE_QUOTES_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_commitment_receipts_count(outcome: &'static str) {
+        counter!(COMMITMENT_RECEIPTS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_gossiped_receipts_count(outcome: &'static str, count: usize) {
+        counter!(GOSSIPED_RECEIPTS_COUNTER, &[("outcome", outcome)]).increment(count as u64);
+    }
+
+    pub fn increment_submission_recoveries_count(outcome: &'static str) {
+        counter!(SUBMISSION_RECOVERIES_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_commitments_inclusion_count(outcome: &'static str, count: u64) {
+        counter!(COMMITMENTS_INCLUSION_COUNTER, &[("outcome", outcome)]).increment(count);
+    }
+
+    pub fn increment_inclusion_list_count(outcome: &'static str) {
+        counter!(INCLUSION_LIST_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_deadline_stages_skipped_count(stage: &'static str) {
+        counter!(DEADLINE_STAGES_SKIPPED_COUNTER, &[("stage", stage)]).increment(1);
+    }
+
+    pub fn increment_forwarded_requests_count(outcome: &'static str) {
+        counter!(FORWARDED_REQUESTS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_confidential_requests_count(outcome: &'static str) {
+        counter!(CONFIDENTIAL_REQUESTS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_retries_count(operation: &'static str, outcome: &'static str) {
+        counter!(RETRIES_COUNTER, &[("operation", operation), ("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_breaker_rejections_count(dependency: &'static str) {
+        counter!(BREAKER_REJECTIONS_COUNTER, &[("dependency", dependency)]).increment(1);
+    }
+
+    pub fn increment_relay_submissions_count(relay: &str, outcome: &'static str) {
+        counter!(RELAY_SUBMISSIONS_COUNTER, &[("relay", relay.to_string()), ("outcome", outcome.to_string())])
+            .increment(1);
+    }
+
+    pub fn increment_relay_compression_fallbacks_count(relay: &str) {
+        counter!(RELAY_COMPRESSION_FALLBACKS_COUNTER, &[("relay", relay.to_string())]).increment(1);
+    }
+
+    pub fn increment_capacity_reservations_count(outcome: &'static str) {
+        counter!(CAPACITY_RESERVATIONS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_authenticated_requests_count(client: String) {
+        counter!(AUTHENTICATED_REQUESTS_COUNTER, &[("client", client)]).increment(1);
+    }
+
+    pub fn increment_auth_failures_count(reason: &'static str) {
+        counter!(AUTH_FAILURES_COUNTER, &[("reason", reason)]).increment(1);
+    }
+
+    pub fn increment_sequenced_requests_count(outcome: &'static str) {
+        counter!(SEQUENCED_REQUESTS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_rate_limited_requests_count(key: &'static str) {
+        counter!(RATE_LIMITED_REQUESTS_COUNTER, &[("key", key)]).increment(1);
+    }
+
+    pub fn increment_rejected_bids_count(reason: &'static str) {
+        counter!(REJECTED_BIDS_COUNTER, &[("reason", reason)]).increment(1);
+    }
+
+    pub fn increment_priority_tier_requests_count(tier: &'static str, outcome: &'static str) {
+        counter!(PRIORITY_TIER_REQUESTS_COUNTER, &[("tier", tier), ("outcome", outcome)])
+            .increment(1);
+    }
+
+    pub fn increment_delegation_syncs_count(outcome: &'static str) {
+        counter!(DELEGATION_SYNCS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_keystore_reloads_count(outcome: &'static str) {
+        counter!(KEYSTORE_RELOADS_COUNTER, &[("outcome", outcome)]).increment(1);
+    }
+
+    pub fn increment_commitment_deadlines_count(armed_by: &'static str) {
+        counter!(COMMITMENT_DEADLINES_COUNTER, &[("armed_by", armed_by)]).increment(1);
+    }
+
+    pub fn increment_mempool_replacements_count(action: &'static str) {
+        counter!(MEMPOOL_REPLACEMENTS_COUNTER, &[("action", action)]).increment(1);
+    }
+
+    pub fn increment_fallback_value_estimates_count(source: &'static str) {
+        counter!(FALLBACK_VALUE_ESTIMATES_COUNTER, &[("source", source)]).increment(1);
+    }
+
+    pub fn increment_blob_memory_rejections_count() {
+        counter!(BLOB_MEMORY_REJECTIONS_COUNTER).increment(1);
+    }
+
     /// Gauges ----------------------------------------------------------------
 
     pub fn set_latest_head(slot: u32) {
         gauge!(LATEST_HEAD).set(slot);
     }
 
+    pub fn set_slot_clock_drift(drift_ms: i64) {
+        gauge!(SLOT_CLOCK_DRIFT_MILLIS).set(drift_ms as f64);
+    }
+
+    pub fn set_el_sync_lag_blocks(lag_blocks: u64) {
+        gauge!(EL_SYNC_LAG_BLOCKS).set(lag_blocks as f64);
+    }
+
+    pub fn set_signer_inflight_signings(backend: &'static str, count: usize) {
+        gauge!(SIGNER_INFLIGHT_SIGNINGS, &[("backend", backend)]).set(count as f64);
+    }
+
+    pub fn set_events_subscribers(count: usize) {
+        gauge!(EVENTS_SUBSCRIBERS).set(count as f64);
+    }
+
+    pub fn set_delegation_gaps(reason: &'static str, count: usize) {
+        gauge!(DELEGATION_GAPS, &[("reason", reason)]).set(count as f64);
+    }
+
+    pub fn set_pending_blob_bytes(bytes: usize) {
+        gauge!(PENDING_BLOB_BYTES).set(bytes as f64);
+    }
+
+    pub fn set_breaker_state(dependency: &'static str, state: u8) {
+        gauge!(BREAKER_STATE, &[("dependency", dependency)]).set(state as f64);
+    }
+
+    pub fn set_runtime_workers(count: usize) {
+        gauge!(RUNTIME_WORKERS).set(count as f64);
+    }
+
+    pub fn set_runtime_alive_tasks(count: usize) {
+        gauge!(RUNTIME_ALIVE_TASKS).set(count as f64);
+    }
+
+    pub fn set_runtime_global_queue_depth(depth: usize) {
+        gauge!(RUNTIME_GLOBAL_QUEUE_DEPTH).set(depth as f64);
+    }
+
+    pub fn set_keystore_keys(count: usize) {
+        gauge!(KEYSTORE_KEYS).set(count as f64);
+    }
+
     /// Mixed ----------------------------------------------------------------
 
     /// Observes the duration of an HTTP request by storing it in a histogram,
@@ -133,6 +580,47 @@ impl ApiMetrics {
         histogram!(HTTP_REQUESTS_DURATION_SECONDS, &labels,).record(duration.as_secs_f64());
     }
 
+    pub fn observe_relay_request_queue_time(relay: &str, duration: Duration) {
+        histogram!(RELAY_REQUEST_QUEUE_SECONDS, &[("relay", relay.to_string())])
+            .record(duration.as_secs_f64());
+    }
+
+    pub fn observe_deadline_stage(stage: &'static str, duration: Duration) {
+        histogram!(DEADLINE_STAGE_DURATION_SECONDS, &[("stage", stage)])
+            .record(duration.as_secs_f64());
+    }
+
+    /// Record a constraints submission of `raw_bytes`, sent as `sent_bytes` in `encoding`.
+    pub fn observe_relay_submission(
+        relay: &str,
+        encoding: &'static str,
+        raw_bytes: usize,
+        sent_bytes: usize,
+        duration: Duration,
+    ) {
+        let relay = relay.to_string();
+        counter!(RELAY_SUBMISSION_BYTES_COUNTER, &[("relay", relay.clone()), ("body", "raw".to_string())])
+            .increment(raw_bytes as u64);
+        counter!(RELAY_SUBMISSION_BYTES_COUNTER, &[("relay", relay.clone()), ("body", "sent".to_string())])
+            .increment(sent_bytes as u64);
+        histogram!(RELAY_SUBMISSION_SECONDS, &[("relay", relay), ("encoding", encoding.to_string())])
+            .record(duration.as_secs_f64());
+    }
+
+    pub fn observe_lock_wait(lock: &'static str, access: &'static str, duration: Duration) {
+        histogram!(LOCK_WAIT_SECONDS, &[("lock", lock), ("access", access)])
+            .record(duration.as_secs_f64());
+    }
+
+    pub fn observe_runtime_scheduling_delay(delay: Duration) {
+        histogram!(RUNTIME_SCHEDULING_DELAY_SECONDS).record(delay.as_secs_f64());
+    }
+
+    pub fn observe_commitment_inclusion(latency: Duration, slot_offset: i64) {
+        histogram!(COMMITMENT_INCLUSION_SECONDS).record(latency.as_secs_f64());
+        histogram!(COMMITMENT_INCLUSION_SLOT_OFFSET).record(slot_offset as f64);
+    }
+
     pub fn set_account_states(count: usize) {
         gauge!(ACCOUNT_STATES).set(count as f64);
     }
@@ -152,6 +640,7 @@ pub fn run_metrics_server(metrics_port: u16) -> Result<()> {
     }
 
     ApiMetrics::describe_all();
+    runtime::spawn_runtime_monitor();
 
     Ok(())
 }
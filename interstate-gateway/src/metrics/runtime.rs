@@ -0,0 +1,74 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use super::ApiMetrics;
+
+/// Interval between two samples of the tokio runtime.
+pub const RUNTIME_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Await the acquisition of a lock on the hot path, recording the time waited for it by
+/// `lock` and `access`.
+pub async fn timed<F: Future>(lock: &'static str, access: &'static str, acquire: F) -> F::Output {
+    let started = Instant::now();
+    let guard = acquire.await;
+    ApiMetrics::observe_lock_wait(lock, access, started.elapsed());
+    guard
+}
+
+/// Sample the tokio runtime every [RUNTIME_SAMPLE_INTERVAL], until it shuts down.
+///
+/// Besides the stable runtime metrics, the delay before a freshly spawned task is polled is
+/// measured, growing as the workers saturate or get blocked.
+pub fn spawn_runtime_monitor() {
+    let handle = tokio::runtime::Handle::current();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(RUNTIME_SAMPLE_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+        loop {
+            interval.tick().await;
+            let metrics = handle.metrics();
+            ApiMetrics::set_runtime_workers(metrics.num_workers());
+            ApiMetrics::set_runtime_alive_tasks(metrics.num_alive_tasks());
+            ApiMetrics::set_runtime_global_queue_depth(metrics.global_queue_depth());
+
+            if let Ok(delay) = scheduling_delay().await {
+                ApiMetrics::observe_runtime_scheduling_delay(delay);
+            }
+        }
+    });
+}
+
+/// Time from spawning a task to its first poll.
+async fn scheduling_delay() -> Result<Duration, tokio::task::JoinError> {
+    let spawned = Instant::now();
+    tokio::spawn(async move { spawned.elapsed() }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::sync::Mutex;
+
+    use super::{scheduling_delay, timed};
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_timed_lock_and_scheduling_delay() {
+        let lock = Arc::new(Mutex::new(0u64));
+        let held = lock.clone().lock_owned().await;
+        let release = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(held);
+        });
+
+        // Waits for the holder while still handing out the guard
+        let mut guard = timed("test", "write", lock.lock()).await;
+        *guard += 1;
+        assert_eq!(*guard, 1);
+        release.await.unwrap();
+
+        assert!(scheduling_delay().await.unwrap() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,91 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::sync::watch;
+
+/// Time the in-flight requests and the last constraints submission have to complete once the
+/// shutdown is requested, within the default termination grace period of Kubernetes.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shutdown of the sidecar, requested by SIGINT or SIGTERM.
+///
+/// Once requested, the sidecar stops taking commitment requests and head updates, lets the
+/// requests in flight complete and submits the pending constraints of the next slot before
+/// exiting.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownController {
+    requested: Arc<watch::Sender<bool>>,
+}
+
+impl ShutdownController {
+    /// Request the shutdown on SIGINT or SIGTERM.
+    pub fn listen() -> Self {
+        let controller = Self::default();
+        let requested = controller.requested.clone();
+        tokio::spawn(async move {
+            let signal = wait_for_signal().await;
+            tracing::info!(signal, "Received the shutdown signal");
+            requested.send_replace(true);
+        });
+        controller
+    }
+
+    pub fn trigger(&self) {
+        self.requested.send_replace(true);
+    }
+
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Resolves once the shutdown is requested.
+    pub async fn requested(&self) {
+        let mut requested = self.requested.subscribe();
+        if requested.wait_for(|requested| *requested).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+async fn wait_for_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = sigterm.recv() => "SIGTERM",
+            },
+            Err(err) => {
+                tracing::error!(?err, "Failed to listen for SIGTERM");
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "SIGINT"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ShutdownController;
+
+    #[tokio::test]
+    async fn test_shutdown_requested() {
+        let shutdown = ShutdownController::default();
+        assert!(!shutdown.is_requested());
+        let waiting = shutdown.clone();
+        let waiter = tokio::spawn(async move { waiting.requested().await });
+
+        shutdown.trigger();
+        assert!(shutdown.is_requested());
+        tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+    }
+}
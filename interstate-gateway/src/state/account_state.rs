@@ -2,7 +2,10 @@ use std::ops::{Deref, DerefMut};
 
 use alloy_v092::primitives::{Address, U256};
 
-use crate::{metrics::ApiMetrics, utils::score_cache::ScoreCache};
+use crate::{
+    metrics::ApiMetrics,
+    utils::score_cache::{ScoreCache, ScoreCacheStats, SharedScoreCacheStats},
+};
 
 const GET_SCORE: isize = 4;
 const INSERT_SCORE: isize = 4;
@@ -18,6 +21,8 @@ pub struct AccountState {
 #[derive(Debug, Default)]
 pub struct AccountStateCache(
     pub ScoreCache<GET_SCORE, INSERT_SCORE, UPDATE_SCORE, Address, AccountState>,
+    /// Stats snapshot refreshed on every cache operation.
+    pub SharedScoreCacheStats,
 );
 
 impl Deref for AccountStateCache {
@@ -34,8 +39,42 @@ impl DerefMut for AccountStateCache {
 }
 
 impl AccountStateCache {
+    pub fn get(&mut self, address: &Address) -> Option<AccountState> {
+        let prev = self.0.stats();
+        let account_state = self.0.get(address).copied();
+        ApiMetrics::increment_account_states_lookups(account_state.is_some());
+        self.record_stats(prev);
+        account_state
+    }
+
     pub fn insert(&mut self, address: Address, account_state: AccountState) {
-        ApiMetrics::set_account_states(self.len());
+        let prev = self.0.stats();
         self.0.insert(address, account_state);
+        self.record_stats(prev);
+    }
+
+    /// Drop the expired account states and compact the cache.
+    pub fn gc(&mut self) {
+        let prev = self.0.stats();
+        let removed = self.0.gc();
+        if removed > 0 {
+            tracing::debug!(removed, "Garbage collected expired account states");
+        }
+        self.record_stats(prev);
+    }
+
+    /// Report the evictions since `prev` and refresh the shared stats snapshot.
+    fn record_stats(&self, prev: ScoreCacheStats) {
+        let stats = self.0.stats();
+        ApiMetrics::increment_account_states_evictions(
+            "capacity",
+            stats.evictions - prev.evictions,
+        );
+        ApiMetrics::increment_account_states_evictions(
+            "expired",
+            stats.expirations - prev.expirations,
+        );
+        ApiMetrics::set_account_states(stats.len);
+        *self.1.write() = stats;
     }
 }
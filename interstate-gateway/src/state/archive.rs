@@ -0,0 +1,258 @@
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use beacon_api_client::ProposerDuty;
+use ethereum_consensus::deneb::BeaconBlockHeader;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use super::{slot_clock::SlotClock, wal::read_entries, ConstraintState};
+use crate::config::ChainConfig;
+
+/// Responses of the beacon node fetched to move the state to a new head.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BeaconResponses {
+    /// Header of the head block, missing if it couldn't be fetched.
+    pub header: Option<BeaconBlockHeader>,
+    /// Proposer duties of the epoch of the head, only fetched when it starts a new epoch.
+    pub proposer_duties: Option<Vec<ProposerDuty>>,
+    /// Proposer duties of the next epoch, fetched along with the ones of the epoch of the head.
+    pub next_proposer_duties: Option<Vec<ProposerDuty>>,
+    /// Why a response is missing.
+    pub error: Option<String>,
+}
+
+/// Record of the [SlotArchive], one JSON object per line. Times are in milliseconds since
+/// the unix epoch.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SlotEvent<B = BeaconResponses> {
+    /// The sidecar started following the chain, with the parameters of its slot clock.
+    Started { genesis_time: u64, slot_time: u64, drift_threshold_ms: u64 },
+    /// The head event of `slot` arrived, on the system clock.
+    Head { slot: u64, at_ms: i64 },
+    /// The beacon node responded to the queries made to move to the head `slot`.
+    Beacon { slot: u64, responses: B },
+    /// The commitment deadline was reached on the slot clock, the constraints of `slot` being
+    /// due.
+    Deadline { slot: u64, at_ms: i64 },
+}
+
+/// Archive of the slot handling of the sidecar: the head events and the commitment deadlines
+/// as they fire, and the beacon node responses the state is updated from.
+///
+/// It is replayed against a [ConstraintState] with [replay], the recorded responses standing in
+/// for the beacon node, to reproduce the slot handling bugs reported by operators.
+#[derive(Debug, Clone)]
+pub struct SlotArchive {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl SlotArchive {
+    /// Open the archive at `path`, appending to the events already recorded.
+    pub fn open(path: PathBuf) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self { path, file: Arc::new(Mutex::new(file)) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append an event. Failures are only logged, the archive being a debugging aid.
+    pub fn record<B: Serialize>(&self, event: &SlotEvent<B>) {
+        let appended = serde_json::to_vec(event).map_err(io::Error::from).and_then(|mut line| {
+            line.push(b'\n');
+            self.file.lock().write_all(&line)
+        });
+        if let Err(err) = appended {
+            tracing::warn!(?err, path = %self.path.display(), "Failed to archive a slot event");
+        }
+    }
+}
+
+/// Read the events of the archive at `path`.
+pub fn read_archive(path: &Path) -> io::Result<Vec<SlotEvent>> {
+    read_entries(path)
+}
+
+/// State of the slot handling once an event was replayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayStep {
+    pub event: String,
+    pub latest_slot: u64,
+    pub epoch: u64,
+    /// Number of proposer duties known for the epoch.
+    pub proposers: usize,
+    pub clock_offset_ms: i64,
+}
+
+impl fmt::Display for ReplayStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<56} head {} epoch {} proposers {} clock offset {}ms",
+            self.event, self.latest_slot, self.epoch, self.proposers, self.clock_offset_ms
+        )
+    }
+}
+
+/// Replay the archived `events` against `state`, as the sidecar handled them, returning the
+/// state after each of them.
+///
+/// The head events correct the slot clock at their recorded arrival, and the state moves to
+/// the new heads with the recorded beacon node responses, so no beacon node is queried.
+pub fn replay(state: &mut ConstraintState, events: Vec<SlotEvent>) -> Vec<ReplayStep> {
+    let mut clock = state.slot_clock.clone();
+    let mut steps = Vec::with_capacity(events.len());
+
+    for event in events {
+        let event = match event {
+            SlotEvent::Started { genesis_time, slot_time, drift_threshold_ms } => {
+                let chain = ChainConfig { slot_time, ..state.config.clone() };
+                clock = SlotClock::for_chain(genesis_time, &chain, drift_threshold_ms);
+                state.slot_clock = clock.clone();
+                format!("started, genesis at {genesis_time}")
+            }
+            SlotEvent::Head { slot, at_ms } => {
+                let arrival = UNIX_EPOCH + Duration::from_millis(at_ms.max(0) as u64);
+                let drift = clock.observe_head(slot, arrival);
+                let delay = at_ms - clock.slot_start_ms(slot);
+                format!("head event {slot} at {at_ms} (+{delay}ms, drift {drift}ms)")
+            }
+            SlotEvent::Beacon { slot, responses } => {
+                let event = match &responses.error {
+                    Some(err) => format!("beacon responses {slot}, failed: {err}"),
+                    None => format!("beacon responses {slot}"),
+                };
+                state.slot_clock = clock.clone();
+                state.apply_beacon_responses(slot, responses);
+                event
+            }
+            SlotEvent::Deadline { slot, at_ms } => match state.commitment_deadline_ms(slot) {
+                Some(deadline_ms) => {
+                    format!("deadline {slot} at {at_ms} ({:+}ms)", at_ms - deadline_ms)
+                }
+                None => format!("deadline {slot} at {at_ms}, no known proposer"),
+            },
+        };
+
+        steps.push(ReplayStep {
+            event,
+            latest_slot: state.latest_slot,
+            epoch: state.current_epoch.value,
+            proposers: state.current_epoch.proposer_duties.len(),
+            clock_offset_ms: clock.offset_ms(),
+        });
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use alloy::primitives::hex;
+    use beacon_api_client::ProposerDuty;
+    use ethereum_consensus::deneb::BeaconBlockHeader;
+    use reqwest::Url;
+
+    use super::{read_archive, replay, BeaconResponses, SlotArchive, SlotEvent};
+    use crate::{
+        config::ChainConfig,
+        state::{
+            execution::{ExecutionSnapshot, ExecutionState},
+            fetcher::ClientState,
+            slot_clock::SlotClock,
+            ConstraintState,
+        },
+    };
+
+    fn archive_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("archive-test-{name}-{}.jsonl", std::process::id()))
+    }
+
+    fn duty(slot: u64) -> ProposerDuty {
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let pubkey = hex::encode_prefixed(sk.sk_to_pk().to_bytes());
+        serde_json::from_value(serde_json::json!({
+            "pubkey": pubkey,
+            "validator_index": "1",
+            "slot": slot.to_string(),
+        }))
+        .unwrap()
+    }
+
+    fn state() -> ConstraintState {
+        // Never queried, the responses are replayed
+        let url = Url::parse("http://127.0.0.1:1").unwrap();
+        let execution = ExecutionState::from_head(
+            ClientState::new(url.clone()),
+            Default::default(),
+            30_000_000,
+            1,
+            ExecutionSnapshot::default(),
+        );
+        ConstraintState::new(
+            beacon_api_client::mainnet::Client::new(url),
+            ChainConfig::default().get_commitment_deadline_duration(),
+            SlotClock::new(0, 12, 500),
+            execution,
+            &ChainConfig::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_replay_archived_slots() {
+        let path = archive_path("replay");
+        let _ = std::fs::remove_file(&path);
+
+        let genesis_ms = 1_000_000;
+        let slot_time = ChainConfig::default().slot_time;
+        let archive = SlotArchive::open(path.clone()).unwrap();
+        archive.record(&SlotEvent::<BeaconResponses>::Started {
+            genesis_time: genesis_ms as u64 / 1_000,
+            slot_time,
+            drift_threshold_ms: 500,
+        });
+        for slot in [32, 33] {
+            let at_ms = genesis_ms + (slot * slot_time * 1_000) as i64 + 2_000;
+            archive.record(&SlotEvent::<BeaconResponses>::Head { slot, at_ms });
+            let responses = BeaconResponses {
+                header: Some(BeaconBlockHeader { slot, ..Default::default() }),
+                // Fetched with the first head of the epoch
+                proposer_duties: (slot == 32).then(|| (32..64).map(duty).collect()),
+                next_proposer_duties: None,
+                error: None,
+            };
+            archive.record(&SlotEvent::Beacon { slot, responses: &responses });
+        }
+        // The head of slot 34 couldn't be fetched
+        archive.record(&SlotEvent::<BeaconResponses>::Head { slot: 34, at_ms: 1_410_000 });
+        archive.record(&SlotEvent::Beacon {
+            slot: 34,
+            responses: &BeaconResponses { error: Some("timeout".into()), ..Default::default() },
+        });
+        archive.record(&SlotEvent::<BeaconResponses>::Deadline { slot: 35, at_ms: 1_418_000 });
+
+        let events = read_archive(&path).unwrap();
+        assert_eq!(events.len(), 7);
+        let steps = replay(&mut state(), events);
+
+        assert_eq!((steps[2].latest_slot, steps[2].epoch, steps[2].proposers), (32, 1, 32));
+        assert_eq!((steps[4].latest_slot, steps[4].proposers), (33, 32));
+        // The failed update kept the previous head
+        assert_eq!(steps[6].latest_slot, 33);
+        assert!(steps[6].event.starts_with("deadline 35"));
+        assert!(steps.iter().all(|step| step.clock_offset_ms == 0));
+
+        let _ = std::fs::remove_file(path);
+    }
+}
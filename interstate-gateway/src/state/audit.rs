@@ -0,0 +1,289 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy::primitives::{B256, U256};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    config::ChainConfig,
+    constraints::{
+        builder::{GetPayloadResponse, SignedBuilderBid},
+        SignedConstraints,
+    },
+};
+
+/// Number of epochs kept in the audit trail.
+const AUDIT_RETENTION_EPOCHS: u64 = 256;
+
+/// Bid returned to the proposer of a slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AuditedBid {
+    /// Relay, or builder proxy, the bid was fetched from, `local` for the fallback payload.
+    pub relay: String,
+    #[schema(value_type = String)]
+    pub value_wei: U256,
+    /// Hash of the execution payload header of the bid.
+    #[schema(value_type = String)]
+    pub header_hash: B256,
+}
+
+impl AuditedBid {
+    pub fn new(relay: impl Into<String>, bid: &SignedBuilderBid) -> Self {
+        Self {
+            relay: relay.into(),
+            value_wei: bid.message.value.to_string().parse().unwrap_or_default(),
+            header_hash: B256::from_slice(bid.message.header.block_hash.as_ref()),
+        }
+    }
+}
+
+/// Audit chain of a proposed slot, from the constraints we signed to the delivered payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SlotAudit {
+    pub slot: u64,
+    /// Id of the batch of constraints submitted to the relay, see [batch_id](super::wal::batch_id).
+    #[schema(value_type = Option<String>)]
+    pub constraints_batch: Option<B256>,
+    #[schema(value_type = Vec<Object>)]
+    pub constraints: Vec<SignedConstraints>,
+    pub bid: Option<AuditedBid>,
+    /// Block hash of the payload delivered to the proposer.
+    #[schema(value_type = Option<String>)]
+    pub payload_hash: Option<B256>,
+}
+
+impl SlotAudit {
+    /// Whether the delivered payload is the one of the bid, `None` until both are known.
+    pub fn payload_matches_bid(&self) -> Option<bool> {
+        Some(self.bid.as_ref()?.header_hash == self.payload_hash?)
+    }
+}
+
+/// Record of the audit log, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum AuditEntry {
+    Constraints { slot: u64, batch: B256, constraints: Vec<SignedConstraints> },
+    Bid { slot: u64, bid: AuditedBid },
+    Payload { slot: u64, block_hash: B256 },
+}
+
+impl AuditEntry {
+    fn slot(&self) -> u64 {
+        match self {
+            Self::Constraints { slot, .. } | Self::Bid { slot, .. } | Self::Payload { slot, .. } => {
+                *slot
+            }
+        }
+    }
+
+    fn apply(self, audits: &mut BTreeMap<u64, SlotAudit>) {
+        let slot = self.slot();
+        let audit = audits.entry(slot).or_insert_with(|| SlotAudit { slot, ..Default::default() });
+        match self {
+            Self::Constraints { batch, constraints, .. } => {
+                audit.constraints_batch = Some(batch);
+                audit.constraints = constraints;
+            }
+            Self::Bid { bid, .. } => audit.bid = Some(bid),
+            Self::Payload { block_hash, .. } => audit.payload_hash = Some(block_hash),
+        }
+    }
+}
+
+/// Audit trail of our proposals, linking the constraints submitted to the relay, the bid
+/// returned to the proposer and the payload delivered, for dispute resolution.
+///
+/// Kept in memory, and appended to the audit log when set so that it survives restarts.
+#[derive(Debug, Clone)]
+pub struct AuditTrail {
+    audits: Arc<RwLock<BTreeMap<u64, SlotAudit>>>,
+    log: Option<Arc<Mutex<File>>>,
+    /// Slots of an epoch of the chain, the audits are retained for whole epochs.
+    slots_per_epoch: u64,
+}
+
+impl Default for AuditTrail {
+    fn default() -> Self {
+        Self::new(ChainConfig::default().slots_per_epoch)
+    }
+}
+
+impl AuditTrail {
+    /// An audit trail kept in memory only.
+    pub fn new(slots_per_epoch: u64) -> Self {
+        Self { audits: Default::default(), log: None, slots_per_epoch }
+    }
+
+    /// Open the audit log at `path`, loading the audits still within the retention window of
+    /// `current_slot`. The log is compacted to these audits.
+    pub fn open(path: PathBuf, current_slot: u64, slots_per_epoch: u64) -> io::Result<Self> {
+        let oldest = oldest_retained(current_slot, slots_per_epoch);
+        let mut audits = BTreeMap::new();
+        for entry in read_entries(&path)? {
+            if entry.slot() >= oldest {
+                entry.apply(&mut audits);
+            }
+        }
+
+        // Replace the log atomically, a crash while compacting keeps the previous one
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        let mut compacted = Vec::new();
+        for entry in audits.values().flat_map(entries_of) {
+            serde_json::to_writer(&mut compacted, &entry)?;
+            compacted.push(b'\n');
+        }
+        let mut file = File::create(&tmp)?;
+        file.write_all(&compacted)?;
+        file.sync_all()?;
+        std::fs::rename(tmp, &path)?;
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+        Ok(Self {
+            audits: Arc::new(RwLock::new(audits)),
+            log: Some(Arc::new(Mutex::new(file))),
+            slots_per_epoch,
+        })
+    }
+
+    pub fn slot(&self, slot: u64) -> Option<SlotAudit> {
+        self.audits.read().get(&slot).cloned()
+    }
+
+    /// Record the constraints of `slot` submitted to the relay, of id `batch`.
+    pub fn record_constraints(&self, slot: u64, batch: B256, constraints: &[SignedConstraints]) {
+        self.record(AuditEntry::Constraints { slot, batch, constraints: constraints.to_vec() });
+    }
+
+    /// Record the bid returned to the proposer of `slot`.
+    pub fn record_bid(&self, slot: u64, bid: AuditedBid) {
+        self.record(AuditEntry::Bid { slot, bid });
+    }
+
+    /// Record the payload delivered to the proposer of `slot`.
+    pub fn record_payload(&self, slot: u64, payload: &GetPayloadResponse) {
+        let block_hash = B256::from_slice(payload.block_hash().as_ref());
+        self.record(AuditEntry::Payload { slot, block_hash });
+    }
+
+    /// Drop the audits older than the retention window. They are dropped from the log when it
+    /// is next opened.
+    pub fn prune(&self, current_slot: u64) {
+        let oldest = oldest_retained(current_slot, self.slots_per_epoch);
+        self.audits.write().retain(|slot, _| *slot >= oldest);
+    }
+
+    fn record(&self, entry: AuditEntry) {
+        if let Some(log) = &self.log {
+            if let Err(err) = append(log, &entry) {
+                tracing::error!(?err, slot = entry.slot(), "Failed to append to the audit log");
+            }
+        }
+        entry.apply(&mut self.audits.write());
+    }
+}
+
+fn oldest_retained(current_slot: u64, slots_per_epoch: u64) -> u64 {
+    current_slot.saturating_sub(AUDIT_RETENTION_EPOCHS * slots_per_epoch)
+}
+
+/// The entries recording `audit`.
+fn entries_of(audit: &SlotAudit) -> Vec<AuditEntry> {
+    let slot = audit.slot;
+    let mut entries = Vec::new();
+    if let Some(batch) = audit.constraints_batch {
+        entries.push(AuditEntry::Constraints { slot, batch, constraints: audit.constraints.clone() });
+    }
+    if let Some(bid) = &audit.bid {
+        entries.push(AuditEntry::Bid { slot, bid: bid.clone() });
+    }
+    if let Some(block_hash) = audit.payload_hash {
+        entries.push(AuditEntry::Payload { slot, block_hash });
+    }
+    entries
+}
+
+fn append(log: &Mutex<File>, entry: &AuditEntry) -> io::Result<()> {
+    let mut line = serde_json::to_vec(entry)?;
+    line.push(b'\n');
+
+    let mut file = log.lock();
+    file.write_all(&line)?;
+    file.sync_data()
+}
+
+/// Read the entries of the log, skipping the last one if it was only partially written.
+fn read_entries(path: &Path) -> io::Result<Vec<AuditEntry>> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut entries = Vec::new();
+    let mut lines = content.split(|byte| *byte == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_slice(line) {
+            Ok(entry) => entries.push(entry),
+            // The last line has no newline when the sidecar crashed while appending it
+            Err(_) if lines.peek().is_none() => {
+                tracing::warn!(path = %path.display(), "Ignoring a partially written entry");
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::{B256, U256};
+
+    use super::{AuditTrail, AuditedBid};
+    use crate::state::wal::batch_id;
+    use crate::constraints::{ConstraintsMessage, SignedConstraints};
+
+    #[test]
+    fn test_audit_trail_survives_restart() {
+        let path = std::env::temp_dir().join(format!("audit-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let constraints = vec![SignedConstraints {
+            message: ConstraintsMessage { slot: 100, ..Default::default() },
+            signature: Default::default(),
+        }];
+        let bid = AuditedBid {
+            relay: "local".to_string(),
+            value_wei: U256::from(7),
+            header_hash: B256::repeat_byte(1),
+        };
+
+        let trail = AuditTrail::open(path.clone(), 90, 32).unwrap();
+        trail.record_constraints(100, batch_id(&constraints), &constraints);
+        trail.record_bid(100, bid.clone());
+        assert_eq!(trail.slot(100).unwrap().payload_matches_bid(), None);
+
+        let reopened = AuditTrail::open(path.clone(), 101, 32).unwrap();
+        let audit = reopened.slot(100).unwrap();
+        assert_eq!(audit.constraints, constraints);
+        assert_eq!(audit.bid, Some(bid));
+        assert!(reopened.slot(99).is_none());
+
+        // Audits past the retention window are dropped on the next open
+        drop((trail, reopened));
+        let pruned = AuditTrail::open(path.clone(), 100 + 257 * 32, 32).unwrap();
+        assert!(pruned.slot(100).is_none());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::metrics::ApiMetrics;
+
+/// Default time a single stage of the deadline handler may take before the optional stages
+/// are skipped.
+pub const DEFAULT_DEADLINE_STAGE_BUDGET_MILLIS: u64 = 1_000;
+
+/// Time kept for an optional stage to run, below which it is skipped.
+const MIN_OPTIONAL_STAGE_MILLIS: u64 = 250;
+
+/// Timing of a stage of the deadline handler.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StageReport {
+    pub stage: &'static str,
+    pub elapsed_ms: u64,
+    pub over_budget: bool,
+    pub skipped: bool,
+}
+
+/// Outcome of the deadline handler of a slot, logged once it's done.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetReport {
+    pub slot: u64,
+    pub budget_ms: u64,
+    pub elapsed_ms: u64,
+    pub stages: Vec<StageReport>,
+}
+
+/// Time budget of the critical path from the commitment deadline to the relay submission.
+///
+/// The required stages always run, within the remaining budget. The optional stages are
+/// skipped once a stage overran its own budget or when there is no time left for them, so
+/// that the constraints reach the relay before the cutoff of the slot.
+#[derive(Debug)]
+pub struct DeadlineBudget {
+    slot: u64,
+    started_at: Instant,
+    total: Duration,
+    stage_budget: Duration,
+    exhausted: bool,
+    stages: Vec<StageReport>,
+}
+
+impl DeadlineBudget {
+    pub fn new(slot: u64, total: Duration, stage_budget: Duration) -> Self {
+        Self {
+            slot,
+            started_at: Instant::now(),
+            total,
+            stage_budget,
+            exhausted: false,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Time left before the cutoff.
+    pub fn remaining(&self) -> Duration {
+        self.total.saturating_sub(self.started_at.elapsed())
+    }
+
+    /// Run a required stage.
+    pub async fn stage<F: Future>(&mut self, stage: &'static str, fut: F) -> F::Output {
+        let started_at = Instant::now();
+        let output = fut.await;
+        self.record(stage, started_at.elapsed());
+        output
+    }
+
+    /// Run an optional stage if the budget allows it, returning `None` if it was skipped.
+    pub async fn optional<F: Future>(&mut self, stage: &'static str, fut: F) -> Option<F::Output> {
+        if self.exhausted || self.remaining() < Duration::from_millis(MIN_OPTIONAL_STAGE_MILLIS) {
+            tracing::warn!(slot = self.slot, stage, "Deadline budget exhausted, skipping stage");
+            ApiMetrics::increment_deadline_stages_skipped_count(stage);
+            self.stages.push(StageReport { stage, elapsed_ms: 0, over_budget: false, skipped: true });
+            return None;
+        }
+
+        Some(self.stage(stage, fut).await)
+    }
+
+    fn record(&mut self, stage: &'static str, elapsed: Duration) {
+        let over_budget = elapsed > self.stage_budget;
+        if over_budget {
+            tracing::warn!(
+                slot = self.slot,
+                stage,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Deadline stage exceeded its budget"
+            );
+            self.exhausted = true;
+        }
+
+        ApiMetrics::observe_deadline_stage(stage, elapsed);
+        self.stages.push(StageReport {
+            stage,
+            elapsed_ms: elapsed.as_millis() as u64,
+            over_budget,
+            skipped: false,
+        });
+    }
+
+    /// Finish the budget, logging the report of the slot.
+    pub fn finish(self) -> BudgetReport {
+        let report = BudgetReport {
+            slot: self.slot,
+            budget_ms: self.total.as_millis() as u64,
+            elapsed_ms: self.started_at.elapsed().as_millis() as u64,
+            stages: self.stages,
+        };
+
+        match serde_json::to_string(&report) {
+            Ok(json) => tracing::info!(slot = report.slot, report = %json, "Deadline budget report"),
+            Err(err) => tracing::error!(?err, "Failed to serialize the deadline budget report"),
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::DeadlineBudget;
+
+    #[tokio::test]
+    async fn test_optional_stages_skipped_once_over_budget() {
+        let mut budget =
+            DeadlineBudget::new(10, Duration::from_secs(4), Duration::from_millis(50));
+
+        budget.stage("submit", async {}).await;
+        assert_eq!(budget.optional("confirm", async { 1 }).await, Some(1));
+
+        budget.stage("fallback", tokio::time::sleep(Duration::from_millis(60))).await;
+        assert_eq!(budget.optional("collector", async { 1 }).await, None);
+
+        let report = budget.finish();
+        assert_eq!(report.stages.len(), 4);
+        assert!(report.stages[2].over_budget);
+        assert!(report.stages[3].skipped);
+    }
+}
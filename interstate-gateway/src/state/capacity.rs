@@ -0,0 +1,353 @@
+use std::ops::Range;
+
+use alloy::primitives::B256;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{
+    execution::SharedExecutionSnapshot, reservations::ReservationBook, shards::SlotShards,
+    slot_clock::SlotClock,
+    DEFAULT_MAX_COMMITMENTS_IN_BLOCK, DEFAULT_MAX_COMMITMENT_GAS, DEFAULT_MAX_PENDING_BLOB_BYTES,
+};
+use crate::{
+    commitment::{forward::SharedProposers, quote::Quoter},
+    constraints::{SignedConstraints, TransactionExt},
+    state::blob_count,
+};
+
+/// Most epochs of slots reported by a single capacity query.
+pub const MAX_CAPACITY_EPOCHS: u64 = 2;
+
+/// Gas of the transaction priced in the capacity reports, a simple transfer.
+const PRICED_GAS: u64 = 21_000;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum CapacityError {
+    #[error("invalid slot range {0:?}, expected `a..b`")]
+    InvalidRange(String),
+    #[error("{requested} slots requested, at most {max} can be queried at once")]
+    TooManySlots { requested: u64, max: u64 },
+}
+
+/// Parse a slot range given as `a..b`, `b` excluded, or as a single slot, of up to
+/// `max_slots` slots.
+pub fn parse_slot_range(range: &str, max_slots: u64) -> Result<Range<u64>, CapacityError> {
+    let invalid = || CapacityError::InvalidRange(range.to_string());
+    let parse = |slot: &str| slot.trim().parse::<u64>().map_err(|_| invalid());
+    let (start, end) = match range.split_once("..") {
+        Some((start, end)) => (parse(start)?, parse(end)?),
+        None => {
+            let slot = parse(range)?;
+            (slot, slot.saturating_add(1))
+        }
+    };
+
+    if end <= start {
+        return Err(invalid());
+    }
+    if end - start > max_slots {
+        return Err(CapacityError::TooManySlots { requested: end - start, max: max_slots });
+    }
+    Ok(start..end)
+}
+
+/// Caps of the commitments the constraint state accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityLimits {
+    pub max_commitments_in_block: usize,
+    pub max_commitment_gas: u64,
+    pub max_pending_blob_bytes: usize,
+}
+
+impl Default for CapacityLimits {
+    fn default() -> Self {
+        Self {
+            max_commitments_in_block: DEFAULT_MAX_COMMITMENTS_IN_BLOCK,
+            max_commitment_gas: DEFAULT_MAX_COMMITMENT_GAS,
+            max_pending_blob_bytes: DEFAULT_MAX_PENDING_BLOB_BYTES,
+        }
+    }
+}
+
+/// Room left for commitments in a slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SlotCapacity {
+    pub slot: u64,
+    /// Proposer of the slot, known for the slots of the current epoch.
+    #[schema(value_type = Option<String>)]
+    pub proposer: Option<BlsPublicKey>,
+    /// Whether commitments to the slot can be requested: its proposer is known and it hasn't
+    /// started yet.
+    pub open: bool,
+    pub commitments: usize,
+    /// Transactions that can still be committed in the slot.
+    pub remaining_commitments: usize,
+    pub committed_gas: u64,
+    /// Gas reserved by the rollup sequencers and not used yet, not available to the others.
+    pub reserved_gas: u64,
+    pub remaining_gas: u64,
+    /// Bytes of blob sidecars that can still be committed, shared with the other pending slots.
+    pub remaining_blob_bytes: usize,
+    /// Minimum priority fee in wei of a transfer committed in the slot, at its committed gas.
+    pub min_priority_fee: Option<u64>,
+}
+
+/// Live capacity of the gateway, queried by the router to balance the commitment requests
+/// across the gateways.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CapacityReport {
+    pub current_slot: u64,
+    #[schema(value_type = u64)]
+    pub basefee: u128,
+    #[schema(value_type = u64)]
+    pub blob_basefee: u128,
+    pub slots: Vec<SlotCapacity>,
+}
+
+/// A constraints message committed to a slot, as submitted to the relay at the deadline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct CommittedConstraints {
+    /// Delegatee key the message is signed with.
+    #[schema(value_type = String)]
+    pub signer: BlsPublicKey,
+    pub tx_hashes: Vec<B256>,
+    /// Gas limit of the transactions of the message.
+    pub gas: u64,
+    pub blobs: usize,
+    #[schema(value_type = Object)]
+    pub signed_constraints: SignedConstraints,
+}
+
+impl From<&SignedConstraints> for CommittedConstraints {
+    fn from(signed: &SignedConstraints) -> Self {
+        let transactions = &signed.message.transactions;
+        Self {
+            signer: signed.message.pubkey.clone(),
+            tx_hashes: transactions.iter().map(|c| B256::from_slice(c.tx.hash().as_slice())).collect(),
+            gas: transactions.iter().map(|c| c.tx.gas_limit()).sum(),
+            blobs: blob_count(transactions),
+            signed_constraints: signed.clone(),
+        }
+    }
+}
+
+/// Constraints promised in a slot so far, for the relays and auditors to verify before the
+/// deadline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SlotConstraints {
+    pub slot: u64,
+    pub constraints: Vec<CommittedConstraints>,
+}
+
+/// Totals of the constraints promised in a slot so far.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct SlotSummary {
+    pub slot: u64,
+    #[schema(value_type = Option<String>)]
+    pub proposer: Option<BlsPublicKey>,
+    /// Constraints messages signed for the slot.
+    pub constraints: usize,
+    pub transactions: usize,
+    pub committed_gas: u64,
+    pub blobs: usize,
+    pub blob_bytes: usize,
+}
+
+/// Read-only view of the capacity of the upcoming slots, served by the commitments API
+/// without locking the constraint state.
+#[derive(Debug, Clone)]
+pub struct CapacityView {
+    blocks: SlotShards,
+    proposers: SharedProposers,
+    quoter: Quoter,
+    execution: SharedExecutionSnapshot,
+    slot_clock: SlotClock,
+    limits: CapacityLimits,
+    reservations: ReservationBook,
+}
+
+impl CapacityView {
+    pub fn new(
+        blocks: SlotShards,
+        proposers: SharedProposers,
+        quoter: Quoter,
+        execution: SharedExecutionSnapshot,
+        slot_clock: SlotClock,
+    ) -> Self {
+        Self {
+            blocks,
+            proposers,
+            quoter,
+            execution,
+            slot_clock,
+            limits: Default::default(),
+            reservations: Default::default(),
+        }
+    }
+
+    pub fn with_reservations(mut self, reservations: ReservationBook) -> Self {
+        self.reservations = reservations;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: CapacityLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Most slots reported by a single query, [MAX_CAPACITY_EPOCHS] of the chain.
+    pub fn max_slots(&self) -> u64 {
+        MAX_CAPACITY_EPOCHS * self.slot_clock.slots_per_epoch()
+    }
+
+    /// Capacity of each slot of `slots`.
+    pub fn report(&self, slots: Range<u64>) -> CapacityReport {
+        let current_slot = self.slot_clock.current_slot();
+        let execution = self.execution.load();
+        let remaining_blob_bytes =
+            self.limits.max_pending_blob_bytes.saturating_sub(self.blocks.blob_bytes());
+        let proposers = self.proposers.read();
+
+        let slots = slots
+            .map(|slot| {
+                let (commitments, committed_gas) = self
+                    .blocks
+                    .with_block(slot, |block| (block.transactions_count(), block.committed_gas()))
+                    .unwrap_or_default();
+                let proposer = proposers.get(&slot).cloned();
+                let reserved_gas = self.reservations.held_back(slot, None);
+                let remaining_gas = self
+                    .limits
+                    .max_commitment_gas
+                    .saturating_sub(committed_gas)
+                    .saturating_sub(reserved_gas);
+
+                SlotCapacity {
+                    slot,
+                    open: proposer.is_some() && slot > current_slot,
+                    proposer,
+                    commitments,
+                    // A request is refused once it would reach the cap
+                    remaining_commitments: self
+                        .limits
+                        .max_commitments_in_block
+                        .saturating_sub(commitments + 1),
+                    committed_gas,
+                    reserved_gas,
+                    remaining_gas,
+                    remaining_blob_bytes,
+                    min_priority_fee: (remaining_gas >= PRICED_GAS)
+                        .then(|| self.quoter.min_priority_fee(slot, PRICED_GAS).ok())
+                        .flatten(),
+                }
+            })
+            .collect();
+
+        CapacityReport {
+            current_slot,
+            basefee: execution.basefee,
+            blob_basefee: execution.blob_basefee,
+            slots,
+        }
+    }
+
+    /// Constraints pending for `slot`, none once its block was submitted or pruned.
+    pub fn constraints(&self, slot: u64) -> SlotConstraints {
+        let constraints = self
+            .blocks
+            .with_block(slot, |block| {
+                block.signed_constraints_list.iter().map(CommittedConstraints::from).collect()
+            })
+            .unwrap_or_default();
+        SlotConstraints { slot, constraints }
+    }
+
+    /// Totals of the constraints pending for `slot`.
+    pub fn summary(&self, slot: u64) -> SlotSummary {
+        let (constraints, transactions, committed_gas, blobs, blob_bytes) = self
+            .blocks
+            .with_block(slot, |block| {
+                (
+                    block.transactions_count(),
+                    block.get_transactions().len(),
+                    block.committed_gas(),
+                    block.blob_count(),
+                    block.blob_bytes(),
+                )
+            })
+            .unwrap_or_default();
+        SlotSummary {
+            slot,
+            proposer: self.proposers.read().get(&slot).cloned(),
+            constraints,
+            transactions,
+            committed_gas,
+            blobs,
+            blob_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+
+    use super::{parse_slot_range, CapacityError, CapacityView};
+    use crate::{
+        commitment::{forward::SharedProposers, quote::Quoter},
+        constraints::{ConstraintsMessage, SignedConstraints},
+        state::{
+            pricing::{PreconfPricer, PriorityTier},
+            shards::SlotShards,
+            slot_clock::SlotClock,
+        },
+    };
+
+    #[test]
+    fn test_slot_capacity() {
+        assert_eq!(parse_slot_range("100..132", 64), Ok(100..132));
+        assert_eq!(parse_slot_range("100", 64), Ok(100..101));
+        assert!(matches!(parse_slot_range("132..100", 64), Err(CapacityError::InvalidRange(_))));
+        assert!(matches!(parse_slot_range("100..", 64), Err(CapacityError::InvalidRange(_))));
+        assert!(matches!(
+            parse_slot_range("0..65", 64),
+            Err(CapacityError::TooManySlots { requested: 65, max: 64 })
+        ));
+
+        let blocks = SlotShards::default();
+        let signed = SignedConstraints {
+            message: ConstraintsMessage { slot: 11, ..Default::default() },
+            signature: Default::default(),
+        };
+        blocks.add_constraints(11, signed, 0, PriorityTier::Standard);
+        let proposers = SharedProposers::default();
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        proposers.write().insert(11, BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap());
+        let quoter =
+            Quoter::new(None, 0, PreconfPricer::default(), 0, Default::default(), Default::default());
+        // Genesis at the unix epoch, far in the past of slot 11
+        let view = CapacityView::new(
+            blocks,
+            proposers,
+            quoter,
+            Default::default(),
+            SlotClock::new(0, 12, 0),
+        );
+
+        let report = view.report(10..12);
+        assert_eq!(report.slots.len(), 2);
+        assert!(report.slots[0].proposer.is_none());
+        let slot = &report.slots[1];
+        assert_eq!((slot.slot, slot.commitments), (11, 1));
+        assert!(slot.proposer.is_some() && !slot.open);
+        assert!(slot.min_priority_fee.is_some());
+
+        // What was promised in the slot so far, for the auditors
+        let summary = view.summary(11);
+        assert_eq!((summary.constraints, summary.committed_gas, summary.blobs), (1, 0, 0));
+        assert!(summary.proposer.is_some());
+        assert_eq!(view.constraints(11).constraints.len(), 1);
+        assert!(view.constraints(12).constraints.is_empty());
+    }
+}
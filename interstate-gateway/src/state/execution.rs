@@ -1,28 +1,37 @@
-use alloy::consensus::Transaction;
+use alloy::{consensus::Transaction, signers::local::PrivateKeySigner};
 use alloy_v092::{
     consensus::{BlobTransactionValidationError, EnvKzgSettings},
-    eips::eip4844::MAX_BLOBS_PER_BLOCK,
     primitives::{Address, U256},
     transports::TransportError,
 };
+use arc_swap::ArcSwap;
 use ethereum_consensus::deneb::Slot;
+use reth_primitives::TxType;
 
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    num::NonZero,
+    sync::Arc,
+};
 use thiserror::Error;
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, trace, warn};
 
 use crate::{
-    builder::BlockTemplate, commitment::request::PreconfRequest, config::limits::LimitOptions, constraints::TransactionExt, metrics::ApiMetrics, utils::{
-        score_cache::ScoreCache,
-        transactions::{calculate_max_basefee, max_transaction_cost, validate_transaction},
+    builder::{constraint::SignedConstraints, BlockTemplate}, commitment::{quote::Quoter, request::PreconfRequest}, config::limits::LimitOptions, constraints::TransactionExt, metrics::ApiMetrics, utils::{
+        score_cache::{ScoreCache, SharedScoreCacheStats},
+        transactions::{
+            calculate_max_basefee, estimation_request, max_transaction_cost, validate_transaction,
+        },
     }
 };
 
 use super::{
     account_state::{AccountState, AccountStateCache},
     fetcher::StateFetcher,
-    pricing::{self, PreconfPricer},
+    pricing::{self, CommittedSpace, PreconfPricer, PricingInput, SharedCommittedSpace, Underpriced},
+    revenue::RevenueTracker,
     signature::SignatureError,
+    stale::StaleTxIndex,
 };
 
 #[derive(Debug, Error)]
@@ -41,14 +50,22 @@ pub enum ValidationError {
     NonceTooHigh(u64, u64),
     #[error("Account has code")]
     AccountHasCode,
+    #[error("Contract deployment collides with the existing account {0}")]
+    DeploymentCollision(Address),
     #[error("Gas limit too high")]
     GasLimitTooHigh,
+    #[error("Gas limit {0} is too far above the estimated gas {1}")]
+    GasLimitAboveEstimate(u64, u64),
+    #[error("Gas estimation failed: {0}")]
+    GasEstimationFailed(String),
     #[error("Transaction input size too high")]
     TransactionSizeTooHigh,
+    #[error("Contract creation init code size too high")]
+    InitCodeSizeTooHigh,
     #[error("Max priority fee per gas is greater than max fee per gas")]
     MaxPriorityFeePerGasTooHigh,
-    #[error("Max priority fee per gas {0} is less than min priority fee {1}")]
-    MaxPriorityFeePerGasTooLow(u128, u128),
+    #[error(transparent)]
+    MaxPriorityFeePerGasTooLow(Underpriced),
     #[error("Not enough balance to pay for value + maximum fee")]
     InsufficientBalance,
     #[error("Pricing calculation error: {0}")]
@@ -85,10 +102,14 @@ impl ValidationError {
             Self::NonceTooLow(_, _) => "nonce_too_low",
             Self::NonceTooHigh(_, _) => "nonce_too_high",
             Self::AccountHasCode => "account_has_code",
+            Self::DeploymentCollision(_) => "deployment_collision",
             Self::GasLimitTooHigh => "gas_limit_too_high",
+            Self::GasLimitAboveEstimate(_, _) => "gas_limit_above_estimate",
+            Self::GasEstimationFailed(_) => "gas_estimation_failed",
             Self::TransactionSizeTooHigh => "transaction_size_too_high",
+            Self::InitCodeSizeTooHigh => "init_code_size_too_high",
             Self::MaxPriorityFeePerGasTooHigh => "max_priority_fee_per_gas_too_high",
-            Self::MaxPriorityFeePerGasTooLow(_, _) => "max_priority_fee_per_gas_too_low",
+            Self::MaxPriorityFeePerGasTooLow(_) => "max_priority_fee_per_gas_too_low",
             Self::InsufficientBalance => "insufficient_balance",
             Self::Pricing(_) => "pricing",
             Self::Eip4844Limit => "eip4844_limit",
@@ -103,12 +124,88 @@ impl ValidationError {
     }
 }
 
+/// Estimates the gas the transactions of the requests are priced on, so that oversized gas
+/// limits can't grief the block capacity. Run before locking the execution state, the
+/// estimates being round trips to the execution client.
+#[derive(Debug, Clone)]
+pub struct GasEstimator<C> {
+    client: C,
+    /// Max ratio of the declared gas limit to the estimate.
+    max_ratio: NonZero<u64>,
+}
+
+impl<C: StateFetcher> GasEstimator<C> {
+    /// Gas each transaction of the request is priced on. Transactions declaring a gas limit too
+    /// far above their estimate are refused.
+    ///
+    /// Only the first transaction is estimated against the head: the next ones run on the
+    /// state left by the previous ones, which the execution client can't estimate against,
+    /// and are priced on their declared gas limit.
+    pub async fn priced_gas(&self, req: &PreconfRequest) -> Result<Vec<u64>, ValidationError> {
+        let mut priced_gas = req.tx_gas_limits().collect::<Vec<_>>();
+        let Some(tx) = req.txs.first() else {
+            return Ok(priced_gas);
+        };
+        // Blob transactions can't be simulated without their fees, nor EIP-7702 ones without
+        // their authorizations
+        if tx.tx.blob_sidecar().is_some() || tx.tx.tx_type() == TxType::Eip7702 {
+            return Ok(priced_gas);
+        }
+
+        let declared = tx.tx.gas_limit();
+        let sender = tx.sender.ok_or(ValidationError::RecoverSigner)?;
+        let estimated = self
+            .client
+            .estimate_gas(&estimation_request(&tx.tx, sender))
+            .await
+            .map_err(|err| ValidationError::GasEstimationFailed(err.to_string()))?;
+
+        if declared > estimated.saturating_mul(self.max_ratio.get()) {
+            return Err(ValidationError::GasLimitAboveEstimate(declared, estimated));
+        }
+        priced_gas[0] = estimated.min(declared);
+        Ok(priced_gas)
+    }
+}
+
+/// Head of the execution state, replaced as a whole on head updates so that its readers
+/// always see the fields of the same head.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecutionSnapshot {
+    pub block_number: u64,
+    pub slot: u64,
+    pub basefee: u128,
+    pub blob_basefee: u128,
+}
+
+/// Latest [ExecutionSnapshot], read without locking the execution state.
+pub type SharedExecutionSnapshot = Arc<ArcSwap<ExecutionSnapshot>>;
+
+impl ExecutionSnapshot {
+    /// Validate the target slot and the fees of a request against the head, returning the
+    /// slot difference to the head and the maximum basefee the transactions must cover.
+    pub fn validate_request(&self, req: &PreconfRequest) -> Result<(u64, u128), ValidationError> {
+        if req.slot < self.slot {
+            debug!(target_slot = req.slot, slot = self.slot, "Target slot lower than current slot");
+            return Err(ValidationError::SlotTooLow(self.slot));
+        }
+
+        let slot_diff = req.slot - self.slot;
+        let max_basefee = calculate_max_basefee(self.basefee, slot_diff)
+            .ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
+
+        debug!(%slot_diff, basefee = self.basefee, %max_basefee, "Validating basefee");
+        if !req.validate_basefee(max_basefee) {
+            return Err(ValidationError::BaseFeeTooLow(max_basefee));
+        }
+
+        Ok((slot_diff, max_basefee))
+    }
+}
+
 #[derive(Debug)]
 pub struct ExecutionState<C> {
-    block_number: u64,
-    slot: u64,
-    basefee: u128,
-    blob_basefee: u128,
+    snapshot: SharedExecutionSnapshot,
     account_states: AccountStateCache,
     block_templates: HashMap<Slot, BlockTemplate>,
     chain_id: u64,
@@ -117,6 +214,9 @@ pub struct ExecutionState<C> {
     client: C,
     validation_params: ValidationParams,
     pricing: PreconfPricer,
+    revenue: RevenueTracker,
+    committed_space: SharedCommittedSpace,
+    stale: StaleTxIndex,
 }
 
 #[derive(Debug)]
@@ -148,38 +248,134 @@ impl<C: StateFetcher> ExecutionState<C> {
             client.get_head(),
             client.get_chain_id()
         )?;
+        let head = ExecutionSnapshot { block_number, slot: 0, basefee, blob_basefee };
 
+        Ok(Self::from_head(client, limits, gas_limit, chain_id, head))
+    }
+
+    /// An execution state starting at `head`, without querying the client.
+    pub fn from_head(
+        client: C,
+        limits: LimitOptions,
+        gas_limit: u64,
+        chain_id: u64,
+        head: ExecutionSnapshot,
+    ) -> Self {
         let num_accounts = limits
             .max_account_states_size
             .get()
             .div_ceil(size_of::<AccountState>() + size_of::<Address>());
 
-        Ok(Self {
-            basefee,
-            blob_basefee,
-            block_number,
+        Self {
+            snapshot: Arc::new(ArcSwap::from_pointee(head)),
             chain_id,
             limits,
             client,
-            slot: 0,
-            account_states: AccountStateCache(ScoreCache::with_max_len(num_accounts)),
+            account_states: AccountStateCache(
+                ScoreCache::with_max_len(num_accounts)
+                    .with_policy(limits.account_states_eviction_policy)
+                    .with_ttl_secs(limits.account_states_ttl_secs),
+                Default::default(),
+            ),
             block_templates: HashMap::new(),
             kzg_settings: EnvKzgSettings::default(),
             validation_params: ValidationParams::new(gas_limit),
-            pricing: PreconfPricer::new(gas_limit),
-        })
+            pricing: PreconfPricer::new(gas_limit).with_max_blobs(limits.max_blobs_per_block),
+            revenue: RevenueTracker::default(),
+            committed_space: Default::default(),
+            stale: StaleTxIndex::default(),
+        }
+    }
+
+    /// Report the revenue in epochs of `slots_per_epoch` slots.
+    pub fn with_slots_per_epoch(mut self, slots_per_epoch: u64) -> Self {
+        self.revenue = RevenueTracker::new(slots_per_epoch);
+        self
+    }
+
+    /// The latest head of the execution state.
+    pub fn snapshot(&self) -> Arc<ExecutionSnapshot> {
+        self.snapshot.load_full()
+    }
+
+    /// Handle to the latest head, for the validations done outside of the state lock.
+    pub fn shared_snapshot(&self) -> SharedExecutionSnapshot {
+        self.snapshot.clone()
+    }
+
+    /// Shared snapshot of the account states cache stats.
+    pub fn account_states_stats(&self) -> SharedScoreCacheStats {
+        self.account_states.1.clone()
+    }
+
+    /// Index of the nonces and recent transactions known to the state, to refuse the
+    /// requests for stale transactions early.
+    pub fn stale_index(&self) -> StaleTxIndex {
+        self.stale.clone()
+    }
+
+    /// Shared revenue accounting of our proposals.
+    pub fn revenue(&self) -> RevenueTracker {
+        self.revenue.clone()
+    }
+
+    /// Quoter pricing commitments at the gas committed in our block templates, signing the
+    /// quotes with `signer`.
+    pub fn quoter(&self, signer: Option<PrivateKeySigner>, ttl_ms: u64) -> Quoter {
+        Quoter::new(
+            signer,
+            ttl_ms,
+            self.pricing.clone(),
+            self.limits.min_inclusion_profit,
+            self.committed_space.clone(),
+            self.snapshot.clone(),
+        )
     }
 
     pub fn basefee(&self) -> u128 {
-        self.basefee
+        self.snapshot.load().basefee
+    }
+
+    /// Minimum priority fee in wei for a transaction using `gas` in the given slot, given the
+    /// space already committed for it.
+    pub fn min_priority_fee(&self, slot: u64, gas: u64) -> Option<u64> {
+        let input = PricingInput {
+            gas,
+            blobs: 0,
+            committed: self.committed_space(slot),
+            slots_ahead: slot.saturating_sub(self.snapshot.load().slot),
+        };
+        self.pricing.min_inclusion_tip(&input).ok()
     }
 
+    /// Gas and blobs committed in the block template of `slot`.
+    fn committed_space(&self, slot: u64) -> CommittedSpace {
+        self.block_templates
+            .get(&slot)
+            .map(|t| CommittedSpace { gas: t.committed_gas(), blobs: t.blob_count() })
+            .unwrap_or_default()
+    }
+
+    /// The estimator of the gas the requests are priced on, when it isn't their declared gas.
+    pub fn gas_estimator(&self) -> Option<GasEstimator<C>>
+    where
+        C: Clone,
+    {
+        let max_ratio = self.limits.max_gas_limit_ratio?;
+        Some(GasEstimator { client: self.client.clone(), max_ratio })
+    }
+
+    /// Validate the request against the execution state, its transactions being priced on the
+    /// gas at the same index of `priced_gas`.
     pub async fn verify_el_tx(
         &mut self,
         req: &mut PreconfRequest,
+        priced_gas: &[u64],
     ) -> Result<(), ValidationError> {
-        req.recover_signers();
+        req.recover_signers().map_err(|_| ValidationError::RecoverSigner)?;
 
+        // The whole request is validated against the same head, even if it is replaced meanwhile
+        let head = self.snapshot.load_full();
         let target_slot = req.slot;
 
         // info!("Validating Chain Id");
@@ -188,15 +384,14 @@ impl<C: StateFetcher> ExecutionState<C> {
         }
 
         // info!("Validating Committed gas");
-        let preconfirmed_gas = self
-            .get_block_template(target_slot)
-            .map(|t: &BlockTemplate| t.committed_gas())
-            .unwrap_or(0);
+        let committed = self.committed_space(target_slot);
+        let preconfirmed_gas = committed.gas;
 
         // info!("Validating Transaction Size");
-        if preconfirmed_gas + req.gas_limit() >= self.limits.max_committed_gas_per_slot.get() {
+        let max_committed_gas = self.limits.max_committed_gas_per_slot.get();
+        if preconfirmed_gas.saturating_add(req.total_gas_limit()) > max_committed_gas {
             return Err(ValidationError::MaxCommittedGasReachedForSlot(
-                self.slot,
+                target_slot,
                 self.limits.max_committed_gas_per_slot.get(),
             ));
         }
@@ -207,7 +402,7 @@ impl<C: StateFetcher> ExecutionState<C> {
         }
 
         // info!("Validating Gas limit is higher than the maximum block gas limit");
-        if req.gas_limit() > self.validation_params.block_gas_limit {
+        if req.max_tx_gas_limit() > self.validation_params.block_gas_limit {
             return Err(ValidationError::GasLimitTooHigh);
         }
 
@@ -221,45 +416,33 @@ impl<C: StateFetcher> ExecutionState<C> {
             return Err(ValidationError::MaxPriorityFeePerGasTooHigh);
         }
 
-        // info!("Validating if the max_fee_per_gas would cover the maximum possible basefee.");
-        let slot_diff = target_slot.saturating_sub(self.slot);
-
-        info!("basefee and slot_diff, {:?}, {:?}", self.basefee, slot_diff);
-        let max_basefee = calculate_max_basefee(self.basefee, slot_diff)
-            .ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
-
-        debug!(%slot_diff, basefee = self.basefee, %max_basefee, "Validating basefee");
-
-        // info!("Validating the base fee");
-        if !req.validate_basefee(max_basefee) {
-            return Err(ValidationError::BaseFeeTooLow(max_basefee));
-        }
-
-        // info!("Validating max_priority_fee_per_gas is greater than or equal to the calculated min_priority_fee");
-        if let Err(err) = req.validate_min_priority_fee(
-            &self.pricing,
-            preconfirmed_gas,
-            self.limits.min_inclusion_profit,
-            max_basefee,
-        ) {
-            return Err(match err {
-                pricing::PricingError::TipTooLow {
-                    tip,
-                    min_priority_fee,
-                } => ValidationError::MaxPriorityFeePerGasTooLow(tip, min_priority_fee),
-                other => ValidationError::Pricing(other),
-            });
-        }
-
-        // info!("Validating target slot lower than the current slot");
-        if target_slot < self.slot {
-            debug!(%target_slot, %self.slot, "Target slot lower than current slot");
-            return Err(ValidationError::SlotTooLow(self.slot));
+        // info!("Validating the target slot and if the max_fee_per_gas would cover the maximum possible basefee.");
+        let (slot_diff, max_basefee) = head.validate_request(req)?;
+
+        // Transactions forced in from the mempool don't pay for the commitment
+        if !req.inclusion_list {
+            // info!("Validating max_priority_fee_per_gas is greater than or equal to the calculated min_priority_fee");
+            if let Err(err) = req.validate_min_priority_fee(
+                &self.pricing,
+                committed,
+                slot_diff,
+                self.limits.min_inclusion_profit,
+                max_basefee,
+                priced_gas,
+            ) {
+                return Err(match err {
+                    pricing::PricingError::TipTooLow(underpriced) => {
+                        ValidationError::MaxPriorityFeePerGasTooLow(underpriced)
+                    }
+                    other => ValidationError::Pricing(other),
+                });
+            }
         }
 
         // info!("Validating  each transaction in the request against the account state, keeping track of the nonce and balance diffs");
         let mut bundle_nonce_diff_map = HashMap::new();
         let mut bundle_balance_diff_map = HashMap::new();
+        let mut bundle_blobs = 0;
         for tx in &req.txs {
             let sender = tx.sender.expect("Recovered sender");
 
@@ -271,7 +454,7 @@ impl<C: StateFetcher> ExecutionState<C> {
                 return Err(ValidationError::SlotTooLow(highest_slot_for_account));
             }
 
-            let account_state = match self.account_states.get(&sender).copied() {
+            let account_state = match self.account_states.get(&sender) {
                 Some(account) => account,
                 None => {
                     let account = match self.client.get_account_state(&sender, None).await {
@@ -285,6 +468,7 @@ impl<C: StateFetcher> ExecutionState<C> {
                     };
 
                     self.account_states.insert(sender, account);
+                    self.stale.record_nonces([(sender, account.transaction_count)]);
                     account
                 }
             };
@@ -300,6 +484,21 @@ impl<C: StateFetcher> ExecutionState<C> {
             let sender_nonce_diff = bundle_nonce_diff_map.entry(sender).or_insert(0);
             let sender_balance_diff = bundle_balance_diff_map.entry(sender).or_insert(U256::ZERO);
 
+            // The value + maximum fee of everything already committed for the sender, in the
+            // block templates and earlier in this bundle, must stay covered by its balance.
+            let committed_spend = balance_diff.saturating_add(*sender_balance_diff);
+            let total_spend = committed_spend.saturating_add(max_transaction_cost(&tx.tx));
+            if total_spend > account_state.balance {
+                debug!(
+                    ?sender,
+                    %committed_spend,
+                    %total_spend,
+                    balance = %account_state.balance,
+                    "Cumulative spend exceeds the sender balance"
+                );
+                return Err(ValidationError::InsufficientBalance);
+            }
+
             let account_state_with_diffs = AccountState {
                 transaction_count: account_state
                     .transaction_count
@@ -316,14 +515,25 @@ impl<C: StateFetcher> ExecutionState<C> {
 
             validate_transaction(&account_state_with_diffs, &tx.tx)?;
 
+            // A deployment to an account with code or a nonce would fail on chain (EIP-684)
+            if let Some(address) = tx.created_address().filter(|_| self.limits.check_deployment_collisions) {
+                let existing = self
+                    .client
+                    .get_account_state(&address, None)
+                    .await
+                    .map_err(|err| ValidationError::Internal(format!("Error fetching account state: {err:?}")))?;
+                if existing.has_code || existing.transaction_count > 0 {
+                    return Err(ValidationError::DeploymentCollision(address));
+                }
+            }
+
             if let Some(transaction) = tx.tx.as_eip4844() {
-                if let Some(template) = self.block_templates.get(&target_slot) {
-                    if template.blob_count() >= MAX_BLOBS_PER_BLOCK {
-                        return Err(ValidationError::Eip4844Limit);
-                    }
+                bundle_blobs += transaction.blob_versioned_hashes.len();
+                if committed.blobs + bundle_blobs > self.pricing.max_blobs() {
+                    return Err(ValidationError::Eip4844Limit);
                 }
 
-                let max_blob_basefee = calculate_max_basefee(self.blob_basefee, slot_diff)
+                let max_blob_basefee = calculate_max_basefee(head.blob_basefee, slot_diff)
                     .ok_or(ValidationError::MaxBaseFeeCalcOverflow)?;
 
                 let blob_basefee = transaction.max_fee_per_blob_gas().unwrap_or(0);
@@ -350,25 +560,32 @@ impl<C: StateFetcher> ExecutionState<C> {
         block_number: Option<u64>,
         slot: u64,
     ) -> Result<(), TransportError> {
-        self.slot = slot;
+        // Requests for past slots are refused right away, even if the rest of the head
+        // fails to update
+        let head = self.snapshot.load_full();
+        self.snapshot.store(Arc::new(ExecutionSnapshot { slot, ..*head }));
+        self.account_states.gc();
+        let cached = self.account_states.keys().copied().collect::<HashSet<_>>();
+        self.stale.retain_nonces(|address| cached.contains(address));
 
         let accounts = self.account_states.keys().collect::<Vec<_>>();
         let update = self.client.get_state_update(accounts, block_number).await;
         trace!(%slot, ?update, "Applying execution state update");
 
-        for template in self.remove_block_templates_until(slot) {
+        for (template_slot, template) in self.remove_block_templates_until(slot) {
             debug!(%slot, "Removed block template for slot");
             let hashes = template.transaction_hashes();
             let receipts = self.client.get_receipts_unordered(hashes.as_ref()).await?;
 
             let mut receipts_len = 0;
             for receipt in receipts.iter().flatten() {
-                let tip_per_gas = receipt.effective_gas_price - self.basefee;
+                let tip_per_gas = receipt.effective_gas_price - head.basefee;
                 let total_tip = tip_per_gas * receipt.gas_used as u128;
 
                 trace!(hash = %receipt.transaction_hash, total_tip, "Receipt found");
 
                 ApiMetrics::increment_gross_tip_revenue_count(total_tip);
+                self.revenue.record_preconf_tips(template_slot, U256::from(total_tip));
                 receipts_len += 1;
             }
 
@@ -391,15 +608,28 @@ impl<C: StateFetcher> ExecutionState<C> {
             }
         }
 
-        self.apply_state_update(update?);
+        let update = update?;
+        if !self.stale.has_block(update.block_number) {
+            match self.client.get_block_transaction_hashes(update.block_number).await {
+                Ok(hashes) => self.stale.record_block(update.block_number, hashes),
+                Err(err) => warn!(?err, block = update.block_number, "Failed to index the block"),
+            }
+        }
+        self.apply_state_update(slot, update);
 
         Ok(())
     }
 
-    fn apply_state_update(&mut self, update: StateUpdate) {
-        self.block_number = update.block_number;
-        self.basefee = update.min_basefee;
+    fn apply_state_update(&mut self, slot: u64, update: StateUpdate) {
+        self.snapshot.store(Arc::new(ExecutionSnapshot {
+            block_number: update.block_number,
+            slot,
+            basefee: update.min_basefee,
+            blob_basefee: update.min_blob_basefee,
+        }));
 
+        let nonces = update.account_states.iter();
+        self.stale.record_nonces(nonces.map(|(address, state)| (*address, state.transaction_count)));
         for (address, state) in update.account_states {
             let Some(prev_state) = self.account_states.get_mut(&address) else {
                 error!(%address, "Account state requested for update but not found in cache");
@@ -412,27 +642,52 @@ impl<C: StateFetcher> ExecutionState<C> {
     }
 
     fn refresh_templates(&mut self) {
+        // Each template is checked against the state left by the commitments of the slots
+        // before it
+        let mut slots = self.block_templates.keys().copied().collect::<Vec<_>>();
+        slots.sort_unstable();
+
         for (address, (account_state, _)) in self.account_states.iter() {
             trace!(%address, ?account_state, "Refreshing templates...");
 
             let (address, mut expected_account_state) = (*address, *account_state);
 
-            for template in self.block_templates.values_mut() {
+            for slot in &slots {
+                let template = self.block_templates.get_mut(slot).expect("listed above");
                 template.retain(address, expected_account_state);
 
                 if let Some((nonce_diff, balance_diff)) = template.get_diff(&address) {
                     expected_account_state.transaction_count += nonce_diff;
-                    expected_account_state.balance -= balance_diff;
+                    expected_account_state.balance =
+                        expected_account_state.balance.saturating_sub(balance_diff);
                 }
             }
         }
+
+        self.publish_committed_space();
+    }
+
+    /// Share the space committed per slot with the quoter.
+    fn publish_committed_space(&self) {
+        *self.committed_space.write() =
+            self.block_templates.keys().map(|slot| (*slot, self.committed_space(*slot))).collect();
+    }
+
+    /// Record committed constraints in the block template of `slot`, so that the spend of
+    /// their senders is accounted for when validating later requests.
+    pub fn add_constraint(&mut self, slot: u64, signed_constraints: SignedConstraints) {
+        self.block_templates
+            .entry(slot)
+            .or_default()
+            .add_constraints(signed_constraints);
+        self.publish_committed_space();
     }
 
     pub fn get_block_template(&mut self, slot: u64) -> Option<&BlockTemplate> {
         self.block_templates.get(&slot)
     }
 
-    pub fn remove_block_templates_until(&mut self, slot: u64) -> Vec<BlockTemplate> {
+    pub fn remove_block_templates_until(&mut self, slot: u64) -> Vec<(u64, BlockTemplate)> {
         let mut slots_to_remove = self
             .block_templates
             .keys()
@@ -444,9 +699,10 @@ impl<C: StateFetcher> ExecutionState<C> {
         let mut templates = Vec::with_capacity(slots_to_remove.len());
         for s in slots_to_remove {
             if let Some(template) = self.block_templates.remove(&s) {
-                templates.push(template);
+                templates.push((s, template));
             }
         }
+        self.publish_committed_space();
 
         templates
     }
@@ -481,3 +737,91 @@ fn compute_diffs(
         },
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::{EthereumWallet, TransactionBuilder},
+        primitives::{Address, PrimitiveSignature, U256},
+        signers::local::PrivateKeySigner,
+    };
+
+    use super::{ExecutionSnapshot, ExecutionState, ValidationError};
+    use crate::{
+        builder::constraint::{ConstraintsMessage, SignedConstraints},
+        commitment::request::PreconfRequest,
+        constraints::Constraint,
+        state::{account_state::AccountState, fetcher::ClientState},
+        test_utils::default_test_transaction,
+        utils::transactions::max_transaction_cost,
+    };
+
+    #[test]
+    fn test_snapshot_validate_request() {
+        let head = ExecutionSnapshot {
+            block_number: 100,
+            slot: 10,
+            basefee: 1_000_000_000,
+            blob_basefee: 1,
+        };
+        let request = PreconfRequest {
+            slot: 9,
+            txs: vec![],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: Address::ZERO,
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+        assert!(matches!(head.validate_request(&request), Err(ValidationError::SlotTooLow(10))));
+
+        let request = PreconfRequest { slot: 12, ..request };
+        let (slot_diff, max_basefee) = head.validate_request(&request).unwrap();
+        assert_eq!(slot_diff, 2);
+        assert!(max_basefee > head.basefee);
+    }
+
+    #[tokio::test]
+    async fn test_templates_refreshed_in_slot_order() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let sender = signer.address();
+        // Never queried
+        let client = ClientState::new("http://127.0.0.1:1".parse::<reqwest::Url>()?);
+        let mut state =
+            ExecutionState::from_head(client, Default::default(), 30_000_000, 1, Default::default());
+
+        // Consecutive nonces committed to consecutive slots, added out of order
+        let mut total_cost = U256::ZERO;
+        for (slot, nonce) in [(13, 3), (11, 1), (10, 0), (12, 2)] {
+            let tx = default_test_transaction(sender, Some(nonce)).with_chain_id(1);
+            let mut constraint = Constraint::decode_enveloped(tx.build(&wallet).await?.encoded_2718())?;
+            constraint.sender = Some(sender);
+            total_cost += max_transaction_cost(&constraint.tx);
+
+            let message = ConstraintsMessage::from_tx(Default::default(), slot, constraint);
+            state.add_constraint(slot, SignedConstraints { message, signature: Default::default() });
+        }
+
+        // Each slot is checked against the nonce and the balance left by the previous ones
+        let account = AccountState { transaction_count: 0, balance: total_cost, has_code: false };
+        state.account_states.insert(sender, account);
+        state.refresh_templates();
+        for slot in 10..14 {
+            assert_eq!(state.block_templates[&slot].signed_constraints_list.len(), 1);
+        }
+
+        // Without the balance for the last one, only its commitment is dropped
+        let balance = total_cost - U256::from(1);
+        state.account_states.insert(sender, AccountState { balance, ..account });
+        state.refresh_templates();
+        assert!(state.block_templates[&13].signed_constraints_list.is_empty());
+        assert_eq!(state.block_templates[&12].signed_constraints_list.len(), 1);
+
+        Ok(())
+    }
+}
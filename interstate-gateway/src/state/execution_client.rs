@@ -1,19 +1,19 @@
 use std::ops::{Deref, DerefMut};
 
 use alloy_v092::{
-    eips::BlockNumberOrTag,
+    eips::{BlockId, BlockNumberOrTag},
     primitives::{Address, Bytes, TxHash, B256, U256, U64},
     providers::{ProviderBuilder, RootProvider},
     rpc::{
         client::{BatchRequest, ClientBuilder, RpcClient},
-        types::{FeeHistory, TransactionReceipt},
+        types::{AccessListResult, Block, FeeHistory, Header, SyncStatus, TransactionReceipt, TransactionRequest},
     },
     transports::{http::Http, TransportErrorKind, TransportResult},
 };
 use futures::{stream::FuturesUnordered, StreamExt};
 use reqwest::{Client, Url};
 
-use super::account_state::AccountState;
+use super::{account_state::AccountState, mempool::TxpoolContent};
 
 #[derive(Clone, Debug)]
 pub struct ExecutionClient {
@@ -95,6 +95,70 @@ impl ExecutionClient {
         Ok(result.to())
     }
 
+    pub async fn get_sync_status(&self) -> TransportResult<SyncStatus> {
+        self.rpc.request("eth_syncing", ()).await
+    }
+
+    pub async fn get_block_timestamp(&self, block: BlockNumberOrTag) -> TransportResult<u64> {
+        let found: Option<Block> = self.rpc.request("eth_getBlockByNumber", (block, false)).await?;
+
+        let Some(found) = found else {
+            return Err(TransportErrorKind::Custom(format!("Block {block} not found").into()).into());
+        };
+
+        Ok(found.header.timestamp)
+    }
+
+    /// Header of the block `hash`, none when the block is unknown.
+    pub async fn get_header_by_hash(&self, hash: B256) -> TransportResult<Option<Header>> {
+        let found: Option<Block> = self.rpc.request("eth_getBlockByHash", (hash, false)).await?;
+
+        Ok(found.map(|block| block.header))
+    }
+
+    /// Balance change of `address` in block `hash`, i.e. the payment it received in it, read
+    /// by block hash so that a reorg of the block can't be mistaken for it.
+    pub async fn get_balance_change(&self, address: Address, hash: B256) -> TransportResult<U256> {
+        let Some(header) = self.get_header_by_hash(hash).await? else {
+            return Err(TransportErrorKind::Custom(format!("Block {hash} not found").into()).into());
+        };
+
+        let balance = |block: B256| {
+            self.rpc.request::<_, U256>("eth_getBalance", (address, BlockId::hash(block)))
+        };
+        let (before, after) = tokio::try_join!(balance(header.parent_hash), balance(hash))?;
+        Ok(after.saturating_sub(before))
+    }
+
+    /// Hashes of the transactions of block `number`.
+    pub async fn get_block_transaction_hashes(&self, number: u64) -> TransportResult<Vec<TxHash>> {
+        let block = BlockNumberOrTag::Number(number);
+        let found: Option<Block> = self.rpc.request("eth_getBlockByNumber", (block, false)).await?;
+
+        let Some(found) = found else {
+            return Err(TransportErrorKind::Custom(format!("Block {block} not found").into()).into());
+        };
+
+        Ok(found.transactions.hashes().collect())
+    }
+
+    /// Gas used by `request` with the access list generated for it by `eth_createAccessList`,
+    /// falling back to `eth_estimateGas` when no access list can be generated.
+    pub async fn estimate_gas_with_access_list(
+        &self,
+        request: &TransactionRequest,
+    ) -> TransportResult<u64> {
+        let result: AccessListResult =
+            self.rpc.request("eth_createAccessList", (request, BlockNumberOrTag::Latest)).await?;
+        if result.error.is_none() {
+            return Ok(result.gas_used.saturating_to());
+        }
+
+        let gas: U64 =
+            self.rpc.request("eth_estimateGas", (request, BlockNumberOrTag::Latest)).await?;
+        Ok(gas.to())
+    }
+
     pub async fn get_account_state(
         &self,
         address: &Address,
@@ -129,6 +193,16 @@ impl ExecutionClient {
         })
     }
 
+    /// Raw signed transaction, also found for the transactions still in the mempool.
+    pub async fn get_raw_transaction(&self, hash: TxHash) -> TransportResult<Option<Bytes>> {
+        self.rpc.request("eth_getRawTransactionByHash", [hash]).await
+    }
+
+    /// Transactions of the mempool, per sender and nonce.
+    pub async fn get_txpool_content(&self) -> TransportResult<TxpoolContent> {
+        self.rpc.request("txpool_content", ()).await
+    }
+
     #[allow(unused)]
     pub async fn send_raw_transaction(&self, raw: Bytes) -> TransportResult<B256> {
         self.rpc.request("eth_sendRawTransaction", [raw]).await
@@ -1,22 +1,21 @@
-use std::{collections::HashMap, time::Duration};
+use std::{collections::HashMap, future::Future};
 
 use alloy_v092::{
     eips::BlockNumberOrTag,
     primitives::{Address, Bytes, TxHash, U256, U64},
-    rpc::types::TransactionReceipt,
-    transports::TransportError,
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    transports::{TransportError, TransportErrorKind},
 };
 use futures::{stream::FuturesOrdered, StreamExt};
 use reqwest::Url;
-use tracing::error;
 
 use super::{
     account_state::AccountState, execution::StateUpdate, execution_client::ExecutionClient,
 };
-
-const MAX_RETRIES: u32 = 8;
-
-const RETRY_BACKOFF_MS: u64 = 200;
+use crate::utils::{
+    breaker::{BreakerError, BreakerPolicy, CircuitBreaker, Dependency},
+    retry::{retry_with_backoff, RetryError, RetryPolicy},
+};
 
 #[async_trait::async_trait]
 pub trait StateFetcher {
@@ -44,26 +43,57 @@ pub trait StateFetcher {
         &self,
         hashes: &[TxHash],
     ) -> Result<Vec<Option<TransactionReceipt>>, TransportError>;
+
+    async fn estimate_gas(&self, request: &TransactionRequest) -> Result<u64, TransportError>;
+
+    async fn get_block_transaction_hashes(
+        &self,
+        number: u64,
+    ) -> Result<Vec<TxHash>, TransportError>;
 }
 
 #[derive(Clone, Debug)]
 pub struct ClientState {
     client: ExecutionClient,
-    retry_backoff: Duration,
+    retry: RetryPolicy,
+    /// Fails the calls fast while the execution client keeps failing.
+    breaker: CircuitBreaker,
 }
 
 impl ClientState {
     pub fn new<U: Into<Url>>(url: U) -> Self {
         Self {
             client: ExecutionClient::new(url),
-            retry_backoff: Duration::from_millis(RETRY_BACKOFF_MS),
+            retry: RetryPolicy::default(),
+            breaker: CircuitBreaker::new(Dependency::Execution, BreakerPolicy::default()),
         }
     }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Share the circuit breaker of the execution client with its other clients.
+    pub fn with_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Run `call` through the circuit breaker of the execution client.
+    async fn guarded<T>(
+        &self,
+        call: impl Future<Output = Result<T, TransportError>>,
+    ) -> Result<T, TransportError> {
+        self.breaker.call(call).await.map_err(|err| match err {
+            BreakerError::Open(open) => TransportErrorKind::custom(open),
+            BreakerError::Failed(err) => err,
+        })
+    }
 }
 
-#[async_trait::async_trait]
-impl StateFetcher for ClientState {
-    async fn get_state_update(
+impl ClientState {
+    async fn fetch_state_update(
         &self,
         addresses: Vec<&Address>,
         block_number: Option<u64>,
@@ -164,17 +194,28 @@ impl StateFetcher for ClientState {
             block_number,
         })
     }
+}
+
+#[async_trait::async_trait]
+impl StateFetcher for ClientState {
+    async fn get_state_update(
+        &self,
+        addresses: Vec<&Address>,
+        block_number: Option<u64>,
+    ) -> Result<StateUpdate, TransportError> {
+        self.guarded(self.fetch_state_update(addresses, block_number)).await
+    }
 
     async fn get_head(&self) -> Result<u64, TransportError> {
-        self.client.get_head().await
+        self.guarded(self.client.get_head()).await
     }
 
     async fn get_basefee(&self, block_number: Option<u64>) -> Result<u128, TransportError> {
-        self.client.get_basefee(block_number).await
+        self.guarded(self.client.get_basefee(block_number)).await
     }
 
     async fn get_blob_basefee(&self, block_number: Option<u64>) -> Result<u128, TransportError> {
-        self.client.get_blob_basefee(block_number).await
+        self.guarded(self.client.get_blob_basefee(block_number)).await
     }
 
     async fn get_account_state(
@@ -182,32 +223,38 @@ impl StateFetcher for ClientState {
         address: &Address,
         block_number: Option<u64>,
     ) -> Result<AccountState, TransportError> {
-        let mut retries = 0;
-
-        loop {
-            match self.client.get_account_state(address, block_number).await {
-                Ok(state) => return Ok(state),
-                Err(e) => {
-                    retries += 1;
-                    if retries >= MAX_RETRIES {
-                        return Err(e);
-                    }
-
-                    error!(error = ?e, "Error getting account state, retrying...");
-                    tokio::time::sleep(self.retry_backoff).await;
-                }
-            }
-        }
+        let retried = retry_with_backoff("account_state", &self.retry, || {
+            self.client.get_account_state(address, block_number)
+        });
+        self.guarded(async {
+            retried.await.map_err(|err| match err {
+                RetryError::Timeout(elapsed) => TransportErrorKind::custom(elapsed),
+                RetryError::Failed(err) => err,
+            })
+        })
+        .await
     }
 
     async fn get_chain_id(&self) -> Result<u64, TransportError> {
-        self.client.get_chain_id().await
+        self.guarded(self.client.get_chain_id()).await
     }
 
     async fn get_receipts_unordered(
         &self,
         hashes: &[TxHash],
     ) -> Result<Vec<Option<TransactionReceipt>>, TransportError> {
-        self.client.get_receipts(hashes).await
+        self.guarded(self.client.get_receipts(hashes)).await
+    }
+
+    async fn estimate_gas(&self, request: &TransactionRequest) -> Result<u64, TransportError> {
+        // Not guarded, the estimates of the transactions that revert fail on their own
+        self.client.estimate_gas_with_access_list(request).await
+    }
+
+    async fn get_block_transaction_hashes(
+        &self,
+        number: u64,
+    ) -> Result<Vec<TxHash>, TransportError> {
+        self.guarded(self.client.get_block_transaction_hashes(number)).await
     }
 }
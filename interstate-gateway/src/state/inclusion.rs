@@ -0,0 +1,491 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy_v092::{
+    eips::BlockNumberOrTag,
+    primitives::{TxHash, B256},
+    transports::TransportResult,
+};
+use beacon_api_client::{mainnet::Client, Topic};
+use futures::StreamExt;
+use parking_lot::RwLock;
+use reqwest::Url;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use tokio::{sync::broadcast, task::AbortHandle};
+use utoipa::ToSchema;
+
+use super::{execution_client::ExecutionClient, slot_clock::SlotClock};
+use crate::{
+    commitment::events::{ApiEvent, EventBroadcaster},
+    metrics::ApiMetrics,
+    utils::now_ms,
+};
+
+/// Attempts at finding the committed transactions, as the execution client may still be
+/// importing the payload when the block event arrives.
+const FINALIZE_ATTEMPTS: usize = 3;
+const FINALIZE_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Number of recent commitments the advertised reliability statistics are computed over.
+const MAX_STATS_COMMITMENTS: usize = 1024;
+
+/// Period the outcomes published to the status pages are tallied over.
+pub const OUTCOMES_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Event of the `block` beacon topic, emitted once a block was imported.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BlockEvent {
+    #[serde(deserialize_with = "deserialize_quoted_u64")]
+    pub slot: u64,
+    /// Root of the beacon block.
+    pub block: B256,
+    #[serde(default)]
+    pub execution_optimistic: bool,
+}
+
+#[derive(Debug)]
+pub struct BlockTopic;
+
+impl Topic for BlockTopic {
+    const NAME: &'static str = "block";
+
+    type Data = BlockEvent;
+}
+
+#[derive(Debug)]
+pub struct BlockEventListener {
+    /// Channel to receive updates of the "block" beacon topic
+    blocks_rx: broadcast::Receiver<BlockEvent>,
+    /// Handle to the background task that listens for block events.
+    quit: AbortHandle,
+}
+
+impl BlockEventListener {
+    /// Start listening for block events
+    pub fn run(beacon_client: Client) -> Self {
+        let (blocks_tx, blocks_rx) = broadcast::channel(32);
+
+        let task = tokio::spawn(async move {
+            loop {
+                let mut event_stream = match beacon_client.get_events::<BlockTopic>().await {
+                    Ok(events) => events,
+                    Err(err) => {
+                        tracing::warn!(?err, "failed to subscribe to block topic, retrying...");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+
+                // Keep reading the stream until it breaks, then resubscribe
+                loop {
+                    match event_stream.next().await {
+                        Some(Ok(event)) => {
+                            let _ = blocks_tx.send(event);
+                        }
+                        Some(Err(err)) => {
+                            tracing::warn!(?err, "error reading block event stream, retrying...");
+                            break;
+                        }
+                        None => {
+                            tracing::warn!("block event stream ended, retrying...");
+                            break;
+                        }
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        });
+
+        Self { blocks_rx, quit: task.abort_handle() }
+    }
+
+    pub fn stop(self) {
+        self.quit.abort();
+    }
+
+    pub async fn next_block(&mut self) -> Result<BlockEvent, broadcast::error::RecvError> {
+        self.blocks_rx.recv().await
+    }
+}
+
+/// A committed transaction and when its request was accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrackedCommitment {
+    pub hash: TxHash,
+    /// Milliseconds since the unix epoch.
+    pub accepted_ms: u64,
+}
+
+/// Where and when a committed transaction landed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Inclusion {
+    pub hash: TxHash,
+    /// Slot of the block the transaction landed in.
+    pub slot: u64,
+    /// Time from the acceptance of the request to the block.
+    pub latency_ms: u64,
+}
+
+impl Inclusion {
+    /// Slots between the slot of the commitment and the block the transaction landed in,
+    /// zero for honored commitments.
+    pub fn slot_offset(&self, slot: u64) -> i64 {
+        self.slot as i64 - slot as i64
+    }
+}
+
+/// Outcome of the commitments of a slot in the block proposed for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InclusionReport {
+    pub slot: u64,
+    /// Committed transactions included in the block of the slot.
+    pub included: Vec<TxHash>,
+    /// Committed transactions missing from the block of the slot, i.e. broken commitments.
+    pub missing: Vec<TxHash>,
+    /// Committed transactions that landed, in the block of the slot or later.
+    pub inclusions: Vec<Inclusion>,
+}
+
+impl InclusionReport {
+    /// Whether all the commitments of the slot were honored.
+    pub fn is_honored(&self) -> bool {
+        self.missing.is_empty()
+    }
+}
+
+/// Reliability of the commitments of the gateway over its recent commitments.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct ReliabilitySummary {
+    pub commitments: usize,
+    /// Commitments included in the block of their slot.
+    pub honored: usize,
+    /// Commitments included in a later block.
+    pub late: usize,
+    pub latency_ms_p50: Option<u64>,
+    pub latency_ms_p90: Option<u64>,
+    pub latency_ms_p99: Option<u64>,
+}
+
+/// Outcome of the commitments finalized over [`OUTCOMES_WINDOW`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RecentOutcomes {
+    /// Commitments included in the block of their slot.
+    pub honored: usize,
+    /// Commitments missing from the block of their slot.
+    pub broken: usize,
+    /// Average time from the acceptance of the landed commitments to their block.
+    pub avg_inclusion_latency_ms: Option<u64>,
+}
+
+/// Slot offset and latency of a landed commitment, `None` for the ones that never landed.
+type CommitmentSample = Option<(i64, u64)>;
+
+/// Outcome of the commitments of a slot, finalized at `at_ms`.
+#[derive(Debug, Clone, Copy)]
+struct SlotOutcome {
+    at_ms: u64,
+    honored: usize,
+    broken: usize,
+    landed: usize,
+    latency_ms: u64,
+}
+
+#[derive(Debug, Default)]
+struct Samples {
+    commitments: VecDeque<CommitmentSample>,
+    slots: VecDeque<SlotOutcome>,
+}
+
+/// Recent commitments and where they landed, shared with the commitments API which advertises
+/// their summary to the clients.
+#[derive(Debug, Clone, Default)]
+pub struct InclusionStats(Arc<RwLock<Samples>>);
+
+impl InclusionStats {
+    /// Record the commitments of a finalized slot.
+    pub fn record(&self, report: &InclusionReport) {
+        self.record_at(report, now_ms());
+    }
+
+    fn record_at(&self, report: &InclusionReport, at_ms: u64) {
+        let commitments = report.included.len() + report.missing.len();
+        let landed = report
+            .inclusions
+            .iter()
+            .map(|inclusion| Some((inclusion.slot_offset(report.slot), inclusion.latency_ms)));
+        let never_landed = commitments.saturating_sub(report.inclusions.len());
+
+        let outcome = SlotOutcome {
+            at_ms,
+            honored: report.included.len(),
+            broken: report.missing.len(),
+            landed: report.inclusions.len(),
+            latency_ms: report.inclusions.iter().map(|inclusion| inclusion.latency_ms).sum(),
+        };
+
+        let mut samples = self.0.write();
+        samples.commitments.extend(landed.chain(std::iter::repeat(None).take(never_landed)));
+        let excess = samples.commitments.len().saturating_sub(MAX_STATS_COMMITMENTS);
+        samples.commitments.drain(..excess);
+
+        samples.slots.push_back(outcome);
+        let since_ms = at_ms.saturating_sub(OUTCOMES_WINDOW.as_millis() as u64);
+        while samples.slots.front().is_some_and(|slot| slot.at_ms < since_ms) {
+            samples.slots.pop_front();
+        }
+    }
+
+    /// Outcome of the commitments finalized over the last [`OUTCOMES_WINDOW`].
+    pub fn recent_outcomes(&self) -> RecentOutcomes {
+        self.outcomes_at(now_ms())
+    }
+
+    fn outcomes_at(&self, now_ms: u64) -> RecentOutcomes {
+        let since_ms = now_ms.saturating_sub(OUTCOMES_WINDOW.as_millis() as u64);
+        let samples = self.0.read();
+        let slots = samples.slots.iter().filter(|slot| slot.at_ms >= since_ms);
+
+        let (mut outcomes, mut landed, mut latency_ms) = (RecentOutcomes::default(), 0, 0);
+        for slot in slots {
+            outcomes.honored += slot.honored;
+            outcomes.broken += slot.broken;
+            landed += slot.landed as u64;
+            latency_ms += slot.latency_ms;
+        }
+        outcomes.avg_inclusion_latency_ms = (landed > 0).then(|| latency_ms / landed);
+        outcomes
+    }
+
+    pub fn summary(&self) -> ReliabilitySummary {
+        let samples = self.0.read();
+        let samples = &samples.commitments;
+        let landed = samples.iter().flatten();
+
+        let mut latencies = landed.clone().map(|(_, latency)| *latency).collect::<Vec<_>>();
+        latencies.sort_unstable();
+        let percentile = |p: usize| {
+            (!latencies.is_empty()).then(|| latencies[(latencies.len() - 1) * p / 100])
+        };
+
+        ReliabilitySummary {
+            commitments: samples.len(),
+            honored: landed.clone().filter(|(offset, _)| *offset == 0).count(),
+            late: landed.filter(|(offset, _)| *offset > 0).count(),
+            latency_ms_p50: percentile(50),
+            latency_ms_p90: percentile(90),
+            latency_ms_p99: percentile(99),
+        }
+    }
+}
+
+/// Finalizes the commitments of the slots we committed constraints in as soon as their block
+/// is imported, rather than on the next head update.
+#[derive(Debug, Clone)]
+pub struct InclusionTracker {
+    pending: Arc<RwLock<HashMap<u64, Vec<TrackedCommitment>>>>,
+    client: ExecutionClient,
+    slot_clock: SlotClock,
+    webhook_url: Option<Url>,
+    http: reqwest::Client,
+    events: EventBroadcaster,
+    stats: InclusionStats,
+}
+
+impl InclusionTracker {
+    pub fn new(
+        client: ExecutionClient,
+        slot_clock: SlotClock,
+        webhook_url: Option<Url>,
+        events: EventBroadcaster,
+        stats: InclusionStats,
+    ) -> Self {
+        Self {
+            pending: Default::default(),
+            client,
+            slot_clock,
+            webhook_url,
+            http: reqwest::Client::new(),
+            events,
+            stats,
+        }
+    }
+
+    /// Track the transactions committed in `slot` until its block is imported. Slots whose
+    /// block never came are forgotten after an epoch.
+    pub fn track(&self, slot: u64, commitments: Vec<TrackedCommitment>) {
+        if commitments.is_empty() {
+            return;
+        }
+
+        let mut pending = self.pending.write();
+        let retention_slots = self.slot_clock.slots_per_epoch();
+        pending.retain(|s, _| *s + retention_slots > slot);
+        // Extended by the submissions following an early one
+        pending.entry(slot).or_default().extend(commitments);
+    }
+
+    /// Classify the commitments of `slot` once its block was imported, and report the
+    /// outcome. Returns `None` if we didn't commit anything in the slot.
+    pub async fn finalize(&self, slot: u64) -> Option<InclusionReport> {
+        let commitments = self.pending.write().remove(&slot)?;
+
+        let report = match self.classify(slot, &commitments).await {
+            Ok(report) => report,
+            Err(err) => {
+                tracing::error!(?err, slot, "Failed to fetch the inclusion of the commitments");
+                return None;
+            }
+        };
+
+        ApiMetrics::increment_commitments_inclusion_count("included", report.included.len() as u64);
+        ApiMetrics::increment_commitments_inclusion_count("missing", report.missing.len() as u64);
+        for inclusion in &report.inclusions {
+            ApiMetrics::observe_commitment_inclusion(
+                Duration::from_millis(inclusion.latency_ms),
+                inclusion.slot_offset(slot),
+            );
+        }
+        self.stats.record(&report);
+        if report.is_honored() {
+            tracing::info!(slot, included = report.included.len(), "Commitments honored");
+        } else {
+            tracing::warn!(slot, missing = ?report.missing, "Commitments broken");
+        }
+
+        self.events.send(ApiEvent::CommitmentsFinalized {
+            slot,
+            honored: report.is_honored(),
+            included: report.included.len(),
+            missing: report.missing.len(),
+        });
+
+        if let Some(url) = self.webhook_url.clone() {
+            let request = self.http.post(url).json(&report);
+            tokio::spawn(async move {
+                if let Err(err) = request.send().await.and_then(|r| r.error_for_status()) {
+                    tracing::error!(?err, "Failed to push the inclusion report");
+                }
+            });
+        }
+
+        Some(report)
+    }
+
+    async fn classify(
+        &self,
+        slot: u64,
+        commitments: &[TrackedCommitment],
+    ) -> TransportResult<InclusionReport> {
+        let hashes = commitments.iter().map(|c| c.hash).collect::<Vec<_>>();
+
+        // Timestamp of the block each landed transaction is in, in seconds
+        let mut landed = HashMap::new();
+        for attempt in 1..=FINALIZE_ATTEMPTS {
+            let receipts = self.client.get_receipts(&hashes).await?;
+
+            let mut timestamps = HashMap::new();
+            landed.clear();
+            for receipt in receipts.iter().flatten() {
+                let Some(number) = receipt.block_number else { continue };
+                let timestamp = match timestamps.get(&number) {
+                    Some(timestamp) => *timestamp,
+                    None => {
+                        let timestamp = self
+                            .client
+                            .get_block_timestamp(BlockNumberOrTag::Number(number))
+                            .await?;
+                        *timestamps.entry(number).or_insert(timestamp)
+                    }
+                };
+                landed.insert(receipt.transaction_hash, timestamp);
+            }
+
+            let in_slot = landed.values().filter(|t| self.slot_clock.slot_at(**t) == slot).count();
+            if in_slot == hashes.len() || attempt == FINALIZE_ATTEMPTS {
+                break;
+            }
+            tokio::time::sleep(FINALIZE_RETRY_DELAY).await;
+        }
+
+        let inclusions = commitments
+            .iter()
+            .filter_map(|commitment| {
+                let timestamp = *landed.get(&commitment.hash)?;
+                Some(Inclusion {
+                    hash: commitment.hash,
+                    slot: self.slot_clock.slot_at(timestamp),
+                    latency_ms: (timestamp * 1_000).saturating_sub(commitment.accepted_ms),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // Only the transactions landed in the block of the slot honor the commitments
+        let (included, missing) = hashes.into_iter().partition(|hash| {
+            inclusions.iter().any(|inclusion| inclusion.hash == *hash && inclusion.slot == slot)
+        });
+        Ok(InclusionReport { slot, included, missing, inclusions })
+    }
+}
+
+fn deserialize_quoted_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    s.parse().map_err(de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_v092::primitives::TxHash;
+
+    use super::{BlockEvent, Inclusion, InclusionReport, InclusionStats, OUTCOMES_WINDOW};
+
+    #[test]
+    fn test_block_event_deserialization() {
+        let event: BlockEvent = serde_json::from_str(
+            r#"{
+                "slot": "10",
+                "block": "0x9a2fefd2fdb57f74993c7780ea5b9030d2897b615b89f808011ca5aebed54eaf",
+                "execution_optimistic": false
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(event.slot, 10);
+        assert!(!event.execution_optimistic);
+    }
+
+    #[test]
+    fn test_inclusion_stats() {
+        let hash = |byte| TxHash::repeat_byte(byte);
+        let inclusion = |byte, slot, latency_ms| Inclusion { hash: hash(byte), slot, latency_ms };
+
+        let stats = InclusionStats::default();
+        let report = InclusionReport {
+            slot: 10,
+            included: vec![hash(1), hash(2)],
+            missing: vec![hash(3), hash(4)],
+            inclusions: vec![
+                inclusion(1, 10, 3_000),
+                inclusion(2, 10, 1_000),
+                inclusion(3, 11, 15_000),
+            ],
+        };
+        stats.record_at(&report, 1_000);
+
+        let summary = stats.summary();
+        assert_eq!(summary.commitments, 4);
+        assert_eq!((summary.honored, summary.late), (2, 1));
+        assert_eq!(summary.latency_ms_p50, Some(3_000));
+        assert_eq!(summary.latency_ms_p99, Some(3_000));
+
+        let outcomes = stats.outcomes_at(1_000);
+        assert_eq!((outcomes.honored, outcomes.broken), (2, 2));
+        assert_eq!(outcomes.avg_inclusion_latency_ms, Some(6_333));
+
+        let day_later = 1_000 + OUTCOMES_WINDOW.as_millis() as u64 + 1;
+        assert_eq!(stats.outcomes_at(day_later).honored, 0);
+        assert_eq!(stats.outcomes_at(day_later).avg_inclusion_latency_ms, None);
+    }
+}
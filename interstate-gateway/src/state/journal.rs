@@ -0,0 +1,178 @@
+use std::{path::Path, sync::Arc};
+
+#[cfg(feature = "sqlite-journal")]
+use parking_lot::Mutex;
+
+use super::pricing::PriorityTier;
+use crate::constraints::SignedConstraints;
+
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+    #[cfg(feature = "sqlite-journal")]
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("constraints journal not compiled in")]
+    Disabled,
+}
+
+/// Durable journal of the signed constraints of the pending blocks, so that the constraints
+/// accepted before a crash are still submitted at the commitment deadline after a restart.
+pub trait ConstraintJournal: Send + Sync {
+    /// Record constraints signed for `slot` with the tier of their commitment, durably before
+    /// returning.
+    fn append(
+        &self,
+        slot: u64,
+        priority: PriorityTier,
+        constraints: &SignedConstraints,
+    ) -> Result<(), JournalError>;
+
+    /// The constraints recorded for the slots after `slot` with their tier, in the order they
+    /// were recorded.
+    fn pending(
+        &self,
+        slot: u64,
+    ) -> Result<Vec<(u64, PriorityTier, SignedConstraints)>, JournalError>;
+
+    /// Forget the constraints of `slot` and the slots before, which can no longer be included.
+    fn prune(&self, slot: u64) -> Result<(), JournalError>;
+}
+
+/// Open the journal at `path`, creating it if needed.
+pub fn open_journal(path: &Path) -> Result<Arc<dyn ConstraintJournal>, JournalError> {
+    #[cfg(feature = "sqlite-journal")]
+    return Ok(Arc::new(SqliteJournal::open(path)?));
+
+    #[cfg(not(feature = "sqlite-journal"))]
+    {
+        let _ = path;
+        Err(JournalError::Disabled)
+    }
+}
+
+/// Journal stored in a SQLite database, one row per signed constraints message.
+#[cfg(feature = "sqlite-journal")]
+pub struct SqliteJournal {
+    conn: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite-journal")]
+impl SqliteJournal {
+    pub fn open(path: &Path) -> Result<Self, JournalError> {
+        let conn = rusqlite::Connection::open(path)?;
+        // Appends are committed to the write-ahead log, synced on every commit
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "synchronous", "FULL")?;
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS constraints (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                slot INTEGER NOT NULL,
+                message BLOB NOT NULL,
+                priority TEXT NOT NULL DEFAULT 'standard'
+            )",
+            [],
+        )?;
+        // The journals written before the tiers were recorded restore at the standard one
+        let has_priority: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('constraints') WHERE name = 'priority'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_priority {
+            conn.execute(
+                "ALTER TABLE constraints ADD COLUMN priority TEXT NOT NULL DEFAULT 'standard'",
+                [],
+            )?;
+        }
+        conn.execute("CREATE INDEX IF NOT EXISTS constraints_slot ON constraints (slot)", [])?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+}
+
+#[cfg(feature = "sqlite-journal")]
+impl ConstraintJournal for SqliteJournal {
+    fn append(
+        &self,
+        slot: u64,
+        priority: PriorityTier,
+        constraints: &SignedConstraints,
+    ) -> Result<(), JournalError> {
+        let message = serde_json::to_vec(constraints)?;
+        self.conn.lock().execute(
+            "INSERT INTO constraints (slot, message, priority) VALUES (?1, ?2, ?3)",
+            rusqlite::params![slot as i64, message, priority.as_str()],
+        )?;
+        Ok(())
+    }
+
+    fn pending(
+        &self,
+        slot: u64,
+    ) -> Result<Vec<(u64, PriorityTier, SignedConstraints)>, JournalError> {
+        let conn = self.conn.lock();
+        let mut statement = conn.prepare(
+            "SELECT slot, priority, message FROM constraints WHERE slot > ?1 ORDER BY id",
+        )?;
+        let rows = statement.query_map([slot as i64], |row| {
+            Ok((row.get::<_, i64>(0)? as u64, row.get::<_, String>(1)?, row.get::<_, Vec<u8>>(2)?))
+        })?;
+
+        let mut pending = Vec::new();
+        for row in rows {
+            let (slot, priority, message) = row?;
+            let priority = serde_json::from_value(serde_json::Value::String(priority))?;
+            pending.push((slot, priority, serde_json::from_slice(&message)?));
+        }
+        Ok(pending)
+    }
+
+    fn prune(&self, slot: u64) -> Result<(), JournalError> {
+        self.conn.lock().execute("DELETE FROM constraints WHERE slot <= ?1", [slot as i64])?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "sqlite-journal"))]
+mod tests {
+    use super::{ConstraintJournal, SqliteJournal};
+    use crate::{
+        constraints::{ConstraintsMessage, SignedConstraints},
+        state::pricing::PriorityTier,
+    };
+
+    #[test]
+    fn test_sqlite_journal_survives_restart() {
+        let path = std::env::temp_dir().join(format!("constraints-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let signed = |slot: u64, top: bool| SignedConstraints {
+            message: ConstraintsMessage { slot, top, ..Default::default() },
+            signature: Default::default(),
+        };
+
+        let journal = SqliteJournal::open(&path).unwrap();
+        journal.append(10, PriorityTier::Standard, &signed(10, false)).unwrap();
+        journal.append(11, PriorityTier::Top, &signed(11, true)).unwrap();
+        journal.append(11, PriorityTier::Fast, &signed(11, false)).unwrap();
+        drop(journal);
+
+        // The tiers are restored along with the constraints
+        let journal = SqliteJournal::open(&path).unwrap();
+        let pending = journal.pending(10).unwrap();
+        assert_eq!(
+            pending,
+            vec![
+                (11, PriorityTier::Top, signed(11, true)),
+                (11, PriorityTier::Fast, signed(11, false))
+            ]
+        );
+
+        journal.prune(11).unwrap();
+        assert!(journal.pending(0).unwrap().is_empty());
+
+        drop(journal);
+        std::fs::remove_file(path).unwrap();
+    }
+}
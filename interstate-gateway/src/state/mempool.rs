@@ -0,0 +1,469 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+use alloy::primitives::{Address as RequestSender, PrimitiveSignature, U256};
+use alloy_v092::primitives::{Address, TxHash, U128, U64};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{execution_client::ExecutionClient, slot_clock::SlotClock};
+use crate::{
+    commitment::request::{CommitmentRequestEvent, PreconfRequest},
+    constraints::Constraint,
+    metrics::ApiMetrics,
+};
+
+/// Default number of slots a transaction must be pending before it is forced in.
+pub const DEFAULT_INCLUSION_LIST_MIN_PENDING_SLOTS: u64 = 3;
+/// Default minimum priority fee of the transactions forced in, 1 gwei.
+pub const DEFAULT_INCLUSION_LIST_MIN_PRIORITY_FEE: u128 = 1_000_000_000;
+/// Default maximum gas limit of a transaction forced in.
+pub const DEFAULT_INCLUSION_LIST_MAX_GAS: u64 = 1_000_000;
+/// Default maximum number of transactions forced in per slot.
+pub const DEFAULT_INCLUSION_LIST_MAX_TXS: usize = 8;
+
+/// Type of the blob transactions, whose sidecars aren't served with the raw transaction.
+const BLOB_TX_TYPE: u64 = 3;
+
+/// Operator policy selecting the pending transactions forced in our own proposals, as
+/// constraints, to resist censorship.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InclusionListPolicy {
+    /// Slots a transaction must have been pending for, i.e. skipped by other proposers.
+    pub min_pending_slots: u64,
+    /// Minimum priority fee, so that we don't force in transactions that just underpay.
+    pub min_priority_fee: u128,
+    /// Maximum gas limit of a transaction.
+    pub max_gas: u64,
+    /// Maximum number of transactions forced in per slot.
+    pub max_txs: usize,
+}
+
+impl Default for InclusionListPolicy {
+    fn default() -> Self {
+        Self {
+            min_pending_slots: DEFAULT_INCLUSION_LIST_MIN_PENDING_SLOTS,
+            min_priority_fee: DEFAULT_INCLUSION_LIST_MIN_PRIORITY_FEE,
+            max_gas: DEFAULT_INCLUSION_LIST_MAX_GAS,
+            max_txs: DEFAULT_INCLUSION_LIST_MAX_TXS,
+        }
+    }
+}
+
+/// Response of `txpool_content`, only the executable transactions are kept.
+#[derive(Debug, Default, Deserialize)]
+pub struct TxpoolContent {
+    pending: HashMap<Address, BTreeMap<String, PendingTx>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PendingTx {
+    hash: TxHash,
+    nonce: U64,
+    gas: U64,
+    #[serde(rename = "type", default)]
+    tx_type: U64,
+    gas_price: Option<U128>,
+    max_priority_fee_per_gas: Option<U128>,
+}
+
+impl PendingTx {
+    fn priority_fee(&self) -> u128 {
+        self.max_priority_fee_per_gas.or(self.gas_price).unwrap_or_default().to()
+    }
+}
+
+/// What to do with a request committing a transaction that a pending transaction of the
+/// same sender and nonce, paying a higher priority fee, would replace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReplacementPolicy {
+    /// The mempool isn't checked.
+    #[default]
+    Off,
+    /// Commit the transaction anyway, logging the replacement.
+    Warn,
+    /// Refuse to commit the transaction.
+    Reject,
+}
+
+impl FromStr for ReplacementPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("unknown policy `{other}`, expected off, warn or reject")),
+        }
+    }
+}
+
+impl fmt::Display for ReplacementPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Off => "off",
+            Self::Warn => "warn",
+            Self::Reject => "reject",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PooledTx {
+    hash: TxHash,
+    priority_fee: u128,
+}
+
+/// A pending transaction that would replace a transaction of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Replacement {
+    /// Index of the replaced transaction in the request.
+    pub index: usize,
+    pub hash: TxHash,
+}
+
+/// Detects the transactions of a request that are about to be replaced by a transaction
+/// already broadcast to the public mempool, with the same sender and nonce and a higher
+/// priority fee. Committing to them would be a broken promise, as the replacement
+/// invalidates them once included.
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementGuard {
+    policy: ReplacementPolicy,
+    pool: Arc<RwLock<HashMap<(Address, u64), PooledTx>>>,
+}
+
+impl ReplacementGuard {
+    pub fn new(policy: ReplacementPolicy) -> Self {
+        Self { policy, pool: Default::default() }
+    }
+
+    pub fn policy(&self) -> ReplacementPolicy {
+        self.policy
+    }
+
+    /// Poll the mempool every `interval` in the background, unless the policy is off.
+    pub fn spawn(&self, client: ExecutionClient, interval: Duration) {
+        if self.policy == ReplacementPolicy::Off {
+            return;
+        }
+        let guard = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match client.get_txpool_content().await {
+                    Ok(content) => guard.update(content),
+                    Err(err) => tracing::error!(?err, "Failed to poll the mempool"),
+                }
+            }
+        });
+    }
+
+    fn update(&self, content: TxpoolContent) {
+        let pool = content
+            .pending
+            .into_iter()
+            .flat_map(|(sender, txs)| {
+                txs.into_values().map(move |tx| {
+                    let pooled = PooledTx { hash: tx.hash, priority_fee: tx.priority_fee() };
+                    ((sender, tx.nonce.to::<u64>()), pooled)
+                })
+            })
+            .collect();
+        *self.pool.write() = pool;
+    }
+
+    /// The first transaction of `request` a pending transaction would replace.
+    ///
+    /// A pending transaction paying the same fee or less is either the transaction itself or
+    /// one it replaces, and is ignored.
+    pub fn find_replacement(&self, request: &PreconfRequest) -> Option<Replacement> {
+        if self.policy == ReplacementPolicy::Off {
+            return None;
+        }
+
+        let pool = self.pool.read();
+        request.txs.iter().enumerate().find_map(|(index, constraint)| {
+            let signer = constraint.sender.or_else(|| constraint.tx.recover_signer())?;
+            let key = (Address::from_slice(signer.as_slice()), constraint.tx.nonce());
+            let pooled = pool.get(&key)?;
+            let priority_fee = constraint
+                .tx
+                .max_priority_fee_per_gas()
+                .unwrap_or_else(|| constraint.tx.max_fee_per_gas());
+
+            (pooled.hash.as_slice() != constraint.tx.hash().as_slice() &&
+                pooled.priority_fee > priority_fee)
+                .then_some(Replacement { index, hash: pooled.hash })
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    first_seen_slot: u64,
+    priority_fee: u128,
+    gas: u64,
+    is_blob: bool,
+}
+
+/// Watches the mempool for transactions stuck for several slots, and forces the ones matching
+/// the [InclusionListPolicy] in our own proposals.
+///
+/// Only the lowest nonce of each sender is considered, so that a forced transaction is always
+/// executable on top of the chain.
+#[derive(Debug, Clone)]
+pub struct MempoolWatcher {
+    candidates: Arc<RwLock<HashMap<TxHash, Candidate>>>,
+    submitted: Arc<RwLock<HashSet<TxHash>>>,
+    client: ExecutionClient,
+    slot_clock: SlotClock,
+    policy: InclusionListPolicy,
+}
+
+impl MempoolWatcher {
+    pub fn new(client: ExecutionClient, slot_clock: SlotClock, policy: InclusionListPolicy) -> Self {
+        Self {
+            candidates: Default::default(),
+            submitted: Default::default(),
+            client,
+            slot_clock,
+            policy,
+        }
+    }
+
+    /// Poll the mempool every `interval` in the background.
+    pub fn spawn(&self, interval: Duration) {
+        let watcher = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match watcher.client.get_txpool_content().await {
+                    Ok(content) => watcher.update(content, watcher.slot_clock.current_slot()),
+                    Err(err) => tracing::error!(?err, "Failed to poll the mempool"),
+                }
+            }
+        });
+    }
+
+    fn update(&self, content: TxpoolContent, slot: u64) {
+        let heads = content
+            .pending
+            .into_values()
+            .filter_map(|txs| txs.into_values().min_by_key(|tx| tx.nonce))
+            .collect::<Vec<_>>();
+
+        let mut candidates = self.candidates.write();
+        let previous = std::mem::take(&mut *candidates);
+        for tx in heads {
+            let first_seen_slot = previous.get(&tx.hash).map_or(slot, |c| c.first_seen_slot);
+            candidates.insert(
+                tx.hash,
+                Candidate {
+                    first_seen_slot,
+                    priority_fee: tx.priority_fee(),
+                    gas: tx.gas.to(),
+                    is_blob: tx.tx_type.to::<u64>() == BLOB_TX_TYPE,
+                },
+            );
+        }
+
+        // Transactions gone from the mempool were included or dropped
+        self.submitted.write().retain(|hash| candidates.contains_key(hash));
+    }
+
+    /// The transactions to force in `slot`, the longest pending first.
+    pub fn inclusion_list(&self, slot: u64) -> Vec<TxHash> {
+        let submitted = self.submitted.read();
+        let mut list = self
+            .candidates
+            .read()
+            .iter()
+            .filter(|(hash, candidate)| {
+                !submitted.contains(*hash) &&
+                    !candidate.is_blob &&
+                    slot.saturating_sub(candidate.first_seen_slot) >=
+                        self.policy.min_pending_slots &&
+                    candidate.priority_fee >= self.policy.min_priority_fee &&
+                    candidate.gas <= self.policy.max_gas
+            })
+            .map(|(hash, candidate)| (candidate.first_seen_slot, *hash))
+            .collect::<Vec<_>>();
+
+        list.sort();
+        list.into_iter().take(self.policy.max_txs).map(|(_, hash)| hash).collect()
+    }
+
+    /// Commit the inclusion list of `slot` through the commitment requests loop, which
+    /// validates the transactions against the block template and signs their constraints.
+    pub async fn submit(
+        &self,
+        slot: u64,
+        chain_id: u64,
+        event_sender: mpsc::Sender<CommitmentRequestEvent>,
+    ) {
+        for hash in self.inclusion_list(slot) {
+            let raw = match self.client.get_raw_transaction(hash).await {
+                Ok(Some(raw)) => raw,
+                Ok(None) => continue,
+                Err(err) => {
+                    tracing::error!(?err, %hash, "Failed to fetch the raw pending transaction");
+                    continue;
+                }
+            };
+
+            let constraint = match Constraint::decode_enveloped(raw) {
+                Ok(constraint) => constraint,
+                Err(err) => {
+                    tracing::warn!(?err, %hash, "Failed to decode the pending transaction");
+                    continue;
+                }
+            };
+
+            let req = PreconfRequest {
+                slot,
+                txs: vec![constraint],
+                signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+                sender: RequestSender::ZERO,
+                chain_id,
+                quote: None,
+                atomic: false,
+                partial: false,
+                priority: Default::default(),
+                inclusion_list: true,
+            };
+
+            let (res, response) = oneshot::channel();
+            if event_sender.send(CommitmentRequestEvent { req, res }).await.is_err() {
+                return;
+            }
+
+            match response.await {
+                Ok(Ok(_)) => {
+                    tracing::info!(slot, %hash, "Forced a pending transaction in our proposal");
+                    ApiMetrics::increment_inclusion_list_count("committed");
+                    self.submitted.write().insert(hash);
+                }
+                Ok(Err(err)) => {
+                    tracing::debug!(?err, slot, %hash, "Pending transaction not forced in");
+                    ApiMetrics::increment_inclusion_list_count("rejected");
+                }
+                Err(_) => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::EthereumWallet,
+        primitives::{PrimitiveSignature, U256},
+        signers::local::PrivateKeySigner,
+    };
+
+    use super::{
+        InclusionListPolicy, MempoolWatcher, ReplacementGuard, ReplacementPolicy, TxpoolContent,
+    };
+    use crate::{
+        commitment::request::PreconfRequest,
+        constraints::Constraint,
+        state::{execution_client::ExecutionClient, slot_clock::SlotClock},
+        test_utils::default_test_transaction,
+    };
+
+    fn content(txs: &str) -> TxpoolContent {
+        serde_json::from_str(&format!(r#"{{ "pending": {{ {txs} }}, "queued": {{}} }}"#)).unwrap()
+    }
+
+    #[test]
+    fn test_inclusion_list_policy() {
+        let watcher = MempoolWatcher::new(
+            ExecutionClient::new("http://localhost:8545".parse::<reqwest::Url>().unwrap()),
+            SlotClock::new(0, 12, 0),
+            InclusionListPolicy { min_pending_slots: 2, ..Default::default() },
+        );
+
+        let hash = |n: u8| format!("0x{}", format!("{n:02x}").repeat(32));
+        let pool = content(&format!(
+            r#"
+            "0x0000000000000000000000000000000000000001": {{
+                "4": {{ "hash": "{}", "nonce": "0x4", "gas": "0x5208", "type": "0x2", "maxPriorityFeePerGas": "0x77359400" }},
+                "5": {{ "hash": "{}", "nonce": "0x5", "gas": "0x5208", "type": "0x2", "maxPriorityFeePerGas": "0x77359400" }}
+            }},
+            "0x0000000000000000000000000000000000000002": {{
+                "0": {{ "hash": "{}", "nonce": "0x0", "gas": "0x5208", "type": "0x0", "gasPrice": "0x1" }}
+            }}"#,
+            hash(1),
+            hash(2),
+            hash(3),
+        ));
+        watcher.update(pool, 10);
+        assert!(watcher.inclusion_list(11).is_empty());
+
+        // Only the lowest nonce paying enough is forced in once pending long enough
+        let list = watcher.inclusion_list(12);
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].to_string(), hash(1));
+
+        // Gone from the mempool
+        watcher.update(content(""), 12);
+        assert!(watcher.inclusion_list(12).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_replacement_paying_more() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let raw = default_test_transaction(signer.address(), Some(7)).build(&wallet).await?;
+        let request = PreconfRequest {
+            slot: 1,
+            txs: vec![Constraint::decode_enveloped(raw.encoded_2718())?],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: signer.address(),
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+
+        // The request pays a priority fee of 1 gwei
+        let pool = |hash: String, priority_fee: &str| {
+            content(&format!(
+                r#""{}": {{ "7": {{ "hash": "{hash}", "nonce": "0x7", "gas": "0x5208", "maxPriorityFeePerGas": "{priority_fee}" }} }}"#,
+                signer.address()
+            ))
+        };
+        let other = format!("0x{}", "09".repeat(32));
+        let guard = ReplacementGuard::new(ReplacementPolicy::Reject);
+
+        guard.update(pool(other.clone(), "0x77359400"));
+        let replacement = guard.find_replacement(&request).unwrap();
+        assert_eq!((replacement.index, replacement.hash.to_string()), (0, other.clone()));
+
+        // Replaced by the request, or the transaction of the request itself
+        guard.update(pool(other.clone(), "0x3b9aca00"));
+        assert!(guard.find_replacement(&request).is_none());
+        guard.update(pool(raw.tx_hash().to_string(), "0x77359400"));
+        assert!(guard.find_replacement(&request).is_none());
+
+        // The mempool isn't checked when off
+        let guard = ReplacementGuard::default();
+        guard.update(pool(other, "0x77359400"));
+        assert!(guard.find_replacement(&request).is_none());
+
+        Ok(())
+    }
+}
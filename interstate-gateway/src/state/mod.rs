@@ -1,21 +1,45 @@
 pub mod account_state;
+pub mod archive;
+pub mod audit;
+pub mod budget;
+pub mod capacity;
 pub mod execution;
 pub mod execution_client;
 pub mod fetcher;
+pub mod inclusion;
+pub mod journal;
+pub mod mempool;
 pub mod pricing;
+pub mod reservations;
+pub mod revenue;
+pub mod scheduler;
+pub mod schema;
+pub mod shards;
 pub mod signature;
+pub mod slot_clock;
+pub mod stale;
+pub mod status;
+pub mod store;
+pub mod sync;
+pub mod wal;
 
 use std::{
     collections::HashMap,
     mem,
     num::NonZero,
     pin::Pin,
+    sync::Arc,
     task::{Context, Poll},
     time::{Duration, Instant},
 };
 
-use alloy::rpc::types::beacon::events::HeadEvent;
+use alloy::{
+    eips::eip4844::{BYTES_PER_BLOB, BYTES_PER_COMMITMENT, BYTES_PER_PROOF},
+    primitives::B256,
+    rpc::types::beacon::events::HeadEvent,
+};
 use alloy_v092::consensus::{Signed, TxEip1559, TxEip2930, TxEip4844, TxEip7702, TxLegacy};
+use alloy_v092::primitives::TxHash;
 use beacon_api_client::Topic;
 use beacon_api_client::{mainnet::Client, BlockId, ProposerDuty};
 use ethereum_consensus::{
@@ -25,10 +49,11 @@ use ethereum_consensus::{
         mainnet::{Blob, BlobsBundle},
         BeaconBlockHeader,
     },
-    phase0::mainnet::SLOTS_PER_EPOCH,
 };
-use execution::ExecutionState;
+use archive::{BeaconResponses, SlotArchive, SlotEvent};
+use execution::{ExecutionState, ValidationError};
 use fetcher::ClientState;
+use pricing::{PriorityTier, Underpriced};
 use futures::StreamExt;
 use futures::{future::poll_fn, Future, FutureExt};
 use reth_primitives::PooledTransactionsElement::{
@@ -36,21 +61,38 @@ use reth_primitives::PooledTransactionsElement::{
 };
 use reth_primitives::{PooledTransactionsElement, TransactionSigned};
 use reth_primitives_v115::PooledTransaction;
+use shards::SlotShards;
 use signature::AlloySignatureWrapper;
 use tokio::time::Sleep;
-use tokio::{sync::broadcast, task::AbortHandle};
+use tokio::{
+    sync::{broadcast, Mutex},
+    task::AbortHandle,
+};
 
 use crate::{
-    constraints::{SignedConstraints, TransactionExt},
-    metrics::ApiMetrics,
+    commitment::forward::{update_proposers, SharedProposers},
+    constraints::{
+        versioned::ConstraintsVersion, Constraint, ConstraintsSubmissionStatus,
+        SignedConstraints, TransactionExt,
+    },
+    metrics::{runtime::timed, ApiMetrics},
 };
 use tokio::time::error::Elapsed;
+use slot_clock::SlotClock;
+use inclusion::TrackedCommitment;
+use journal::{ConstraintJournal, JournalError};
+use reservations::ReservationBook;
+use status::StatusBoard;
 
 use crate::config::ChainConfig;
 use crate::config::ValidatorIndexes;
 use crate::{
     commitment::request::PreconfRequest,
-    utils::transactions::FullTransaction,
+    utils::{
+        breaker::{BreakerError, BreakerOpen, BreakerPolicy, CircuitBreaker, Dependency},
+        retry::{retry_with_backoff, RetryError, RetryPolicy},
+        transactions::FullTransaction,
+    },
 };
 
 #[derive(Debug, thiserror::Error)]
@@ -71,6 +113,48 @@ pub enum StateError {
     MaxRetriesExceeded,
     #[error("Timeout error: {0}")]
     Timeout(Elapsed),
+    #[error("blob limit of {limit} per block exceeded in slot {slot}: {committed} committed, {requested} requested")]
+    BlobLimitExceeded { slot: u64, committed: usize, requested: usize, limit: usize },
+    #[error("pending blob memory cap of {cap} bytes exceeded: {held} held, {requested} requested")]
+    BlobMemoryCap { held: usize, requested: usize, cap: usize },
+    #[error(transparent)]
+    CircuitOpen(#[from] BreakerOpen),
+    #[error(transparent)]
+    Underpriced(Underpriced),
+    #[error(transparent)]
+    Validation(ValidationError),
+}
+
+impl StateError {
+    /// Stable code of the error, reported with the transactions rejected from partial requests.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidSlot(_) => "invalid_slot",
+            Self::DeadlineExpired => "deadline_expired",
+            Self::NoValidatorInSlot => "no_validator_in_slot",
+            Self::BlobLimitExceeded { .. } => "blob_limit_exceeded",
+            Self::BlobMemoryCap { .. } => "blob_memory_cap",
+            Self::CircuitOpen(_) => "unavailable",
+            Self::Underpriced(_) => "underpriced",
+            Self::Validation(err) => err.to_tag_str(),
+            Self::Custom(_) => "invalid_transaction",
+            Self::FailedFetcingProposerDuties
+            | Self::BeaconApiError(_)
+            | Self::MaxRetriesExceeded
+            | Self::Timeout(_) => "internal_error",
+        }
+    }
+}
+
+/// Outcome of the validation of a partial request.
+#[derive(Debug)]
+pub struct PartialValidation {
+    /// Request of the accepted transactions only.
+    pub accepted: PreconfRequest,
+    /// Index in the original request of each accepted transaction.
+    pub accepted_indexes: Vec<usize>,
+    /// Index in the original request of each rejected transaction, and why it was rejected.
+    pub rejected: Vec<(usize, StateError)>,
 }
 
 #[derive(Debug, Default)]
@@ -82,9 +166,12 @@ pub struct Epoch {
 }
 
 pub struct ConstraintState {
-    pub blocks: HashMap<u64, Block>,
-    pub commitment_deadline: CommitmentDeadline,
+    /// Pending blocks, locked by slot independently of the rest of the state.
+    pub blocks: SlotShards,
+    /// Relay acknowledgment status of the constraints submitted for recent slots.
+    pub submissions: HashMap<u64, ConstraintsSubmissionStatus>,
     pub deadline_duration: Duration,
+    pub slot_clock: SlotClock,
     pub latest_slot: u64,
     pub latest_slot_timestamp: Instant,
     pub current_epoch: Epoch,
@@ -96,105 +183,235 @@ pub struct ConstraintState {
     pub max_tx_input_bytes: usize,
     pub max_init_code_byte_size: usize,
     pub config: ChainConfig,
+    /// Constraints message version negotiated with the relay, used to sign constraints.
+    pub constraints_version: ConstraintsVersion,
     pub beacon_client: Client,
-    pub execution: ExecutionState<ClientState>,
+    /// Locked on its own, so that the execution checks of a request don't hold the state.
+    pub execution: Arc<Mutex<ExecutionState<ClientState>>>,
+    /// Proposers of the current epoch, shared with the commitments API to forward requests.
+    pub proposers: SharedProposers,
+    /// Live state reported to the operators by the status endpoint.
+    pub status: StatusBoard,
+    /// How the calls to the beacon node are retried.
+    pub retry: RetryPolicy,
+    /// Fails the calls to the beacon node fast while it keeps failing.
+    pub beacon_breaker: CircuitBreaker,
+    /// Max bytes of blob sidecars held for the pending constraints of all the slots.
+    pub max_pending_blob_bytes: usize,
+    /// Whether the signatures of the constraints are batch verified at the deadline.
+    pub verify_constraints: bool,
+    /// Where the beacon node responses the head is updated from are archived, if anywhere.
+    pub archive: Option<SlotArchive>,
+    /// Recurring capacity reservations of the rollup sequencers, held back from the others.
+    pub reservations: ReservationBook,
+    /// Slot of the last commitment deadline reached, the constraints of the slot after it
+    /// being submitted then. Armed by the slot clock, so that a missed head event doesn't
+    /// leave the next slot open.
+    pub deadline_reached: Option<u64>,
+    /// Last slot whose constraints were submitted ahead of its deadline, the relays being
+    /// degraded.
+    pub submitted_early: Option<u64>,
+    /// Whether the relays accept the constraints committed after an early submission in a
+    /// second one. They are refused otherwise.
+    pub incremental_submissions: bool,
+    /// Durable copy of the pending constraints, restored after a restart.
+    journal: Option<Arc<dyn ConstraintJournal>>,
 }
 
-use tokio::time::timeout;
+/// Default cap of the commitments in a block.
+pub const DEFAULT_MAX_COMMITMENTS_IN_BLOCK: usize = 128;
 
-const TIMEOUT_SECS: u64 = 10;
-const MAX_RETRIES: u8 = 5;
-const RETRY_BACKOFF_MILLIS: u64 = 100;
+/// Default cap of the gas committed in a block.
+pub const DEFAULT_MAX_COMMITMENT_GAS: u64 = 10_000_000;
+
+/// Default cap of the memory held by the pending blob sidecars, 256 blobs.
+pub const DEFAULT_MAX_PENDING_BLOB_BYTES: usize = 256 * BYTES_PER_BLOB;
 
 impl ConstraintState {
     pub fn new(
         beacon_client: Client,
         commitment_deadline_duration: Duration,
+        slot_clock: SlotClock,
         execution: ExecutionState<ClientState>,
         config: &ChainConfig,
     ) -> Self {
         Self {
-            blocks: HashMap::new(),
-            commitment_deadline: CommitmentDeadline::new(0, Duration::from_millis(100)),
+            blocks: SlotShards::default(),
+            submissions: HashMap::new(),
             deadline_duration: commitment_deadline_duration,
+            slot_clock,
             latest_slot: Default::default(),
             latest_slot_timestamp: Instant::now(),
             current_epoch: Default::default(),
             beacon_client,
-            execution,
+            execution: Arc::new(Mutex::new(execution)),
             header: BeaconBlockHeader::default(),
-            max_commitments_in_block: 128,
-            max_commitment_gas: NonZero::new(10_000_000).unwrap(),
+            max_commitments_in_block: DEFAULT_MAX_COMMITMENTS_IN_BLOCK,
+            max_commitment_gas: NonZero::new(DEFAULT_MAX_COMMITMENT_GAS).unwrap(),
             min_priority_fee: 1_000_000_000,
             block_gas_limit: 30_000_000,
             max_tx_input_bytes: 4 * 32 * 1024,
             max_init_code_byte_size: 2 * 24576,
             config: config.clone(),
+            constraints_version: ConstraintsVersion::default(),
+            proposers: Default::default(),
+            status: Default::default(),
+            retry: Default::default(),
+            beacon_breaker: CircuitBreaker::new(Dependency::Beacon, BreakerPolicy::default()),
+            max_pending_blob_bytes: DEFAULT_MAX_PENDING_BLOB_BYTES,
+            verify_constraints: false,
+            archive: None,
+            reservations: ReservationBook::default(),
+            deadline_reached: None,
+            submitted_early: None,
+            incremental_submissions: false,
+            journal: None,
+        }
+    }
+
+    /// Journal the constraints added from now on, after restoring the ones journaled for the
+    /// slots that haven't started yet. Returns the number of restored constraints messages.
+    pub async fn attach_journal(
+        &mut self,
+        journal: Arc<dyn ConstraintJournal>,
+    ) -> Result<usize, JournalError> {
+        let current_slot = self.slot_clock.current_slot();
+        journal.prune(current_slot)?;
+        let pending = journal.pending(current_slot)?;
+
+        let restored = pending.len();
+        for (slot, priority, signed_constraints) in pending {
+            self.add_constraint(slot, signed_constraints, priority).await;
+        }
+        self.journal = Some(journal);
+        Ok(restored)
+    }
+
+    /// Record the commitment deadline reached in `slot`, refusing the requests to the slot after
+    /// it from now on.
+    pub fn reach_deadline(&mut self, slot: u64) {
+        self.deadline_reached = self.deadline_reached.max(Some(slot));
+    }
+
+    /// Report the head, the upcoming proposals and the pending constraints to the operators.
+    pub fn publish_status(&self) {
+        self.status.update(
+            self.latest_slot,
+            &self.current_epoch.proposer_duties,
+            self.blocks.pending_constraints(),
+        );
+    }
+
+    /// Add the constraints of a commitment of the `priority` tier to the block of `slot`.
+    pub async fn add_constraint(
+        &self,
+        slot: u64,
+        signed_constraints: SignedConstraints,
+        priority: PriorityTier,
+    ) {
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.append(slot, priority, &signed_constraints) {
+                tracing::error!(?err, slot, "Failed to journal the constraints");
+                self.status.record_error("journal", err);
+            }
         }
+        self.insert_constraint(slot, signed_constraints, priority).await;
     }
 
-    pub fn add_constraint(&mut self, slot: u64, signed_constraints: SignedConstraints) {
-        if let Some(block) = self.blocks.get_mut(&slot) {
-            block.add_constraints(signed_constraints);
-        } else {
-            let mut block = Block::default();
-            block.add_constraints(signed_constraints);
-            self.blocks.insert(slot, block);
+    /// Add the constraints journaled by the other instances sharing the journal, such as the one
+    /// this instance took over from, for the slots that haven't started yet. Returns the number
+    /// of constraints messages added.
+    pub async fn import_journal(&self) -> Result<usize, JournalError> {
+        let Some(journal) = &self.journal else {
+            return Ok(0);
+        };
+
+        let mut imported = 0;
+        let pending = journal.pending(self.slot_clock.current_slot())?;
+        for (slot, priority, signed_constraints) in pending {
+            let digest = signed_constraints.message.digest();
+            let known = self.blocks.with_block(slot, |block| block.digests().contains(&digest));
+            if known != Some(true) {
+                self.insert_constraint(slot, signed_constraints, priority).await;
+                imported += 1;
+            }
         }
+        Ok(imported)
     }
 
-    pub fn replace_constraints(&mut self, slot: u64, signed_constraints: &Vec<SignedConstraints>) {
+    async fn insert_constraint(
+        &self,
+        slot: u64,
+        signed_constraints: SignedConstraints,
+        priority: PriorityTier,
+    ) {
+        self.execution
+            .lock()
+            .await
+            .add_constraint(slot, signed_constraints.clone().into());
+
+        for constraint in &signed_constraints.message.transactions {
+            if let Some(sender) = constraint.sender {
+                self.reservations.record_usage(slot, sender, constraint.tx.gas_limit());
+            }
+        }
+
+        let accepted_ms = self.slot_clock.now_ms().max(0) as u64;
+        self.blocks.add_constraints(slot, signed_constraints, accepted_ms, priority);
+        self.record_blob_memory();
+        self.publish_status();
+    }
+
+    /// Bytes of blob sidecars held for the pending constraints of all the slots.
+    pub fn pending_blob_bytes(&self) -> usize {
+        self.blocks.blob_bytes()
+    }
+
+    fn record_blob_memory(&self) {
+        ApiMetrics::set_pending_blob_bytes(self.pending_blob_bytes());
+    }
+
+    pub fn replace_constraints(&self, slot: u64, signed_constraints: &Vec<SignedConstraints>) {
         tracing::debug!("here is replace constraints function");
-        if let Some(block) = self.blocks.get_mut(&slot) {
-            tracing::debug!(
-                "current constraints {}",
-                block.signed_constraints_list.len()
-            );
-            block.replace_constraints(signed_constraints);
-            tracing::debug!(
-                "replaced constraints {}",
-                block.signed_constraints_list.len()
-            );
-        } else {
-            let mut block = Block::default();
-            block.replace_constraints(signed_constraints);
-            self.blocks.insert(slot, block.clone());
-            tracing::debug!(
-                "replaced constraints {}",
-                block.signed_constraints_list.len()
-            );
-        }
-    }
-
-    pub fn remove_constraints_at_slot(&mut self, slot: u64) -> Option<Block> {
-        tracing::debug!("constraints block in slot {}, {:#?}", slot ,  self.blocks.get(&slot));
-        self.blocks.remove(&slot)
+        self.blocks.replace_constraints(slot, signed_constraints);
+        tracing::debug!("replaced constraints {}", signed_constraints.len());
+        self.record_blob_memory();
+    }
+
+    pub fn remove_constraints_at_slot(&self, slot: u64) -> Option<Block> {
+        let block = self.blocks.remove(slot);
+        tracing::debug!("constraints block in slot {}, {:#?}", slot, block);
+        self.record_blob_memory();
+        block
     }
 
+    /// Validate a request, under the commit lock of its slot so that the block can't fill up
+    /// before its constraints are added.
     pub async fn validate_preconf_request(
-        &mut self,
+        &self,
         mut request: PreconfRequest,
     ) -> Result<ECBlsPublicKey, StateError> {
         // Check if the chain is eth mainnet
         if request.chain_id != self.config.id {
-            return Err(StateError::Custom(format!(
-                "Invalid chain ID: expected {}, got {:?}",
-                self.config.id, request.chain_id
-            )));
+            tracing::debug!(expected = self.config.id, got = request.chain_id, "Invalid chain ID");
+            return Err(StateError::Validation(ValidationError::ChainIdMismatch));
         }
 
         // Check if the slot is in the current epoch
-        if request.slot < self.current_epoch.start_slot
-            || request.slot >= self.current_epoch.start_slot + SLOTS_PER_EPOCH
-        {
-            tracing::debug!("slots data: {},{},{}",request.slot,self.current_epoch.start_slot, self.current_epoch.start_slot + SLOTS_PER_EPOCH);
+        let epoch_end_slot = self.current_epoch.start_slot + self.config.slots_per_epoch;
+        if request.slot < self.current_epoch.start_slot || request.slot >= epoch_end_slot {
+            tracing::debug!("slots data: {},{},{}",request.slot,self.current_epoch.start_slot, epoch_end_slot);
             return Err(StateError::InvalidSlot(request.slot));
         }
 
-        // If the request is for the next slot, check if it's within the commitment deadline
-        if request.slot == self.latest_slot + 1
-            && self.latest_slot_timestamp + self.deadline_duration < Instant::now()
-        {
+        // Check that the commitment deadline, in the slot before the requested one, is ahead.
+        // Its block is submitted and removed once reached, whether its head event arrived or not
+        let reached = self.deadline_reached.is_some_and(|reached| request.slot <= reached + 1);
+        let deadline = request.slot.saturating_sub(1);
+        if reached || self.slot_clock.duration_until(deadline, self.deadline_duration).is_zero() {
+            return Err(StateError::DeadlineExpired);
+        }
+        if self.submitted_early == Some(request.slot) && !self.incremental_submissions {
             return Err(StateError::DeadlineExpired);
         }
 
@@ -202,56 +419,85 @@ impl ConstraintState {
         let public_key = self.find_validator_pubkey_for_slot(request.slot)?;
 
         if request.txs.len() >= self.max_commitments_in_block {
-            return Err(StateError::Custom(
-                "Overflow commitments amount".to_string(),
-            ));
+            return Err(StateError::Validation(ValidationError::MaxCommitmentsReachedForSlot(
+                request.slot,
+                self.max_commitments_in_block,
+            )));
         }
 
         // Check if there is room for more commitments
-        if let Some(block) = self.blocks.get(&request.slot) {
-            if block.transactions_count() + request.txs.len() >= self.max_commitments_in_block {
-                return Err(StateError::Custom(
-                    "Overflow commitments amount".to_string(),
-                ));
-            }
+        let (transactions_count, template_committed_gas, committed_blobs) = self
+            .blocks
+            .with_block(request.slot, |block| {
+                (block.transactions_count(), block.committed_gas(), block.blob_count())
+            })
+            .unwrap_or_default();
+        if transactions_count + request.txs.len() >= self.max_commitments_in_block {
+            return Err(StateError::Validation(ValidationError::MaxCommitmentsReachedForSlot(
+                request.slot,
+                self.max_commitments_in_block,
+            )));
         }
 
-        // Check if the committed gas exceeds the maximum
-        let template_committed_gas = self
-            .blocks
-            .get(&request.slot)
-            .map(|t| t.committed_gas())
-            .unwrap_or(0);
+        // Check if the committed gas exceeds the maximum, less the gas reserved by the
+        // sequencers other than the sender
+        let held_back = self.reservations.held_back(request.slot, Some(&request.sender));
+        if template_committed_gas.saturating_add(request.total_gas_limit()).saturating_add(held_back)
+            > self.max_commitment_gas.into()
+        {
+            tracing::debug!(slot = request.slot, held_back, "Refusing a request overflowing the committed gas");
+            return Err(StateError::Validation(ValidationError::MaxCommittedGasReachedForSlot(
+                request.slot,
+                self.max_commitment_gas.get(),
+            )));
+        }
 
-        if template_committed_gas + request.gas_limit() > self.max_commitment_gas.into() {
-            return Err(StateError::Custom("Overflow gas limit".to_string()));
+        // Check that the blobs fit in the block, as committed to the other requests
+        let requested_blobs = blob_count(&request.txs);
+        let limit = self.config.max_blobs_per_block;
+        if requested_blobs > 0 && committed_blobs + requested_blobs > limit {
+            return Err(StateError::BlobLimitExceeded {
+                slot: request.slot,
+                committed: committed_blobs,
+                requested: requested_blobs,
+                limit,
+            });
+        }
+
+        // Check that the blobs fit in the memory held for the pending blob sidecars, so that a
+        // burst of blob transactions can't exhaust it before the deadline
+        let requested = blob_sidecar_bytes(&request.txs);
+        if requested > 0 {
+            let held = self.pending_blob_bytes();
+            if held.saturating_add(requested) > self.max_pending_blob_bytes {
+                ApiMetrics::increment_blob_memory_rejections_count();
+                return Err(StateError::BlobMemoryCap {
+                    held,
+                    requested,
+                    cap: self.max_pending_blob_bytes,
+                });
+            }
         }
 
         // Check if the transaction size exceeds the maximum
         if !request.validate_tx_size_limit(self.max_tx_input_bytes) {
-            return Err(StateError::Custom(
-                "Overflow transaction size in input bytes".to_string(),
-            ));
+            return Err(StateError::Validation(ValidationError::TransactionSizeTooHigh));
         }
 
         // Check if the transaction is a contract creation and the init code size exceeds the
         // maximum
         if !request.validate_init_code_limit(self.max_init_code_byte_size) {
-            return Err(StateError::Custom(
-                "Overflow transaction size in code bytes".to_string(),
-            ));
+            return Err(StateError::Validation(ValidationError::InitCodeSizeTooHigh));
         }
 
-        // Check if the gas limit is higher than the maximum block gas limit
-        if request.gas_limit() > self.block_gas_limit {
-            return Err(StateError::Custom("Overflow gas limit".to_string()));
+        // Check if any transaction gas limit is higher than the maximum block gas limit
+        if request.max_tx_gas_limit() > self.block_gas_limit {
+            return Err(StateError::Validation(ValidationError::GasLimitTooHigh));
         }
 
         // Ensure max_priority_fee_per_gas is less than max_fee_per_gas
         if !request.validate_max_priority_fee() {
-            return Err(StateError::Custom(
-                "Overflow transaction priority fee".to_string(),
-            ));
+            return Err(StateError::Validation(ValidationError::MaxPriorityFeePerGasTooHigh));
         }
 
         // Check if the max_fee_per_gas would cover the maximum possible basefee.
@@ -259,23 +505,87 @@ impl ConstraintState {
 
         // TODO: Calculate the max possible basefee given the slot diff.
         if request.slot <= self.latest_slot {
-            return Err(StateError::Custom(
-                "Target slot is passed already".to_string(),
-            ));
+            return Err(StateError::InvalidSlot(request.slot));
         }
 
+        // The gas is estimated before locking the execution state, so that the requests to the
+        // other slots aren't held behind the round trips to the execution client
+        let estimator =
+            timed("execution_state", "lock", self.execution.lock()).await.gas_estimator();
+        let priced_gas = match estimator {
+            // Transactions forced in from the mempool don't pay for the commitment
+            Some(estimator) if !request.inclusion_list => estimator.priced_gas(&request).await,
+            _ => Ok(request.tx_gas_limits().collect()),
+        };
+
         // // Execution Layer Validation
-        let result = self.execution.verify_el_tx(&mut request).await;
+        let result = match priced_gas {
+            Ok(priced_gas) => {
+                timed("execution_state", "lock", self.execution.lock())
+                    .await
+                    .verify_el_tx(&mut request, &priced_gas)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
         match result {
             Ok(_) => Ok(public_key),
-            Err(err) => {
-                return Err(StateError::Custom(
-                    "Execution Layer Validation Failed!".to_string(),
-                ))
+            // Reported with the required tip, for the sender to raise it
+            Err(ValidationError::MaxPriorityFeePerGasTooLow(underpriced)) => {
+                Err(StateError::Underpriced(underpriced))
             }
+            Err(err) => Err(StateError::Validation(err)),
         }
     }
 
+    /// Validate the transactions of a partial request one at a time, each along with the ones
+    /// accepted before it, so that they are still valid once committed together. Fails like
+    /// [Self::validate_preconf_request] with the error of the first transaction when none is
+    /// accepted.
+    pub async fn validate_partial_request(
+        &self,
+        request: PreconfRequest,
+    ) -> Result<(ECBlsPublicKey, PartialValidation), StateError> {
+        let mut validation = PartialValidation {
+            accepted: PreconfRequest { txs: Vec::new(), ..request.clone() },
+            accepted_indexes: Vec::new(),
+            rejected: Vec::new(),
+        };
+        let mut pubkey = None;
+
+        for (index, tx) in request.txs.iter().enumerate() {
+            let mut candidate = validation.accepted.clone();
+            candidate.txs.push(tx.clone());
+            match self.validate_preconf_request(candidate.clone()).await {
+                Ok(key) => {
+                    pubkey = Some(key);
+                    validation.accepted = candidate;
+                    validation.accepted_indexes.push(index);
+                }
+                Err(err) => validation.rejected.push((index, err)),
+            }
+        }
+
+        match pubkey {
+            Some(pubkey) => Ok((pubkey, validation)),
+            None if validation.rejected.is_empty() => {
+                let pubkey = self.validate_preconf_request(request.clone()).await?;
+                Ok((pubkey, PartialValidation { accepted: request, ..validation }))
+            }
+            None => Err(validation.rejected.swap_remove(0).1),
+        }
+    }
+
+    /// Unix timestamp in milliseconds of the commitment deadline for `slot`, if it has a
+    /// known proposer in the current epoch.
+    pub fn commitment_deadline_ms(&self, slot: u64) -> Option<i64> {
+        self.find_validator_pubkey_for_slot(slot).ok()?;
+        Some(
+            self.slot_clock.slot_start_ms(slot.saturating_sub(1))
+                + self.deadline_duration.as_millis() as i64,
+        )
+    }
+
     fn find_validator_pubkey_for_slot(&self, slot: u64) -> Result<ECBlsPublicKey, StateError> {
         self.current_epoch
             .proposer_duties
@@ -289,101 +599,222 @@ impl ConstraintState {
         &self,
         head: u64,
     ) -> Result<BeaconBlockHeader, StateError> {
-        let mut retries_remaining = MAX_RETRIES;
-        let mut backoff_millis = RETRY_BACKOFF_MILLIS;
-
-        loop {
-            let result = timeout(
-                Duration::from_secs(TIMEOUT_SECS),
-                self.beacon_client.get_beacon_header(BlockId::Slot(head)),
-            )
-            .await
-            .map_err(StateError::Timeout)?;
+        let retried = retry_with_backoff("beacon_header", &self.retry, || {
+            self.beacon_client.get_beacon_header(BlockId::Slot(head))
+        });
+        let update = self.beacon_breaker.call(retried).await.map_err(|err| match err {
+            BreakerError::Open(open) => StateError::CircuitOpen(open),
+            BreakerError::Failed(RetryError::Timeout(elapsed)) => StateError::Timeout(elapsed),
+            BreakerError::Failed(RetryError::Failed(_)) => StateError::MaxRetriesExceeded,
+        })?;
 
-            if let Ok(update) = result {
-                return Ok(update.header.message);
-            }
+        Ok(update.header.message)
+    }
 
-            if retries_remaining == 0 {
-                return Err(StateError::MaxRetriesExceeded);
-            }
+    /// Move to `head`, with the slot clock corrected with the arrival of its event.
+    pub async fn update_head(&mut self, head: u64, slot_clock: SlotClock) -> Result<(), StateError> {
+        self.slot_clock = slot_clock;
 
-            retries_remaining -= 1;
-            tokio::time::sleep(Duration::from_millis(backoff_millis)).await;
-            backoff_millis *= 2;
+        let (responses, fetched) = self.fetch_beacon_responses(head).await;
+        if let Some(archive) = &self.archive {
+            archive.record(&SlotEvent::Beacon { slot: head, responses: &responses });
         }
+        self.apply_beacon_responses(head, responses);
+
+        fetched
     }
 
-    pub async fn update_head(&mut self, head: u64) -> Result<(), StateError> {
-        self.commitment_deadline = CommitmentDeadline::new(head + 1, self.deadline_duration);
+    /// Fetch the header of `head`, and the proposer duties of its epoch if it is a new one.
+    /// The responses received before a failure are returned along with it.
+    async fn fetch_beacon_responses(
+        &self,
+        head: u64,
+    ) -> (BeaconResponses, Result<(), StateError>) {
+        let header = match self.get_beacon_header_with_retry(head).await {
+            Ok(header) => header,
+            Err(err) => {
+                let responses =
+                    BeaconResponses { error: Some(err.to_string()), ..Default::default() };
+                return (responses, Err(err));
+            }
+        };
 
-        self.header = self.get_beacon_header_with_retry(head).await?;
+        let epoch = self.config.epoch_of(header.slot);
+        let mut responses = BeaconResponses { header: Some(header), ..Default::default() };
+        if epoch == self.current_epoch.value {
+            return (responses, Ok(()));
+        }
+        match self.fetch_proposer_duties(epoch).await {
+            Ok(duties) => {
+                responses.proposer_duties = Some(duties);
+                // The lookahead only serves the forwarding of requests to the peer gateways
+                match self.fetch_proposer_duties(epoch + 1).await {
+                    Ok(duties) => responses.next_proposer_duties = Some(duties),
+                    Err(err) => {
+                        tracing::warn!(?err, epoch = epoch + 1, "Failed to fetch the lookahead")
+                    }
+                }
+                (responses, Ok(()))
+            }
+            Err(err) => {
+                responses.error = Some(err.to_string());
+                (responses, Err(err))
+            }
+        }
+    }
+
+    /// Move to `head` with the responses of the beacon node, fetched or replayed from the
+    /// [SlotArchive]. The head is kept if its header is missing.
+    pub fn apply_beacon_responses(&mut self, head: u64, responses: BeaconResponses) {
+        let Some(header) = responses.header else {
+            return;
+        };
+        self.header = header;
 
         self.latest_slot_timestamp = Instant::now();
         self.latest_slot = head;
 
         let slot = self.header.slot;
         ApiMetrics::set_latest_head(slot as u32);
-        let epoch = slot / SLOTS_PER_EPOCH;
+        let epoch = self.config.epoch_of(slot);
 
-        self.blocks.remove(&(slot));
+        self.blocks.prune(slot);
+        self.record_blob_memory();
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.prune(slot) {
+                tracing::error!(?err, slot, "Failed to prune the constraints journal");
+            }
+        }
+        // The submission status is kept for an epoch
+        let retention_slots = self.config.slots_per_epoch;
+        self.submissions.retain(|s, _| *s + retention_slots > slot);
 
         if epoch != self.current_epoch.value {
             self.current_epoch.value = epoch;
-            self.current_epoch.start_slot = epoch * SLOTS_PER_EPOCH;
-
-            self.fetch_proposer_duties(epoch).await?;
+            self.current_epoch.start_slot = self.config.epoch_start_slot(epoch);
+
+            if let Some(duties) = responses.proposer_duties {
+                self.current_epoch.proposer_duties = duties;
+                let lookahead = responses.next_proposer_duties.iter().flatten();
+                update_proposers(
+                    &self.proposers,
+                    self.current_epoch.proposer_duties.iter().chain(lookahead),
+                );
+            }
         }
-
-        Ok(())
+        self.publish_status();
     }
 
-    async fn fetch_proposer_duties(&mut self, epoch: u64) -> Result<(), StateError> {
-        // Retry settings
-        let retry_delay = Duration::from_secs(2);
-        let max_retries = 5;
-
-        let mut retries = 0;
+    async fn fetch_proposer_duties(&self, epoch: u64) -> Result<Vec<ProposerDuty>, StateError> {
+        let retried = retry_with_backoff("proposer_duties", &self.retry, || {
+            self.beacon_client.get_proposer_duties(epoch)
+        });
+        let (_, duties) = self.beacon_breaker.call(retried).await.map_err(|err| match err {
+            BreakerError::Open(open) => StateError::CircuitOpen(open),
+            BreakerError::Failed(_) => StateError::FailedFetcingProposerDuties,
+        })?;
 
-        loop {
-            match self
-                .beacon_client
-                .get_proposer_duties(epoch)
-                .await
-                .map_err(|_| StateError::FailedFetcingProposerDuties)
-            {
-                Ok(duties) => {
-                    self.current_epoch.proposer_duties = duties.1;
-                    break;
-                }
-                Err(_) if retries < max_retries => {
-                    retries += 1;
-                    tokio::time::sleep(retry_delay).await;
-                }
-                Err(err) => return Err(err),
-            };
-        }
-        Ok(())
+        Ok(duties)
     }
 }
 
 #[derive(Debug, Default, Clone)]
 pub struct Block {
     pub signed_constraints_list: Vec<SignedConstraints>,
+    /// When the request of each committed transaction was accepted, in milliseconds since the
+    /// unix epoch.
+    pub accepted_ms: HashMap<TxHash, u64>,
+    /// Bytes of the blob sidecars of the constraints.
+    blob_bytes: usize,
+    /// Blobs of the constraints, as in their [BlobsBundle].
+    blobs: usize,
+    /// Digests of the constraints messages, computed as they are added rather than at the
+    /// deadline.
+    digests: Vec<[u8; 32]>,
+    /// Priority tier of each constraints message, the list being ordered by decreasing tier.
+    priorities: Vec<PriorityTier>,
 }
 
 impl Block {
     pub fn add_constraints(&mut self, constraints: SignedConstraints) {
-        self.signed_constraints_list.push(constraints);
+        self.add_prioritized(constraints, PriorityTier::Standard);
+    }
+
+    /// Add constraints of the `priority` tier, after the ones of the same or a higher tier and
+    /// ahead of the lower ones.
+    pub fn add_prioritized(&mut self, constraints: SignedConstraints, priority: PriorityTier) {
+        let index = self.priorities.partition_point(|tier| *tier >= priority);
+        self.blob_bytes += blob_sidecar_bytes(&constraints.message.transactions);
+        self.blobs += blob_count(&constraints.message.transactions);
+        self.digests.insert(index, constraints.message.digest());
+        self.priorities.insert(index, priority);
+        self.signed_constraints_list.insert(index, constraints);
     }
 
     pub fn replace_constraints(&mut self, constraints: &Vec<SignedConstraints>) {
         self.signed_constraints_list = constraints.clone();
+        self.priorities = vec![PriorityTier::Standard; constraints.len()];
+        self.recount();
     }
 
     pub fn remove_constraints(&mut self, slot: u64) {
-        self.signed_constraints_list
-            .remove(slot.try_into().unwrap());
+        let index: usize = slot.try_into().unwrap();
+        self.signed_constraints_list.remove(index);
+        self.priorities.remove(index);
+        self.recount();
+    }
+
+    /// Keep the constraints whose entry in `valid` is set, returning the number dropped.
+    pub fn retain_valid(&mut self, valid: &[bool]) -> usize {
+        let before = self.signed_constraints_list.len();
+        let mut kept = valid.iter();
+        self.signed_constraints_list.retain(|_| kept.next().copied().unwrap_or(false));
+        let mut kept = valid.iter();
+        self.priorities.retain(|_| kept.next().copied().unwrap_or(false));
+        self.recount();
+        before - self.signed_constraints_list.len()
+    }
+
+    /// Bytes of the blob sidecars of the constraints, held until the block is dropped.
+    pub fn blob_bytes(&self) -> usize {
+        self.blob_bytes
+    }
+
+    /// Blobs of the constraints, counting against the blob limit of the block.
+    pub fn blob_count(&self) -> usize {
+        self.blobs
+    }
+
+    /// Digests of the constraints messages, in the [ConstraintsVersion::V1] format.
+    pub fn digests(&self) -> &[[u8; 32]] {
+        &self.digests
+    }
+
+    /// The [batch_id](wal::batch_id) of the constraints.
+    pub fn batch_id(&self) -> B256 {
+        wal::batch_id_of(self.digests.iter().copied())
+    }
+
+    fn recount(&mut self) {
+        self.blob_bytes = self
+            .signed_constraints_list
+            .iter()
+            .map(|sc| blob_sidecar_bytes(&sc.message.transactions))
+            .sum();
+        self.blobs =
+            self.signed_constraints_list.iter().map(|sc| blob_count(&sc.message.transactions)).sum();
+        self.digests = self.signed_constraints_list.iter().map(|sc| sc.message.digest()).collect();
+    }
+
+    /// The committed transactions, with the time their request was accepted.
+    pub fn commitments(&self) -> Vec<TrackedCommitment> {
+        self.get_transactions()
+            .iter()
+            .map(|tx| TrackedCommitment {
+                hash: *tx.hash(),
+                accepted_ms: self.accepted_ms.get(tx.hash()).copied().unwrap_or_default(),
+            })
+            .collect()
     }
 
     pub fn get_transactions(&self) -> Vec<PooledTransactionsElement> {
@@ -451,6 +882,24 @@ impl Block {
     }
 }
 
+/// Blobs of `constraints`, as parsed to a [BlobsBundle].
+pub fn blob_count(constraints: &[Constraint]) -> usize {
+    constraints.iter().filter_map(|c| c.tx.blob_sidecar()).map(|sidecar| sidecar.blobs.len()).sum()
+}
+
+/// Bytes of the blob sidecars of `constraints`, as held in memory.
+pub fn blob_sidecar_bytes(constraints: &[Constraint]) -> usize {
+    constraints
+        .iter()
+        .filter_map(|c| c.tx.blob_sidecar())
+        .map(|sidecar| {
+            sidecar.blobs.len() * BYTES_PER_BLOB
+                + sidecar.commitments.len() * BYTES_PER_COMMITMENT
+                + sidecar.proofs.len() * BYTES_PER_PROOF
+        })
+        .sum()
+}
+
 /// The deadline for a which a commitment is considered valid.
 #[derive(Debug)]
 pub struct CommitmentDeadline {
@@ -565,3 +1014,160 @@ impl HeadEventListener {
         self.new_heads_rx.resubscribe()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::EthereumWallet,
+        primitives::{hex, PrimitiveSignature, U256},
+        signers::local::PrivateKeySigner,
+    };
+    use beacon_api_client::ProposerDuty;
+    use ethereum_consensus::crypto::PublicKey as ECBlsPublicKey;
+    use reqwest::Url;
+
+    use super::{
+        execution::{ExecutionSnapshot, ExecutionState},
+        fetcher::ClientState,
+        pricing::PriorityTier,
+        slot_clock::SlotClock,
+        Block, ConstraintState, Epoch, StateError,
+    };
+    use crate::{
+        commitment::request::PreconfRequest,
+        config::ChainConfig,
+        constraints::{Constraint, ConstraintsMessage, SignedConstraints},
+        test_utils::{default_test_blob_transaction, default_test_transaction},
+    };
+
+    fn duty(slot: u64) -> ProposerDuty {
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        serde_json::from_value(serde_json::json!({
+            "pubkey": hex::encode_prefixed(sk.sk_to_pk().to_bytes()),
+            "validator_index": "1",
+            "slot": slot.to_string(),
+        }))
+        .unwrap()
+    }
+
+    /// State with the head of slot 9, and the proposers of slots 11 and 12.
+    fn state_at(slot_clock: SlotClock) -> ConstraintState {
+        // Never queried, the requests are refused before the execution checks
+        let url = Url::parse("http://127.0.0.1:1").unwrap();
+        let execution = ExecutionState::from_head(
+            ClientState::new(url.clone()),
+            Default::default(),
+            30_000_000,
+            1,
+            ExecutionSnapshot::default(),
+        );
+        let mut state = ConstraintState::new(
+            beacon_api_client::mainnet::Client::new(url),
+            Duration::from_secs(8),
+            slot_clock,
+            execution,
+            &ChainConfig::default(),
+        );
+        state.latest_slot = 9;
+        state.current_epoch =
+            Epoch { value: 0, start_slot: 0, proposer_duties: vec![duty(11), duty(12)] };
+        // Refuses the requests past the deadline checks
+        state.max_commitments_in_block = 0;
+        state
+    }
+
+    fn request(slot: u64) -> PreconfRequest {
+        PreconfRequest {
+            slot,
+            txs: vec![],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: Default::default(),
+            chain_id: ChainConfig::default().id,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_reached_without_head_event() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let expired = |result| matches!(result, Err(StateError::DeadlineExpired));
+
+        // A second into slot 10, whose head event is missed
+        let mut state = state_at(SlotClock::new(now - 10 * 12 - 1, 12, 500));
+        let refused = state.validate_preconf_request(request(11)).await.unwrap_err();
+        assert_eq!(refused.code(), "max_commitments_reached_for_slot");
+
+        // Its deadline is reached from the slot clock, and the block of slot 11 submitted
+        state.reach_deadline(10);
+        assert!(expired(state.validate_preconf_request(request(11)).await));
+        assert!(!expired(state.validate_preconf_request(request(12)).await));
+
+        // Past the deadline on the slot clock, before the deadline is handled
+        let state = state_at(SlotClock::new(now - 10 * 12 - 9, 12, 500));
+        assert!(expired(state.validate_preconf_request(request(11)).await));
+    }
+
+    #[tokio::test]
+    async fn test_blob_bytes_accounting() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let pubkey = ECBlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        let signed = |raw: Vec<u8>| -> eyre::Result<SignedConstraints> {
+            let constraint = Constraint::decode_enveloped(raw)?;
+            let message = ConstraintsMessage::from_tx(pubkey.clone(), 1, constraint);
+            Ok(SignedConstraints { message, signature: Default::default() })
+        };
+
+        let blob_tx = default_test_blob_transaction(signer.address(), Some(0), 2);
+        let blob_tx = signed(blob_tx.build(&wallet).await?.encoded_2718())?;
+        let tx = default_test_transaction(signer.address(), Some(1));
+        let tx = signed(tx.build(&wallet).await?.encoded_2718())?;
+
+        let mut block = Block::default();
+        block.add_constraints(tx.clone());
+        assert_eq!(block.blob_bytes(), 0);
+        block.add_constraints(blob_tx);
+        assert_eq!(block.blob_bytes(), 2 * (131_072 + 48 + 48));
+        // Counted against the blob limit as they end up in the bundle
+        assert_eq!(block.blob_count(), 2);
+        assert_eq!(block.parse_to_blobs_bundle().blobs.len(), block.blob_count());
+        block.replace_constraints(&vec![tx]);
+        assert_eq!(block.blob_bytes(), 0);
+        assert_eq!(block.blob_count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_constraints_ordered_by_priority() {
+        let signed = |slot: u64| SignedConstraints {
+            message: ConstraintsMessage { slot, ..Default::default() },
+            signature: Default::default(),
+        };
+
+        let mut block = Block::default();
+        block.add_prioritized(signed(1), PriorityTier::Standard);
+        block.add_prioritized(signed(2), PriorityTier::Top);
+        block.add_prioritized(signed(3), PriorityTier::Fast);
+        block.add_prioritized(signed(4), PriorityTier::Top);
+        block.add_constraints(signed(5));
+        let order = |block: &Block| {
+            block.signed_constraints_list.iter().map(|sc| sc.message.slot).collect::<Vec<_>>()
+        };
+        // Ahead of the lower tiers, in their order of arrival within a tier
+        assert_eq!(order(&block), [2, 4, 3, 1, 5]);
+        assert_eq!(block.digests()[0], signed(2).message.digest());
+
+        assert_eq!(block.retain_valid(&[false, true, true, true, true]), 1);
+        block.add_prioritized(signed(6), PriorityTier::Fast);
+        assert_eq!(order(&block), [4, 3, 6, 1, 5]);
+    }
+}
@@ -1,13 +1,148 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::eips::eip4844::MAX_BLOBS_PER_BLOCK;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
 pub const DEFAULT_BLOCK_GAS_LIMIT: u64 = 30_000_000;
 
 const BASE_MULTIPLIER: f64 = 0.019;
 const GAS_SCALAR: f64 = 1.02e-6;
+/// Tip in wei for committing a blob while the blob space of the block is still empty.
+const BLOB_PREMIUM_WEI: u64 = 10_000_000_000_000;
+/// Premium of the commitments to the next slot, decreasing with the slots ahead of the head.
+const PROXIMITY_PREMIUM: f64 = 0.25;
+/// Multipliers of the tips of the requests of the priority tiers above the standard one.
+const FAST_TIER_MULTIPLIER: f64 = 1.5;
+const TOP_TIER_MULTIPLIER: f64 = 3.0;
+
+/// Priority tier requested for a commitment. The higher tiers pay a multiple of the minimum
+/// inclusion tip, and their transactions are placed ahead of the lower tiers in the block.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PriorityTier {
+    #[default]
+    Standard,
+    Fast,
+    Top,
+}
+
+impl PriorityTier {
+    /// Multiplier of the minimum inclusion tip of the tier.
+    pub const fn multiplier(&self) -> f64 {
+        match self {
+            Self::Standard => 1.0,
+            Self::Fast => FAST_TIER_MULTIPLIER,
+            Self::Top => TOP_TIER_MULTIPLIER,
+        }
+    }
+
+    /// Minimum tip per gas of the tier, given the minimum one of the standard tier.
+    pub fn price(&self, standard_tip: u128) -> u128 {
+        (standard_tip as f64 * self.multiplier()) as u128
+    }
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Fast => "fast",
+            Self::Top => "top",
+        }
+    }
+
+    pub fn is_standard(&self) -> bool {
+        *self == Self::Standard
+    }
+}
+
+impl std::fmt::Display for PriorityTier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Gas and blobs committed in a slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CommittedSpace {
+    pub gas: u64,
+    pub blobs: usize,
+}
+
+/// Space committed per slot, shared with the quoting and pricing endpoints.
+pub type SharedCommittedSpace = Arc<RwLock<HashMap<u64, CommittedSpace>>>;
+
+/// What the inclusion tip of a transaction is priced on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PricingInput {
+    pub gas: u64,
+    pub blobs: usize,
+    /// Space already committed in the slot of the transaction.
+    pub committed: CommittedSpace,
+    /// Slots from the head to the slot of the transaction, 1 for the next slot.
+    pub slots_ahead: u64,
+}
+
+/// A transaction of a request tips less than the minimum inclusion tip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema, thiserror::Error)]
+#[error("Max priority fee per gas {tip} of transaction {index} is less than min priority fee {required_tip}")]
+pub struct Underpriced {
+    /// Index of the transaction in the request.
+    pub index: usize,
+    /// Priority fee per gas paid by the transaction, in wei.
+    #[schema(value_type = u64)]
+    pub tip: u128,
+    /// Minimum priority fee per gas of the transaction, in wei.
+    #[schema(value_type = u64)]
+    pub required_tip: u128,
+}
+
+/// Pricing of a slot at the space committed in it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SlotPricing {
+    pub slot: u64,
+    pub committed: CommittedSpace,
+    pub remaining_gas: u64,
+    pub remaining_blobs: usize,
+    /// Minimum priority fee per gas in wei of a transfer committed in the slot, including the
+    /// inclusion profit. Missing if no more gas can be committed.
+    pub min_priority_fee: Option<u64>,
+    /// Tip in wei for committing one more blob. Missing if the blob space is full.
+    pub blob_fee: Option<u64>,
+    /// Multiplier applied to the tips of the slot for its proximity to the head.
+    pub proximity_multiplier: f64,
+}
+
+/// Current pricing of the commitments, served by `GET /api/v1/pricing`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct PricingReport {
+    pub head_slot: u64,
+    /// Profit in wei per gas added to the minimum inclusion tips.
+    pub min_inclusion_profit: u64,
+    pub slots: Vec<SlotPricing>,
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PreconfPricer {
     block_gas_limit: u64,
     base_multiplier: f64,
     gas_scalar: f64,
+    max_blobs: usize,
+    blob_premium: u64,
+    proximity_premium: f64,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -19,8 +154,11 @@ pub enum PricingError {
     #[error("Invalid gas limit: Incoming gas ({incoming_gas}) is zero")]
     InvalidGasLimit { incoming_gas: u64 },
 
-    #[error("Tip {tip} is too low. Minimum required priority fee is {min_priority_fee}")]
-    TipTooLow { tip: u128, min_priority_fee: u128 },
+    #[error("Insufficient remaining blob space: requested {requested}, available {available}")]
+    InsufficientBlobSpace { requested: usize, available: usize },
+
+    #[error(transparent)]
+    TipTooLow(Underpriced),
 }
 
 impl Default for PreconfPricer {
@@ -35,7 +173,52 @@ impl PreconfPricer {
             block_gas_limit,
             base_multiplier: BASE_MULTIPLIER,
             gas_scalar: GAS_SCALAR,
+            max_blobs: MAX_BLOBS_PER_BLOCK,
+            blob_premium: BLOB_PREMIUM_WEI,
+            proximity_premium: PROXIMITY_PREMIUM,
+        }
+    }
+
+    /// Minimum inclusion tip per gas in wei of a transaction: the value of the block space it
+    /// takes, plus a premium for its blobs rising as the blob space of the block fills up, the
+    /// sum raised for the slots close to the head.
+    pub fn min_inclusion_tip(&self, input: &PricingInput) -> Result<u64, PricingError> {
+        let gas_tip = self.calculate_min_priority_fee(input.gas, input.committed.gas)?;
+        let blob_tip = self.blob_fee(input.committed.blobs, input.blobs)? / input.gas;
+
+        Ok(((gas_tip + blob_tip) as f64 * self.proximity_multiplier(input.slots_ahead)) as u64)
+    }
+
+    /// Tip in wei for committing `blobs` more blobs with `committed_blobs` already committed.
+    pub fn blob_fee(&self, committed_blobs: usize, blobs: usize) -> Result<u64, PricingError> {
+        let available = self.max_blobs.saturating_sub(committed_blobs);
+        if blobs > available {
+            return Err(PricingError::InsufficientBlobSpace { requested: blobs, available });
         }
+
+        // Each blob is priced on the blob space left before it
+        Ok((0..blobs)
+            .map(|i| self.blob_premium * self.max_blobs as u64 / (available - i) as u64)
+            .sum())
+    }
+
+    /// Multiplier of the tips of the commitments `slots_ahead` of the head.
+    pub fn proximity_multiplier(&self, slots_ahead: u64) -> f64 {
+        1.0 + self.proximity_premium / slots_ahead.max(1) as f64
+    }
+
+    /// Price the blob space of blocks holding `max_blobs` blobs.
+    pub fn with_max_blobs(mut self, max_blobs: usize) -> Self {
+        self.max_blobs = max_blobs;
+        self
+    }
+
+    pub fn max_blobs(&self) -> usize {
+        self.max_blobs
+    }
+
+    pub fn block_gas_limit(&self) -> u64 {
+        self.block_gas_limit
     }
 
     pub fn calculate_min_priority_fee(
@@ -80,3 +263,49 @@ fn validate_fee_inputs(
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CommittedSpace, PreconfPricer, PricingError, PricingInput, PriorityTier};
+
+    #[test]
+    fn test_min_inclusion_tip() {
+        let pricer = PreconfPricer::default();
+        let transfer = PricingInput { gas: 21_000, slots_ahead: 4, ..Default::default() };
+        let tip = pricer.min_inclusion_tip(&transfer).unwrap();
+
+        // Filling the block, adding blobs and getting closer to the head all raise the tip
+        let fuller = PricingInput {
+            committed: CommittedSpace { gas: 20_000_000, blobs: 0 },
+            ..transfer
+        };
+        assert!(pricer.min_inclusion_tip(&fuller).unwrap() > tip);
+
+        let blob_tx = PricingInput { blobs: 2, ..transfer };
+        let blob_tip = pricer.min_inclusion_tip(&blob_tx).unwrap();
+        assert!(blob_tip > tip);
+        let scarcer = PricingInput { committed: CommittedSpace { gas: 0, blobs: 3 }, ..blob_tx };
+        assert!(pricer.min_inclusion_tip(&scarcer).unwrap() > blob_tip);
+
+        let next_slot = PricingInput { slots_ahead: 1, ..transfer };
+        assert!(pricer.min_inclusion_tip(&next_slot).unwrap() > tip);
+
+        let full = PricingInput { committed: CommittedSpace { gas: 0, blobs: 5 }, ..blob_tx };
+        assert!(matches!(
+            pricer.min_inclusion_tip(&full),
+            Err(PricingError::InsufficientBlobSpace { requested: 2, available: 1 })
+        ));
+    }
+
+    #[test]
+    fn test_priority_tiers() {
+        assert_eq!(PriorityTier::Standard.price(1_000), 1_000);
+        assert!(PriorityTier::Top.price(1_000) > PriorityTier::Fast.price(1_000));
+        assert!(PriorityTier::Fast.price(1_000) > 1_000);
+
+        assert_eq!(serde_json::to_string(&PriorityTier::Fast).unwrap(), r#""fast""#);
+        assert!(
+            PriorityTier::Top > PriorityTier::Fast && PriorityTier::Fast > PriorityTier::Standard
+        );
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use alloy::primitives::{keccak256, Address, PrimitiveSignature, B256};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::DEFAULT_MAX_COMMITMENT_GAS;
+use crate::{
+    commitment::request::{deserialize_sig, serialize_sig},
+    config::limits::DEFAULT_MIN_PROFIT,
+    metrics::ApiMetrics,
+};
+
+/// Default cap of the gas reserved in a slot, half of the committed gas cap.
+pub const DEFAULT_MAX_RESERVED_GAS: u64 = DEFAULT_MAX_COMMITMENT_GAS / 2;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ReservationError {
+    #[error("capacity reservations are not enabled")]
+    Disabled,
+    #[error("invalid reservation signature")]
+    InvalidSignature,
+    #[error("sequencer {0} is not allowed to reserve capacity")]
+    NotAllowed(Address),
+    #[error("a reservation takes some gas in each slot")]
+    NoGas,
+    #[error("invalid reserved slots {first}..={last:?}, starting at or before the current slot {current}")]
+    InvalidSlots { first: u64, last: Option<u64>, current: u64 },
+    #[error("price of {price} wei per gas below the minimum of {min}")]
+    PriceTooLow { price: u64, min: u64 },
+    #[error("only {available} gas left to reserve in the requested slots, {requested} requested")]
+    Oversubscribed { requested: u64, available: u64 },
+}
+
+/// Recurring reservation of capacity requested by a rollup sequencer, signed by it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ReservationRequest {
+    /// Gas reserved in each slot proposed by our validators.
+    pub gas_per_slot: u64,
+    /// Price in wei per reserved gas, owed for every slot reserved whether the gas is used or
+    /// not.
+    pub price_per_gas: u64,
+    pub first_slot: u64,
+    /// Last slot reserved, included. Reserved for every slot from the first one if unset.
+    pub last_slot: Option<u64>,
+    /// Signature of the sequencer over the [digest](ReservationRequest::digest).
+    #[serde(deserialize_with = "deserialize_sig", serialize_with = "serialize_sig")]
+    #[schema(value_type = String)]
+    pub signature: PrimitiveSignature,
+}
+
+impl ReservationRequest {
+    pub fn digest(&self) -> B256 {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.gas_per_slot.to_be_bytes());
+        data.extend_from_slice(&self.price_per_gas.to_be_bytes());
+        data.extend_from_slice(&self.first_slot.to_be_bytes());
+        data.extend_from_slice(&self.last_slot.unwrap_or(u64::MAX).to_be_bytes());
+        keccak256(data)
+    }
+}
+
+/// Capacity held for a sequencer in each of our proposal slots, and what it owes for it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CapacityReservation {
+    pub id: u64,
+    #[schema(value_type = String)]
+    pub sequencer: Address,
+    pub gas_per_slot: u64,
+    pub price_per_gas: u64,
+    pub first_slot: u64,
+    pub last_slot: Option<u64>,
+    /// Proposal slots reserved so far.
+    pub slots_served: u64,
+    /// Wei owed for the proposal slots reserved so far.
+    #[schema(value_type = String)]
+    pub owed_wei: u128,
+}
+
+impl CapacityReservation {
+    pub fn covers(&self, slot: u64) -> bool {
+        slot >= self.first_slot && self.last_slot.map_or(true, |last| slot <= last)
+    }
+
+    fn overlaps(&self, first: u64, last: Option<u64>) -> bool {
+        self.last_slot.map_or(true, |own_last| first <= own_last)
+            && last.map_or(true, |last| self.first_slot <= last)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Reservations {
+    next_id: u64,
+    by_id: BTreeMap<u64, CapacityReservation>,
+    /// Gas committed by each sequencer in the slots not settled yet.
+    used: HashMap<(u64, Address), u64>,
+}
+
+/// Recurring capacity reservations of the rollup sequencers allowed by the operator.
+///
+/// The gas reserved and not used yet in a slot is held back from the public requests, the
+/// requests of a sequencer using its own reservation first. Each of our proposal slots is
+/// charged to the reservations covering it once its deadline is reached.
+#[derive(Debug, Clone)]
+pub struct ReservationBook {
+    reservations: Arc<RwLock<Reservations>>,
+    sequencers: Arc<HashSet<Address>>,
+    max_reserved_gas: u64,
+    min_price_per_gas: u64,
+}
+
+impl Default for ReservationBook {
+    fn default() -> Self {
+        Self {
+            reservations: Default::default(),
+            sequencers: Default::default(),
+            max_reserved_gas: DEFAULT_MAX_RESERVED_GAS,
+            min_price_per_gas: DEFAULT_MIN_PROFIT,
+        }
+    }
+}
+
+impl ReservationBook {
+    /// Reservations of the `sequencers`, disabled if there are none.
+    pub fn new(sequencers: HashSet<Address>) -> Self {
+        Self { sequencers: Arc::new(sequencers), ..Default::default() }
+    }
+
+    /// Cap the gas reserved in a slot, across the reservations.
+    pub fn with_max_reserved_gas(mut self, max_reserved_gas: u64) -> Self {
+        self.max_reserved_gas = max_reserved_gas;
+        self
+    }
+
+    pub fn with_min_price_per_gas(mut self, min_price_per_gas: u64) -> Self {
+        self.min_price_per_gas = min_price_per_gas;
+        self
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.sequencers.is_empty()
+    }
+
+    /// Register the reservation of the sequencer that signed `request`, starting after
+    /// `current_slot`.
+    pub fn register(
+        &self,
+        request: &ReservationRequest,
+        current_slot: u64,
+    ) -> Result<CapacityReservation, ReservationError> {
+        let registered = self.try_register(request, current_slot);
+        ApiMetrics::increment_capacity_reservations_count(match registered {
+            Ok(_) => "registered",
+            Err(_) => "refused",
+        });
+        registered
+    }
+
+    fn try_register(
+        &self,
+        request: &ReservationRequest,
+        current_slot: u64,
+    ) -> Result<CapacityReservation, ReservationError> {
+        if !self.is_enabled() {
+            return Err(ReservationError::Disabled);
+        }
+        let sequencer = request
+            .signature
+            .recover_address_from_prehash(&request.digest())
+            .map_err(|_| ReservationError::InvalidSignature)?;
+        if !self.sequencers.contains(&sequencer) {
+            return Err(ReservationError::NotAllowed(sequencer));
+        }
+
+        let ReservationRequest { gas_per_slot, price_per_gas, first_slot, last_slot, .. } =
+            *request;
+        if gas_per_slot == 0 {
+            return Err(ReservationError::NoGas);
+        }
+        if first_slot <= current_slot || last_slot.is_some_and(|last| last < first_slot) {
+            return Err(ReservationError::InvalidSlots {
+                first: first_slot,
+                last: last_slot,
+                current: current_slot,
+            });
+        }
+        if price_per_gas < self.min_price_per_gas {
+            return Err(ReservationError::PriceTooLow {
+                price: price_per_gas,
+                min: self.min_price_per_gas,
+            });
+        }
+
+        let mut reservations = self.reservations.write();
+        // Counted as if all the overlapping reservations held the same slot
+        let reserved: u64 = reservations
+            .by_id
+            .values()
+            .filter(|r| r.overlaps(first_slot, last_slot))
+            .map(|r| r.gas_per_slot)
+            .sum();
+        let available = self.max_reserved_gas.saturating_sub(reserved);
+        if gas_per_slot > available {
+            return Err(ReservationError::Oversubscribed { requested: gas_per_slot, available });
+        }
+
+        let id = reservations.next_id;
+        reservations.next_id += 1;
+        let reservation = CapacityReservation {
+            id,
+            sequencer,
+            gas_per_slot,
+            price_per_gas,
+            first_slot,
+            last_slot,
+            slots_served: 0,
+            owed_wei: 0,
+        };
+        reservations.by_id.insert(id, reservation.clone());
+        tracing::info!(id, %sequencer, gas_per_slot, first_slot, ?last_slot, "Registered a capacity reservation");
+        Ok(reservation)
+    }
+
+    pub fn list(&self) -> Vec<CapacityReservation> {
+        self.reservations.read().by_id.values().cloned().collect()
+    }
+
+    /// Gas reserved in `slot` by the sequencers other than `requester` and not used yet,
+    /// unavailable to the requests of `requester`.
+    pub fn held_back(&self, slot: u64, requester: Option<&Address>) -> u64 {
+        let reservations = self.reservations.read();
+        reservations
+            .by_id
+            .values()
+            .filter(|r| r.covers(slot) && Some(&r.sequencer) != requester)
+            .map(|r| {
+                let used = reservations.used.get(&(slot, r.sequencer)).copied().unwrap_or_default();
+                r.gas_per_slot.saturating_sub(used)
+            })
+            .sum()
+    }
+
+    /// Record the gas committed in `slot` to a transaction of `sender`, drawn from its
+    /// reservation if it holds one.
+    pub fn record_usage(&self, slot: u64, sender: Address, gas: u64) {
+        let mut reservations = self.reservations.write();
+        if reservations.by_id.values().any(|r| r.sequencer == sender && r.covers(slot)) {
+            *reservations.used.entry((slot, sender)).or_default() += gas;
+        }
+    }
+
+    /// Charge our proposal `slot` to the reservations covering it.
+    pub fn settle(&self, slot: u64) {
+        let mut reservations = self.reservations.write();
+        reservations.used.retain(|(used_slot, _), _| *used_slot > slot);
+        for reservation in reservations.by_id.values_mut().filter(|r| r.covers(slot)) {
+            reservation.slots_served += 1;
+            reservation.owed_wei += reservation.gas_per_slot as u128 * reservation.price_per_gas as u128;
+            ApiMetrics::increment_capacity_reservations_count("charged");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::{PrimitiveSignature, U256},
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+
+    use super::{ReservationBook, ReservationError, ReservationRequest};
+
+    fn request(signer: &PrivateKeySigner, gas_per_slot: u64, first_slot: u64) -> ReservationRequest {
+        let mut request = ReservationRequest {
+            gas_per_slot,
+            price_per_gas: 3_000_000_000,
+            first_slot,
+            last_slot: Some(first_slot + 10),
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+        };
+        request.signature = signer.sign_hash_sync(&request.digest()).unwrap();
+        request
+    }
+
+    #[test]
+    fn test_recurring_reservations() {
+        let sequencer = PrivateKeySigner::random();
+        let other = PrivateKeySigner::random();
+        let book = ReservationBook::new([sequencer.address()].into()).with_max_reserved_gas(3_000_000);
+
+        assert_eq!(
+            book.register(&request(&other, 2_000_000, 10), 5),
+            Err(ReservationError::NotAllowed(other.address()))
+        );
+        assert!(matches!(
+            book.register(&request(&sequencer, 2_000_000, 5), 5),
+            Err(ReservationError::InvalidSlots { .. })
+        ));
+        let reservation = book.register(&request(&sequencer, 2_000_000, 10), 5).unwrap();
+        assert_eq!(reservation.sequencer, sequencer.address());
+        assert_eq!(
+            book.register(&request(&sequencer, 2_000_000, 15), 5),
+            Err(ReservationError::Oversubscribed { requested: 2_000_000, available: 1_000_000 })
+        );
+
+        // Held back from the others until the sequencer uses it
+        assert_eq!(book.held_back(9, None), 0);
+        assert_eq!(book.held_back(12, Some(&other.address())), 2_000_000);
+        assert_eq!(book.held_back(12, Some(&sequencer.address())), 0);
+        book.record_usage(12, sequencer.address(), 1_500_000);
+        book.record_usage(12, other.address(), 21_000);
+        assert_eq!(book.held_back(12, None), 500_000);
+
+        // Charged whether used or not
+        book.settle(12);
+        book.settle(13);
+        book.settle(30);
+        let reservation = &book.list()[0];
+        assert_eq!(reservation.slots_served, 2);
+        assert_eq!(reservation.owed_wei, 2 * 2_000_000 * 3_000_000_000);
+        assert_eq!(book.held_back(12, None), 2_000_000);
+    }
+}
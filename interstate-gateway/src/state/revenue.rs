@@ -0,0 +1,213 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path, sync::Arc};
+
+use alloy_v092::primitives::U256;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::config::ChainConfig;
+
+/// Number of epochs kept in the revenue reports.
+const REVENUE_RETENTION_EPOCHS: u64 = 256;
+
+const CSV_HEADER: &str =
+    "epoch,slot,preconf_tips_wei,builder_bid_wei,local_payload,onchain_payment_wei,discrepancy_wei\n";
+
+/// Value earned for a single proposal.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ProposalRevenue {
+    pub slot: u64,
+    /// Priority fees paid by the preconfirmed transactions included in the block.
+    #[schema(value_type = String)]
+    pub preconf_tips_wei: U256,
+    /// Value of the bid returned to the proposer.
+    #[schema(value_type = Option<String>)]
+    pub builder_bid_wei: Option<U256>,
+    /// Whether the bid was for the locally built fallback payload.
+    pub local_payload: bool,
+    /// Balance change of the fee recipient in the proposed block.
+    #[schema(value_type = Option<String>)]
+    pub onchain_payment_wei: Option<U256>,
+}
+
+impl ProposalRevenue {
+    /// Difference between the promised bid and the payment observed on chain.
+    pub fn discrepancy_wei(&self) -> Option<i128> {
+        let bid = self.builder_bid_wei?;
+        let paid = self.onchain_payment_wei?;
+        Some(u128::try_from(paid).ok()? as i128 - u128::try_from(bid).ok()? as i128)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct EpochRevenueReport {
+    pub epoch: u64,
+    pub proposals: Vec<ProposalRevenue>,
+    #[schema(value_type = String)]
+    pub total_preconf_tips_wei: U256,
+    #[schema(value_type = String)]
+    pub total_builder_bids_wei: U256,
+    #[schema(value_type = String)]
+    pub total_onchain_payments_wei: U256,
+}
+
+/// Revenue accounting of our proposals, shared between the builder proxy recording the bids
+/// and the head updates reconciling them with the on-chain payments.
+#[derive(Debug, Clone)]
+pub struct RevenueTracker {
+    proposals: Arc<RwLock<BTreeMap<u64, ProposalRevenue>>>,
+    /// Slots of an epoch of the chain, the proposals are reported per epoch.
+    slots_per_epoch: u64,
+}
+
+impl Default for RevenueTracker {
+    fn default() -> Self {
+        Self::new(ChainConfig::default().slots_per_epoch)
+    }
+}
+
+impl RevenueTracker {
+    pub fn new(slots_per_epoch: u64) -> Self {
+        Self { proposals: Default::default(), slots_per_epoch }
+    }
+
+    /// A tracker serving the proposals recorded by another instance.
+    pub fn from_proposals(proposals: Vec<ProposalRevenue>, slots_per_epoch: u64) -> Self {
+        let proposals = proposals.into_iter().map(|p| (p.slot, p)).collect();
+        Self { proposals: Arc::new(RwLock::new(proposals)), slots_per_epoch }
+    }
+
+    pub fn proposals(&self) -> Vec<ProposalRevenue> {
+        self.proposals.read().values().cloned().collect()
+    }
+
+    pub fn record_bid(&self, slot: u64, value: U256, local_payload: bool) {
+        let mut proposals = self.proposals.write();
+        let proposal = proposals.entry(slot).or_insert_with(|| ProposalRevenue {
+            slot,
+            ..Default::default()
+        });
+        proposal.builder_bid_wei = Some(value);
+        proposal.local_payload = local_payload;
+    }
+
+    pub fn record_preconf_tips(&self, slot: u64, tips: U256) {
+        let mut proposals = self.proposals.write();
+        let proposal = proposals.entry(slot).or_insert_with(|| ProposalRevenue {
+            slot,
+            ..Default::default()
+        });
+        proposal.preconf_tips_wei += tips;
+    }
+
+    /// Record the payment received for a proposal. Returns false if `slot` isn't a
+    /// proposal we served a bid for.
+    pub fn record_onchain_payment(&self, slot: u64, payment: U256) -> bool {
+        let mut proposals = self.proposals.write();
+        let Some(proposal) = proposals.get_mut(&slot).filter(|p| p.builder_bid_wei.is_some())
+        else {
+            return false;
+        };
+        proposal.onchain_payment_wei = Some(payment);
+
+        if let Some(discrepancy) = proposal.discrepancy_wei().filter(|d| *d < 0) {
+            tracing::warn!(slot, discrepancy, "On-chain proposer payment is lower than the bid");
+        }
+
+        true
+    }
+
+    /// Whether a bid was served for `slot` and is still waiting for its on-chain payment.
+    pub fn awaits_payment(&self, slot: u64) -> bool {
+        self.proposals
+            .read()
+            .get(&slot)
+            .is_some_and(|p| p.builder_bid_wei.is_some() && p.onchain_payment_wei.is_none())
+    }
+
+    /// Drop the proposals older than the retention window.
+    pub fn prune(&self, current_slot: u64) {
+        let oldest = current_slot.saturating_sub(REVENUE_RETENTION_EPOCHS * self.slots_per_epoch);
+        self.proposals.write().retain(|slot, _| *slot >= oldest);
+    }
+
+    pub fn epoch_reports(&self) -> Vec<EpochRevenueReport> {
+        let mut reports: BTreeMap<u64, EpochRevenueReport> = BTreeMap::new();
+
+        for proposal in self.proposals.read().values() {
+            let epoch = proposal.slot / self.slots_per_epoch;
+            let report = reports.entry(epoch).or_insert_with(|| EpochRevenueReport {
+                epoch,
+                ..Default::default()
+            });
+
+            report.total_preconf_tips_wei += proposal.preconf_tips_wei;
+            report.total_builder_bids_wei += proposal.builder_bid_wei.unwrap_or_default();
+            report.total_onchain_payments_wei += proposal.onchain_payment_wei.unwrap_or_default();
+            report.proposals.push(proposal.clone());
+        }
+
+        reports.into_values().collect()
+    }
+
+    /// The proposals as CSV, one row per proposal.
+    pub fn to_csv(&self) -> String {
+        let mut csv = CSV_HEADER.to_string();
+        for proposal in self.proposals.read().values() {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{}",
+                proposal.slot / self.slots_per_epoch,
+                proposal.slot,
+                proposal.preconf_tips_wei,
+                optional(proposal.builder_bid_wei),
+                proposal.local_payload,
+                optional(proposal.onchain_payment_wei),
+                optional(proposal.discrepancy_wei()),
+            );
+        }
+        csv
+    }
+
+    /// Export the CSV report to `path`.
+    pub fn export_csv(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_csv())
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_v092::primitives::U256;
+
+    use super::RevenueTracker;
+
+    #[test]
+    fn test_epoch_revenue_report() {
+        let tracker = RevenueTracker::new(32);
+
+        tracker.record_preconf_tips(33, U256::from(5));
+        tracker.record_bid(33, U256::from(100), false);
+        tracker.record_bid(40, U256::from(50), true);
+        assert!(tracker.awaits_payment(33));
+
+        // Payments for slots we didn't serve a bid for are ignored
+        assert!(!tracker.record_onchain_payment(34, U256::from(1)));
+        assert!(tracker.record_onchain_payment(33, U256::from(90)));
+        assert!(!tracker.awaits_payment(33));
+
+        let reports = tracker.epoch_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].epoch, 1);
+        assert_eq!(reports[0].total_builder_bids_wei, U256::from(150));
+        assert_eq!(reports[0].total_onchain_payments_wei, U256::from(90));
+        assert_eq!(reports[0].total_preconf_tips_wei, U256::from(5));
+
+        let csv = tracker.to_csv();
+        assert!(csv.contains("1,33,5,100,false,90,-10\n"));
+        assert!(csv.contains("1,40,0,50,true,,\n"));
+    }
+}
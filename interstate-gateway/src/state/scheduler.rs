@@ -0,0 +1,196 @@
+use std::{future::poll_fn, task::Poll, time::Duration};
+
+use futures::FutureExt;
+
+use super::{slot_clock::SlotClock, CommitmentDeadline};
+use crate::metrics::ApiMetrics;
+
+/// Arms the commitment deadline of every slot from the slot clock, so that a missing or late
+/// head event for a slot doesn't leave the next one without a deadline.
+///
+/// The deadline of slot `N` is reached `deadline_duration` into it and yields `N + 1`, as the
+/// ones armed on head events used to. Head events only re-anchor the pending deadline to the
+/// slot clock they just corrected, and never re-arm a deadline that was already reached.
+///
+/// With an early fire, a checkpoint is also armed that long before each deadline, at which the
+/// constraints may be submitted ahead of it.
+#[derive(Debug)]
+pub struct DeadlineScheduler {
+    deadline_duration: Duration,
+    deadline: Option<CommitmentDeadline>,
+    early_fire: Option<Duration>,
+    early: Option<CommitmentDeadline>,
+    /// Slot of the last early checkpoint reached.
+    early_reached: Option<u64>,
+    /// Slot of the pending deadline.
+    armed: Option<u64>,
+    /// Whether the head event of the armed slot arrived before its deadline.
+    head_seen: bool,
+    /// Slot of the last deadline reached.
+    reached: Option<u64>,
+}
+
+impl DeadlineScheduler {
+    pub fn new(deadline_duration: Duration) -> Self {
+        Self {
+            deadline_duration,
+            deadline: None,
+            early_fire: None,
+            early: None,
+            early_reached: None,
+            armed: None,
+            head_seen: false,
+            reached: None,
+        }
+    }
+
+    /// Arm a checkpoint `early_fire` before each deadline, see [Self::next].
+    pub fn with_early_fire(mut self, early_fire: Duration) -> Self {
+        self.early_fire = Some(early_fire);
+        self
+    }
+
+    /// Slot of the pending deadline, if any.
+    pub fn armed_slot(&self) -> Option<u64> {
+        self.armed
+    }
+
+    fn arm(&mut self, slot: u64, clock: &SlotClock) {
+        let sleep = clock.duration_until(slot, self.deadline_duration);
+        self.deadline = Some(CommitmentDeadline::new(slot + 1, sleep));
+        self.armed = Some(slot);
+
+        self.early = self
+            .early_fire
+            .filter(|_| self.early_reached.map_or(true, |reached| reached < slot))
+            .map(|early_fire| {
+                let offset = self.deadline_duration.saturating_sub(early_fire);
+                CommitmentDeadline::new(slot + 1, clock.duration_until(slot, offset))
+            });
+    }
+
+    /// Arm the deadline of the slot the clock is in, or of the slot following the last
+    /// deadline reached if the clock hasn't moved past it, unless one is already pending.
+    pub fn schedule(&mut self, clock: &SlotClock) {
+        if self.armed.is_some() {
+            return;
+        }
+        let next = self.reached.map_or(0, |slot| slot + 1).max(clock.current_slot());
+        self.arm(next, clock);
+        self.head_seen = false;
+    }
+
+    /// Reconcile with the head event of `head`, once the slot clock observed it.
+    pub fn on_head(&mut self, head: u64, clock: &SlotClock) {
+        if self.reached.is_some_and(|reached| reached >= head) {
+            tracing::debug!(head, "Head event arrived after its commitment deadline");
+            return;
+        }
+        // The deadline of an earlier slot is overdue, and reached before this one is armed
+        if self.armed.is_some_and(|armed| armed < head) {
+            return;
+        }
+        self.arm(head, clock);
+        self.head_seen = true;
+    }
+
+    /// Wait for the pending deadline, arming it first if needed, and return the slot whose
+    /// constraints are due. Cancel safe, the deadline is kept until it is reached.
+    pub async fn wait(&mut self, clock: &SlotClock) -> Option<u64> {
+        loop {
+            match self.next(clock).await? {
+                DeadlineEvent::Early(_) => continue,
+                DeadlineEvent::Reached(slot) => return Some(slot),
+            }
+        }
+    }
+
+    /// Wait for the pending deadline or its early checkpoint, reached once per slot, arming
+    /// them first if needed. Cancel safe, as [Self::wait].
+    pub async fn next(&mut self, clock: &SlotClock) -> Option<DeadlineEvent> {
+        self.schedule(clock);
+        let deadline = self.deadline.as_mut()?;
+        let early = &mut self.early;
+        let event = poll_fn(|cx| {
+            if let Poll::Ready(slot) = deadline.poll_unpin(cx) {
+                return Poll::Ready(slot.map(DeadlineEvent::Reached));
+            }
+            match early.as_mut().map(|early| early.poll_unpin(cx)) {
+                Some(Poll::Ready(slot)) => Poll::Ready(slot.map(DeadlineEvent::Early)),
+                _ => Poll::Pending,
+            }
+        })
+        .await?;
+
+        match event {
+            DeadlineEvent::Early(_) => {
+                self.early_reached = self.armed;
+            }
+            DeadlineEvent::Reached(_) => {
+                ApiMetrics::increment_commitment_deadlines_count(if self.head_seen {
+                    "head"
+                } else {
+                    "clock"
+                });
+                self.reached = self.armed.take();
+                self.deadline = None;
+            }
+        }
+        self.early = None;
+        Some(event)
+    }
+}
+
+/// A commitment deadline or its early checkpoint reached, with the slot whose constraints are
+/// due.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeadlineEvent {
+    Early(u64),
+    Reached(u64),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use super::{DeadlineEvent, DeadlineScheduler};
+    use crate::state::slot_clock::SlotClock;
+
+    #[tokio::test]
+    async fn test_early_checkpoint() {
+        // Just into slot 10, the deadline and its early checkpoint are ahead
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let clock = SlotClock::new(now - 10 * 12, 12, 500);
+        let mut scheduler = DeadlineScheduler::new(Duration::from_secs(11))
+            .with_early_fire(Duration::from_secs(11));
+
+        assert_eq!(scheduler.next(&clock).await, Some(DeadlineEvent::Early(11)));
+        assert_eq!(scheduler.armed_slot(), Some(10));
+
+        // Re-anchoring the deadline doesn't arm the checkpoint reached again
+        scheduler.on_head(10, &clock);
+        let again = tokio::time::timeout(Duration::from_millis(10), scheduler.next(&clock));
+        assert!(again.await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_deadlines_armed_without_head_events() {
+        // A second into slot 10
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let clock = SlotClock::new(now - 10 * 12 - 1, 12, 500);
+        let mut scheduler = DeadlineScheduler::new(Duration::ZERO);
+
+        // Armed from the clock, and reached without any head event
+        assert_eq!(scheduler.wait(&clock).await, Some(11));
+
+        // The late head event doesn't re-arm it, the next slot is armed instead
+        scheduler.on_head(10, &clock);
+        assert_eq!(scheduler.armed_slot(), None);
+        scheduler.schedule(&clock);
+        assert_eq!(scheduler.armed_slot(), Some(11));
+
+        // A head event for the armed slot re-anchors it
+        scheduler.on_head(11, &clock);
+        assert_eq!(scheduler.armed_slot(), Some(11));
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+/// A store persisted by the sidecar across restarts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Store {
+    ConstraintsJournal,
+    SubmissionLog,
+    AuditLog,
+    RequestLog,
+    DelegationRegistry,
+}
+
+impl Store {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::ConstraintsJournal => "constraints_journal",
+            Self::SubmissionLog => "submission_log",
+            Self::AuditLog => "audit_log",
+            Self::RequestLog => "request_log",
+            Self::DelegationRegistry => "delegation_registry",
+        }
+    }
+
+    /// Migrations of the store, in order: the `i`-th one upgrades it from version `i`.
+    pub fn migrations(&self) -> &'static [Migration] {
+        const ADOPT: Migration =
+            Migration { description: "adopt the versioned schema", apply: adopt };
+        match self {
+            Self::ConstraintsJournal
+            | Self::SubmissionLog
+            | Self::AuditLog
+            | Self::RequestLog
+            | Self::DelegationRegistry => &[ADOPT],
+        }
+    }
+
+    /// The version of the store written by this sidecar.
+    pub fn version(&self) -> u32 {
+        self.migrations().len() as u32
+    }
+}
+
+/// Stores written before they were versioned are already in the first versioned format.
+fn adopt(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Upgrade of a store to the next version, applied to the store at the given path.
+#[derive(Clone, Copy)]
+pub struct Migration {
+    pub description: &'static str,
+    pub apply: fn(&Path) -> io::Result<()>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SchemaError {
+    #[error("failed to read the schema version of {store}: {source}")]
+    Version { store: Store, source: io::Error },
+    #[error("{store} is at version {version}, this sidecar supports up to {supported}")]
+    Newer { store: Store, version: u32, supported: u32 },
+    #[error("failed to back up {store}: {source}")]
+    Backup { store: Store, source: io::Error },
+    #[error("failed to migrate {store} from version {from}: {source}")]
+    Migration { store: Store, from: u32, source: io::Error },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrateOptions {
+    /// Report the migrations due without applying them.
+    pub dry_run: bool,
+    /// Copy the files of a store aside before migrating it.
+    pub backup: bool,
+}
+
+/// Migration of a store from the version it was written with to the current one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoreUpgrade {
+    pub store: Store,
+    pub path: PathBuf,
+    pub from: u32,
+    pub to: u32,
+    /// Where the store was backed up to, if it was.
+    pub backup: Option<PathBuf>,
+}
+
+impl fmt::Display for StoreUpgrade {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}) v{} -> v{}", self.store, self.path.display(), self.from, self.to)?;
+        if let Some(backup) = &self.backup {
+            write!(f, ", backed up to {}", backup.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Version marker of a store, kept next to it.
+#[derive(Debug, Serialize, Deserialize)]
+struct SchemaVersion {
+    store: Store,
+    version: u32,
+}
+
+/// The stores enabled in `config`, with their paths.
+pub fn configured_stores(config: &Config) -> Vec<(Store, PathBuf)> {
+    [
+        (Store::ConstraintsJournal, &config.constraints_journal_path),
+        (Store::SubmissionLog, &config.submission_log_path),
+        (Store::AuditLog, &config.audit_log_path),
+        (Store::RequestLog, &config.request_log_path),
+        (Store::DelegationRegistry, &config.delegation_registry_path),
+    ]
+    .into_iter()
+    .filter_map(|(store, path)| path.clone().map(|path| (store, path)))
+    .collect()
+}
+
+/// Bring the `stores` to their current version before they are opened, returning the
+/// migrations applied, or due with [MigrateOptions::dry_run].
+///
+/// Nothing is migrated when a store was written by a newer sidecar, which can't be downgraded.
+pub fn migrate_stores(
+    stores: &[(Store, PathBuf)],
+    options: MigrateOptions,
+) -> Result<Vec<StoreUpgrade>, SchemaError> {
+    for (store, path) in stores {
+        let version = read_version(*store, path)?.unwrap_or_default();
+        if version > store.version() {
+            return Err(SchemaError::Newer { store: *store, version, supported: store.version() });
+        }
+    }
+
+    let mut upgrades = Vec::new();
+    for (store, path) in stores {
+        if let Some(upgrade) = migrate_store(*store, path, store.migrations(), options)? {
+            upgrades.push(upgrade);
+        }
+    }
+    Ok(upgrades)
+}
+
+fn migrate_store(
+    store: Store,
+    path: &Path,
+    migrations: &[Migration],
+    options: MigrateOptions,
+) -> Result<Option<StoreUpgrade>, SchemaError> {
+    let current = migrations.len() as u32;
+    let stamp = |version| {
+        write_version(path, &SchemaVersion { store, version })
+            .map_err(|source| SchemaError::Migration { store, from: version, source })
+    };
+
+    // New stores are created in the current version
+    if !path.exists() {
+        if !options.dry_run {
+            stamp(current)?;
+        }
+        return Ok(None);
+    }
+
+    let from = read_version(store, path)?.unwrap_or_default();
+    if from >= current {
+        return Ok(None);
+    }
+    let mut upgrade =
+        StoreUpgrade { store, path: path.to_path_buf(), from, to: current, backup: None };
+    if options.dry_run {
+        return Ok(Some(upgrade));
+    }
+
+    if options.backup {
+        let backup = sibling(path, &format!(".v{from}.bak"));
+        copy_store(path, &backup).map_err(|source| SchemaError::Backup { store, source })?;
+        upgrade.backup = Some(backup);
+    }
+
+    // Stamped after each step, so that an interrupted upgrade resumes where it stopped
+    for (version, migration) in migrations.iter().enumerate().skip(from as usize) {
+        let version = version as u32;
+        tracing::info!(%store, version, migration = migration.description, "Migrating store");
+        (migration.apply)(path).map_err(|source| SchemaError::Migration {
+            store,
+            from: version,
+            source,
+        })?;
+        stamp(version + 1)?;
+    }
+    Ok(Some(upgrade))
+}
+
+/// The version of the store at `path`, `None` when it was written before it was versioned.
+fn read_version(store: Store, path: &Path) -> Result<Option<u32>, SchemaError> {
+    let content = match std::fs::read(sibling(path, ".schema")) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => return Err(SchemaError::Version { store, source }),
+    };
+    let marker: SchemaVersion = serde_json::from_slice(&content)
+        .map_err(|err| SchemaError::Version { store, source: err.into() })?;
+    Ok(Some(marker.version))
+}
+
+/// Replace the version marker atomically, so that a crash never leaves a partial one.
+fn write_version(path: &Path, version: &SchemaVersion) -> io::Result<()> {
+    let marker = sibling(path, ".schema");
+    let tmp = sibling(&marker, &format!(".tmp.{}", std::process::id()));
+    std::fs::write(&tmp, serde_json::to_vec(version)?)?;
+    std::fs::rename(tmp, marker)
+}
+
+/// Copy the store at `path` to `backup`, along with the write-ahead log of SQLite stores.
+fn copy_store(path: &Path, backup: &Path) -> io::Result<()> {
+    std::fs::copy(path, backup)?;
+    let wal = sibling(path, "-wal");
+    if wal.exists() {
+        std::fs::copy(wal, sibling(backup, "-wal"))?;
+    }
+    Ok(())
+}
+
+/// `path` with `suffix` appended to its file name.
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, path::Path};
+
+    use super::{
+        migrate_store, migrate_stores, read_version, sibling, MigrateOptions, Migration,
+        SchemaError, Store,
+    };
+
+    fn append(path: &Path) -> io::Result<()> {
+        let mut content = std::fs::read_to_string(path)?;
+        content.push('+');
+        std::fs::write(path, content)
+    }
+
+    #[test]
+    fn test_migrate_store() {
+        let dir = std::env::temp_dir().join(format!("schema-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.jsonl");
+        let migrations = [
+            Migration { description: "first", apply: append },
+            Migration { description: "second", apply: append },
+        ];
+        let options = MigrateOptions { dry_run: false, backup: true };
+
+        // New stores are stamped with the current version
+        assert_eq!(migrate_store(Store::AuditLog, &path, &migrations, options).unwrap(), None);
+        assert_eq!(read_version(Store::AuditLog, &path).unwrap(), Some(2));
+        std::fs::write(&path, "v0").unwrap();
+        std::fs::remove_file(sibling(&path, ".schema")).unwrap();
+
+        // A dry run touches nothing
+        let dry_run = MigrateOptions { dry_run: true, ..options };
+        let upgrade = migrate_store(Store::AuditLog, &path, &migrations, dry_run).unwrap();
+        assert_eq!(upgrade.map(|upgrade| (upgrade.from, upgrade.to)), Some((0, 2)));
+        assert_eq!(read_version(Store::AuditLog, &path).unwrap(), None);
+
+        let upgrade = migrate_store(Store::AuditLog, &path, &migrations, options).unwrap().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "v0++");
+        assert_eq!(std::fs::read_to_string(upgrade.backup.unwrap()).unwrap(), "v0");
+        assert_eq!(read_version(Store::AuditLog, &path).unwrap(), Some(2));
+        assert_eq!(migrate_store(Store::AuditLog, &path, &migrations, options).unwrap(), None);
+
+        // Stores written by a newer sidecar are refused
+        let err = migrate_stores(&[(Store::AuditLog, path)], options).unwrap_err();
+        assert!(matches!(err, SchemaError::Newer { version: 2, supported: 1, .. }));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
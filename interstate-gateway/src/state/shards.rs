@@ -0,0 +1,171 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::Arc,
+};
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    primitives::{Address, Bytes, B256},
+};
+use dashmap::DashMap;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+
+use super::{pricing::PriorityTier, Block};
+use crate::constraints::SignedConstraints;
+
+/// Pending blocks of the upcoming slots, sharded by slot so that the commitments to a slot,
+/// the submission of another one at its deadline and the head updates don't wait on each
+/// other.
+///
+/// The blocks are only locked for the time of an update. The commitments to a slot are
+/// serialized by its commit lock, held from their validation until their constraints are
+/// added, and by the submission of its constraints. The commitments of a sender are also
+/// serialized across slots by its commit lock, so that each is validated against the nonces
+/// and the spend of the previous ones.
+#[derive(Debug, Clone, Default)]
+pub struct SlotShards {
+    blocks: Arc<DashMap<u64, Block>>,
+    commits: Arc<DashMap<u64, Arc<Mutex<()>>>>,
+    senders: Arc<DashMap<Address, Arc<Mutex<()>>>>,
+    /// Constraints submitted to the relays for each slot, by hash with their EIP-2718
+    /// encoding, which the relay bids must prove they include.
+    submitted: Arc<DashMap<u64, HashMap<B256, Bytes>>>,
+}
+
+impl SlotShards {
+    /// Wait for the commit lock of `slot`.
+    pub async fn lock_slot(&self, slot: u64) -> OwnedMutexGuard<()> {
+        let lock = self.commits.entry(slot).or_default().clone();
+        lock.lock_owned().await
+    }
+
+    /// Wait for the commit locks of `senders`, taken in order so that the requests sharing
+    /// senders can't deadlock. Taken after the commit lock of the slot.
+    pub async fn lock_senders(
+        &self,
+        senders: impl IntoIterator<Item = Address>,
+    ) -> Vec<OwnedMutexGuard<()>> {
+        let senders = senders.into_iter().collect::<BTreeSet<_>>();
+        let mut guards = Vec::with_capacity(senders.len());
+        for sender in senders {
+            let lock = self.senders.entry(sender).or_default().clone();
+            guards.push(lock.lock_owned().await);
+        }
+        guards
+    }
+
+    pub fn contains(&self, slot: u64) -> bool {
+        self.blocks.contains_key(&slot)
+    }
+
+    /// Read the block of `slot`, if any.
+    pub fn with_block<T>(&self, slot: u64, f: impl FnOnce(&Block) -> T) -> Option<T> {
+        self.blocks.get(&slot).map(|block| f(&block))
+    }
+
+    /// Add constraints of the `priority` tier to the block of `slot`, accepted at
+    /// `accepted_ms`.
+    pub fn add_constraints(
+        &self,
+        slot: u64,
+        constraints: SignedConstraints,
+        accepted_ms: u64,
+        priority: PriorityTier,
+    ) {
+        let mut block = self.blocks.entry(slot).or_default();
+        for constraint in &constraints.message.transactions {
+            block.accepted_ms.entry(*constraint.tx.hash()).or_insert(accepted_ms);
+        }
+        block.add_prioritized(constraints, priority);
+    }
+
+    pub fn replace_constraints(&self, slot: u64, constraints: &Vec<SignedConstraints>) {
+        self.blocks.entry(slot).or_default().replace_constraints(constraints);
+    }
+
+    pub fn remove(&self, slot: u64) -> Option<Block> {
+        self.blocks.remove(&slot).map(|(_, block)| block)
+    }
+
+    /// Record the constraints of `block` as submitted for `slot`, next to the ones submitted
+    /// before.
+    pub fn record_submitted(&self, slot: u64, block: &Block) {
+        let mut submitted = self.submitted.entry(slot).or_default();
+        for tx in block.convert_constraints_to_transactions() {
+            submitted.insert(tx.hash(), tx.encoded_2718().into());
+        }
+    }
+
+    /// Constraints submitted for `slot`, by hash with their EIP-2718 encoding.
+    pub fn submitted(&self, slot: u64) -> Option<HashMap<B256, Bytes>> {
+        self.submitted.get(&slot).map(|submitted| submitted.clone())
+    }
+
+    /// Drop the blocks of `slot` and the slots before, which can no longer be proposed.
+    pub fn prune(&self, slot: u64) {
+        self.blocks.retain(|s, _| *s > slot);
+        self.commits.retain(|s, _| *s > slot);
+        self.submitted.retain(|s, _| *s > slot);
+        // The locks of the senders without a commitment in flight
+        self.senders.retain(|_, lock| Arc::strong_count(lock) > 1);
+    }
+
+    /// Bytes of blob sidecars held for the pending constraints of all the slots.
+    pub fn blob_bytes(&self) -> usize {
+        self.blocks.iter().map(|block| block.blob_bytes()).sum()
+    }
+
+    /// Number of constraints pending for each slot.
+    pub fn pending_constraints(&self) -> BTreeMap<u64, usize> {
+        self.blocks.iter().map(|block| (*block.key(), block.transactions_count())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy::primitives::Address;
+
+    use super::SlotShards;
+    use crate::{
+        constraints::{ConstraintsMessage, SignedConstraints},
+        state::pricing::PriorityTier,
+    };
+
+    #[tokio::test]
+    async fn test_slots_locked_independently() {
+        let shards = SlotShards::default();
+        let signed = |slot: u64| SignedConstraints {
+            message: ConstraintsMessage { slot, ..Default::default() },
+            signature: Default::default(),
+        };
+
+        let _committing = shards.lock_slot(10).await;
+        // Another slot isn't held by the commitments to slot 10
+        tokio::time::timeout(Duration::from_secs(1), shards.lock_slot(11)).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(10), shards.lock_slot(10))
+            .await
+            .is_err());
+
+        shards.add_constraints(10, signed(10), 0, PriorityTier::Standard);
+        shards.add_constraints(11, signed(11), 0, PriorityTier::Standard);
+        shards.add_constraints(11, signed(11), 0, PriorityTier::Fast);
+        assert_eq!(shards.pending_constraints().into_iter().collect::<Vec<_>>(), [(10, 1), (11, 2)]);
+
+        shards.prune(10);
+        assert!(!shards.contains(10));
+        assert_eq!(shards.remove(11).unwrap().transactions_count(), 2);
+
+        // A sender is held across slots, until its commitment is added
+        let (alice, bob) = (Address::with_last_byte(1), Address::with_last_byte(2));
+        let committing = shards.lock_senders([bob, alice, bob]).await;
+        assert_eq!(committing.len(), 2);
+        assert!(tokio::time::timeout(Duration::from_millis(10), shards.lock_senders([alice]))
+            .await
+            .is_err());
+        shards.prune(11);
+        drop(committing);
+        tokio::time::timeout(Duration::from_secs(1), shards.lock_senders([alice])).await.unwrap();
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use ethereum_consensus::phase0::mainnet::SLOTS_PER_EPOCH;
+use parking_lot::Mutex;
+
+use crate::{config::ChainConfig, metrics::ApiMetrics};
+
+/// Number of recent head events used to estimate the clock drift.
+const DRIFT_WINDOW_SLOTS: usize = 16;
+
+/// Drift above which the slot clock offset is corrected.
+pub const DEFAULT_DRIFT_THRESHOLD_MILLIS: u64 = 500;
+
+/// Wall-clock view of the beacon chain slots, corrected for host clock drift.
+///
+/// Head events can't arrive before their slot starts, and on a healthy node at least some
+/// of them arrive before the attestation deadline (a third into the slot). The smallest
+/// delay over the recent window falling outside of that envelope means the host clock has
+/// skewed, in which case the offset is corrected so slot boundaries stay aligned with the chain.
+/// The drift is only estimated over a full window, so that a single late head doesn't move it.
+///
+/// Clones share the correction, so that every component follows the slots of the chain.
+#[derive(Debug, Clone)]
+pub struct SlotClock {
+    genesis_time: u64,
+    slot_time: u64,
+    slots_per_epoch: u64,
+    drift_threshold_ms: i64,
+    /// Correction applied to the system clock, in milliseconds.
+    offset_ms: Arc<AtomicI64>,
+    /// Recent head event delays relative to the start of their slot, in milliseconds.
+    delays: Arc<Mutex<VecDeque<i64>>>,
+}
+
+impl SlotClock {
+    pub fn new(genesis_time: u64, slot_time: u64, drift_threshold_ms: u64) -> Self {
+        Self {
+            genesis_time,
+            slot_time,
+            slots_per_epoch: SLOTS_PER_EPOCH,
+            drift_threshold_ms: drift_threshold_ms as i64,
+            offset_ms: Arc::default(),
+            delays: Arc::new(Mutex::new(VecDeque::with_capacity(DRIFT_WINDOW_SLOTS))),
+        }
+    }
+
+    /// The clock of a beacon chain started at `genesis_time`, with the slot timing of `chain`.
+    pub fn for_chain(genesis_time: u64, chain: &ChainConfig, drift_threshold_ms: u64) -> Self {
+        Self {
+            slots_per_epoch: chain.slots_per_epoch,
+            ..Self::new(genesis_time, chain.slot_time, drift_threshold_ms)
+        }
+    }
+
+    pub fn slot_time(&self) -> u64 {
+        self.slot_time
+    }
+
+    pub fn slots_per_epoch(&self) -> u64 {
+        self.slots_per_epoch
+    }
+
+    /// The epoch of `slot`.
+    pub fn epoch_of(&self, slot: u64) -> u64 {
+        slot / self.slots_per_epoch
+    }
+
+    /// Current time in milliseconds since the unix epoch, with the drift correction applied.
+    pub fn now_ms(&self) -> i64 {
+        to_unix_ms(SystemTime::now()) + self.offset_ms()
+    }
+
+    /// Start time of the given slot in milliseconds since the unix epoch.
+    pub fn slot_start_ms(&self, slot: u64) -> i64 {
+        ((self.genesis_time + slot * self.slot_time) * 1_000) as i64
+    }
+
+    /// The slot of a block with the given timestamp in seconds.
+    pub fn slot_at(&self, timestamp: u64) -> u64 {
+        timestamp.saturating_sub(self.genesis_time) / self.slot_time
+    }
+
+    /// The slot the corrected clock is currently in.
+    pub fn current_slot(&self) -> u64 {
+        let since_genesis = self.now_ms() - (self.genesis_time * 1_000) as i64;
+        (since_genesis.max(0) as u64) / (self.slot_time * 1_000)
+    }
+
+    /// Time left until `offset` into the given slot, or zero if it has already passed.
+    pub fn duration_until(&self, slot: u64, offset: Duration) -> Duration {
+        let target = self.slot_start_ms(slot) + offset.as_millis() as i64;
+        Duration::from_millis((target - self.now_ms()).max(0) as u64)
+    }
+
+    /// Record the arrival of a head event for `slot` and return the estimated drift in ms.
+    ///
+    /// If the drift over a full window exceeds the threshold the clock offset is corrected and
+    /// the window reset.
+    pub fn observe_head(&self, slot: u64, arrival: SystemTime) -> i64 {
+        let mut delays = self.delays.lock();
+        let offset_ms = self.offset_ms();
+        let delay = to_unix_ms(arrival) + offset_ms - self.slot_start_ms(slot);
+
+        if delays.len() == DRIFT_WINDOW_SLOTS {
+            delays.pop_front();
+        }
+        delays.push_back(delay);
+
+        let drift = self.drift_of(&delays);
+        ApiMetrics::set_slot_clock_drift(drift);
+
+        if delays.len() == DRIFT_WINDOW_SLOTS && drift.abs() > self.drift_threshold_ms {
+            tracing::warn!(
+                slot,
+                drift_ms = drift,
+                offset_ms,
+                "Host clock drifted from the beacon chain, correcting slot clock offset"
+            );
+            self.offset_ms.store(offset_ms - drift, Ordering::Relaxed);
+            delays.clear();
+        }
+
+        drift
+    }
+
+    /// Estimated drift of the corrected clock in milliseconds. Positive values mean the host
+    /// clock is ahead of the chain.
+    pub fn drift_ms(&self) -> i64 {
+        self.drift_of(&self.delays.lock())
+    }
+
+    fn drift_of(&self, delays: &VecDeque<i64>) -> i64 {
+        let Some(min_delay) = delays.iter().min().copied() else {
+            return 0;
+        };
+
+        let max_delay = (self.slot_time * 1_000 / 3) as i64;
+        if min_delay < 0 {
+            min_delay
+        } else if min_delay > max_delay {
+            min_delay - max_delay
+        } else {
+            0
+        }
+    }
+
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
+}
+
+fn to_unix_ms(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_millis() as i64,
+        Err(err) => -(err.duration().as_millis() as i64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{SlotClock, DRIFT_WINDOW_SLOTS};
+
+    #[test]
+    fn test_slot_clock_corrects_drift() {
+        let clock = SlotClock::new(1_000, 12, 500);
+        let shared = clock.clone();
+
+        // Head event arriving 1s into the slot is within the expected envelope
+        let arrival = UNIX_EPOCH + Duration::from_secs(1_000 + 12 + 1);
+        assert_eq!(clock.observe_head(1, arrival), 0);
+        assert_eq!(clock.offset_ms(), 0);
+
+        // Head events arriving 2s before their slot starts: host clock is 2s behind, which is
+        // only corrected once seen over a full window
+        for slot in 2..DRIFT_WINDOW_SLOTS as u64 {
+            let arrival = UNIX_EPOCH + Duration::from_secs(1_000 + slot * 12 - 2);
+            assert_eq!(clock.observe_head(slot, arrival), -2_000);
+            assert_eq!(clock.offset_ms(), 0);
+        }
+        let slot = DRIFT_WINDOW_SLOTS as u64;
+        let arrival = UNIX_EPOCH + Duration::from_secs(1_000 + slot * 12 - 2);
+        assert_eq!(clock.observe_head(slot, arrival), -2_000);
+        assert_eq!(clock.offset_ms(), 2_000);
+        // The clones of the clock are corrected too
+        assert_eq!(shared.offset_ms(), 2_000);
+
+        // After the correction the same skew is no longer detected
+        let arrival = UNIX_EPOCH + Duration::from_secs(1_000 + (slot + 1) * 12 - 1);
+        assert_eq!(shared.observe_head(slot + 1, arrival), 0);
+    }
+}
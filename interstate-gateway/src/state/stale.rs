@@ -0,0 +1,143 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::Arc,
+};
+
+use alloy_v092::primitives::{Address, TxHash};
+use parking_lot::RwLock;
+
+use crate::commitment::request::PreconfRequest;
+
+/// Number of recent blocks whose transactions are indexed.
+const RECENT_BLOCKS: usize = 64;
+
+/// Why a transaction of a request can never be included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleReason {
+    /// The transaction is in one of the recent blocks.
+    AlreadyIncluded,
+    /// The sender already used the nonce, its next one is `next`.
+    NonceConsumed { nonce: u64, next: u64 },
+}
+
+/// A transaction of a request that can never be included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleTx {
+    /// Index of the transaction in the request.
+    pub index: usize,
+    pub reason: StaleReason,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+    /// Next nonce of the senders whose account state is cached, as of the head.
+    nonces: HashMap<Address, u64>,
+    /// Transactions of the recent blocks, the latest last.
+    blocks: VecDeque<(u64, HashSet<TxHash>)>,
+}
+
+/// Cheap index of the account nonces and recent transactions known to the execution state,
+/// to refuse requests for transactions already mined or with a consumed nonce before paying
+/// for their full validation under the state lock.
+///
+/// Nonces only ever increase, so a nonce below the one of a lagging head is still consumed.
+#[derive(Debug, Clone, Default)]
+pub struct StaleTxIndex(Arc<RwLock<Index>>);
+
+impl StaleTxIndex {
+    /// Record the next nonces of accounts as of the head.
+    pub fn record_nonces(&self, nonces: impl IntoIterator<Item = (Address, u64)>) {
+        self.0.write().nonces.extend(nonces);
+    }
+
+    /// Forget the nonces of the accounts no longer cached.
+    pub fn retain_nonces(&self, cached: impl Fn(&Address) -> bool) {
+        self.0.write().nonces.retain(|address, _| cached(address));
+    }
+
+    /// Whether the transactions of block `number` are indexed.
+    pub fn has_block(&self, number: u64) -> bool {
+        self.0.read().blocks.iter().any(|(block, _)| *block == number)
+    }
+
+    /// Index the transactions of block `number`, dropping the oldest block if full.
+    pub fn record_block(&self, number: u64, hashes: impl IntoIterator<Item = TxHash>) {
+        let mut index = self.0.write();
+        index.blocks.push_back((number, hashes.into_iter().collect()));
+        while index.blocks.len() > RECENT_BLOCKS {
+            index.blocks.pop_front();
+        }
+    }
+
+    /// The first transaction of `request` already mined or with a consumed nonce.
+    pub fn check(&self, request: &PreconfRequest) -> Option<StaleTx> {
+        let index = self.0.read();
+        request.txs.iter().enumerate().find_map(|(i, constraint)| {
+            let hash = TxHash::from_slice(constraint.tx.hash().as_slice());
+            if index.blocks.iter().any(|(_, hashes)| hashes.contains(&hash)) {
+                return Some(StaleTx { index: i, reason: StaleReason::AlreadyIncluded });
+            }
+
+            let sender = constraint.sender.or_else(|| constraint.tx.recover_signer())?;
+            let next = *index.nonces.get(&Address::from_slice(sender.as_slice()))?;
+            let nonce = constraint.tx.nonce();
+            (nonce < next)
+                .then_some(StaleTx { index: i, reason: StaleReason::NonceConsumed { nonce, next } })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        eips::eip2718::Encodable2718,
+        network::EthereumWallet,
+        primitives::{PrimitiveSignature, U256},
+        signers::local::PrivateKeySigner,
+    };
+    use alloy_v092::primitives::{Address, TxHash};
+
+    use super::{StaleReason, StaleTx, StaleTxIndex};
+    use crate::{
+        commitment::request::PreconfRequest, constraints::Constraint,
+        test_utils::default_test_transaction,
+    };
+
+    #[tokio::test]
+    async fn test_stale_transactions() -> eyre::Result<()> {
+        let signer = PrivateKeySigner::random();
+        let wallet = EthereumWallet::from(signer.clone());
+        let raw = default_test_transaction(signer.address(), Some(3)).build(&wallet).await?;
+        let request = PreconfRequest {
+            slot: 1,
+            txs: vec![Constraint::decode_enveloped(raw.encoded_2718())?],
+            signature: PrimitiveSignature::new(U256::ZERO, U256::ZERO, false),
+            sender: signer.address(),
+            chain_id: 1337,
+            quote: None,
+            atomic: false,
+            partial: false,
+            priority: Default::default(),
+            inclusion_list: false,
+        };
+        let sender = Address::from_slice(signer.address().as_slice());
+        let index = StaleTxIndex::default();
+        assert_eq!(index.check(&request), None);
+
+        // The nonce of the transaction is the next one, or unknown
+        index.record_nonces([(sender, 3)]);
+        assert_eq!(index.check(&request), None);
+        index.record_nonces([(sender, 4)]);
+        let consumed = StaleReason::NonceConsumed { nonce: 3, next: 4 };
+        assert_eq!(index.check(&request), Some(StaleTx { index: 0, reason: consumed }));
+
+        // Mined in a recent block
+        index.retain_nonces(|_| false);
+        index.record_block(100, [TxHash::from_slice(raw.tx_hash().as_slice())]);
+        assert!(index.has_block(100));
+        let included = StaleTx { index: 0, reason: StaleReason::AlreadyIncluded };
+        assert_eq!(index.check(&request), Some(included));
+
+        Ok(())
+    }
+}
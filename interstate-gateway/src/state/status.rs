@@ -0,0 +1,240 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::{self, Write},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use beacon_api_client::ProposerDuty;
+use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::utils::now_ms;
+
+/// Number of recent errors kept for the operators.
+const MAX_RECENT_ERRORS: usize = 10;
+
+/// Components of the sidecar whose health is reported to the operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Component {
+    Relay,
+    Signer,
+}
+
+impl Component {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Relay => "relay",
+            Self::Signer => "signer",
+        }
+    }
+}
+
+/// Outcome of the latest calls to a component.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ComponentHealth {
+    pub last_success_ms: Option<u64>,
+    pub last_failure_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+impl ComponentHealth {
+    /// Whether the latest call succeeded, `None` if the component wasn't called yet.
+    pub fn is_healthy(&self) -> Option<bool> {
+        match (self.last_success_ms, self.last_failure_ms) {
+            (None, None) => None,
+            (_, None) => Some(true),
+            (None, Some(_)) => Some(false),
+            (Some(success), Some(failure)) => Some(success > failure),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct UpcomingProposal {
+    pub slot: u64,
+    #[schema(value_type = String)]
+    pub pubkey: BlsPublicKey,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RecordedError {
+    pub at_ms: u64,
+    pub source: String,
+    pub message: String,
+}
+
+/// Live state of the sidecar, served to the operators by the status endpoint.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct SidecarStatus {
+    pub current_slot: u64,
+    pub upcoming_proposals: Vec<UpcomingProposal>,
+    /// Number of signed constraints waiting for the deadline, per slot.
+    pub pending_constraints: BTreeMap<u64, usize>,
+    pub relay: ComponentHealth,
+    pub signer: ComponentHealth,
+    /// Most recent errors first.
+    #[schema(value_type = Vec<RecordedError>)]
+    pub recent_errors: VecDeque<RecordedError>,
+}
+
+impl SidecarStatus {
+    /// Render the status as a terminal view, with ages relative to `now_ms`.
+    pub fn render(&self, now_ms: u64) -> String {
+        let ago = |at_ms: u64| format!("{}s ago", now_ms.saturating_sub(at_ms) / 1000);
+        let mut out = String::new();
+
+        let _ = writeln!(out, "Current slot      {}", self.current_slot);
+
+        let _ = writeln!(out, "\nUpcoming proposals");
+        if self.upcoming_proposals.is_empty() {
+            let _ = writeln!(out, "  none this epoch");
+        }
+        for proposal in &self.upcoming_proposals {
+            let _ = writeln!(out, "  slot {:<10} {:?}", proposal.slot, proposal.pubkey);
+        }
+
+        let _ = writeln!(out, "\nPending constraints");
+        if self.pending_constraints.is_empty() {
+            let _ = writeln!(out, "  none");
+        }
+        for (slot, count) in &self.pending_constraints {
+            let _ = writeln!(out, "  slot {slot:<10} {count}");
+        }
+
+        let _ = writeln!(out);
+        for (component, health) in [(Component::Relay, &self.relay), (Component::Signer, &self.signer)]
+        {
+            let state = match (health.is_healthy(), health.last_success_ms, &health.last_error) {
+                (None, ..) => "unknown".to_string(),
+                (Some(true), Some(at_ms), _) => format!("ok, last success {}", ago(at_ms)),
+                (_, _, error) => format!(
+                    "FAILING since {}: {}",
+                    ago(health.last_failure_ms.unwrap_or_default()),
+                    error.as_deref().unwrap_or_default()
+                ),
+            };
+            let _ = writeln!(out, "{:<18}{state}", format!("{} health", component.as_str()));
+        }
+
+        let _ = writeln!(out, "\nLast errors");
+        if self.recent_errors.is_empty() {
+            let _ = writeln!(out, "  none");
+        }
+        for error in &self.recent_errors {
+            let _ = writeln!(out, "  {:<10} [{}] {}", ago(error.at_ms), error.source, error.message);
+        }
+
+        out
+    }
+}
+
+/// Status of the sidecar, updated as it runs and shared with the status endpoint.
+#[derive(Debug, Clone)]
+pub struct StatusBoard {
+    status: Arc<RwLock<SidecarStatus>>,
+    started: Instant,
+}
+
+impl Default for StatusBoard {
+    fn default() -> Self {
+        Self { status: Default::default(), started: Instant::now() }
+    }
+}
+
+impl StatusBoard {
+    pub fn snapshot(&self) -> SidecarStatus {
+        self.status.read().clone()
+    }
+
+    /// Time since the sidecar started.
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Whether the latest call to the relay succeeded, `None` if it wasn't called yet.
+    pub fn is_relay_healthy(&self) -> Option<bool> {
+        self.status.read().relay.is_healthy()
+    }
+
+    /// Record the head slot, the proposals left in the epoch and the pending constraints.
+    pub fn update(
+        &self,
+        slot: u64,
+        duties: &[ProposerDuty],
+        pending_constraints: BTreeMap<u64, usize>,
+    ) {
+        let mut status = self.status.write();
+        status.current_slot = slot;
+        status.upcoming_proposals = duties
+            .iter()
+            .filter(|duty| duty.slot > slot)
+            .map(|duty| UpcomingProposal { slot: duty.slot, pubkey: duty.public_key.clone() })
+            .collect();
+        status.pending_constraints = pending_constraints;
+    }
+
+    pub fn record_success(&self, component: Component) {
+        let mut status = self.status.write();
+        let health = match component {
+            Component::Relay => &mut status.relay,
+            Component::Signer => &mut status.signer,
+        };
+        health.last_success_ms = Some(now_ms());
+    }
+
+    /// Record a failed call to `component`, also kept as a recent error.
+    pub fn record_failure(&self, component: Component, err: impl fmt::Display) {
+        let message = err.to_string();
+        {
+            let mut status = self.status.write();
+            let health = match component {
+                Component::Relay => &mut status.relay,
+                Component::Signer => &mut status.signer,
+            };
+            health.last_failure_ms = Some(now_ms());
+            health.last_error = Some(message.clone());
+        }
+        self.record_error(component.as_str(), message);
+    }
+
+    pub fn record_error(&self, source: &str, err: impl fmt::Display) {
+        let mut status = self.status.write();
+        status.recent_errors.push_front(RecordedError {
+            at_ms: now_ms(),
+            source: source.to_string(),
+            message: err.to_string(),
+        });
+        status.recent_errors.truncate(MAX_RECENT_ERRORS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Component, StatusBoard, MAX_RECENT_ERRORS};
+
+    #[test]
+    fn test_component_health() {
+        let status = StatusBoard::default();
+        assert_eq!(status.snapshot().relay.is_healthy(), None);
+
+        status.record_success(Component::Relay);
+        status.record_failure(Component::Signer, "keystore locked");
+        let snapshot = status.snapshot();
+        assert_eq!(snapshot.relay.is_healthy(), Some(true));
+        assert_eq!(snapshot.signer.is_healthy(), Some(false));
+        assert_eq!(snapshot.recent_errors[0].source, "signer");
+
+        let view = snapshot.render(snapshot.recent_errors[0].at_ms);
+        assert!(view.contains("FAILING since 0s ago: keystore locked"));
+
+        for i in 0..2 * MAX_RECENT_ERRORS {
+            status.record_error("head", i);
+        }
+        let snapshot = status.snapshot();
+        assert_eq!(snapshot.recent_errors.len(), MAX_RECENT_ERRORS);
+        assert_eq!(snapshot.recent_errors[0].message, (2 * MAX_RECENT_ERRORS - 1).to_string());
+    }
+}
@@ -0,0 +1,167 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::{
+    revenue::{ProposalRevenue, RevenueTracker},
+    slot_clock::SlotClock,
+};
+use crate::{
+    commitment::events::{ApiEvent, EventBroadcaster},
+    utils::{
+        now_ms,
+        score_cache::{ScoreCacheStats, SharedScoreCacheStats},
+    },
+};
+
+/// State the primary shares with the read-only replicas serving the query endpoints.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreSnapshot {
+    /// Unix timestamp in ms at which the primary wrote the snapshot.
+    pub updated_at_ms: u64,
+    pub current_slot: u64,
+    /// Latest pricing update of the primary.
+    pub pricing: Option<ApiEvent>,
+    pub proposals: Vec<ProposalRevenue>,
+    pub account_states: ScoreCacheStats,
+}
+
+impl StoreSnapshot {
+    /// Age of the snapshot in ms, i.e. how stale the data served by a replica is.
+    pub fn age_ms(&self) -> u64 {
+        now_ms().saturating_sub(self.updated_at_ms)
+    }
+}
+
+/// Persistent store shared by the instances of a sidecar, e.g. a file on a shared volume.
+///
+/// Only the primary writes it, replacing the snapshot atomically so that the replicas
+/// reloading it never see a partial write.
+#[derive(Debug, Clone)]
+pub struct SharedStore {
+    path: PathBuf,
+}
+
+impl SharedStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&self, snapshot: &StoreSnapshot) -> io::Result<()> {
+        let tmp = self.path.with_extension(format!("tmp.{}", std::process::id()));
+        std::fs::write(&tmp, serde_json::to_vec(snapshot)?)?;
+        std::fs::rename(tmp, &self.path)
+    }
+
+    pub fn read(&self) -> io::Result<StoreSnapshot> {
+        Ok(serde_json::from_slice(&std::fs::read(&self.path)?)?)
+    }
+
+    /// Publish the state of the primary every `interval` in the background.
+    pub fn spawn_publisher(
+        &self,
+        interval: Duration,
+        slot_clock: SlotClock,
+        revenue: RevenueTracker,
+        account_states: SharedScoreCacheStats,
+        events: EventBroadcaster,
+    ) {
+        let store = self.clone();
+        let pricing = Arc::new(RwLock::new(None));
+
+        // Keep the latest pricing update, it's computed under the constraint state lock
+        let latest = pricing.clone();
+        let mut events = events.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event @ ApiEvent::PricingUpdate { .. }) => *latest.write() = Some(event),
+                    Ok(_) | Err(RecvError::Lagged(_)) => {}
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+
+                let snapshot = StoreSnapshot {
+                    updated_at_ms: now_ms(),
+                    current_slot: slot_clock.current_slot(),
+                    pricing: pricing.read().clone(),
+                    proposals: revenue.proposals(),
+                    account_states: account_states.read().clone(),
+                };
+                if let Err(err) = store.write(&snapshot) {
+                    tracing::error!(?err, path = %store.path.display(), "Failed to publish the shared store");
+                }
+            }
+        });
+    }
+
+    /// Reload the snapshot published by the primary every `interval` in the background.
+    pub fn spawn_reloader(&self, interval: Duration) -> Arc<RwLock<Option<StoreSnapshot>>> {
+        let snapshot = Arc::new(RwLock::new(None));
+
+        let store = self.clone();
+        let latest = snapshot.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match store.read() {
+                    Ok(loaded) => *latest.write() = Some(loaded),
+                    // Keep serving the previous snapshot, the primary may not have started yet
+                    Err(err) => tracing::warn!(?err, "Failed to reload the shared store"),
+                }
+            }
+        });
+
+        snapshot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_v092::primitives::U256;
+
+    use super::{SharedStore, StoreSnapshot};
+    use crate::{commitment::events::ApiEvent, state::revenue::RevenueTracker};
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let path = std::env::temp_dir().join(format!("store-test-{}.json", std::process::id()));
+        let store = SharedStore::new(path.clone());
+
+        let revenue = RevenueTracker::new(32);
+        revenue.record_bid(40, U256::from(50), true);
+
+        let snapshot = StoreSnapshot {
+            current_slot: 41,
+            pricing: Some(ApiEvent::PricingUpdate { slot: 42, basefee: 7, min_priority_fee: None }),
+            proposals: revenue.proposals(),
+            ..Default::default()
+        };
+        store.write(&snapshot).unwrap();
+
+        // Replicas serve the same reports as the primary
+        let loaded = store.read().unwrap();
+        assert_eq!(loaded.pricing, snapshot.pricing);
+        assert_eq!(RevenueTracker::from_proposals(loaded.proposals, 32).to_csv(), revenue.to_csv());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
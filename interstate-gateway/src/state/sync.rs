@@ -0,0 +1,176 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy_v092::{primitives::B256, rpc::types::SyncStatus};
+use eyre::Context;
+use parking_lot::RwLock;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::execution_client::ExecutionClient;
+use crate::{metrics::ApiMetrics, utils::url::join_path};
+
+/// Beacon block of the head, whose execution payload is the head of the chain.
+const BEACON_HEAD_BLOCK_PATH: &str = "/eth/v2/beacon/blocks/head";
+
+/// Beacon blocks, by slot.
+const BEACON_BLOCKS_PATH: &str = "/eth/v2/beacon/blocks";
+
+/// Default number of blocks the execution client can lag behind before commitments are refused.
+pub const DEFAULT_MAX_EL_LAG_BLOCKS: u64 = 2;
+
+/// Sync status of the execution client, as seen by the last poll.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ElSyncStatus {
+    /// Whether the execution client reports that it is syncing.
+    pub syncing: bool,
+    /// Number of blocks the execution client is behind the chain head.
+    pub lag_blocks: u64,
+}
+
+/// Tracks whether the execution client is synced enough for the validation of commitments
+/// against its state to be reliable.
+///
+/// The lag is taken from `eth_syncing` while the client syncs, and otherwise from the number
+/// of its latest block against the one of the execution payload of the beacon head, which
+/// catches a client stuck on a stale head without reporting it. Missed slots aren't lag.
+#[derive(Debug, Clone)]
+pub struct ElSyncMonitor {
+    status: Arc<RwLock<ElSyncStatus>>,
+    max_lag_blocks: u64,
+}
+
+impl ElSyncMonitor {
+    pub fn new(max_lag_blocks: u64) -> Self {
+        Self { status: Default::default(), max_lag_blocks }
+    }
+
+    pub fn status(&self) -> ElSyncStatus {
+        *self.status.read()
+    }
+
+    /// Returns the lag in blocks if the execution client is too far behind to accept
+    /// commitments.
+    pub fn check(&self) -> Result<(), u64> {
+        let status = self.status();
+        if status.lag_blocks > self.max_lag_blocks {
+            Err(status.lag_blocks)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn update(&self, status: ElSyncStatus) {
+        let was_healthy = self.check().is_ok();
+        *self.status.write() = status;
+        ApiMetrics::set_el_sync_lag_blocks(status.lag_blocks);
+
+        match (was_healthy, self.check().is_ok()) {
+            (true, false) => tracing::warn!(
+                lag_blocks = status.lag_blocks,
+                syncing = status.syncing,
+                "Execution client is behind, refusing new commitments"
+            ),
+            (false, true) => tracing::info!("Execution client caught up, accepting commitments"),
+            _ => {}
+        }
+    }
+
+    /// Poll the execution client and the beacon node every `interval` in the background.
+    pub fn spawn(
+        &self,
+        client: ExecutionClient,
+        beacon: reqwest::Client,
+        beacon_url: Url,
+        interval: Duration,
+    ) {
+        let monitor = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                match poll_sync_status(&client, &beacon, &beacon_url).await {
+                    Ok(status) => monitor.update(status),
+                    Err(err) => {
+                        tracing::error!(?err, "Failed to poll the execution client sync status")
+                    }
+                }
+            }
+        });
+    }
+}
+
+async fn poll_sync_status(
+    client: &ExecutionClient,
+    beacon: &reqwest::Client,
+    beacon_url: &Url,
+) -> eyre::Result<ElSyncStatus> {
+    if let SyncStatus::Info(info) = client.get_sync_status().await? {
+        let lag = info.highest_block.saturating_sub(info.current_block);
+        return Ok(ElSyncStatus { syncing: true, lag_blocks: lag.saturating_to() });
+    }
+
+    let head = client.get_head().await?;
+    let url = join_path(beacon_url, BEACON_HEAD_BLOCK_PATH)?;
+    let block: Value = beacon.get(url).send().await?.error_for_status()?.json().await?;
+    let chain_head = execution_block_number(&block).wrap_err("invalid beacon head block")?;
+
+    Ok(ElSyncStatus { syncing: false, lag_blocks: chain_head.saturating_sub(head) })
+}
+
+/// Number of the execution payload of a beacon block response.
+fn execution_block_number(block: &Value) -> eyre::Result<u64> {
+    let number = block
+        .pointer("/data/message/body/execution_payload/block_number")
+        .and_then(Value::as_str)
+        .ok_or_else(|| eyre::eyre!("missing execution payload block number"))?;
+    Ok(number.parse()?)
+}
+
+/// Hash of the execution payload of the beacon block of `slot`.
+pub async fn slot_execution_block_hash(
+    beacon: &reqwest::Client,
+    beacon_url: &Url,
+    slot: u64,
+) -> eyre::Result<B256> {
+    let url = join_path(beacon_url, &format!("{BEACON_BLOCKS_PATH}/{slot}"))?;
+    let block: Value = beacon.get(url).send().await?.error_for_status()?.json().await?;
+    execution_block_hash(&block).wrap_err("invalid beacon block")
+}
+
+/// Hash of the execution payload of a beacon block response.
+fn execution_block_hash(block: &Value) -> eyre::Result<B256> {
+    let hash = block
+        .pointer("/data/message/body/execution_payload/block_hash")
+        .and_then(Value::as_str)
+        .ok_or_else(|| eyre::eyre!("missing execution payload block hash"))?;
+    Ok(hash.parse()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_v092::primitives::B256;
+    use serde_json::json;
+
+    use super::{execution_block_hash, execution_block_number, ElSyncMonitor, ElSyncStatus};
+
+    #[test]
+    fn test_el_sync_gating() {
+        let block = json!({ "data": { "message": { "body": {
+            "execution_payload": { "block_number": "1036", "block_hash": format!("{}", B256::ZERO) }
+        } } } });
+        assert_eq!(execution_block_number(&block).unwrap(), 1_036);
+        assert_eq!(execution_block_hash(&block).unwrap(), B256::ZERO);
+        // Pre-merge blocks have no execution payload
+        assert!(execution_block_number(&json!({ "data": { "message": { "body": {} } } })).is_err());
+
+        let monitor = ElSyncMonitor::new(2);
+        assert!(monitor.check().is_ok());
+
+        monitor.update(ElSyncStatus { syncing: true, lag_blocks: 3 });
+        assert_eq!(monitor.clone().check(), Err(3));
+
+        monitor.update(ElSyncStatus { syncing: false, lag_blocks: 2 });
+        assert!(monitor.check().is_ok());
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy::primitives::{Keccak256, B256};
+use parking_lot::Mutex;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{constraints::SignedConstraints, metrics::ApiMetrics};
+
+/// Record of the write-ahead log of the constraints submissions, one JSON object per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum WalEntry {
+    /// About to submit the batch of constraints of the slot.
+    Intent { slot: u64, batch: B256, constraints: Vec<SignedConstraints> },
+    /// The relay acknowledged the batch.
+    Acked { slot: u64, batch: B256 },
+    /// The submission of the batch failed for good, e.g. it was rejected or missed the slot.
+    Abandoned { slot: u64, batch: B256 },
+}
+
+/// A submission that was in flight when the previous instance stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingSubmission {
+    pub slot: u64,
+    pub batch: B256,
+    pub constraints: Vec<SignedConstraints>,
+}
+
+/// Identifier of a batch of constraints, the hash of the digests of its messages.
+pub fn batch_id(constraints: &[SignedConstraints]) -> B256 {
+    batch_id_of(constraints.iter().map(|signed| signed.message.digest()))
+}
+
+/// The [batch_id] of the constraints with the given message digests, computed when they
+/// were added.
+pub fn batch_id_of(digests: impl IntoIterator<Item = [u8; 32]>) -> B256 {
+    let mut hasher = Keccak256::new();
+    for digest in digests {
+        hasher.update(digest);
+    }
+    hasher.finalize()
+}
+
+/// Write-ahead log of the constraints submissions to the relay.
+///
+/// A batch is recorded before it is submitted, then settled once the relay acknowledged it or
+/// its submission failed for good. The batches recorded but never settled were in flight when
+/// the sidecar stopped: they are resumed on startup while their slot hasn't started, so that
+/// constraints are neither dropped nor submitted twice after a crash near the deadline.
+#[derive(Debug, Clone)]
+pub struct SubmissionLog {
+    path: PathBuf,
+    file: Arc<Mutex<File>>,
+}
+
+impl SubmissionLog {
+    /// Open the log at `path` and return the submissions still in flight that can be resumed
+    /// at `current_slot`. The log is compacted to these submissions.
+    pub fn open(path: PathBuf, current_slot: u64) -> io::Result<(Self, Vec<PendingSubmission>)> {
+        let mut in_flight = BTreeMap::new();
+        for entry in read_entries::<WalEntry>(&path)? {
+            match entry {
+                WalEntry::Intent { slot, batch, constraints } => {
+                    in_flight.insert((slot, batch), constraints);
+                }
+                WalEntry::Acked { slot, batch } | WalEntry::Abandoned { slot, batch } => {
+                    in_flight.remove(&(slot, batch));
+                }
+            }
+        }
+
+        let mut pending = Vec::new();
+        for ((slot, batch), constraints) in in_flight {
+            // Constraints are only valid until their slot starts
+            if slot <= current_slot {
+                tracing::warn!(slot, %batch, "Dropping a submission interrupted after its slot");
+                ApiMetrics::increment_submission_recoveries_count("expired");
+                continue;
+            }
+            pending.push(PendingSubmission { slot, batch, constraints });
+        }
+
+        // Replace the log atomically, a crash while compacting keeps the previous one
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        let mut compacted = Vec::new();
+        for submission in &pending {
+            let entry = WalEntry::Intent {
+                slot: submission.slot,
+                batch: submission.batch,
+                constraints: submission.constraints.clone(),
+            };
+            serde_json::to_writer(&mut compacted, &entry)?;
+            compacted.push(b'\n');
+        }
+        let mut file = File::create(&tmp)?;
+        file.write_all(&compacted)?;
+        file.sync_all()?;
+        std::fs::rename(tmp, &path)?;
+
+        let file = OpenOptions::new().append(true).open(&path)?;
+        Ok((Self { path, file: Arc::new(Mutex::new(file)) }, pending))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Record that the batch `constraints` of `slot`, of id `batch`, is about to be submitted.
+    pub fn record_intent(
+        &self,
+        slot: u64,
+        batch: B256,
+        constraints: &[SignedConstraints],
+    ) -> io::Result<()> {
+        self.append(&WalEntry::Intent { slot, batch, constraints: constraints.to_vec() })
+    }
+
+    pub fn record_acked(&self, slot: u64, batch: B256) -> io::Result<()> {
+        self.append(&WalEntry::Acked { slot, batch })
+    }
+
+    pub fn record_abandoned(&self, slot: u64, batch: B256) -> io::Result<()> {
+        self.append(&WalEntry::Abandoned { slot, batch })
+    }
+
+    /// Append an entry and flush it to disk before returning.
+    fn append(&self, entry: &WalEntry) -> io::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let mut file = self.file.lock();
+        file.write_all(&line)?;
+        file.sync_data()
+    }
+}
+
+/// Read the entries of a JSON lines log, skipping the last one if it was only partially
+/// written.
+pub(super) fn read_entries<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    let content = match std::fs::read(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut entries = Vec::new();
+    let mut lines = content.split(|byte| *byte == b'\n').peekable();
+    while let Some(line) = lines.next() {
+        if line.is_empty() {
+            continue;
+        }
+        match serde_json::from_slice(line) {
+            Ok(entry) => entries.push(entry),
+            // The last line has no newline when the sidecar crashed while appending it
+            Err(_) if lines.peek().is_none() => {
+                tracing::warn!(path = %path.display(), "Ignoring a partially written entry");
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::PathBuf};
+
+    use ethereum_consensus::crypto::PublicKey as BlsPublicKey;
+
+    use super::{batch_id, SubmissionLog};
+    use crate::constraints::{ConstraintsMessage, SignedConstraints};
+
+    fn log_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("wal-test-{name}-{}.jsonl", std::process::id()))
+    }
+
+    fn constraints(slot: u64) -> Vec<SignedConstraints> {
+        let sk = blst::min_pk::SecretKey::key_gen(&[1; 32], &[]).unwrap();
+        let pubkey = BlsPublicKey::try_from(&sk.sk_to_pk().to_bytes()[..]).unwrap();
+        let message = ConstraintsMessage { pubkey, slot, ..Default::default() };
+        vec![SignedConstraints { message, ..Default::default() }]
+    }
+
+    #[test]
+    fn test_submissions_resumed_after_crashes() {
+        let path = log_path("crashes");
+        let _ = std::fs::remove_file(&path);
+
+        // Crash before any submission, nothing to resume
+        let (log, pending) = SubmissionLog::open(path.clone(), 10).unwrap();
+        assert!(pending.is_empty());
+
+        // Crash after recording the intent of slot 11, before or during its submission
+        let batch = batch_id(&constraints(11));
+        log.record_intent(11, batch, &constraints(11)).unwrap();
+        drop(log);
+        let (log, pending) = SubmissionLog::open(path.clone(), 10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].slot, pending[0].batch), (11, batch));
+        assert_eq!(pending[0].constraints, constraints(11));
+
+        // Crash after the relay acknowledged it, it isn't submitted twice
+        log.record_acked(11, batch).unwrap();
+        drop(log);
+        let (log, pending) = SubmissionLog::open(path.clone(), 10).unwrap();
+        assert!(pending.is_empty());
+
+        // Crash while appending an entry, the partial entry is ignored
+        let batch = batch_id(&constraints(12));
+        log.record_intent(12, batch, &constraints(12)).unwrap();
+        drop(log);
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"stage":"acked","slot":12,"ba"#).unwrap();
+        let (log, pending) = SubmissionLog::open(path.clone(), 10).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!((pending[0].slot, pending[0].batch), (12, batch));
+
+        // Restarting once the slot started drops it
+        drop(log);
+        let (_, pending) = SubmissionLog::open(path.clone(), 12).unwrap();
+        assert!(pending.is_empty());
+
+        let _ = std::fs::remove_file(path);
+    }
+}
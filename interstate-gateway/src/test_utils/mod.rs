@@ -1,10 +1,16 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, path::PathBuf};
 
 use alloy::{
-    network::TransactionBuilder,
-    primitives::{Address, U256},
+    eips::{
+        eip4844::{Blob, BlobTransactionSidecar, Bytes48},
+        eip7702::Authorization,
+    },
+    network::{TransactionBuilder, TransactionBuilder4844, TransactionBuilder7702},
+    primitives::{Address, B256, U256},
     rpc::types::TransactionRequest,
+    signers::{local::PrivateKeySigner, SignerSync},
 };
+use serde::Deserialize;
 
 use crate::config::Config;
 
@@ -68,3 +74,61 @@ pub(crate) fn default_test_transaction(sender: Address, nonce: Option<u64>) -> T
         .with_max_priority_fee_per_gas(1_000_000_000) // 1 gwei
         .with_max_fee_per_gas(20_000_000_000)
 }
+
+/// Pre-generated KZG commitment and proof of the all-zero blob, the point at infinity.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct BlobFixture {
+    pub commitment: Bytes48,
+    pub proof: Bytes48,
+    pub versioned_hash: B256,
+}
+
+impl BlobFixture {
+    /// Load the fixture from `mock_data/blobs/zero_blob.json`.
+    pub(crate) fn load() -> Self {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push("mock_data/blobs/zero_blob.json");
+
+        serde_json::from_slice(&std::fs::read(path).expect("Blob fixture exists"))
+            .expect("Valid blob fixture")
+    }
+
+    /// The sidecar of a transaction carrying `count` zero blobs.
+    pub(crate) fn sidecar(&self, count: usize) -> BlobTransactionSidecar {
+        BlobTransactionSidecar::new(
+            vec![Blob::ZERO; count],
+            vec![self.commitment; count],
+            vec![self.proof; count],
+        )
+    }
+}
+
+/// Create a default EIP-4844 transaction template carrying `blobs` zero blobs.
+pub(crate) fn default_test_blob_transaction(
+    sender: Address,
+    nonce: Option<u64>,
+    blobs: usize,
+) -> TransactionRequest {
+    default_test_transaction(sender, nonce)
+        .with_max_fee_per_blob_gas(1_000_000_000) // 1 gwei
+        .with_blob_sidecar(BlobFixture::load().sidecar(blobs))
+}
+
+/// Create a default EIP-7702 transaction template of `signer`, carrying its signed
+/// authorization to delegate its account to `delegate`.
+pub(crate) fn default_test_7702_transaction(
+    signer: &PrivateKeySigner,
+    nonce: Option<u64>,
+    delegate: Address,
+) -> TransactionRequest {
+    let nonce = nonce.unwrap_or(0);
+    // The sender authorizes its own account, after the nonce of the transaction is used
+    let authorization =
+        Authorization { chain_id: U256::from(1337), address: delegate, nonce: nonce + 1 };
+    let signature = signer.sign_hash_sync(&authorization.signature_hash()).expect("signed");
+
+    default_test_transaction(signer.address(), Some(nonce))
+        // Covers the cost of the authorization
+        .with_gas_limit(100_000)
+        .with_authorization_list(vec![authorization.into_signed(signature)])
+}
@@ -0,0 +1,377 @@
+use std::{collections::VecDeque, fmt, future::Future, sync::Arc, time::Duration};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::time::Instant;
+use utoipa::ToSchema;
+
+use crate::metrics::ApiMetrics;
+
+pub const DEFAULT_BREAKER_ERROR_RATE: f64 = 0.5;
+pub const DEFAULT_BREAKER_MIN_CALLS: u32 = 10;
+pub const DEFAULT_BREAKER_WINDOW_MILLIS: u64 = 30_000;
+pub const DEFAULT_BREAKER_OPEN_MILLIS: u64 = 5_000;
+
+/// When the circuit breakers of the dependencies open, and for how long.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakerPolicy {
+    /// Share of the calls failed within the window above which the breaker opens, 1 never
+    /// opens it.
+    pub error_rate: f64,
+    /// Calls needed within the window before the error rate is considered.
+    pub min_calls: u32,
+    pub window: Duration,
+    /// Time the breaker fails fast before letting a probe call through.
+    pub open_for: Duration,
+}
+
+impl Default for BreakerPolicy {
+    fn default() -> Self {
+        Self {
+            error_rate: DEFAULT_BREAKER_ERROR_RATE,
+            min_calls: DEFAULT_BREAKER_MIN_CALLS,
+            window: Duration::from_millis(DEFAULT_BREAKER_WINDOW_MILLIS),
+            open_for: Duration::from_millis(DEFAULT_BREAKER_OPEN_MILLIS),
+        }
+    }
+}
+
+/// Downstream dependency of the sidecar guarded by a circuit breaker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Dependency {
+    Relay,
+    Signer,
+    Beacon,
+    Execution,
+}
+
+impl Dependency {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Relay => "relay",
+            Self::Signer => "signer",
+            Self::Beacon => "beacon",
+            Self::Execution => "execution",
+        }
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Calls go through.
+    Closed,
+    /// A single probe call goes through, closing the breaker if it succeeds.
+    HalfOpen,
+    /// Calls fail fast.
+    Open,
+}
+
+impl BreakerState {
+    /// Value of the state in the `breaker_state` gauge.
+    const fn gauge(&self) -> u8 {
+        match self {
+            Self::Closed => 0,
+            Self::HalfOpen => 1,
+            Self::Open => 2,
+        }
+    }
+}
+
+/// A call failed fast by an open circuit breaker.
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize, ToSchema)]
+#[error("{dependency} unavailable: circuit breaker open, retry in {retry_in_ms}ms")]
+pub struct BreakerOpen {
+    pub dependency: Dependency,
+    pub retry_in_ms: u64,
+}
+
+#[derive(Debug, Error)]
+pub enum BreakerError<E> {
+    #[error(transparent)]
+    Open(BreakerOpen),
+    #[error(transparent)]
+    Failed(E),
+}
+
+/// Circuit breaker of a dependency, opening once too many of the calls to it fail so that
+/// the calls fail fast instead of each waiting on its timeouts and retries.
+///
+/// Clones share the same state. Once open, a single probe call is let through after
+/// [BreakerPolicy::open_for], closing the breaker if it succeeds or opening it again.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    dependency: Dependency,
+    policy: BreakerPolicy,
+    inner: Arc<Mutex<Inner>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: BreakerState,
+    /// Outcomes of the calls within the window, while closed.
+    outcomes: VecDeque<(Instant, bool)>,
+    opened_at: Instant,
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    pub fn new(dependency: Dependency, policy: BreakerPolicy) -> Self {
+        ApiMetrics::set_breaker_state(dependency.as_str(), BreakerState::Closed.gauge());
+        Self {
+            dependency,
+            policy,
+            inner: Arc::new(Mutex::new(Inner {
+                state: BreakerState::Closed,
+                outcomes: VecDeque::new(),
+                opened_at: Instant::now(),
+                probing: false,
+            })),
+        }
+    }
+
+    pub fn dependency(&self) -> Dependency {
+        self.dependency
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().state
+    }
+
+    /// The error a call would fail fast with right now, without admitting one.
+    pub fn failing_fast(&self) -> Option<BreakerOpen> {
+        let inner = self.inner.lock();
+        let elapsed = inner.opened_at.elapsed();
+        if inner.state != BreakerState::Open || elapsed >= self.policy.open_for {
+            return None;
+        }
+
+        ApiMetrics::increment_breaker_rejections_count(self.dependency.as_str());
+        let retry_in_ms = (self.policy.open_for - elapsed).as_millis() as u64;
+        Some(BreakerOpen { dependency: self.dependency, retry_in_ms })
+    }
+
+    /// Admit a call, failing fast while the breaker is open or its probe is in flight.
+    pub fn admit(&self) -> Result<(), BreakerOpen> {
+        let mut inner = self.inner.lock();
+        let retry_in = match inner.state {
+            BreakerState::Closed => return Ok(()),
+            BreakerState::Open => {
+                let elapsed = inner.opened_at.elapsed();
+                if elapsed >= self.policy.open_for {
+                    inner.probing = true;
+                    self.transition(&mut inner, BreakerState::HalfOpen);
+                    return Ok(());
+                }
+                self.policy.open_for - elapsed
+            }
+            BreakerState::HalfOpen if !inner.probing => {
+                inner.probing = true;
+                return Ok(());
+            }
+            BreakerState::HalfOpen => Duration::ZERO,
+        };
+
+        ApiMetrics::increment_breaker_rejections_count(self.dependency.as_str());
+        Err(BreakerOpen { dependency: self.dependency, retry_in_ms: retry_in.as_millis() as u64 })
+    }
+
+    /// Record the outcome of an admitted call.
+    pub fn record(&self, success: bool) {
+        let mut inner = self.inner.lock();
+        match inner.state {
+            BreakerState::Closed => {
+                let now = Instant::now();
+                inner.outcomes.push_back((now, success));
+                while inner
+                    .outcomes
+                    .front()
+                    .is_some_and(|(at, _)| now.duration_since(*at) > self.policy.window)
+                {
+                    inner.outcomes.pop_front();
+                }
+
+                let calls = inner.outcomes.len();
+                let failures = inner.outcomes.iter().filter(|(_, success)| !success).count();
+                if calls >= self.policy.min_calls as usize &&
+                    failures as f64 > self.policy.error_rate * calls as f64
+                {
+                    tracing::warn!(dependency = %self.dependency, failures, calls, "Circuit breaker opened");
+                    self.open(&mut inner);
+                }
+            }
+            BreakerState::HalfOpen => {
+                inner.probing = false;
+                if success {
+                    tracing::info!(dependency = %self.dependency, "Circuit breaker closed");
+                    self.transition(&mut inner, BreakerState::Closed);
+                } else {
+                    self.open(&mut inner);
+                }
+            }
+            // Calls admitted before the breaker opened
+            BreakerState::Open => {}
+        }
+    }
+
+    /// Run `operation` if the breaker admits it, recording its outcome. An operation dropped
+    /// before completing, e.g. on a timeout, is recorded as failed.
+    pub async fn call<T, E>(
+        &self,
+        operation: impl Future<Output = Result<T, E>>,
+    ) -> Result<T, BreakerError<E>> {
+        self.admit().map_err(BreakerError::Open)?;
+
+        let mut pending = PendingCall { breaker: self, done: false };
+        let result = operation.await;
+        pending.done = true;
+        self.record(result.is_ok());
+        result.map_err(BreakerError::Failed)
+    }
+
+    fn open(&self, inner: &mut Inner) {
+        inner.opened_at = Instant::now();
+        self.transition(inner, BreakerState::Open);
+    }
+
+    fn transition(&self, inner: &mut Inner, state: BreakerState) {
+        inner.state = state;
+        inner.outcomes.clear();
+        ApiMetrics::set_breaker_state(self.dependency.as_str(), state.gauge());
+    }
+}
+
+/// Records a call dropped before completing as failed.
+struct PendingCall<'a> {
+    breaker: &'a CircuitBreaker,
+    done: bool,
+}
+
+impl Drop for PendingCall<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.breaker.record(false);
+        }
+    }
+}
+
+/// Circuit breakers of the dependencies of the sidecar, shared by all their clients.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakers {
+    pub relay: CircuitBreaker,
+    pub signer: CircuitBreaker,
+    pub beacon: CircuitBreaker,
+    pub execution: CircuitBreaker,
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self::new(BreakerPolicy::default())
+    }
+}
+
+impl CircuitBreakers {
+    pub fn new(policy: BreakerPolicy) -> Self {
+        Self {
+            relay: CircuitBreaker::new(Dependency::Relay, policy),
+            signer: CircuitBreaker::new(Dependency::Signer, policy),
+            beacon: CircuitBreaker::new(Dependency::Beacon, policy),
+            execution: CircuitBreaker::new(Dependency::Execution, policy),
+        }
+    }
+
+    pub fn get(&self, dependency: Dependency) -> &CircuitBreaker {
+        match dependency {
+            Dependency::Relay => &self.relay,
+            Dependency::Signer => &self.signer,
+            Dependency::Beacon => &self.beacon,
+            Dependency::Execution => &self.execution,
+        }
+    }
+
+    /// The first of `dependencies` whose calls fail fast right now.
+    pub fn failing_fast(&self, dependencies: &[Dependency]) -> Option<BreakerOpen> {
+        dependencies.iter().find_map(|dependency| self.get(*dependency).failing_fast())
+    }
+
+    /// The state of the breaker of each dependency.
+    pub fn report(&self) -> BreakersReport {
+        let breakers = [&self.relay, &self.signer, &self.beacon, &self.execution]
+            .into_iter()
+            .map(|breaker| BreakerStatus { dependency: breaker.dependency(), state: breaker.state() })
+            .collect();
+        BreakersReport { breakers }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BreakerStatus {
+    pub dependency: Dependency,
+    pub state: BreakerState,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BreakersReport {
+    pub breakers: Vec<BreakerStatus>,
+}
+
+impl BreakersReport {
+    /// Whether a dependency fails fast, or waits on a probe to close its breaker.
+    pub fn is_degraded(&self) -> bool {
+        self.breakers.iter().any(|breaker| breaker.state != BreakerState::Closed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{BreakerError, BreakerPolicy, BreakerState, CircuitBreaker, Dependency};
+
+    #[tokio::test]
+    async fn test_circuit_breaker() {
+        let open_for = Duration::from_millis(20);
+        let policy =
+            BreakerPolicy { error_rate: 0.5, min_calls: 4, window: Duration::from_secs(10), open_for };
+        let breaker = CircuitBreaker::new(Dependency::Relay, policy);
+
+        // Under the min calls, or at the error rate, the breaker stays closed
+        for success in [false, false, true, true] {
+            breaker.record(success);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record(false);
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        assert_eq!(breaker.failing_fast().map(|open| open.dependency), Some(Dependency::Relay));
+        let result = breaker.call(async { Ok::<_, ()>(()) }).await;
+        assert!(matches!(result, Err(BreakerError::Open(open)) if open.retry_in_ms <= 20));
+
+        // A single probe goes through once the breaker has been open long enough
+        tokio::time::sleep(open_for).await;
+        breaker.admit().unwrap();
+        assert_eq!(breaker.state(), BreakerState::HalfOpen);
+        assert!(breaker.admit().is_err());
+        breaker.record(false);
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        // A probe dropped before completing doesn't keep the breaker half open
+        tokio::time::sleep(open_for).await;
+        let probe = breaker.call(std::future::pending::<Result<(), ()>>());
+        assert!(tokio::time::timeout(Duration::from_millis(1), probe).await.is_err());
+        assert_eq!(breaker.state(), BreakerState::Open);
+
+        tokio::time::sleep(open_for).await;
+        assert!(breaker.failing_fast().is_none());
+        assert!(breaker.call(async { Ok::<_, ()>(()) }).await.is_ok());
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+}
@@ -0,0 +1,91 @@
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+
+/// Header identifying the operator of the gateway to the relays and signers.
+pub const OPERATOR_ID_HEADER: &str = "x-interstate-operator-id";
+
+/// User agent of the gateway, with the commit it was built from when `GIT_COMMIT` was set at
+/// build time.
+pub fn default_user_agent() -> String {
+    let version = env!("CARGO_PKG_VERSION");
+    match option_env!("GIT_COMMIT") {
+        Some(commit) => format!("interstate-gateway/{version}+{}", &commit[..commit.len().min(8)]),
+        None => format!("interstate-gateway/{version}"),
+    }
+}
+
+/// Identification of the gateway sent with every outbound request, to the relays, the remote
+/// signers and the beacon node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboundHeaders {
+    pub user_agent: String,
+    /// Sent in [OPERATOR_ID_HEADER] when set.
+    pub operator_id: Option<String>,
+}
+
+impl Default for OutboundHeaders {
+    fn default() -> Self {
+        Self { user_agent: default_user_agent(), operator_id: None }
+    }
+}
+
+impl OutboundHeaders {
+    /// The headers, skipping the values that aren't valid header values.
+    pub fn header_map(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Ok(user_agent) = HeaderValue::from_str(&self.user_agent) {
+            headers.insert(USER_AGENT, user_agent);
+        }
+        if let Some(Ok(operator_id)) = self.operator_id.as_deref().map(HeaderValue::from_str) {
+            headers.insert(OPERATOR_ID_HEADER, operator_id);
+        }
+        headers
+    }
+
+    /// A client builder sending the headers with every request.
+    pub fn client_builder(&self) -> reqwest::ClientBuilder {
+        reqwest::Client::builder().default_headers(self.header_map())
+    }
+
+    pub fn client(&self) -> reqwest::Client {
+        self.client_builder().build().expect("Valid HTTP client")
+    }
+
+    /// A beacon node client sending the headers with every request.
+    pub fn beacon_client(&self, url: reqwest::Url) -> beacon_api_client::mainnet::Client {
+        // The beacon client is built on an older reqwest
+        let mut headers = reqwest_v011::header::HeaderMap::new();
+        for (name, value) in &self.header_map() {
+            if let (Ok(name), Ok(value)) = (
+                reqwest_v011::header::HeaderName::from_bytes(name.as_str().as_bytes()),
+                reqwest_v011::header::HeaderValue::from_bytes(value.as_bytes()),
+            ) {
+                headers.insert(name, value);
+            }
+        }
+        let client = reqwest_v011::Client::builder()
+            .default_headers(headers)
+            .build()
+            .expect("Valid HTTP client");
+        beacon_api_client::mainnet::Client::new_with_client(client, url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::USER_AGENT;
+
+    use super::{OutboundHeaders, OPERATOR_ID_HEADER};
+
+    #[test]
+    fn test_outbound_headers() {
+        let headers = OutboundHeaders::default().header_map();
+        assert!(headers[USER_AGENT].to_str().unwrap().starts_with("interstate-gateway/"));
+        assert!(!headers.contains_key(OPERATOR_ID_HEADER));
+
+        let outbound =
+            OutboundHeaders { user_agent: "custom/1.0".into(), operator_id: Some("op-1".into()) };
+        let headers = outbound.header_map();
+        assert_eq!(headers[USER_AGENT], "custom/1.0");
+        assert_eq!(headers[OPERATOR_ID_HEADER], "op-1");
+    }
+}
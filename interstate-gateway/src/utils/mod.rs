@@ -1,7 +1,14 @@
+pub mod breaker;
+pub mod http;
+pub mod retry;
 pub mod score_cache;
 pub mod transactions;
+pub mod url;
 
-use std::collections::HashSet;
+use std::{
+    collections::HashSet,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use alloy::hex;
 use blst::min_pk::SecretKey;
@@ -19,6 +26,11 @@ pub fn create_random_bls_secretkey() -> SecretKey {
     SecretKey::key_gen(&ikm, &[]).unwrap()
 }
 
+/// Milliseconds since the unix epoch on the system clock, zero if the clock is before it.
+pub fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
 pub async fn send_sidecar_info(pubkeys: Vec<String>, server_url: Url, sidecar_port: u16) -> eyre::Result<()> {
     let ip = reqwest::get("http://checkip.amazonaws.com")
         .await?
@@ -30,7 +42,7 @@ pub async fn send_sidecar_info(pubkeys: Vec<String>, server_url: Url, sidecar_po
     sidecar_url.push_str(":");
     sidecar_url.push_str(sidecar_port.to_string().as_str());
     
-    let client = reqwest::ClientBuilder::new().user_agent("interstate-pbs-module").build().unwrap();
+    let client = http::OutboundHeaders::default().client();
     let mut pubkey_array: Vec<PublicKey> = vec![];
     for pk in pubkeys {
         let w3s_pubkey = PublicKey::try_from(hex::decode(pk).unwrap_or_default().as_slice()).unwrap_or_default();
@@ -0,0 +1,133 @@
+use std::{future::Future, time::Duration};
+
+use rand::Rng;
+use thiserror::Error;
+use tokio::time::{error::Elapsed, timeout};
+
+use crate::metrics::ApiMetrics;
+
+pub const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 6;
+pub const DEFAULT_RETRY_INITIAL_BACKOFF_MILLIS: u64 = 200;
+pub const DEFAULT_RETRY_MAX_BACKOFF_MILLIS: u64 = 4_000;
+pub const DEFAULT_RETRY_ATTEMPT_TIMEOUT_MILLIS: u64 = 10_000;
+
+/// How calls to the beacon node, the execution client and the relay are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts made in total, including the first one.
+    pub max_attempts: u32,
+    /// Backoff after the first failed attempt, doubled after each one.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Timeout of each attempt.
+    pub attempt_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_RETRY_MAX_ATTEMPTS,
+            initial_backoff: Duration::from_millis(DEFAULT_RETRY_INITIAL_BACKOFF_MILLIS),
+            max_backoff: Duration::from_millis(DEFAULT_RETRY_MAX_BACKOFF_MILLIS),
+            attempt_timeout: Duration::from_millis(DEFAULT_RETRY_ATTEMPT_TIMEOUT_MILLIS),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Exponential backoff after the failed `attempt`, counted from 1, without jitter.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+
+    /// Backoff after the failed `attempt` with equal jitter, between half and all of it, so
+    /// that sidecars failing together don't retry in lockstep.
+    pub fn jittered_backoff(&self, attempt: u32) -> Duration {
+        let backoff = self.backoff(attempt);
+        let half = backoff / 2;
+        half + rand::thread_rng().gen_range(Duration::ZERO..=backoff - half)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum RetryError<E> {
+    #[error("timed out: {0}")]
+    Timeout(#[from] Elapsed),
+    #[error(transparent)]
+    Failed(E),
+}
+
+/// Run `operation` until it succeeds or the attempts of `policy` are exhausted, returning the
+/// error of the last attempt.
+///
+/// Retries and exhausted attempts are counted in the `retries_counter` metric, labeled with
+/// `name`.
+pub async fn retry_with_backoff<T, E, F, Fut>(
+    name: &'static str,
+    policy: &RetryPolicy,
+    mut operation: F,
+) -> Result<T, RetryError<E>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    E: std::fmt::Debug,
+{
+    let mut attempt = 1;
+    loop {
+        let err = match timeout(policy.attempt_timeout, operation()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(err)) => RetryError::Failed(err),
+            Err(elapsed) => RetryError::Timeout(elapsed),
+        };
+
+        if attempt >= policy.max_attempts {
+            ApiMetrics::increment_retries_count(name, "exhausted");
+            return Err(err);
+        }
+
+        let backoff = policy.jittered_backoff(attempt);
+        tracing::debug!(?err, name, attempt, ?backoff, "Retrying");
+        ApiMetrics::increment_retries_count(name, "retried");
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{retry_with_backoff, RetryError, RetryPolicy};
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+            attempt_timeout: Duration::from_millis(50),
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(1));
+        assert_eq!(policy.backoff(3), Duration::from_millis(2));
+
+        let mut calls = 0;
+        let result = retry_with_backoff("test", &policy, || {
+            calls += 1;
+            let calls = calls;
+            async move { if calls < 3 { Err(calls) } else { Ok(calls) } }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let result: Result<(), _> = retry_with_backoff("test", &policy, || async { Err(()) }).await;
+        assert!(matches!(result, Err(RetryError::Failed(()))));
+
+        let result: Result<(), RetryError<()>> = retry_with_backoff("test", &policy, || async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            Ok(())
+        })
+        .await;
+        assert!(matches!(result, Err(RetryError::Timeout(_))));
+    }
+}
@@ -1,11 +1,67 @@
 use std::{
     borrow::Borrow,
     collections::HashMap,
-    fmt::Debug,
+    fmt::{self, Debug},
     hash::{BuildHasher, Hash, RandomState},
     ops::{Deref, DerefMut},
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
+use clap::ValueEnum;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Policy used to pick the entries to evict once the cache is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EvictionPolicy {
+    /// Evict the entries with the lowest score first.
+    #[default]
+    Score,
+    /// Evict the least recently used entries first.
+    Lru,
+}
+
+impl FromStr for EvictionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "score" => Ok(Self::Score),
+            "lru" => Ok(Self::Lru),
+            other => Err(format!("unknown eviction policy `{other}`, expected score or lru")),
+        }
+    }
+}
+
+impl fmt::Display for EvictionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Score => "score",
+            Self::Lru => "lru",
+        })
+    }
+}
+
+/// Snapshot of the cache size and its hit/miss/eviction counters.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ScoreCacheStats {
+    pub len: usize,
+    pub max_len: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub expirations: u64,
+    pub policy: EvictionPolicy,
+    pub ttl_secs: Option<u64>,
+}
+
+/// Stats of a cache shared with readers that don't own it, e.g. the debug endpoint.
+pub type SharedScoreCacheStats = Arc<RwLock<ScoreCacheStats>>;
+
 pub struct ScoreCache<
     const GET_SCORE: isize,
     const INSERT_SCORE: isize,
@@ -16,6 +72,14 @@ pub struct ScoreCache<
 > {
     map: HashMap<K, (V, isize), S>,
     max_len: usize,
+    policy: EvictionPolicy,
+    ttl: Option<Duration>,
+    /// Last access time of each entry, used for LRU eviction and expiry.
+    touched: HashMap<K, Instant>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    expirations: u64,
 }
 
 impl<const GET_SCORE: isize, const INSERT_SCORE: isize, const UPDATE_SCORE: isize, K, V> Default
@@ -57,6 +121,8 @@ impl<
         f.debug_struct("ScoreCache")
             .field("map", &self.map)
             .field("max_len", &self.max_len)
+            .field("policy", &self.policy)
+            .field("ttl", &self.ttl)
             .finish()
     }
 }
@@ -66,10 +132,7 @@ impl<const GET_SCORE: isize, const INSERT_SCORE: isize, const UPDATE_SCORE: isiz
 {
     #[inline]
     pub fn new() -> Self {
-        Self {
-            map: HashMap::<K, (V, isize)>::new(),
-            max_len: usize::MAX,
-        }
+        Self::with_max_len(usize::MAX)
     }
 
     #[inline]
@@ -77,13 +140,60 @@ impl<const GET_SCORE: isize, const INSERT_SCORE: isize, const UPDATE_SCORE: isiz
         Self {
             map: HashMap::<K, (V, isize)>::new(),
             max_len,
+            policy: EvictionPolicy::default(),
+            ttl: None,
+            touched: HashMap::new(),
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+            expirations: 0,
+        }
+    }
+}
+
+impl<const GET_SCORE: isize, const INSERT_SCORE: isize, const UPDATE_SCORE: isize, K, V, S>
+    ScoreCache<GET_SCORE, INSERT_SCORE, UPDATE_SCORE, K, V, S>
+{
+    /// Set the policy used to evict entries once the cache is full.
+    #[inline]
+    pub fn with_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Set the time after which an entry that hasn't been accessed expires.
+    #[inline]
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Same as [Self::with_ttl], where a TTL of zero seconds disables expiry.
+    #[inline]
+    pub fn with_ttl_secs(self, ttl_secs: u64) -> Self {
+        if ttl_secs == 0 {
+            return self;
+        }
+        self.with_ttl(Duration::from_secs(ttl_secs))
+    }
+
+    pub fn stats(&self) -> ScoreCacheStats {
+        ScoreCacheStats {
+            len: self.map.len(),
+            max_len: self.max_len,
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            expirations: self.expirations,
+            policy: self.policy,
+            ttl_secs: self.ttl.map(|ttl| ttl.as_secs()),
         }
     }
 }
 impl<const GET_SCORE: isize, const INSERT_SCORE: isize, const UPDATE_SCORE: isize, K, V, S>
     ScoreCache<GET_SCORE, INSERT_SCORE, UPDATE_SCORE, K, V, S>
 where
-    K: Eq + Hash,
+    K: Eq + Hash + Clone,
     S: BuildHasher,
 {
     #[inline]
@@ -92,6 +202,10 @@ where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
+        if !self.touch(k) {
+            return None;
+        }
+
         self.map.get_mut(k).map(|(v, score)| {
             *score = score.saturating_add(GET_SCORE);
             &*v
@@ -104,6 +218,10 @@ where
         K: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
+        if !self.touch(k) {
+            return None;
+        }
+
         self.map.get_mut(k).map(|(v, score)| {
             *score = score.saturating_add(UPDATE_SCORE);
             v
@@ -113,19 +231,155 @@ where
     #[inline]
     pub fn insert(&mut self, k: K, v: V) -> Option<V> {
         self.clear_stales();
+        self.touched.insert(k.clone(), Instant::now());
         self.map.insert(k, (v, INSERT_SCORE)).map(|(v, _)| v)
     }
-}
 
-impl<const GET_SCORE: isize, const INSERT_SCORE: isize, const UPDATE_SCORE: isize, K, V, S>
-    ScoreCache<GET_SCORE, INSERT_SCORE, UPDATE_SCORE, K, V, S>
-{
+    /// Remove the expired entries and release the memory held by the removed ones.
+    /// Returns the number of removed entries.
+    pub fn gc(&mut self) -> usize {
+        let removed = self.remove_expired();
+        self.touched.retain(|k, _| self.map.contains_key(k));
+        self.map.shrink_to_fit();
+        self.touched.shrink_to_fit();
+        removed
+    }
+
+    /// Record an access to the entry, counting hits and misses. Expired entries are
+    /// removed and reported as misses.
+    fn touch<Q>(&mut self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.map.contains_key(k) {
+            self.misses += 1;
+            return false;
+        }
+
+        let now = Instant::now();
+        if self.is_expired(self.touched.get(k), now) {
+            self.map.remove(k);
+            self.touched.remove(k);
+            self.expirations += 1;
+            self.misses += 1;
+            return false;
+        }
+
+        if let Some(touched) = self.touched.get_mut(k) {
+            *touched = now;
+        }
+        self.hits += 1;
+        true
+    }
+
+    #[inline]
+    fn is_expired(&self, touched: Option<&Instant>, now: Instant) -> bool {
+        match (self.ttl, touched) {
+            (Some(ttl), Some(touched)) => now.duration_since(*touched) > ttl,
+            _ => false,
+        }
+    }
+
+    fn remove_expired(&mut self) -> usize {
+        if self.ttl.is_none() {
+            return 0;
+        }
+
+        let now = Instant::now();
+        let expired = self
+            .map
+            .keys()
+            .filter(|k| self.is_expired(self.touched.get(*k), now))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for k in &expired {
+            self.map.remove(k);
+            self.touched.remove(k);
+        }
+        self.expirations += expired.len() as u64;
+        expired.len()
+    }
+
     #[inline]
     fn clear_stales(&mut self) {
-        let mut i = 0;
-        while self.len() >= self.max_len {
-            self.map.retain(|_, (_, score)| *score > i);
-            i += 1;
+        if self.len() < self.max_len {
+            return;
         }
+
+        self.remove_expired();
+
+        let len = self.len();
+        match self.policy {
+            EvictionPolicy::Score => {
+                let mut i = 0;
+                while self.len() >= self.max_len {
+                    self.map.retain(|_, (_, score)| *score > i);
+                    i += 1;
+                }
+                self.touched.retain(|k, _| self.map.contains_key(k));
+            }
+            EvictionPolicy::Lru => {
+                while self.len() >= self.max_len {
+                    // Entries without an access time were inserted through `DerefMut`
+                    // and are evicted first.
+                    let Some(oldest) = self
+                        .map
+                        .keys()
+                        .min_by_key(|k| self.touched.get(*k).copied())
+                        .cloned()
+                    else {
+                        break;
+                    };
+                    self.map.remove(&oldest);
+                    self.touched.remove(&oldest);
+                }
+            }
+        }
+        self.evictions += (len - self.len()) as u64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{EvictionPolicy, ScoreCache};
+
+    type Cache = ScoreCache<4, 4, { -1 }, u64, u64>;
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = Cache::with_max_len(2).with_policy(EvictionPolicy::Lru);
+        cache.insert(1, 1);
+        cache.insert(2, 2);
+        assert!(cache.get(&1).is_some());
+
+        // Entry 2 is the least recently used one
+        cache.insert(3, 3);
+        assert!(cache.contains_key(&1));
+        assert!(!cache.contains_key(&2));
+
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.hits, 1);
+        assert!(cache.get(&2).is_none());
+        assert_eq!(cache.stats().misses, 1);
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let mut cache = Cache::new().with_ttl(Duration::ZERO);
+        cache.insert(1, 1);
+        std::thread::sleep(Duration::from_millis(1));
+
+        assert!(cache.get(&1).is_none());
+        assert_eq!(cache.stats().expirations, 1);
+
+        cache.insert(2, 2);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(cache.gc(), 1);
+        assert!(cache.is_empty());
     }
 }
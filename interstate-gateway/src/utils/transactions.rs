@@ -6,6 +6,7 @@ use alloy_v092::{
     eips::eip2718::{Decodable2718, Encodable2718},
     hex,
     primitives::{Address, U256},
+    rpc::types::TransactionRequest,
 };
 
 use reth_primitives::PooledTransactionsElement;
@@ -216,6 +217,18 @@ pub fn calculate_max_basefee(current: u128, block_diff: u64) -> Option<u128> {
     Some(max_basefee)
 }
 
+/// Call of `transaction` from `sender` used to estimate its gas. Nonce, gas and fees are left
+/// out, so that the estimate doesn't depend on the commitments preceding it.
+pub fn estimation_request(transaction: &PooledTransactionsElement, sender: Address) -> TransactionRequest {
+    TransactionRequest {
+        from: Some(sender),
+        to: Some(transaction.tx_kind()),
+        value: Some(transaction.value()),
+        input: transaction.input().clone().into(),
+        ..Default::default()
+    }
+}
+
 pub fn max_transaction_cost(transaction: &PooledTransactionsElement) -> U256 {
     let gas_limit = transaction.gas_limit() as u128;
 
@@ -0,0 +1,97 @@
+use reqwest::Url;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum UrlError {
+    #[error("unsupported scheme `{0}` in {1}, expected http or https")]
+    UnsupportedScheme(String, Url),
+    #[error("missing host in {0}")]
+    MissingHost(Url),
+    #[error("unexpected query or fragment in base url {0}")]
+    UnexpectedQuery(Url),
+    #[error("failed to join `{path}` to {base}: {source}")]
+    Join {
+        base: Url,
+        path: String,
+        source: url::ParseError,
+    },
+}
+
+/// Check that `url` can be used as the base url of an API and normalize it so that its
+/// path ends with a `/`, which keeps the path when endpoints are joined to it.
+pub fn normalize_base_url(url: &Url) -> Result<Url, UrlError> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(UrlError::UnsupportedScheme(url.scheme().to_string(), url.clone()));
+    }
+
+    if !matches!(url.host_str(), Some(host) if !host.is_empty()) {
+        return Err(UrlError::MissingHost(url.clone()));
+    }
+
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(UrlError::UnexpectedQuery(url.clone()));
+    }
+
+    let mut url = url.clone();
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(url)
+}
+
+/// Join an endpoint path, optionally with a query string, to a base url.
+///
+/// Unlike [Url::join], the path of the base url is kept: joining `/eth/v1/builder/status`
+/// to `https://relay.xyz/prefix` gives `https://relay.xyz/prefix/eth/v1/builder/status`.
+pub fn join_path(base: &Url, path: &str) -> Result<Url, UrlError> {
+    let base = normalize_base_url(base)?;
+    base.join(path.trim_start_matches('/')).map_err(|source| UrlError::Join {
+        base: base.clone(),
+        path: path.to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::Url;
+
+    use super::{join_path, normalize_base_url, UrlError};
+
+    #[test]
+    fn test_join_path_trailing_slashes() {
+        for base in ["http://relay.xyz", "http://relay.xyz/"] {
+            let base = Url::parse(base).unwrap();
+            for path in ["eth/v1/builder/status", "/eth/v1/builder/status"] {
+                assert_eq!(
+                    join_path(&base, path).unwrap().as_str(),
+                    "http://relay.xyz/eth/v1/builder/status"
+                );
+            }
+        }
+
+        for base in ["https://relay.xyz/prefix", "https://relay.xyz/prefix/"] {
+            let base = Url::parse(base).unwrap();
+            assert_eq!(
+                join_path(&base, "/relay/v1/builder/delegations?slot=1").unwrap().as_str(),
+                "https://relay.xyz/prefix/relay/v1/builder/delegations?slot=1"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_base_urls() {
+        let url = Url::parse("ws://relay.xyz").unwrap();
+        assert!(matches!(normalize_base_url(&url), Err(UrlError::UnsupportedScheme(..))));
+
+        let url = Url::parse("mailto:relay@relay.xyz").unwrap();
+        assert!(matches!(join_path(&url, "/status"), Err(UrlError::UnsupportedScheme(..))));
+
+        let url = Url::parse("http://relay.xyz/?token=secret").unwrap();
+        assert!(matches!(normalize_base_url(&url), Err(UrlError::UnexpectedQuery(_))));
+
+        let url = Url::parse("http://127.0.0.1:3040").unwrap();
+        assert_eq!(normalize_base_url(&url).unwrap().as_str(), "http://127.0.0.1:3040/");
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    consensus::{Transaction, TxEnvelope},
+    eips::eip2718::Decodable2718,
+    primitives::{keccak256, Address, Bytes, B256},
+    rpc::types::beacon::BlsPublicKey,
+};
+use parking_lot::RwLock;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tracing::{error, warn};
+
+use crate::{
+    metrics,
+    types::{ConstraintsMessage, VerifiedConstraints},
+};
+
+/// Evidence of two distinct constraint sets signed by the same key for one slot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictEvidence {
+    pub slot: u64,
+    pub pubkey: BlsPublicKey,
+    /// The constraints first received for the slot.
+    pub first: Vec<VerifiedConstraints>,
+    /// The conflicting constraints received afterwards.
+    pub second: Vec<VerifiedConstraints>,
+    pub detected_at_ms: u64,
+}
+
+/// Maximum number of alerts kept, the oldest ones being evicted first.
+const MAX_ALERTS: usize = 1024;
+
+/// Constraints signed by a key for a slot, merged across its submissions.
+#[derive(Debug, Default)]
+struct Submission {
+    ids: HashSet<B256>,
+    /// The top-of-block bundle, of which a key commits to at most one per slot.
+    top: Option<B256>,
+    /// The transaction committed for each sender nonce.
+    nonces: HashMap<(Address, u64), B256>,
+    constraints: Vec<VerifiedConstraints>,
+}
+
+impl Submission {
+    /// Merge `batch` into the constraints of the key, unless it contradicts them by
+    /// committing to another top-of-block bundle or to another transaction for a nonce.
+    /// Nothing is merged on a contradiction.
+    fn merge(&mut self, batch: &[VerifiedConstraints]) -> bool {
+        let mut top = self.top;
+        let mut nonces = self.nonces.clone();
+        for signed in batch {
+            if signed.message.top {
+                let id = message_id(&signed.message);
+                if top.is_some_and(|top| top != id) {
+                    return false;
+                }
+                top = Some(id);
+            }
+
+            for (sender, nonce, hash) in signed.message.transactions.iter().filter_map(sender_nonce)
+            {
+                if *nonces.entry((sender, nonce)).or_insert(hash) != hash {
+                    return false;
+                }
+            }
+        }
+
+        self.top = top;
+        self.nonces = nonces;
+        for signed in batch {
+            if self.ids.insert(message_id(&signed.message)) {
+                self.constraints.push(signed.clone());
+            }
+        }
+        true
+    }
+}
+
+/// Detects sidecars committing contradicting constraints for the same proposer slot, which
+/// is a potential equivocation of the validator or its delegatee.
+///
+/// The constraints of a key add up across its submissions for a slot, so re-submissions,
+/// follow-ups and per-transaction batches don't raise alerts: committing to a second
+/// top-of-block bundle or to a second transaction for a sender nonce does. Only constraints
+/// with a verified signature must be checked, lest anyone forge evidence against a key.
+///
+/// The evidence is appended to `evidence_path` as JSON lines and pushed to `webhook_url`, if
+/// set. The latest [MAX_ALERTS] alerts are kept in memory.
+#[derive(Debug, Clone)]
+pub struct ConflictDetector {
+    submissions: Arc<RwLock<HashMap<(u64, BlsPublicKey), Submission>>>,
+    alerts: Arc<RwLock<VecDeque<ConflictEvidence>>>,
+    evidence_path: Option<PathBuf>,
+    webhook_url: Option<Url>,
+    client: reqwest::Client,
+}
+
+impl ConflictDetector {
+    /// Create a detector, loading the evidence persisted by previous runs.
+    pub fn new(evidence_path: Option<PathBuf>, webhook_url: Option<Url>) -> Self {
+        let alerts = evidence_path.as_deref().map(load_evidence).unwrap_or_default();
+
+        Self {
+            submissions: Default::default(),
+            alerts: Arc::new(RwLock::new(alerts)),
+            evidence_path,
+            webhook_url,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Check a submission against the constraints previously received for the same slots and
+    /// keys, raising an alert for each conflict.
+    pub fn check(&self, constraints: &[VerifiedConstraints]) -> Vec<ConflictEvidence> {
+        let mut batches: HashMap<(u64, BlsPublicKey), Vec<VerifiedConstraints>> = HashMap::new();
+        for signed in constraints {
+            let key = (signed.message.slot, signed.message.pubkey);
+            batches.entry(key).or_default().push(signed.clone());
+        }
+
+        let mut conflicts = Vec::new();
+        let mut submissions = self.submissions.write();
+        for ((slot, pubkey), batch) in batches {
+            let submission = submissions.entry((slot, pubkey)).or_default();
+            if !submission.merge(&batch) {
+                conflicts.push(ConflictEvidence {
+                    slot,
+                    pubkey,
+                    first: submission.constraints.clone(),
+                    second: batch,
+                    detected_at_ms: now_ms(),
+                });
+            }
+        }
+        drop(submissions);
+
+        for evidence in &conflicts {
+            self.raise(evidence.clone());
+        }
+
+        conflicts
+    }
+
+    /// All the conflicts detected, oldest first.
+    pub fn alerts(&self) -> Vec<ConflictEvidence> {
+        self.alerts.read().iter().cloned().collect()
+    }
+
+    /// Forget the submissions for slots before `slot`. Alerts are kept.
+    pub fn remove_before(&self, slot: u64) {
+        self.submissions.write().retain(|(s, _), _| *s >= slot);
+    }
+
+    fn raise(&self, evidence: ConflictEvidence) {
+        warn!(
+            slot = evidence.slot,
+            pubkey = %evidence.pubkey,
+            "Conflicting constraints signed by the same key for one slot"
+        );
+        metrics::CONSTRAINTS_CONFLICTS_COUNT.inc();
+
+        if let Some(path) = &self.evidence_path {
+            if let Err(err) = append_evidence(path, &evidence) {
+                error!(?err, path = %path.display(), "Failed to persist conflict evidence");
+            }
+        }
+
+        if let Some(url) = self.webhook_url.clone() {
+            let request = self.client.post(url).json(&evidence);
+            tokio::spawn(async move {
+                match request.send().await {
+                    Ok(response) if !response.status().is_success() => {
+                        error!(status = %response.status(), "Conflict webhook rejected the alert")
+                    }
+                    Ok(_) => {}
+                    Err(err) => error!(?err, "Failed to push the conflict alert"),
+                }
+            });
+        }
+
+        let mut alerts = self.alerts.write();
+        if alerts.len() == MAX_ALERTS {
+            alerts.pop_front();
+        }
+        alerts.push_back(evidence);
+    }
+}
+
+/// The sender, nonce and hash of a transaction, if it can be decoded.
+fn sender_nonce(raw: &Bytes) -> Option<(Address, u64, B256)> {
+    let tx = TxEnvelope::decode_2718(&mut raw.as_ref()).ok()?;
+    let sender = tx.recover_signer().ok()?;
+    Some((sender, tx.nonce(), *tx.tx_hash()))
+}
+
+/// Identifies the content of a constraints message, regardless of its signature.
+fn message_id(message: &ConstraintsMessage) -> B256 {
+    let mut data = vec![message.top as u8];
+    for tx in &message.transactions {
+        data.extend_from_slice(keccak256(tx).as_slice());
+    }
+    keccak256(data)
+}
+
+fn append_evidence(path: &Path, evidence: &ConflictEvidence) -> std::io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(evidence)?)
+}
+
+/// Load the latest [MAX_ALERTS] alerts persisted at `path`.
+fn load_evidence(path: &Path) -> VecDeque<ConflictEvidence> {
+    let mut alerts = VecDeque::new();
+    let Ok(file) = std::fs::File::open(path) else {
+        return alerts;
+    };
+
+    for evidence in BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str(&line).ok())
+    {
+        if alerts.len() == MAX_ALERTS {
+            alerts.pop_front();
+        }
+        alerts.push_back(evidence);
+    }
+    alerts
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        consensus::{SignableTransaction, TxEip1559, TxEnvelope},
+        eips::eip2718::Encodable2718,
+        primitives::Bytes,
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+
+    use super::ConflictDetector;
+    use crate::types::{ConstraintsMessage, VerifiedConstraints};
+
+    fn transaction(signer: &PrivateKeySigner, nonce: u64, tip: u128) -> Bytes {
+        let tx = TxEip1559 {
+            chain_id: 1,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: tip,
+            ..Default::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        Bytes::from(TxEnvelope::from(tx.into_signed(signature)).encoded_2718())
+    }
+
+    fn constraints(slot: u64, top: bool, transactions: Vec<Bytes>) -> VerifiedConstraints {
+        VerifiedConstraints {
+            message: ConstraintsMessage { pubkey: Default::default(), slot, top, transactions },
+            signature: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_conflicting_constraints_raise_alerts() {
+        let path = std::env::temp_dir().join(format!("conflicts-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let detector = ConflictDetector::new(Some(path.clone()), None);
+        let sender = PrivateKeySigner::random();
+        let (tx0, tx1) = (transaction(&sender, 0, 1), transaction(&sender, 1, 1));
+
+        let first = [constraints(10, false, vec![tx0.clone()]), constraints(11, true, vec![tx1])];
+        assert!(detector.check(&first).is_empty());
+        // Re-submissions and follow-ups of the same key add up
+        assert!(detector.check(&[constraints(10, false, vec![tx0])]).is_empty());
+        let follow_up = constraints(10, false, vec![transaction(&sender, 1, 1)]);
+        assert!(detector.check(&[follow_up]).is_empty());
+
+        // Another transaction for a committed nonce, or another top-of-block bundle, conflict
+        let conflicts = detector.check(&[constraints(10, false, vec![transaction(&sender, 0, 2)])]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!((conflicts[0].slot, conflicts[0].first.len()), (10, 2));
+        let other_top = constraints(11, true, vec![transaction(&PrivateKeySigner::random(), 0, 1)]);
+        assert_eq!(detector.check(&[other_top]).len(), 1);
+        assert_eq!(detector.alerts().len(), 2);
+
+        // The evidence survives restarts
+        assert_eq!(ConflictDetector::new(Some(path.clone()), None).alerts().len(), 2);
+
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,10 +1,18 @@
 use crate::metrics;
-use alloy::eips::eip2718::Eip2718Error;
+use alloy::{
+    consensus::{Transaction, TxEnvelope},
+    eips::eip2718::{Decodable2718, Eip2718Error},
+    rpc::types::beacon::BlsPublicKey,
+};
 use parking_lot::RwLock;
-use std::{collections::HashMap, sync::Arc};
-use tracing::{error, info};
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info, warn};
 
-use super::types::{ConstraintsMessage, ConstraintsWithProofData};
+use super::types::{ConstraintsMessage, ConstraintsWithProofData, TopOfBlockPolicy};
 
 pub(crate) const PER_SLOT_MAX_CONSTRAINTS: usize = 128;
 
@@ -12,12 +20,23 @@ pub(crate) const PER_SLOT_MAX_CONSTRAINTS: usize = 128;
 #[derive(Clone, Default, Debug)]
 pub struct ConstraintStore {
     cache: Arc<RwLock<HashMap<u64, Vec<ConstraintsWithProofData>>>>,
+    top_of_block: TopOfBlockArbiter,
+    rounds: Arc<RwLock<HashMap<u64, TopOfBlockRound>>>,
+}
+
+/// Arbitration of the top-of-block bundles of a slot, which closes [TopOfBlockArbiter::window]
+/// after the first one is received. The bundle kept is then forwarded to the relays, which
+/// can't be told to drop it, so no other bundle can replace it afterwards.
+#[derive(Clone, Copy, Debug)]
+struct TopOfBlockRound {
+    closes_at: Instant,
+    settled: bool,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum StoreConflict {
-    #[error("Only one ToB constraint is allowed per slot.")]
-    TopOfBlock,
+    #[error("Only one ToB constraint is allowed per slot: {0}")]
+    TopOfBlock(String),
     #[error("Duplicate transactions found within the same slot.")]
     DuplicateTransaction,
 }
@@ -32,13 +51,93 @@ pub enum StoreError {
     LimitExceeded(u64),
 }
 
+/// Picks the top-of-block bundle kept for a slot when sidecars disagree on it.
+///
+/// The sidecar keys are only trusted once the signatures of their constraints are verified.
+#[derive(Clone, Default, Debug)]
+pub struct TopOfBlockArbiter {
+    policy: TopOfBlockPolicy,
+    /// Sidecar keys from the highest to the lowest priority. Unlisted sidecars come last.
+    priority: Vec<BlsPublicKey>,
+    /// Time the bundles of a slot are arbitrated for before the one kept is forwarded.
+    window: Duration,
+}
+
+impl TopOfBlockArbiter {
+    /// The first bundle received always wins with the [TopOfBlockPolicy::EarliestSigned]
+    /// policy, so it is forwarded without waiting for the `window` to close.
+    pub fn new(policy: TopOfBlockPolicy, priority: Vec<BlsPublicKey>, window: Duration) -> Self {
+        let window = match policy {
+            TopOfBlockPolicy::EarliestSigned => Duration::ZERO,
+            TopOfBlockPolicy::HighestTip | TopOfBlockPolicy::Priority => window,
+        };
+        Self { policy, priority, window }
+    }
+
+    /// Returns why `challenger` loses against the `incumbent` bundle of its slot, or None
+    /// if it replaces it. Ties are won by the incumbent.
+    ///
+    /// Constraints carry no signing time, so the earliest signed bundle is the first one
+    /// received.
+    pub fn arbitrate(
+        &self,
+        incumbent: &ConstraintsMessage,
+        challenger: &ConstraintsMessage,
+    ) -> Option<String> {
+        match self.policy {
+            TopOfBlockPolicy::EarliestSigned => {
+                Some(format!("a bundle from {} was received earlier", incumbent.pubkey))
+            }
+            TopOfBlockPolicy::HighestTip => {
+                let (kept, offered) = (max_tip(incumbent), max_tip(challenger));
+                (offered <= kept).then(|| {
+                    format!(
+                        "a bundle from {} tips {kept} wei, this one {offered} wei",
+                        incumbent.pubkey
+                    )
+                })
+            }
+            TopOfBlockPolicy::Priority => (self.rank(&challenger.pubkey)
+                >= self.rank(&incumbent.pubkey))
+            .then(|| format!("a bundle from {} has a higher priority", incumbent.pubkey)),
+        }
+    }
+
+    fn rank(&self, pubkey: &BlsPublicKey) -> usize {
+        self.priority.iter().position(|p| p == pubkey).unwrap_or(self.priority.len())
+    }
+}
+
+/// Maximum tip paid by the transactions of a bundle, taking their whole gas limit as used.
+/// Undecodable transactions tip nothing.
+fn max_tip(constraints: &ConstraintsMessage) -> u128 {
+    constraints
+        .transactions
+        .iter()
+        .filter_map(|raw| TxEnvelope::decode_2718(&mut raw.as_ref()).ok())
+        .map(|tx| tx.priority_fee_or_price().saturating_mul(tx.gas_limit() as u128))
+        .fold(0, u128::saturating_add)
+}
+
+impl TopOfBlockRound {
+    /// Closed once its window elapsed, or once settled.
+    fn is_closed(&self) -> bool {
+        self.settled || Instant::now() >= self.closes_at
+    }
+}
+
 impl ConstraintStore {
-    pub fn new() -> Self {
-        Self { cache: Default::default() }
+    pub fn new(top_of_block: TopOfBlockArbiter) -> Self {
+        Self { cache: Default::default(), top_of_block, rounds: Default::default() }
     }
 
     /// Adds constraints for the specified slot to the cache. This function will first check for conflicts
     /// and return an error if any are found. It also decodes transactions for future use.
+    ///
+    /// A top-of-block bundle conflicting with the one stored for the slot is arbitrated by the
+    /// configured [TopOfBlockPolicy], replacing it or being rejected with the reason. Bundles
+    /// can't replace the one of the slot once its round is closed, see
+    /// [ConstraintStore::settle_top_of_block].
     pub fn add_constraints(
         &mut self,
         slot: u64,
@@ -52,13 +151,48 @@ impl ConstraintStore {
 
         let mut cache = self.cache.write();
         if let Some(cs) = cache.get_mut(&slot) {
+            let incumbent = cs.iter().position(|c| c.message.top);
+            if let Some(index) = incumbent.filter(|_| message_with_data.message.top) {
+                let incumbent = &cs[index].message;
+                if let Some(reason) =
+                    self.top_of_block.arbitrate(incumbent, &message_with_data.message)
+                {
+                    metrics::TOP_OF_BLOCK_ARBITRATIONS_COUNT.with_label_values(&["rejected"]).inc();
+                    return Err(StoreConflict::TopOfBlock(reason).into());
+                }
+                if self.rounds.read().get(&slot).is_some_and(|round| round.is_closed()) {
+                    metrics::TOP_OF_BLOCK_ARBITRATIONS_COUNT.with_label_values(&["rejected"]).inc();
+                    return Err(StoreConflict::TopOfBlock(format!(
+                        "the arbitration of the slot closed with a bundle from {}",
+                        incumbent.pubkey
+                    ))
+                    .into());
+                }
+
+                warn!(
+                    slot,
+                    replaced = %incumbent.pubkey,
+                    by = %message_with_data.message.pubkey,
+                    "Replaced the top-of-block constraints of the slot"
+                );
+                metrics::TOP_OF_BLOCK_ARBITRATIONS_COUNT.with_label_values(&["replaced"]).inc();
+                cs[index] = message_with_data;
+                return Ok(());
+            }
+
             if cs.len() >= PER_SLOT_MAX_CONSTRAINTS {
                 error!("Max constraints per slot reached for slot {}", slot);
                 return Err(StoreError::LimitExceeded(slot));
             }
 
+            if message_with_data.message.top {
+                self.open_round(slot);
+            }
             cs.push(message_with_data);
         } else {
+            if message_with_data.message.top {
+                self.open_round(slot);
+            }
             cache.insert(slot, vec![message_with_data]);
         }
 
@@ -71,8 +205,9 @@ impl ConstraintStore {
     /// Returns a [StoreConflict] if there is a conflict, or None if there are no issues.
     ///
     /// # Possible conflicts
-    /// - Multiple ToB constraints per slot
     /// - Duplicates of the same transaction per slot
+    ///
+    /// Multiple ToB constraints per slot are arbitrated when adding them.
     pub fn check_conflicts(
         &self,
         slot: &u64,
@@ -80,11 +215,6 @@ impl ConstraintStore {
     ) -> Option<StoreConflict> {
         if let Some(saved_constraints) = self.cache.read().get(slot) {
             for saved_constraint in saved_constraints {
-                //  Only one ToB constraint per slot is allowed
-                // if constraints.top && saved_constraint.message.top {
-                //     return Some(StoreConflict::TopOfBlock);
-                // }
-
                 // Check for duplicate transactions
                 for tx in &constraints.transactions {
                     if saved_constraint.message.transactions.iter().any(|existing| tx == existing) {
@@ -97,9 +227,55 @@ impl ConstraintStore {
         None
     }
 
+    fn open_round(&self, slot: u64) {
+        let closes_at = Instant::now() + self.top_of_block.window;
+        self.rounds.write().entry(slot).or_insert(TopOfBlockRound { closes_at, settled: false });
+    }
+
+    /// When the top-of-block round of the slot closes, if one is open.
+    pub fn top_of_block_closes_at(&self, slot: u64) -> Option<Instant> {
+        self.rounds.read().get(&slot).map(|round| round.closes_at)
+    }
+
+    /// Settle the top-of-block round of the slot once closed: returns whether `constraints`
+    /// won it, and should be forwarded to the relays, or why it was replaced.
+    pub fn settle_top_of_block(
+        &self,
+        slot: u64,
+        constraints: &ConstraintsMessage,
+    ) -> Result<(), StoreConflict> {
+        let cache = self.cache.read();
+        let kept = cache.get(&slot).and_then(|cs| cs.iter().find(|c| c.message.top));
+        match kept {
+            Some(kept) if kept.message == *constraints => {
+                if let Some(round) = self.rounds.write().get_mut(&slot) {
+                    round.settled = true;
+                }
+                Ok(())
+            }
+            Some(kept) => Err(StoreConflict::TopOfBlock(format!(
+                "replaced by a bundle from {}",
+                kept.message.pubkey
+            ))),
+            None => Err(StoreConflict::TopOfBlock("no longer stored".to_string())),
+        }
+    }
+
+    /// Removes constraints which won't be forwarded to the relays, leaving the others of the
+    /// slot.
+    pub fn remove_message(&self, slot: u64, constraints: &ConstraintsMessage) {
+        let mut cache = self.cache.write();
+        let Some(cs) = cache.get_mut(&slot) else { return };
+        if let Some(index) = cs.iter().position(|c| c.message == *constraints) {
+            cs.remove(index);
+            metrics::CACHE_SIZE_CONSTRAINTS.dec();
+        }
+    }
+
     /// Removes all constraints before the given slot.
     pub fn remove_before_constraints(&self, slot: u64) {
         self.cache.write().retain(|k, _| *k >= slot);
+        self.rounds.write().retain(|k, _| *k >= slot);
         metrics::CACHE_SIZE_CONSTRAINTS.set(self.total_constraints() as i64);
     }
 
@@ -110,7 +286,111 @@ impl ConstraintStore {
         })
     }
 
+    /// Returns the constraints for the slots in `[from_slot, to_slot]`, ordered by slot.
+    pub fn get_range(&self, from_slot: u64, to_slot: u64) -> Vec<ConstraintsMessage> {
+        let cache = self.cache.read();
+        let mut slots = cache.keys().filter(|s| (from_slot..=to_slot).contains(*s)).collect::<Vec<_>>();
+        slots.sort_unstable();
+
+        slots
+            .into_iter()
+            .flat_map(|slot| cache[slot].iter().map(|c| c.message.clone()))
+            .collect()
+    }
+
     fn total_constraints(&self) -> usize {
         self.cache.read().values().map(|v| v.len()).sum()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        consensus::{SignableTransaction, TxEip1559, TxEnvelope},
+        eips::eip2718::Encodable2718,
+        primitives::{Bytes, PrimitiveSignature, U256},
+        rpc::types::beacon::BlsPublicKey,
+    };
+
+    use std::time::{Duration, Instant};
+
+    use super::{ConstraintStore, StoreConflict, StoreError, TopOfBlockArbiter};
+    use crate::types::{ConstraintsMessage, TopOfBlockPolicy};
+
+    fn top_of_block(sidecar: u8, tip: u128) -> ConstraintsMessage {
+        let tx = TxEip1559 {
+            chain_id: 1,
+            nonce: sidecar as u64,
+            gas_limit: 21_000,
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: tip,
+            ..Default::default()
+        };
+        let signature = PrimitiveSignature::new(U256::from(1), U256::from(1), false);
+        let tx = TxEnvelope::from(tx.into_signed(signature));
+
+        ConstraintsMessage {
+            pubkey: BlsPublicKey::repeat_byte(sidecar),
+            slot: 10,
+            top: true,
+            transactions: vec![Bytes::from(tx.encoded_2718())],
+        }
+    }
+
+    fn stored_sidecar(store: &ConstraintStore) -> BlsPublicKey {
+        let constraints = store.get_range(10, 10);
+        assert_eq!(constraints.len(), 1);
+        constraints[0].pubkey
+    }
+
+    #[test]
+    fn test_top_of_block_arbitration() {
+        let priority = vec![BlsPublicKey::repeat_byte(2)];
+        let arbiter =
+            |policy| TopOfBlockArbiter::new(policy, priority.clone(), Duration::from_secs(3600));
+
+        let mut store = ConstraintStore::new(arbiter(TopOfBlockPolicy::EarliestSigned));
+        store.add_constraints(10, top_of_block(1, 1)).unwrap();
+        let rejected = store.add_constraints(10, top_of_block(2, 2));
+        assert!(matches!(rejected, Err(StoreError::Conflict(StoreConflict::TopOfBlock(_)))));
+        assert_eq!(stored_sidecar(&store), BlsPublicKey::repeat_byte(1));
+
+        let mut store = ConstraintStore::new(arbiter(TopOfBlockPolicy::HighestTip));
+        store.add_constraints(10, top_of_block(1, 2)).unwrap();
+        assert!(store.add_constraints(10, top_of_block(2, 2)).is_err());
+        store.add_constraints(10, top_of_block(3, 3)).unwrap();
+        assert_eq!(stored_sidecar(&store), BlsPublicKey::repeat_byte(3));
+
+        let mut store = ConstraintStore::new(arbiter(TopOfBlockPolicy::Priority));
+        store.add_constraints(10, top_of_block(1, 3)).unwrap();
+        store.add_constraints(10, top_of_block(2, 1)).unwrap();
+        assert!(store.add_constraints(10, top_of_block(3, 3)).is_err());
+        assert_eq!(stored_sidecar(&store), BlsPublicKey::repeat_byte(2));
+    }
+
+    #[test]
+    fn test_top_of_block_round() {
+        let arbiter = TopOfBlockArbiter::new(TopOfBlockPolicy::HighestTip, vec![], Duration::ZERO);
+        let mut store = ConstraintStore::new(arbiter);
+        let (replaced, kept) = (top_of_block(1, 1), top_of_block(2, 2));
+        store.add_constraints(10, replaced.clone()).unwrap();
+
+        // Bundles can't replace the kept one once the round is closed
+        assert!(store.top_of_block_closes_at(10).is_some_and(|at| at <= Instant::now()));
+        assert!(store.add_constraints(10, kept.clone()).is_err());
+        store.settle_top_of_block(10, &replaced).unwrap();
+
+        let arbiter =
+            TopOfBlockArbiter::new(TopOfBlockPolicy::HighestTip, vec![], Duration::from_secs(3600));
+        let mut store = ConstraintStore::new(arbiter);
+        store.add_constraints(10, replaced.clone()).unwrap();
+        store.add_constraints(10, kept.clone()).unwrap();
+        // The replaced sidecar is told why
+        assert!(matches!(
+            store.settle_top_of_block(10, &replaced),
+            Err(StoreConflict::TopOfBlock(reason)) if reason.contains(&kept.pubkey.to_string())
+        ));
+        store.settle_top_of_block(10, &kept).unwrap();
+        assert!(store.add_constraints(10, top_of_block(3, 3)).is_err());
+    }
+}
@@ -7,6 +7,10 @@ pub enum PbsClientError {
     #[allow(unused)]
     NoPayload,
     BadRequest,
+    /// The constraints conflict with the ones of another sidecar.
+    Rejected(String),
+    /// The slot isn't covered by a valid slot manifest.
+    Unrouted(String),
 }
 
 impl PbsClientError {
@@ -15,18 +19,22 @@ impl PbsClientError {
             PbsClientError::NoResponse => StatusCode::SERVICE_UNAVAILABLE,
             PbsClientError::NoPayload => StatusCode::BAD_GATEWAY,
             PbsClientError::BadRequest => StatusCode::BAD_REQUEST,
+            PbsClientError::Rejected(_) => StatusCode::CONFLICT,
+            PbsClientError::Unrouted(_) => StatusCode::FORBIDDEN,
         }
     }
 }
 
 impl IntoResponse for PbsClientError {
     fn into_response(self) -> axum::response::Response {
+        let status = self.status_code();
         let msg = match self {
-            PbsClientError::NoResponse => "There is no response from relays",
-            PbsClientError::NoPayload => "There is no payload from relays",
-            PbsClientError::BadRequest => "Bad request",
+            PbsClientError::NoResponse => "There is no response from relays".to_string(),
+            PbsClientError::NoPayload => "There is no payload from relays".to_string(),
+            PbsClientError::BadRequest => "Bad request".to_string(),
+            PbsClientError::Rejected(reason) | PbsClientError::Unrouted(reason) => reason,
         };
 
-        (self.status_code(), msg).into_response()
+        (status, msg).into_response()
     }
 }
@@ -2,10 +2,13 @@ use cb_common::config::load_pbs_custom_config;
 use cb_pbs::{PbsService, PbsState};
 use eyre::Result;
 
+mod alerts;
 mod constraints;
 mod error;
+mod manifests;
 mod metrics;
 mod proofs;
+mod receipts;
 mod server;
 mod types;
 
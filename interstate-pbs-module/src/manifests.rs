@@ -0,0 +1,173 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{eips::merge::EPOCH_SLOTS, rpc::types::beacon::BlsPublicKey};
+use parking_lot::RwLock;
+
+use crate::types::{SlotManifest, VerifiedConstraints};
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ManifestError {
+    #[error("Slot {slot} isn't part of epoch {epoch}.")]
+    SlotOutsideEpoch { slot: u64, epoch: u64 },
+    #[error("Slot {slot} is already served by sidecar {sidecar}.")]
+    SlotTaken { slot: u64, sidecar: BlsPublicKey },
+    #[error("Slot {0} isn't covered by any slot manifest.")]
+    NotCovered(u64),
+    #[error("The manifest of slot {slot} doesn't allow {pubkey} to sign its constraints.")]
+    UnknownSigner { slot: u64, pubkey: BlsPublicKey },
+    #[error("The manifest of slot {slot} doesn't serve the proposals of {pubkey}.")]
+    UnknownProposer { slot: u64, pubkey: BlsPublicKey },
+    #[error("Slot {slot} is limited to {limit} constrained transactions by its manifest.")]
+    LimitExceeded { slot: u64, limit: u64 },
+}
+
+/// The routing table built from the verified slot manifests of the sidecars, by slot.
+///
+/// The signatures of the manifests are verified before they are inserted.
+#[derive(Clone, Default, Debug)]
+pub struct ManifestStore {
+    slots: Arc<RwLock<HashMap<u64, Arc<SlotManifest>>>>,
+}
+
+impl ManifestStore {
+    /// Routes the slots of `manifest` to its sidecar, replacing the previous manifest of the
+    /// sidecar for these slots. Nothing is routed if one of the slots is outside of its epoch
+    /// or served by another sidecar.
+    pub fn insert(&self, manifest: SlotManifest) -> Result<(), ManifestError> {
+        let epoch = manifest.epoch;
+        let epoch_slots = epoch * EPOCH_SLOTS..(epoch + 1) * EPOCH_SLOTS;
+        if let Some(&slot) = manifest.slots.iter().find(|slot| !epoch_slots.contains(slot)) {
+            return Err(ManifestError::SlotOutsideEpoch { slot, epoch });
+        }
+
+        let mut slots = self.slots.write();
+        for slot in &manifest.slots {
+            if let Some(other) = slots.get(slot) {
+                if other.sidecar_pubkey != manifest.sidecar_pubkey {
+                    let sidecar = other.sidecar_pubkey;
+                    return Err(ManifestError::SlotTaken { slot: *slot, sidecar });
+                }
+            }
+        }
+
+        let manifest = Arc::new(manifest);
+        for slot in &manifest.slots {
+            slots.insert(*slot, manifest.clone());
+        }
+        Ok(())
+    }
+
+    /// Returns the manifest covering `slot`, if any.
+    pub fn covering(&self, slot: u64) -> Option<Arc<SlotManifest>> {
+        self.slots.read().get(&slot).cloned()
+    }
+
+    /// Checks that the constraints are signed by the sidecars serving their slots, or by
+    /// the proposers themselves, within the limits of the manifests.
+    pub fn check_constraints(
+        &self,
+        constraints: &[VerifiedConstraints],
+    ) -> Result<(), ManifestError> {
+        let mut counts = HashMap::<u64, u64>::new();
+        for signed in constraints {
+            let (slot, pubkey) = (signed.message.slot, signed.message.pubkey);
+            let manifest = self.covering(slot).ok_or(ManifestError::NotCovered(slot))?;
+            if manifest.sidecar_pubkey != pubkey && !manifest.pubkeys.contains(&pubkey) {
+                return Err(ManifestError::UnknownSigner { slot, pubkey });
+            }
+
+            let count = counts.entry(slot).or_default();
+            *count += signed.message.transactions.len() as u64;
+            if *count > manifest.max_constraints_per_slot {
+                let limit = manifest.max_constraints_per_slot;
+                return Err(ManifestError::LimitExceeded { slot, limit });
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the proposer of `slot` is served by the sidecar covering it.
+    pub fn check_proposer(&self, slot: u64, pubkey: &BlsPublicKey) -> Result<(), ManifestError> {
+        let manifest = self.covering(slot).ok_or(ManifestError::NotCovered(slot))?;
+        if !manifest.pubkeys.contains(pubkey) {
+            return Err(ManifestError::UnknownProposer { slot, pubkey: *pubkey });
+        }
+        Ok(())
+    }
+
+    /// Removes the manifests of the slots before `slot`.
+    pub fn remove_before(&self, slot: u64) {
+        self.slots.write().retain(|s, _| *s >= slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{eips::merge::EPOCH_SLOTS, rpc::types::beacon::BlsPublicKey};
+
+    use super::{ManifestError, ManifestStore};
+    use crate::types::{ConstraintsMessage, SlotManifest, VerifiedConstraints};
+
+    fn manifest(sidecar: u8, epoch: u64, slots: Vec<u64>) -> SlotManifest {
+        SlotManifest {
+            epoch,
+            sidecar_pubkey: BlsPublicKey::repeat_byte(sidecar),
+            slots,
+            pubkeys: vec![BlsPublicKey::repeat_byte(0xaa)],
+            max_constraints_per_slot: 2,
+        }
+    }
+
+    fn constraints(signer: u8, slot: u64, count: usize) -> VerifiedConstraints {
+        VerifiedConstraints {
+            message: ConstraintsMessage {
+                pubkey: BlsPublicKey::repeat_byte(signer),
+                slot,
+                top: false,
+                transactions: vec![Default::default(); count],
+            },
+            signature: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_routes_covered_slots_only() {
+        let store = ManifestStore::default();
+        let epoch = 2;
+        let (first, last) = (epoch * EPOCH_SLOTS, (epoch + 1) * EPOCH_SLOTS - 1);
+
+        assert_eq!(
+            store.insert(manifest(1, epoch, vec![first, last + 1])),
+            Err(ManifestError::SlotOutsideEpoch { slot: last + 1, epoch })
+        );
+        store.insert(manifest(1, epoch, vec![first, last])).unwrap();
+        assert!(matches!(
+            store.insert(manifest(2, epoch, vec![last])),
+            Err(ManifestError::SlotTaken { slot, .. }) if slot == last
+        ));
+
+        // Signed by the sidecar or the proposer, within the limit of the manifest
+        assert!(store
+            .check_constraints(&[constraints(1, first, 1), constraints(0xaa, first, 1)])
+            .is_ok());
+        assert!(matches!(
+            store.check_constraints(&[constraints(1, first, 3)]),
+            Err(ManifestError::LimitExceeded { limit: 2, .. })
+        ));
+        assert!(matches!(
+            store.check_constraints(&[constraints(2, first, 1)]),
+            Err(ManifestError::UnknownSigner { .. })
+        ));
+        assert_eq!(
+            store.check_constraints(&[constraints(1, first + 1, 1)]),
+            Err(ManifestError::NotCovered(first + 1))
+        );
+
+        assert!(store.check_proposer(last, &BlsPublicKey::repeat_byte(0xaa)).is_ok());
+        assert!(store.check_proposer(last, &BlsPublicKey::repeat_byte(0xbb)).is_err());
+
+        store.remove_before(last);
+        assert!(store.covering(first).is_none());
+        assert!(store.covering(last).is_some());
+    }
+}
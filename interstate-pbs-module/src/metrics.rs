@@ -2,7 +2,8 @@ use cb_pbs::PbsService;
 use lazy_static::lazy_static;
 use prometheus::{
     register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
-    register_int_gauge_with_registry, HistogramVec, IntCounterVec, IntGauge, Registry,
+    register_int_counter_with_registry, register_int_gauge_with_registry, HistogramVec,
+    IntCounter, IntCounterVec, IntGauge, Registry,
 };
 
 pub(crate) const ERROR_CODE_TIMEOUT_STR: &str = "555";
@@ -14,6 +15,10 @@ pub(crate) fn initialize_metrics() -> eyre::Result<()> {
     PbsService::register_metric(Box::new(RELAY_HTTP_STATUS.clone()));
     PbsService::register_metric(Box::new(INVALID_BIDS_COUNT.clone()));
     PbsService::register_metric(Box::new(CACHE_SIZE_CONSTRAINTS.clone()));
+    PbsService::register_metric(Box::new(CONSTRAINTS_CONFLICTS_COUNT.clone()));
+    PbsService::register_metric(Box::new(TOP_OF_BLOCK_ARBITRATIONS_COUNT.clone()));
+    PbsService::register_metric(Box::new(RELAY_POST_RETRIES_COUNT.clone()));
+    PbsService::register_metric(Box::new(COLLECTED_RECEIPTS_COUNT.clone()));
 
     // Initialize PBS Service metrics
     PbsService::init_metrics()
@@ -58,4 +63,39 @@ lazy_static! {
     )
     .unwrap();
 
+
+    /// Conflicting constraint sets signed by the same key for one slot
+    pub static ref CONSTRAINTS_CONFLICTS_COUNT: IntCounter = register_int_counter_with_registry!(
+        "constraints_conflicts_total",
+        "Total number of conflicting constraint sets signed by the same key for one slot",
+        INTERSTATE_BOOST_METRICS
+    )
+    .unwrap();
+
+    /// Conflicting top-of-block bundles for one slot, by outcome
+    pub static ref TOP_OF_BLOCK_ARBITRATIONS_COUNT: IntCounterVec = register_int_counter_vec_with_registry!(
+        "top_of_block_arbitrations_total",
+        "Total number of conflicting top-of-block bundles for one slot, categorized by outcome",
+        &["outcome"],
+        INTERSTATE_BOOST_METRICS
+    )
+    .unwrap();
+
+    /// Retried POST requests per relay and endpoint
+    pub static ref RELAY_POST_RETRIES_COUNT: IntCounterVec = register_int_counter_vec_with_registry!(
+        "relay_post_retries_total",
+        "Total number of retried POST requests to relays, categorized by endpoint and relay ID",
+        &["endpoint", "relay_id"],
+        INTERSTATE_BOOST_METRICS
+    )
+    .unwrap();
+
+    /// Receipts gossiped by the sidecars, by outcome
+    pub static ref COLLECTED_RECEIPTS_COUNT: IntCounterVec = register_int_counter_vec_with_registry!(
+        "collected_receipts_total",
+        "Total number of receipts gossiped by the sidecars, categorized by outcome",
+        &["outcome"],
+        INTERSTATE_BOOST_METRICS
+    )
+    .unwrap();
 }
@@ -0,0 +1,103 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use alloy::primitives::B256;
+use parking_lot::RwLock;
+
+use crate::types::GossipedReceipt;
+
+/// Most receipts collected for a single slot, the ones gossiped past it are dropped.
+pub(crate) const MAX_RECEIPTS_PER_SLOT: usize = 4096;
+
+#[derive(Debug, Default)]
+struct SlotReceipts {
+    digests: HashSet<B256>,
+    receipts: Vec<GossipedReceipt>,
+}
+
+/// The inclusion receipts gossiped by the sidecars, giving a view of what was committed
+/// where across the network.
+///
+/// Only receipts with a verified signature must be collected. Each receipt is kept once per
+/// slot, whether it was gossiped in full or as its digest.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiptCollector {
+    slots: Arc<RwLock<BTreeMap<u64, SlotReceipts>>>,
+}
+
+impl ReceiptCollector {
+    /// Collect the receipts, returning the number of receipts not collected before.
+    pub fn insert(&self, receipts: impl IntoIterator<Item = GossipedReceipt>) -> usize {
+        let mut slots = self.slots.write();
+        let mut collected = 0;
+        for receipt in receipts {
+            let slot = slots.entry(receipt.slot()).or_default();
+            if slot.receipts.len() < MAX_RECEIPTS_PER_SLOT && slot.digests.insert(receipt.digest())
+            {
+                slot.receipts.push(receipt);
+                collected += 1;
+            }
+        }
+        collected
+    }
+
+    /// The receipts collected for `slot`, in the order they were received.
+    pub fn by_slot(&self, slot: u64) -> Vec<GossipedReceipt> {
+        self.slots.read().get(&slot).map(|slot| slot.receipts.clone()).unwrap_or_default()
+    }
+
+    /// Forget the receipts of the slots before `slot`.
+    pub fn remove_before(&self, slot: u64) {
+        let mut slots = self.slots.write();
+        *slots = slots.split_off(&slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::B256;
+
+    use super::ReceiptCollector;
+    use crate::types::{GossipedReceipt, PreconfReceipt, ReceiptDigest, SignedPreconfReceipt};
+
+    fn receipt(slot: u64) -> SignedPreconfReceipt {
+        SignedPreconfReceipt {
+            message: PreconfReceipt {
+                slot,
+                tx_hashes: vec![B256::repeat_byte(1)],
+                validator_pubkey: Default::default(),
+                expiry_ms: slot * 12_000,
+                deployments: vec![],
+            },
+            signer: Default::default(),
+            signature: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_collects_each_receipt_once() {
+        let collector = ReceiptCollector::default();
+        let full = receipt(10);
+        let hash_only = GossipedReceipt::HashOnly(ReceiptDigest {
+            slot: 10,
+            validator_pubkey: Default::default(),
+            expiry_ms: full.message.expiry_ms,
+            digest: full.message.digest(),
+            tx_count: 1,
+            signer: Default::default(),
+            signature: Default::default(),
+        });
+
+        let gossiped = [GossipedReceipt::Full(full.clone()), GossipedReceipt::Full(receipt(11))];
+        assert_eq!(collector.insert(gossiped), 2);
+        // The digest of a collected receipt, or its re-submission, adds nothing
+        assert_eq!(collector.insert([hash_only, GossipedReceipt::Full(full.clone())]), 0);
+        assert_eq!(collector.by_slot(10), vec![GossipedReceipt::Full(full)]);
+
+        collector.remove_before(11);
+        assert!(collector.by_slot(10).is_empty());
+        assert_eq!(collector.by_slot(11).len(), 1);
+    }
+}
@@ -9,7 +9,7 @@ use serde_json::Value;
 
 use async_trait::async_trait;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::{header::USER_AGENT, HeaderMap, HeaderValue, StatusCode},
     response::IntoResponse,
     routing::{get, post},
@@ -37,19 +37,25 @@ use tracing::{debug, error, info, warn, Instrument};
 
 use crate::{
     metrics::{
-        ERROR_CODE_TIMEOUT_STR, INVALID_BIDS_COUNT, LATENCY_BY_RELAY, RELAY_HTTP_STATUS,
-        TAG_GET_HEADER_WITH_PROOFS,
+        COLLECTED_RECEIPTS_COUNT, ERROR_CODE_TIMEOUT_STR, INVALID_BIDS_COUNT, LATENCY_BY_RELAY,
+        RELAY_HTTP_STATUS, RELAY_POST_RETRIES_COUNT, TAG_GET_HEADER_WITH_PROOFS,
     },
     types::ValidationContext,
 };
 
 use super::{
-    constraints::ConstraintStore,
+    alerts::{ConflictDetector, ConflictEvidence},
+    constraints::{
+        ConstraintStore, StoreConflict, StoreError, TopOfBlockArbiter, PER_SLOT_MAX_CONSTRAINTS,
+    },
     error::PbsClientError,
+    manifests::ManifestStore,
     proofs::validate_multiproofs,
+    receipts::ReceiptCollector,
     types::{
-        Config, FetchHeaderParams, GetHeaderWithProofsResponse, RequestConfig, SignedDelegation,
-        SignedRevocation, VerifiedConstraints,
+        Config, ConstraintsPage, ConstraintsQuery, FetchHeaderParams, GetHeaderWithProofsResponse,
+        GossipedReceipt, ReceiptsQuery, RequestConfig, SignedDelegation, SignedRevocation,
+        SignedSlotManifest, VerifiedConstraints,
     },
 };
 
@@ -58,10 +64,18 @@ const DELEGATE_ROUTE: &str = "/constraints/v1/builder/delegate";
 const REVOKE_ROUTE: &str = "/constraints/v1/builder/revoke";
 const HEADER_WITH_PROOFS_ROUTE: &str =
     "/eth/v1/builder/header_with_proofs/:slot/:parent_hash/:pubkey";
+const CONSTRAINTS_SPEC_ROUTE: &str = "/constraints/v1/spec";
+const CONSTRAINTS_ALERTS_ROUTE: &str = "/constraints/v1/alerts";
+const SLOT_MANIFESTS_ROUTE: &str = "/constraints/v1/builder/manifests";
+const RECEIPTS_COLLECT_ROUTE: &str = "/constraints/v1/builder/receipts_collect";
+
+/// Version of the constraints API served by the module.
+const CONSTRAINTS_API_VERSION: &str = "v1";
+const DEFAULT_CONSTRAINTS_PAGE_LIMIT: usize = 32;
+const MAX_CONSTRAINTS_PAGE_LIMIT: usize = 128;
 
 const ERROR_CODE_TIMEOUT: u16 = 555;
 
-const SECONDS_PER_SLOT: u64 = 12;
 const MILLIS_PER_SECOND: u64 = 1_000;
 
 // State containing runtime-specific information
@@ -70,6 +84,9 @@ pub struct BuilderRuntimeState {
     #[allow(unused)]
     config: Config,
     constraints: ConstraintStore,
+    conflicts: ConflictDetector,
+    manifests: ManifestStore,
+    receipts: ReceiptCollector,
     client: reqwest::Client,
 }
 
@@ -77,9 +94,23 @@ impl BuilderApiState for BuilderRuntimeState {}
 
 impl BuilderRuntimeState {
     pub fn new(settings: Config) -> Self {
+        let conflicts = ConflictDetector::new(
+            settings.conflicts_evidence_path.clone(),
+            settings.conflicts_webhook_url.clone(),
+        );
+
+        let top_of_block = TopOfBlockArbiter::new(
+            settings.top_of_block_policy,
+            settings.sidecar_priority.clone(),
+            Duration::from_millis(settings.top_of_block_window_ms),
+        );
+
         Self {
             config: settings,
-            constraints: ConstraintStore::new(),
+            constraints: ConstraintStore::new(top_of_block),
+            conflicts,
+            manifests: ManifestStore::default(),
+            receipts: ReceiptCollector::default(),
             client: reqwest::Client::new(),
         }
     }
@@ -102,6 +133,9 @@ impl BuilderApi<BuilderRuntimeState> for ConstraintsApi {
 
         info!("Clearing constraints before slot {slot}");
         runtime_state.data.constraints.remove_before_constraints(slot);
+        runtime_state.data.conflicts.remove_before(slot);
+        runtime_state.data.manifests.remove_before(slot);
+        runtime_state.data.receipts.remove_before(slot.saturating_sub(EPOCH_SLOTS));
 
         register_validator(validator_registrations, request_headers, runtime_state).await
     }
@@ -109,10 +143,16 @@ impl BuilderApi<BuilderRuntimeState> for ConstraintsApi {
     /// Fetches the extra routes necessary for supporting the constraints API as per
     fn extra_routes() -> Option<Router<PbsState<BuilderRuntimeState>>> {
         let mut router = Router::new();
-        router = router.route(SUBMIT_CONSTRAINTS_ROUTE, post(submit_constraints));
+        router =
+            router.route(SUBMIT_CONSTRAINTS_ROUTE, post(submit_constraints).get(get_constraints));
         router = router.route(DELEGATE_ROUTE, post(delegate));
         router = router.route(REVOKE_ROUTE, post(revoke));
         router = router.route(HEADER_WITH_PROOFS_ROUTE, get(get_header_with_proofs));
+        router = router.route(CONSTRAINTS_SPEC_ROUTE, get(get_constraints_spec));
+        router = router.route(CONSTRAINTS_ALERTS_ROUTE, get(get_conflict_alerts));
+        router = router.route(SLOT_MANIFESTS_ROUTE, post(submit_slot_manifests));
+        router = router
+            .route(RECEIPTS_COLLECT_ROUTE, post(collect_receipts).get(get_collected_receipts));
         Some(router)
     }
 }
@@ -128,31 +168,210 @@ async fn submit_constraints(
         .await
         .map_err(|e| PbsClientError::BadRequest)?;
 
+    // Forged constraints must neither reach the relays nor raise alerts against their key
+    let chain = state.config.chain;
+    if let Some(forged) = constraints.iter().find(|c| !c.verify_signature(chain, &c.message.pubkey))
+    {
+        warn!(slot = forged.message.slot, pubkey = %forged.message.pubkey, "Invalid constraints signature");
+        return Err(PbsClientError::BadRequest);
+    }
+
+    // Only accept constraints for the current or next epoch.
+    info!("current_slot: {}", current_slot);
+    info!("epoch_slots: {}", EPOCH_SLOTS);
+    if let Some(slot) = constraints
+        .iter()
+        .map(|c| c.message.slot)
+        .find(|slot| *slot > current_slot + EPOCH_SLOTS * 2)
+    {
+        warn!(slot, current_slot, "The constraints are scheduled for a time that is too far in the future to be processed at this moment.");
+        return Err(PbsClientError::BadRequest);
+    }
+
+    // Only the slots served by a sidecar that published a valid manifest are routed
+    if state.data.config.require_slot_manifests {
+        if let Err(e) = state.data.manifests.check_constraints(&constraints) {
+            warn!(error = %e, "Refused constraints not covered by a slot manifest");
+            return Err(PbsClientError::Unrouted(e.to_string()));
+        }
+    }
+
+    // Raise alerts before storing, as the store may reject the conflicting constraints
+    state.data.conflicts.check(&constraints);
+
     // Save constraints for the slot to verify proofs against later.
-    for signed_constraints in &constraints {
+    let store = &mut state.data.constraints;
+    for (i, signed_constraints) in constraints.iter().enumerate() {
         let slot = signed_constraints.message.slot;
-        info!("current_slot: {}", current_slot);
-        info!("epoch_slots: {}", EPOCH_SLOTS);
         info!("received_target_ slot: {}", slot);
 
-        // Only accept constraints for the current or next epoch.
-        if slot > current_slot + EPOCH_SLOTS * 2 {
-            warn!(slot, current_slot, "The constraints are scheduled for a time that is too far in the future to be processed at this moment.");
-            return Err(PbsClientError::BadRequest);
-        }
         info!("starting to add constraints");
-        if let Err(e) =
-            state.data.constraints.add_constraints(slot, signed_constraints.message.clone())
-        {
-            error!(slot, error = %e, "Failed to save constraints");
-            return Err(PbsClientError::BadRequest);
+        match store.add_constraints(slot, signed_constraints.message.clone()) {
+            Ok(()) => {}
+            // Tell the sidecar why its bundle lost, so that it can release the commitment
+            Err(StoreError::Conflict(e @ StoreConflict::TopOfBlock(_))) => {
+                warn!(slot, error = %e, "Rejected conflicting top-of-block constraints");
+                remove_submission(store, &constraints[..i]);
+                return Err(PbsClientError::Rejected(e.to_string()));
+            }
+            Err(e) => {
+                error!(slot, error = %e, "Failed to save constraints");
+                remove_submission(store, &constraints[..i]);
+                return Err(PbsClientError::BadRequest);
+            }
+        }
+    }
+
+    // The relays can't be told to drop a top-of-block bundle, so it is only forwarded once it
+    // won the arbitration of its slot. The sidecars of the replaced ones are told why.
+    for top in constraints.iter().filter(|c| c.message.top) {
+        let slot = top.message.slot;
+        if let Some(closes_at) = store.top_of_block_closes_at(slot) {
+            tokio::time::sleep_until(closes_at.into()).await;
+        }
+        if let Err(e) = store.settle_top_of_block(slot, &top.message) {
+            warn!(slot, error = %e, "Replaced top-of-block constraints");
+            remove_submission(store, &constraints);
+            return Err(PbsClientError::Rejected(e.to_string()));
         }
     }
+
     info!("starting to post to relay");
     relay_post_request(state, SUBMIT_CONSTRAINTS_ROUTE, &constraints).await?;
     Ok(StatusCode::OK)
 }
 
+/// Routes the slots of the manifests published by the sidecars, once their signatures are
+/// verified.
+#[tracing::instrument(skip_all)]
+async fn submit_slot_manifests(
+    State(state): State<PbsState<BuilderRuntimeState>>,
+    Json(manifests): Json<Vec<SignedSlotManifest>>,
+) -> Result<impl IntoResponse, PbsClientError> {
+    let chain = state.config.chain;
+    if let Some(forged) = manifests.iter().find(|m| !m.verify_signature(chain)) {
+        warn!(epoch = forged.message.epoch, sidecar = %forged.message.sidecar_pubkey, "Invalid slot manifest signature");
+        return Err(PbsClientError::BadRequest);
+    }
+
+    for signed in manifests {
+        let (epoch, sidecar) = (signed.message.epoch, signed.message.sidecar_pubkey);
+        if let Err(e) = state.data.manifests.insert(signed.message) {
+            warn!(epoch, %sidecar, error = %e, "Refused slot manifest");
+            return Err(PbsClientError::Rejected(e.to_string()));
+        }
+        info!(epoch, %sidecar, "Routing the slots of a slot manifest");
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Collects the inclusion receipts gossiped by the sidecars. The receipts with an invalid
+/// signature are dropped, as the gossip of the others isn't retried.
+#[tracing::instrument(skip_all)]
+async fn collect_receipts(
+    State(state): State<PbsState<BuilderRuntimeState>>,
+    Json(receipts): Json<Vec<GossipedReceipt>>,
+) -> Result<impl IntoResponse, PbsClientError> {
+    let chain = state.config.chain;
+    let (verified, forged): (Vec<_>, Vec<_>) =
+        receipts.into_iter().partition(|receipt| receipt.verify_signature(chain));
+    if let Some(receipt) = forged.first() {
+        warn!(slot = receipt.slot(), forged = forged.len(), "Invalid receipt signatures");
+        COLLECTED_RECEIPTS_COUNT.with_label_values(&["invalid"]).inc_by(forged.len() as u64);
+    }
+
+    let received = verified.len();
+    let collected = state.data.receipts.insert(verified);
+    debug!(received, collected, "Collected gossiped receipts");
+    COLLECTED_RECEIPTS_COUNT.with_label_values(&["collected"]).inc_by(collected as u64);
+    let duplicates = (received - collected) as u64;
+    COLLECTED_RECEIPTS_COUNT.with_label_values(&["duplicate"]).inc_by(duplicates);
+
+    Ok(StatusCode::OK)
+}
+
+/// Returns the receipts collected for a slot, in the order they were received.
+#[tracing::instrument(skip_all)]
+async fn get_collected_receipts(
+    State(state): State<PbsState<BuilderRuntimeState>>,
+    Query(query): Query<ReceiptsQuery>,
+) -> Json<Vec<GossipedReceipt>> {
+    Json(state.data.receipts.by_slot(query.slot))
+}
+
+/// Removes the constraints of a rejected submission, which aren't forwarded to the relays.
+fn remove_submission(store: &ConstraintStore, constraints: &[VerifiedConstraints]) {
+    for signed in constraints {
+        store.remove_message(signed.message.slot, &signed.message);
+    }
+}
+
+/// Returns the evidence of conflicting constraints signed by the same key for one slot.
+#[tracing::instrument(skip_all)]
+async fn get_conflict_alerts(
+    State(state): State<PbsState<BuilderRuntimeState>>,
+) -> Json<Vec<ConflictEvidence>> {
+    Json(state.data.conflicts.alerts())
+}
+
+/// Returns a page of the stored constraints for a slot range, ordered by slot.
+#[tracing::instrument(skip_all)]
+async fn get_constraints(
+    State(state): State<PbsState<BuilderRuntimeState>>,
+    Query(query): Query<ConstraintsQuery>,
+) -> Result<impl IntoResponse, PbsClientError> {
+    let from_slot = query.from_slot.unwrap_or_default();
+    let to_slot = query.to_slot.unwrap_or(u64::MAX);
+    if from_slot > to_slot {
+        return Err(PbsClientError::BadRequest);
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_CONSTRAINTS_PAGE_LIMIT);
+    if limit == 0 || limit > MAX_CONSTRAINTS_PAGE_LIMIT {
+        return Err(PbsClientError::BadRequest);
+    }
+
+    let constraints = state.data.constraints.get_range(from_slot, to_slot);
+    let start = query.cursor.unwrap_or_default().min(constraints.len());
+    let end = (start + limit).min(constraints.len());
+
+    let page = ConstraintsPage {
+        data: constraints[start..end].to_vec(),
+        next_cursor: (end < constraints.len()).then_some(end),
+    };
+
+    Ok((StatusCode::OK, Json(page)))
+}
+
+/// Capability document so that clients can detect what the constraints API supports.
+async fn get_constraints_spec() -> impl IntoResponse {
+    Json(serde_json::json!({
+        "version": CONSTRAINTS_API_VERSION,
+        "endpoints": [
+            { "method": "POST", "path": SUBMIT_CONSTRAINTS_ROUTE },
+            { "method": "GET", "path": SUBMIT_CONSTRAINTS_ROUTE },
+            { "method": "POST", "path": DELEGATE_ROUTE },
+            { "method": "POST", "path": REVOKE_ROUTE },
+            { "method": "GET", "path": HEADER_WITH_PROOFS_ROUTE },
+            { "method": "GET", "path": CONSTRAINTS_SPEC_ROUTE },
+            { "method": "GET", "path": CONSTRAINTS_ALERTS_ROUTE },
+            { "method": "POST", "path": SLOT_MANIFESTS_ROUTE },
+            { "method": "POST", "path": RECEIPTS_COLLECT_ROUTE },
+            { "method": "GET", "path": RECEIPTS_COLLECT_ROUTE },
+        ],
+        "capabilities": {
+            "slot_range_queries": true,
+            "message_versions": [CONSTRAINTS_API_VERSION],
+            "pagination": {
+                "default_limit": DEFAULT_CONSTRAINTS_PAGE_LIMIT,
+                "max_limit": MAX_CONSTRAINTS_PAGE_LIMIT,
+            },
+            "max_constraints_per_slot": PER_SLOT_MAX_CONSTRAINTS,
+        },
+    }))
+}
+
 /// Transfers the right to submit constraints to another BLS key.
 #[tracing::instrument(skip_all)]
 async fn delegate(
@@ -182,7 +401,11 @@ async fn get_header_with_proofs(
     Path(params): Path<FetchHeaderParams>,
     req_headers: HeaderMap,
 ) -> Result<impl IntoResponse, PbsClientError> {
-    let ms_into_slot = ms_into_slot(params.slot, state.data.config.genesis_time_sec);
+    let ms_into_slot = ms_into_slot(
+        params.slot,
+        state.data.config.genesis_time_sec,
+        state.config.chain.slot_time_sec(),
+    );
 
     let max_timeout_ms = state
         .pbs_config()
@@ -199,18 +422,39 @@ async fn get_header_with_proofs(
         return Ok(StatusCode::NO_CONTENT.into_response());
     }
 
+    if state.data.config.require_slot_manifests {
+        if let Err(e) = state.data.manifests.check_proposer(params.slot, &params.pubkey) {
+            warn!(error = %e, "Not routing a header query without a slot manifest");
+            return Ok(StatusCode::NO_CONTENT.into_response());
+        }
+    }
+
     // prepare headers, except for start time which is set in `send_one_get_header`
     let mut send_headers = HeaderMap::new();
 
     send_headers.insert(USER_AGENT, get_user_agent_with_version(&req_headers).unwrap());
 
-    let relays = state.config.relays.clone();
+    // Get and remove the constraints for this slot
+    let maybe_constraints = state.data.constraints.remove_constraints(params.slot);
+
+    // Vanilla relays can't prove the inclusion of the constraints, so they are only queried
+    // for slots without constraints
+    let relays = state
+        .config
+        .relays
+        .iter()
+        .filter_map(|relay| {
+            let with_proofs = state.data.config.capabilities(&relay.id).header_with_proofs;
+            (with_proofs || maybe_constraints.is_none()).then(|| (relay.clone(), with_proofs))
+        })
+        .collect::<Vec<_>>();
 
     let mut handles = Vec::with_capacity(relays.len());
-    for relay in relays.iter() {
+    for (relay, with_proofs) in relays.iter() {
         handles.push(send_timed_get_header(
             params,
             relay.clone(),
+            *with_proofs,
             state.config.chain,
             send_headers.clone(),
             ms_into_slot,
@@ -226,11 +470,8 @@ async fn get_header_with_proofs(
     let mut relay_bids = Vec::with_capacity(relays.len());
     let mut hash_to_proofs = HashMap::new();
 
-    // Get and remove the constraints for this slot
-    let maybe_constraints = state.data.constraints.remove_constraints(params.slot);
-
     for (i, res) in results.into_iter().enumerate() {
-        let relay_id = relays[i].id.as_ref();
+        let relay_id = relays[i].0.id.as_ref();
 
         match res {
             Ok(Some(res)) => {
@@ -290,18 +531,21 @@ async fn get_header_with_proofs(
 }
 
 #[tracing::instrument(skip_all, name = "handler", fields(relay_id = relay.id.as_ref()))]
+#[allow(clippy::too_many_arguments)]
 async fn send_timed_get_header(
     params: FetchHeaderParams,
     relay: RelayClient,
+    with_proofs: bool,
     chain: Chain,
     headers: HeaderMap,
     ms_into_slot: u64,
     mut timeout_left_ms: u64,
     validation: ValidationContext,
 ) -> Result<Option<GetHeaderWithProofsResponse>, PbsError> {
+    let route = if with_proofs { "header_with_proofs" } else { "header" };
     let url = relay.get_url(&format!(
-        "/eth/v1/builder/header_with_proofs/{}/{}/{}",
-        params.slot, params.parent_hash, params.pubkey
+        "/eth/v1/builder/{}/{}/{}/{}",
+        route, params.slot, params.parent_hash, params.pubkey
     ))?;
 
     if relay.config.enable_timing_games {
@@ -533,7 +777,8 @@ fn validate_header(
     Ok(())
 }
 
-/// Send a POST request to all relays. Only returns an error if all of the requests fail.
+/// Send a POST request to all the relays supporting the constraints API. Only returns an error
+/// if all of the requests fail.
 async fn relay_post_request<T>(
     state: PbsState<BuilderRuntimeState>,
     path: &str,
@@ -542,50 +787,98 @@ async fn relay_post_request<T>(
 where
     T: Serialize,
 {
-    debug!("Sending POST request to {} relays", state.config.relays.len());
-    // Forward constraints to all relays.
+    let relays = state
+        .config
+        .relays
+        .iter()
+        .filter(|relay| state.data.config.capabilities(&relay.id).constraints)
+        .collect::<Vec<_>>();
+    if relays.is_empty() {
+        warn!(path, "No relay supports the constraints API");
+    }
+
+    debug!("Sending POST request to {} relays", relays.len());
+    // Forward to the capable relays, each one retried on its own.
+    let attempts = state.data.config.relay_post_attempts;
+    let backoff = Duration::from_millis(state.data.config.relay_post_backoff_ms);
     let mut responses = FuturesUnordered::new();
 
-    for relay in state.config.relays {
+    for relay in relays {
         let url = relay.get_url(path).map_err(|_| PbsClientError::BadRequest)?;
-        responses.push(relay.client.post(url).json(&body).send());
+        responses.push(post_to_relay(relay, url, path, body, attempts, backoff));
     }
 
     let mut success = false;
-    while let Some(res) = responses.next().await {
+    while let Some(accepted) = responses.next().await {
+        success |= accepted;
+    }
+
+    if success {
+        Ok(())
+    } else {
+        Err(PbsClientError::NoResponse)
+    }
+}
+
+/// POST `body` to one relay, retrying the failed requests and the server errors of the relay
+/// with a linearly growing backoff. Returns whether the relay accepted it.
+async fn post_to_relay<T>(
+    relay: &RelayClient,
+    url: Url,
+    path: &str,
+    body: &T,
+    attempts: u32,
+    backoff: Duration,
+) -> bool
+where
+    T: Serialize,
+{
+    for attempt in 1..=attempts.max(1) {
+        if attempt > 1 {
+            RELAY_POST_RETRIES_COUNT.with_label_values(&[path, &relay.id]).inc();
+            sleep(backoff * (attempt - 1)).await;
+        }
+
+        let start_request = Instant::now();
+        let res = relay.client.post(url.clone()).json(body).send().await;
+        LATENCY_BY_RELAY
+            .with_label_values(&[path, &relay.id])
+            .observe(start_request.elapsed().as_secs_f64());
+
         match res {
             Ok(response) => {
-                let url = response.url().clone();
                 let status = response.status();
+                RELAY_HTTP_STATUS.with_label_values(&[status.as_str(), path, &relay.id]).inc();
+                if status == StatusCode::OK {
+                    debug!(%url, attempt, "Successfully sent POST request to relay");
+                    return true;
+                }
+
                 let body = response.text().await.ok();
-                if status != StatusCode::OK {
-                    error!(
-                        %status,
-                        %url,
-                        "Failed to POST to relay: {body:?}"
-                    )
-                } else {
-                    debug!(%url, "Successfully sent POST request to relay");
-                    success = true;
+                error!(%status, %url, attempt, "Failed to POST to relay: {body:?}");
+                // The relay refused the request itself, which a retry won't change
+                if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                    return false;
                 }
             }
-            Err(e) => error!(error = ?e, "Failed to POST to relay"),
+            Err(e) => {
+                RELAY_HTTP_STATUS
+                    .with_label_values(&[ERROR_CODE_TIMEOUT_STR, path, &relay.id])
+                    .inc();
+                error!(error = ?e, %url, attempt, "Failed to POST to relay");
+            }
         }
     }
 
-    if success {
-        Ok(())
-    } else {
-        Err(PbsClientError::NoResponse)
-    }
+    false
 }
 
-fn timestamp_of_slot_start_millis(slot: u64, genesis: u64) -> u64 {
-    let seconds_since_genesis = genesis + slot * SECONDS_PER_SLOT;
+fn timestamp_of_slot_start_millis(slot: u64, genesis: u64, slot_time_sec: u64) -> u64 {
+    let seconds_since_genesis = genesis + slot * slot_time_sec;
     seconds_since_genesis * MILLIS_PER_SECOND
 }
-fn ms_into_slot(slot: u64, genesis: u64) -> u64 {
-    let slot_start_ms = timestamp_of_slot_start_millis(slot, genesis);
+fn ms_into_slot(slot: u64, genesis: u64, slot_time_sec: u64) -> u64 {
+    let slot_start_ms = timestamp_of_slot_start_millis(slot, genesis, slot_time_sec);
     utcnow_ms().saturating_sub(slot_start_ms)
 }
 
@@ -1,7 +1,7 @@
 use alloy::{
     consensus::{Signed, TxEip4844Variant, TxEip4844WithSidecar, TxEnvelope},
     eips::eip2718::{Decodable2718, Eip2718Error, Eip2718Result, Encodable2718},
-    primitives::{keccak256, Bytes, TxHash, B256, U256},
+    primitives::{keccak256, Address, Bytes, TxHash, B256, U256},
     rpc::types::{
         beacon::{BlsPublicKey, BlsSignature},
         Block,
@@ -15,7 +15,7 @@ use parking_lot::RwLock;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use ssz_derive::{Decode, Encode};
-use std::{ops::Deref, sync::Arc};
+use std::{collections::HashMap, ops::Deref, path::PathBuf, sync::Arc};
 use tree_hash::TreeHash;
 
 use cb_common::{
@@ -30,6 +30,87 @@ use cb_common::{
 pub struct Config {
     pub genesis_time_sec: u64,
     pub beacon_rpc: Url,
+    /// File the evidence of conflicting constraints is appended to
+    #[serde(default)]
+    pub conflicts_evidence_path: Option<PathBuf>,
+    /// Webhook notified of conflicting constraints
+    #[serde(default)]
+    pub conflicts_webhook_url: Option<Url>,
+    /// Capabilities of the relays by relay id. Relays not listed support the constraints API
+    #[serde(default)]
+    pub relay_capabilities: HashMap<String, RelayCapabilities>,
+    /// How conflicting top-of-block bundles of different sidecars for one slot are arbitrated
+    #[serde(default)]
+    pub top_of_block_policy: TopOfBlockPolicy,
+    /// Sidecar keys from the highest to the lowest priority, for the `priority` policy
+    #[serde(default)]
+    pub sidecar_priority: Vec<BlsPublicKey>,
+    /// Time the top-of-block bundles of a slot are arbitrated for before the one kept is
+    /// forwarded to the relays, in ms
+    #[serde(default = "default_top_of_block_window_ms")]
+    pub top_of_block_window_ms: u64,
+    /// Attempts at forwarding constraints, delegations and revocations to each relay
+    #[serde(default = "default_relay_post_attempts")]
+    pub relay_post_attempts: u32,
+    /// Backoff between the attempts at forwarding to a relay, growing with each retry, in ms
+    #[serde(default = "default_relay_post_backoff_ms")]
+    pub relay_post_backoff_ms: u64,
+    /// Only route the constraints and header queries of the slots covered by a valid slot
+    /// manifest of a sidecar
+    #[serde(default)]
+    pub require_slot_manifests: bool,
+}
+
+impl Config {
+    pub fn capabilities(&self, relay_id: &str) -> RelayCapabilities {
+        self.relay_capabilities.get(relay_id).copied().unwrap_or_default()
+    }
+}
+
+/// What a relay supports on top of the vanilla builder API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub struct RelayCapabilities {
+    /// Accepts constraints, delegations and revocations
+    #[serde(default = "default_true")]
+    pub constraints: bool,
+    /// Serves headers with the inclusion proofs of the constraints
+    #[serde(default = "default_true")]
+    pub header_with_proofs: bool,
+}
+
+impl Default for RelayCapabilities {
+    fn default() -> Self {
+        Self { constraints: true, header_with_proofs: true }
+    }
+}
+
+/// Which top-of-block bundle is kept when several are submitted for the same slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopOfBlockPolicy {
+    /// The first bundle received is kept.
+    #[default]
+    EarliestSigned,
+    /// The bundle paying the highest tip is kept.
+    HighestTip,
+    /// The bundle of the sidecar with the highest configured priority is kept.
+    Priority,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_top_of_block_window_ms() -> u64 {
+    500
+}
+
+fn default_relay_post_attempts() -> u32 {
+    3
+}
+
+fn default_relay_post_backoff_ms() -> u64 {
+    100
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
@@ -39,6 +120,22 @@ pub struct FetchHeaderParams {
     pub pubkey: BlsPublicKey,
 }
 
+/// Query parameters of the constraints endpoint. Both slot bounds are inclusive and
+/// `cursor` is the `next_cursor` returned with the previous page.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ConstraintsQuery {
+    pub from_slot: Option<u64>,
+    pub to_slot: Option<u64>,
+    pub cursor: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConstraintsPage {
+    pub data: Vec<ConstraintsMessage>,
+    pub next_cursor: Option<usize>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct UpdateSlotParams {
     pub slot: u64,
@@ -53,7 +150,6 @@ pub struct VerifiedConstraints {
 impl VerifiedConstraints {
     //// Verifies the signature of this message using the specified BLS public key.
     /// The `chain` and `COMMIT_BOOST_DOMAIN` parameters are utilized to derive the signing root.
-    #[allow(unused)]
     pub fn verify_signature(&self, chain: Chain, pubkey: &BlsPublicKey) -> bool {
         let domain = compute_domain(chain, COMMIT_BOOST_DOMAIN);
         let digest = match self.message.digest() {
@@ -186,6 +282,150 @@ fn calculate_tx_hash_tree_root(
     }
 }
 
+/// Slots a sidecar serves in an epoch, published so that only the slots it covers are routed.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SlotManifest {
+    pub epoch: u64,
+    /// Key the sidecar signs this manifest and its constraints with
+    pub sidecar_pubkey: BlsPublicKey,
+    /// Proposer slots of the epoch served by the sidecar
+    pub slots: Vec<u64>,
+    /// Validator keys whose proposals the sidecar commits for
+    pub pubkeys: Vec<BlsPublicKey>,
+    /// Maximum number of transactions constrained in each slot
+    pub max_constraints_per_slot: u64,
+}
+
+impl SlotManifest {
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(self.epoch.to_le_bytes());
+        hasher.update(self.sidecar_pubkey);
+        for slot in &self.slots {
+            hasher.update(slot.to_le_bytes());
+        }
+        for pubkey in &self.pubkeys {
+            hasher.update(pubkey);
+        }
+        hasher.update(self.max_constraints_per_slot.to_le_bytes());
+
+        hasher.finalize().into()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSlotManifest {
+    pub message: SlotManifest,
+    pub signature: BlsSignature,
+}
+
+impl SignedSlotManifest {
+    /// Verifies that the sidecar key of the manifest signed it with the `COMMIT_BOOST_DOMAIN`.
+    pub fn verify_signature(&self, chain: Chain) -> bool {
+        let domain = compute_domain(chain, COMMIT_BOOST_DOMAIN);
+        let signing_root = compute_signing_root(self.message.digest(), domain);
+        verify_bls_signature(&self.message.sidecar_pubkey, &signing_root, &self.signature).is_ok()
+    }
+}
+
+/// Inclusion receipt of a sidecar, signed by the delegatee key that signed its constraints.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreconfReceipt {
+    pub slot: u64,
+    pub tx_hashes: Vec<B256>,
+    pub validator_pubkey: BlsPublicKey,
+    pub expiry_ms: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deployments: Vec<ContractDeployment>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractDeployment {
+    pub tx_hash: B256,
+    pub address: Address,
+}
+
+impl PreconfReceipt {
+    pub fn digest(&self) -> B256 {
+        let mut data = Vec::with_capacity(64 + 32 * self.tx_hashes.len());
+        data.extend_from_slice(&self.slot.to_be_bytes());
+        data.extend_from_slice(self.validator_pubkey.as_ref());
+        data.extend_from_slice(&self.expiry_ms.to_be_bytes());
+        for hash in &self.tx_hashes {
+            data.extend_from_slice(hash.as_slice());
+        }
+        for deployment in &self.deployments {
+            data.extend_from_slice(deployment.tx_hash.as_slice());
+            data.extend_from_slice(deployment.address.as_slice());
+        }
+
+        keccak256(data)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedPreconfReceipt {
+    pub message: PreconfReceipt,
+    pub signer: BlsPublicKey,
+    pub signature: BlsSignature,
+}
+
+/// A [SignedPreconfReceipt] without its transactions, gossiped by the sidecars keeping them
+/// private.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReceiptDigest {
+    pub slot: u64,
+    pub validator_pubkey: BlsPublicKey,
+    pub expiry_ms: u64,
+    pub digest: B256,
+    pub tx_count: usize,
+    pub signer: BlsPublicKey,
+    pub signature: BlsSignature,
+}
+
+/// Receipt gossiped by a sidecar to the receipts collector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GossipedReceipt {
+    Full(SignedPreconfReceipt),
+    HashOnly(ReceiptDigest),
+}
+
+impl GossipedReceipt {
+    pub fn slot(&self) -> u64 {
+        match self {
+            Self::Full(receipt) => receipt.message.slot,
+            Self::HashOnly(digest) => digest.slot,
+        }
+    }
+
+    /// The digest of the receipt, which the signature is over.
+    pub fn digest(&self) -> B256 {
+        match self {
+            Self::Full(receipt) => receipt.message.digest(),
+            Self::HashOnly(digest) => digest.digest,
+        }
+    }
+
+    /// Verifies the signature of the receipt using the `COMMIT_BOOST_DOMAIN`, as the
+    /// constraints of its transactions.
+    pub fn verify_signature(&self, chain: Chain) -> bool {
+        let (signer, signature) = match self {
+            Self::Full(receipt) => (&receipt.signer, &receipt.signature),
+            Self::HashOnly(digest) => (&digest.signer, &digest.signature),
+        };
+        let domain = compute_domain(chain, COMMIT_BOOST_DOMAIN);
+        let signing_root = compute_signing_root(self.digest().0, domain);
+        verify_bls_signature(signer, &signing_root, signature).is_ok()
+    }
+}
+
+/// Query parameters of the collected receipts endpoint.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ReceiptsQuery {
+    pub slot: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
 pub struct SignedDelegation {
     pub message: DelegationMessage,
@@ -197,6 +437,10 @@ pub struct DelegationMessage {
     pub action: u8,
     pub validator_pubkey: BlsPublicKey,
     pub delegatee_pubkey: BlsPublicKey,
+    /// Last epoch in which the delegation is valid, if it is time-bounded. Kept as is when
+    /// forwarding, as it is part of the signed message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry_epoch: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode)]
@@ -218,6 +462,8 @@ pub type GetHeaderWithProofsResponse = VersionedResponse<SignedExecutionPayloadH
 pub struct SignedExecutionPayloadHeaderWithProofs {
     #[serde(flatten)]
     pub header: SignedExecutionPayloadHeader,
+    /// Missing in the headers of vanilla relays
+    #[serde(default)]
     pub proofs: InclusionProofs,
 }
 
@@ -1,11 +1,11 @@
-use std::{fs, fs::DirEntry, path::PathBuf, env, collections::HashMap, ffi::OsString, io, path::Path};
+use std::{fs, fs::DirEntry, path::PathBuf, collections::HashMap, ffi::OsString, io, path::Path};
 use dotenv::dotenv;
 use alloy::{
     primitives::B256,
     signers::k256::sha2::{Digest, Sha256},
 };
 use blst::{min_pk::Signature, BLST_ERROR};
-use clap::{Parser, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use ethereum_consensus::{
     crypto::{PublicKey as BlsPublicKey, SecretKey as BlsSecretKey, Signature as BlsSignature},
     deneb::{compute_fork_data_root, compute_signing_root, Root},
@@ -13,7 +13,7 @@ use ethereum_consensus::{
 use eyre::{bail, eyre, Context, ContextCompat, Result};
 use lighthouse_eth2_keystore::Keystore;
 use reqwest::{Certificate, Identity, StatusCode, Url};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::fmt::Subscriber;
 
@@ -28,14 +28,157 @@ pub const BLS_DST_PREFIX: &[u8] = b"BLS_SIG_BLS12381G2_XMD:SHA-256_SSWU_RO_POP_"
 pub const DEFAULT_KEYSTORE_PASSWORD: &str = r#"𝔱𝔢𝔰𝔱𝔭𝔞𝔰𝔰𝔴𝔬𝔯𝔡🔑"#;
 
 const PERMISSION_DELEGATE_PATH: &str = "/constraints/v1/builder/delegate";
+const PERMISSION_REVOKE_PATH: &str = "/constraints/v1/builder/revoke";
 
 
-/// CLI arguments
+/// CLI arguments. Every flag can also be set from the environment or a `.env` file.
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Cli {
-    /// The action to perform (delegate or revoke)
-    action: Action,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Sign delegations of the validator keys to the delegatee, and submit them to the relay.
+    Delegate(SignArgs),
+    /// Sign revocations of the delegations to the delegatee, and submit them to the relay.
+    Revoke(SignArgs),
+    /// Verify the signatures of the signed messages of a file.
+    Verify {
+        /// JSON file of signed delegations and revocations.
+        #[arg(long, env = "OUT_FILE")]
+        file: String,
+        #[arg(long, env = "CHAIN", value_enum, default_value_t = Chain::Helder)]
+        chain: Chain,
+    },
+    /// List the validator keys of the signer.
+    ListKeys {
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+struct SignArgs {
+    #[command(flatten)]
+    source: SourceArgs,
+    /// BLS public key of the delegatee.
+    #[arg(long, env = "DELEGATEE_PUBLICKEY", value_parser = parse_bls_public_key)]
+    delegatee_pubkey: BlsPublicKey,
+    #[arg(long, env = "CHAIN", value_enum, default_value_t = Chain::Helder)]
+    chain: Chain,
+    /// URL of the relay to submit the signed messages to. They are only written to the
+    /// output file when not set.
+    #[arg(long, env = "RELAY_URL")]
+    relay_url: Option<String>,
+    /// File to write the signed messages to.
+    #[arg(long, env = "OUT_FILE")]
+    out: Option<String>,
+    /// Last epoch in which the delegations are valid. They never expire when not set.
+    #[arg(long, env = "EXPIRY_EPOCH")]
+    expiry_epoch: Option<u64>,
+}
+
+/// Where the validator keys are.
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+#[clap(rename_all = "kebab_case")]
+enum SignerType {
+    /// EIP-2335 keystores.
+    Keystores,
+    /// Remote Web3Signer, or commit-boost signer.
+    Web3signer,
+    /// Remote Dirk instance.
+    Dirk,
+}
+
+#[derive(Args, Debug)]
+struct SourceArgs {
+    #[arg(long = "signer", env = "SIGNER_TYPE", value_enum, ignore_case = true)]
+    signer: SignerType,
+    /// Directory of the keystores, one sub-directory per validator.
+    #[arg(long, env = "KEYS_PATH", required_if_eq("signer", "keystores"))]
+    keys_path: Option<String>,
+    /// Directory of the keystore passwords, one file per validator.
+    #[arg(long, env = "SECRETS_PATH", conflicts_with = "keystore_password")]
+    secrets_path: Option<String>,
+    /// Password of all the keystores.
+    #[arg(long, env = "KEYSTORE_PASSWORD", hide_env_values = true)]
+    keystore_password: Option<String>,
+    #[arg(long, env = "WEB3SIGNER_URL", required_if_eq("signer", "web3signer"))]
+    web3signer_url: Option<String>,
+    #[arg(long, env = "DIRK_URL", required_if_eq("signer", "dirk"))]
+    dirk_url: Option<String>,
+}
+
+impl SourceArgs {
+    fn keystore_secret(&self) -> Result<KeystoreSecret> {
+        match (&self.secrets_path, &self.keystore_password) {
+            (Some(path), _) => KeystoreSecret::from_directory(path),
+            (None, Some(password)) => Ok(KeystoreSecret::from_unique_password(password.clone())),
+            (None, None) => bail!("--secrets-path or --keystore-password is required"),
+        }
+    }
+
+    /// Sign `action` messages to `delegatee_pubkey` with every validator key of the signer,
+    /// the delegations being bounded to `expiry_epoch` when given.
+    async fn sign(
+        &self,
+        delegatee_pubkey: BlsPublicKey,
+        chain: Chain,
+        action: Action,
+        expiry_epoch: Option<u64>,
+    ) -> Result<Vec<SignedMessage>> {
+        match self.signer {
+            SignerType::Keystores => {
+                let keys_path = self.keys_path.as_deref().wrap_err("--keys-path is required")?;
+                let signed_messages = generate_from_keystore(
+                    keys_path,
+                    self.keystore_secret()?,
+                    delegatee_pubkey,
+                    chain,
+                    action,
+                    expiry_epoch,
+                )?;
+                debug!("Signed {} messages with keystore", signed_messages.len());
+
+                for message in &signed_messages {
+                    verify_message_signature(message, chain).wrap_err("invalid signature")?;
+                }
+                Ok(signed_messages)
+            }
+            SignerType::Web3signer => {
+                let url = self.web3signer_url.clone().wrap_err("--web3signer-url is required")?;
+                let signed_messages = generate_from_web3signer(
+                    Web3SignerOpts { url },
+                    delegatee_pubkey,
+                    action,
+                    expiry_epoch,
+                )
+                .await?;
+                debug!("Signed {} messages with web3signature", signed_messages.len());
+                Ok(signed_messages)
+            }
+            SignerType::Dirk => bail!("signing with Dirk is not supported yet"),
+        }
+    }
+
+    /// The validator public keys of the signer.
+    async fn list_keys(&self) -> Result<Vec<String>> {
+        match self.signer {
+            SignerType::Keystores => {
+                let keys_path = self.keys_path.as_deref().wrap_err("--keys-path is required")?;
+                keystore_pubkeys(keys_path)
+            }
+            SignerType::Web3signer => {
+                let url = self.web3signer_url.clone().wrap_err("--web3signer-url is required")?;
+                let mut signer = Web3Signer::connect(url).await?;
+                signer.list_accounts().await
+            }
+            SignerType::Dirk => bail!("listing the keys of Dirk is not supported yet"),
+        }
+    }
 }
 
 
@@ -49,95 +192,69 @@ async fn main() ->eyre::Result<()> {
 
     tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
 
-    let signer_type = env::var("SIGNER_TYPE").expect("please set a signer_type");
-    let relay_url  = env::var("RELAY_URL").expect("couldn't find relay url in env file");
-    let delegate_pubkey_str = env::var("DELEGATEE_PUBLICKEY").expect("couldn't find delegatee publickey in env file");
-    let delegatee_pubkey:BlsPublicKey = parse_bls_public_key(delegate_pubkey_str.as_str()).expect("Invalid public key");
-    let relay_endpoint = relay_url + PERMISSION_DELEGATE_PATH;  // Create the full URL once
-    let out = env::var("OUT_FILE").expect("couldn't find out file in env file");
-
-
-    if signer_type == "KEYSTORES" {
-        let keys_path = env::var("KEYS_PATH").expect("couldn't find keys path in env file");
-        let password_path = env::var("SECRETS_PATH").expect("couldn't find secrets path in env file");
-        let keystore_secret = KeystoreSecret::from_directory(password_path.as_str()).unwrap();
-
-        let signed_messages = generate_from_keystore(
-            &keys_path,
-            keystore_secret,
-            delegatee_pubkey.clone(),
-            Chain::Helder,
-            Action::Delegate,
-        ).expect("Invalid signed message request");
-
-        debug!("Signed {} messages with keystore", signed_messages.len());
-
-        // Verify signatures
-        for message in &signed_messages {
-            verify_message_signature(message, Chain::Helder).expect("invalid signature");
+    match Cli::parse().command {
+        Command::Delegate(args) => sign_and_submit(args, Action::Delegate).await,
+        Command::Revoke(args) => sign_and_submit(args, Action::Revoke).await,
+        Command::Verify { file, chain } => {
+            let content = fs::read_to_string(&file).wrap_err(format!("failed to read {file}"))?;
+            let signed_messages: Vec<SignedMessage> = serde_json::from_str(&content)?;
+            for message in &signed_messages {
+                verify_message_signature(message, chain)?;
+            }
+            info!("verified {} signed messages", signed_messages.len());
+            Ok(())
         }
-
-        let client = reqwest::ClientBuilder::new().build().unwrap();
-
-        let response = client
-            .post(&relay_endpoint)
-            .header("content-type", "application/json")
-            .body(serde_json::to_string(&signed_messages)?)
-            .send()
-            .await?;
-
-        let status = response.status();
-        // Print response status
-        info!("Response status: {}", status);
-
-        // Print response body
-        let body = response.text().await?;
-        info!("Response body: {}", body);
-
-        if status != StatusCode::OK {
-            error!("failed to send  delegations to relay");
-        } else {
-            info!("submited  {} delegations to relay", signed_messages.len());
+        Command::ListKeys { source } => {
+            for key in source.list_keys().await? {
+                println!("{key}");
+            }
+            Ok(())
         }
     }
+}
 
-    if signer_type == "WEB3SIGNER" {
-        let web3signer_url = env::var("WEB3SIGNER_URL").expect("couldn't find web3signer url in env file");
-
-        let signed_messages_web3 = generate_from_web3signer(
-            Web3SignerOpts{
-                url:web3signer_url},
-            delegatee_pubkey,
-            Action::Delegate
-            ).await?;
-
-        debug!("Signed {} messages with web3signature", signed_messages_web3.len());
-
-        let client = reqwest::ClientBuilder::new().build().unwrap();
-
-        let response = client
-            .post(&relay_endpoint)
-            .header("content-type", "application/json")
-            .body(serde_json::to_string(&signed_messages_web3)?)
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        // Print response status
-        info!("Response status: {}", status);
-
-        // Print response body
-        let body = response.text().await?;
-        info!("Response body: {}", body);
+/// Sign the messages of `action`, write them to the output file and submit them to the relay.
+async fn sign_and_submit(args: SignArgs, action: Action) -> Result<()> {
+    let signed_messages = args
+        .source
+        .sign(args.delegatee_pubkey, args.chain, action.clone(), args.expiry_epoch)
+        .await?;
 
-        if status != StatusCode::OK {
-            error!("failed to send  delegations to relay");
-        } else {
-            info!("submited  {} delegations to relay", signed_messages_web3.len());
-        }
+    if let Some(out) = &args.out {
+        write_to_file(out, &signed_messages)?;
+        info!("wrote {} signed messages to {}", signed_messages.len(), out);
     }
 
+    let Some(relay_url) = &args.relay_url else {
+        return Ok(());
+    };
+    let path = match action {
+        Action::Delegate => PERMISSION_DELEGATE_PATH,
+        Action::Revoke => PERMISSION_REVOKE_PATH,
+    };
+
+    let client = reqwest::ClientBuilder::new().build()?;
+
+    let response = client
+        .post(format!("{}{}", relay_url.trim_end_matches('/'), path))
+        .header("content-type", "application/json")
+        .body(serde_json::to_string(&signed_messages)?)
+        .send()
+        .await?;
+
+    let status = response.status();
+    // Print response status
+    info!("Response status: {}", status);
+
+    // Print response body
+    let body = response.text().await?;
+    info!("Response body: {}", body);
+
+    if status != StatusCode::OK {
+        error!("failed to send {} messages to relay", signed_messages.len());
+        bail!("relay responded with {status}");
+    }
+    info!("submitted {} messages to relay", signed_messages.len());
     Ok(())
 }
 
@@ -149,12 +266,15 @@ async fn main() ->eyre::Result<()> {
 /// - Create messages
 /// - Compute the signing roots and sign the message
 /// - Return the signed message
+///
+/// Delegations are bounded to `expiry_epoch` when given.
 pub fn generate_from_keystore(
     keys_path: &str,
     keystore_secret: KeystoreSecret,
     delegatee_pubkey: BlsPublicKey,
     chain: Chain,
     action: Action,
+    expiry_epoch: Option<u64>,
 ) -> Result<Vec<SignedMessage>> {
     let keystores_paths = keystore_paths(keys_path)?;
     let mut signed_messages = Vec::with_capacity(keystores_paths.len());
@@ -169,7 +289,9 @@ pub fn generate_from_keystore(
 
         match action {
             Action::Delegate => {
-                let message = DelegationMessage::new(validator_pubkey, delegatee_pubkey.clone());
+                let mut message =
+                    DelegationMessage::new(validator_pubkey, delegatee_pubkey.clone());
+                message.expiry_epoch = expiry_epoch;
                 let signing_root = compute_commit_boost_signing_root(message.digest(), &chain)?;
                 let signature = validator_private_key.sign(signing_root.0.into());
                 let signature = BlsSignature::try_from(signature.serialize().as_ref())?;
@@ -367,6 +489,17 @@ pub fn keystore_paths(keys_path: &str) -> Result<Vec<PathBuf>> {
     Ok(keystores_paths)
 }
 
+/// The validator public keys of the keystores in `keys_path`, read without decrypting them.
+pub fn keystore_pubkeys(keys_path: &str) -> Result<Vec<String>> {
+    keystore_paths(keys_path)?
+        .into_iter()
+        .map(|path| {
+            let ks = Keystore::from_json_file(path).map_err(KeystoreError::Eth2Keystore)?;
+            Ok(format!("0x{}", ks.pubkey()))
+        })
+        .collect()
+}
+
 fn read_path(entry: io::Result<DirEntry>) -> Result<PathBuf> {
     Ok(entry.map_err(KeystoreError::ReadFromDirectory)?.path())
 }
@@ -392,11 +525,13 @@ pub fn write_to_file<T: Serialize>(out: &str, data: &T) -> Result<()> {
     Ok(())
 }
 
-/// Generate signed delegations/recovations using a remote Web3Signer.
+/// Generate signed delegations/recovations using a remote Web3Signer. Delegations are
+/// bounded to `expiry_epoch` when given.
 pub async fn generate_from_web3signer(
     opts: Web3SignerOpts,
     delegatee_pubkey: BlsPublicKey,
     action: Action,
+    expiry_epoch: Option<u64>,
 ) -> Result<Vec<SignedMessage>> {
     // Connect to web3signer.
     let mut web3signer = Web3Signer::connect(opts.url).await?;
@@ -415,7 +550,8 @@ pub async fn generate_from_web3signer(
 
         match action {
             Action::Delegate => {
-                let message = DelegationMessage::new(pubkey.clone(), delegatee_pubkey.clone());
+                let mut message = DelegationMessage::new(pubkey.clone(), delegatee_pubkey.clone());
+                message.expiry_epoch = expiry_epoch;
                 // Web3Signer expects the pre-pended 0x.
                 let signing_root = format!("0x{}", &hex::encode(message.digest()));
                 let returned_signature =
@@ -609,6 +745,41 @@ pub enum SignedMessage {
     Revocation(SignedRevocation),
 }
 
+impl<'de> Deserialize<'de> for SignedMessage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct RawMessage {
+            action: u8,
+            validator_pubkey: BlsPublicKey,
+            delegatee_pubkey: BlsPublicKey,
+            #[serde(default)]
+            expiry_epoch: Option<u64>,
+        }
+
+        #[derive(Deserialize)]
+        struct RawSignedMessage {
+            message: RawMessage,
+            signature: BlsSignature,
+        }
+
+        // Both kinds have the same fields, they only differ by their action
+        let RawSignedMessage { message, signature } = RawSignedMessage::deserialize(deserializer)?;
+        let RawMessage { action, validator_pubkey, delegatee_pubkey, expiry_epoch } = message;
+        match action {
+            a if a == SignedMessageAction::Delegation as u8 => {
+                let message =
+                    DelegationMessage { action, validator_pubkey, delegatee_pubkey, expiry_epoch };
+                Ok(Self::Delegation(SignedDelegation { message, signature }))
+            }
+            a if a == SignedMessageAction::Revocation as u8 => {
+                let message = RevocationMessage { action, validator_pubkey, delegatee_pubkey };
+                Ok(Self::Revocation(SignedRevocation { message, signature }))
+            }
+            other => Err(serde::de::Error::custom(format!("unknown message action {other}"))),
+        }
+    }
+}
+
 impl SignedMessage {
     /// Verify the signature of a signed message
     pub fn verify_signature(&self, chain: Chain) -> eyre::Result<()> {
@@ -650,20 +821,33 @@ pub struct DelegationMessage {
     action: u8,
     pub validator_pubkey: BlsPublicKey,
     pub delegatee_pubkey: BlsPublicKey,
+    /// Last epoch in which the delegation is valid. Delegations without expiry never expire.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry_epoch: Option<u64>,
 }
 
 impl DelegationMessage {
     /// Create a new delegation message.
     pub fn new(validator_pubkey: BlsPublicKey, delegatee_pubkey: BlsPublicKey) -> Self {
-        Self { action: SignedMessageAction::Delegation as u8, validator_pubkey, delegatee_pubkey }
+        Self {
+            action: SignedMessageAction::Delegation as u8,
+            validator_pubkey,
+            delegatee_pubkey,
+            expiry_epoch: None,
+        }
     }
 
     /// Compute the digest of the delegation message.
+    ///
+    /// The expiry is only hashed when set, as done by the gateway.
     pub fn digest(&self) -> [u8; 32] {
         let mut hasher = Sha256::new();
         hasher.update([self.action]);
         hasher.update(self.validator_pubkey.to_vec());
         hasher.update(self.delegatee_pubkey.to_vec());
+        if let Some(expiry_epoch) = self.expiry_epoch {
+            hasher.update(expiry_epoch.to_le_bytes());
+        }
 
         hasher.finalize().into()
     }